@@ -34,9 +34,29 @@ async fn main() {
         test.set_end(&end);
 
         let mut tester = Tester::new();
+        let mut progress = tester.progress();
+        tokio::spawn(async move {
+            while let Some(p) = progress.recv().await {
+                eprint!("\r{ticker} {p}");
+            }
+            eprintln!();
+        });
+        let cancel = tester.cancel_token();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+            }
+        });
         tester.run(strategy, &mut test).await;
+        if test.status == TestStatus::Canceled {
+            println!("Canceled");
+            return;
+        }
 
         let summary = Summary::new(&test.trade_list);
         println!("{summary}");
+
+        let report = Report::save(&test).unwrap();
+        println!("Report: {}", report.display());
     }
 }
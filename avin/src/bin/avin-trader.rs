@@ -13,5 +13,8 @@ async fn main() {
     utils::init_logger();
 
     let mut trader = Trader::new();
-    trader.start().await;
+    if let Err(e) = trader.start().await {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
 }
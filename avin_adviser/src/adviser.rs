@@ -51,6 +51,7 @@ impl Adviser {
                 match e {
                     Event::Bar(e) => asset.bar_event(e),
                     Event::Tic(e) => asset.tic_event(e),
+                    Event::Book(_) => {}
                     Event::Order(_e) => todo!(),
                 }
 
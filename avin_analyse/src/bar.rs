@@ -179,14 +179,8 @@ fn analyse_name(
     feat: Option<&Feat>,
     metric: Option<&Metric>,
 ) -> String {
-    if feat.is_some() && metric.is_some() {
-        format!(
-            "{} {} {} {}",
-            NAME,
-            tf,
-            feat.unwrap().name(),
-            metric.unwrap().name(),
-        )
+    if let (Some(feat), Some(metric)) = (feat, metric) {
+        format!("{} {} {} {}", NAME, tf, feat.name(), metric.name())
     } else {
         format!("{NAME} {tf} {NAME}")
     }
@@ -318,35 +312,35 @@ fn set_metrics(chart: &Chart, vol_df: &mut DataFrame) {
 
     // collect values
     for bar in chart.bars().iter() {
-        let cdf = chart.bar_body_cdf(bar).unwrap();
+        let cdf = chart.bar_body_cdf(&bar).unwrap();
         let size = Size::from_cdf(cdf);
         let sz = size.sz();
         body_cdf.push(cdf);
         body_size.push(size.name());
         body_sz.push(sz.name());
 
-        let cdf = chart.bar_full_cdf(bar).unwrap();
+        let cdf = chart.bar_full_cdf(&bar).unwrap();
         let size = Size::from_cdf(cdf);
         let sz = size.sz();
         full_cdf.push(cdf);
         full_size.push(size.name());
         full_sz.push(sz.name());
 
-        let cdf = chart.bar_lower_cdf(bar).unwrap();
+        let cdf = chart.bar_lower_cdf(&bar).unwrap();
         let size = Size::from_cdf(cdf);
         let sz = size.sz();
         lower_cdf.push(cdf);
         lower_size.push(size.name());
         lower_sz.push(sz.name());
 
-        let cdf = chart.bar_upper_cdf(bar).unwrap();
+        let cdf = chart.bar_upper_cdf(&bar).unwrap();
         let size = Size::from_cdf(cdf);
         let sz = size.sz();
         upper_cdf.push(cdf);
         upper_size.push(size.name());
         upper_sz.push(sz.name());
 
-        let cdf = chart.bar_vol_cdf(bar).unwrap();
+        let cdf = chart.bar_vol_cdf(&bar).unwrap();
         let size = Size::from_cdf(cdf);
         let sz = size.sz();
         vol_cdf.push(cdf);
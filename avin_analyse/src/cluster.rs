@@ -80,14 +80,8 @@ fn analyse_name(
     feat: Option<&Feat>,
     metric: Option<&Metric>,
 ) -> String {
-    if feat.is_some() && metric.is_some() {
-        format!(
-            "{} {} {} {}",
-            NAME,
-            tf,
-            feat.unwrap().name(),
-            metric.unwrap().name(),
-        )
+    if let (Some(feat), Some(metric)) = (feat, metric) {
+        format!("{} {} {} {}", NAME, tf, feat.name(), metric.name())
     } else {
         format!("{NAME} {tf} {NAME}")
     }
@@ -5,9 +5,13 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+use chrono::Utc;
+
 use avin_core::{
-    Action, Event, GetBarsAction, LimitOrder, MarketData, MarketOrder, Order,
-    OrderAction, OrderEvent, StreamAction, TimeFrame,
+    Action, Event, GetAccountAction, GetBarsAction, GetCashOperationsAction,
+    GetOperationsAction, GetOrdersAction, GetPositionAction, LimitOrder,
+    MarketData, MarketOrder, Order, OrderAction, OrderEvent, StopOrder,
+    StreamAction, TimeFrame,
 };
 use avin_utils::AvinError;
 
@@ -38,23 +42,53 @@ impl Tinkoff {
         // receive actions main loop
         while let Some(a) = self.action_rx.recv().await {
             match a {
-                Action::GetAccount(_a) => todo!(),
+                Action::GetAccount(a) => self.get_account_action(a).await,
                 Action::GetBars(a) => self.get_bars_action(a).await,
+                Action::GetOrders(a) => self.get_orders_action(a).await,
+                Action::GetPosition(a) => self.get_position_action(a).await,
+                Action::GetOperations(a) => {
+                    self.get_operations_action(a).await
+                }
+                Action::GetCashOperations(a) => {
+                    self.get_cash_operations_action(a).await
+                }
                 Action::Post(a) => {
                     self.post_action(a).await;
                 }
-                Action::Cancel(_) => todo!(),
+                Action::Cancel(a) => {
+                    self.cancel_action(a).await;
+                }
                 Action::Subscribe(a) => {
                     self.subscribe_action(a).await;
                 }
-                Action::Unsubscribe(_) => todo!(),
+                Action::Unsubscribe(a) => {
+                    self.unsubscribe_action(a).await;
+                }
                 Action::TradeClosed(_) => unreachable!(),
                 Action::TradeOpened(_) => unreachable!(),
+                Action::Signal(_) => unreachable!(),
+                Action::Notify(_) => unreachable!(),
             }
         }
     }
 
     // private
+    async fn get_account_action(&mut self, a: GetAccountAction) {
+        let mut account = match self.client.get_account(&a.name).await {
+            Ok(account) => account,
+            Err(e) => {
+                log::error!("Tinkoff.get_account_action: {e}");
+                // канал закрывается без ответа, счет неизвестен
+                return;
+            }
+        };
+        if let Err(e) = self.client.update_account(&mut account).await {
+            log::error!("Tinkoff.get_account_action: {e}");
+            return;
+        }
+
+        a.tx.send(account).unwrap();
+    }
     async fn get_bars_action(&mut self, a: GetBarsAction) {
         let bars = self
             .client
@@ -64,7 +98,94 @@ impl Tinkoff {
 
         a.tx.send(bars).unwrap();
     }
+    async fn get_orders_action(&mut self, a: GetOrdersAction) {
+        let mut orders = Vec::new();
+        match self.client.get_limit_orders(&a.account, &a.iid).await {
+            Ok(limits) => orders.extend(limits.into_iter().map(Order::Limit)),
+            Err(e) => log::error!("Tinkoff.get_orders_action: {e}"),
+        }
+        match self.client.get_stop_orders(&a.account, &a.iid).await {
+            Ok(stops) => orders.extend(stops.into_iter().map(Order::Stop)),
+            Err(e) => log::error!("Tinkoff.get_orders_action: {e}"),
+        }
+
+        a.tx.send(orders).unwrap();
+    }
+    async fn get_position_action(&mut self, a: GetPositionAction) {
+        let position =
+            match self.client.get_position(&a.account, &a.iid).await {
+                Ok(position) => position,
+                Err(e) => {
+                    log::error!("Tinkoff.get_position_action: {e}");
+                    // канал закрывается без ответа, позиция неизвестна
+                    return;
+                }
+            };
+
+        a.tx.send(position).unwrap();
+    }
+    async fn get_operations_action(&mut self, a: GetOperationsAction) {
+        let operations = self
+            .client
+            .get_operations(&a.account, &a.iid, Some(&a.from), None)
+            .await
+            .unwrap();
+
+        a.tx.send(operations).unwrap();
+    }
+    async fn get_cash_operations_action(
+        &mut self,
+        a: GetCashOperationsAction,
+    ) {
+        let till = Utc::now();
+        let operations = match self
+            .client
+            .get_cash_operations(&a.account, &a.from, &till)
+            .await
+        {
+            Ok(operations) => operations,
+            Err(e) => {
+                log::error!("Tinkoff.get_cash_operations_action: {e}");
+                return;
+            }
+        };
+
+        a.tx.send(operations).unwrap();
+    }
+    async fn cancel_action(&mut self, a: OrderAction) {
+        let result = match a.order {
+            Order::Limit(LimitOrder::Posted(posted)) => self
+                .client
+                .cancel_limit(&a.account, posted)
+                .await
+                .map(Order::Limit),
+            Order::Stop(StopOrder::Posted(posted)) => self
+                .client
+                .cancel_stop(&a.account, posted)
+                .await
+                .map(Order::Stop),
+            order => {
+                log::error!("Tinkoff.cancel_action: not posted {order}");
+                return;
+            }
+        };
+
+        let order = match result {
+            Ok(order) => order,
+            Err(e) => {
+                log::error!("Tinkoff.cancel_action: {e}");
+                return;
+            }
+        };
+        let e = OrderEvent::new(a.account, a.iid, a.owner, order);
+        self.event_tx.send(Event::Order(e)).unwrap();
+    }
     async fn post_action(&mut self, a: OrderAction) {
+        if let Err(e) = a.iid.check_listed() {
+            log::error!("Tinkoff.post_action: {e}");
+            return;
+        }
+
         let result = match a.order {
             Order::Market(market) => match market {
                 MarketOrder::New(new_market) => {
@@ -92,6 +213,10 @@ impl Tinkoff {
     }
     async fn subscribe_action(&mut self, a: StreamAction) {
         log::info!("Tinkoff.subscribe_action({a})");
+        if let Err(e) = a.iid.check_listed() {
+            log::error!("Tinkoff.subscribe_action: {e}");
+            return;
+        }
 
         for md in a.market_data_kinds {
             match md {
@@ -128,10 +253,35 @@ impl Tinkoff {
                     .subscribe_bar(&a.iid, &TimeFrame::Month)
                     .await
                     .unwrap(),
+                MarketData::BAR_1S | MarketData::BAR_5S => {
+                    log::error!("Tinkoff not provide {md} stream");
+                }
                 MarketData::TRADE_STATS => unreachable!(),
                 MarketData::ORDER_STATS => unreachable!(),
                 MarketData::OB_STATS => unreachable!(),
             };
         }
     }
+    async fn unsubscribe_action(&mut self, a: StreamAction) {
+        log::info!("Tinkoff.unsubscribe_action({a})");
+
+        for md in a.market_data_kinds {
+            let result = match md {
+                MarketData::TIC => self.client.unsubscribe_tic(&a.iid).await,
+                md => {
+                    let tf = TimeFrame::all()
+                        .into_iter()
+                        .find(|tf| tf.market_data() == md);
+                    let Some(tf) = tf else {
+                        log::error!("Tinkoff not provide {md} stream");
+                        continue;
+                    };
+                    self.client.unsubscribe_bar(&a.iid, &tf).await
+                }
+            };
+            if let Err(e) = result {
+                log::error!("Tinkoff.unsubscribe_action: {e}");
+            }
+        }
+    }
 }
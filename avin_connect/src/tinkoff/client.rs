@@ -6,16 +6,19 @@
  ****************************************************************************/
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use chrono::{DateTime, Datelike, Timelike, Utc};
 use tonic::transport::{Channel, ClientTlsConfig};
 
 use avin_core::{
-    Account, Bar, BarEvent, Category, Direction, Event, FilledMarketOrder,
-    Iid, LimitOrder, MarketOrder, NewLimitOrder, NewMarketOrder,
-    NewStopOrder, Operation, Order, PostedLimitOrder, PostedMarketOrder,
-    PostedStopOrder, RejectedLimitOrder, RejectedMarketOrder, Share,
-    StopOrder, StopOrderKind, Tic, TicEvent, TimeFrame, Transaction,
+    Account, Bar, BarEvent, BookEvent, CashOperation, Category, Direction,
+    Event, FilledMarketOrder, Iid, LimitOrder, MarketOrder, NewLimitOrder,
+    NewMarketOrder, NewStopOrder, Operation, OperationKind, Order, OrderBook,
+    PartiallyFilledLimitOrder, PartiallyFilledMarketOrder, PostedLimitOrder,
+    PostedMarketOrder, PostedStopOrder, RejectedLimitOrder,
+    RejectedMarketOrder, Share, StopOrder, StopOrderKind, Tic, TicEvent,
+    TimeFrame, Transaction,
 };
 use avin_utils::{self as utils, CFG, Cmd};
 
@@ -26,12 +29,15 @@ use api::marketdata::market_data_request::Payload as Req;
 use api::marketdata::market_data_response::Payload as Res;
 use api::marketdata::{
     CandleInstrument, InfoInstrument, MarketDataRequest, MarketDataResponse,
-    SubscribeCandlesRequest, SubscribeInfoRequest, SubscribeTradesRequest,
-    SubscriptionAction, SubscriptionInterval, TradeInstrument,
+    OrderBookInstrument, SubscribeCandlesRequest, SubscribeInfoRequest,
+    SubscribeOrderBookRequest, SubscribeTradesRequest, SubscriptionAction,
+    SubscriptionInterval, TradeInstrument,
     market_data_service_client::MarketDataServiceClient,
     market_data_stream_service_client::MarketDataStreamServiceClient,
 };
 use api::operations::operations_service_client::OperationsServiceClient;
+use api::operations::operations_stream_service_client::OperationsStreamServiceClient;
+use api::operations::positions_stream_response::Payload as PositionsPayload;
 use api::orders::TradesStreamRequest;
 use api::orders::orders_service_client::OrdersServiceClient;
 use api::orders::orders_stream_service_client::OrdersStreamServiceClient;
@@ -54,6 +60,8 @@ pub struct TinkoffClient {
     marketdata: Option<MarketDataServiceClient<T>>,
     marketdata_stream: Option<MarketDataStreamServiceClient<T>>,
     data_stream_tx: Option<flume::Sender<MarketDataRequest>>,
+    balances: Arc<Mutex<HashMap<String, Balance>>>,
+    margins: HashMap<String, bool>,
 
     event_tx: tokio::sync::mpsc::UnboundedSender<Event>,
     tasks: Vec<tokio::task::JoinHandle<()>>,
@@ -72,6 +80,8 @@ impl TinkoffClient {
             marketdata: None,
             marketdata_stream: None,
             data_stream_tx: None,
+            balances: Arc::new(Mutex::new(HashMap::new())),
+            margins: HashMap::new(),
 
             event_tx,
             tasks: Vec::new(),
@@ -116,6 +126,7 @@ impl TinkoffClient {
 
         self.create_marketdata_stream().await.unwrap();
         self.create_transactions_stream().await.unwrap();
+        self.create_positions_stream().await.unwrap();
 
         Ok(())
     }
@@ -203,6 +214,36 @@ impl TinkoffClient {
         Ok(())
     }
 
+    /// Поток денежных позиций всех счетов: балансы обновляются в кэше
+    /// клиента, [`TinkoffClient::update_account`] берет их оттуда без
+    /// запроса к брокеру.
+    pub async fn create_positions_stream(
+        &mut self,
+    ) -> Result<(), &'static str> {
+        // create request
+        let accounts = self.get_accounts().await?;
+        let request = api::operations::PositionsStreamRequest {
+            accounts: accounts.iter().map(|i| i.id().clone()).collect(),
+        };
+
+        // create client
+        let client = OperationsStreamServiceClient::with_interceptor(
+            self.channel.clone().unwrap(),
+            self.interceptor.clone().unwrap(),
+        );
+
+        // run loop
+        let balances = self.balances.clone();
+        let task = tokio::spawn(async move {
+            start_positions_stream(request, client, balances).await
+        });
+
+        // save task handle
+        self.tasks.push(task);
+
+        Ok(())
+    }
+
     // instrument info
     pub async fn get_shares(&mut self) -> Result<Vec<Share>, &'static str> {
         // create request
@@ -256,7 +297,7 @@ impl TinkoffClient {
             .unwrap()
             .get_accounts(request)
             .await
-            .unwrap();
+            .map_err(|_| "accounts request failed")?;
         // api::users::GetAccountsResponse
         let message = response.into_parts();
         // vec[api::users::Account]
@@ -285,7 +326,7 @@ impl TinkoffClient {
             .unwrap()
             .get_accounts(request)
             .await
-            .unwrap();
+            .map_err(|_| "accounts request failed")?;
         let message = response.into_parts();
         let t_accounts = message.1.accounts; // api::users::Account
 
@@ -299,6 +340,129 @@ impl TinkoffClient {
 
         Err("account not found")
     }
+    /// Обновляет баланс и признак маржинальной торговли счета. Баланс
+    /// берется из потока позиций, если он уже пришел, иначе запросом.
+    pub async fn update_account(
+        &mut self,
+        a: &mut Account,
+    ) -> Result<(), &'static str> {
+        let currency = a.currency().clone();
+        let cached = self
+            .balances
+            .lock()
+            .unwrap()
+            .get(a.id())
+            .and_then(|i| i.get(&currency));
+        match cached {
+            Some((ts, available, blocked)) => {
+                a.set_balance(ts, &currency, available + blocked, blocked);
+            }
+            None => self.request_balance(a).await?,
+        }
+
+        // маржинальные показатели доступны только для маржинальных
+        // счетов, для остальных брокер возвращает ошибку запроса. Сбой
+        // связи ответом не считается: признак остается прежним
+        if let Some(margin) = self.margins.get(a.id()) {
+            a.set_margin(*margin);
+            return Ok(());
+        }
+        let request =
+            tonic::Request::new(api::users::GetMarginAttributesRequest {
+                account_id: a.id().to_string(),
+            });
+        let response = self
+            .users
+            .as_mut()
+            .unwrap()
+            .get_margin_attributes(request)
+            .await;
+        let margin = match response {
+            Ok(_) => true,
+            Err(e) if is_refusal(e.code()) => false,
+            Err(e) => {
+                log::warn!("Margin attributes {}: {}", a.id(), e.message());
+                return Ok(());
+            }
+        };
+        self.margins.insert(a.id().to_string(), margin);
+        a.set_margin(margin);
+
+        Ok(())
+    }
+    async fn request_balance(
+        &mut self,
+        a: &mut Account,
+    ) -> Result<(), &'static str> {
+        // create request
+        let request =
+            tonic::Request::new(api::operations::PositionsRequest {
+                account_id: a.id().to_string(),
+            });
+
+        // send request
+        let response = self
+            .operations
+            .as_mut()
+            .unwrap()
+            .get_positions(request)
+            .await
+            .map_err(|_| "positions request failed")?;
+        // api::operations::PositionsResponse
+        let message = response.into_parts().1;
+
+        // money - свободные средства в валюте счета, blocked -
+        // заблокированные в той же валюте, всего на счете - их сумма
+        let currency = a.currency().clone();
+        let available: f64 = message
+            .money
+            .iter()
+            .filter(|m| m.currency == currency)
+            .map(|m| f64::from(m.clone()))
+            .sum();
+        let blocked: f64 = message
+            .blocked
+            .iter()
+            .filter(|m| m.currency == currency)
+            .map(|m| f64::from(m.clone()))
+            .sum();
+        let ts = Utc::now().timestamp_nanos_opt().unwrap();
+        a.set_balance(ts, &currency, available + blocked, blocked);
+
+        Ok(())
+    }
+    pub async fn get_position(
+        &mut self,
+        a: &Account,
+        iid: &Iid,
+    ) -> Result<i64, &'static str> {
+        // create request
+        let request =
+            tonic::Request::new(api::operations::PositionsRequest {
+                account_id: a.id().to_string(),
+            });
+
+        // send request
+        let response = self
+            .operations
+            .as_mut()
+            .unwrap()
+            .get_positions(request)
+            .await
+            .map_err(|_| "positions request failed")?;
+        // api::operations::PositionsResponse
+        let message = response.into_parts().1;
+
+        // бумаги, заблокированные заявками, тоже на счете
+        let position = message
+            .securities
+            .iter()
+            .filter(|s| &s.figi == iid.figi())
+            .map(|s| s.balance + s.blocked)
+            .sum();
+
+        Ok(position)
+    }
     pub async fn get_limit_orders(
         &mut self,
         a: &Account,
@@ -470,6 +634,51 @@ impl TinkoffClient {
         Ok(a_operations)
     }
 
+    pub async fn get_cash_operations(
+        &mut self,
+        a: &Account,
+        from: &DateTime<Utc>,
+        till: &DateTime<Utc>,
+    ) -> Result<Vec<CashOperation>, &'static str> {
+        // create request, empty figi == all operations of account
+        let from = prost_types::Timestamp {
+            seconds: from.timestamp(),
+            nanos: 0,
+        };
+        let to = prost_types::Timestamp {
+            seconds: till.timestamp(),
+            nanos: 0,
+        };
+        let request =
+            tonic::Request::new(api::operations::OperationsRequest {
+                account_id: a.id().to_string(),
+                from: Some(from),
+                to: Some(to),
+                state: api::operations::OperationState::Executed as i32,
+                figi: String::new(),
+            });
+
+        // send request
+        let response = self
+            .operations
+            .as_mut()
+            .unwrap()
+            .get_operations(request)
+            .await
+            .map_err(|_| "operations request failed")?;
+        let t_operations = response.into_parts().1.operations;
+
+        // convert tinkoff::api::operations::Operation -> avin::CashOperation
+        // торговые операции (покупка/продажа) пропускаем
+        let mut a_operations: Vec<CashOperation> = t_operations
+            .into_iter()
+            .filter_map(t_cash_operation)
+            .collect();
+        a_operations.sort_by_key(|i| i.ts);
+
+        Ok(a_operations)
+    }
+
     // orders
     pub async fn post_market(
         &mut self,
@@ -477,6 +686,12 @@ impl TinkoffClient {
         iid: &Iid,
         order: NewMarketOrder,
     ) -> Result<Order, &'static str> {
+        // check instrument, before send to broker
+        if let Err(e) = iid.check_listed() {
+            log::error!("{e}");
+            return Err("instrument delisted");
+        }
+
         // create request
         let direction: api::orders::OrderDirection =
             order.direction.clone().into();
@@ -551,6 +766,12 @@ impl TinkoffClient {
         iid: &Iid,
         order: NewLimitOrder,
     ) -> Result<Order, &'static str> {
+        // check order, before send to broker
+        if let Err(e) = iid.validate_order(order.price, order.lots) {
+            log::error!("{e}");
+            return Err("invalid limit order");
+        }
+
         // create request
         let direction: api::orders::OrderDirection =
             order.direction.clone().into();
@@ -589,6 +810,15 @@ impl TinkoffClient {
         iid: &Iid,
         order: NewStopOrder,
     ) -> Result<StopOrder, &'static str> {
+        // check order, before send to broker
+        let exec_price = order.exec_price.unwrap_or(order.stop_price);
+        for price in [order.stop_price, exec_price] {
+            if let Err(e) = iid.validate_order(price, order.lots) {
+                log::error!("{e}");
+                return Err("invalid stop order");
+            }
+        }
+
         // create request
         let last_price = self.get_last_price(iid).await.unwrap();
         let t_order_type = t_stop_order_type(&order, last_price);
@@ -823,6 +1053,11 @@ impl TinkoffClient {
         &mut self,
         iid: &Iid,
     ) -> Result<(), &'static str> {
+        if let Err(e) = iid.check_listed() {
+            log::error!("{e}");
+            return Err("instrument delisted");
+        }
+
         // create request
         let info_instrument = InfoInstrument {
             figi: "".to_string(),
@@ -845,6 +1080,11 @@ impl TinkoffClient {
         iid: &Iid,
         tf: &TimeFrame,
     ) -> Result<(), &'static str> {
+        if let Err(e) = iid.check_listed() {
+            log::error!("{e}");
+            return Err("instrument delisted");
+        }
+
         // create request
         let interval: SubscriptionInterval = (*tf).into();
         let candle_instrument = CandleInstrument {
@@ -872,6 +1112,11 @@ impl TinkoffClient {
         &mut self,
         iid: &Iid,
     ) -> Result<(), &'static str> {
+        if let Err(e) = iid.check_listed() {
+            log::error!("{e}");
+            return Err("instrument delisted");
+        }
+
         // create request
         let instrument = TradeInstrument {
             figi: "".to_string(),
@@ -891,14 +1136,46 @@ impl TinkoffClient {
 
         Ok(())
     }
+    pub async fn subscribe_book(
+        &mut self,
+        iid: &Iid,
+        depth: i32,
+    ) -> Result<(), &'static str> {
+        if let Err(e) = iid.check_listed() {
+            log::error!("{e}");
+            return Err("instrument delisted");
+        }
+
+        // create request
+        let instrument = OrderBookInstrument {
+            figi: "".to_string(),
+            depth,
+            instrument_id: iid.figi().clone(),
+        };
+        let request = MarketDataRequest {
+            payload: Some(Req::SubscribeOrderBookRequest(
+                SubscribeOrderBookRequest {
+                    subscription_action: SubscriptionAction::Subscribe as i32,
+                    instruments: vec![instrument],
+                },
+            )),
+        };
+
+        // send request in existed stream
+        self.data_stream_tx.as_mut().unwrap().send(request).unwrap();
+
+        Ok(())
+    }
     pub async fn unsubscribe_bar(
         &mut self,
         iid: &Iid,
+        tf: &TimeFrame,
     ) -> Result<(), &'static str> {
         // create request
+        let interval: SubscriptionInterval = (*tf).into();
         let candle_instrument = CandleInstrument {
             figi: "".to_string(),
-            interval: SubscriptionInterval::OneMinute as i32,
+            interval: interval as i32,
             instrument_id: iid.figi().clone(),
         };
         let request = MarketDataRequest {
@@ -918,9 +1195,51 @@ impl TinkoffClient {
     }
     pub async fn unsubscribe_tic(
         &mut self,
-        _iid: &Iid,
+        iid: &Iid,
+    ) -> Result<(), &'static str> {
+        // create request
+        let instrument = TradeInstrument {
+            figi: "".to_string(),
+            instrument_id: iid.figi().clone(),
+        };
+        let request = MarketDataRequest {
+            payload: Some(Req::SubscribeTradesRequest(
+                SubscribeTradesRequest {
+                    subscription_action: SubscriptionAction::Unsubscribe
+                        as i32,
+                    instruments: vec![instrument],
+                },
+            )),
+        };
+
+        self.data_stream_tx.as_mut().unwrap().send(request).unwrap();
+
+        Ok(())
+    }
+    pub async fn unsubscribe_book(
+        &mut self,
+        iid: &Iid,
+        depth: i32,
     ) -> Result<(), &'static str> {
-        todo!();
+        // create request
+        let instrument = OrderBookInstrument {
+            figi: "".to_string(),
+            depth,
+            instrument_id: iid.figi().clone(),
+        };
+        let request = MarketDataRequest {
+            payload: Some(Req::SubscribeOrderBookRequest(
+                SubscribeOrderBookRequest {
+                    subscription_action: SubscriptionAction::Unsubscribe
+                        as i32,
+                    instruments: vec![instrument],
+                },
+            )),
+        };
+
+        self.data_stream_tx.as_mut().unwrap().send(request).unwrap();
+
+        Ok(())
     }
 
     // private
@@ -964,7 +1283,10 @@ async fn start_marketdata_stream(
                 let e: TicEvent = tic.into();
                 sender.send(Event::Tic(e)).unwrap();
             }
-            Res::Orderbook(_) => todo!(),
+            Res::Orderbook(book) => {
+                let e: BookEvent = book.into();
+                sender.send(Event::Book(e)).unwrap();
+            }
             Res::TradingStatus(_) => {
                 // log::debug!("{i:#?}");
                 log::warn!("Сделать обработку смены статуса актива!")
@@ -1012,6 +1334,81 @@ async fn start_transaction_stream(
     }
 }
 
+async fn start_positions_stream(
+    request: api::operations::PositionsStreamRequest,
+    mut client: OperationsStreamServiceClient<T>,
+    balances: Arc<Mutex<HashMap<String, Balance>>>,
+) {
+    // send request
+    let response = match client.positions_stream(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("Positions stream: {}", e.message());
+            return;
+        }
+    };
+
+    // get stream
+    let mut positions_stream = response.into_inner();
+
+    while let Ok(Some(msg)) = positions_stream.message().await {
+        if let Some(PositionsPayload::Position(data)) = msg.payload {
+            let mut balances = balances.lock().unwrap();
+            let balance =
+                balances.entry(data.account_id.clone()).or_default();
+            balance.apply(&data);
+        }
+    }
+    log::error!("Positions stream stopped, balances are requested");
+    balances.lock().unwrap().clear();
+}
+/// Ошибки запроса маржинальных показателей, которые означают, что
+/// маржинальная торговля на счете не доступна.
+fn is_refusal(code: tonic::Code) -> bool {
+    matches!(
+        code,
+        tonic::Code::InvalidArgument
+            | tonic::Code::FailedPrecondition
+            | tonic::Code::NotFound
+            | tonic::Code::PermissionDenied
+    )
+}
+
+/// Денежные позиции счета из потока позиций: валюта -> (время,
+/// свободные, заблокированные). Поток присылает только изменившиеся
+/// валюты, поэтому позиции обновляются по одной.
+#[derive(Debug, Default)]
+struct Balance {
+    money: HashMap<String, (i64, f64, f64)>,
+}
+impl Balance {
+    fn get(&self, currency: &str) -> Option<(i64, f64, f64)> {
+        self.money.get(currency).copied()
+    }
+    fn apply(&mut self, data: &api::operations::PositionData) {
+        let ts = data.date.as_ref().map_or_else(
+            || Utc::now().timestamp_nanos_opt().unwrap(),
+            |i| i.seconds * 1_000_000_000 + i.nanos as i64,
+        );
+        for i in data.money.iter() {
+            let Some(currency) = i
+                .available_value
+                .as_ref()
+                .or(i.blocked_value.as_ref())
+                .map(|i| i.currency.clone())
+            else {
+                continue;
+            };
+            let value = |v: &Option<api::operations::MoneyValue>| {
+                v.clone().map_or(0.0, f64::from)
+            };
+            let available = value(&i.available_value);
+            let blocked = value(&i.blocked_value);
+            self.money.insert(currency, (ts, available, blocked));
+        }
+    }
+}
+
 // from Tinkoff to avin
 impl From<api::orders::MoneyValue> for f64 {
     fn from(t: api::orders::MoneyValue) -> f64 {
@@ -1020,6 +1417,13 @@ impl From<api::orders::MoneyValue> for f64 {
         t.units as f64 + frac
     }
 }
+impl From<api::operations::MoneyValue> for f64 {
+    fn from(t: api::operations::MoneyValue) -> f64 {
+        let frac: f64 = t.nano as f64 / 1_000_000_000.0;
+
+        t.units as f64 + frac
+    }
+}
 impl From<api::stoporders::MoneyValue> for f64 {
     fn from(t: api::stoporders::MoneyValue) -> f64 {
         let frac: f64 = t.nano as f64 / 1_000_000_000.0;
@@ -1266,13 +1670,14 @@ impl From<api::orders::OrderState> for MarketOrder {
                 todo!()
             }
             s::ExecutionReportStatusPartiallyfill => {
-                let order = PostedMarketOrder {
+                let order = PartiallyFilledMarketOrder {
                     direction,
                     lots,
                     broker_id,
+                    executed: t.lots_executed as u32,
                     transactions,
                 };
-                MarketOrder::Posted(order)
+                MarketOrder::PartiallyFilled(order)
             }
         }
     }
@@ -1357,6 +1762,7 @@ impl From<api::orders::OrderState> for LimitOrder {
         //     },
         // ]
 
+        let status = t.execution_report_status();
         let direction: Direction = t.direction().into();
 
         let mut transactions = Vec::new();
@@ -1365,6 +1771,19 @@ impl From<api::orders::OrderState> for LimitOrder {
             transactions.push(t);
         }
 
+        use api::orders::OrderExecutionReportStatus as s;
+        if status == s::ExecutionReportStatusPartiallyfill {
+            let order = PartiallyFilledLimitOrder {
+                direction,
+                lots: t.lots_requested as u32,
+                price: t.initial_security_price.unwrap().into(),
+                broker_id: t.order_id,
+                executed: t.lots_executed as u32,
+                transactions,
+            };
+            return LimitOrder::PartiallyFilled(order);
+        }
+
         let posted_limit_order = PostedLimitOrder {
             direction,
             lots: t.lots_requested as u32,
@@ -1538,7 +1957,17 @@ impl From<api::orders::PostOrderResponse> for LimitOrder {
             }
 
             status::ExecutionReportStatusPartiallyfill => {
-                todo!();
+                // NOTE: транзакций в PostOrderResponse нет, только
+                // количество исполненных лотов
+                let order = PartiallyFilledLimitOrder {
+                    direction: t.direction().into(),
+                    lots: t.lots_requested as u32,
+                    price: t.initial_security_price.unwrap().into(),
+                    broker_id: t.order_id,
+                    executed: t.lots_executed as u32,
+                    transactions: Vec::new(),
+                };
+                LimitOrder::PartiallyFilled(order)
             }
         }
     }
@@ -1784,6 +2213,22 @@ impl From<api::marketdata::Candle> for BarEvent {
         BarEvent { bar, tf, figi }
     }
 }
+impl From<api::marketdata::OrderBook> for BookEvent {
+    fn from(b: api::marketdata::OrderBook) -> Self {
+        let ts = b.time.unwrap_or_default();
+        let ts = DateTime::from_timestamp(ts.seconds, ts.nanos as u32)
+            .unwrap()
+            .timestamp_nanos_opt()
+            .unwrap();
+        let level = |o: api::marketdata::Order| -> (f64, u32) {
+            (o.price.unwrap_or_default().into(), o.quantity as u32)
+        };
+        let bids = b.bids.into_iter().map(level).collect();
+        let asks = b.asks.into_iter().map(level).collect();
+
+        BookEvent::new(b.figi, OrderBook::new(ts, bids, asks))
+    }
+}
 impl From<api::marketdata::Trade> for TicEvent {
     fn from(t: api::marketdata::Trade) -> Self {
         let direction: Direction = t.direction().into();
@@ -1812,6 +2257,49 @@ impl From<api::marketdata::Trade> for TicEvent {
         TicEvent { figi, tic }
     }
 }
+fn t_cash_operation(t: api::operations::Operation) -> Option<CashOperation> {
+    use api::operations::OperationType as T;
+
+    // заводы и выводы бумаг (InputSecurities, OutputSecurities) деньги
+    // не двигают и пропускаются вместе с торговыми операциями
+    let kind = match T::try_from(t.operation_type).ok()? {
+        T::Input => OperationKind::Deposit,
+        T::Output => OperationKind::Withdrawal,
+        T::Dividend | T::DividendTransfer => OperationKind::Dividend,
+        T::Coupon => OperationKind::Coupon,
+        T::BondRepayment | T::BondRepaymentFull => OperationKind::Repayment,
+        T::Tax
+        | T::BondTax
+        | T::DividendTax
+        | T::BenefitTax
+        | T::TaxCorrection
+        | T::TaxProgressive
+        | T::BondTaxProgressive
+        | T::DividendTaxProgressive
+        | T::BenefitTaxProgressive
+        | T::TaxCorrectionProgressive
+        | T::TaxRepoProgressive
+        | T::TaxRepo
+        | T::TaxRepoHold => OperationKind::Tax,
+        T::BrokerFee
+        | T::ServiceFee
+        | T::MarginFee
+        | T::SuccessFee
+        | T::TrackMfee
+        | T::TrackPfee => OperationKind::Commission,
+        T::AccruingVarmargin | T::WritingOffVarmargin => {
+            OperationKind::VariationMargin
+        }
+        _ => return None,
+    };
+
+    let ts = t.date?;
+    let ts = DateTime::from_timestamp(ts.seconds, ts.nanos as u32)?
+        .timestamp_nanos_opt()?;
+    let value: f64 = t.payment?.into();
+
+    Some(CashOperation::new(ts, kind, value, &t.currency))
+}
 fn std_exchange_name(exchange_name: &str) -> String {
     let exchange_name = exchange_name.to_uppercase();
 
@@ -1979,6 +2467,80 @@ mod tests {
         assert_eq!(q.nano, 150000000);
     }
 
+    #[test]
+    fn cash_operation_kind() {
+        use api::operations::OperationType as T;
+
+        let operation = |kind: T| api::operations::Operation {
+            operation_type: kind as i32,
+            currency: "rub".to_string(),
+            payment: Some(api::operations::MoneyValue {
+                currency: "rub".to_string(),
+                units: 1000,
+                nano: 0,
+            }),
+            date: Some(prost_types::Timestamp {
+                seconds: 100,
+                nanos: 0,
+            }),
+            ..Default::default()
+        };
+        let kind = |t: T| t_cash_operation(operation(t)).map(|i| i.kind);
+
+        assert_eq!(kind(T::Input), Some(OperationKind::Deposit));
+        assert_eq!(kind(T::Coupon), Some(OperationKind::Coupon));
+        assert_eq!(kind(T::BondRepayment), Some(OperationKind::Repayment));
+        assert_eq!(
+            kind(T::BondRepaymentFull),
+            Some(OperationKind::Repayment)
+        );
+
+        // перевод бумаг и торговые операции - не движение денег
+        assert_eq!(kind(T::InputSecurities), None);
+        assert_eq!(kind(T::OutputSecurities), None);
+        assert_eq!(kind(T::Buy), None);
+    }
+    #[test]
+    fn positions_stream_balance() {
+        let money = |currency: &str, units| api::operations::MoneyValue {
+            currency: currency.to_string(),
+            units,
+            nano: 0,
+        };
+        let mut data = api::operations::PositionData {
+            account_id: "1".to_string(),
+            money: vec![api::operations::PositionsMoney {
+                available_value: Some(money("rub", 7_500)),
+                blocked_value: Some(money("rub", 2_500)),
+            }],
+            date: Some(prost_types::Timestamp {
+                seconds: 100,
+                nanos: 500,
+            }),
+            ..Default::default()
+        };
+        let mut balance = Balance::default();
+        balance.apply(&data);
+
+        // поток прислал только доллары, рубли прежние
+        data.money = vec![api::operations::PositionsMoney {
+            available_value: Some(money("usd", 10)),
+            blocked_value: None,
+        }];
+        balance.apply(&data);
+        assert_eq!(
+            balance.get("rub"),
+            Some((100_000_000_500, 7500.0, 2500.0))
+        );
+        assert_eq!(balance.get("usd"), Some((100_000_000_500, 10.0, 0.0)));
+
+        let (ts, available, blocked) = balance.get("rub").unwrap();
+        let mut a = Account::new("Alex", "1");
+        a.set_balance(ts, "rub", available + blocked, blocked);
+        assert_eq!(a.cash(), 10_000.0);
+        assert_eq!(a.free(), 7_500.0);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn get_shares() {
@@ -2210,6 +2772,7 @@ mod tests {
                     assert_eq!(e.figi, *sber.figi());
                     tic -= 1;
                 }
+                Event::Book(_) => {}
                 Event::Order(_) => {}
             }
             if bar <= 0 && tic <= 0 {
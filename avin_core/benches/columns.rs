@@ -0,0 +1,161 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Columns and indicator kernels against per-bar recalculation.
+//!
+//! # ru
+//! Сравнение расчета индикаторов по колонкам баров ядрами avin_utils
+//! с пересчетом окна на каждом баре по вектору баров, как считались
+//! индикаторы раньше. Пять лет минутных баров (~650 тысяч).
+//!
+//! Запуск: cargo bench -p avin_core --bench columns
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use avin_core::{Bar, BarColumns};
+use avin_utils as utils;
+
+const BARS: usize = 5 * 250 * 525;
+const PERIOD: usize = 250;
+const RUNS: u32 = 5;
+
+fn main() {
+    let bars = bars(BARS);
+    println!("{BARS} bars, period {PERIOD}, best of {RUNS} runs");
+
+    // колонки строятся один раз, как их хранит график
+    let cols = BarColumns::from_bars(&bars);
+    compare(
+        "atr",
+        || atr_per_bar(&bars, PERIOD),
+        || {
+            let tr = utils::true_range(cols.high(), cols.low(), cols.close());
+            utils::sma(&tr, PERIOD)
+        },
+    );
+    compare(
+        "sma",
+        || sma_per_bar(&bars, PERIOD),
+        || utils::sma(cols.close(), PERIOD),
+    );
+    compare(
+        "highest",
+        || highest_per_bar(&bars, PERIOD),
+        || utils::highest(cols.high(), PERIOD),
+    );
+    compare(
+        "lowest",
+        || lowest_per_bar(&bars, PERIOD),
+        || utils::lowest(cols.low(), PERIOD),
+    );
+}
+
+fn compare(
+    name: &str,
+    per_bar: impl Fn() -> Vec<f64>,
+    columns: impl Fn() -> Vec<f64>,
+) {
+    // скользящая сумма и сумма окна расходятся в последних знаках
+    let same = per_bar()
+        .iter()
+        .zip(columns())
+        .all(|(a, b)| (a - b).abs() < 1e-6);
+    assert!(same, "{name}: results differ");
+
+    let old = best(&per_bar);
+    let new = best(&columns);
+    println!(
+        "{name:<8} per bar {:>9.2} ms   columns {:>7.2} ms   x{:.1}",
+        ms(old),
+        ms(new),
+        old.as_secs_f64() / new.as_secs_f64()
+    );
+}
+fn best(f: &impl Fn() -> Vec<f64>) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let begin = Instant::now();
+            black_box(f());
+            begin.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Случайное блуждание, детерминированное.
+fn bars(count: usize) -> Vec<Bar> {
+    let mut seed: u64 = 42;
+    let mut price = 100.0;
+    let mut bars = Vec::with_capacity(count);
+
+    for i in 0..count {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let step = (seed % 201) as f64 / 100.0 - 1.0;
+        let o = price;
+        price = (price + step * 0.1).max(1.0);
+        let c = price;
+        let h = o.max(c) + (seed % 7) as f64 * 0.01;
+        let l = o.min(c) - (seed % 5) as f64 * 0.01;
+        bars.push(Bar::new(i as i64, o, h, l, c, seed % 1000));
+    }
+
+    bars
+}
+
+// Пересчет окна на каждом баре по вектору баров.
+fn atr_per_bar(bars: &[Bar], period: usize) -> Vec<f64> {
+    let mut tr = Vec::with_capacity(bars.len());
+    let mut atr = Vec::with_capacity(bars.len());
+
+    for (i, bar) in bars.iter().enumerate() {
+        let range = match i {
+            0 => bar.h - bar.l,
+            _ => {
+                let prev_c = bars[i - 1].c;
+                let hc = (bar.h - prev_c).abs();
+                let lc = (bar.l - prev_c).abs();
+                (bar.h - bar.l).max(hc).max(lc)
+            }
+        };
+        tr.push(range);
+
+        let window = &tr[(i + 1).saturating_sub(period)..=i];
+        atr.push(window.iter().sum::<f64>() / window.len() as f64);
+    }
+
+    atr
+}
+fn sma_per_bar(bars: &[Bar], period: usize) -> Vec<f64> {
+    (0..bars.len())
+        .map(|i| {
+            let window = &bars[(i + 1).saturating_sub(period)..=i];
+            window.iter().map(|b| b.c).sum::<f64>() / window.len() as f64
+        })
+        .collect()
+}
+fn highest_per_bar(bars: &[Bar], period: usize) -> Vec<f64> {
+    (0..bars.len())
+        .map(|i| {
+            let window = &bars[(i + 1).saturating_sub(period)..=i];
+            window.iter().fold(f64::MIN, |a, b| a.max(b.h))
+        })
+        .collect()
+}
+fn lowest_per_bar(bars: &[Bar], period: usize) -> Vec<f64> {
+    (0..bars.len())
+        .map(|i| {
+            let window = &bars[(i + 1).saturating_sub(period)..=i];
+            window.iter().fold(f64::MAX, |a, b| a.min(b.l))
+        })
+        .collect()
+}
@@ -5,10 +5,15 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
-use crate::Trade;
+use crate::{Signal, Trade};
 
 use super::GetAccountAction;
 use super::GetBarsAction;
+use super::GetCashOperationsAction;
+use super::GetOperationsAction;
+use super::GetOrdersAction;
+use super::GetPositionAction;
+use super::NotifyAction;
 use super::OrderAction;
 use super::StreamAction;
 
@@ -26,23 +31,36 @@ pub enum Action {
     TradeClosed(Trade),
     TradeOpened(Trade),
 
+    Signal(Signal),
+    Notify(NotifyAction),
+
     Subscribe(StreamAction),
     Unsubscribe(StreamAction),
 
     GetAccount(GetAccountAction),
     GetBars(GetBarsAction),
+    GetOrders(GetOrdersAction),
+    GetPosition(GetPositionAction),
+    GetOperations(GetOperationsAction),
+    GetCashOperations(GetCashOperationsAction),
 }
 impl std::fmt::Display for Action {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Action::GetAccount(a) => write!(f, "Action={a}"),
             Action::GetBars(a) => write!(f, "Action={a}"),
+            Action::GetOrders(a) => write!(f, "Action={a}"),
+            Action::GetPosition(a) => write!(f, "Action={a}"),
+            Action::GetOperations(a) => write!(f, "Action={a}"),
+            Action::GetCashOperations(a) => write!(f, "Action={a}"),
             Action::Post(a) => write!(f, "Action={a}"),
             Action::Cancel(a) => write!(f, "Action={a}"),
             Action::Subscribe(a) => write!(f, "Action={a}"),
             Action::Unsubscribe(a) => write!(f, "Action={a}"),
             Action::TradeOpened(a) => write!(f, "Action={a}"),
             Action::TradeClosed(a) => write!(f, "Action={a}"),
+            Action::Signal(a) => write!(f, "Action={a}"),
+            Action::Notify(a) => write!(f, "Action={a}"),
         }
     }
 }
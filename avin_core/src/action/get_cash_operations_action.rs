@@ -0,0 +1,49 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, Utc};
+
+use crate::{Account, CashOperation};
+
+/// Message to get recent non-trade cash operations of account.
+///
+/// # ru
+/// Сообщение о запросе неторговых денежных операций счета у брокера
+/// начиная с from: дивиденды, купоны, налоги, комиссии, пополнения и
+/// выводы. Трейдер запрашивает их при загрузке счета, чтобы движение
+/// денег, пока он не работал, было видно в логе.
+///
+/// Содержит счет, начало периода и канал для передачи ответа.
+#[derive(Debug)]
+pub struct GetCashOperationsAction {
+    pub account: Account,
+    pub from: DateTime<Utc>,
+    pub tx: tokio::sync::oneshot::Sender<Vec<CashOperation>>,
+}
+impl GetCashOperationsAction {
+    /// Create new get cash operations action.
+    ///
+    /// # ru
+    /// Создает новое действие с запросом денежных операций у брокера.
+    pub fn new(
+        account: Account,
+        from: DateTime<Utc>,
+        tx: tokio::sync::oneshot::Sender<Vec<CashOperation>>,
+    ) -> Self {
+        Self { account, from, tx }
+    }
+}
+impl std::fmt::Display for GetCashOperationsAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "GetCashOperationsAction={} from={}",
+            self.account.name(),
+            self.from
+        )
+    }
+}
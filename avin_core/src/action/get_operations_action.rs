@@ -0,0 +1,57 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, Utc};
+
+use crate::{Account, Iid, Operation};
+
+/// Message to get recent operations of instrument.
+///
+/// # ru
+/// Сообщение о запросе исполненных операций счета по инструменту у
+/// брокера начиная с from. Трейдер запрашивает их при запуске, чтобы
+/// узнать, что исполнилось, пока он не работал.
+///
+/// Содержит счет, идентификатор инструмента, начало периода и канал
+/// для передачи ответа.
+#[derive(Debug)]
+pub struct GetOperationsAction {
+    pub account: Account,
+    pub iid: Iid,
+    pub from: DateTime<Utc>,
+    pub tx: tokio::sync::oneshot::Sender<Vec<Operation>>,
+}
+impl GetOperationsAction {
+    /// Create new get operations action.
+    ///
+    /// # ru
+    /// Создает новое действие с запросом операций у брокера.
+    pub fn new(
+        account: Account,
+        iid: Iid,
+        from: DateTime<Utc>,
+        tx: tokio::sync::oneshot::Sender<Vec<Operation>>,
+    ) -> Self {
+        Self {
+            account,
+            iid,
+            from,
+            tx,
+        }
+    }
+}
+impl std::fmt::Display for GetOperationsAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "GetOperationsAction={} {} from={}",
+            self.account.name(),
+            self.iid,
+            self.from
+        )
+    }
+}
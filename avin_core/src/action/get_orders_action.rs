@@ -0,0 +1,42 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use crate::{Account, Iid, Order};
+
+/// Message to get active orders.
+///
+/// # ru
+/// Сообщение о запросе активных ордеров счета по инструменту у
+/// брокера: выставленные лимитные и стоп ордера. Трейдер запрашивает
+/// их при запуске работы и передает стратегиям, чтобы после
+/// перезапуска они не выставили те же ордера второй раз.
+///
+/// Содержит счет, идентификатор инструмента и канал для передачи ответа.
+#[derive(Debug)]
+pub struct GetOrdersAction {
+    pub account: Account,
+    pub iid: Iid,
+    pub tx: tokio::sync::oneshot::Sender<Vec<Order>>,
+}
+impl GetOrdersAction {
+    /// Create new get orders action.
+    ///
+    /// # ru
+    /// Создает новое действие с запросом активных ордеров у брокера.
+    pub fn new(
+        account: Account,
+        iid: Iid,
+        tx: tokio::sync::oneshot::Sender<Vec<Order>>,
+    ) -> Self {
+        Self { account, iid, tx }
+    }
+}
+impl std::fmt::Display for GetOrdersAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "GetOrdersAction={} {}", self.account.name(), self.iid)
+    }
+}
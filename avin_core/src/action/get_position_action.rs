@@ -0,0 +1,42 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use crate::{Account, Iid};
+
+/// Message to get position of instrument.
+///
+/// # ru
+/// Сообщение о запросе позиции счета по инструменту у брокера: сколько
+/// бумаг (не лотов) сейчас на счете, короткая позиция - меньше нуля.
+/// Трейдер запрашивает ее при запуске, чтобы сверить со своим
+/// сохраненным состоянием.
+///
+/// Содержит счет, идентификатор инструмента и канал для передачи ответа.
+#[derive(Debug)]
+pub struct GetPositionAction {
+    pub account: Account,
+    pub iid: Iid,
+    pub tx: tokio::sync::oneshot::Sender<i64>,
+}
+impl GetPositionAction {
+    /// Create new get position action.
+    ///
+    /// # ru
+    /// Создает новое действие с запросом позиции у брокера.
+    pub fn new(
+        account: Account,
+        iid: Iid,
+        tx: tokio::sync::oneshot::Sender<i64>,
+    ) -> Self {
+        Self { account, iid, tx }
+    }
+}
+impl std::fmt::Display for GetPositionAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "GetPositionAction={} {}", self.account.name(), self.iid)
+    }
+}
@@ -8,11 +8,21 @@
 mod _action;
 mod get_account_action;
 mod get_bars_action;
+mod get_cash_operations_action;
+mod get_operations_action;
+mod get_orders_action;
+mod get_position_action;
+mod notify_action;
 mod order_action;
 mod stream_action;
 
 pub use _action::Action;
 pub use get_account_action::GetAccountAction;
 pub use get_bars_action::GetBarsAction;
+pub use get_cash_operations_action::GetCashOperationsAction;
+pub use get_operations_action::GetOperationsAction;
+pub use get_orders_action::GetOrdersAction;
+pub use get_position_action::GetPositionAction;
+pub use notify_action::{NotifyAction, NotifyLevel};
 pub use order_action::OrderAction;
 pub use stream_action::StreamAction;
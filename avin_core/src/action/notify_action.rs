@@ -0,0 +1,102 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_utils::AvinError;
+
+/// Importance of notification.
+///
+/// # ru
+/// Важность уведомления. Каналы доставки трейдера могут пропускать
+/// уведомления ниже своего уровня.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NotifyLevel {
+    Info,
+    Warning,
+    Alert,
+}
+impl NotifyLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Alert => "alert",
+        }
+    }
+}
+impl TryFrom<&str> for NotifyLevel {
+    type Error = AvinError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "info" => Ok(Self::Info),
+            "warning" | "warn" => Ok(Self::Warning),
+            "alert" => Ok(Self::Alert),
+            _ => Err(AvinError::InvalidValue(format!(
+                "notify level '{value}', expected info, warning, alert"
+            ))),
+        }
+    }
+}
+impl std::fmt::Display for NotifyLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Message of strategy to user.
+///
+/// # ru
+/// Уведомление стратегии для пользователя, например "entered long SBER
+/// @ 280, stop 276". Стратегия не знает, как оно будет доставлено:
+/// в боевом режиме `Trader` передает его своим каналам уведомлений
+/// (лог, файл для GUI терминала, Telegram), тестер только пишет в лог.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotifyAction {
+    pub strategy: String,
+    pub level: NotifyLevel,
+    pub message: String,
+}
+impl NotifyAction {
+    /// Create new notification.
+    ///
+    /// # ru
+    /// Создает новое уведомление от стратегии.
+    pub fn new(strategy: &str, level: NotifyLevel, message: &str) -> Self {
+        Self {
+            strategy: strategy.to_string(),
+            level,
+            message: message.to_string(),
+        }
+    }
+}
+impl std::fmt::Display for NotifyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "NotifyAction=[{}] {}: {}",
+            self.level, self.strategy, self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level() {
+        assert_eq!(
+            NotifyLevel::try_from("Warn").unwrap(),
+            NotifyLevel::Warning
+        );
+        assert!(NotifyLevel::try_from("loud").is_err());
+        assert!(NotifyLevel::Alert > NotifyLevel::Info);
+
+        let a = NotifyAction::new("Grid", NotifyLevel::Alert, "level 99");
+        assert_eq!(a.to_string(), "NotifyAction=[alert] Grid: level 99");
+    }
+}
@@ -0,0 +1,220 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use tokio::runtime::Handle;
+
+use avin_utils::{AlertSinkCfg, AvinError};
+
+use super::{EmailSink, TelegramSink, WebhookSink};
+use crate::NotifyLevel;
+
+/// Message for user delivered by alert sinks.
+///
+/// # ru
+/// Оповещение пользователя: источник (стратегия, скан), важность,
+/// заголовок, текст и время события. Как доставить - решают каналы
+/// [`AlertSink`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub source: String,
+    pub level: NotifyLevel,
+    pub title: String,
+    pub text: String,
+    pub ts: i64,
+}
+impl Alert {
+    pub fn new(
+        source: &str,
+        level: NotifyLevel,
+        title: &str,
+        text: &str,
+        ts: i64,
+    ) -> Self {
+        Self {
+            source: source.to_string(),
+            level,
+            title: title.to_string(),
+            text: text.to_string(),
+            ts,
+        }
+    }
+    /// Alert as JSON object.
+    ///
+    /// # ru
+    /// Оповещение как JSON объект, так его получает вебхук.
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "source": self.source,
+            "level": self.level.name(),
+            "title": self.title,
+            "text": self.text,
+            "ts_nanos": self.ts,
+        })
+    }
+}
+impl std::fmt::Display for Alert {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Alert=[{}] {}: {}", self.level, self.source, self.title)
+    }
+}
+
+/// Delivery channel of alerts.
+///
+/// # ru
+/// Канал доставки оповещений. Отправка не должна задерживать
+/// вызывающего: сетевые каналы отправляют в отдельной задаче на
+/// рантайме tokio, переданном при создании, - так отправлять можно и из
+/// потоков вне рантайма. Ошибки доставки только пишутся в лог.
+pub trait AlertSink: Send + Sync {
+    fn name(&self) -> &str;
+    fn send(&self, alert: &Alert);
+}
+
+/// Set of alert sinks with minimal level of each.
+///
+/// # ru
+/// Набор каналов доставки, у каждого свой минимальный уровень
+/// важности. Общий для уведомлений трейдера и оповещений сканера.
+#[derive(Default)]
+pub struct AlertSinks {
+    sinks: Vec<(Box<dyn AlertSink>, NotifyLevel)>,
+}
+impl AlertSinks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create sinks enabled in config, rt - runtime for sending tasks.
+    ///
+    /// # ru
+    /// Создает каналы, включенные в конфиге, rt - рантайм tokio, в
+    /// котором каналы отправляют оповещения.
+    pub fn from_cfg(
+        cfg: &AlertSinkCfg,
+        rt: &Handle,
+    ) -> Result<Self, AvinError> {
+        let mut sinks = Self::new();
+
+        if !cfg.telegram_token.is_empty() && !cfg.telegram_chat.is_empty() {
+            let sink = TelegramSink::new(
+                &cfg.telegram_token,
+                &cfg.telegram_chat,
+                rt.clone(),
+            );
+            sinks.add(Box::new(sink), level(&cfg.telegram_level)?);
+        }
+        if !cfg.webhook_url.is_empty() {
+            let sink = WebhookSink::new(&cfg.webhook_url, rt.clone());
+            sinks.add(Box::new(sink), level(&cfg.webhook_level)?);
+        }
+        if !cfg.smtp_host.is_empty() && !cfg.email_to.is_empty() {
+            let sink = EmailSink::from_cfg(cfg, rt.clone())?;
+            sinks.add(Box::new(sink), level(&cfg.email_level)?);
+        }
+
+        Ok(sinks)
+    }
+    pub fn add(&mut self, sink: Box<dyn AlertSink>, level: NotifyLevel) {
+        self.sinks.push((sink, level));
+    }
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+    /// Send alert to sinks with level not above alert level.
+    ///
+    /// # ru
+    /// Отправляет оповещение в каналы, уровень которых не выше уровня
+    /// оповещения.
+    pub fn send(&self, alert: &Alert) {
+        for (sink, level) in self.sinks.iter() {
+            if alert.level >= *level {
+                sink.send(alert);
+            }
+        }
+    }
+}
+impl std::fmt::Debug for AlertSinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let names: Vec<String> = self
+            .sinks
+            .iter()
+            .map(|(sink, level)| format!("{}>={}", sink.name(), level))
+            .collect();
+
+        write!(f, "AlertSinks={names:?}")
+    }
+}
+
+fn level(s: &str) -> Result<NotifyLevel, AvinError> {
+    if s.is_empty() {
+        return Ok(NotifyLevel::Info);
+    }
+
+    NotifyLevel::try_from(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct Memory(Arc<Mutex<Vec<String>>>);
+    impl AlertSink for Memory {
+        fn name(&self) -> &str {
+            "memory"
+        }
+        fn send(&self, alert: &Alert) {
+            self.0.lock().unwrap().push(alert.title.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn sinks() {
+        let cfg = AlertSinkCfg {
+            telegram_token: "token".to_string(),
+            telegram_chat: "chat".to_string(),
+            webhook_url: "http://localhost/hook".to_string(),
+            webhook_level: "alert".to_string(),
+            ..Default::default()
+        };
+        let sinks = AlertSinks::from_cfg(&cfg, &Handle::current()).unwrap();
+        assert_eq!(sinks.len(), 2);
+        assert_eq!(
+            format!("{sinks:?}"),
+            r#"AlertSinks=["telegram>=info", "webhook>=alert"]"#
+        );
+        let cfg = AlertSinkCfg {
+            email_level: "loud".to_string(),
+            smtp_host: "localhost".to_string(),
+            email_from: "bot@localhost".to_string(),
+            email_to: vec!["user@localhost".to_string()],
+            ..Default::default()
+        };
+        assert!(AlertSinks::from_cfg(&cfg, &Handle::current()).is_err());
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut sinks = AlertSinks::new();
+        sinks.add(Box::new(Memory(sent.clone())), NotifyLevel::Warning);
+        for (level, title) in [
+            (NotifyLevel::Info, "a"),
+            (NotifyLevel::Warning, "b"),
+            (NotifyLevel::Alert, "c"),
+        ] {
+            sinks.send(&Alert::new("MaCross", level, title, "", 0));
+        }
+        assert_eq!(*sent.lock().unwrap(), vec!["b", "c"]);
+
+        let a = Alert::new("MaCross", NotifyLevel::Alert, "SBER", "stop", 1);
+        assert_eq!(
+            a.json().to_string(),
+            r#"{"level":"alert","source":"MaCross","text":"stop","title":"SBER","ts_nanos":1}"#
+        );
+    }
+}
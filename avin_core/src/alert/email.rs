@@ -0,0 +1,198 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use lettre::message::{Mailbox, Message, header::ContentType};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use tokio::runtime::Handle;
+
+use avin_utils::{self as utils, AlertSinkCfg, AvinError, MSK_OFFSET};
+
+use super::{Alert, AlertSink};
+
+/// Alerts by email through SMTP server.
+///
+/// # ru
+/// Оповещения по почте через SMTP сервер, соединение всегда
+/// шифрованное: порт 465 - сразу TLS, любой другой - обязательный
+/// STARTTLS (обычно 587). Логин и пароль, если заданы, уходят только
+/// после установки TLS. Письмо - простой текст в UTF-8, тема -
+/// заголовок оповещения.
+#[derive(Debug, Clone)]
+pub struct EmailSink {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    rt: Handle,
+}
+impl EmailSink {
+    /// Create by config, rt - runtime for sending tasks.
+    ///
+    /// # ru
+    /// Создает по конфигу, rt - рантайм tokio, в котором идет отправка.
+    /// Ошибка - если адреса или сервер заданы неверно.
+    pub fn from_cfg(
+        cfg: &AlertSinkCfg,
+        rt: Handle,
+    ) -> Result<Self, AvinError> {
+        let port = if cfg.smtp_port == 0 {
+            465
+        } else {
+            cfg.smtp_port
+        };
+        let builder = if port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&cfg.smtp_host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(
+                &cfg.smtp_host,
+            )
+        };
+        let mut builder = builder.map_err(invalid)?.port(port);
+        if !cfg.smtp_user.is_empty() {
+            let credentials = Credentials::new(
+                cfg.smtp_user.clone(),
+                cfg.smtp_password.clone(),
+            );
+            builder = builder.credentials(credentials);
+        }
+
+        let from = if cfg.email_from.is_empty() {
+            &cfg.smtp_user
+        } else {
+            &cfg.email_from
+        };
+        let from = from.parse::<Mailbox>().map_err(invalid)?;
+        let to = cfg
+            .email_to
+            .iter()
+            .map(|i| i.parse::<Mailbox>().map_err(invalid))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+            to,
+            rt,
+        })
+    }
+    /// Email of alert.
+    ///
+    /// # ru
+    /// Письмо оповещения: тема - заголовок, в теле текст, ниже
+    /// важность, источник и московское время события.
+    pub fn message(&self, alert: &Alert) -> Result<Message, AvinError> {
+        let dt = utils::dt(alert.ts) + MSK_OFFSET;
+        let body = format!(
+            "{}\n\n[{}] {} {}",
+            alert.text,
+            alert.level,
+            alert.source,
+            dt.format("%Y-%m-%d %H:%M:%S")
+        );
+
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(&alert.title)
+            .header(ContentType::TEXT_PLAIN);
+        for to in self.to.iter() {
+            builder = builder.to(to.clone());
+        }
+
+        builder.body(body).map_err(invalid)
+    }
+}
+impl AlertSink for EmailSink {
+    fn name(&self) -> &str {
+        "email"
+    }
+    fn send(&self, alert: &Alert) {
+        let msg = match self.message(alert) {
+            Ok(msg) => msg,
+            Err(err) => {
+                log::error!("Alert email: {err}");
+                return;
+            }
+        };
+        let transport = self.transport.clone();
+
+        self.rt.spawn(async move {
+            if let Err(err) = transport.send(msg).await {
+                log::error!("Alert email: {err}");
+            }
+        });
+    }
+}
+
+fn invalid(err: impl std::fmt::Display) -> AvinError {
+    AvinError::InvalidValue(format!("email: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NotifyLevel;
+
+    fn alert() -> Alert {
+        Alert::new(
+            "MaCross",
+            NotifyLevel::Alert,
+            "SBER stop",
+            "position closed\n.dot line",
+            1_700_000_000_000_000_000,
+        )
+    }
+
+    #[tokio::test]
+    async fn message() {
+        let cfg = AlertSinkCfg {
+            smtp_host: "smtp.example.com".to_string(),
+            smtp_user: "bot@example.com".to_string(),
+            smtp_password: "secret".to_string(),
+            email_to: vec![
+                "a@example.com".to_string(),
+                "b@example.com".to_string(),
+            ],
+            ..Default::default()
+        };
+        let sink = EmailSink::from_cfg(&cfg, Handle::current()).unwrap();
+        let msg = sink.message(&alert()).unwrap();
+        let text = String::from_utf8(msg.formatted()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert!(lines.contains(&"From: bot@example.com"));
+        assert!(lines.contains(&"To: a@example.com, b@example.com"));
+        assert!(lines.contains(&"Subject: SBER stop"));
+        assert!(lines.contains(&"position closed"));
+        assert!(lines.contains(&".dot line"));
+        assert!(lines.contains(&"[alert] MaCross 2023-11-15 01:13:20"));
+
+        // русская тема кодируется заголовком MIME
+        let mut a = alert();
+        a.title = "Сбер стоп".to_string();
+        let text =
+            String::from_utf8(sink.message(&a).unwrap().formatted()).unwrap();
+        assert!(text.contains("Subject: =?utf-8?"));
+    }
+
+    #[tokio::test]
+    async fn invalid_cfg() {
+        let cfg = AlertSinkCfg {
+            smtp_host: "smtp.example.com".to_string(),
+            smtp_port: 587,
+            email_from: "bot@example.com".to_string(),
+            email_to: vec!["not an address".to_string()],
+            ..Default::default()
+        };
+        assert!(EmailSink::from_cfg(&cfg, Handle::current()).is_err());
+
+        let cfg = AlertSinkCfg {
+            email_to: vec!["a@example.com".to_string()],
+            ..cfg
+        };
+        assert!(EmailSink::from_cfg(&cfg, Handle::current()).is_ok());
+    }
+}
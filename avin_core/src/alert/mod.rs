@@ -0,0 +1,16 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+mod _alert;
+mod email;
+mod telegram;
+mod webhook;
+
+pub use _alert::{Alert, AlertSink, AlertSinks};
+pub use email::EmailSink;
+pub use telegram::TelegramSink;
+pub use webhook::WebhookSink;
@@ -0,0 +1,66 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use tokio::runtime::Handle;
+
+use super::{Alert, AlertSink};
+
+/// Alerts to Telegram chat by bot.
+///
+/// # ru
+/// Оповещения в чат Telegram через бота: заголовок первой строкой,
+/// ниже текст.
+#[derive(Debug, Clone)]
+pub struct TelegramSink {
+    token: String,
+    chat: String,
+    client: reqwest::Client,
+    rt: Handle,
+}
+impl TelegramSink {
+    /// Create by bot token and chat id, rt - runtime for sending tasks.
+    ///
+    /// # ru
+    /// Создает по токену бота и id чата, rt - рантайм tokio, в котором
+    /// идет отправка.
+    pub fn new(token: &str, chat: &str, rt: Handle) -> Self {
+        Self {
+            token: token.to_string(),
+            chat: chat.to_string(),
+            client: reqwest::Client::new(),
+            rt,
+        }
+    }
+}
+impl AlertSink for TelegramSink {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+    fn send(&self, alert: &Alert) {
+        let url =
+            format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+        let text = if alert.text.is_empty() {
+            alert.title.clone()
+        } else {
+            format!("{}\n{}", alert.title, alert.text)
+        };
+        let request = self
+            .client
+            .post(url)
+            .form(&[("chat_id", self.chat.as_str()), ("text", &text)]);
+
+        self.rt.spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    log::error!("Alert telegram: {}", response.status());
+                }
+                Ok(_) => {}
+                Err(err) => log::error!("Alert telegram: {err}"),
+            }
+        });
+    }
+}
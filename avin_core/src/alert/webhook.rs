@@ -0,0 +1,60 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use reqwest::header::CONTENT_TYPE;
+use tokio::runtime::Handle;
+
+use super::{Alert, AlertSink};
+
+/// Alerts to HTTP webhook as JSON.
+///
+/// # ru
+/// Оповещения на HTTP вебхук: POST запрос с JSON объектом оповещения,
+/// см. [`Alert::json`]. Подходит для своих сервисов, Slack/Discord
+/// через промежуточный адаптер, n8n и тп.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+    rt: Handle,
+}
+impl WebhookSink {
+    /// Create by url, rt - runtime for sending tasks.
+    ///
+    /// # ru
+    /// Создает по url вебхука, rt - рантайм tokio, в котором идет
+    /// отправка.
+    pub fn new(url: &str, rt: Handle) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+            rt,
+        }
+    }
+}
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+    fn send(&self, alert: &Alert) {
+        let request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(alert.json().to_string());
+
+        self.rt.spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    log::error!("Alert webhook: {}", response.status());
+                }
+                Ok(_) => {}
+                Err(err) => log::error!("Alert webhook: {err}"),
+            }
+        });
+    }
+}
@@ -100,6 +100,41 @@ impl AssetList {
         Ok(asset_list)
     }
 
+    /// Asset list as csv, format of [`AssetList::from_csv`].
+    ///
+    /// # ru
+    /// Список активов в csv формате, как в [`AssetList::from_csv`].
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for asset in self.assets.iter() {
+            let iid = asset.iid();
+            csv.push_str(&format!(
+                "{};{};{};\n",
+                iid.exchange(),
+                iid.category(),
+                iid.ticker()
+            ));
+        }
+
+        csv
+    }
+    /// Save asset list to csv file.
+    ///
+    /// # ru
+    /// Сохраняет список активов в csv файл.
+    pub fn save(&self, path: &Path) -> Result<(), AvinError> {
+        Cmd::write(&self.to_csv(), path)
+    }
+    /// Add asset, if it is not in list yet.
+    ///
+    /// # ru
+    /// Добавляет актив в конец списка, если его там еще нет.
+    pub fn add(&mut self, asset: Asset) {
+        if self.find_figi(asset.figi()).is_none() {
+            self.assets.push(asset);
+        }
+    }
+
     /// Return asset list name.
     ///
     /// # ru
@@ -187,6 +222,20 @@ mod tests {
         assert_eq!(asset_list.assets().len(), 3);
     }
 
+    #[test]
+    fn to_csv() {
+        let mut asset_list = AssetList::new("scan_gap_up");
+        let gazp = fixture::share("GAZP", "BBG004730RP0");
+        asset_list.add(Asset::from_iid(fixture::iid()));
+        asset_list.add(Asset::from_iid(gazp));
+        asset_list.add(Asset::from_iid(fixture::iid()));
+        assert_eq!(asset_list.len(), 2);
+        assert_eq!(
+            asset_list.to_csv(),
+            "MOEX;SHARE;SBER;\nMOEX;SHARE;GAZP;\n"
+        );
+    }
+
     #[test]
     #[should_panic]
     fn from_incorrect_csv() {
@@ -18,8 +18,25 @@ pub enum Category {
     FUTURE,
     OPTION,
     ETF,
+    SPOT,
 }
 impl Category {
+    /// Return all categories.
+    ///
+    /// # ru
+    /// Возвращает все категории инструментов.
+    pub fn all() -> Vec<Category> {
+        vec![
+            Self::CURRENCY,
+            Self::INDEX,
+            Self::SHARE,
+            Self::BOND,
+            Self::FUTURE,
+            Self::OPTION,
+            Self::ETF,
+            Self::SPOT,
+        ]
+    }
     /// Return category name
     ///
     /// # ru
@@ -33,6 +50,7 @@ impl Category {
             Self::FUTURE => "FUTURE",
             Self::OPTION => "OPTION",
             Self::ETF => "ETF",
+            Self::SPOT => "SPOT",
         }
     }
 }
@@ -46,6 +64,7 @@ impl From<&str> for Category {
             "FUTURE" => Category::FUTURE,
             "OPTION" => Category::OPTION,
             "ETF" => Category::ETF,
+            "SPOT" => Category::SPOT,
             _ => todo!("Invalid value for category: {value}"),
         }
     }
@@ -64,6 +83,7 @@ mod tests {
         assert_eq!(Category::FUTURE.name(), "FUTURE");
         assert_eq!(Category::OPTION.name(), "OPTION");
         assert_eq!(Category::ETF.name(), "ETF");
+        assert_eq!(Category::SPOT.name(), "SPOT");
     }
 
     #[test]
@@ -75,6 +95,7 @@ mod tests {
         assert_eq!(Category::FUTURE.to_string(), "FUTURE");
         assert_eq!(Category::OPTION.to_string(), "OPTION");
         assert_eq!(Category::ETF.to_string(), "ETF");
+        assert_eq!(Category::SPOT.to_string(), "SPOT");
     }
     #[test]
     fn from_str() {
@@ -85,5 +106,6 @@ mod tests {
         assert_eq!(Category::from("FUTURE"), Category::FUTURE);
         assert_eq!(Category::from("OPTION"), Category::OPTION);
         assert_eq!(Category::from("ETF"), Category::ETF);
+        assert_eq!(Category::from("SPOT"), Category::SPOT);
     }
 }
@@ -12,8 +12,16 @@
 #[derive(Debug, PartialEq, Clone, Copy, strum::Display)]
 pub enum Exchange {
     MOEX,
+    BINANCE,
 }
 impl Exchange {
+    /// Return all exchanges.
+    ///
+    /// # ru
+    /// Возвращает все биржи.
+    pub fn all() -> Vec<Exchange> {
+        vec![Self::MOEX, Self::BINANCE]
+    }
     /// Return exchange name
     ///
     /// # ru
@@ -21,6 +29,7 @@ impl Exchange {
     pub fn name(&self) -> &'static str {
         match self {
             Self::MOEX => "MOEX",
+            Self::BINANCE => "BINANCE",
         }
     }
 }
@@ -28,6 +37,7 @@ impl From<&str> for Exchange {
     fn from(value: &str) -> Self {
         match value.to_uppercase().as_str() {
             "MOEX" => Exchange::MOEX,
+            "BINANCE" => Exchange::BINANCE,
             _ => panic!("Invalid exchange: {value}"),
         }
     }
@@ -40,13 +50,16 @@ mod tests {
     #[test]
     fn name() {
         assert_eq!(Exchange::MOEX.name(), "MOEX");
+        assert_eq!(Exchange::BINANCE.name(), "BINANCE");
     }
     #[test]
     fn to_str() {
         assert_eq!(Exchange::MOEX.to_string(), "MOEX");
+        assert_eq!(Exchange::BINANCE.to_string(), "BINANCE");
     }
     #[test]
     fn from_str() {
         assert_eq!(Exchange::from("MOEX"), Exchange::MOEX);
+        assert_eq!(Exchange::from("binance"), Exchange::BINANCE);
     }
 }
@@ -9,9 +9,10 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 
 use bitcode::{Decode, Encode};
+use chrono::{DateTime, NaiveDate, Utc};
 use polars::frame::DataFrame;
 
-use avin_utils::{AvinError, CFG};
+use avin_utils::{self as utils, AvinError, CFG};
 
 /// Iid - Instrument ID.
 ///
@@ -78,6 +79,10 @@ impl Iid {
         for i in columns {
             info.insert(i.to_string(), df.get_as_str(i));
         }
+        // инструмент из архива делистинга
+        if df.column("delisted").is_ok() {
+            info.insert("delisted".to_string(), df.get_as_str("delisted"));
+        }
 
         Ok(Iid::new(info))
     }
@@ -143,6 +148,131 @@ impl Iid {
     pub fn step(&self) -> f64 {
         self.info.get("step").unwrap().parse().unwrap()
     }
+    /// Return delisting date, if instrument found in archive.
+    ///
+    /// # ru
+    /// Возвращает дату делистинга, если инструмент найден в архиве
+    /// кэша, то есть больше не торгуется. Такой инструмент годится
+    /// только для работы с историческими данными.
+    pub fn delisted(&self) -> Option<&String> {
+        self.info.get("delisted")
+    }
+    /// Return listing date: date of first 1M bar (or day bar) at broker.
+    ///
+    /// # ru
+    /// Возвращает начало торгов инструментом: время первой минутной
+    /// (или дневной) свечи у брокера из кэша, None - неизвестно.
+    pub fn listed_dt(&self) -> Option<DateTime<Utc>> {
+        ["first_1m", "first_d"]
+            .iter()
+            .find_map(|key| parse_dt(self.info.get(*key)?))
+    }
+    /// Return delisting date as datetime.
+    ///
+    /// # ru
+    /// Возвращает дату делистинга, None - инструмент торгуется.
+    pub fn delisted_dt(&self) -> Option<DateTime<Utc>> {
+        parse_dt(self.delisted()?)
+    }
+    /// Part of period [begin, end) when instrument was traded.
+    ///
+    /// # ru
+    /// Часть периода [begin, end), когда инструмент торговался: от
+    /// листинга до делистинга. None - в этом периоде не торговался.
+    /// Используется в бэктестах по набору инструментов, чтобы не
+    /// торговать бумагу до ее появления на бирже и после ухода с нее.
+    pub fn trading_period(
+        &self,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let begin = match self.listed_dt() {
+            Some(listed) => begin.max(listed),
+            None => begin,
+        };
+        let end = match self.delisted_dt() {
+            Some(delisted) => end.min(delisted),
+            None => end,
+        };
+
+        (begin < end).then_some((begin, end))
+    }
+    /// Check instrument is listed, before subscribe or trade it.
+    ///
+    /// # ru
+    /// Проверяет, что инструмент торгуется. Для инструмента после
+    /// делистинга возвращает ошибку - подписка на рыночные данные
+    /// и выставление ордеров невозможны.
+    pub fn check_listed(&self) -> Result<(), AvinError> {
+        match self.delisted() {
+            Some(date) => {
+                let msg = format!("{self} delisted {date}");
+                Err(AvinError::InvalidValue(msg))
+            }
+            None => Ok(()),
+        }
+    }
+    /// Round price to the minimum price increment.
+    ///
+    /// # ru
+    /// Округляет цену до ближайшей кратной минимальному шагу цены.
+    /// Брокер не примет ордер с ценой не кратной шагу.
+    pub fn round_to_step(&self, price: f64) -> f64 {
+        utils::round_price(price, self.step())
+    }
+    /// Check price is multiple of the minimum price increment.
+    ///
+    /// # ru
+    /// Проверяет, что цена кратна минимальному шагу цены.
+    pub fn is_on_step(&self, price: f64) -> bool {
+        self.round_to_step(price) == utils::round(price, 9)
+    }
+    /// Validate price and lots of order before posting.
+    ///
+    /// # ru
+    /// Проверяет цену и количество лотов ордера перед выставлением:
+    /// инструмент не снят с торгов, количество лотов больше нуля, цена положительная и кратна шагу
+    /// цены. Позволяет получить понятную ошибку до отправки ордера,
+    /// вместо отказа брокера.
+    pub fn validate_order(
+        &self,
+        price: f64,
+        lots: u32,
+    ) -> Result<(), AvinError> {
+        self.check_listed()?;
+        if lots == 0 {
+            let msg = format!("{self} lots must be greater than zero");
+            return Err(AvinError::InvalidValue(msg));
+        }
+        if !price.is_finite() || price <= 0.0 {
+            let msg = format!("{self} invalid price {price}");
+            return Err(AvinError::InvalidValue(msg));
+        }
+        if !self.is_on_step(price) {
+            let msg = format!(
+                "{self} price {price} is not multiple of step {}",
+                self.step()
+            );
+            return Err(AvinError::InvalidValue(msg));
+        }
+
+        Ok(())
+    }
+    /// Convert quantity of securities to whole lots.
+    ///
+    /// # ru
+    /// Переводит количество бумаг в количество целых лотов, остаток
+    /// меньше лота отбрасывается.
+    pub fn to_lots(&self, quantity: u32) -> u32 {
+        quantity / self.lot()
+    }
+    /// Convert lots to quantity of securities.
+    ///
+    /// # ru
+    /// Переводит количество лотов в количество бумаг.
+    pub fn to_quantity(&self, lots: u32) -> u32 {
+        lots * self.lot()
+    }
     /// Return the dir path with market data of instrument.
     ///
     /// # ru
@@ -156,6 +286,21 @@ impl Iid {
         p
     }
 }
+/// Дата из кэша инструментов: timestamp в наносекундах (кэш avin-data),
+/// RFC 3339 (кэш avin_connect) или дата "2024-06-17" (архив делистинга).
+fn parse_dt(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(ts) = s.parse::<i64>() {
+        return Some(DateTime::from_timestamp_nanos(ts));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.to_utc());
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
 impl std::fmt::Display for Iid {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
@@ -192,6 +337,7 @@ impl Getter for DataFrame {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixture;
     use std::path::Path;
 
     #[test]
@@ -215,6 +361,65 @@ mod tests {
         assert_eq!(iid.step(), 0.01);
     }
     #[test]
+    fn step_and_lot() {
+        let iid = fixture::iid_with(&[("ticker", "GAZP"), ("step", "0.05")]);
+
+        assert_eq!(iid.round_to_step(123.42), 123.40);
+        assert_eq!(iid.round_to_step(123.43), 123.45);
+        assert!(iid.is_on_step(123.45));
+        assert!(!iid.is_on_step(123.44));
+
+        assert!(iid.validate_order(123.45, 1).is_ok());
+        assert!(iid.validate_order(123.44, 1).is_err());
+        assert!(iid.validate_order(123.45, 0).is_err());
+        assert!(iid.validate_order(-1.0, 1).is_err());
+
+        assert_eq!(iid.to_lots(125), 12);
+        assert_eq!(iid.to_quantity(12), 120);
+    }
+    #[test]
+    fn delisted() {
+        let iid = fixture::iid_with(&[("ticker", "YNDX")]);
+        assert_eq!(iid.delisted(), None);
+        assert!(iid.check_listed().is_ok());
+
+        let fields = [("ticker", "YNDX"), ("delisted", "2024-06-17")];
+        let iid = fixture::iid_with(&fields);
+        assert_eq!(iid.delisted().unwrap(), "2024-06-17");
+        assert!(iid.check_listed().is_err());
+        assert!(iid.validate_order(4000.0, 1).is_err());
+    }
+    #[test]
+    fn trading_period() {
+        let dt = |y, m, d| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        let iid = fixture::iid_with(&[
+            ("ticker", "YNDX"),
+            ("first_1m", "2018-03-07T07:00:00Z"),
+            ("delisted", "2024-06-17"),
+        ]);
+
+        let listed = dt(2018, 3, 7) + chrono::TimeDelta::hours(7);
+        assert_eq!(iid.listed_dt(), Some(listed));
+        assert_eq!(
+            iid.trading_period(dt(2015, 1, 1), dt(2025, 1, 1)),
+            Some((listed, dt(2024, 6, 17)))
+        );
+        assert_eq!(iid.trading_period(dt(2025, 1, 1), dt(2026, 1, 1)), None);
+
+        let ts = dt(2019, 1, 1).timestamp_nanos_opt().unwrap().to_string();
+        let iid = fixture::iid_with(&[("ticker", "YNDX"), ("first_1m", &ts)]);
+        assert_eq!(
+            iid.trading_period(dt(2015, 1, 1), dt(2026, 1, 1)),
+            Some((dt(2019, 1, 1), dt(2026, 1, 1)))
+        );
+    }
+    #[test]
     fn to_string() {
         let mut info = HashMap::new();
         info.insert("exchange".to_string(), "MOEX".to_string());
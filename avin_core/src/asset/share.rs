@@ -449,8 +449,12 @@ impl Share {
     /// Принимает [`TicEvent`], сохраняет новый тик в активе. Используется
     /// тестером и трейдером при получении нового бара из стрима данных.
     /// Не предназначена для прямого использования пользователем.
-    pub fn tic_event(&mut self, _e: TicEvent) {
-        todo!();
+    ///
+    /// Графики тиком не обновляются, они обновляются барами 1М из
+    /// [`Share::bar_event`]. Для баров меньше минуты используйте
+    /// [`crate::BarBuilder`].
+    pub fn tic_event(&mut self, e: TicEvent) {
+        self.tics.push(e.tic);
     }
 
     pub fn clear(&mut self) {
@@ -11,20 +11,35 @@
 /// Брокерский счет.
 ///
 /// Содержит имя счета и id для брокера. Используется при выставлении ордеров.
+///
+/// Так же хранит последнее известное состояние счета: валюту, денежные
+/// средства, заблокированные средства и признак маржинальной торговли.
+/// Состояние обновляется брокером при запросе портфеля, поэтому проверки
+/// рисков могут пользоваться им без обращения к брокеру каждый раз.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Account {
     name: String,
     broker_id: String,
+    currency: String,
+    cash: f64,
+    blocked: f64,
+    margin: bool,
+    ts: i64,
 }
 impl Account {
     /// Create new account.
     ///
     /// # ru
-    /// Конструктор.
+    /// Конструктор. Состояние счета пустое до первого обновления.
     pub fn new(name: &str, broker_id: &str) -> Self {
         Self {
             name: name.to_string(),
             broker_id: broker_id.to_string(),
+            currency: "rub".to_string(),
+            cash: 0.0,
+            blocked: 0.0,
+            margin: false,
+            ts: 0,
         }
     }
 
@@ -42,6 +57,73 @@ impl Account {
     pub fn id(&self) -> &String {
         &self.broker_id
     }
+    /// Return account currency.
+    ///
+    /// # ru
+    /// Возвращает ISO код валюты счета, в которой учитываются средства.
+    pub fn currency(&self) -> &String {
+        &self.currency
+    }
+    /// Return total cash, including blocked funds.
+    ///
+    /// # ru
+    /// Возвращает денежные средства на счете, включая заблокированные.
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+    /// Return blocked funds.
+    ///
+    /// # ru
+    /// Возвращает средства заблокированные под активные заявки.
+    pub fn blocked(&self) -> f64 {
+        self.blocked
+    }
+    /// Return free funds, available for new orders.
+    ///
+    /// # ru
+    /// Возвращает свободные средства, доступные для новых заявок.
+    pub fn free(&self) -> f64 {
+        self.cash - self.blocked
+    }
+    /// Check margin trading is enabled.
+    ///
+    /// # ru
+    /// Проверяет, доступна ли на счете маржинальная торговля.
+    pub fn is_margin(&self) -> bool {
+        self.margin
+    }
+    /// Return timestamp of last state update.
+    ///
+    /// # ru
+    /// Возвращает timestamp последнего обновления состояния счета,
+    /// 0 если состояние еще не обновлялось.
+    pub fn ts(&self) -> i64 {
+        self.ts
+    }
+    /// Update account balance.
+    ///
+    /// # ru
+    /// Обновляет денежные средства счета. Вызывается брокером при
+    /// получении портфеля из запроса или из потока.
+    pub fn set_balance(
+        &mut self,
+        ts: i64,
+        currency: &str,
+        cash: f64,
+        blocked: f64,
+    ) {
+        self.currency = currency.to_string();
+        self.cash = cash;
+        self.blocked = blocked;
+        self.ts = ts;
+    }
+    /// Update margin status.
+    ///
+    /// # ru
+    /// Устанавливает признак маржинальной торговли на счете.
+    pub fn set_margin(&mut self, margin: bool) {
+        self.margin = margin;
+    }
 }
 impl std::fmt::Display for Account {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -58,5 +140,21 @@ mod tests {
         let a = Account::new("Alex", "broker_id=100500");
         assert_eq!(a.name(), "Alex");
         assert_eq!(a.id(), "broker_id=100500");
+        assert_eq!(a.cash(), 0.0);
+        assert_eq!(a.ts(), 0);
+        assert!(!a.is_margin());
+    }
+    #[test]
+    fn balance() {
+        let mut a = Account::new("Alex", "broker_id=100500");
+        a.set_balance(100500, "rub", 10_000.0, 2_500.0);
+        a.set_margin(true);
+
+        assert_eq!(a.currency(), "rub");
+        assert_eq!(a.cash(), 10_000.0);
+        assert_eq!(a.blocked(), 2_500.0);
+        assert_eq!(a.free(), 7_500.0);
+        assert_eq!(a.ts(), 100500);
+        assert!(a.is_margin());
     }
 }
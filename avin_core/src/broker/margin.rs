@@ -0,0 +1,127 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use bitcode::{Decode, Encode};
+
+use avin_utils::AvinError;
+
+use crate::Iid;
+
+/// Margin model of account: shorts, leverage, margin calls.
+///
+/// # ru
+/// Модель маржинальной торговли. Используется виртуальными счетами
+/// тестера (VirtualBroker) и бумажной торговли (PaperBroker): без нее
+/// счет не ограничен - любая позиция открывается, шорт ничего не
+/// стоит. С моделью брокер ведет счет - деньги и позицию:
+/// - ордер, увеличивающий позицию, отклоняется, если капитала не
+///   хватает на начальную маржу новой позиции;
+/// - начальная маржа - стоимость позиции, умноженная на ставку риска
+///   (dlong / dshort инструмента), минимальная - половина начальной;
+/// - если капитал опустился ниже минимальной маржи, брокер отменяет
+///   все активные ордера и закрывает позицию по рынку (margin call);
+/// - за каждую ночь, через которую перенесена позиция с заемными
+///   деньгами (плечо в лонг) или бумагами (шорт), начисляется плата по
+///   годовым ставкам rate и short_rate, она добавляется к комиссии
+///   следующего исполненного ордера - долгие свинг-позиции платят за
+///   каждую ночь, внутридневные не платят ничего;
+/// - по фьючерсам денег взаймы нет: при покупке списывается только
+///   гарантийное обеспечение, а в каждый клиринг (смена дня) позиция
+///   переоценивается и разница цены зачисляется или списывается со
+///   счета как вариационная маржа.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct Margin {
+    /// Ставка риска длинной позиции (dlong), 0.25 - плечо 4.
+    pub long: f64,
+    /// Ставка риска короткой позиции (dshort).
+    pub short: f64,
+    /// Годовая ставка за заемные деньги, 0.2 == 20%.
+    pub rate: f64,
+    /// Годовая ставка за заемные бумаги в шорте.
+    pub short_rate: f64,
+}
+impl Margin {
+    /// Create model, rate of borrowed money and securities is same.
+    ///
+    /// # ru
+    /// Создает модель, ставка за заемные деньги и бумаги одна, ставку
+    /// шорта можно изменить через поле short_rate.
+    pub fn new(long: f64, short: f64, rate: f64) -> Self {
+        Self {
+            long,
+            short,
+            rate,
+            short_rate: rate,
+        }
+    }
+    /// Create model from instrument risk rates dlong / dshort.
+    ///
+    /// # ru
+    /// Создает модель по ставкам риска инструмента, которые брокер
+    /// сохраняет в информации об инструменте ("long", "short").
+    pub fn from_iid(iid: &Iid, rate: f64) -> Result<Self, AvinError> {
+        let parse = |key: &str| -> Result<f64, AvinError> {
+            let value = iid.info().get(key).ok_or_else(|| {
+                AvinError::NotFound(format!("{iid} risk rate {key}"))
+            })?;
+            value.parse().map_err(|_| {
+                AvinError::InvalidValue(format!("{iid} {key}={value}"))
+            })
+        };
+
+        Ok(Self::new(parse("long")?, parse("short")?, rate))
+    }
+    /// Initial margin of position.
+    ///
+    /// # ru
+    /// Начальная маржа позиции position бумаг (< 0 - шорт) по цене.
+    pub fn initial(&self, position: i64, price: f64) -> f64 {
+        let rate = if position >= 0 { self.long } else { self.short };
+
+        position.unsigned_abs() as f64 * price * rate
+    }
+    /// Minimal margin of position, below it position is liquidated.
+    ///
+    /// # ru
+    /// Минимальная маржа, при капитале ниже позиция закрывается.
+    pub fn minimal(&self, position: i64, price: f64) -> f64 {
+        self.initial(position, price) / 2.0
+    }
+}
+impl std::fmt::Display for Margin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "long {} short {} rate {}%",
+            self.long,
+            self.short,
+            self.rate * 100.0
+        )?;
+        if self.short_rate != self.rate {
+            write!(f, " short rate {}%", self.short_rate * 100.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn from_iid() {
+        let iid = fixture::iid_with(&[("long", "0.25"), ("short", "0.3")]);
+
+        let margin = Margin::from_iid(&iid, 0.2).unwrap();
+        assert_eq!(margin, Margin::new(0.25, 0.3, 0.2));
+        assert_eq!(margin.initial(100, 10.0), 250.0);
+        assert_eq!(margin.initial(-100, 10.0), 300.0);
+        assert_eq!(margin.minimal(-100, 10.0), 150.0);
+    }
+}
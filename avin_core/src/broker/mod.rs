@@ -6,5 +6,7 @@
  ****************************************************************************/
 
 mod account;
+mod margin;
 
 pub use account::Account;
+pub use margin::Margin;
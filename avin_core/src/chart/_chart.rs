@@ -8,20 +8,33 @@
 use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
+use polars::frame::DataFrame;
 
-use avin_utils::{AvinError, bisect_left, bisect_right};
+use avin_utils::{self as utils, AvinError, bisect_left, bisect_right};
 
-use crate::{Bar, Iid, Indicator, Manager, TimeFrame};
+use crate::{
+    Bar, BarColumns, BarSlice, Iid, Indicator, Manager, Tic, TimeFrame,
+};
+
+type BarHook = Box<dyn FnMut(&Bar) + Send>;
 
 /// Aggregation of instrument id, timeframe and bars.
 ///
 /// # ru
 /// График - хранит идентификатора инструмента, таймфрейм и бары.
+///
+/// Бары хранятся по колонкам ([`BarColumns`]): ts, open, high, low,
+/// close, volume - отдельные массивы. Методы графика возвращают бары
+/// по значению, собранные из колонок, а срезы [`BarSlice`] дают
+/// доступ к колонкам без копирования, например для расчета
+/// индикаторов ядрами avin_utils.
 pub struct Chart {
     iid: Iid,
     tf: TimeFrame,
-    bars: Vec<Bar>,
+    cols: BarColumns,
     ind: HashMap<String, Indicator>,
+    on_new_bar: Vec<BarHook>,
+    on_update: Vec<BarHook>,
 }
 impl Chart {
     /// Create new chart.
@@ -48,8 +61,10 @@ impl Chart {
         Self {
             iid: iid.clone(),
             tf,
-            bars,
+            cols: BarColumns::from_bars(&bars),
             ind: HashMap::new(),
+            on_new_bar: Vec::new(),
+            on_update: Vec::new(),
         }
     }
     /// Create new chart without bars.
@@ -59,6 +74,21 @@ impl Chart {
     pub fn empty(iid: &Iid, tf: TimeFrame) -> Self {
         Self::new(iid, tf, Vec::new())
     }
+    /// Create chart from tics.
+    ///
+    /// # ru
+    /// Создает график из тиков, тики должны идти по возрастанию времени.
+    /// Время баров выравнивается на начало периода таймфрейма, объем
+    /// бара - в бумагах: лоты * размер лота. Для баров меньше минуты
+    /// используйте [`crate::BarBuilder`].
+    pub fn from_tics(iid: &Iid, tf: TimeFrame, tics: &[Tic]) -> Self {
+        let mut chart = Self::empty(iid, tf);
+        for tic in tics.iter() {
+            chart.adding_tic(tic);
+        }
+
+        chart
+    }
     /// Loading chart with bars from half-open interval [begin, end)
     /// market data must be available in [`CFG.dir.data()`].
     ///
@@ -112,51 +142,50 @@ impl Chart {
     /// Return bars of chart.
     ///
     /// # ru
-    /// Возвращает cсылку на вектор исторических баров в графике.
-    pub fn bars(&self) -> &Vec<Bar> {
-        &self.bars
+    /// Возвращает срез всех баров графика, включая реал-тайм бар.
+    /// Срез ссылается на колонки графика без копирования: колонки
+    /// доступны как `bars.c`, `bars.h`..., отдельные бары - через
+    /// `bars.bar(i)` или `bars.iter()`.
+    pub fn bars(&self) -> BarSlice<'_> {
+        self.cols.view()
     }
     /// Get bar by number.
     ///
     /// # ru
-    /// Возвращает ссылку на бар по номеру или None, если такой отсутствует.
+    /// Возвращает бар по номеру или None, если такой отсутствует.
     ///
     /// Поведение как Pine от TradingView.
     /// Бар с индексом 0 == текущий реалтайм бар, тоже что chart.now().
     /// Бар с индексом 1 == последний исторический бар.
     /// Бар с индексом 2 == предпоследний бар в графике.
     /// И так далее...
-    pub fn bar(&self, n: usize) -> Option<&Bar> {
-        if n == 0 {
-            return self.bars.last();
-        };
-
-        let index = self.bars.len() - n - 1;
-        self.bars.get(index)
+    pub fn bar(&self, n: usize) -> Option<Bar> {
+        let index = self.cols.len().checked_sub(n + 1)?;
+        self.cols.bar(index)
     }
     /// Return fist historical bar of chart.
     ///
     /// # ru
-    /// Возвращает ссылку на первый исторический бар или None,
+    /// Возвращает первый исторический бар или None,
     /// если график не содержит баров.
-    pub fn first(&self) -> Option<&Bar> {
-        self.bars.first()
+    pub fn first(&self) -> Option<Bar> {
+        self.cols.bar(0)
     }
     /// Return last historical bar of chart
     ///
     /// # ru
-    /// Возвращает ссылку на последний исторический бар или None,
+    /// Возвращает последний исторический бар или None,
     /// если график не содержит баров.
-    pub fn last(&self) -> Option<&Bar> {
-        self.bars.get(self.bars.len() - 2)
+    pub fn last(&self) -> Option<Bar> {
+        self.bar(1)
     }
     /// Return real-time bar of chart
     ///
     /// # ru
-    /// Возвращает ссылку на текущий real-time бар или None,
+    /// Возвращает текущий real-time бар или None,
     /// если график не содержит баров.
-    pub fn now(&self) -> Option<&Bar> {
-        self.bars.last()
+    pub fn now(&self) -> Option<Bar> {
+        self.cols.last()
     }
     /// Return last price
     ///
@@ -165,21 +194,53 @@ impl Chart {
     /// или последнего исторического бара. Если график не содержит баров,
     /// возвращает None.
     pub fn last_price(&self) -> Option<f64> {
-        let last_bar = self.bars.last()?;
-        Some(last_bar.c)
+        self.cols.close().last().copied()
     }
     /// Select bars in closed range [from, till].
     ///
     /// # ru
     /// Возвращает срез баров в закрытом интервале заданном
     /// начальным и конечным timestamp [from, till].
-    pub fn select(&self, from: i64, till: i64) -> &[Bar] {
+    ///
+    /// Если интервал целиком вне графика - возвращает пустой срез.
+    pub fn select(&self, from: i64, till: i64) -> BarSlice<'_> {
         assert!(from <= till);
 
-        let f = bisect_right(&self.bars, from, |b| b.ts).unwrap();
-        let t = bisect_left(&self.bars, till, |b| b.ts).unwrap();
+        let ts = self.cols.ts();
+        let Some(f) = bisect_right(ts, from, |t| *t) else {
+            return self.cols.slice(0..0);
+        };
+        let Some(t) = bisect_left(ts, till, |t| *t) else {
+            return self.cols.slice(0..0);
+        };
+
+        self.cols.slice(f..t + 1)
+    }
+    /// Get bar that contains datetime.
+    ///
+    /// # ru
+    /// Возвращает бар, в период которого попадает заданное
+    /// время, или None, если такого бара в графике нет.
+    pub fn bar_at(&self, dt: DateTime<Utc>) -> Option<Bar> {
+        let ts = utils::ts(dt);
+        let index = bisect_left(self.cols.ts(), ts, |t| *t)?;
+        let bar = self.cols.bar(index)?;
+
+        if ts < self.tf.next_ts(bar.ts) {
+            Some(bar)
+        } else {
+            None
+        }
+    }
+    /// Return last n bars of chart, including real-time bar.
+    ///
+    /// # ru
+    /// Возвращает срез последних n баров графика, включая текущий
+    /// реал-тайм бар. Если баров в графике меньше - возвращает все.
+    pub fn last_n(&self, n: usize) -> BarSlice<'_> {
+        let len = self.cols.len();
 
-        &self.bars[f..=t]
+        self.cols.slice(len.saturating_sub(n)..len)
     }
     /// Add new bar
     /// Depending on datetime of 'new_bar' this function do:
@@ -192,14 +253,29 @@ impl Chart {
     /// - обновит текущий реал-тайм бар новым баром;
     /// - сделает текущий реал-тайм бар историческим (last), а новый
     ///   поставит текущим (now);
+    ///
+    /// После обновления индикаторов вызывает подписчиков: on_new_bar
+    /// если исторический бар закрылся, и on_update всегда.
     pub fn add_bar(&mut self, new_bar: Bar) {
-        self.adding_bar(new_bar);
+        let closed = self.adding_bar(new_bar);
+        self.update_ind();
+        self.notify(closed);
+    }
+    /// Add new tic in chart.
+    ///
+    /// # ru
+    /// Добавляет в график тик: обновляет текущий реал-тайм бар, или
+    /// начинает новый, если тик относится к следующему периоду.
+    /// Индикаторы и подписчики обновляются как в [`Chart::add_bar`].
+    pub fn add_tic(&mut self, tic: &Tic) {
+        let closed = self.adding_tic(tic);
         self.update_ind();
+        self.notify(closed);
     }
     /// Get bar with this timestamp.
     ///
     /// # ru
-    /// Возвращает ссылку на бар с заданным timestamp или None,
+    /// Возвращает бар с заданным timestamp или None,
     /// если такой отсутствует.
     ///
     /// Используется в GUI ChartWidget, и поэтому имеет спецефическое
@@ -211,29 +287,57 @@ impl Chart {
     /// Такое поведение имеет Тинькофф терминал, если мышь находится
     /// справа от графика, где нет баров, то отображается информация
     /// по последнему бару в графике.
-    pub fn get_bar_of_ts(&self, ts: i64) -> Option<&Bar> {
+    pub fn get_bar_of_ts(&self, ts: i64) -> Option<Bar> {
         // если вообще баров нет -> None
-        if self.bars.is_empty() {
+        if self.cols.is_empty() {
             return None;
         }
 
         // если первый бар в графике есть
         // и если время меньше чем время первого бара -> None
-        let bar = self.bars.first()?;
+        let bar = self.first()?;
         if ts < bar.ts {
             return None;
         }
 
         // если текущий бар есть
         // и если время больше чем время текущего бара -> текущий бар
-        let bar = self.bars.last()?;
+        let bar = self.now()?;
         if ts > bar.ts {
             return Some(bar);
         }
 
         // Иначе время где-то в пределах имеющихся баров, делаем поиск
-        let index = bisect_left(&self.bars, ts, |b| b.ts).unwrap();
-        self.bars.get(index)
+        let index = bisect_left(self.cols.ts(), ts, |t| *t).unwrap();
+        self.cols.bar(index)
+    }
+
+    /// Export bars and indicators to DataFrame.
+    ///
+    /// # ru
+    /// Возвращает датафрейм с барами графика (ts_nanos, open, high, low,
+    /// close, volume) и колонками всех добавленных на график
+    /// индикаторов. Удобно для передачи данных в avin_analyse или
+    /// во внешние блокноты.
+    pub fn to_df(&self) -> DataFrame {
+        let mut df = self.bars().to_df();
+
+        // порядок индикаторов в HashMap не определен, сортируем по id
+        // чтобы порядок колонок был стабильным
+        let mut ids: Vec<&String> = self.ind.keys().collect();
+        ids.sort();
+        for id in ids {
+            let ind_df = self.ind[id].to_df(self.bars());
+            let columns: Vec<_> = ind_df
+                .get_columns()
+                .iter()
+                .filter(|c| c.name() != "ts_nanos")
+                .cloned()
+                .collect();
+            df.hstack_mut(&columns).unwrap();
+        }
+
+        df
     }
 
     /// Add indicator.
@@ -262,14 +366,40 @@ impl Chart {
         self.ind.get_mut(id)
     }
 
+    /// Subscribe on bar closing.
+    ///
+    /// # ru
+    /// Регистрирует функцию, которая вызывается когда реал-тайм бар
+    /// становится историческим. В функцию передается закрывшийся бар.
+    ///
+    /// Избавляет индикаторы, стратегии и виджеты GUI от
+    /// самостоятельной проверки "изменился ли last_ts".
+    pub fn on_new_bar<F>(&mut self, f: F)
+    where
+        F: FnMut(&Bar) + Send + 'static,
+    {
+        self.on_new_bar.push(Box::new(f));
+    }
+    /// Subscribe on real-time bar update.
+    ///
+    /// # ru
+    /// Регистрирует функцию, которая вызывается при каждом добавлении
+    /// бара в график. В функцию передается текущий реал-тайм бар.
+    pub fn on_update<F>(&mut self, f: F)
+    where
+        F: FnMut(&Bar) + Send + 'static,
+    {
+        self.on_update.push(Box::new(f));
+    }
+
     // private
-    fn adding_bar(&mut self, new_bar: Bar) {
-        let last_bar = self.bars.last_mut();
+    fn adding_bar(&mut self, new_bar: Bar) -> bool {
+        let last_bar = self.cols.last();
 
         // если баров не было - в пустой график добавляем первый бар
         if last_bar.is_none() {
-            self.bars.push(new_bar);
-            return;
+            self.cols.push(new_bar);
+            return false;
         }
 
         // далее ситуации когда в графике есть бары
@@ -277,8 +407,8 @@ impl Chart {
 
         // если время одинаковое - только обновить текущий бар
         if last_bar.ts == new_bar.ts {
-            *last_bar = new_bar;
-            return;
+            self.cols.set_last(new_bar);
+            return false;
         }
 
         // время смены бара
@@ -287,8 +417,8 @@ impl Chart {
         // если время пришедшего нового бара больше текущего последнего
         // и при этом меньше чем время смены бара, - джоинить этот бар
         if new_bar.ts > last_bar.ts && new_bar.ts < next_ts {
-            *last_bar = Bar::join(*last_bar, new_bar);
-            return;
+            self.cols.set_last(Bar::join(last_bar, new_bar));
+            return false;
         }
 
         // если время пришедшего нового бара больше текущего последнего
@@ -297,13 +427,45 @@ impl Chart {
         // минутках после смены дня идет разрыв во времени... и не будет
         // бара 23:50 будет сразу бар 06:59.
         if new_bar.ts > last_bar.ts && new_bar.ts >= next_ts {
-            self.bars.push(new_bar);
+            self.cols.push(new_bar);
+            return true;
+        }
+
+        false
+    }
+    fn adding_tic(&mut self, tic: &Tic) -> bool {
+        let ts = self.tf.prev_ts(tic.ts);
+        let v = tic.lots as u64 * self.iid.lot() as u64;
+        let p = tic.price;
+        let bar = Bar::new(ts, p, p, p, p, v);
+
+        // тик в текущем баре - склеиваем, иначе обычное добавление бара
+        match self.cols.last() {
+            Some(last) if last.ts == ts => {
+                self.cols.set_last(Bar::join(last, bar));
+                false
+            }
+            _ => self.adding_bar(bar),
         }
     }
     #[inline]
     fn update_ind(&mut self) {
+        let bars = self.cols.view();
         for (_id, ind) in self.ind.iter_mut() {
-            ind.update(&self.bars);
+            ind.update(bars);
+        }
+    }
+    fn notify(&mut self, closed: bool) {
+        if closed && let Some(bar) = self.last() {
+            for f in self.on_new_bar.iter_mut() {
+                f(&bar);
+            }
+        }
+
+        if let Some(bar) = self.now() {
+            for f in self.on_update.iter_mut() {
+                f(&bar);
+            }
         }
     }
 }
@@ -320,6 +482,7 @@ mod tests {
     use avin_utils as utils;
 
     use super::*;
+    use crate::fixture::iid;
     use crate::*;
 
     #[test]
@@ -343,7 +506,7 @@ mod tests {
 
         let chart = Chart::empty(&iid, tf);
         assert_eq!(chart.tf, tf);
-        assert_eq!(chart.bars.len(), 0);
+        assert_eq!(chart.bars().len(), 0);
     }
     #[test]
     fn load() {
@@ -407,6 +570,104 @@ mod tests {
         assert_eq!(selected.len(), 3);
     }
     #[test]
+    fn bar_at_and_last_n() {
+        let iid = iid();
+
+        let tf = TimeFrame::H1;
+        let dt = |d, h, m| Utc.with_ymd_and_hms(2023, 8, d, h, m, 0).unwrap();
+        let ts = |d, h| utils::ts(dt(d, h, 0));
+        let bars = vec![
+            Bar::new(ts(1, 10), 1.0, 1.0, 1.0, 1.0, 1),
+            Bar::new(ts(1, 11), 2.0, 2.0, 2.0, 2.0, 1),
+            Bar::new(ts(1, 12), 3.0, 3.0, 3.0, 3.0, 1),
+        ];
+        let chart = Chart::new(&iid, tf, bars);
+
+        let bar = chart.bar_at(dt(1, 11, 30));
+        assert_eq!(bar.unwrap().c, 2.0);
+        let bar = chart.bar_at(dt(1, 9, 30));
+        assert!(bar.is_none());
+        let bar = chart.bar_at(dt(1, 13, 30));
+        assert!(bar.is_none());
+
+        assert_eq!(chart.last_n(2).len(), 2);
+        assert_eq!(chart.last_n(2).bar(0).unwrap().c, 2.0);
+        assert_eq!(chart.last_n(10).len(), 3);
+
+        // out of chart range
+        let from = ts(2, 10);
+        let till = ts(2, 12);
+        assert!(chart.select(from, till).is_empty());
+    }
+    #[test]
+    fn hooks() {
+        use std::sync::{Arc, Mutex};
+
+        let iid = iid();
+
+        let mut chart = Chart::empty(&iid, TimeFrame::M10);
+        let closed = Arc::new(Mutex::new(Vec::new()));
+        let updates = Arc::new(Mutex::new(0));
+        let c = closed.clone();
+        chart.on_new_bar(move |bar| c.lock().unwrap().push(bar.ts));
+        let u = updates.clone();
+        chart.on_update(move |_bar| *u.lock().unwrap() += 1);
+
+        let min = 60 * 1_000_000_000;
+        chart.add_bar(Bar::new(0, 1.0, 1.0, 1.0, 1.0, 1));
+        chart.add_bar(Bar::new(min, 2.0, 2.0, 2.0, 2.0, 1));
+        assert!(closed.lock().unwrap().is_empty());
+
+        chart.add_bar(Bar::new(10 * min, 3.0, 3.0, 3.0, 3.0, 1));
+        assert_eq!(*closed.lock().unwrap(), vec![0]);
+        assert_eq!(*updates.lock().unwrap(), 3);
+    }
+    #[test]
+    fn to_df() {
+        let iid = iid();
+
+        let bars = vec![
+            Bar::new(1, 10.0, 12.0, 9.0, 11.0, 1),
+            Bar::new(2, 11.0, 14.0, 10.0, 13.0, 1),
+            Bar::new(3, 13.0, 13.5, 8.0, 9.0, 1),
+            Bar::new(4, 9.0, 10.0, 7.0, 9.5, 1),
+        ];
+        let mut chart = Chart::new(&iid, TimeFrame::Day, bars);
+
+        let df = chart.to_df();
+        assert_eq!(df.height(), 4);
+        assert_eq!(df.width(), 6);
+
+        ExtremumIndicator::init(&mut chart);
+        let df = chart.to_df();
+        assert_eq!(df.width(), 11);
+        let t1 = df.column("extremum_t1").unwrap().f64().unwrap();
+        assert_eq!(t1.get(1), Some(14.0));
+        assert_eq!(t1.get(0), None);
+    }
+    #[test]
+    fn from_tics() {
+        let iid = iid();
+
+        let dt = |m, s| Utc.with_ymd_and_hms(2025, 1, 3, 10, m, s).unwrap();
+        let ts = |m, s| utils::ts(dt(m, s));
+        let tics = [
+            Tic::new(ts(0, 5), Direction::Buy, 1, 300.0, 3000.0),
+            Tic::new(ts(0, 40), Direction::Sell, 2, 299.0, 5980.0),
+            Tic::new(ts(1, 10), Direction::Buy, 1, 301.0, 3010.0),
+        ];
+        let mut chart = Chart::from_tics(&iid, TimeFrame::M1, &tics[..2]);
+        assert_eq!(chart.bars().len(), 1);
+        assert_eq!(
+            chart.now().unwrap(),
+            Bar::new(ts(0, 0), 300.0, 300.0, 299.0, 299.0, 30)
+        );
+
+        chart.add_tic(&tics[2]);
+        assert_eq!(chart.bars().len(), 2);
+        assert_eq!(chart.now().unwrap().ts, ts(1, 0));
+    }
+    #[test]
     fn add_bar_10m() {
         // 1M
         // Bar: dt=2025-01-03 09:59:00 o=280 h=280 l=280 c=280 v=158150
@@ -442,7 +703,7 @@ mod tests {
         let bars = chart.bars();
         assert_eq!(bars.len(), 3);
         assert_eq!(
-            bars[0],
+            bars.bar(0).unwrap(),
             Bar::new(
                 1735887540000000000_i64,
                 280.0,
@@ -453,7 +714,7 @@ mod tests {
             )
         );
         assert_eq!(
-            bars[1],
+            bars.bar(1).unwrap(),
             Bar::new(
                 1735887600000000000_i64,
                 279.99,
@@ -465,7 +726,7 @@ mod tests {
         );
         // Bar: dt=2025-01-03 10:10:00 o=278.53 h=279.21 l=278.14 c=278.73 v=759910
         assert_eq!(
-            bars[2],
+            bars.bar(2).unwrap(),
             Bar::new(
                 1735888200000000000_i64,
                 278.53,
@@ -511,7 +772,7 @@ mod tests {
         let bars = chart.bars();
         assert_eq!(bars.len(), 2);
         assert_eq!(
-            bars[0],
+            bars.bar(0).unwrap(),
             Bar::new(
                 1735887540000000000_i64,
                 280.0,
@@ -522,7 +783,7 @@ mod tests {
             )
         );
         assert_eq!(
-            bars[1],
+            bars.bar(1).unwrap(),
             Bar::new(
                 1735887600000000000_i64,
                 279.99,
@@ -567,7 +828,7 @@ mod tests {
         let bars = chart.bars();
         assert_eq!(bars.len(), 1);
         assert_eq!(
-            bars[0],
+            bars.bar(0).unwrap(),
             Bar::new(
                 1735887540000000000_i64,
                 280.0,
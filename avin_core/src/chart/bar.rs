@@ -6,7 +6,7 @@
  ****************************************************************************/
 
 use chrono::prelude::*;
-use polars::prelude::DataFrame;
+use polars::prelude::{DataFrame, NamedFrom, Series};
 
 use crate::Range;
 use avin_utils as utils;
@@ -162,6 +162,41 @@ impl Bar {
 
         Ok(bars)
     }
+    /// Create DataFrame from bars.
+    ///
+    /// # ru
+    /// Создает датафрейм из вектора баров, обратное преобразование к
+    /// [`Bar::from_df`]. Колонки: ts_nanos, open, high, low, close, volume.
+    ///
+    /// ## Examples
+    /// ```
+    /// use avin_core::Bar;
+    ///
+    /// let b1 = Bar::new(123000000, 320.5, 321.2, 320.1, 320.8, 10);
+    /// let b2 = Bar::new(124000000, 320.8, 322.2, 321.1, 321.8, 11);
+    /// let df = Bar::to_df(&[b1, b2]);
+    ///
+    /// assert_eq!(df.height(), 2);
+    /// assert_eq!(Bar::from_df(&df).unwrap(), vec![b1, b2]);
+    /// ```
+    pub fn to_df(bars: &[Bar]) -> DataFrame {
+        let ts: Vec<i64> = bars.iter().map(|b| b.ts).collect();
+        let o: Vec<f64> = bars.iter().map(|b| b.o).collect();
+        let h: Vec<f64> = bars.iter().map(|b| b.h).collect();
+        let l: Vec<f64> = bars.iter().map(|b| b.l).collect();
+        let c: Vec<f64> = bars.iter().map(|b| b.c).collect();
+        let v: Vec<i64> = bars.iter().map(|b| b.v as i64).collect();
+
+        DataFrame::new(vec![
+            Series::new("ts_nanos".into(), ts).into(),
+            Series::new("open".into(), o).into(),
+            Series::new("high".into(), h).into(),
+            Series::new("low".into(), l).into(),
+            Series::new("close".into(), c).into(),
+            Series::new("volume".into(), v).into(),
+        ])
+        .unwrap()
+    }
     /// Join self and other bar, used when converting timeframes.
     ///
     /// # ru
@@ -193,6 +228,35 @@ impl Bar {
         }
     }
 
+    /// Join sequence of bars into one bar.
+    ///
+    /// # ru
+    /// Объединяет последовательность баров в один: время и открытие
+    /// первого бара, закрытие последнего, максимум/минимум и сумма объема
+    /// по всем барам. Бары должны идти по возрастанию времени.
+    ///
+    /// ## Examples
+    /// ```
+    /// use avin_core::Bar;
+    ///
+    /// let b1 = Bar::new(123000000, 320.5, 321.2, 320.1, 320.8, 10);
+    /// let b2 = Bar::new(124000000, 320.8, 322.2, 321.1, 321.8, 11);
+    /// let b3 = Bar::new(125000000, 321.8, 321.9, 319.1, 319.5, 12);
+    /// let joined = Bar::join_all(&[b1, b2, b3]);
+    /// assert_eq!(joined.ts, b1.ts);
+    /// assert_eq!(joined.o, b1.o);
+    /// assert_eq!(joined.h, b2.h);
+    /// assert_eq!(joined.l, b3.l);
+    /// assert_eq!(joined.c, b3.c);
+    /// assert_eq!(joined.v, 33);
+    /// ```
+    pub fn join_all(bars: &[Bar]) -> Bar {
+        assert!(!bars.is_empty());
+
+        let first = bars[0];
+        bars[1..].iter().fold(first, |acc, b| Bar::join(acc, *b))
+    }
+
     /// Return DateTime UTC of bar.
     ///
     /// # ru
@@ -0,0 +1,132 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::TimeDelta;
+
+use crate::{Bar, Iid, Tic};
+
+/// Builder of bars with arbitrary period from tics.
+///
+/// # ru
+/// Построитель баров из тиков с произвольным периодом, в том числе
+/// меньше минуты (5 секунд, 15 секунд...), для которых нет
+/// [`crate::TimeFrame`].
+///
+/// Время бара выравнивается на начало его периода, объем бара - в
+/// бумагах, как у баров из рыночных данных: лоты * размер лота.
+#[derive(Debug, Clone)]
+pub struct BarBuilder {
+    period: i64,
+    lot: u64,
+    now: Option<Bar>,
+}
+impl BarBuilder {
+    /// Create new builder.
+    ///
+    /// # ru
+    /// Конструктор. Период должен быть больше нуля.
+    pub fn new(iid: &Iid, period: TimeDelta) -> Self {
+        let period = period.num_nanoseconds().unwrap();
+        assert!(period > 0);
+
+        Self {
+            period,
+            lot: iid.lot() as u64,
+            now: None,
+        }
+    }
+    /// Build bars from historical tics.
+    ///
+    /// # ru
+    /// Строит бары из исторических тиков, тики должны идти по
+    /// возрастанию времени. Последний, возможно не завершенный, бар
+    /// тоже попадает в результат.
+    pub fn build(iid: &Iid, period: TimeDelta, tics: &[Tic]) -> Vec<Bar> {
+        let mut builder = Self::new(iid, period);
+        let mut bars: Vec<Bar> =
+            tics.iter().filter_map(|tic| builder.add_tic(tic)).collect();
+        if let Some(bar) = builder.now {
+            bars.push(bar);
+        }
+
+        bars
+    }
+
+    /// Return period of bars.
+    ///
+    /// # ru
+    /// Возвращает период строящихся баров.
+    pub fn period(&self) -> TimeDelta {
+        TimeDelta::nanoseconds(self.period)
+    }
+    /// Return current real-time bar.
+    ///
+    /// # ru
+    /// Возвращает текущий не завершенный бар, или None если тиков
+    /// еще не было.
+    pub fn now(&self) -> Option<&Bar> {
+        self.now.as_ref()
+    }
+    /// Add tic, return closed bar if period changed.
+    ///
+    /// # ru
+    /// Добавляет тик в текущий бар. Если тик относится к следующему
+    /// периоду - текущий бар закрывается и возвращается, а тик
+    /// начинает новый бар.
+    pub fn add_tic(&mut self, tic: &Tic) -> Option<Bar> {
+        let ts = tic.ts - tic.ts.rem_euclid(self.period);
+        let v = tic.lots as u64 * self.lot;
+        let bar = Bar::new(ts, tic.price, tic.price, tic.price, tic.price, v);
+
+        match self.now {
+            Some(now) if now.ts == ts => {
+                self.now = Some(Bar::join(now, bar));
+                None
+            }
+            Some(now) => {
+                self.now = Some(bar);
+                Some(now)
+            }
+            None => {
+                self.now = Some(bar);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Direction;
+    use crate::fixture::iid;
+
+    #[test]
+    fn build() {
+        let iid = iid();
+
+        let sec = 1_000_000_000;
+        let tics = vec![
+            Tic::new(sec, Direction::Buy, 1, 300.0, 3000.0),
+            Tic::new(2 * sec, Direction::Buy, 2, 301.0, 6020.0),
+            Tic::new(3 * sec, Direction::Sell, 1, 299.0, 2990.0),
+            Tic::new(6 * sec, Direction::Sell, 1, 298.0, 2980.0),
+        ];
+
+        let mut builder = BarBuilder::new(&iid, TimeDelta::seconds(5));
+        assert_eq!(builder.add_tic(&tics[0]), None);
+        assert_eq!(builder.add_tic(&tics[1]), None);
+        assert_eq!(builder.add_tic(&tics[2]), None);
+        let closed = builder.add_tic(&tics[3]).unwrap();
+        assert_eq!(closed, Bar::new(0, 300.0, 301.0, 299.0, 299.0, 40));
+        assert_eq!(builder.now().unwrap().ts, 5 * sec);
+
+        let bars = BarBuilder::build(&iid, TimeDelta::seconds(5), &tics);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0], closed);
+    }
+}
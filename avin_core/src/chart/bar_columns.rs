@@ -0,0 +1,352 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::ops::Range;
+
+use polars::prelude::{DataFrame, NamedFrom, Series};
+
+use crate::Bar;
+
+/// Columnar storage of bars.
+///
+/// # ru
+/// Колоночное хранение баров (structure of arrays): отдельные
+/// непрерывные массивы ts, open, high, low, close, volume.
+///
+/// Это внутреннее хранилище баров графика: [`crate::Chart`] не держит
+/// вектор баров, а отдает срезы колонок [`BarSlice`]. Колонки удобны
+/// для быстрых расчетов индикаторов на многолетних минутных графиках:
+/// ядра из avin_utils (sma, ema, true_range...) работают прямо со
+/// срезами колонок, без копирования. Отдельный бар собирается из
+/// колонок по запросу, см. [`BarColumns::bar`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BarColumns {
+    ts: Vec<i64>,
+    o: Vec<f64>,
+    h: Vec<f64>,
+    l: Vec<f64>,
+    c: Vec<f64>,
+    v: Vec<u64>,
+}
+impl BarColumns {
+    /// Create empty columns.
+    ///
+    /// # ru
+    /// Конструктор, пустые колонки.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create columns from bars.
+    ///
+    /// # ru
+    /// Создает колонки из вектора баров.
+    pub fn from_bars(bars: &[Bar]) -> Self {
+        let mut cols = Self {
+            ts: Vec::with_capacity(bars.len()),
+            o: Vec::with_capacity(bars.len()),
+            h: Vec::with_capacity(bars.len()),
+            l: Vec::with_capacity(bars.len()),
+            c: Vec::with_capacity(bars.len()),
+            v: Vec::with_capacity(bars.len()),
+        };
+        for bar in bars.iter() {
+            cols.push(*bar);
+        }
+
+        cols
+    }
+
+    /// Return count of bars.
+    ///
+    /// # ru
+    /// Возвращает количество баров.
+    pub fn len(&self) -> usize {
+        self.ts.len()
+    }
+    /// Check columns is empty.
+    ///
+    /// # ru
+    /// Проверяет, что колонки пустые.
+    pub fn is_empty(&self) -> bool {
+        self.ts.is_empty()
+    }
+    pub fn ts(&self) -> &[i64] {
+        &self.ts
+    }
+    pub fn open(&self) -> &[f64] {
+        &self.o
+    }
+    pub fn high(&self) -> &[f64] {
+        &self.h
+    }
+    pub fn low(&self) -> &[f64] {
+        &self.l
+    }
+    pub fn close(&self) -> &[f64] {
+        &self.c
+    }
+    pub fn volume(&self) -> &[u64] {
+        &self.v
+    }
+    /// Return bar by index.
+    ///
+    /// # ru
+    /// Возвращает бар по индексу, собранный из колонок, или None если
+    /// индекс вне диапазона.
+    pub fn bar(&self, i: usize) -> Option<Bar> {
+        if i >= self.len() {
+            return None;
+        }
+
+        Some(Bar::new(
+            self.ts[i], self.o[i], self.h[i], self.l[i], self.c[i], self.v[i],
+        ))
+    }
+    /// Return last bar.
+    ///
+    /// # ru
+    /// Возвращает последний бар или None, если колонки пустые.
+    pub fn last(&self) -> Option<Bar> {
+        self.bar(self.len().checked_sub(1)?)
+    }
+    /// Return zero-copy view of all columns.
+    ///
+    /// # ru
+    /// Возвращает срез всех колонок, без копирования.
+    pub fn view(&self) -> BarSlice<'_> {
+        self.slice(0..self.len())
+    }
+    /// Return zero-copy slice of columns.
+    ///
+    /// # ru
+    /// Возвращает срез колонок по диапазону индексов, без копирования.
+    pub fn slice(&self, range: Range<usize>) -> BarSlice<'_> {
+        BarSlice {
+            ts: &self.ts[range.clone()],
+            o: &self.o[range.clone()],
+            h: &self.h[range.clone()],
+            l: &self.l[range.clone()],
+            c: &self.c[range.clone()],
+            v: &self.v[range],
+        }
+    }
+
+    /// Append bar.
+    ///
+    /// # ru
+    /// Добавляет бар в конец колонок.
+    pub fn push(&mut self, bar: Bar) {
+        self.ts.push(bar.ts);
+        self.o.push(bar.o);
+        self.h.push(bar.h);
+        self.l.push(bar.l);
+        self.c.push(bar.c);
+        self.v.push(bar.v);
+    }
+    /// Replace last bar.
+    ///
+    /// # ru
+    /// Заменяет последний бар, используется при обновлении реал-тайм
+    /// бара. Если колонки пустые - добавляет бар.
+    pub fn set_last(&mut self, bar: Bar) {
+        if self.is_empty() {
+            self.push(bar);
+            return;
+        }
+
+        let i = self.len() - 1;
+        self.ts[i] = bar.ts;
+        self.o[i] = bar.o;
+        self.h[i] = bar.h;
+        self.l[i] = bar.l;
+        self.c[i] = bar.c;
+        self.v[i] = bar.v;
+    }
+}
+
+/// Zero-copy slice of bar columns.
+///
+/// # ru
+/// Срез колонок баров, ссылается на данные [`BarColumns`] без
+/// копирования.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarSlice<'a> {
+    pub ts: &'a [i64],
+    pub o: &'a [f64],
+    pub h: &'a [f64],
+    pub l: &'a [f64],
+    pub c: &'a [f64],
+    pub v: &'a [u64],
+}
+impl<'a> BarSlice<'a> {
+    /// Return count of bars.
+    ///
+    /// # ru
+    /// Возвращает количество баров в срезе.
+    pub fn len(&self) -> usize {
+        self.ts.len()
+    }
+    /// Check slice is empty.
+    ///
+    /// # ru
+    /// Проверяет, что срез пустой.
+    pub fn is_empty(&self) -> bool {
+        self.ts.is_empty()
+    }
+    /// Return bar by index.
+    ///
+    /// # ru
+    /// Возвращает бар по индексу, собранный из колонок, или None если
+    /// индекс вне диапазона.
+    pub fn bar(&self, i: usize) -> Option<Bar> {
+        if i >= self.len() {
+            return None;
+        }
+
+        Some(Bar::new(
+            self.ts[i], self.o[i], self.h[i], self.l[i], self.c[i], self.v[i],
+        ))
+    }
+    /// Return first bar.
+    ///
+    /// # ru
+    /// Возвращает первый бар среза или None, если срез пустой.
+    pub fn first(&self) -> Option<Bar> {
+        self.bar(0)
+    }
+    /// Return last bar.
+    ///
+    /// # ru
+    /// Возвращает последний бар среза или None, если срез пустой.
+    pub fn last(&self) -> Option<Bar> {
+        self.bar(self.len().checked_sub(1)?)
+    }
+    /// Return sub slice by range of indexes.
+    ///
+    /// # ru
+    /// Возвращает часть среза по диапазону индексов, без копирования.
+    pub fn slice(&self, range: Range<usize>) -> BarSlice<'a> {
+        BarSlice {
+            ts: &self.ts[range.clone()],
+            o: &self.o[range.clone()],
+            h: &self.h[range.clone()],
+            l: &self.l[range.clone()],
+            c: &self.c[range.clone()],
+            v: &self.v[range],
+        }
+    }
+    /// Return iterator over bars.
+    ///
+    /// # ru
+    /// Возвращает итератор по барам среза, бары собираются из колонок
+    /// на лету.
+    pub fn iter(&self) -> BarIter<'a> {
+        BarIter {
+            slice: *self,
+            range: 0..self.len(),
+        }
+    }
+    /// Copy bars to vector.
+    ///
+    /// # ru
+    /// Копирует бары среза в вектор, для кода которому нужен
+    /// именно `&[Bar]`.
+    pub fn to_vec(&self) -> Vec<Bar> {
+        self.iter().collect()
+    }
+    /// Export bars to DataFrame.
+    ///
+    /// # ru
+    /// Возвращает датафрейм с колонками ts_nanos, open, high, low,
+    /// close, volume, как [`Bar::to_df`], но без промежуточного вектора
+    /// баров.
+    pub fn to_df(&self) -> DataFrame {
+        let v: Vec<i64> = self.v.iter().map(|v| *v as i64).collect();
+
+        DataFrame::new(vec![
+            Series::new("ts_nanos".into(), self.ts).into(),
+            Series::new("open".into(), self.o).into(),
+            Series::new("high".into(), self.h).into(),
+            Series::new("low".into(), self.l).into(),
+            Series::new("close".into(), self.c).into(),
+            Series::new("volume".into(), v).into(),
+        ])
+        .unwrap()
+    }
+}
+impl<'a> IntoIterator for BarSlice<'a> {
+    type Item = Bar;
+    type IntoIter = BarIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over bars of [`BarSlice`].
+///
+/// # ru
+/// Итератор по барам среза колонок, возвращает бары по значению.
+#[derive(Debug, Clone)]
+pub struct BarIter<'a> {
+    slice: BarSlice<'a>,
+    range: Range<usize>,
+}
+impl Iterator for BarIter<'_> {
+    type Item = Bar;
+
+    fn next(&mut self) -> Option<Bar> {
+        let i = self.range.next()?;
+        self.slice.bar(i)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+impl DoubleEndedIterator for BarIter<'_> {
+    fn next_back(&mut self) -> Option<Bar> {
+        let i = self.range.next_back()?;
+        self.slice.bar(i)
+    }
+}
+impl ExactSizeIterator for BarIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns() {
+        let bars = vec![
+            Bar::new(1, 10.0, 12.0, 9.0, 11.0, 100),
+            Bar::new(2, 11.0, 13.0, 10.0, 12.0, 200),
+        ];
+        let mut cols = BarColumns::from_bars(&bars);
+        assert_eq!(cols.len(), 2);
+        assert_eq!(cols.close(), &[11.0, 12.0]);
+        assert_eq!(cols.bar(1), Some(bars[1]));
+        assert_eq!(cols.bar(2), None);
+
+        cols.push(Bar::new(3, 12.0, 14.0, 11.0, 13.0, 300));
+        cols.set_last(Bar::new(3, 12.0, 15.0, 11.0, 14.0, 400));
+        assert_eq!(cols.high(), &[12.0, 13.0, 15.0]);
+
+        let slice = cols.slice(1..3);
+        assert_eq!(slice.len(), 2);
+        assert_eq!(slice.ts, &[2, 3]);
+        assert_eq!(slice.bar(1).unwrap().v, 400);
+        assert_eq!(slice.slice(1..2).first(), slice.last());
+
+        // бары среза по значению, в обе стороны
+        let ts: Vec<i64> = cols.view().iter().map(|b| b.ts).collect();
+        assert_eq!(ts, vec![1, 2, 3]);
+        assert_eq!(cols.view().iter().rev().nth(1), Some(bars[1]));
+        assert_eq!(cols.view().to_vec()[..2], bars[..]);
+        assert_eq!(cols.view().to_df(), Bar::to_df(&cols.view().to_vec()));
+        assert_eq!(BarColumns::new().last(), None);
+    }
+}
@@ -7,10 +7,14 @@
 
 mod _chart;
 mod bar;
+mod bar_builder;
+mod bar_columns;
 mod range;
 mod timeframe;
 
 pub use _chart::Chart;
 pub use bar::Bar;
+pub use bar_builder::BarBuilder;
+pub use bar_columns::{BarColumns, BarIter, BarSlice};
 pub use range::Range;
 pub use timeframe::TimeFrame;
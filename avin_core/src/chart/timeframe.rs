@@ -8,7 +8,7 @@
 use chrono::{DateTime, Datelike, TimeDelta, Timelike};
 use time_unit::TimeUnit;
 
-use crate::MarketData;
+use crate::{Bar, MarketData};
 
 /// List for selecting the timeframe.
 ///
@@ -100,7 +100,7 @@ impl TimeFrame {
                 let dt = dt.with_minute(0).unwrap();
                 let dt = dt.with_hour(0).unwrap();
                 let ts = dt.timestamp_nanos_opt().unwrap();
-                let need_days = 6 - dt.weekday() as i64;
+                let need_days = 7 - dt.weekday() as i64;
                 ts + need_days * TimeUnit::Days.get_unit_nanoseconds() as i64
             }
             TimeFrame::Month => {
@@ -141,8 +141,39 @@ impl TimeFrame {
                 let dt = dt.with_minute(0).unwrap().with_hour(0).unwrap();
                 dt.timestamp_nanos_opt().unwrap()
             }
-            other => todo!("TimeFrame::prev_ts({}, {})", ts, other),
+            TimeFrame::Week => {
+                let dt = dt.with_minute(0).unwrap().with_hour(0).unwrap();
+                let past_days = dt.weekday().num_days_from_monday() as i64;
+                let ts = dt.timestamp_nanos_opt().unwrap();
+                ts - past_days * TimeUnit::Days.get_unit_nanoseconds() as i64
+            }
+            TimeFrame::Month => {
+                let dt = dt.with_minute(0).unwrap().with_hour(0).unwrap();
+                let dt = dt.with_day(1).unwrap();
+                dt.timestamp_nanos_opt().unwrap()
+            }
+        }
+    }
+    /// Aggregate bars to this timeframe.
+    ///
+    /// # ru
+    /// Преобразует бары младшего таймфрейма в бары данного таймфрейма.
+    /// Время каждого нового бара выравнивается на начало его периода
+    /// [`TimeFrame::prev_ts`], OHLCV рассчитывается как в [`Bar::join`].
+    /// Бары должны идти по возрастанию времени.
+    pub fn aggregate(&self, bars: &[Bar]) -> Vec<Bar> {
+        let mut out: Vec<Bar> = Vec::new();
+
+        for bar in bars.iter() {
+            let ts = self.prev_ts(bar.ts);
+
+            match out.last_mut() {
+                Some(last) if last.ts == ts => *last = Bar::join(*last, *bar),
+                _ => out.push(Bar { ts, ..*bar }),
+            }
         }
+
+        out
     }
     /// Return TimeDelta for this timeframe.
     ///
@@ -341,5 +372,47 @@ mod tests {
             prev_dt,
             Utc.with_ymd_and_hms(2023, 8, 1, 0, 0, 0).unwrap()
         );
+
+        let prev_ts = TimeFrame::Week.prev_ts(ts);
+        let prev_dt = DateTime::from_timestamp_nanos(prev_ts);
+        assert_eq!(
+            prev_dt,
+            Utc.with_ymd_and_hms(2023, 7, 31, 0, 0, 0).unwrap()
+        );
+        let next_ts = TimeFrame::Week.next_ts(ts);
+        let next_dt = DateTime::from_timestamp_nanos(next_ts);
+        assert_eq!(
+            next_dt,
+            Utc.with_ymd_and_hms(2023, 8, 7, 0, 0, 0).unwrap()
+        );
+
+        let prev_ts = TimeFrame::Month.prev_ts(ts);
+        let prev_dt = DateTime::from_timestamp_nanos(prev_ts);
+        assert_eq!(
+            prev_dt,
+            Utc.with_ymd_and_hms(2023, 8, 1, 0, 0, 0).unwrap()
+        );
+    }
+    #[test]
+    fn aggregate() {
+        let ts = |h, m| {
+            let dt = Utc.with_ymd_and_hms(2023, 8, 1, h, m, 0).unwrap();
+            dt.timestamp_nanos_opt().unwrap()
+        };
+        let bars = vec![
+            Bar::new(ts(10, 0), 10.0, 11.0, 9.0, 10.5, 1),
+            Bar::new(ts(10, 30), 10.5, 12.0, 10.0, 11.5, 2),
+            Bar::new(ts(10, 50), 11.5, 11.5, 8.0, 9.0, 3),
+            Bar::new(ts(11, 10), 9.0, 9.5, 8.5, 9.2, 4),
+        ];
+
+        let h1 = TimeFrame::H1.aggregate(&bars);
+        assert_eq!(h1.len(), 2);
+        assert_eq!(h1[0], Bar::new(ts(10, 0), 10.0, 12.0, 8.0, 9.0, 6));
+        assert_eq!(h1[1], Bar::new(ts(11, 0), 9.0, 9.5, 8.5, 9.2, 4));
+
+        let day = TimeFrame::Day.aggregate(&bars);
+        assert_eq!(day.len(), 1);
+        assert_eq!(day[0], Bar::new(ts(0, 0), 10.0, 12.0, 8.0, 9.2, 10));
     }
 }
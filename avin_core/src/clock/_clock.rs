@@ -0,0 +1,99 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use chrono::{DateTime, Utc};
+
+/// Source of current time for strategies and brokers.
+///
+/// # ru
+/// Источник текущего времени. Стратегии, расписания и истечение
+/// ордеров по времени берут "сейчас" из часов, а не из
+/// `Utc::now()`: в боевом режиме это реальное время [`RealClock`], в
+/// тестере и симуляторе - время рынка [`SimClock`]. Так один и тот же
+/// код стратегии ведет себя одинаково в бэктесте, воспроизведении и
+/// вживую. Часы передаются стратегии через
+/// `avin_strategy::Strategy::set_clock`.
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Current time, ts nanos.
+    ///
+    /// # ru
+    /// Текущее время, ts nanos.
+    fn ts(&self) -> i64;
+    /// Current time.
+    ///
+    /// # ru
+    /// Текущее время.
+    fn now(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(self.ts())
+    }
+}
+
+/// Real time clock.
+///
+/// # ru
+/// Реальное время, для боевого режима и бумажной торговли.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealClock;
+impl Clock for RealClock {
+    fn ts(&self) -> i64 {
+        Utc::now().timestamp_nanos_opt().unwrap()
+    }
+}
+
+/// Simulated clock, time is set by data stream.
+///
+/// # ru
+/// Время рынка, его двигает поток данных: тестер ставит время на
+/// каждом событии - закрытие 1М бара, время тика. Клоны часов общие,
+/// стратегия видит время, выставленное тестером.
+#[derive(Debug, Clone, Default)]
+pub struct SimClock {
+    ts: Arc<AtomicI64>,
+}
+impl SimClock {
+    pub fn new(ts: i64) -> Self {
+        Self {
+            ts: Arc::new(AtomicI64::new(ts)),
+        }
+    }
+    pub fn set(&self, ts: i64) {
+        self.ts.store(ts, Ordering::Relaxed);
+    }
+}
+impl Clock for SimClock {
+    fn ts(&self) -> i64 {
+        self.ts.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn sim_clock() {
+        let dt = Utc.with_ymd_and_hms(2025, 1, 10, 10, 0, 0).unwrap();
+        let clock = SimClock::new(dt.timestamp_nanos_opt().unwrap());
+        let shared: Arc<dyn Clock> = Arc::new(clock.clone());
+        assert_eq!(shared.now(), dt);
+
+        let later = dt + chrono::TimeDelta::minutes(1);
+        clock.set(later.timestamp_nanos_opt().unwrap());
+        assert_eq!(shared.now(), later);
+    }
+    #[test]
+    fn real_clock() {
+        let before = Utc::now();
+        let now = RealClock.now();
+        assert!(now >= before);
+    }
+}
@@ -0,0 +1,10 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+mod _clock;
+
+pub use _clock::{Clock, RealClock, SimClock};
@@ -0,0 +1,305 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::{Path, PathBuf};
+
+use polars::prelude::*;
+use serde::Deserialize;
+
+use avin_utils::AvinError;
+
+use crate::Iid;
+
+/// Kind of corporate action.
+///
+/// # ru
+/// Тип корпоративного события.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    /// Split: value - new shares for one old share (after / before).
+    ///
+    /// # ru
+    /// Сплит: value - сколько новых акций за одну старую, для
+    /// обратного сплита меньше 1.
+    Split,
+    /// Dividend: value - dividend per share.
+    ///
+    /// # ru
+    /// Дивиденд: value - размер дивиденда на одну акцию.
+    Dividend,
+}
+
+/// Corporate action event of instrument.
+///
+/// # ru
+/// Корпоративное событие инструмента. ts_nanos - начало первого дня
+/// торгов после события (для дивиденда - дата отсечки без
+/// дивиденда), бары до этого времени корректируются. close -
+/// необязательная цена закрытия перед событием, для расчета
+/// коэффициента дивиденда.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CorporateAction {
+    pub ts_nanos: i64,
+    pub kind: ActionKind,
+    pub value: f64,
+    #[serde(default)]
+    pub close: Option<f64>,
+}
+
+/// Corporate actions of instrument, used for adjust bars.
+///
+/// # ru
+/// Список корпоративных событий инструмента. Хранится в файле
+/// `<data>/<exchange>/<category>/<ticker>/ACTIONS.json`, который
+/// записывает `avin-data actions`, его можно править и вручную.
+///
+/// Корректировка обратная: последние цены остаются как есть, а бары
+/// до события пересчитываются. Сплит делит цены и умножает объем на
+/// коэффициент сплита. Дивиденд умножает цены на 1 - D / C, где C -
+/// цена закрытия перед событием.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CorporateActions {
+    actions: Vec<CorporateAction>,
+}
+impl CorporateActions {
+    /// Create from actions, actions will be sorted by time.
+    ///
+    /// # ru
+    /// Создает список событий, события сортируются по времени.
+    pub fn new(mut actions: Vec<CorporateAction>) -> Self {
+        actions.sort_by_key(|a| a.ts_nanos);
+
+        Self { actions }
+    }
+    /// Load actions of instrument, empty if file not exists.
+    ///
+    /// # ru
+    /// Загружает события инструмента. Если файла нет - пустой список.
+    pub fn load(iid: &Iid) -> Result<Self, AvinError> {
+        let path = Self::path(iid);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::load_from(&path)
+    }
+    /// Load actions from json file.
+    ///
+    /// # ru
+    /// Загружает события из json файла.
+    pub fn load_from(path: &Path) -> Result<Self, AvinError> {
+        let text = std::fs::read_to_string(path).map_err(|_| {
+            AvinError::NotFound(format!("{}", path.display()))
+        })?;
+
+        Self::from_json(&text)
+    }
+    /// Parse actions from json string.
+    ///
+    /// # ru
+    /// Разбирает события из json строки.
+    pub fn from_json(text: &str) -> Result<Self, AvinError> {
+        let actions: Vec<CorporateAction> = serde_json::from_str(text)
+            .map_err(|e| AvinError::InvalidValue(e.to_string()))?;
+
+        Ok(Self::new(actions))
+    }
+    /// Return path of actions file.
+    ///
+    /// # ru
+    /// Возвращает путь к файлу событий инструмента.
+    pub fn path(iid: &Iid) -> PathBuf {
+        let mut path = iid.path();
+        path.push("ACTIONS.json");
+
+        path
+    }
+
+    /// Return actions.
+    ///
+    /// # ru
+    /// Возвращает события, отсортированные по времени.
+    pub fn actions(&self) -> &Vec<CorporateAction> {
+        &self.actions
+    }
+    /// Check is empty.
+    ///
+    /// # ru
+    /// Проверяет, есть ли события.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+    /// Back-adjust bars dataframe for splits and optionally dividends.
+    ///
+    /// # ru
+    /// Корректирует датафрейм баров на сплиты, и если dividends -
+    /// на дивиденды. Колонка value (оборот в деньгах) не меняется.
+    pub fn adjust(
+        &self,
+        df: DataFrame,
+        dividends: bool,
+    ) -> Result<DataFrame, AvinError> {
+        if self.actions.is_empty() || df.is_empty() {
+            return Ok(df);
+        }
+
+        let err = |e: PolarsError| AvinError::InvalidValue(e.to_string());
+        let ts: Vec<i64> = df
+            .column("ts_nanos")
+            .map_err(err)?
+            .i64()
+            .map_err(err)?
+            .into_no_null_iter()
+            .collect();
+        let close: Vec<f64> = df
+            .column("close")
+            .map_err(err)?
+            .f64()
+            .map_err(err)?
+            .into_no_null_iter()
+            .collect();
+
+        let mut price_k = vec![1.0; ts.len()];
+        let mut volume_k = vec![1.0; ts.len()];
+        for action in self.actions.iter() {
+            // бары до события, ts отсортированы
+            let n = ts.partition_point(|t| *t < action.ts_nanos);
+            if n == 0 {
+                continue;
+            }
+
+            let (pk, vk) = match action.kind {
+                ActionKind::Split => (1.0 / action.value, action.value),
+                ActionKind::Dividend => {
+                    if !dividends {
+                        continue;
+                    }
+                    let c = action.close.unwrap_or(close[n - 1]);
+                    (1.0 - action.value / c, 1.0)
+                }
+            };
+            price_k.iter_mut().take(n).for_each(|k| *k *= pk);
+            volume_k.iter_mut().take(n).for_each(|k| *k *= vk);
+        }
+
+        let mut df = df;
+        for name in ["open", "high", "low", "close"] {
+            let col: Vec<f64> = df
+                .column(name)
+                .map_err(err)?
+                .f64()
+                .map_err(err)?
+                .into_no_null_iter()
+                .zip(price_k.iter())
+                .map(|(x, k)| x * k)
+                .collect();
+            df.with_column(Series::new(name.into(), col)).map_err(err)?;
+        }
+        let volume: Vec<i64> = df
+            .column("volume")
+            .map_err(err)?
+            .i64()
+            .map_err(err)?
+            .into_no_null_iter()
+            .zip(volume_k.iter())
+            .map(|(x, k)| (x as f64 * k).round() as i64)
+            .collect();
+        df.with_column(Series::new("volume".into(), volume))
+            .map_err(err)?;
+
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bars() -> DataFrame {
+        df!(
+            "ts_nanos" => [1_i64, 2, 3, 4],
+            "open" => [100.0, 100.0, 1.0, 1.0],
+            "high" => [100.0, 100.0, 1.0, 1.0],
+            "low" => [100.0, 100.0, 1.0, 1.0],
+            "close" => [100.0, 100.0, 1.0, 0.9],
+            "volume" => [10_i64, 10, 1000, 1000],
+            "value" => [1000.0, 1000.0, 1000.0, 900.0],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn from_json() {
+        let text = r#"[
+            {"ts_nanos": 4, "kind": "dividend", "value": 0.1},
+            {"ts_nanos": 3, "kind": "split", "value": 100.0}
+        ]"#;
+        let actions = CorporateActions::from_json(text).unwrap();
+        let a = actions.actions();
+        assert_eq!(a.len(), 2);
+        assert_eq!(a[0].kind, ActionKind::Split);
+        assert_eq!(a[1].kind, ActionKind::Dividend);
+        assert_eq!(a[1].close, None);
+    }
+    #[test]
+    fn adjust_split() {
+        let actions = CorporateActions::new(vec![CorporateAction {
+            ts_nanos: 3,
+            kind: ActionKind::Split,
+            value: 100.0,
+            close: None,
+        }]);
+
+        let df = actions.adjust(bars(), false).unwrap();
+        let close: Vec<f64> = df
+            .column("close")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let volume: Vec<i64> = df
+            .column("volume")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(close, vec![1.0, 1.0, 1.0, 0.9]);
+        assert_eq!(volume, vec![1000, 1000, 1000, 1000]);
+    }
+    #[test]
+    fn adjust_dividend() {
+        let actions = CorporateActions::new(vec![
+            CorporateAction {
+                ts_nanos: 3,
+                kind: ActionKind::Split,
+                value: 100.0,
+                close: None,
+            },
+            CorporateAction {
+                ts_nanos: 4,
+                kind: ActionKind::Dividend,
+                value: 0.1,
+                close: None,
+            },
+        ]);
+
+        // без флага дивиденды не учитываются
+        let df = actions.adjust(bars(), false).unwrap();
+        let close = df.column("close").unwrap().f64().unwrap();
+        assert_eq!(close.get(2), Some(1.0));
+
+        // дивиденд 0.1 при закрытии 1.0 - коэффициент 0.9
+        let df = actions.adjust(bars(), true).unwrap();
+        let close = df.column("close").unwrap().f64().unwrap();
+        assert!((close.get(0).unwrap() - 0.9).abs() < 1e-9);
+        assert!((close.get(2).unwrap() - 0.9).abs() < 1e-9);
+        assert_eq!(close.get(3), Some(0.9));
+    }
+}
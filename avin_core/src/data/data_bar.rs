@@ -68,23 +68,40 @@ fn load_file(
     market_data: MarketData,
     year: i32,
 ) -> Result<DataFrame, AvinError> {
-    // get path
-    let mut path = iid.path();
-    path.push(market_data.name());
-    path.push(format!("{year}.parquet"));
+    // get path: бары года лежат в <year>.parquet или, при разбиении
+    // по месяцам (data.storage.partition), в <year>/<MM>.parquet
+    let mut dir = iid.path();
+    dir.push(market_data.name());
+    let path = dir.join(format!("{year}.parquet"));
+    let year_dir = dir.join(format!("{year}"));
+
+    let files = if Cmd::is_exist(&path) {
+        vec![path]
+    } else if year_dir.is_dir() {
+        let mut files = Cmd::get_files(&year_dir)?;
+        files.retain(|f| f.extension().is_some_and(|e| e == "parquet"));
+        files
+    } else {
+        Vec::new()
+    };
 
     // check path is exist
-    if !Cmd::is_exist(&path) {
+    if files.is_empty() {
         let msg = format!("{iid} {market_data}");
         return Err(AvinError::NotFound(msg.to_string()));
     }
 
-    // read file
-    match Cmd::read_pqt(&path) {
-        Ok(df) => Ok(df),
-        Err(why) => {
-            let msg = format!("read {} - {}", path.display(), why);
-            Err(AvinError::IOError(msg.to_string()))
+    // read files
+    let mut df = DataFrame::empty_with_schema(&DataSchema::bar());
+    for path in files.iter() {
+        match Cmd::read_pqt(path) {
+            Ok(part) => df.extend(&part).unwrap(),
+            Err(why) => {
+                let msg = format!("read {} - {}", path.display(), why);
+                return Err(AvinError::IOError(msg.to_string()));
+            }
         }
     }
+
+    Ok(df)
 }
@@ -0,0 +1,158 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use avin_utils::{AvinError, CFG};
+
+use crate::{Iid, MarketData};
+
+/// Freshness of one stored market data of instrument.
+///
+/// # ru
+/// Состояние одного типа рыночных данных инструмента после последнего
+/// обновления демоном avin-data.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MarketDataStatus {
+    pub last_ts: Option<i64>,
+    pub status: String,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+impl MarketDataStatus {
+    /// Check last update was successful.
+    ///
+    /// # ru
+    /// Проверяет, что последнее обновление прошло без ошибок.
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+/// Status file written by `avin-data daemon`.
+///
+/// # ru
+/// Файл статуса, который пишет `avin-data daemon` после каждого шага
+/// обновления: `<data>/status.json`. По нему GUI и трейдер могут
+/// узнать актуальность данных, например перед стартом стратегии.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DataStatus {
+    pub state: String,
+    pub updated: Option<String>,
+    pub next_run: Option<String>,
+    pub instruments: HashMap<String, HashMap<String, MarketDataStatus>>,
+}
+impl DataStatus {
+    /// Load status file from data dir.
+    ///
+    /// # ru
+    /// Загружает файл статуса из папки с данными. Возвращает
+    /// NotFound если демон еще ни разу не запускался.
+    pub fn load() -> Result<Self, AvinError> {
+        Self::load_from(&Self::path())
+    }
+    /// Load status file from path.
+    ///
+    /// # ru
+    /// Загружает файл статуса по указанному пути.
+    pub fn load_from(path: &Path) -> Result<Self, AvinError> {
+        let text = std::fs::read_to_string(path).map_err(|_| {
+            AvinError::NotFound(format!("{}", path.display()))
+        })?;
+
+        Self::from_json(&text)
+    }
+    /// Parse status from json string.
+    ///
+    /// # ru
+    /// Разбирает статус из json строки.
+    pub fn from_json(text: &str) -> Result<Self, AvinError> {
+        serde_json::from_str(text)
+            .map_err(|e| AvinError::InvalidValue(e.to_string()))
+    }
+    /// Return path of status file.
+    ///
+    /// # ru
+    /// Возвращает путь к файлу статуса.
+    pub fn path() -> PathBuf {
+        let mut path = CFG.dir.data();
+        path.push("status.json");
+
+        path
+    }
+
+    /// Check last update run was successful.
+    ///
+    /// # ru
+    /// Проверяет, что последний запуск обновления прошел без ошибок.
+    pub fn is_ok(&self) -> bool {
+        self.state == "ok"
+    }
+    /// Return status of market data of instrument.
+    ///
+    /// # ru
+    /// Возвращает состояние данных инструмента, или None если демон
+    /// эти данные не обновлял.
+    pub fn get(
+        &self,
+        iid: &Iid,
+        md: MarketData,
+    ) -> Option<&MarketDataStatus> {
+        let key = iid.to_string().to_uppercase();
+        self.instruments.get(&key)?.get(md.name())
+    }
+    /// Return timestamp of last stored data.
+    ///
+    /// # ru
+    /// Возвращает timestamp последних сохраненных данных инструмента.
+    pub fn last_ts(&self, iid: &Iid, md: MarketData) -> Option<i64> {
+        self.get(iid, md)?.last_ts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_json() {
+        let text = r#"{
+            "state": "failed",
+            "updated": "2025-06-05T16:00:00+00:00",
+            "next_run": "2025-06-05T20:55:00+00:00",
+            "instruments": {
+                "MOEX_SHARE_SBER": {
+                    "BAR_1M": {
+                        "last_ts": 1749137940000000000,
+                        "last_dt": "2025-06-05T15:39:00+00:00",
+                        "status": "ok",
+                        "attempts": 1,
+                        "error": null
+                    },
+                    "TIC": {
+                        "last_ts": null,
+                        "last_dt": null,
+                        "status": "failed",
+                        "attempts": 3,
+                        "error": "SystemExit: 1"
+                    }
+                }
+            }
+        }"#;
+        let status = DataStatus::from_json(text).unwrap();
+        assert!(!status.is_ok());
+
+        let sber = &status.instruments["MOEX_SHARE_SBER"];
+        assert_eq!(sber["BAR_1M"].last_ts, Some(1749137940000000000));
+        assert!(sber["BAR_1M"].is_ok());
+        assert!(!sber["TIC"].is_ok());
+        assert_eq!(sber["TIC"].attempts, 3);
+    }
+}
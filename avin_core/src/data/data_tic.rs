@@ -5,23 +5,55 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+use std::path::PathBuf;
+
 use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
 use polars::prelude::*;
 
 use avin_utils::{self as utils, AvinError, Cmd};
 
-use crate::{Iid, MarketData};
+use crate::{DataSchema, Iid, MarketData};
 
 #[derive(Debug)]
 pub struct DataTic {}
 impl DataTic {
-    #[allow(dead_code)]
     pub fn save(
-        _iid: &Iid,
-        _md: MarketData,
-        _df: DataFrame,
+        iid: &Iid,
+        md: MarketData,
+        df: DataFrame,
     ) -> Result<(), AvinError> {
-        todo!()
+        if df.is_empty() {
+            return Ok(());
+        }
+
+        // sort by time, then split by days - one file per day
+        let df = df
+            .sort(["ts_nanos"], SortMultipleOptions::default())
+            .unwrap();
+        let ts: Vec<i64> = df
+            .column("ts_nanos")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+
+        let mut begin = 0;
+        while begin < ts.len() {
+            let day = DateTime::from_timestamp_nanos(ts[begin]).date_naive();
+            let mut end = begin;
+            while end < ts.len()
+                && DateTime::from_timestamp_nanos(ts[end]).date_naive() == day
+            {
+                end += 1;
+            }
+
+            let part = df.slice(begin as i64, end - begin);
+            Self::save_file(iid, md, day, part)?;
+            begin = end;
+        }
+
+        Ok(())
     }
     pub fn load(
         iid: &Iid,
@@ -30,15 +62,7 @@ impl DataTic {
         end: DateTime<Utc>,
     ) -> Result<DataFrame, AvinError> {
         // create empty df
-        let schema = Schema::from_iter(vec![
-            Field::new("ts_nanos".into(), DataType::Int64),
-            Field::new("direction".into(), DataType::String),
-            Field::new("lots".into(), DataType::Int64),
-            Field::new("price".into(), DataType::Float64),
-            Field::new("value".into(), DataType::Float64),
-            Field::new("session".into(), DataType::Int8),
-            Field::new("tradeno".into(), DataType::Int64),
-        ]);
+        let schema = DataSchema::tic();
         let mut df = DataFrame::empty_with_schema(&schema);
 
         // load data by days
@@ -69,17 +93,50 @@ impl DataTic {
 
         Ok(df)
     }
-    pub fn load_file(
+    fn save_file(
         iid: &Iid,
         md: MarketData,
         day: NaiveDate,
-    ) -> Result<DataFrame, AvinError> {
-        // get path
+        part: DataFrame,
+    ) -> Result<(), AvinError> {
+        let path = Self::file_path(iid, md, day);
+
+        // если файл за этот день уже есть - тики в интервале нового
+        // куска заменяются, остальные сохраняются
+        let mut df = match Self::load_file(iid, md, day) {
+            Ok(existing) => {
+                let first = part.column("ts_nanos").unwrap().i64().unwrap();
+                let min = first.min().unwrap();
+                let max = first.max().unwrap();
+                let ts = existing.column("ts_nanos").unwrap().i64().unwrap();
+                let mask = ts.lt(min) | ts.gt(max);
+                let mut df = existing.filter(&mask).unwrap();
+                df.vstack_mut(&part).unwrap();
+                df.sort(["ts_nanos"], SortMultipleOptions::default())
+                    .unwrap()
+            }
+            Err(AvinError::NotFound(_)) => part,
+            Err(other) => return Err(other),
+        };
+
+        Cmd::write_pqt(&mut df, &path)
+    }
+    fn file_path(iid: &Iid, md: MarketData, day: NaiveDate) -> PathBuf {
         let mut path = iid.path();
         path.push(md.name());
         path.push(day.year().to_string());
         path.push(format!("{}.parquet", day.format("%Y-%m-%d")));
 
+        path
+    }
+    pub fn load_file(
+        iid: &Iid,
+        md: MarketData,
+        day: NaiveDate,
+    ) -> Result<DataFrame, AvinError> {
+        // get path
+        let path = Self::file_path(iid, md, day);
+
         if !Cmd::is_exist(&path) {
             let msg = format!("{iid} {md}");
             return Err(AvinError::NotFound(msg.to_string()));
@@ -5,15 +5,55 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
-use cached::proc_macro::cached;
 use polars::prelude::*;
 
 use avin_utils::{AvinError, CFG, Cmd};
 
-use crate::{Category, Exchange, Iid, Source};
+use crate::{Category, Exchange, Iid, Source, Synthetic};
 
+const CATEGORIES: [Category; 8] = [
+    Category::CURRENCY,
+    Category::INDEX,
+    Category::SHARE,
+    Category::BOND,
+    Category::FUTURE,
+    Category::OPTION,
+    Category::ETF,
+    Category::SPOT,
+];
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// кэшированные в памяти файлы кэша инструментов
+static CACHE: LazyLock<RwLock<HashMap<PathBuf, CacheEntry>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    df: DataFrame,
+    loaded: Instant,
+    modified: SystemTime,
+}
+
+/// Cache of instruments info, written by "avin-data cache".
+///
+/// # ru
+/// Кэш информации об инструментах, файлы пишет "avin-data cache".
+/// Прочитанные файлы хранятся в памяти процесса не дольше
+/// `data.cache_ttl` секунд, после этого файл перечитывается, если
+/// изменился - так подхватывается новый кэш после "avin-data cache"
+/// без перезапуска. Если файл старше `data.cache_max_age` дней -
+/// в лог пишется предупреждение.
+///
+/// Инструменты, пропавшие из кэша после делистинга, "avin-data cache"
+/// переносит в архив `<cache>/<source>/ARCHIVE/<category>.parquet`.
+/// Если инструмента нет в кэше, он ищется в архиве - так старые
+/// бэктесты продолжают работать. Такой [`Iid`] помечен
+/// [`Iid::delisted`], подписаться на него или торговать нельзя.
 #[derive(Debug, PartialEq, Clone)]
 pub struct IidCache {
     source: Source,
@@ -23,10 +63,118 @@ pub struct IidCache {
 
 impl IidCache {
     pub fn find_iid(s: &str) -> Result<Iid, AvinError> {
-        cached_find_iid(s.to_string())
+        // синтетические инструменты не кэшируются, их можно
+        // перерегистрировать
+        if let Some(synthetic) = Synthetic::find(s) {
+            return Ok(synthetic.iid().clone());
+        }
+
+        find_iid(s)
     }
     pub fn find_figi(figi: &str) -> Result<Iid, AvinError> {
-        cached_find_figi(figi.to_string())
+        find_figi(figi)
+    }
+    /// Search instruments by ticker, name, FIGI, ISIN or uid.
+    ///
+    /// # ru
+    /// Нечеткий поиск инструментов по всем кэшам и синтетическим
+    /// инструментам. Результаты отсортированы по релевантности:
+    /// точное совпадение тикера, идентификатора, FIGI, ISIN или uid,
+    /// затем тикер начинается с запроса, тикер содержит запрос,
+    /// название содержит запрос. Регистр не учитывается.
+    pub fn search(query: &str) -> Vec<Iid> {
+        let query = query.trim().to_uppercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut found: Vec<(u8, Iid)> = Vec::new();
+        for synthetic in Synthetic::all() {
+            let iid = synthetic.iid();
+            let ids = [iid.to_string()];
+            if let Some(r) = rank(&query, iid.ticker(), iid.name(), &ids) {
+                found.push((r, iid.clone()));
+            }
+        }
+        for source in [Source::TINKOFF, Source::BINANCE] {
+            for category in CATEGORIES {
+                if !Cmd::is_exist(&create_file_path(source, category)) {
+                    continue;
+                }
+                match load_df(source, category) {
+                    Ok(df) => search_df(&query, &df, &mut found),
+                    Err(e) => log::warn!("{e}"),
+                }
+            }
+        }
+
+        found.sort_by(|a, b| {
+            let a = (a.0, a.1.ticker().len(), a.1.ticker());
+            let b = (b.0, b.1.ticker().len(), b.1.ticker());
+            a.cmp(&b)
+        });
+        found.into_iter().map(|(_, iid)| iid).collect()
+    }
+    /// All instruments of category, including delisted from archive.
+    ///
+    /// # ru
+    /// Все инструменты биржи и категории: актуальный кэш и архив
+    /// делистинга. Инструменты из архива помечены [`Iid::delisted`].
+    pub fn universe(
+        exchange: Exchange,
+        category: Category,
+    ) -> Result<Vec<Iid>, AvinError> {
+        let source = match exchange {
+            Exchange::MOEX => Source::TINKOFF,
+            Exchange::BINANCE => Source::BINANCE,
+        };
+
+        let mut iids = Vec::new();
+        let mut tickers = Vec::new();
+        let mut add = |df: &DataFrame| {
+            for i in 0..df.height() {
+                match Iid::from_df(&df.slice(i as i64, 1)) {
+                    Ok(iid) if !tickers.contains(iid.ticker()) => {
+                        tickers.push(iid.ticker().clone());
+                        iids.push(iid);
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("{e}"),
+                }
+            }
+        };
+
+        add(&load_df(source, category)?);
+        let path = create_archive_path(source, category);
+        if Cmd::is_exist(&path) {
+            let ttl = Duration::from_secs(CFG.data.cache_ttl);
+            add(&load_cached(&path, ttl, Duration::MAX)?);
+        }
+
+        Ok(iids)
+    }
+    /// Drop instruments info loaded in memory.
+    ///
+    /// # ru
+    /// Сбрасывает кэш в памяти, следующий поиск перечитает файлы.
+    pub fn refresh() {
+        CACHE.write().unwrap().clear();
+    }
+    /// Return age of cache file.
+    ///
+    /// # ru
+    /// Возвращает возраст файла кэша инструментов источника и
+    /// категории, NotFound если кэша нет.
+    pub fn age(
+        source: Source,
+        category: Category,
+    ) -> Result<Duration, AvinError> {
+        let path = create_file_path(source, category);
+        let modified = modified(&path)?;
+
+        Ok(SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default())
     }
 
     #[allow(dead_code)]
@@ -39,62 +187,186 @@ impl IidCache {
     }
 }
 
-#[cached]
-fn cached_find_iid(s: String) -> Result<Iid, AvinError> {
+fn find_iid(s: &str) -> Result<Iid, AvinError> {
     // parse str
     let parts: Vec<&str> = s.split('_').collect();
     if parts.len() != 3 {
-        return Err(AvinError::InvalidValue(s));
+        return Err(AvinError::InvalidValue(s.to_string()));
     };
 
     // convert values
-    let _exchange = Exchange::from(parts[0]);
+    let exchange = Exchange::from(parts[0]);
     let category = Category::from(parts[1]);
     let ticker = parts[2].to_uppercase();
 
-    // load instrument info df
-    let source = Source::TINKOFF;
-    let df = cached_load_df(source, category).unwrap();
-
-    // find row
-    let mask = df
-        .column("ticker")
-        .unwrap()
-        .str()
-        .unwrap()
-        .equal(ticker.as_str());
-    let row = df.filter(&mask).unwrap();
-
-    Iid::from_df(&row)
+    // load instrument info df: для Мос.биржи кэш Тинькофф,
+    // для криптовалют - кэш Binance (avin-data cache -s binance)
+    let source = match exchange {
+        Exchange::MOEX => Source::TINKOFF,
+        Exchange::BINANCE => Source::BINANCE,
+    };
+    match find_row(source, category, "ticker", &ticker)? {
+        Some(row) => Iid::from_df(&row),
+        None => Err(AvinError::NotFound(s.to_string())),
+    }
 }
-#[cached]
-fn cached_find_figi(figi: String) -> Result<Iid, AvinError> {
+fn find_figi(figi: &str) -> Result<Iid, AvinError> {
     // load instrument info df
     let source = Source::TINKOFF;
     let category = Category::SHARE;
-    let df = cached_load_df(source, category).unwrap();
-
-    // find row
-    let mask = df
-        .column("figi")
-        .unwrap()
-        .str()
-        .unwrap()
-        .equal(figi.as_str());
-    let row = df.filter(&mask).unwrap();
-
-    Iid::from_df(&row)
+    match find_row(source, category, "figi", figi)? {
+        Some(row) => Iid::from_df(&row),
+        None => Err(AvinError::NotFound(figi.to_string())),
+    }
 }
-#[cached]
-fn cached_load_df(
+fn find_row(
+    source: Source,
+    category: Category,
+    column: &str,
+    value: &str,
+) -> Result<Option<DataFrame>, AvinError> {
+    let row = filter_eq(&load_df(source, category)?, column, value);
+    if !row.is_empty() {
+        return Ok(Some(row));
+    }
+
+    // нет в актуальном кэше - ищем в архиве делистинга
+    let path = create_archive_path(source, category);
+    if !Cmd::is_exist(&path) {
+        return Ok(None);
+    }
+    let ttl = Duration::from_secs(CFG.data.cache_ttl);
+    let archive = load_cached(&path, ttl, Duration::MAX)?;
+    let row = filter_eq(&archive, column, value);
+
+    Ok((!row.is_empty()).then_some(row))
+}
+fn filter_eq(df: &DataFrame, column: &str, value: &str) -> DataFrame {
+    let mask = df.column(column).unwrap().str().unwrap().equal(value);
+
+    df.filter(&mask).unwrap()
+}
+fn load_df(
     source: Source,
     category: Category,
 ) -> Result<DataFrame, AvinError> {
     let path = create_file_path(source, category);
-    let df = Cmd::read_pqt(&path).unwrap();
+    let ttl = Duration::from_secs(CFG.data.cache_ttl);
+    let max_age = DAY * CFG.data.cache_max_age;
+
+    load_cached(&path, ttl, max_age)
+}
+fn load_cached(
+    path: &Path,
+    ttl: Duration,
+    max_age: Duration,
+) -> Result<DataFrame, AvinError> {
+    if let Some(entry) = CACHE.read().unwrap().get(path)
+        && entry.loaded.elapsed() < ttl
+    {
+        return Ok(entry.df.clone());
+    }
+
+    // время жизни истекло - перечитываем, только если файл изменился
+    let modified = modified(path)?;
+    let mut cache = CACHE.write().unwrap();
+    if let Some(entry) = cache.get_mut(path)
+        && entry.modified == modified
+    {
+        entry.loaded = Instant::now();
+        return Ok(entry.df.clone());
+    }
+
+    let df = Cmd::read_pqt(path)?;
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default();
+    if age > max_age {
+        log::warn!(
+            "Instrument cache {} is {} days old, update it: avin-data cache",
+            path.display(),
+            age.as_secs() / DAY.as_secs(),
+        );
+    }
+
+    let entry = CacheEntry {
+        df: df.clone(),
+        loaded: Instant::now(),
+        modified,
+    };
+    cache.insert(path.to_path_buf(), entry);
 
     Ok(df)
 }
+fn search_df(query: &str, df: &DataFrame, found: &mut Vec<(u8, Iid)>) {
+    let column = |name: &str| -> Option<StringChunked> {
+        df.column(name).ok()?.str().ok().cloned()
+    };
+    let (Some(exchange), Some(category), Some(ticker)) =
+        (column("exchange"), column("category"), column("ticker"))
+    else {
+        return;
+    };
+    let name = column("name");
+    let figi = column("figi");
+    let isin = column("isin");
+    let uid = column("uid");
+    let get = |col: &Option<StringChunked>, i: usize| -> String {
+        col.as_ref()
+            .and_then(|c| c.get(i))
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    for i in 0..df.height() {
+        let t = ticker.get(i).unwrap_or_default();
+        let ids = [
+            format!(
+                "{}_{}_{}",
+                exchange.get(i).unwrap_or_default(),
+                category.get(i).unwrap_or_default(),
+                t
+            ),
+            get(&figi, i),
+            get(&isin, i),
+            get(&uid, i),
+        ];
+        let Some(r) = rank(query, t, &get(&name, i), &ids) else {
+            continue;
+        };
+
+        // Iid собирается только для найденных строк
+        match Iid::from_df(&df.slice(i as i64, 1)) {
+            Ok(iid) => found.push((r, iid)),
+            Err(e) => log::warn!("{e}"),
+        }
+    }
+}
+fn rank(query: &str, ticker: &str, name: &str, ids: &[String]) -> Option<u8> {
+    let ticker = ticker.to_uppercase();
+    if ticker == query || ids.iter().any(|i| i.to_uppercase() == query) {
+        Some(0)
+    } else if ticker.starts_with(query) {
+        Some(1)
+    } else if ticker.contains(query) {
+        Some(2)
+    } else if name.to_uppercase().contains(query) {
+        Some(3)
+    } else {
+        None
+    }
+}
+fn modified(path: &Path) -> Result<SystemTime, AvinError> {
+    std::fs::metadata(path)
+        .and_then(|i| i.modified())
+        .map_err(|_| {
+            let msg = format!(
+                "instrument cache {}, run: avin-data cache",
+                path.display()
+            );
+            AvinError::NotFound(msg)
+        })
+}
 
 fn create_file_path(source: Source, category: Category) -> PathBuf {
     let mut path = CFG.dir.cache();
@@ -103,3 +375,102 @@ fn create_file_path(source: Source, category: Category) -> PathBuf {
 
     path
 }
+fn create_archive_path(source: Source, category: Category) -> PathBuf {
+    let mut path = CFG.dir.cache();
+    path.push(source.name());
+    path.push("ARCHIVE");
+    path.push(format!("{}.parquet", category.name()));
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_rank() {
+        let ids = ["MOEX_SHARE_SBER".to_string(), "BBG004730N88".to_string()];
+        let r = |q: &str, t: &str, n: &str| rank(q, t, n, &ids);
+
+        assert_eq!(r("SBER", "SBER", "Сбер Банк"), Some(0));
+        assert_eq!(r("BBG004730N88", "SBER", "Сбер Банк"), Some(0));
+        assert_eq!(
+            r("SBE", "SBERP", "Сбер Банк - привилегированные"),
+            Some(1)
+        );
+        assert_eq!(r("BER", "SBER", "Сбер Банк"), Some(2));
+        assert_eq!(r("БАНК", "SBER", "Сбер Банк"), Some(3));
+        assert_eq!(r("GAZP", "SBER", "Сбер Банк"), None);
+    }
+    #[test]
+    fn search_cache_df() {
+        // кэш содержит все колонки Iid, для теста лишние пустые
+        let mut df = df!(
+            "exchange" => ["MOEX", "MOEX", "MOEX"],
+            "category" => ["SHARE", "SHARE", "SHARE"],
+            "ticker" => ["SBERP", "SBER", "GAZP"],
+            "figi" => ["BBG0047315Y7", "BBG004730N88", "BBG004730RP0"],
+            "name" => ["Сбер Банк - привилегированные", "Сбер Банк", "Газпром"],
+        )
+        .unwrap();
+        for name in [
+            "exchange_specific",
+            "country",
+            "currency",
+            "sector",
+            "class_code",
+            "isin",
+            "uid",
+            "lot",
+            "step",
+            "long",
+            "short",
+            "long_qual",
+            "short_qual",
+            "first_1m",
+            "first_d",
+        ] {
+            df.with_column(Series::new(name.into(), ["", "", ""]))
+                .unwrap();
+        }
+
+        let mut found = Vec::new();
+        search_df("SBER", &df, &mut found);
+        let mut ranks: Vec<u8> = found.iter().map(|i| i.0).collect();
+        ranks.sort();
+        assert_eq!(ranks, vec![0, 1]);
+
+        let mut found = Vec::new();
+        search_df("ГАЗ", &df, &mut found);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.ticker(), "GAZP");
+    }
+    #[test]
+    fn reload_changed_file() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("avin_iid_cache_{}.parquet", std::process::id()));
+        let max_age = DAY;
+
+        let mut df = df!("ticker" => ["A"]).unwrap();
+        Cmd::write_pqt(&mut df, &path).unwrap();
+        let loaded = load_cached(&path, Duration::MAX, max_age);
+        assert_eq!(loaded.unwrap().height(), 1);
+
+        // пока не истекло время жизни - файл не перечитывается
+        let mut df = df!("ticker" => ["A", "B"]).unwrap();
+        Cmd::write_pqt(&mut df, &path).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        let minute_ago = SystemTime::now() - Duration::from_secs(60);
+        file.set_modified(minute_ago).unwrap();
+        let loaded = load_cached(&path, Duration::MAX, max_age);
+        assert_eq!(loaded.unwrap().height(), 1);
+
+        // после истечения - перечитывается измененный файл
+        let loaded = load_cached(&path, Duration::ZERO, max_age);
+        assert_eq!(loaded.unwrap().height(), 2);
+
+        std::fs::remove_file(&path).unwrap();
+        CACHE.write().unwrap().remove(&path);
+    }
+}
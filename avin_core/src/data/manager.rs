@@ -5,20 +5,22 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+use std::time::Duration;
+
 use chrono::prelude::*;
 use polars::frame::DataFrame;
 
-use avin_utils::AvinError;
+use avin_utils::{AvinError, CFG};
 
-use crate::Iid;
+use crate::{Category, CorporateActions, Exchange, Iid, Source, Tic};
 
-use super::data_bar::DataBar;
 use super::data_ob::DataOB;
 use super::data_orders::DataOrders;
-use super::data_tic::DataTic;
 use super::data_trades::DataTrades;
 use super::iid_cache::IidCache;
 use super::market_data::MarketData;
+use super::storage::{ParquetStorage, SqliteStorage, Storage};
+use super::synthetic::Synthetic;
 
 /// Fasade class for operations with market data.
 ///
@@ -69,6 +71,72 @@ impl Manager {
     pub fn find_figi(s: &str) -> Result<Iid, AvinError> {
         IidCache::find_figi(s)
     }
+    /// Search instruments by ticker, name, FIGI or ISIN.
+    ///
+    /// # ru
+    /// Нечеткий поиск инструментов, например для выбора инструмента
+    /// в GUI. Ищет по подстроке тикера, названию, FIGI, ISIN среди
+    /// кэшированных и синтетических инструментов, результаты
+    /// отсортированы по релевантности.
+    ///
+    /// ## Examples
+    /// ```no_run
+    /// use avin_core::Manager;
+    ///
+    /// for iid in Manager::search("сбер").iter().take(10) {
+    ///     println!("{iid} {}", iid.name());
+    /// }
+    /// ```
+    pub fn search(query: &str) -> Vec<Iid> {
+        IidCache::search(query)
+    }
+    /// All instruments of category, including delisted.
+    ///
+    /// # ru
+    /// Все инструменты биржи и категории, включая ушедшие с биржи
+    /// (из архива делистинга). В отличие от текущего кэша такой набор
+    /// не страдает ошибкой выжившего: для бэктеста "все акции MOEX"
+    /// каждый инструмент берется только на период торгов, см.
+    /// [`Iid::trading_period`].
+    pub fn universe(
+        exchange: Exchange,
+        category: Category,
+    ) -> Result<Vec<Iid>, AvinError> {
+        IidCache::universe(exchange, category)
+    }
+    /// Reload instruments info cache.
+    ///
+    /// # ru
+    /// Сбрасывает кэш информации об инструментах в памяти процесса,
+    /// следующий поиск перечитает файлы кэша. Без вызова новый кэш
+    /// после "avin-data cache" подхватывается через `data.cache_ttl`
+    /// секунд.
+    pub fn refresh_cache() {
+        IidCache::refresh();
+    }
+    /// Return age of instruments info cache file.
+    ///
+    /// # ru
+    /// Возвращает возраст файла кэша инструментов, например чтобы
+    /// предупредить пользователя о необходимости "avin-data cache".
+    pub fn cache_age(
+        source: Source,
+        category: Category,
+    ) -> Result<Duration, AvinError> {
+        IidCache::age(source, category)
+    }
+    /// Register user-defined synthetic instrument.
+    ///
+    /// # ru
+    /// Регистрирует синтетический инструмент (спред, индекс, корзину).
+    /// После регистрации он доступен через [`Manager::find_iid`], а бары
+    /// загружаются через [`Manager::load`] и строятся из баров ног.
+    ///
+    /// Инструменты можно так же описать в конфиге, в секции
+    /// `[[data.synthetic]]`, тогда регистрация не требуется.
+    pub fn register_synthetic(synthetic: Synthetic) {
+        Synthetic::register(synthetic);
+    }
     /// Load market data
     ///
     /// # ru
@@ -97,19 +165,115 @@ impl Manager {
         begin: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<DataFrame, AvinError> {
+        if iid.info().contains_key("synthetic") {
+            return Self::load_synthetic(iid, md, begin, end);
+        }
+
+        if CFG.data.adjusted && md.is_bar() {
+            let dividends = CFG.data.adjust_dividends;
+            return Self::load_adjusted(iid, md, begin, end, dividends);
+        }
+
+        let storage = Self::storage();
         match md {
-            MarketData::BAR_1M => DataBar::load(iid, md, begin, end),
-            MarketData::BAR_10M => DataBar::load(iid, md, begin, end),
-            MarketData::BAR_1H => DataBar::load(iid, md, begin, end),
-            MarketData::BAR_DAY => DataBar::load(iid, md, begin, end),
-            MarketData::BAR_WEEK => DataBar::load(iid, md, begin, end),
-            MarketData::BAR_MONTH => DataBar::load(iid, md, begin, end),
-            MarketData::TIC => DataTic::load(iid, md, begin, end),
+            MarketData::BAR_1S
+            | MarketData::BAR_5S
+            | MarketData::BAR_1M
+            | MarketData::BAR_10M
+            | MarketData::BAR_1H
+            | MarketData::BAR_DAY
+            | MarketData::BAR_WEEK
+            | MarketData::BAR_MONTH => storage.load_bars(iid, md, begin, end),
+            MarketData::TIC => storage.load_tics(iid, begin, end),
             MarketData::TRADE_STATS => DataTrades::load(iid, md, begin, end),
             MarketData::ORDER_STATS => DataOrders::load(iid, md, begin, end),
             MarketData::OB_STATS => DataOB::load(iid, md, begin, end),
         }
     }
+    /// Load bars adjusted for splits and optionally dividends.
+    ///
+    /// # ru
+    /// Загрузка баров, скорректированных на сплиты, и если dividends -
+    /// на дивиденды. События берутся из файла ACTIONS.json в папке
+    /// инструмента, см. [`CorporateActions`]. Если файла нет - бары
+    /// возвращаются как есть.
+    ///
+    /// [`Manager::load`] делает то же самое, если в конфиге включен
+    /// флаг `data.adjusted`.
+    pub fn load_adjusted(
+        iid: &Iid,
+        md: MarketData,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+        dividends: bool,
+    ) -> Result<DataFrame, AvinError> {
+        if !md.is_bar() {
+            let msg = format!("adjusted {md}, only bars can be adjusted");
+            return Err(AvinError::InvalidValue(msg));
+        }
+
+        let df = Self::storage().load_bars(iid, md, begin, end)?;
+        let actions = CorporateActions::load(iid)?;
+
+        actions.adjust(df, dividends)
+    }
+    /// Save tics.
+    ///
+    /// # ru
+    /// Сохраняет тики в хранилище рыночных данных, в том же формате,
+    /// в каком их загружает [`Manager::load`] с [`MarketData::TIC`]:
+    /// parquet файл на каждый день или таблица базы, см.
+    /// [`Manager::storage`]. Тики в интервале времени сохраняемых
+    /// заменяют уже имеющиеся, остальные остаются.
+    ///
+    /// Используется для записи тиков из потока рыночных данных.
+    pub fn save_tics(iid: &Iid, tics: &[Tic]) -> Result<(), AvinError> {
+        let df = Tic::to_df(tics);
+        Self::storage().save_tics(iid, df)
+    }
+    /// Return market data storage selected in config.
+    ///
+    /// # ru
+    /// Возвращает хранилище рыночных данных по настройке конфига
+    /// `data.format`: "parquet" (по умолчанию) - [`ParquetStorage`],
+    /// "sqlite" - [`SqliteStorage`] с базой `<data>/avin.sqlite`.
+    pub fn storage() -> Box<dyn Storage> {
+        match CFG.data.format.as_str() {
+            "sqlite" => {
+                let path = SqliteStorage::default_path();
+                Box::new(SqliteStorage::new(&path))
+            }
+            _ => Box::new(ParquetStorage),
+        }
+    }
+
+    // private
+    fn load_synthetic(
+        iid: &Iid,
+        md: MarketData,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError> {
+        let Some(synthetic) = Synthetic::find(&iid.to_string()) else {
+            let msg = format!("synthetic instrument {iid}");
+            return Err(AvinError::NotFound(msg));
+        };
+
+        match md {
+            MarketData::BAR_1S
+            | MarketData::BAR_5S
+            | MarketData::BAR_1M
+            | MarketData::BAR_10M
+            | MarketData::BAR_1H
+            | MarketData::BAR_DAY
+            | MarketData::BAR_WEEK
+            | MarketData::BAR_MONTH => synthetic.load(md, begin, end),
+            _ => {
+                let msg = format!("market data {md} for {iid}");
+                Err(AvinError::NotFound(msg))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
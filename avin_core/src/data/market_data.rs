@@ -12,6 +12,8 @@
 #[allow(non_camel_case_types)]
 #[derive(Debug, PartialEq, Clone, Copy, strum::Display)]
 pub enum MarketData {
+    BAR_1S,
+    BAR_5S,
     BAR_1M,
     BAR_10M,
     BAR_1H,
@@ -30,6 +32,8 @@ impl MarketData {
     /// Возвращает название типа биржевых данных.
     pub fn name(&self) -> &'static str {
         match self {
+            Self::BAR_1S => "BAR_1S",
+            Self::BAR_5S => "BAR_5S",
             Self::BAR_1M => "BAR_1M",
             Self::BAR_10M => "BAR_10M",
             Self::BAR_1H => "BAR_1H",
@@ -42,10 +46,30 @@ impl MarketData {
             Self::OB_STATS => "OB_STATS",
         }
     }
+    /// Check market data is bars.
+    ///
+    /// # ru
+    /// Проверяет, что тип данных - бары. Секундные бары источники не
+    /// дают, их собирает из тиков "avin-data build-bars".
+    pub fn is_bar(&self) -> bool {
+        matches!(
+            self,
+            Self::BAR_1S
+                | Self::BAR_5S
+                | Self::BAR_1M
+                | Self::BAR_10M
+                | Self::BAR_1H
+                | Self::BAR_DAY
+                | Self::BAR_WEEK
+                | Self::BAR_MONTH
+        )
+    }
 }
 impl From<&str> for MarketData {
     fn from(value: &str) -> Self {
         match value.to_uppercase().as_str() {
+            "BAR_1S" => MarketData::BAR_1S,
+            "BAR_5S" => MarketData::BAR_5S,
             "BAR_1M" => MarketData::BAR_1M,
             "BAR_10M" => MarketData::BAR_10M,
             "BAR_1H" => MarketData::BAR_1H,
@@ -68,6 +92,10 @@ mod tests {
     #[test]
     fn name() {
         assert_eq!(MarketData::BAR_1M.name(), "BAR_1M");
+        assert!(MarketData::BAR_DAY.is_bar());
+        assert!(!MarketData::TIC.is_bar());
+        assert!(MarketData::BAR_1S.is_bar());
+        assert_eq!(MarketData::BAR_5S.name(), "BAR_5S");
         assert_eq!(MarketData::BAR_10M.name(), "BAR_10M");
         assert_eq!(MarketData::BAR_1H.name(), "BAR_1H");
         assert_eq!(MarketData::BAR_DAY.name(), "BAR_DAY");
@@ -93,6 +121,7 @@ mod tests {
     }
     #[test]
     fn from_str() {
+        assert_eq!(MarketData::BAR_1S, "BAR_1S".into());
         assert_eq!(MarketData::BAR_1M, "BAR_1M".into());
         assert_eq!(MarketData::BAR_10M, "BAR_10M".into());
         assert_eq!(MarketData::BAR_1H, "BAR_1H".into());
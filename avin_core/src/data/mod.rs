@@ -5,9 +5,11 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+mod corporate_action;
 mod data_bar;
 mod data_ob;
 mod data_orders;
+mod data_status;
 mod data_tic;
 mod data_trades;
 mod iid_cache;
@@ -15,8 +17,14 @@ mod manager;
 mod market_data;
 mod schema;
 mod source;
+mod storage;
+mod synthetic;
 
+pub use corporate_action::{ActionKind, CorporateAction, CorporateActions};
+pub use data_status::{DataStatus, MarketDataStatus};
 pub use manager::Manager;
 pub use market_data::MarketData;
 pub use schema::DataSchema;
 pub use source::Source;
+pub use storage::{ParquetStorage, SqliteStorage, Storage};
+pub use synthetic::{Roll, RollAdjust, Synthetic};
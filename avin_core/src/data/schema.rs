@@ -28,8 +28,18 @@ impl DataSchema {
     /// Polars dataframe schema for tics.
     ///
     /// # ru
-    /// Возвращает polars схему датафрейма для тиков.
-    #[allow(unused)]
+    /// Возвращает polars схему датафрейма для тиков. Тики хранятся
+    /// по дням: `<data>/<exchange>/<category>/<ticker>/TIC/<year>/
+    /// <YYYY-MM-DD>.parquet`, день - по UTC.
+    ///
+    /// - `ts_nanos` - время сделки, UTC, наносекунды;
+    /// - `direction` - сторона агрессора: "B" покупка, "S" продажа
+    ///   (регистр не важен);
+    /// - `lots` - количество лотов;
+    /// - `price` - цена;
+    /// - `value` - объем сделки в валюте;
+    /// - `session` - торговая сессия, null если неизвестна;
+    /// - `tradeno` - номер сделки на бирже, null если неизвестен.
     pub fn tic() -> Schema {
         Schema::from_iter(vec![
             Field::new("ts_nanos".into(), DataType::Int64),
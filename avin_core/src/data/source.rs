@@ -13,6 +13,7 @@
 pub enum Source {
     MOEX,
     TINKOFF,
+    BINANCE,
 }
 impl Source {
     /// Return market data source name.
@@ -23,6 +24,7 @@ impl Source {
         match self {
             Self::MOEX => "MOEX",
             Self::TINKOFF => "TINKOFF",
+            Self::BINANCE => "BINANCE",
         }
     }
 }
@@ -31,6 +33,7 @@ impl From<&str> for Source {
         match value.to_uppercase().as_str() {
             "MOEX" => Source::MOEX,
             "TINKOFF" => Source::TINKOFF,
+            "BINANCE" => Source::BINANCE,
             _ => panic!("Invalid source: {value}"),
         }
     }
@@ -44,15 +47,18 @@ mod tests {
     fn name() {
         assert_eq!(Source::MOEX.name(), "MOEX");
         assert_eq!(Source::TINKOFF.name(), "TINKOFF");
+        assert_eq!(Source::BINANCE.name(), "BINANCE");
     }
     #[test]
     fn to_str() {
         assert_eq!(Source::MOEX.to_string(), "MOEX");
         assert_eq!(Source::TINKOFF.to_string(), "TINKOFF");
+        assert_eq!(Source::BINANCE.to_string(), "BINANCE");
     }
     #[test]
     fn from_str() {
         assert_eq!(Source::MOEX, "moex".into());
         assert_eq!(Source::TINKOFF, "TiNkoFf".into());
+        assert_eq!(Source::BINANCE, "binance".into());
     }
 }
@@ -0,0 +1,562 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+use rusqlite::{Connection, params, types::ValueRef};
+
+use avin_utils::{AvinError, CFG};
+
+use crate::{DataSchema, Iid, MarketData};
+
+use super::data_bar::DataBar;
+use super::data_tic::DataTic;
+
+// Схема базы SqliteStorage, такую же создает "avin-data db sync".
+// iid - идентификатор в верхнем регистре: "MOEX_SHARE_SBER",
+// market_data - имя типа данных: "BAR_1M", остальные колонки как в
+// parquet файлах, см. DataSchema::bar() и DataSchema::tic().
+const SCHEMA_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS bars (
+        iid TEXT NOT NULL,
+        market_data TEXT NOT NULL,
+        ts_nanos INTEGER NOT NULL,
+        open REAL NOT NULL,
+        high REAL NOT NULL,
+        low REAL NOT NULL,
+        close REAL NOT NULL,
+        volume INTEGER NOT NULL,
+        value REAL,
+        PRIMARY KEY (iid, market_data, ts_nanos)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS tics (
+        iid TEXT NOT NULL,
+        ts_nanos INTEGER NOT NULL,
+        direction TEXT NOT NULL,
+        lots INTEGER NOT NULL,
+        price REAL NOT NULL,
+        value REAL,
+        session INTEGER,
+        tradeno INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS tics_iid_ts ON tics (iid, ts_nanos);
+";
+
+/// Storage backend of market data.
+///
+/// # ru
+/// Хранилище рыночных данных. По умолчанию используется дерево
+/// parquet файлов [`ParquetStorage`], `data.format = "sqlite"` в
+/// конфиге переключает на один файл базы данных [`SqliteStorage`].
+/// Хранилище из конфига возвращает [`crate::Manager::storage`].
+pub trait Storage {
+    /// Load bars in [begin, end).
+    ///
+    /// # ru
+    /// Загружает бары в интервале [begin, end), если баров нет -
+    /// AvinError::NotFound.
+    fn load_bars(
+        &self,
+        iid: &Iid,
+        md: MarketData,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError>;
+    /// Load tics in [begin, end).
+    ///
+    /// # ru
+    /// Загружает тики в интервале [begin, end), если тиков нет -
+    /// AvinError::NotFound.
+    fn load_tics(
+        &self,
+        iid: &Iid,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError>;
+    /// Save tics, replace stored tics in time interval of df.
+    ///
+    /// # ru
+    /// Сохраняет тики, уже сохраненные тики в интервале времени
+    /// датафрейма заменяются.
+    fn save_tics(&self, iid: &Iid, df: DataFrame) -> Result<(), AvinError>;
+}
+
+/// Default storage: tree of parquet files in data dir.
+///
+/// # ru
+/// Хранилище по умолчанию: дерево parquet файлов в папке данных,
+/// которое пишет avin-data.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParquetStorage;
+impl Storage for ParquetStorage {
+    fn load_bars(
+        &self,
+        iid: &Iid,
+        md: MarketData,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError> {
+        DataBar::load(iid, md, begin, end)
+    }
+    fn load_tics(
+        &self,
+        iid: &Iid,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError> {
+        DataTic::load(iid, MarketData::TIC, begin, end)
+    }
+    fn save_tics(&self, iid: &Iid, df: DataFrame) -> Result<(), AvinError> {
+        DataTic::save(iid, MarketData::TIC, df)
+    }
+}
+
+/// Storage in single SQLite database file.
+///
+/// # ru
+/// Хранилище в одном файле базы SQLite, по умолчанию
+/// `<data>/avin.sqlite`. Базу заполняет из parquet файлов команда
+/// "avin-data db sync", к ней можно делать произвольные SQL запросы:
+/// [`SqliteStorage::query`] или "avin-data db query".
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    path: PathBuf,
+}
+impl SqliteStorage {
+    /// Create storage with database file path.
+    ///
+    /// # ru
+    /// Создает хранилище, файл базы создается при первом обращении.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+    /// Return default database path: data dir / avin.sqlite.
+    ///
+    /// # ru
+    /// Возвращает путь к базе по умолчанию: `<data>/avin.sqlite`.
+    pub fn default_path() -> PathBuf {
+        let mut path = CFG.dir.data();
+        path.push("avin.sqlite");
+
+        path
+    }
+    /// Return database file path.
+    ///
+    /// # ru
+    /// Возвращает путь к файлу базы.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save bars, bars with same time are replaced.
+    ///
+    /// # ru
+    /// Сохраняет бары, бары с тем же временем заменяются.
+    pub fn save_bars(
+        &self,
+        iid: &Iid,
+        md: MarketData,
+        df: &DataFrame,
+    ) -> Result<(), AvinError> {
+        let key = iid_key(iid);
+        let ts = i64_col(df, "ts_nanos")?;
+        let o = f64_col(df, "open")?;
+        let h = f64_col(df, "high")?;
+        let l = f64_col(df, "low")?;
+        let c = f64_col(df, "close")?;
+        let v = i64_col(df, "volume")?;
+        let val = opt_f64_col(df, "value")?;
+
+        let mut conn = self.open()?;
+        let tx = conn.transaction().map_err(db_err)?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO bars VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                )
+                .map_err(db_err)?;
+            for i in 0..ts.len() {
+                stmt.execute(params![
+                    key,
+                    md.name(),
+                    ts[i],
+                    o[i],
+                    h[i],
+                    l[i],
+                    c[i],
+                    v[i],
+                    val[i]
+                ])
+                .map_err(db_err)?;
+            }
+        }
+        tx.commit().map_err(db_err)
+    }
+    /// Execute SQL query and return result as dataframe.
+    ///
+    /// # ru
+    /// Выполняет SQL запрос и возвращает результат как датафрейм.
+    /// Типы колонок определяются по значениям: INTEGER - i64,
+    /// REAL - f64, TEXT - str.
+    ///
+    /// ## Examples
+    /// ```no_run
+    /// use avin_core::SqliteStorage;
+    ///
+    /// let db = SqliteStorage::new(&SqliteStorage::default_path());
+    /// let df = db
+    ///     .query("SELECT iid, COUNT(*) AS n FROM bars GROUP BY iid")
+    ///     .unwrap();
+    /// println!("{df}");
+    /// ```
+    pub fn query(&self, sql: &str) -> Result<DataFrame, AvinError> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare(sql).map_err(db_err)?;
+        let names: Vec<String> =
+            stmt.column_names().iter().map(|i| i.to_string()).collect();
+
+        let mut values: Vec<Vec<AnyValue>> = vec![Vec::new(); names.len()];
+        let mut rows = stmt.query([]).map_err(db_err)?;
+        while let Some(row) = rows.next().map_err(db_err)? {
+            for (i, column) in values.iter_mut().enumerate() {
+                let value = match row.get_ref(i).map_err(db_err)? {
+                    ValueRef::Null => AnyValue::Null,
+                    ValueRef::Integer(x) => AnyValue::Int64(x),
+                    ValueRef::Real(x) => AnyValue::Float64(x),
+                    ValueRef::Text(x) => AnyValue::StringOwned(
+                        String::from_utf8_lossy(x).as_ref().into(),
+                    ),
+                    ValueRef::Blob(x) => AnyValue::BinaryOwned(x.to_vec()),
+                };
+                column.push(value);
+            }
+        }
+
+        let mut columns = Vec::with_capacity(names.len());
+        for (name, column) in names.iter().zip(values.iter()) {
+            let s = Series::from_any_values(name.into(), column, false)
+                .map_err(|e| AvinError::InvalidValue(e.to_string()))?;
+            columns.push(Column::from(s));
+        }
+
+        DataFrame::new(columns)
+            .map_err(|e| AvinError::InvalidValue(e.to_string()))
+    }
+
+    // private
+    fn open(&self) -> Result<Connection, AvinError> {
+        let conn = Connection::open(&self.path).map_err(db_err)?;
+        conn.execute_batch(SCHEMA_SQL).map_err(db_err)?;
+
+        Ok(conn)
+    }
+}
+impl Storage for SqliteStorage {
+    fn load_bars(
+        &self,
+        iid: &Iid,
+        md: MarketData,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError> {
+        let conn = self.open()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT ts_nanos, open, high, low, close, volume, value
+                FROM bars
+                WHERE iid = ?1 AND market_data = ?2
+                    AND ts_nanos >= ?3 AND ts_nanos < ?4
+                ORDER BY ts_nanos",
+            )
+            .map_err(db_err)?;
+
+        let (b, e) = nanos_range(begin, end);
+        let mut ts = Vec::new();
+        let mut o = Vec::new();
+        let mut h = Vec::new();
+        let mut l = Vec::new();
+        let mut c = Vec::new();
+        let mut v = Vec::new();
+        let mut val: Vec<Option<f64>> = Vec::new();
+        let mut rows = stmt
+            .query(params![iid_key(iid), md.name(), b, e])
+            .map_err(db_err)?;
+        while let Some(row) = rows.next().map_err(db_err)? {
+            ts.push(row.get::<_, i64>(0).map_err(db_err)?);
+            o.push(row.get::<_, f64>(1).map_err(db_err)?);
+            h.push(row.get::<_, f64>(2).map_err(db_err)?);
+            l.push(row.get::<_, f64>(3).map_err(db_err)?);
+            c.push(row.get::<_, f64>(4).map_err(db_err)?);
+            v.push(row.get::<_, i64>(5).map_err(db_err)?);
+            val.push(row.get(6).map_err(db_err)?);
+        }
+        if ts.is_empty() {
+            let msg = format!("market data {md} for {iid}");
+            return Err(AvinError::NotFound(msg));
+        }
+
+        df!(
+            "ts_nanos" => ts,
+            "open" => o,
+            "high" => h,
+            "low" => l,
+            "close" => c,
+            "volume" => v,
+            "value" => val,
+        )
+        .map_err(|e| AvinError::InvalidValue(e.to_string()))
+    }
+    fn load_tics(
+        &self,
+        iid: &Iid,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError> {
+        let conn = self.open()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT ts_nanos, direction, lots, price, value, session,
+                    tradeno
+                FROM tics
+                WHERE iid = ?1 AND ts_nanos >= ?2 AND ts_nanos < ?3
+                ORDER BY ts_nanos, rowid",
+            )
+            .map_err(db_err)?;
+
+        let (b, e) = nanos_range(begin, end);
+        let mut ts = Vec::new();
+        let mut direction = Vec::new();
+        let mut lots = Vec::new();
+        let mut price = Vec::new();
+        let mut value: Vec<Option<f64>> = Vec::new();
+        let mut session: Vec<Option<i8>> = Vec::new();
+        let mut tradeno: Vec<Option<i64>> = Vec::new();
+        let mut rows =
+            stmt.query(params![iid_key(iid), b, e]).map_err(db_err)?;
+        while let Some(row) = rows.next().map_err(db_err)? {
+            ts.push(row.get::<_, i64>(0).map_err(db_err)?);
+            direction.push(row.get::<_, String>(1).map_err(db_err)?);
+            lots.push(row.get::<_, i64>(2).map_err(db_err)?);
+            price.push(row.get::<_, f64>(3).map_err(db_err)?);
+            value.push(row.get(4).map_err(db_err)?);
+            session.push(row.get(5).map_err(db_err)?);
+            tradeno.push(row.get(6).map_err(db_err)?);
+        }
+        if ts.is_empty() {
+            let msg = format!("market data {} for {iid}", MarketData::TIC);
+            return Err(AvinError::NotFound(msg));
+        }
+
+        let df = df!(
+            "ts_nanos" => ts,
+            "direction" => direction,
+            "lots" => lots,
+            "price" => price,
+            "value" => value,
+            "session" => session,
+            "tradeno" => tradeno,
+        )
+        .map_err(|e| AvinError::InvalidValue(e.to_string()))?;
+        debug_assert_eq!(df.schema().as_ref(), &DataSchema::tic());
+
+        Ok(df)
+    }
+    fn save_tics(&self, iid: &Iid, df: DataFrame) -> Result<(), AvinError> {
+        if df.is_empty() {
+            return Ok(());
+        }
+
+        let key = iid_key(iid);
+        let ts = i64_col(&df, "ts_nanos")?;
+        let lots = i64_col(&df, "lots")?;
+        let price = f64_col(&df, "price")?;
+        let value = opt_f64_col(&df, "value")?;
+        let err = |e: PolarsError| AvinError::InvalidValue(e.to_string());
+        let direction: Vec<String> = df
+            .column("direction")
+            .map_err(err)?
+            .str()
+            .map_err(err)?
+            .into_no_null_iter()
+            .map(|i| i.to_string())
+            .collect();
+        let session: Vec<Option<i8>> = df
+            .column("session")
+            .map_err(err)?
+            .cast(&DataType::Int8)
+            .map_err(err)?
+            .i8()
+            .map_err(err)?
+            .into_iter()
+            .collect();
+        let tradeno: Vec<Option<i64>> = df
+            .column("tradeno")
+            .map_err(err)?
+            .i64()
+            .map_err(err)?
+            .into_iter()
+            .collect();
+
+        // тики в интервале времени df заменяют сохраненные
+        let first = *ts.iter().min().unwrap();
+        let last = *ts.iter().max().unwrap();
+        let mut conn = self.open()?;
+        let tx = conn.transaction().map_err(db_err)?;
+        tx.execute(
+            "DELETE FROM tics
+            WHERE iid = ?1 AND ts_nanos >= ?2 AND ts_nanos <= ?3",
+            params![key, first, last],
+        )
+        .map_err(db_err)?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT INTO tics VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                )
+                .map_err(db_err)?;
+            for i in 0..ts.len() {
+                stmt.execute(params![
+                    key,
+                    ts[i],
+                    direction[i],
+                    lots[i],
+                    price[i],
+                    value[i],
+                    session[i],
+                    tradeno[i]
+                ])
+                .map_err(db_err)?;
+            }
+        }
+        tx.commit().map_err(db_err)
+    }
+}
+
+fn iid_key(iid: &Iid) -> String {
+    iid.to_string().to_uppercase()
+}
+fn nanos_range(begin: DateTime<Utc>, end: DateTime<Utc>) -> (i64, i64) {
+    let b = begin.timestamp_nanos_opt().unwrap_or(0);
+    let e = end.timestamp_nanos_opt().unwrap_or(i64::MAX);
+
+    (b, e)
+}
+fn db_err(e: rusqlite::Error) -> AvinError {
+    AvinError::IOError(e.to_string())
+}
+fn i64_col(df: &DataFrame, name: &str) -> Result<Vec<i64>, AvinError> {
+    let err = |e: PolarsError| AvinError::InvalidValue(e.to_string());
+    let col = df.column(name).map_err(err)?.i64().map_err(err)?;
+
+    Ok(col.into_no_null_iter().collect())
+}
+fn f64_col(df: &DataFrame, name: &str) -> Result<Vec<f64>, AvinError> {
+    let err = |e: PolarsError| AvinError::InvalidValue(e.to_string());
+    let col = df.column(name).map_err(err)?.f64().map_err(err)?;
+
+    Ok(col.into_no_null_iter().collect())
+}
+fn opt_f64_col(
+    df: &DataFrame,
+    name: &str,
+) -> Result<Vec<Option<f64>>, AvinError> {
+    let err = |e: PolarsError| AvinError::InvalidValue(e.to_string());
+    let col = df.column(name).map_err(err)?.f64().map_err(err)?;
+
+    Ok(col.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::iid;
+
+    fn db(name: &str) -> SqliteStorage {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "avin_storage_{name}_{}.sqlite",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        SqliteStorage::new(&path)
+    }
+
+    #[test]
+    fn sqlite_bars() {
+        let db = db("bars");
+        let iid = iid();
+        let md = MarketData::BAR_1M;
+        let df = df!(
+            "ts_nanos" => [1_i64, 2, 3],
+            "open" => [1.0, 2.0, 3.0],
+            "high" => [1.0, 2.0, 3.0],
+            "low" => [1.0, 2.0, 3.0],
+            "close" => [1.0, 2.0, 3.0],
+            "volume" => [10_i64, 20, 30],
+            "value" => [10.0, 40.0, 90.0],
+        )
+        .unwrap();
+        db.save_bars(&iid, md, &df).unwrap();
+        // повторное сохранение заменяет бары
+        db.save_bars(&iid, md, &df).unwrap();
+
+        let begin = DateTime::from_timestamp_nanos(0);
+        let end = DateTime::from_timestamp_nanos(3);
+        let loaded = db.load_bars(&iid, md, begin, end).unwrap();
+        assert_eq!(loaded, df.head(Some(2)));
+
+        let other = db.load_bars(&iid, MarketData::BAR_1H, begin, end);
+        assert!(matches!(other, Err(AvinError::NotFound(_))));
+
+        let n = db.query("SELECT COUNT(*) AS n FROM bars").unwrap();
+        assert_eq!(n.column("n").unwrap().i64().unwrap().get(0), Some(3));
+
+        std::fs::remove_file(db.path()).unwrap();
+    }
+    #[test]
+    fn sqlite_tics() {
+        let db = db("tics");
+        let iid = iid();
+        let df = df!(
+            "ts_nanos" => [1_i64, 2, 2, 5],
+            "direction" => ["B", "S", "B", "S"],
+            "lots" => [1_i64, 2, 3, 4],
+            "price" => [10.0, 11.0, 12.0, 13.0],
+            "value" => [Some(100.0), Some(220.0), None, Some(520.0)],
+            "session" => [Some(1_i8), Some(1), Some(1), None],
+            "tradeno" => [Some(1_i64), Some(2), Some(3), None],
+        )
+        .unwrap();
+        db.save_tics(&iid, df.clone()).unwrap();
+
+        // новые тики заменяют тики в своем интервале [2, 2]
+        let new = df.slice(1, 1);
+        db.save_tics(&iid, new).unwrap();
+
+        let begin = DateTime::from_timestamp_nanos(0);
+        let end = DateTime::from_timestamp_nanos(10);
+        let loaded = db.load_tics(&iid, begin, end).unwrap();
+        let lots: Vec<i64> = loaded
+            .column("lots")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(lots, vec![1, 2, 4]);
+        assert_eq!(loaded.column("tradeno").unwrap().null_count(), 1);
+
+        std::fs::remove_file(db.path()).unwrap();
+    }
+}
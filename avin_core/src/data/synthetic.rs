@@ -0,0 +1,645 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use polars::prelude::*;
+
+use avin_utils::{
+    self as utils, AvinError, CFG, ContinuousCfg, SyntheticCfg,
+};
+
+use crate::{Bar, Category, Exchange, Iid, Manager, MarketData};
+
+use super::iid_cache::IidCache;
+
+const DAY_NANOS: i64 = 24 * 60 * 60 * 1_000_000_000;
+
+static REGISTRY: LazyLock<RwLock<HashMap<String, Synthetic>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// User-defined instrument: spread, index, basket.
+///
+/// # ru
+/// Синтетический инструмент, определенный пользователем: спред,
+/// индекс, корзина. Имеет собственный Iid и строится из нескольких
+/// инструментов (ног) с весами: цена = sum(вес * цена ноги).
+///
+/// Синтетические инструменты описываются в конфиге в секции
+/// `[[data.synthetic]]` или регистрируются из кода через
+/// [`crate::Manager::register_synthetic`]. После этого они доступны
+/// через [`crate::Manager::find_iid`] и [`crate::Manager::load`] как
+/// обычные инструменты, бары строятся на лету из баров ног.
+///
+/// Непрерывный фьючерс (секция `[[data.continuous]]`) - тоже
+/// синтетический инструмент, его ноги - контракты цепочки, а бары
+/// склеиваются по правилу перехода [`Roll`], см.
+/// [`Synthetic::build_continuous`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synthetic {
+    iid: Iid,
+    legs: Vec<(Iid, f64)>,
+    roll: Option<Roll>,
+}
+impl Synthetic {
+    /// Create synthetic instrument.
+    ///
+    /// # ru
+    /// Конструктор. Строка s - идентификатор в формате
+    /// "exchange_category_ticker", legs - ноги инструмента и их веса.
+    pub fn new(
+        s: &str,
+        name: &str,
+        legs: Vec<(Iid, f64)>,
+    ) -> Result<Self, AvinError> {
+        let parts: Vec<&str> = s.split('_').collect();
+        if parts.len() != 3 || legs.is_empty() {
+            return Err(AvinError::InvalidValue(s.to_string()));
+        };
+
+        // биржа и категория проверяются здесь, иначе опечатка в конфиге
+        // приведет к панике позже, при разборе Iid
+        let exchange = parts[0].to_uppercase();
+        if !Exchange::all().iter().any(|i| i.name() == exchange) {
+            let msg = format!("{s}: invalid exchange {}", parts[0]);
+            return Err(AvinError::InvalidValue(msg));
+        }
+        let category = parts[1].to_uppercase();
+        if !Category::all().iter().any(|i| i.name() == category) {
+            let msg = format!("{s}: invalid category {}", parts[1]);
+            return Err(AvinError::InvalidValue(msg));
+        }
+
+        // минимальный шаг цены - наименьший из шагов ног
+        let step = legs
+            .iter()
+            .map(|(iid, _)| iid.step())
+            .fold(f64::MAX, f64::min);
+
+        let ticker = parts[2].to_uppercase();
+        let mut info = HashMap::new();
+        info.insert("exchange".to_string(), exchange);
+        info.insert("category".to_string(), category);
+        info.insert("ticker".to_string(), ticker.clone());
+        info.insert("figi".to_string(), format!("SYNTHETIC_{ticker}"));
+        info.insert("name".to_string(), name.to_string());
+        info.insert("lot".to_string(), "1".to_string());
+        info.insert("step".to_string(), step.to_string());
+        info.insert("synthetic".to_string(), "true".to_string());
+
+        Ok(Self {
+            iid: Iid::new(info),
+            legs,
+            roll: None,
+        })
+    }
+    /// Create continuous futures contract.
+    ///
+    /// # ru
+    /// Создает непрерывный фьючерс из цепочки контрактов. Контракты
+    /// передаются вместе с датой экспирации, в порядке экспирации.
+    /// Переход на следующий контракт происходит за roll_days дней до
+    /// экспирации текущего, adjust - корректировка цен на переходах,
+    /// см. [`RollAdjust`].
+    pub fn continuous(
+        s: &str,
+        name: &str,
+        contracts: Vec<(Iid, DateTime<Utc>)>,
+        roll_days: u32,
+        adjust: RollAdjust,
+    ) -> Result<Self, AvinError> {
+        let expirations = contracts
+            .iter()
+            .map(|(_, dt)| dt.timestamp_nanos_opt().unwrap())
+            .collect::<Vec<_>>();
+        if expirations.windows(2).any(|w| w[0] >= w[1]) {
+            let msg = format!("{s}: contracts must be sorted by expiration");
+            return Err(AvinError::InvalidValue(msg));
+        }
+
+        let legs = contracts.into_iter().map(|(iid, _)| (iid, 1.0)).collect();
+        let mut synthetic = Self::new(s, name, legs)?;
+        synthetic.roll = Some(Roll {
+            expirations,
+            roll_days,
+            adjust,
+        });
+
+        Ok(synthetic)
+    }
+    /// Create synthetic instrument from config.
+    ///
+    /// # ru
+    /// Создает синтетический инструмент из описания в конфиге,
+    /// идентификаторы ног ищутся через кэш инструментов.
+    pub fn from_cfg(cfg: &SyntheticCfg) -> Result<Self, AvinError> {
+        let mut legs = Vec::with_capacity(cfg.legs.len());
+        for leg in cfg.legs.iter() {
+            let iid = IidCache::find_iid(&leg.iid)?;
+            legs.push((iid, leg.weight));
+        }
+
+        Self::new(&cfg.iid, &cfg.name, legs)
+    }
+    /// Create continuous futures contract from config.
+    ///
+    /// # ru
+    /// Создает непрерывный фьючерс из описания в конфиге, секция
+    /// `[[data.continuous]]`. Истекших контрактов нет в кэше
+    /// инструментов, поэтому их Iid строятся из конфига.
+    pub fn from_continuous_cfg(
+        cfg: &ContinuousCfg,
+    ) -> Result<Self, AvinError> {
+        let mut contracts = Vec::with_capacity(cfg.contracts.len());
+        for contract in cfg.contracts.iter() {
+            let iid = contract_iid(&contract.iid, cfg.step)?;
+            NaiveDate::parse_from_str(&contract.expiration, "%Y-%m-%d")
+                .map_err(|e| {
+                    let msg = format!("{}: {e}", contract.expiration);
+                    AvinError::InvalidValue(msg)
+                })?;
+            let expiration = utils::str_date_to_utc(&contract.expiration);
+            contracts.push((iid, expiration));
+        }
+        let adjust = RollAdjust::try_from(cfg.adjust.as_str())?;
+
+        Self::continuous(
+            &cfg.iid,
+            &cfg.name,
+            contracts,
+            cfg.roll_days,
+            adjust,
+        )
+    }
+
+    /// Return instrument id.
+    ///
+    /// # ru
+    /// Возвращает идентификатор синтетического инструмента.
+    pub fn iid(&self) -> &Iid {
+        &self.iid
+    }
+    /// Return legs of instrument.
+    ///
+    /// # ru
+    /// Возвращает ноги инструмента и их веса.
+    pub fn legs(&self) -> &Vec<(Iid, f64)> {
+        &self.legs
+    }
+    /// Return roll rule, only for continuous contract.
+    ///
+    /// # ru
+    /// Возвращает правило перехода между контрактами, есть только
+    /// у непрерывного фьючерса.
+    pub fn roll(&self) -> Option<&Roll> {
+        self.roll.as_ref()
+    }
+    /// Build bars from bars of legs.
+    ///
+    /// # ru
+    /// Строит бары синтетического инструмента из баров ног. Порядок
+    /// векторов баров должен совпадать с порядком ног. В результат
+    /// попадают только те моменты времени, для которых есть бар у
+    /// каждой ноги.
+    ///
+    /// Open и close считаются точно. High и low - оценка сверху и
+    /// снизу, так как экстремумы ног могли быть в разное время внутри
+    /// бара. Объем синтетического бара не определен и равен 0.
+    pub fn build_bars(&self, legs_bars: &[Vec<Bar>]) -> Vec<Bar> {
+        assert_eq!(legs_bars.len(), self.legs.len());
+
+        let mut indexes = vec![0; legs_bars.len()];
+        let mut bars = Vec::new();
+
+        'outer: loop {
+            // самое позднее время среди текущих баров ног
+            let mut ts = i64::MIN;
+            for (n, leg_bars) in legs_bars.iter().enumerate() {
+                let Some(bar) = leg_bars.get(indexes[n]) else {
+                    break 'outer;
+                };
+                ts = ts.max(bar.ts);
+            }
+
+            // подтягиваем остальные ноги к этому времени
+            let mut aligned = true;
+            for (n, leg_bars) in legs_bars.iter().enumerate() {
+                while leg_bars.get(indexes[n]).is_some_and(|b| b.ts < ts) {
+                    indexes[n] += 1;
+                }
+                match leg_bars.get(indexes[n]) {
+                    Some(bar) => aligned &= bar.ts == ts,
+                    None => break 'outer,
+                }
+            }
+            if !aligned {
+                continue;
+            }
+
+            let mut o = 0.0;
+            let mut h = 0.0;
+            let mut l = 0.0;
+            let mut c = 0.0;
+            for (n, (_iid, w)) in self.legs.iter().enumerate() {
+                let bar = &legs_bars[n][indexes[n]];
+                o += w * bar.o;
+                c += w * bar.c;
+                if *w >= 0.0 {
+                    h += w * bar.h;
+                    l += w * bar.l;
+                } else {
+                    h += w * bar.l;
+                    l += w * bar.h;
+                }
+                indexes[n] += 1;
+            }
+            bars.push(Bar::new(ts, o, h, l, c, 0));
+        }
+
+        bars
+    }
+    /// Build bars of continuous contract from bars of contracts.
+    ///
+    /// # ru
+    /// Строит бары непрерывного фьючерса: каждый контракт используется
+    /// от перехода с предыдущего до перехода на следующий. Цены более
+    /// ранних контрактов корректируются на разницу (или отношение)
+    /// цен закрытия контрактов в момент перехода, последний контракт
+    /// остается без изменений.
+    pub fn build_continuous(&self, legs_bars: &[Vec<Bar>]) -> Vec<Bar> {
+        assert_eq!(legs_bars.len(), self.legs.len());
+        let roll = self.roll.as_ref().expect("not continuous contract");
+
+        // отрезки контрактов [begin, end)
+        let n = legs_bars.len();
+        let days = roll.roll_days as i64 * DAY_NANOS;
+        let mut segments = Vec::with_capacity(n);
+        let mut begin = i64::MIN;
+        for (i, leg_bars) in legs_bars.iter().enumerate() {
+            let end = if i + 1 < n {
+                roll.expirations[i] - days
+            } else {
+                i64::MAX
+            };
+            let segment: Vec<Bar> = leg_bars
+                .iter()
+                .filter(|b| b.ts >= begin && b.ts < end)
+                .cloned()
+                .collect();
+            segments.push(segment);
+            begin = end;
+        }
+
+        // обратная корректировка, от последнего контракта к первому
+        let mut shift = 0.0;
+        let mut ratio = 1.0;
+        for i in (0..n).rev() {
+            for bar in segments[i].iter_mut() {
+                bar.o = bar.o * ratio + shift;
+                bar.h = bar.h * ratio + shift;
+                bar.l = bar.l * ratio + shift;
+                bar.c = bar.c * ratio + shift;
+            }
+            if i == 0 {
+                break;
+            }
+
+            // цены старого и нового контракта в момент перехода
+            let Some(last) = legs_bars[i - 1]
+                .iter()
+                .rfind(|b| b.ts < roll.expirations[i - 1] - days)
+            else {
+                continue;
+            };
+            let Some(next) = legs_bars[i].iter().rfind(|b| b.ts <= last.ts)
+            else {
+                continue;
+            };
+            match roll.adjust {
+                RollAdjust::None => {}
+                RollAdjust::Difference => shift += next.c - last.c,
+                RollAdjust::Ratio => ratio *= next.c / last.c,
+            }
+        }
+
+        segments.into_iter().flatten().collect()
+    }
+    /// Load bars of synthetic instrument.
+    ///
+    /// # ru
+    /// Загружает бары ног и строит из них бары синтетического
+    /// инструмента. Возвращает датафрейм в той же схеме, что и для
+    /// обычных баров.
+    pub fn load(
+        &self,
+        md: MarketData,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataFrame, AvinError> {
+        let mut legs_bars = Vec::with_capacity(self.legs.len());
+        for (iid, _w) in self.legs.iter() {
+            let df = match Manager::storage().load_bars(iid, md, begin, end) {
+                Ok(df) => df,
+                // контракт цепочки может не торговаться в этом периоде
+                Err(AvinError::NotFound(_)) if self.roll.is_some() => {
+                    legs_bars.push(Vec::new());
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let bars = Bar::from_df(&df).map_err(AvinError::InvalidValue)?;
+            legs_bars.push(bars);
+        }
+
+        let bars = match self.roll {
+            Some(_) => self.build_continuous(&legs_bars),
+            None => self.build_bars(&legs_bars),
+        };
+        if bars.is_empty() {
+            let msg = format!("market data {md} for {}", self.iid);
+            return Err(AvinError::NotFound(msg));
+        }
+
+        let df = df!(
+            "ts_nanos" => bars.iter().map(|b| b.ts).collect::<Vec<_>>(),
+            "open" => bars.iter().map(|b| b.o).collect::<Vec<_>>(),
+            "high" => bars.iter().map(|b| b.h).collect::<Vec<_>>(),
+            "low" => bars.iter().map(|b| b.l).collect::<Vec<_>>(),
+            "close" => bars.iter().map(|b| b.c).collect::<Vec<_>>(),
+            "volume" => bars.iter().map(|b| b.v as i64).collect::<Vec<_>>(),
+            "value" => bars.iter().map(|_| 0.0).collect::<Vec<f64>>(),
+        )
+        .unwrap();
+
+        Ok(df)
+    }
+
+    /// Register synthetic instrument.
+    ///
+    /// # ru
+    /// Регистрирует синтетический инструмент, если инструмент с таким
+    /// идентификатором уже есть - он будет заменен.
+    pub fn register(synthetic: Synthetic) {
+        let key = synthetic.iid.to_string().to_uppercase();
+        REGISTRY.write().unwrap().insert(key, synthetic);
+    }
+    /// Find registered or configured synthetic instrument.
+    ///
+    /// # ru
+    /// Ищет синтетический инструмент среди зарегистрированных, а затем
+    /// среди описанных в конфиге. Найденный в конфиге инструмент
+    /// регистрируется, чтобы не собирать его повторно.
+    pub fn find(s: &str) -> Option<Synthetic> {
+        let key = s.to_uppercase();
+        if let Some(synthetic) = REGISTRY.read().unwrap().get(&key) {
+            return Some(synthetic.clone());
+        }
+
+        let result = if let Some(cfg) = CFG
+            .data
+            .synthetic
+            .iter()
+            .find(|i| i.iid.to_uppercase() == key)
+        {
+            Self::from_cfg(cfg)
+        } else {
+            let cfg = CFG
+                .data
+                .continuous
+                .iter()
+                .find(|i| i.iid.to_uppercase() == key)?;
+            Self::from_continuous_cfg(cfg)
+        };
+        match result {
+            Ok(synthetic) => {
+                Self::register(synthetic.clone());
+                Some(synthetic)
+            }
+            Err(e) => {
+                log::error!("Invalid synthetic instrument {key}: {e}");
+                None
+            }
+        }
+    }
+    /// Return all registered and configured synthetic instruments.
+    ///
+    /// # ru
+    /// Возвращает все синтетические инструменты: зарегистрированные
+    /// и описанные в конфиге.
+    pub fn all() -> Vec<Synthetic> {
+        let configured = CFG
+            .data
+            .synthetic
+            .iter()
+            .map(|i| i.iid.clone())
+            .chain(CFG.data.continuous.iter().map(|i| i.iid.clone()));
+        for key in configured {
+            Self::find(&key);
+        }
+
+        let mut all: Vec<Synthetic> =
+            REGISTRY.read().unwrap().values().cloned().collect();
+        all.sort_by_key(|i| i.iid.to_string());
+
+        all
+    }
+}
+
+fn contract_iid(s: &str, step: f64) -> Result<Iid, AvinError> {
+    let parts: Vec<&str> = s.split('_').collect();
+    if parts.len() != 3 {
+        return Err(AvinError::InvalidValue(s.to_string()));
+    };
+
+    let ticker = parts[2].to_uppercase();
+    let mut info = HashMap::new();
+    info.insert("exchange".to_string(), parts[0].to_uppercase());
+    info.insert("category".to_string(), parts[1].to_uppercase());
+    info.insert("ticker".to_string(), ticker.clone());
+    info.insert("figi".to_string(), format!("CONTRACT_{ticker}"));
+    info.insert("name".to_string(), ticker);
+    info.insert("lot".to_string(), "1".to_string());
+    info.insert("step".to_string(), step.to_string());
+
+    Ok(Iid::new(info))
+}
+
+/// Price adjustment on roll of continuous contract.
+///
+/// # ru
+/// Корректировка цен при переходе на следующий контракт:
+/// - None - без корректировки, на переходах будут гэпы;
+/// - Difference - прибавляется разница цен контрактов;
+/// - Ratio - умножается на отношение цен контрактов.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollAdjust {
+    None,
+    Difference,
+    Ratio,
+}
+impl TryFrom<&str> for RollAdjust {
+    type Error = AvinError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        // пустая строка в конфиге - без корректировки
+        match value.to_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "difference" => Ok(Self::Difference),
+            "ratio" => Ok(Self::Ratio),
+            _ => Err(AvinError::InvalidValue(format!(
+                "Invalid roll adjust: {value}"
+            ))),
+        }
+    }
+}
+
+/// Roll rule of continuous contract.
+///
+/// # ru
+/// Правило перехода между контрактами непрерывного фьючерса.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roll {
+    expirations: Vec<i64>,
+    roll_days: u32,
+    adjust: RollAdjust,
+}
+impl Roll {
+    /// Return days before expiration to roll.
+    ///
+    /// # ru
+    /// Возвращает за сколько дней до экспирации происходит переход.
+    pub fn roll_days(&self) -> u32 {
+        self.roll_days
+    }
+    /// Return price adjustment on roll.
+    ///
+    /// # ru
+    /// Возвращает способ корректировки цен на переходах.
+    pub fn adjust(&self) -> RollAdjust {
+        self.adjust
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn iid(ticker: &str, step: &str) -> Iid {
+        let figi = format!("FIGI_{ticker}");
+        let fields = [("ticker", ticker), ("figi", &figi), ("step", step)];
+
+        fixture::iid_with(&fields)
+    }
+
+    #[test]
+    fn new() {
+        let legs =
+            vec![(iid("SBER", "0.01"), 1.0), (iid("SBERP", "0.1"), -1.0)];
+        let s = Synthetic::new("moex_index_sberspread", "Spread", legs);
+        let s = s.unwrap();
+
+        assert_eq!(s.iid().to_string(), "MOEX_INDEX_SBERSPREAD");
+        assert_eq!(s.iid().figi(), "SYNTHETIC_SBERSPREAD");
+        assert_eq!(s.iid().step(), 0.01);
+        assert_eq!(s.legs().len(), 2);
+
+        assert!(Synthetic::new("sberspread", "Spread", Vec::new()).is_err());
+
+        // опечатка в бирже или категории
+        let legs = vec![(iid("SBER", "0.01"), 1.0)];
+        let s = Synthetic::new("moxe_index_spread", "Spread", legs.clone());
+        assert!(matches!(s, Err(AvinError::InvalidValue(_))));
+        let s = Synthetic::new("moex_indx_spread", "Spread", legs);
+        assert!(matches!(s, Err(AvinError::InvalidValue(_))));
+    }
+    #[test]
+    fn build_bars() {
+        let legs =
+            vec![(iid("SBER", "0.01"), 1.0), (iid("SBERP", "0.01"), -1.0)];
+        let s = Synthetic::new("moex_index_sberspread", "Spread", legs);
+        let s = s.unwrap();
+
+        let sber = vec![
+            Bar::new(1, 300.0, 310.0, 295.0, 305.0, 10),
+            Bar::new(2, 305.0, 306.0, 300.0, 301.0, 10),
+            Bar::new(3, 301.0, 302.0, 299.0, 300.0, 10),
+        ];
+        let sberp = vec![
+            Bar::new(1, 290.0, 296.0, 288.0, 292.0, 10),
+            Bar::new(3, 292.0, 293.0, 290.0, 291.0, 10),
+        ];
+        let bars = s.build_bars(&[sber, sberp]);
+
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0], Bar::new(1, 10.0, 22.0, -1.0, 13.0, 0));
+        assert_eq!(bars[1], Bar::new(3, 9.0, 12.0, 6.0, 9.0, 0));
+    }
+    #[test]
+    fn build_continuous() {
+        let day = DAY_NANOS;
+        let contracts = vec![
+            (iid("SIH5", "1"), DateTime::from_timestamp_nanos(10 * day)),
+            (iid("SIM5", "1"), DateTime::from_timestamp_nanos(20 * day)),
+        ];
+        let s = Synthetic::continuous(
+            "moex_future_sicont",
+            "Si",
+            contracts,
+            2,
+            RollAdjust::Difference,
+        )
+        .unwrap();
+        assert_eq!(s.roll().unwrap().roll_days(), 2);
+
+        // переход за 2 дня до экспирации: с 8 дня бары SIM5
+        let near = vec![
+            Bar::new(6 * day, 100.0, 100.0, 100.0, 100.0, 1),
+            Bar::new(7 * day, 101.0, 101.0, 101.0, 101.0, 1),
+            Bar::new(8 * day, 102.0, 102.0, 102.0, 102.0, 1),
+        ];
+        let far = vec![
+            Bar::new(7 * day, 111.0, 111.0, 111.0, 111.0, 5),
+            Bar::new(8 * day, 112.0, 112.0, 112.0, 112.0, 5),
+        ];
+        let bars = s.build_continuous(&[near.clone(), far.clone()]);
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[0], Bar::new(6 * day, 110.0, 110.0, 110.0, 110.0, 1));
+        assert_eq!(bars[1], Bar::new(7 * day, 111.0, 111.0, 111.0, 111.0, 1));
+        assert_eq!(bars[2], far[1]);
+
+        // без корректировки - цены как есть
+        let contracts = vec![
+            (iid("SIH5", "1"), DateTime::from_timestamp_nanos(10 * day)),
+            (iid("SIM5", "1"), DateTime::from_timestamp_nanos(20 * day)),
+        ];
+        let s = Synthetic::continuous(
+            "moex_future_sicont",
+            "Si",
+            contracts,
+            2,
+            RollAdjust::None,
+        )
+        .unwrap();
+        let bars = s.build_continuous(&[near.clone(), far]);
+        assert_eq!(bars[1], near[1]);
+
+        assert!(RollAdjust::try_from("ratio").is_ok());
+        assert!(RollAdjust::try_from("xxx").is_err());
+    }
+    #[test]
+    fn register() {
+        let legs =
+            vec![(iid("GAZP", "0.01"), 0.5), (iid("LKOH", "0.5"), 0.5)];
+        let s = Synthetic::new("moex_index_basket", "Basket", legs).unwrap();
+        Synthetic::register(s.clone());
+
+        assert_eq!(Synthetic::find("moex_index_basket"), Some(s));
+    }
+}
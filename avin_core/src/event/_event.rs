@@ -5,18 +5,19 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
-use super::{BarEvent, OrderEvent, TicEvent};
+use super::{BarEvent, BookEvent, OrderEvent, TicEvent};
 
 /// Market events, that is sending from broker to trader/tester/terminal.
 ///
 /// # ru
-/// Рыночные события: новый бар, новый тик, ордер исполнен,
+/// Рыночные события: новый бар, новый тик, стакан, ордер исполнен,
 /// ордер отклонен и тп. Передаются от брокера трейдеру, тестеру или в
 /// терминал.
 #[derive(Debug, Clone)]
 pub enum Event {
     Bar(BarEvent),
     Tic(TicEvent),
+    Book(BookEvent),
     Order(OrderEvent),
 }
 impl Event {
@@ -29,6 +30,7 @@ impl Event {
         match self {
             Self::Bar(e) => &e.figi,
             Self::Tic(e) => &e.figi,
+            Self::Book(e) => &e.figi,
             Self::Order(e) => e.iid.figi(),
         }
     }
@@ -38,6 +40,7 @@ impl std::fmt::Display for Event {
         match self {
             Event::Bar(e) => write!(f, "Event={e}"),
             Event::Tic(e) => write!(f, "Event={e}"),
+            Event::Book(e) => write!(f, "Event={e}"),
             Event::Order(e) => write!(f, "Event={e}"),
         }
     }
@@ -0,0 +1,30 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use crate::OrderBook;
+
+/// That event sending from broker on every order book update.
+///
+/// # ru
+/// Это событие отправляется брокером при каждом обновлении стакана.
+///
+/// Содержит FIGI инструмента и снимок стакана.
+#[derive(Debug, Clone)]
+pub struct BookEvent {
+    pub figi: String,
+    pub book: OrderBook,
+}
+impl BookEvent {
+    pub fn new(figi: String, book: OrderBook) -> Self {
+        Self { figi, book }
+    }
+}
+impl std::fmt::Display for BookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "BookEvent={} {}", self.figi, self.book)
+    }
+}
@@ -7,10 +7,12 @@
 
 mod _event;
 mod bar_event;
+mod book_event;
 mod order_event;
 mod tic_event;
 
 pub use _event::Event;
 pub use bar_event::BarEvent;
+pub use book_event::BookEvent;
 pub use order_event::OrderEvent;
 pub use tic_event::TicEvent;
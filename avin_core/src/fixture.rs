@@ -0,0 +1,65 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Test fixtures shared by the crates of workspace.
+//!
+//! # ru
+//! Данные для тестов: идентификатор инструмента без кэша инструментов и
+//! событие бара по нему. Доступны в тестах avin_core, остальные крейты
+//! подключают их через feature `test-fixtures` в dev-dependencies.
+
+use std::collections::HashMap;
+
+use crate::{Bar, BarEvent, Event, Iid, TimeFrame};
+
+/// Share SBER: MOEX, lot 10, step 0.01.
+///
+/// # ru
+/// Акция Сбербанка: биржа MOEX, лот 10, шаг цены 0.01.
+pub fn iid() -> Iid {
+    iid_with(&[])
+}
+/// Share with other ticker and figi, other fields as [`iid`].
+///
+/// # ru
+/// Акция с другим тикером и figi, остальные поля как у [`iid`]. Для
+/// тестов с несколькими инструментами.
+pub fn share(ticker: &str, figi: &str) -> Iid {
+    iid_with(&[("ticker", ticker), ("figi", figi), ("name", ticker)])
+}
+/// SBER with replaced or additional fields of instrument info.
+///
+/// # ru
+/// Сбербанк с замененными или дополнительными полями информации об
+/// инструменте, например лот, шаг цены, ставки риска или дата
+/// делистинга.
+pub fn iid_with(fields: &[(&str, &str)]) -> Iid {
+    let mut info = HashMap::new();
+    for (k, v) in [
+        ("exchange", "MOEX"),
+        ("category", "SHARE"),
+        ("ticker", "SBER"),
+        ("figi", "BBG004730N88"),
+        ("name", "Сбер"),
+        ("lot", "10"),
+        ("step", "0.01"),
+    ]
+    .iter()
+    .chain(fields)
+    {
+        info.insert(k.to_string(), v.to_string());
+    }
+
+    Iid::new(info)
+}
+/// Event of 1M bar by [`iid`].
+///
+/// # ru
+/// Событие минутного бара по инструменту [`iid`].
+pub fn bar_event(bar: Bar) -> Event {
+    Event::Bar(BarEvent::new(iid().figi().clone(), TimeFrame::M1, bar))
+}
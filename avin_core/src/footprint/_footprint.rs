@@ -9,7 +9,7 @@ use polars::frame::DataFrame;
 
 use avin_utils as utils;
 
-use crate::{Cluster, Iid, Tic, TimeFrame};
+use crate::{Bar, Cluster, Iid, Tic, TimeFrame};
 
 /// Aggregation of instrument id, timeframe and clusters.
 ///
@@ -63,6 +63,30 @@ impl Footprint {
     pub fn now(&self) -> Option<&Cluster> {
         self.now.as_ref()
     }
+    /// Get cluster of bar.
+    ///
+    /// # ru
+    /// Возвращает ссылку на кластер, рассчитанный по тикам того же
+    /// периода, что и бар, или None если тиков за этот период нет.
+    /// Таймфрейм бара должен совпадать с таймфреймом кластерного графика.
+    pub fn cluster(&self, bar: &Bar) -> Option<&Cluster> {
+        self.get_cluster_of_ts(bar.ts)
+    }
+    /// Get cluster with this timestamp.
+    ///
+    /// # ru
+    /// Возвращает ссылку на кластер с заданным timestamp или None,
+    /// если такой отсутствует.
+    pub fn get_cluster_of_ts(&self, ts: i64) -> Option<&Cluster> {
+        let index = utils::bisect_left(&self.clusters, ts, |c| c.ts)?;
+        let cluster = &self.clusters[index];
+
+        if cluster.ts == ts {
+            Some(cluster)
+        } else {
+            None
+        }
+    }
     pub fn df(&self) -> DataFrame {
         assert!(!self.clusters.is_empty());
 
@@ -120,7 +144,35 @@ impl AsRef<Footprint> for Footprint {
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
-
-    // TODO: test it
+    use super::*;
+    use crate::Direction;
+    use crate::fixture::iid;
+
+    #[test]
+    fn cluster_of_bar() {
+        let iid = iid();
+
+        let min = 60_000_000_000;
+        let tics = vec![
+            Tic::new(min, Direction::Buy, 5, 100.0, 5000.0),
+            Tic::new(min + 10, Direction::Sell, 2, 100.1, 2002.0),
+            Tic::new(min + 20, Direction::Buy, 1, 100.1, 1001.0),
+            Tic::new(3 * min, Direction::Sell, 4, 99.9, 3996.0),
+        ];
+        let fp = Footprint::from_tics(&iid, TimeFrame::M1, &tics);
+        assert_eq!(fp.clusters().len(), 2);
+
+        let bar = Bar::new(min, 100.0, 100.1, 100.0, 100.1, 8);
+        let cluster = fp.cluster(&bar).unwrap();
+        assert_eq!(cluster.vol, 8);
+        assert_eq!(cluster.delta(), 4);
+        assert_eq!(cluster.quantum.quants().len(), 2);
+
+        // no tics in this minute
+        let bar = Bar::new(2 * min, 100.0, 100.1, 100.0, 100.1, 8);
+        assert!(fp.cluster(&bar).is_none());
+
+        let cluster = fp.get_cluster_of_ts(3 * min).unwrap();
+        assert_eq!(cluster.delta(), -4);
+    }
 }
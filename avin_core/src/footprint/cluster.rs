@@ -158,6 +158,13 @@ impl Cluster {
         .unwrap()
     }
 
+    /// Volume delta: buy volume minus sell volume.
+    ///
+    /// # ru
+    /// Дельта объема: объем покупок минус объем продаж (в лотах).
+    pub fn delta(&self) -> i64 {
+        self.vol_b as i64 - self.vol_s as i64
+    }
     pub fn dt(&self) -> DateTime<Utc> {
         DateTime::from_timestamp_nanos(self.ts)
     }
@@ -7,12 +7,14 @@
 
 mod _footprint;
 mod cluster;
+mod order_book;
 mod quant;
 mod quantum;
 mod tic;
 
 pub use _footprint::Footprint;
 pub use cluster::Cluster;
+pub use order_book::OrderBook;
 pub use quant::Quant;
 pub use quantum::Quantum;
 pub use tic::Tic;
@@ -0,0 +1,86 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use crate::Direction;
+
+/// Snapshot of order book: price levels and resting lots.
+///
+/// # ru
+/// Снимок биржевого стакана: ценовые уровни и количество лотов в
+/// заявках на каждом уровне.
+///
+/// Уровни отсортированы от лучшей цены: bids - заявки на покупку по
+/// убыванию цены, asks - заявки на продажу по возрастанию.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OrderBook {
+    pub ts: i64,
+    pub bids: Vec<(f64, u32)>,
+    pub asks: Vec<(f64, u32)>,
+}
+impl OrderBook {
+    pub fn new(
+        ts: i64,
+        bids: Vec<(f64, u32)>,
+        asks: Vec<(f64, u32)>,
+    ) -> Self {
+        Self { ts, bids, asks }
+    }
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.first().map(|(price, _)| *price)
+    }
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.first().map(|(price, _)| *price)
+    }
+    /// Lots resting at price on the side of order direction.
+    ///
+    /// # ru
+    /// Количество лотов в заявках на уровне price на стороне стакана
+    /// ордера с направлением direction: для покупки - bids, для
+    /// продажи - asks. Если уровня в стакане нет - 0.
+    pub fn lots(&self, direction: &Direction, price: f64) -> u32 {
+        let levels = match direction {
+            Direction::Buy => &self.bids,
+            Direction::Sell => &self.asks,
+        };
+
+        levels
+            .iter()
+            .find(|(p, _)| *p == price)
+            .map(|(_, lots)| *lots)
+            .unwrap_or(0)
+    }
+}
+impl std::fmt::Display for OrderBook {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "OrderBook={} bid={:?} ask={:?}",
+            self.ts,
+            self.best_bid(),
+            self.best_ask()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lots() {
+        let book = OrderBook::new(
+            0,
+            vec![(100.0, 5), (99.9, 20)],
+            vec![(100.1, 7), (100.2, 30)],
+        );
+        assert_eq!(book.best_bid(), Some(100.0));
+        assert_eq!(book.best_ask(), Some(100.1));
+        assert_eq!(book.lots(&Direction::Buy, 99.9), 20);
+        assert_eq!(book.lots(&Direction::Sell, 100.2), 30);
+        assert_eq!(book.lots(&Direction::Buy, 100.2), 0);
+    }
+}
@@ -51,6 +51,9 @@ impl Quant {
     pub fn val(&self) -> f64 {
         self.val_b + self.val_s
     }
+    pub fn delta(&self) -> i64 {
+        self.vol_b as i64 - self.vol_s as i64
+    }
     pub fn cdf_b(&self) -> Option<f64> {
         self.cdf_b
     }
@@ -83,6 +86,7 @@ mod tests {
         assert_eq!(quant.val_b, 320.5);
         assert_eq!(quant.val_s, 641.0);
         assert_eq!(quant.vol(), 3);
+        assert_eq!(quant.delta(), -1);
         assert_eq!(quant.val(), 320.5 + 641.0);
 
         quant.add(&b);
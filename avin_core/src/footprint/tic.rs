@@ -7,7 +7,7 @@
 
 use bitcode::{Decode, Encode};
 use chrono::{DateTime, Local, NaiveDateTime, Utc};
-use polars::frame::DataFrame;
+use polars::prelude::{DataFrame, NamedFrom, Series};
 
 use crate::Direction;
 
@@ -93,6 +93,35 @@ impl Tic {
         Ok(tics)
     }
 
+    /// Create DataFrame from tics, for saving.
+    ///
+    /// # ru
+    /// Создает датафрейм из тиков в формате хранения тиковых данных.
+    /// Колонки session и tradeno у тиков из потока неизвестны, и
+    /// заполняются null.
+    pub fn to_df(tics: &[Tic]) -> DataFrame {
+        let n = tics.len();
+        let ts: Vec<i64> = tics.iter().map(|t| t.ts).collect();
+        let direction: Vec<&str> =
+            tics.iter().map(|t| t.direction.to_str()).collect();
+        let lots: Vec<i64> = tics.iter().map(|t| t.lots as i64).collect();
+        let price: Vec<f64> = tics.iter().map(|t| t.price).collect();
+        let value: Vec<f64> = tics.iter().map(|t| t.value).collect();
+        let session: Vec<Option<i8>> = vec![None; n];
+        let tradeno: Vec<Option<i64>> = vec![None; n];
+
+        DataFrame::new(vec![
+            Series::new("ts_nanos".into(), ts).into(),
+            Series::new("direction".into(), direction).into(),
+            Series::new("lots".into(), lots).into(),
+            Series::new("price".into(), price).into(),
+            Series::new("value".into(), value).into(),
+            Series::new("session".into(), session).into(),
+            Series::new("tradeno".into(), tradeno).into(),
+        ])
+        .unwrap()
+    }
+
     pub fn dt(&self) -> DateTime<Utc> {
         DateTime::from_timestamp_nanos(self.ts)
     }
@@ -138,4 +167,15 @@ mod tests {
         assert!(tic.is_buy());
         assert!(!tic.is_sell());
     }
+    #[test]
+    fn df() {
+        let tics = vec![
+            Tic::new(100500, Direction::Buy, 10, 300.0, 3000.0),
+            Tic::new(100501, Direction::Sell, 2, 299.9, 599.8),
+        ];
+
+        let df = Tic::to_df(&tics);
+        assert_eq!(df.height(), 2);
+        assert_eq!(Tic::from_df(&df).unwrap(), tics);
+    }
 }
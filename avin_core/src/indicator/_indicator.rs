@@ -5,7 +5,9 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
-use crate::Bar;
+use polars::frame::DataFrame;
+
+use crate::BarSlice;
 
 use super::extremum::ExtremumData;
 
@@ -28,9 +30,14 @@ impl Indicator {
             Self::Extremum(i) => i.name(),
         }
     }
-    pub fn update(&mut self, bars: &[Bar]) {
+    pub fn update(&mut self, bars: BarSlice) {
         match self {
             Self::Extremum(i) => i.update(bars),
         }
     }
+    pub fn to_df(&self, bars: BarSlice) -> DataFrame {
+        match self {
+            Self::Extremum(i) => i.to_df(bars),
+        }
+    }
 }
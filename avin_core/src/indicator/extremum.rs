@@ -6,9 +6,10 @@
  ****************************************************************************/
 
 use chrono::{DateTime, Local, NaiveDateTime, Utc};
-use strum::EnumIter;
+use polars::prelude::{Column, DataFrame, NamedFrom, Series};
+use strum::{EnumIter, IntoEnumIterator};
 
-use crate::{Bar, Chart};
+use crate::{BarSlice, Chart};
 use ExtremumKind::Max;
 use ExtremumKind::Min;
 use Term::T1;
@@ -19,7 +20,7 @@ use Term::T5;
 use TrendKind::{Bear, Bull};
 use avin_utils::{self as utils, bisect_left, bisect_right};
 
-use super::Indicator;
+use super::{Channel, Indicator, TrendLine};
 
 // random UUID, used as key in HashMap with indicators in struct Chart
 const ID: &str = "9479c78b-d54e-4042-8893-19f7a2a9ed53";
@@ -164,13 +165,10 @@ pub struct Trend {
     kind: TrendKind,
 }
 impl Trend {
-    pub fn new(e1: &Extremum, e2: &Extremum, bars: &[Bar]) -> Trend {
+    pub fn new(e1: &Extremum, e2: &Extremum, bars: BarSlice) -> Trend {
         assert!(e1.ts < e2.ts);
 
-        let mut vol = 0;
-        for bar in bars.iter() {
-            vol += bar.v;
-        }
+        let vol = bars.v.iter().sum();
 
         Trend {
             e1: e1.clone(),
@@ -256,19 +254,56 @@ impl std::fmt::Display for Trend {
     }
 }
 
+/// Minimum swing size for T1 extremums.
+///
+/// # ru
+/// Минимальный размер колебания цены между соседними экстремумами T1.
+/// Используется для фильтрации шума на "рваных" инструментах: если
+/// колебание меньше заданного, оно не создает новых экстремумов T1.
+/// Экстремумы старших периодов T2..T5 строятся уже из отфильтрованных T1.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SwingFilter {
+    /// Без фильтрации, экстремум на каждом колебании цены.
+    #[default]
+    Off,
+    /// Минимальное колебание в процентах от цены экстремума.
+    Percent(f64),
+    /// Минимальное колебание в шагах цены инструмента.
+    Steps(u32),
+    /// Минимальное колебание в ATR: период и множитель.
+    Atr(usize, f64),
+}
+
 // public interface for Chart
 pub trait ExtremumIndicator {
     fn init(&mut self);
+    fn init_with(&mut self, filter: SwingFilter);
+    /// Check indicator is added to chart.
+    ///
+    /// # ru
+    /// Проверяет, что индикатор добавлен на график, остальные методы
+    /// без индикатора паникуют.
+    fn has_extremum(&self) -> bool;
     fn extr(&self, term: Term, n: usize) -> Option<&Extremum>;
     fn trend(&self, term: Term, n: usize) -> Option<&Trend>;
     fn all_extr(&self, term: Term) -> &Vec<Extremum>;
     fn all_trend(&self, term: Term) -> &Vec<Trend>;
+    fn trend_line(&self, term: Term, kind: ExtremumKind)
+    -> Option<TrendLine>;
+    fn channel(&self, term: Term, kind: ExtremumKind) -> Option<Channel>;
 }
 impl ExtremumIndicator for Chart {
     fn init(&mut self) {
         let ind = ExtremumData::new(self);
         self.add_ind(Indicator::Extremum(ind));
     }
+    fn init_with(&mut self, filter: SwingFilter) {
+        let ind = ExtremumData::with_filter(self, filter);
+        self.add_ind(Indicator::Extremum(ind));
+    }
+    fn has_extremum(&self) -> bool {
+        self.get_ind(ID).is_some()
+    }
     fn extr(&self, term: Term, n: usize) -> Option<&Extremum> {
         // get indicator data
         let extr_data = match self.get_ind(ID) {
@@ -305,6 +340,28 @@ impl ExtremumIndicator for Chart {
 
         extr_data.all_trend(term)
     }
+    fn trend_line(
+        &self,
+        term: Term,
+        kind: ExtremumKind,
+    ) -> Option<TrendLine> {
+        // get indicator data
+        let extr_data = match self.get_ind(ID) {
+            Some(Indicator::Extremum(data)) => data,
+            None => panic!("Chart don't have indicator {NAME}"),
+        };
+
+        extr_data.trend_line(term, kind)
+    }
+    fn channel(&self, term: Term, kind: ExtremumKind) -> Option<Channel> {
+        // get indicator data
+        let extr_data = match self.get_ind(ID) {
+            Some(Indicator::Extremum(data)) => data,
+            None => panic!("Chart don't have indicator {NAME}"),
+        };
+
+        extr_data.channel(term, kind)
+    }
 }
 
 // private realization, but struct need to be pub
@@ -333,6 +390,8 @@ pub struct ExtremumData {
     t_t4_now: Option<Trend>,
     t_t5_now: Option<Trend>,
 
+    filter: SwingFilter,
+    step: f64,
     last_ts: i64,
 }
 impl ExtremumData {
@@ -344,7 +403,14 @@ impl ExtremumData {
         NAME
     }
     pub fn new(chart: &Chart) -> Self {
-        let mut data = ExtremumData::default();
+        Self::with_filter(chart, SwingFilter::Off)
+    }
+    pub fn with_filter(chart: &Chart, filter: SwingFilter) -> Self {
+        let mut data = ExtremumData {
+            filter,
+            step: chart.iid().step(),
+            ..Default::default()
+        };
 
         data.calc_e1(chart.bars());
         data.calc_en(T2);
@@ -360,7 +426,7 @@ impl ExtremumData {
 
         data
     }
-    pub fn update(&mut self, bars: &[Bar]) {
+    pub fn update(&mut self, bars: BarSlice) {
         // В тестере/сканере, после init на пустом графике нет ни одного
         // экстремума и нет исторических баров. Поэтому, первое смотрим на
         // наличие исторических баров в принципе.
@@ -415,6 +481,32 @@ impl ExtremumData {
         self.last_ts = current.ts;
     }
 
+    /// Export historical extremums to DataFrame.
+    ///
+    /// # ru
+    /// Возвращает датафрейм с колонкой ts_nanos баров и колонками
+    /// extremum_t1 ... extremum_t5. В строке бара, на котором
+    /// сформировался исторический экстремум, стоит его цена, в
+    /// остальных строках null.
+    pub fn to_df(&self, bars: BarSlice) -> DataFrame {
+        let ts = bars.ts;
+        let mut columns: Vec<Column> =
+            vec![Series::new("ts_nanos".into(), ts).into()];
+
+        for term in Term::iter() {
+            let mut prices: Vec<Option<f64>> = vec![None; ts.len()];
+            for e in self.all_extr(term).iter() {
+                if let Ok(i) = ts.binary_search(&e.ts) {
+                    prices[i] = Some(e.price);
+                }
+            }
+            let name = format!("{}_{}", NAME, term).to_lowercase();
+            columns.push(Series::new(name.into(), prices).into());
+        }
+
+        DataFrame::new(columns).unwrap()
+    }
+
     // private
     fn extr(&self, term: Term, n: usize) -> Option<&Extremum> {
         if n == 0 {
@@ -486,7 +578,37 @@ impl ExtremumData {
         }
     }
 
-    fn calc_e1(&mut self, bars: &[Bar]) {
+    fn trend_line(
+        &self,
+        term: Term,
+        kind: ExtremumKind,
+    ) -> Option<TrendLine> {
+        // линия через два последних исторических экстремума заданного типа
+        let mut same_kind = self.all_extr(term).iter().rev();
+        let e2 = same_kind.find(|e| e.kind == kind)?;
+        let e1 = same_kind.find(|e| e.kind == kind)?;
+
+        TrendLine::new(e1, e2)
+    }
+    fn channel(&self, term: Term, kind: ExtremumKind) -> Option<Channel> {
+        let line = self.trend_line(term, kind)?;
+
+        // параллельная линия через противоположный экстремум между
+        // точками основной линии, наиболее удаленный от нее
+        let b = line.begin().ts;
+        let e = line.end().ts;
+        let distance = |x: &Extremum| (x.price - line.price_at(x.ts)).abs();
+        let opposite = self
+            .all_extr(term)
+            .iter()
+            .filter(|x| x.kind != kind && x.ts > b && x.ts < e)
+            .max_by(|x, y| distance(x).total_cmp(&distance(y)))?
+            .clone();
+
+        Channel::new(line, &opposite)
+    }
+
+    fn calc_e1(&mut self, bars: BarSlice) {
         // if chart is empty
         if bars.len() < 2 {
             self.e_t1 = Vec::new();
@@ -499,8 +621,7 @@ impl ExtremumData {
         let mut t1_now;
 
         // start extremum kind (Max | Min) depends on first bar (bull | bear)
-        let mut prev = &bars[0];
-        let bars = &bars[1..];
+        let mut prev = bars.first().unwrap();
         if prev.is_bull() {
             t1_now = Extremum::new(prev.ts, T1, Max, prev.h);
         } else {
@@ -508,7 +629,7 @@ impl ExtremumData {
         }
 
         // cacl extremums Term::T1
-        for cur in bars.iter() {
+        for cur in bars.iter().skip(1) {
             if t1_now.is_max() {
                 if cur.h > prev.h {
                     t1_now = Extremum::new(cur.ts, T1, Max, cur.h);
@@ -530,6 +651,69 @@ impl ExtremumData {
         self.e_t1 = t1;
         self.e_t1_now = Some(t1_now);
         self.last_ts = bars.last().unwrap().ts;
+
+        if self.filter != SwingFilter::Off {
+            self.filter_e1(bars);
+        }
+    }
+    fn filter_e1(&mut self, bars: BarSlice) {
+        let Some(now) = self.e_t1_now.take() else {
+            return;
+        };
+
+        // ATR считаем один раз на весь график, если он нужен фильтру
+        let atr = match self.filter {
+            SwingFilter::Atr(period, _) => calc_atr(bars, period),
+            _ => Vec::new(),
+        };
+
+        // в потоке сырых экстремумов T1 последний - реал-тайм экстремум
+        let mut raw = std::mem::take(&mut self.e_t1).into_iter();
+        let mut out = Vec::new();
+        let mut cand = raw.next().unwrap_or(now.clone());
+
+        for cur in raw.chain(std::iter::once(now)) {
+            // экстремум того же типа - только обновляем кандидата,
+            // если цена ушла дальше
+            if cur.kind == cand.kind {
+                let further = match cand.kind {
+                    Max => cur.price > cand.price,
+                    Min => cur.price < cand.price,
+                };
+                if further {
+                    cand = cur;
+                }
+                continue;
+            }
+
+            // противоположный экстремум - фиксируем кандидата, только
+            // если колебание не меньше минимального, иначе это шум
+            let swing = (cur.price - cand.price).abs();
+            if swing >= self.min_swing(&cand, &cur, bars.ts, &atr) {
+                out.push(cand);
+                cand = cur;
+            }
+        }
+
+        self.e_t1 = out;
+        self.e_t1_now = Some(cand);
+    }
+    fn min_swing(
+        &self,
+        from: &Extremum,
+        to: &Extremum,
+        ts: &[i64],
+        atr: &[f64],
+    ) -> f64 {
+        match self.filter {
+            SwingFilter::Off => 0.0,
+            SwingFilter::Percent(p) => from.price * p / 100.0,
+            SwingFilter::Steps(n) => n as f64 * self.step,
+            SwingFilter::Atr(_, k) => {
+                let i = bisect_left(ts, to.ts, |t| *t).unwrap_or(0);
+                atr[i] * k
+            }
+        }
     }
     fn calc_en(&mut self, out_term: Term) {
         let in_extr = match out_term {
@@ -607,7 +791,7 @@ impl ExtremumData {
             }
         };
     }
-    fn calc_trends(&mut self, term: Term, bars: &[Bar]) {
+    fn calc_trends(&mut self, term: Term, bars: BarSlice) {
         let in_extr = match term {
             T1 => &self.e_t1,
             T2 => &self.e_t2,
@@ -661,18 +845,28 @@ impl ExtremumData {
 }
 
 #[inline]
-fn build_trend(e1: &Extremum, e2: &Extremum, all_bars: &[Bar]) -> Trend {
+fn build_trend(e1: &Extremum, e2: &Extremum, all_bars: BarSlice) -> Trend {
     // select bars of trend
-    let f = bisect_right(all_bars, e1.ts, |b| b.ts).unwrap();
-    let t = bisect_left(all_bars, e2.ts, |b| b.ts).unwrap();
-    let bars_of_trend = &all_bars[f..=t];
+    let f = bisect_right(all_bars.ts, e1.ts, |t| *t).unwrap();
+    let t = bisect_left(all_bars.ts, e2.ts, |t| *t).unwrap();
+    let bars_of_trend = all_bars.slice(f..t + 1);
 
     Trend::new(e1, e2, bars_of_trend)
 }
 
+// Average True Range, simple moving average of true range.
+// Для первых баров, пока их меньше периода, среднее по имеющимся.
+// Считается прямо по колонкам графика, без копирования баров.
+fn calc_atr(bars: BarSlice, period: usize) -> Vec<f64> {
+    let tr = utils::true_range(bars.h, bars.l, bars.c);
+
+    utils::sma(&tr, period)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixture::iid;
     use crate::*;
     use avin_utils as utils;
 
@@ -743,4 +937,77 @@ mod tests {
         let trend = chart.trend(T1, 3).unwrap();
         assert_eq!(trend.len(), 3);
     }
+    #[test]
+    fn swing_filter() {
+        let iid = iid();
+
+        let bars = vec![
+            Bar::new(1, 100.0, 101.0, 99.0, 101.0, 10),
+            Bar::new(2, 101.0, 102.0, 100.0, 101.5, 10),
+            Bar::new(3, 101.5, 101.5, 100.8, 101.0, 10),
+            Bar::new(4, 101.0, 103.0, 101.0, 102.5, 10),
+            Bar::new(5, 102.5, 104.0, 102.0, 103.0, 10),
+            Bar::new(6, 103.0, 103.0, 97.0, 98.0, 10),
+            Bar::new(7, 98.0, 99.0, 96.0, 97.0, 10),
+            Bar::new(8, 97.0, 100.0, 97.0, 99.5, 10),
+        ];
+        let mut chart = Chart::new(&iid, TimeFrame::Day, bars);
+        assert!(!chart.has_extremum());
+
+        // without filter - extremum on every swing
+        ExtremumIndicator::init(&mut chart);
+        assert!(chart.has_extremum());
+        assert_eq!(chart.all_extr(T1).len(), 4);
+
+        // small swing 102 -> 100.8 filtered as noise
+        for filter in [SwingFilter::Percent(2.0), SwingFilter::Steps(300)] {
+            chart.init_with(filter);
+            let all = chart.all_extr(T1);
+            assert_eq!(all.len(), 2);
+            assert_eq!(all[0].kind, ExtremumKind::Max);
+            assert_eq!(all[0].price, 104.0);
+            assert_eq!(all[1].kind, ExtremumKind::Min);
+            assert_eq!(all[1].price, 96.0);
+
+            let now = chart.extr(T1, 0).unwrap();
+            assert_eq!(now.kind, ExtremumKind::Max);
+            assert_eq!(now.price, 100.0);
+        }
+
+        // huge ATR multiplier - no one swing is enough
+        chart.init_with(SwingFilter::Atr(3, 100.0));
+        assert!(chart.all_extr(T1).is_empty());
+    }
+    #[test]
+    fn trend_line_and_channel() {
+        let iid = iid();
+
+        let bars = vec![
+            Bar::new(1, 100.0, 101.0, 99.0, 101.0, 10),
+            Bar::new(2, 101.0, 102.0, 100.0, 101.5, 10),
+            Bar::new(3, 101.5, 101.5, 100.8, 101.0, 10),
+            Bar::new(4, 101.0, 103.0, 101.0, 102.5, 10),
+            Bar::new(5, 102.5, 104.0, 102.0, 103.0, 10),
+            Bar::new(6, 103.0, 103.0, 97.0, 98.0, 10),
+            Bar::new(7, 98.0, 99.0, 96.0, 97.0, 10),
+            Bar::new(8, 97.0, 100.0, 97.0, 99.5, 10),
+        ];
+        let mut chart = Chart::new(&iid, TimeFrame::Day, bars);
+
+        // no extremums - no line
+        chart.init_with(SwingFilter::Atr(3, 100.0));
+        assert!(chart.trend_line(T1, ExtremumKind::Max).is_none());
+        assert!(chart.channel(T1, ExtremumKind::Max).is_none());
+
+        // line through last two T1 max: 102 -> 104
+        ExtremumIndicator::init(&mut chart);
+        let line = chart.trend_line(T1, ExtremumKind::Max).unwrap();
+        assert_eq!(line.begin().price, 102.0);
+        assert_eq!(line.end().price, 104.0);
+        assert!(line.is_rising());
+
+        // channel with opposite min 100.8 between them
+        let channel = chart.channel(T1, ExtremumKind::Max).unwrap();
+        assert_eq!(channel.opposite().price, 100.8);
+    }
 }
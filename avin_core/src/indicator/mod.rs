@@ -7,6 +7,12 @@
 
 mod _indicator;
 mod extremum;
+mod spread;
+mod trend_line;
 
 pub use _indicator::Indicator;
-pub use extremum::{Extremum, ExtremumIndicator, ExtremumKind, Term, Trend};
+pub use extremum::{
+    Extremum, ExtremumIndicator, ExtremumKind, SwingFilter, Term, Trend,
+};
+pub use spread::{Spread, SpreadKind};
+pub use trend_line::{Channel, TrendLine};
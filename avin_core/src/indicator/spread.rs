@@ -0,0 +1,252 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use crate::{Bar, Iid};
+
+/// How spread of pair is built.
+///
+/// # ru
+/// Способ построения спреда пары. Ratio - отношение цен a / b,
+/// хедж по деньгам. Beta - разность a - beta * b, beta считается
+/// регрессией цен a на цены b, хедж по бете.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadKind {
+    Ratio,
+    Beta,
+}
+
+/// Spread of two instruments for pairs trading.
+///
+/// # ru
+/// Спред двух инструментов для парного трейдинга (статистический
+/// арбитраж), например SBER / SBERP. Строится по ценам закрытия баров
+/// обоих инструментов, совпадающих по времени, бары без пары
+/// пропускаются. Z-score спреда показывает, насколько спред отошел от
+/// своего среднего.
+///
+/// ```ignore
+/// let mut spread = Spread::new(sber.iid(), sberp.iid(), SpreadKind::Beta);
+/// spread.update(sber_chart.bars(), sberp_chart.bars());
+/// let z = spread.zscore(100);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Spread {
+    a: Iid,
+    b: Iid,
+    kind: SpreadKind,
+    beta: f64,
+    ts: Vec<i64>,
+    values: Vec<f64>,
+}
+impl Spread {
+    /// Create empty spread of instruments a and b.
+    ///
+    /// # ru
+    /// Конструктор, пустой спред. Значения появляются после update.
+    pub fn new(a: &Iid, b: &Iid, kind: SpreadKind) -> Self {
+        Self {
+            a: a.clone(),
+            b: b.clone(),
+            kind,
+            beta: 1.0,
+            ts: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+    pub fn a(&self) -> &Iid {
+        &self.a
+    }
+    pub fn b(&self) -> &Iid {
+        &self.b
+    }
+    pub fn kind(&self) -> SpreadKind {
+        self.kind
+    }
+    /// Hedge ratio of beta spread.
+    ///
+    /// # ru
+    /// Коэффициент хеджа: сколько штук b на одну штуку a. Для Ratio
+    /// спреда всегда 1.
+    pub fn beta(&self) -> f64 {
+        self.beta
+    }
+    /// Timestamps of spread values.
+    ///
+    /// # ru
+    /// Время значений спреда - время совпавших баров.
+    pub fn ts(&self) -> &[i64] {
+        &self.ts
+    }
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+    pub fn last(&self) -> Option<f64> {
+        self.values.last().copied()
+    }
+    /// Recalculate spread from bars of both instruments.
+    ///
+    /// # ru
+    /// Пересчитывает спред по барам инструментов a и b, бары должны
+    /// быть отсортированы по времени. Beta пересчитывается по всем
+    /// совпавшим барам.
+    pub fn update(&mut self, a: &[Bar], b: &[Bar]) {
+        let (ts, pa, pb) = align(a, b);
+
+        self.beta = match self.kind {
+            SpreadKind::Ratio => 1.0,
+            SpreadKind::Beta => beta(&pa, &pb),
+        };
+        self.values = match self.kind {
+            SpreadKind::Ratio => {
+                pa.iter().zip(&pb).map(|(a, b)| a / b).collect()
+            }
+            SpreadKind::Beta => {
+                pa.iter().zip(&pb).map(|(a, b)| a - self.beta * b).collect()
+            }
+        };
+        self.ts = ts;
+    }
+    /// Z-score of spread over period.
+    ///
+    /// # ru
+    /// Z-score спреда за период, вектор той же длины, что и значения.
+    pub fn zscore(&self, period: usize) -> Vec<f64> {
+        avin_utils::zscore(&self.values, period)
+    }
+    /// Lots of b that hedge lots of a.
+    ///
+    /// # ru
+    /// Количество лотов b, хеджирующее lots лотов a при текущих ценах:
+    /// для Ratio - равные суммы денег, для Beta - beta штук b на штуку
+    /// a. Не меньше 1 лота, если lots не 0.
+    pub fn hedge_lots(&self, lots: u32, price_a: f64, price_b: f64) -> u32 {
+        if lots == 0 {
+            return 0;
+        }
+
+        let quantity = (lots * self.a.lot()) as f64;
+        let quantity_b = match self.kind {
+            SpreadKind::Ratio => quantity * price_a / price_b,
+            SpreadKind::Beta => quantity * self.beta.abs(),
+        };
+        let lots_b = (quantity_b / self.b.lot() as f64).round() as u32;
+
+        lots_b.max(1)
+    }
+}
+
+// цены закрытия баров, совпавших по времени
+fn align(a: &[Bar], b: &[Bar]) -> (Vec<i64>, Vec<f64>, Vec<f64>) {
+    let mut ts = Vec::new();
+    let mut pa = Vec::new();
+    let mut pb = Vec::new();
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].ts.cmp(&b[j].ts) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                ts.push(a[i].ts);
+                pa.push(a[i].c);
+                pb.push(b[j].c);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    (ts, pa, pb)
+}
+
+// наклон регрессии y на x, без разброса x - 1
+fn beta(y: &[f64], x: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    if n == 0.0 {
+        return 1.0;
+    }
+    let mx = x.iter().sum::<f64>() / n;
+    let my = y.iter().sum::<f64>() / n;
+
+    let mut cov = 0.0;
+    let mut var = 0.0;
+    for (x, y) in x.iter().zip(y) {
+        cov += (x - mx) * (y - my);
+        var += (x - mx).powi(2);
+    }
+    if var == 0.0 {
+        return 1.0;
+    }
+
+    cov / var
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    fn iid(ticker: &str, figi: &str, lot: &str) -> Iid {
+        fixture::iid_with(&[
+            ("ticker", ticker),
+            ("figi", figi),
+            ("name", ticker),
+            ("lot", lot),
+        ])
+    }
+    fn bars(prices: &[(i64, f64)]) -> Vec<Bar> {
+        prices
+            .iter()
+            .map(|(ts, p)| Bar::new(*ts, *p, *p, *p, *p, 1))
+            .collect()
+    }
+
+    #[test]
+    fn ratio() {
+        let a = iid("SBER", "BBG004730N88", "10");
+        let b = iid("SBERP", "BBG0047315Y7", "1");
+        let mut spread = Spread::new(&a, &b, SpreadKind::Ratio);
+
+        // бар b на 2 и бар a на 4 без пары
+        let a_bars = bars(&[(1, 300.0), (3, 310.0), (4, 320.0)]);
+        let b_bars = bars(&[(1, 300.0), (2, 305.0), (3, 310.0 / 1.1)]);
+        spread.update(&a_bars, &b_bars);
+        assert_eq!(spread.ts(), &[1, 3]);
+        assert_eq!(spread.values()[0], 1.0);
+        assert!((spread.last().unwrap() - 1.1).abs() < 1e-9);
+
+        // 2 лота a = 20 штук по 300 = 6000, b по 200 = 30 штук
+        assert_eq!(spread.hedge_lots(2, 300.0, 200.0), 30);
+        assert_eq!(spread.hedge_lots(0, 300.0, 200.0), 0);
+    }
+    #[test]
+    fn beta_hedged() {
+        let a = iid("SBER", "BBG004730N88", "1");
+        let b = iid("SBERP", "BBG0047315Y7", "1");
+        let mut spread = Spread::new(&a, &b, SpreadKind::Beta);
+
+        // a = 2 * b + 10 + шум
+        let noise = [0.5, -0.5, 0.5, -0.5, 0.5, -0.5];
+        let mut a_bars = Vec::new();
+        let mut b_bars = Vec::new();
+        for (i, e) in noise.iter().enumerate() {
+            let p = 100.0 + i as f64;
+            a_bars.push((i as i64, 2.0 * p + 10.0 + e));
+            b_bars.push((i as i64, p));
+        }
+        spread.update(&bars(&a_bars), &bars(&b_bars));
+
+        assert!((spread.beta() - 2.0).abs() < 0.2);
+        assert_eq!(spread.values().len(), 6);
+        let lots = (10.0 * spread.beta()).round() as u32;
+        assert_eq!(spread.hedge_lots(10, 0.0, 0.0), lots);
+
+        let z = spread.zscore(6);
+        assert_eq!(z.len(), 6);
+        assert!(z[5] < 0.0);
+    }
+}
@@ -0,0 +1,203 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use super::extremum::Extremum;
+
+/// Straight line through two extremums.
+///
+/// # ru
+/// Линия тренда, проведенная через два экстремума. Позволяет получить
+/// цену линии в любой момент времени, в том числе в будущем - проекция
+/// линии вперед. Используется для торговли касаний и пробоев.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrendLine {
+    e1: Extremum,
+    e2: Extremum,
+}
+impl TrendLine {
+    /// Create trend line from two extremums.
+    ///
+    /// # ru
+    /// Конструктор. Первый экстремум должен быть раньше второго, иначе
+    /// линию не построить - возвращает None.
+    pub fn new(e1: &Extremum, e2: &Extremum) -> Option<Self> {
+        if e1.ts >= e2.ts {
+            return None;
+        }
+
+        Some(Self {
+            e1: e1.clone(),
+            e2: e2.clone(),
+        })
+    }
+    /// Return first point of line.
+    ///
+    /// # ru
+    /// Возвращает первый экстремум, через который проведена линия.
+    pub fn begin(&self) -> &Extremum {
+        &self.e1
+    }
+    /// Return second point of line.
+    ///
+    /// # ru
+    /// Возвращает второй экстремум, через который проведена линия.
+    pub fn end(&self) -> &Extremum {
+        &self.e2
+    }
+    /// Return slope of line, price change per second.
+    ///
+    /// # ru
+    /// Возвращает наклон линии - изменение цены за секунду.
+    pub fn slope(&self) -> f64 {
+        let dp = self.e2.price - self.e1.price;
+        let dt = (self.e2.ts - self.e1.ts) as f64 / 1_000_000_000.0;
+
+        dp / dt
+    }
+    /// Return price of line at timestamp.
+    ///
+    /// # ru
+    /// Возвращает цену линии в заданный момент времени. Для времени
+    /// после второго экстремума - проекция линии вперед.
+    pub fn price_at(&self, ts: i64) -> f64 {
+        let dt = (ts - self.e1.ts) as f64 / 1_000_000_000.0;
+
+        self.e1.price + self.slope() * dt
+    }
+    pub fn is_rising(&self) -> bool {
+        self.e2.price > self.e1.price
+    }
+    pub fn is_falling(&self) -> bool {
+        self.e2.price < self.e1.price
+    }
+}
+
+/// Price channel, trend line and parallel line through opposite extremum.
+///
+/// # ru
+/// Ценовой канал. Основная линия проведена через два однотипных
+/// экстремума, параллельная ей линия - через противоположный экстремум
+/// между ними, наиболее удаленный от основной линии.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    line: TrendLine,
+    opposite: Extremum,
+}
+impl Channel {
+    /// Create channel from trend line and opposite extremum.
+    ///
+    /// # ru
+    /// Конструктор. Противоположный экстремум должен быть другого типа,
+    /// чем экстремумы линии, иначе возвращает None.
+    pub fn new(line: TrendLine, opposite: &Extremum) -> Option<Self> {
+        if line.begin().kind == opposite.kind {
+            return None;
+        }
+
+        Some(Self {
+            line,
+            opposite: opposite.clone(),
+        })
+    }
+    /// Return base trend line of channel.
+    ///
+    /// # ru
+    /// Возвращает основную линию канала.
+    pub fn line(&self) -> &TrendLine {
+        &self.line
+    }
+    /// Return opposite extremum of channel.
+    ///
+    /// # ru
+    /// Возвращает противоположный экстремум, через который проведена
+    /// параллельная линия.
+    pub fn opposite(&self) -> &Extremum {
+        &self.opposite
+    }
+    /// Return channel width in price.
+    ///
+    /// # ru
+    /// Возвращает ширину канала в цене, по вертикали.
+    pub fn width(&self) -> f64 {
+        let base = self.line.price_at(self.opposite.ts);
+
+        (self.opposite.price - base).abs()
+    }
+    /// Return price of upper line at timestamp.
+    ///
+    /// # ru
+    /// Возвращает цену верхней границы канала в заданный момент времени.
+    pub fn upper_at(&self, ts: i64) -> f64 {
+        let base = self.line.price_at(ts);
+
+        if self.line.begin().is_max() {
+            base
+        } else {
+            base + self.width()
+        }
+    }
+    /// Return price of lower line at timestamp.
+    ///
+    /// # ru
+    /// Возвращает цену нижней границы канала в заданный момент времени.
+    pub fn lower_at(&self, ts: i64) -> f64 {
+        let base = self.line.price_at(ts);
+
+        if self.line.begin().is_min() {
+            base
+        } else {
+            base - self.width()
+        }
+    }
+    /// Check price is inside channel at timestamp.
+    ///
+    /// # ru
+    /// Проверяет, находится ли цена внутри канала в заданный момент.
+    pub fn contains(&self, ts: i64, price: f64) -> bool {
+        self.lower_at(ts) <= price && price <= self.upper_at(ts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExtremumKind, Term};
+
+    const SEC: i64 = 1_000_000_000;
+
+    #[test]
+    fn trend_line() {
+        let e1 = Extremum::new(10 * SEC, Term::T3, ExtremumKind::Min, 100.0);
+        let e2 = Extremum::new(20 * SEC, Term::T3, ExtremumKind::Min, 110.0);
+        let line = TrendLine::new(&e1, &e2).unwrap();
+
+        assert!(line.is_rising());
+        assert_eq!(line.slope(), 1.0);
+        assert_eq!(line.price_at(10 * SEC), 100.0);
+        assert_eq!(line.price_at(15 * SEC), 105.0);
+        assert_eq!(line.price_at(30 * SEC), 120.0);
+
+        // точки в обратном порядке или в один момент - линии нет
+        assert!(TrendLine::new(&e2, &e1).is_none());
+        assert!(TrendLine::new(&e1, &e1).is_none());
+    }
+    #[test]
+    fn channel() {
+        let e1 = Extremum::new(10 * SEC, Term::T3, ExtremumKind::Min, 100.0);
+        let e2 = Extremum::new(20 * SEC, Term::T3, ExtremumKind::Min, 110.0);
+        let top = Extremum::new(15 * SEC, Term::T3, ExtremumKind::Max, 112.0);
+        let line = TrendLine::new(&e1, &e2).unwrap();
+        assert!(Channel::new(line.clone(), &e1).is_none());
+        let channel = Channel::new(line, &top).unwrap();
+
+        assert_eq!(channel.width(), 7.0);
+        assert_eq!(channel.lower_at(30 * SEC), 120.0);
+        assert_eq!(channel.upper_at(30 * SEC), 127.0);
+        assert!(channel.contains(30 * SEC, 125.0));
+        assert!(!channel.contains(30 * SEC, 128.0));
+    }
+}
@@ -50,39 +50,58 @@
 //!    их передает брокеру (или VirtualBroker в режиме тестера).
 
 mod action;
+mod alert;
 mod asset;
 mod broker;
 mod chart;
+mod clock;
 mod data;
 mod event;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixture;
 mod footprint;
 mod indicator;
 mod operation;
 mod order;
+mod signal;
 mod trade;
 
 pub use action::{
-    Action, GetAccountAction, GetBarsAction, OrderAction, StreamAction,
+    Action, GetAccountAction, GetBarsAction, GetCashOperationsAction,
+    GetOperationsAction, GetOrdersAction, GetPositionAction, NotifyAction,
+    NotifyLevel, OrderAction, StreamAction,
+};
+pub use alert::{
+    Alert, AlertSink, AlertSinks, EmailSink, TelegramSink, WebhookSink,
 };
 pub use asset::{Asset, AssetList, Category, Exchange, Iid, Share};
-pub use broker::Account;
-pub use chart::{Bar, Chart, Range, TimeFrame};
-pub use data::{DataSchema, Manager, MarketData, Source};
-pub use event::{BarEvent, Event, OrderEvent, TicEvent};
-pub use footprint::{Cluster, Footprint, Quant, Quantum, Tic};
-pub use operation::{Operation, Transaction};
-pub use trade::{Summary, Trade, TradeKind, TradeList};
+pub use broker::{Account, Margin};
+pub use chart::{
+    Bar, BarBuilder, BarColumns, BarIter, BarSlice, Chart, Range, TimeFrame,
+};
+pub use clock::{Clock, RealClock, SimClock};
+pub use data::{
+    ActionKind, CorporateAction, CorporateActions, DataSchema, DataStatus,
+    Manager, MarketData, MarketDataStatus, ParquetStorage, Roll, RollAdjust,
+    Source, SqliteStorage, Storage, Synthetic,
+};
+pub use event::{BarEvent, BookEvent, Event, OrderEvent, TicEvent};
+pub use footprint::{Cluster, Footprint, OrderBook, Quant, Quantum, Tic};
+pub use operation::{CashOperation, Operation, OperationKind, Transaction};
+pub use signal::Signal;
+pub use trade::{ClosedTrade, Summary, Trade, TradeKind, TradeList};
 
 // order
 pub use order::{Direction, LimitOrder, MarketOrder, Order, StopOrder};
 // market order statuses
 pub use order::{
-    FilledMarketOrder, NewMarketOrder, PostedMarketOrder, RejectedMarketOrder,
+    FilledMarketOrder, NewMarketOrder, PartiallyFilledMarketOrder,
+    PostedMarketOrder, RejectedMarketOrder,
 };
 // limit order statuses
 pub use order::{
-    CanceledLimitOrder, FilledLimitOrder, NewLimitOrder, PostedLimitOrder,
-    RejectedLimitOrder,
+    CanceledLimitOrder, FilledLimitOrder, NewLimitOrder,
+    PartiallyFilledLimitOrder, PostedLimitOrder, RejectedLimitOrder,
 };
 // stop order statuses
 pub use order::{
@@ -93,4 +112,9 @@ pub use order::{
 // indicator
 pub use indicator::Indicator;
 // extrumum indicator
-pub use indicator::{Extremum, ExtremumIndicator, ExtremumKind, Term, Trend};
+pub use indicator::{Channel, TrendLine};
+pub use indicator::{
+    Extremum, ExtremumIndicator, ExtremumKind, SwingFilter, Term, Trend,
+};
+// pairs trading
+pub use indicator::{Spread, SpreadKind};
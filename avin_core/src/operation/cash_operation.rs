@@ -0,0 +1,153 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use bitcode::{Decode, Encode};
+use chrono::{DateTime, Utc};
+
+use avin_utils::CFG;
+
+/// Kind of non-trade cash operation.
+///
+/// # ru
+/// Тип неторговой денежной операции на счете.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum OperationKind {
+    Deposit,
+    Withdrawal,
+    Dividend,
+    Coupon,
+    Repayment,
+    Tax,
+    Commission,
+    VariationMargin,
+}
+impl std::fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Deposit => write!(f, "Deposit"),
+            Self::Withdrawal => write!(f, "Withdrawal"),
+            Self::Dividend => write!(f, "Dividend"),
+            Self::Coupon => write!(f, "Coupon"),
+            Self::Repayment => write!(f, "Repayment"),
+            Self::Tax => write!(f, "Tax"),
+            Self::Commission => write!(f, "Commission"),
+            Self::VariationMargin => write!(f, "VariationMargin"),
+        }
+    }
+}
+
+/// Non-trade cash operation: dividends, taxes, fees, deposits...
+///
+/// # ru
+/// Неторговая денежная операция: дивиденды, купоны, погашения
+/// облигаций, налоги, комиссии брокера, пополнения и выводы,
+/// вариационная маржа.
+///
+/// Сумма со знаком: положительная - поступление на счет, отрицательная -
+/// списание со счета. Используется в истории счета и при расчете
+/// результата в тестере, наравне с торговыми [`crate::Operation`].
+#[derive(Debug, PartialEq, Encode, Decode, Clone)]
+pub struct CashOperation {
+    pub ts: i64,
+    pub kind: OperationKind,
+    pub value: f64,
+    pub currency: String,
+}
+impl CashOperation {
+    /// Create new cash operation.
+    ///
+    /// # ru
+    /// Конструктор.
+    pub fn new(
+        ts: i64,
+        kind: OperationKind,
+        value: f64,
+        currency: &str,
+    ) -> Self {
+        Self {
+            ts,
+            kind,
+            value,
+            currency: currency.to_string(),
+        }
+    }
+    /// Create cash operation from bin format
+    ///
+    /// # ru
+    /// Создает операцию из бинарного формата.
+    pub fn from_bin(bytes: &[u8]) -> Self {
+        bitcode::decode(bytes).unwrap()
+    }
+    /// Create vector bytes from cash operation, for saving.
+    ///
+    /// # ru
+    /// Преобразует операцию в бинарный формат для сохранения на диске.
+    pub fn to_bin(&self) -> Vec<u8> {
+        bitcode::encode(self)
+    }
+
+    /// Return DateTime UTC of operation
+    ///
+    /// # ru
+    /// Возвращает дату и время операции в UTC таймзоне
+    #[inline]
+    pub fn dt(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(self.ts)
+    }
+    /// Check operation is income.
+    ///
+    /// # ru
+    /// Проверяет, является ли операция поступлением на счет.
+    #[inline]
+    pub fn is_income(&self) -> bool {
+        self.value > 0.0
+    }
+    /// Check operation is expense.
+    ///
+    /// # ru
+    /// Проверяет, является ли операция списанием со счета.
+    #[inline]
+    pub fn is_expense(&self) -> bool {
+        self.value < 0.0
+    }
+}
+impl std::fmt::Display for CashOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let dt = format!("{}", self.dt().format(&CFG.usr.dt_fmt));
+        write!(
+            f,
+            "CashOperation={} {} {} {}",
+            dt, self.kind, self.value, self.currency
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        let op =
+            CashOperation::new(100500, OperationKind::Dividend, 340.0, "rub");
+        assert_eq!(op.ts, 100500);
+        assert_eq!(op.kind, OperationKind::Dividend);
+        assert!(op.is_income());
+
+        let op = CashOperation::new(100500, OperationKind::Tax, -44.2, "rub");
+        assert!(op.is_expense());
+    }
+    #[test]
+    fn bin() {
+        let op =
+            CashOperation::new(100500, OperationKind::Coupon, 35.9, "rub");
+
+        let bytes = op.to_bin();
+        let decoded = CashOperation::from_bin(&bytes);
+        assert_eq!(op, decoded);
+    }
+}
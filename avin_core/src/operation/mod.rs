@@ -6,7 +6,9 @@
  ****************************************************************************/
 
 mod _operation;
+mod cash_operation;
 mod transaction;
 
 pub use _operation::Operation;
+pub use cash_operation::{CashOperation, OperationKind};
 pub use transaction::Transaction;
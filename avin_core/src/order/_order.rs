@@ -100,6 +100,18 @@ impl Order {
             Order::Stop(s) => matches!(s, StopOrder::Posted(_)),
         }
     }
+    /// Check is order partially filled.
+    ///
+    /// # ru
+    /// Проверка статуса ордера, если исполнен частично true, иначе false.
+    /// Стоп ордера не исполняются, для них всегда false.
+    pub fn is_partially_filled(&self) -> bool {
+        match self {
+            Order::Market(m) => matches!(m, MarketOrder::PartiallyFilled(_)),
+            Order::Limit(l) => matches!(l, LimitOrder::PartiallyFilled(_)),
+            Order::Stop(_) => false,
+        }
+    }
     /// Check is order filled.
     ///
     /// # ru
@@ -132,12 +144,14 @@ impl Order {
             Order::Market(market) => match market {
                 MarketOrder::New(o) => &o.direction,
                 MarketOrder::Posted(o) => &o.direction,
+                MarketOrder::PartiallyFilled(o) => &o.direction,
                 MarketOrder::Filled(o) => &o.direction,
                 MarketOrder::Rejected(o) => &o.direction,
             },
             Order::Limit(limit) => match limit {
                 LimitOrder::New(o) => &o.direction,
                 LimitOrder::Posted(o) => &o.direction,
+                LimitOrder::PartiallyFilled(o) => &o.direction,
                 LimitOrder::Filled(o) => &o.direction,
                 LimitOrder::Rejected(o) => &o.direction,
                 LimitOrder::Canceled(o) => &o.direction,
@@ -148,8 +162,12 @@ impl Order {
                 StopOrder::Rejected(o) => &o.direction,
                 StopOrder::Canceled(o) => &o.direction,
                 StopOrder::Triggered(o) => match o {
-                    TriggeredStopOrder::Market(o) => &o.direction,
-                    TriggeredStopOrder::Limit(o) => &o.direction,
+                    TriggeredStopOrder::Market { order, .. } => {
+                        &order.direction
+                    }
+                    TriggeredStopOrder::Limit { order, .. } => {
+                        &order.direction
+                    }
                 },
             },
         }
@@ -163,12 +181,14 @@ impl Order {
             Order::Market(market) => match market {
                 MarketOrder::New(o) => o.lots,
                 MarketOrder::Posted(o) => o.lots,
+                MarketOrder::PartiallyFilled(o) => o.lots,
                 MarketOrder::Filled(o) => o.lots,
                 MarketOrder::Rejected(o) => o.lots,
             },
             Order::Limit(limit) => match limit {
                 LimitOrder::New(o) => o.lots,
                 LimitOrder::Posted(o) => o.lots,
+                LimitOrder::PartiallyFilled(o) => o.lots,
                 LimitOrder::Filled(o) => o.lots,
                 LimitOrder::Rejected(o) => o.lots,
                 LimitOrder::Canceled(o) => o.lots,
@@ -179,8 +199,8 @@ impl Order {
                 StopOrder::Rejected(o) => o.lots,
                 StopOrder::Canceled(o) => o.lots,
                 StopOrder::Triggered(o) => match o {
-                    TriggeredStopOrder::Market(o) => o.lots,
-                    TriggeredStopOrder::Limit(o) => o.lots,
+                    TriggeredStopOrder::Market { order, .. } => order.lots,
+                    TriggeredStopOrder::Limit { order, .. } => order.lots,
                 },
             },
         }
@@ -196,12 +216,14 @@ impl Order {
             Order::Market(market) => match market {
                 MarketOrder::New(_) => None,
                 MarketOrder::Posted(o) => Some(&o.transactions),
+                MarketOrder::PartiallyFilled(o) => Some(&o.transactions),
                 MarketOrder::Filled(o) => Some(&o.transactions),
                 MarketOrder::Rejected(_) => None,
             },
             Order::Limit(limit) => match limit {
                 LimitOrder::New(_) => None,
                 LimitOrder::Posted(o) => Some(&o.transactions),
+                LimitOrder::PartiallyFilled(o) => Some(&o.transactions),
                 LimitOrder::Filled(o) => Some(&o.transactions),
                 LimitOrder::Rejected(_) => None,
                 LimitOrder::Canceled(o) => Some(&o.transactions),
@@ -240,12 +262,14 @@ impl Order {
             Order::Market(market) => match market {
                 MarketOrder::New(_) => None,
                 MarketOrder::Posted(o) => Some(&o.broker_id),
+                MarketOrder::PartiallyFilled(o) => Some(&o.broker_id),
                 MarketOrder::Filled(o) => Some(&o.broker_id),
                 MarketOrder::Rejected(_) => None,
             },
             Order::Limit(limit) => match limit {
                 LimitOrder::New(_) => None,
                 LimitOrder::Posted(o) => Some(&o.broker_id),
+                LimitOrder::PartiallyFilled(o) => Some(&o.broker_id),
                 LimitOrder::Filled(o) => Some(&o.broker_id),
                 LimitOrder::Rejected(_) => None,
                 LimitOrder::Canceled(o) => Some(&o.broker_id),
@@ -255,10 +279,7 @@ impl Order {
                 StopOrder::Posted(o) => Some(&o.broker_id),
                 StopOrder::Rejected(_) => None,
                 StopOrder::Canceled(o) => Some(&o.broker_id),
-                StopOrder::Triggered(o) => match o {
-                    TriggeredStopOrder::Market(o) => Some(&o.broker_id),
-                    TriggeredStopOrder::Limit(o) => Some(&o.broker_id),
-                },
+                StopOrder::Triggered(o) => Some(o.stop_id()),
             },
         }
     }
@@ -269,12 +290,18 @@ impl std::fmt::Display for Order {
             Order::Market(market) => match market {
                 MarketOrder::New(_) => write!(f, "MarketOrder-New"),
                 MarketOrder::Posted(_) => write!(f, "MarketOrder-Posted"),
+                MarketOrder::PartiallyFilled(_) => {
+                    write!(f, "MarketOrder-PartiallyFilled")
+                }
                 MarketOrder::Filled(_) => write!(f, "MarketOrder-Filled"),
                 MarketOrder::Rejected(_) => write!(f, "MarketOrder-Rejected"),
             },
             Order::Limit(limit) => match limit {
                 LimitOrder::New(_) => write!(f, "Limit-New"),
                 LimitOrder::Posted(_) => write!(f, "Limit-Posted"),
+                LimitOrder::PartiallyFilled(_) => {
+                    write!(f, "Limit-PartiallyFilled")
+                }
                 LimitOrder::Filled(_) => write!(f, "Limit-Filled"),
                 LimitOrder::Rejected(_) => write!(f, "Limit-Rejected"),
                 LimitOrder::Canceled(_) => write!(f, "Limit-Canceled"),
@@ -285,10 +312,10 @@ impl std::fmt::Display for Order {
                 StopOrder::Rejected(_) => write!(f, "Stop-Rejected"),
                 StopOrder::Canceled(_) => write!(f, "Stop-Canceled"),
                 StopOrder::Triggered(o) => match o {
-                    TriggeredStopOrder::Market(_) => {
+                    TriggeredStopOrder::Market { .. } => {
                         write!(f, "Stop-Triggered-Market")
                     }
-                    TriggeredStopOrder::Limit(_) => {
+                    TriggeredStopOrder::Limit { .. } => {
                         write!(f, "Stop-Triggered-Limit")
                     }
                 },
@@ -26,6 +26,7 @@ use crate::{Direction, Operation, Transaction};
 pub enum LimitOrder {
     New(NewLimitOrder),
     Posted(PostedLimitOrder),
+    PartiallyFilled(PartiallyFilledLimitOrder),
     Filled(FilledLimitOrder),
     Rejected(RejectedLimitOrder),
     Canceled(CanceledLimitOrder),
@@ -44,6 +45,7 @@ impl LimitOrder {
         match self {
             LimitOrder::New(o) => Some(o),
             LimitOrder::Posted(_) => None,
+            LimitOrder::PartiallyFilled(_) => None,
             LimitOrder::Filled(_) => None,
             LimitOrder::Rejected(_) => None,
             LimitOrder::Canceled(_) => None,
@@ -53,6 +55,17 @@ impl LimitOrder {
         match self {
             LimitOrder::New(_) => None,
             LimitOrder::Posted(o) => Some(o),
+            LimitOrder::PartiallyFilled(_) => None,
+            LimitOrder::Filled(_) => None,
+            LimitOrder::Rejected(_) => None,
+            LimitOrder::Canceled(_) => None,
+        }
+    }
+    pub fn as_partially_filled(self) -> Option<PartiallyFilledLimitOrder> {
+        match self {
+            LimitOrder::New(_) => None,
+            LimitOrder::Posted(_) => None,
+            LimitOrder::PartiallyFilled(o) => Some(o),
             LimitOrder::Filled(_) => None,
             LimitOrder::Rejected(_) => None,
             LimitOrder::Canceled(_) => None,
@@ -62,6 +75,7 @@ impl LimitOrder {
         match self {
             LimitOrder::New(_) => None,
             LimitOrder::Posted(_) => None,
+            LimitOrder::PartiallyFilled(_) => None,
             LimitOrder::Filled(o) => Some(o),
             LimitOrder::Rejected(_) => None,
             LimitOrder::Canceled(_) => None,
@@ -71,6 +85,7 @@ impl LimitOrder {
         match self {
             LimitOrder::New(_) => None,
             LimitOrder::Posted(_) => None,
+            LimitOrder::PartiallyFilled(_) => None,
             LimitOrder::Filled(_) => None,
             LimitOrder::Rejected(o) => Some(o),
             LimitOrder::Canceled(_) => None,
@@ -80,6 +95,7 @@ impl LimitOrder {
         match self {
             LimitOrder::New(_) => None,
             LimitOrder::Posted(_) => None,
+            LimitOrder::PartiallyFilled(_) => None,
             LimitOrder::Filled(_) => None,
             LimitOrder::Rejected(_) => None,
             LimitOrder::Canceled(o) => Some(o),
@@ -92,6 +108,9 @@ impl LimitOrder {
     pub fn is_posted(&self) -> bool {
         matches!(self, LimitOrder::Posted(_))
     }
+    pub fn is_partially_filled(&self) -> bool {
+        matches!(self, LimitOrder::PartiallyFilled(_))
+    }
     pub fn is_filled(&self) -> bool {
         matches!(self, LimitOrder::Filled(_))
     }
@@ -106,6 +125,7 @@ impl LimitOrder {
         match self {
             Self::New(_) => None,
             Self::Posted(o) => Some(&o.broker_id),
+            Self::PartiallyFilled(o) => Some(&o.broker_id),
             Self::Filled(o) => Some(&o.broker_id),
             Self::Canceled(o) => Some(&o.broker_id),
             Self::Rejected(_) => None,
@@ -117,6 +137,7 @@ impl std::fmt::Display for LimitOrder {
         match self {
             Self::New(order) => write!(f, "{order}"),
             Self::Posted(order) => write!(f, "{order}"),
+            Self::PartiallyFilled(order) => write!(f, "{order}"),
             Self::Filled(order) => write!(f, "{order}"),
             Self::Canceled(order) => write!(f, "{order}"),
             Self::Rejected(order) => write!(f, "{order}"),
@@ -171,6 +192,23 @@ impl PostedLimitOrder {
     pub fn add_transaction(&mut self, t: Transaction) {
         self.transactions.push(t);
     }
+    pub fn partial_fill(
+        self,
+        lots: u32,
+        t: Transaction,
+    ) -> PartiallyFilledLimitOrder {
+        let mut order = PartiallyFilledLimitOrder {
+            direction: self.direction,
+            lots: self.lots,
+            price: self.price,
+            broker_id: self.broker_id,
+            executed: 0,
+            transactions: self.transactions,
+        };
+        order.add_fill(lots, t);
+
+        order
+    }
     pub fn fill(self, ts: i64, commission: f64) -> FilledLimitOrder {
         let operation = Operation::build(ts, &self.transactions, commission);
         FilledLimitOrder {
@@ -206,6 +244,71 @@ impl std::fmt::Display for PostedLimitOrder {
     }
 }
 
+/// Partially filled limit order.
+///
+/// # ru
+/// Частично исполненный лимитный ордер. Хранит количество уже
+/// исполненных лотов и транзакции. Каждое очередное исполнение
+/// добавляется через [`PartiallyFilledLimitOrder::add_fill`], брокер при
+/// этом отправляет эвент с обновленным ордером.
+#[derive(Debug, PartialEq, Decode, Encode, Clone)]
+pub struct PartiallyFilledLimitOrder {
+    pub direction: Direction,
+    pub lots: u32,
+    pub price: f64,
+    pub broker_id: String,
+    pub executed: u32,
+    pub transactions: Vec<Transaction>,
+}
+impl PartiallyFilledLimitOrder {
+    pub fn add_fill(&mut self, lots: u32, t: Transaction) {
+        assert!(self.executed + lots <= self.lots);
+
+        self.executed += lots;
+        self.transactions.push(t);
+    }
+    pub fn remaining(&self) -> u32 {
+        self.lots - self.executed
+    }
+    pub fn is_complete(&self) -> bool {
+        self.executed == self.lots
+    }
+    pub fn fill(self, ts: i64, commission: f64) -> FilledLimitOrder {
+        let operation = Operation::build(ts, &self.transactions, commission);
+        FilledLimitOrder {
+            direction: self.direction,
+            lots: self.lots,
+            price: self.price,
+            broker_id: self.broker_id,
+            transactions: self.transactions,
+            operation,
+        }
+    }
+    pub fn cancel(self) -> CanceledLimitOrder {
+        CanceledLimitOrder {
+            direction: self.direction,
+            lots: self.lots,
+            price: self.price,
+            broker_id: self.broker_id,
+            transactions: self.transactions,
+        }
+    }
+}
+impl std::fmt::Display for PartiallyFilledLimitOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "LimitOrder::PartiallyFilled={} {}/{}x{} id={} t={:?}",
+            self.direction,
+            self.executed,
+            self.lots,
+            self.price,
+            self.broker_id,
+            self.transactions
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Decode, Encode, Clone)]
 pub struct FilledLimitOrder {
     pub direction: Direction,
@@ -301,6 +404,25 @@ mod tests {
         assert_eq!(order.operation.commission, 4.5);
     }
     #[test]
+    fn partial_fill() {
+        let new = LimitOrder::new(Direction::Sell, 3, 300.0);
+        let posted = new.post("order_id=100500");
+
+        let mut partial = posted.partial_fill(1, Transaction::new(10, 300.0));
+        assert_eq!(partial.executed, 1);
+        assert_eq!(partial.remaining(), 2);
+        assert!(!partial.is_complete());
+
+        partial.add_fill(2, Transaction::new(20, 300.0));
+        assert_eq!(partial.transactions.len(), 2);
+        assert!(partial.is_complete());
+
+        let order = partial.fill(100500, 1.8);
+        assert_eq!(order.broker_id, "order_id=100500");
+        assert_eq!(order.operation.quantity, 30);
+        assert_eq!(order.operation.value, 9000.0);
+    }
+    #[test]
     fn reject() {
         let new = LimitOrder::new(Direction::Buy, 100, 400.0);
         assert_eq!(new.direction, Direction::Buy);
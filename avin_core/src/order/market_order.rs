@@ -26,6 +26,7 @@ use crate::{Direction, Operation, Transaction};
 pub enum MarketOrder {
     New(NewMarketOrder),
     Posted(PostedMarketOrder),
+    PartiallyFilled(PartiallyFilledMarketOrder),
     Filled(FilledMarketOrder),
     Rejected(RejectedMarketOrder),
 }
@@ -39,6 +40,7 @@ impl MarketOrder {
         match self {
             MarketOrder::New(o) => Some(o),
             MarketOrder::Posted(_) => None,
+            MarketOrder::PartiallyFilled(_) => None,
             MarketOrder::Filled(_) => None,
             MarketOrder::Rejected(_) => None,
         }
@@ -47,6 +49,16 @@ impl MarketOrder {
         match self {
             MarketOrder::New(_) => None,
             MarketOrder::Posted(o) => Some(o),
+            MarketOrder::PartiallyFilled(_) => None,
+            MarketOrder::Filled(_) => None,
+            MarketOrder::Rejected(_) => None,
+        }
+    }
+    pub fn as_partially_filled(self) -> Option<PartiallyFilledMarketOrder> {
+        match self {
+            MarketOrder::New(_) => None,
+            MarketOrder::Posted(_) => None,
+            MarketOrder::PartiallyFilled(o) => Some(o),
             MarketOrder::Filled(_) => None,
             MarketOrder::Rejected(_) => None,
         }
@@ -55,6 +67,7 @@ impl MarketOrder {
         match self {
             MarketOrder::New(_) => None,
             MarketOrder::Posted(_) => None,
+            MarketOrder::PartiallyFilled(_) => None,
             MarketOrder::Filled(o) => Some(o),
             MarketOrder::Rejected(_) => None,
         }
@@ -63,6 +76,7 @@ impl MarketOrder {
         match self {
             MarketOrder::New(_) => None,
             MarketOrder::Posted(_) => None,
+            MarketOrder::PartiallyFilled(_) => None,
             MarketOrder::Filled(_) => None,
             MarketOrder::Rejected(o) => Some(o),
         }
@@ -73,6 +87,7 @@ impl std::fmt::Display for MarketOrder {
         match self {
             Self::New(order) => write!(f, "{order}"),
             Self::Posted(order) => write!(f, "{order}"),
+            Self::PartiallyFilled(order) => write!(f, "{order}"),
             Self::Filled(order) => write!(f, "{order}"),
             Self::Rejected(order) => write!(f, "{order}"),
         }
@@ -118,6 +133,22 @@ impl PostedMarketOrder {
     pub fn add_transaction(&mut self, t: Transaction) {
         self.transactions.push(t);
     }
+    pub fn partial_fill(
+        self,
+        lots: u32,
+        t: Transaction,
+    ) -> PartiallyFilledMarketOrder {
+        let mut order = PartiallyFilledMarketOrder {
+            direction: self.direction,
+            lots: self.lots,
+            broker_id: self.broker_id,
+            executed: 0,
+            transactions: self.transactions,
+        };
+        order.add_fill(lots, t);
+
+        order
+    }
     pub fn fill(self, ts_nanos: i64, commission: f64) -> FilledMarketOrder {
         let operation =
             Operation::build(ts_nanos, &self.transactions, commission);
@@ -140,6 +171,59 @@ impl std::fmt::Display for PostedMarketOrder {
     }
 }
 
+/// Partially filled market order.
+///
+/// # ru
+/// Частично исполненный рыночный ордер. Бывает при недостаточной
+/// ликвидности в стакане, хранит количество уже исполненных лотов
+/// и транзакции.
+#[derive(Debug, PartialEq, Decode, Encode, Clone)]
+pub struct PartiallyFilledMarketOrder {
+    pub direction: Direction,
+    pub lots: u32,
+    pub broker_id: String,
+    pub executed: u32,
+    pub transactions: Vec<Transaction>,
+}
+impl PartiallyFilledMarketOrder {
+    pub fn add_fill(&mut self, lots: u32, t: Transaction) {
+        assert!(self.executed + lots <= self.lots);
+
+        self.executed += lots;
+        self.transactions.push(t);
+    }
+    pub fn remaining(&self) -> u32 {
+        self.lots - self.executed
+    }
+    pub fn is_complete(&self) -> bool {
+        self.executed == self.lots
+    }
+    pub fn fill(self, ts_nanos: i64, commission: f64) -> FilledMarketOrder {
+        let operation =
+            Operation::build(ts_nanos, &self.transactions, commission);
+        FilledMarketOrder {
+            direction: self.direction,
+            lots: self.lots,
+            broker_id: self.broker_id,
+            transactions: self.transactions,
+            operation,
+        }
+    }
+}
+impl std::fmt::Display for PartiallyFilledMarketOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "MarketOrder::PartiallyFilled={} {}/{} id={} t={:?}",
+            self.direction,
+            self.executed,
+            self.lots,
+            self.broker_id,
+            self.transactions
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Decode, Encode, Clone)]
 pub struct FilledMarketOrder {
     pub direction: Direction,
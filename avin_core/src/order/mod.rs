@@ -15,11 +15,11 @@ pub use _order::Order;
 pub use direction::Direction;
 pub use limit_order::{
     CanceledLimitOrder, FilledLimitOrder, LimitOrder, NewLimitOrder,
-    PostedLimitOrder, RejectedLimitOrder,
+    PartiallyFilledLimitOrder, PostedLimitOrder, RejectedLimitOrder,
 };
 pub use market_order::{
-    FilledMarketOrder, MarketOrder, NewMarketOrder, PostedMarketOrder,
-    RejectedMarketOrder,
+    FilledMarketOrder, MarketOrder, NewMarketOrder,
+    PartiallyFilledMarketOrder, PostedMarketOrder, RejectedMarketOrder,
 };
 pub use stop_order::{
     CanceledStopOrder, NewStopOrder, PostedStopOrder, RejectedStopOrder,
@@ -7,7 +7,10 @@
 
 use bitcode::{Decode, Encode};
 
-use crate::{Direction, PostedLimitOrder, PostedMarketOrder};
+use crate::{
+    Direction, LimitOrder, MarketOrder, Order, PostedLimitOrder,
+    PostedMarketOrder,
+};
 
 /// List for select stop order kind.
 ///
@@ -134,7 +137,7 @@ impl StopOrder {
             Self::Posted(o) => Some(&o.broker_id),
             Self::Canceled(o) => Some(&o.broker_id),
             Self::Rejected(_) => None,
-            Self::Triggered(_) => None,
+            Self::Triggered(o) => Some(o.stop_id()),
         }
     }
 }
@@ -203,6 +206,12 @@ pub struct PostedStopOrder {
     pub broker_id: String,
 }
 impl PostedStopOrder {
+    /// Trigger stop order, broker_id - id of spawned exchange order.
+    ///
+    /// # ru
+    /// Срабатывание стоп ордера. Принимает id ордера, который брокер
+    /// выставил на биржу при срабатывании стопа. Сработавший стоп хранит
+    /// свой id и порожденный ордер, так что их можно связать между собой.
     pub fn trigger(self, broker_id: &str) -> TriggeredStopOrder {
         match self.exec_price {
             Some(exec_price) => {
@@ -213,7 +222,10 @@ impl PostedStopOrder {
                     broker_id: broker_id.to_string(),
                     transactions: Vec::new(),
                 };
-                TriggeredStopOrder::Limit(order)
+                TriggeredStopOrder::Limit {
+                    stop_id: self.broker_id,
+                    order,
+                }
             }
             None => {
                 let order = PostedMarketOrder {
@@ -222,7 +234,10 @@ impl PostedStopOrder {
                     broker_id: broker_id.to_string(),
                     transactions: Vec::new(),
                 };
-                TriggeredStopOrder::Market(order)
+                TriggeredStopOrder::Market {
+                    stop_id: self.broker_id,
+                    order,
+                }
             }
         }
     }
@@ -255,16 +270,67 @@ impl std::fmt::Display for PostedStopOrder {
 ///
 /// # ru
 /// Обертка для сработавшего стоп ордера.
+///
+/// Содержит id самого стоп ордера и порожденный им при срабатывании
+/// рыночный или лимитный ордер, который дальше исполняется как обычно.
+/// Брокер присылает эвент со сработавшим стопом, а затем эвенты уже
+/// по порожденному ордеру.
 #[derive(Debug, PartialEq, Decode, Encode, Clone)]
 pub enum TriggeredStopOrder {
-    Market(PostedMarketOrder),
-    Limit(PostedLimitOrder),
+    Market {
+        stop_id: String,
+        order: PostedMarketOrder,
+    },
+    Limit {
+        stop_id: String,
+        order: PostedLimitOrder,
+    },
+}
+impl TriggeredStopOrder {
+    /// Return broker id of stop order.
+    ///
+    /// # ru
+    /// Возвращает id сработавшего стоп ордера.
+    pub fn stop_id(&self) -> &String {
+        match self {
+            Self::Market { stop_id, .. } => stop_id,
+            Self::Limit { stop_id, .. } => stop_id,
+        }
+    }
+    /// Return broker id of spawned order.
+    ///
+    /// # ru
+    /// Возвращает id ордера, порожденного стопом при срабатывании.
+    pub fn order_id(&self) -> &String {
+        match self {
+            Self::Market { order, .. } => &order.broker_id,
+            Self::Limit { order, .. } => &order.broker_id,
+        }
+    }
+    /// Return spawned order.
+    ///
+    /// # ru
+    /// Возвращает порожденный стопом ордер, завернутый в [`Order`].
+    pub fn order(&self) -> Order {
+        match self {
+            Self::Market { order, .. } => {
+                Order::Market(MarketOrder::Posted(order.clone()))
+            }
+            Self::Limit { order, .. } => {
+                Order::Limit(LimitOrder::Posted(order.clone()))
+            }
+        }
+    }
 }
 impl std::fmt::Display for TriggeredStopOrder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Market(order) => write!(f, "Triggered={order}"),
-            Self::Limit(order) => write!(f, "Triggered={order}"),
+            Self::Market { stop_id, order } => {
+                write!(f, "Triggered={stop_id} -> {order}")
+            }
+            Self::Limit { stop_id, order } => {
+                write!(f, "Triggered={stop_id} -> {order}")
+            }
         }
     }
 }
@@ -337,7 +403,10 @@ mod tests {
         assert_eq!(posted.broker_id, "order_id=100500");
 
         let triggered_order = posted.trigger("market_order_id=100501");
-        if let TriggeredStopOrder::Market(order) = triggered_order {
+        assert_eq!(triggered_order.stop_id(), "order_id=100500");
+        assert_eq!(triggered_order.order_id(), "market_order_id=100501");
+        assert!(triggered_order.order().is_market());
+        if let TriggeredStopOrder::Market { order, .. } = triggered_order {
             assert_eq!(order.direction, Direction::Buy);
             assert_eq!(order.lots, 2);
             assert_eq!(order.broker_id, "market_order_id=100501");
@@ -364,7 +433,9 @@ mod tests {
         assert_eq!(posted.broker_id, "order_id=100500");
 
         let triggered_order = posted.trigger("limit_order_id=100501");
-        if let TriggeredStopOrder::Limit(order) = triggered_order {
+        assert_eq!(triggered_order.stop_id(), "order_id=100500");
+        assert!(triggered_order.order().is_limit());
+        if let TriggeredStopOrder::Limit { order, .. } = triggered_order {
             assert_eq!(order.direction, Direction::Buy);
             assert_eq!(order.lots, 2);
             assert_eq!(order.price, 4510.0);
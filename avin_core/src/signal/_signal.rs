@@ -0,0 +1,198 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, TimeDelta, Utc};
+use polars::prelude::{DataFrame, df};
+
+use crate::{Iid, TradeKind};
+
+/// Opinion of strategy about market: direction, strength, price targets
+/// and validity window.
+///
+/// # ru
+/// Сигнал - мнение стратегии о рынке, отделенное от исполнения:
+/// направление, сила от 0 до 1, цены входа, стопа и тейка, и окно
+/// действия [ts, expire). Как работать ордера по сигналу решает
+/// исполнитель, например `Executor` из avin_strategy: размер позиции
+/// по силе, лимитный или рыночный вход, отмена входа после истечения.
+///
+/// Стратегия отправляет сигнал как [`crate::Action::Signal`], трейдер
+/// пишет его в лог, тестер сохраняет все сигналы теста отдельно от
+/// журнала ордеров, чтобы их можно было анализировать сами по себе.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub ts: i64,
+    pub strategy: String,
+    pub iid: Iid,
+    pub kind: TradeKind,
+    /// Сила сигнала, от 0 до 1.
+    pub strength: f64,
+    /// Цена входа, None - вход по рынку.
+    pub entry: Option<f64>,
+    pub stop: Option<f64>,
+    pub take: Option<f64>,
+    /// Время окончания действия сигнала, не включительно.
+    pub expire: i64,
+}
+impl Signal {
+    /// Create new signal, valid for `ttl` after `ts`.
+    ///
+    /// # ru
+    /// Создает сигнал, действующий `ttl` после `ts`. Сила
+    /// ограничивается диапазоном [0, 1]. Цены задаются через
+    /// [`Signal::entry`], [`Signal::stop`], [`Signal::take`].
+    pub fn new(
+        ts: i64,
+        strategy: &str,
+        iid: Iid,
+        kind: TradeKind,
+        strength: f64,
+        ttl: TimeDelta,
+    ) -> Self {
+        Self {
+            ts,
+            strategy: strategy.to_string(),
+            iid,
+            kind,
+            strength: strength.clamp(0.0, 1.0),
+            entry: None,
+            stop: None,
+            take: None,
+            expire: ts + ttl.num_nanoseconds().unwrap(),
+        }
+    }
+    /// Set entry price, without it entry is by market.
+    ///
+    /// # ru
+    /// Устанавливает цену входа, без нее вход по рынку.
+    pub fn entry(mut self, price: f64) -> Self {
+        self.entry = Some(price);
+        self
+    }
+    /// Set stop loss price.
+    ///
+    /// # ru
+    /// Устанавливает цену стоп лосса.
+    pub fn stop(mut self, price: f64) -> Self {
+        self.stop = Some(price);
+        self
+    }
+    /// Set take profit price.
+    ///
+    /// # ru
+    /// Устанавливает цену тейк профита.
+    pub fn take(mut self, price: f64) -> Self {
+        self.take = Some(price);
+        self
+    }
+
+    /// Return DateTime UTC of signal
+    ///
+    /// # ru
+    /// Возвращает дату и время сигнала в UTC таймзоне
+    #[inline]
+    pub fn dt(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(self.ts)
+    }
+    /// Check that signal is valid at ts.
+    ///
+    /// # ru
+    /// Проверяет, что сигнал действует в момент ts.
+    pub fn is_valid(&self, ts: i64) -> bool {
+        self.ts <= ts && ts < self.expire
+    }
+    pub fn is_long(&self) -> bool {
+        self.kind == TradeKind::Long
+    }
+    pub fn is_short(&self) -> bool {
+        self.kind == TradeKind::Short
+    }
+    /// Reward to risk ratio, if entry, stop and take are set.
+    ///
+    /// # ru
+    /// Отношение потенциальной прибыли к риску, если заданы вход,
+    /// стоп и тейк.
+    pub fn ratio(&self) -> Option<f64> {
+        let risk = (self.entry? - self.stop?).abs();
+        let reward = (self.take? - self.entry?).abs();
+        if risk == 0.0 {
+            return None;
+        }
+
+        Some(reward / risk)
+    }
+
+    /// Signals as dataframe, for saving and analysis.
+    ///
+    /// # ru
+    /// Таблица сигналов для сохранения и анализа, например в ноутбуке.
+    pub fn df(signals: &[Signal]) -> DataFrame {
+        let s = signals;
+
+        df!(
+            "ts_nanos" => s.iter().map(|i| i.ts).collect::<Vec<_>>(),
+            "strategy" =>
+                s.iter().map(|i| i.strategy.clone()).collect::<Vec<_>>(),
+            "ticker" =>
+                s.iter().map(|i| i.iid.ticker().clone()).collect::<Vec<_>>(),
+            "kind" => s.iter().map(|i| i.kind.to_str()).collect::<Vec<_>>(),
+            "strength" => s.iter().map(|i| i.strength).collect::<Vec<_>>(),
+            "entry" => s.iter().map(|i| i.entry).collect::<Vec<_>>(),
+            "stop" => s.iter().map(|i| i.stop).collect::<Vec<_>>(),
+            "take" => s.iter().map(|i| i.take).collect::<Vec<_>>(),
+            "expire_nanos" =>
+                s.iter().map(|i| i.expire).collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+}
+impl std::fmt::Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let price = |p: Option<f64>| match p {
+            Some(p) => p.to_string(),
+            None => "-".to_string(),
+        };
+
+        write!(
+            f,
+            "Signal={} {} {} {} {:.2} entry={} stop={} take={}",
+            self.dt(),
+            self.strategy,
+            self.iid,
+            self.kind,
+            self.strength,
+            price(self.entry),
+            price(self.stop),
+            price(self.take),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::iid;
+
+    #[test]
+    fn signal() {
+        let ttl = TimeDelta::minutes(5);
+        let s = Signal::new(0, "s", iid(), TradeKind::Long, 1.5, ttl)
+            .entry(100.0)
+            .stop(98.0)
+            .take(106.0);
+
+        assert_eq!(s.strength, 1.0);
+        assert!(s.is_long());
+        assert!(s.is_valid(0));
+        assert!(s.is_valid(ttl.num_nanoseconds().unwrap() - 1));
+        assert!(!s.is_valid(ttl.num_nanoseconds().unwrap()));
+        assert_eq!(s.ratio(), Some(3.0));
+
+        let df = Signal::df(&[s.clone(), s]);
+        assert_eq!(df.height(), 2);
+    }
+}
@@ -9,6 +9,6 @@ mod _trade;
 mod summary;
 mod trade_list;
 
-pub use _trade::{Trade, TradeKind};
+pub use _trade::{ClosedTrade, Trade, TradeKind};
 pub use summary::Summary;
 pub use trade_list::TradeList;
@@ -66,18 +66,22 @@ pub struct Summary {
     pub gross_profit: f64,
     /// Суммарный убыток всех трейдов.
     pub gross_loss: f64,
+    /// Суммарная комиссия всех трейдов, уже учтена в прибыли.
+    pub commission: f64,
 }
 impl Summary {
     // build
     pub fn new(trade_list: &TradeList) -> Self {
         let name = trade_list.name().to_owned();
 
-        // get results of trades
+        // get results and commissions of trades
         let mut results = Vec::new();
+        let mut commission = 0.0;
         for i in trade_list.trades() {
             if let Trade::Closed(trade) = i {
                 let r = trade.result();
                 results.push(r);
+                commission += trade.commission();
             }
         }
 
@@ -98,6 +102,7 @@ impl Summary {
             max_loss: largest_loss(&results),
             win_seq: max_win_series(&results),
             loss_seq: max_loss_series(&results),
+            commission,
         }
     }
 }
@@ -121,6 +126,7 @@ impl std::fmt::Display for Summary {
             "Ratio" => [round(self.ratio, 2)],
             "Avg" => [round(self.average_trade, 2)],
             "Gross profit/loss" => [gross],
+            "Commission" => [round(self.commission, 2)],
         )
         .unwrap();
 
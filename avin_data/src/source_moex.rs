@@ -54,6 +54,10 @@ impl SourceMoex {
         let till = utc_to_msk(till);
 
         match md {
+            MarketData::BAR_1S | MarketData::BAR_5S => {
+                let msg = format!("{md} from MOEX, build it from tics");
+                Err(AvinError::InvalidValue(msg))
+            }
             MarketData::BAR_1M => self.get_bars(iid, md, from, till).await,
             MarketData::BAR_10M => self.get_bars(iid, md, from, till).await,
             MarketData::BAR_1H => self.get_bars(iid, md, from, till).await,
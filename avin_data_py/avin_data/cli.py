@@ -6,16 +6,39 @@
 # LICENSE:      MIT
 # ============================================================================
 
-import click
+from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
+from pathlib import Path
 
-from avin_data.manager import Manager, MarketData, Source
+import click
+import polars as pl
+
+from avin_data.manager import (
+    BookRecorder,
+    CsvImport,
+    Daemon,
+    DataCheck,
+    Manager,
+    MarketData,
+    Source,
+    SqliteStorage,
+)
 from avin_data.utils import (
     CategoryNotFound,
+    Cmd,
+    InvalidData,
     SourceNotFound,
     TickerNotFound,
+    cfg,
     log,
+    now,
+    str_to_utc,
+    ts_to_dt,
+    utc_to_local,
 )
 
+NOT_BARS = (MarketData.TIC, MarketData.BOOK)
+
 
 @click.group()
 def cli():
@@ -54,11 +77,17 @@ def cli():
 def cache(source: str):
     """Кэширование информации об инструментах
 
-    Пока доступны данные только с Московской биржи.
+    Доступны источники: moex, tinkoff, binance, iss.
+
+    Источник iss - бесплатные публичные данные Мос.биржи, без подписки
+    на Algopack. Сохраняет информацию об инструментах в кэш moex.
     """
 
     if source == "all":
         for i in Source:
+            # ISS пишет в тот же кэш что и MOEX, не перезаписываем его
+            if i == Source.ISS:
+                continue
             Manager.cache(i)
         return
 
@@ -71,22 +100,40 @@ def cache(source: str):
 
 @cli.command()
 @click.option("--instrument", "-i", help="Идентификатор инструмента")
-def find(instrument: str):
+@click.option("--query", "-q", help="Тикер, название, FIGI или ISIN")
+@click.option("--limit", "-n", default=20, help="Количество результатов")
+def find(instrument: str, query: str, limit: int):
     """Поиск информации об инструменте
 
     Формат идентификатора инструмента: <exchange>_<category>_<ticker>
 
-        exchange: [moex]
+        exchange: [moex, binance]
 
-        category: [index, share, bond, future, option, etf]
+        category: [index, share, bond, future, option, etf, spot]
 
         ticker: [gazp, lkoh, rosn, ... ]
 
-    Пример: avin-data find -i moex_share_sber
+    Нечеткий поиск -q: по части тикера, названию, FIGI или ISIN,
+    результаты отсортированы по релевантности.
+
+    Примеры:
+
+        avin-data find -i moex_share_sber
+
+        avin-data find -q сбер
 
+        avin-data find -q RU0009029540
     """
 
     try:
+        if query is not None:
+            iids = Manager.search(query)
+            if not iids:
+                log.info(f"Nothing found: {query}")
+            for iid in iids[:limit]:
+                print(f"{iid!s:<32}{iid.name()}")
+            return
+
         result = Manager.find(instrument)
         print(result.pretty())
     except TickerNotFound as e:
@@ -97,12 +144,67 @@ def find(instrument: str):
         log.error(e)
 
 
+@cli.command("list")
+@click.option("--instrument", "-i", default=None, help="Идентификатор")
+@click.option("--json", "as_json", is_flag=True, help="Вывод в json")
+def list_(instrument, as_json):
+    """Обзор сохраненных данных
+
+    Для каждого инструмента с данными на диске показывает типы
+    данных, период от первого до последнего бара, количество и
+    размер файлов, время последнего обновления (время пользователя).
+
+    Примеры:
+
+        avin-data list
+
+        avin-data list -i moex_share_sber --json
+    """
+
+    try:
+        iid = None if instrument is None else Manager.find(instrument)
+        rows = Manager.catalog(iid)
+
+        if as_json:
+            print(Cmd.to_json_str(rows, indent=4))
+            return
+
+        if not rows:
+            log.info("No stored data")
+            return
+
+        print(
+            f"{'INSTRUMENT':<24}{'DATA':<12}{'FROM':<12}{'TILL':<12}"
+            f"{'FILES':>6}{'SIZE, MB':>10}  UPDATED"
+        )
+        for r in rows:
+            first = ts_to_dt(r["first_ts"]).date()
+            last = ts_to_dt(r["last_ts"]).date()
+            size = r["size"] / 1024 / 1024
+            updated = utc_to_local(DateTime.fromisoformat(r["updated"]))
+            print(
+                f"{r['iid']:<24}{r['market_data']:<12}"
+                f"{first!s:<12}{last!s:<12}"
+                f"{r['files']:>6}{size:>10.1f}  {updated}"
+            )
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
 @cli.command()
 @click.option("--instrument", "-i", help="Идентификатор инструмента")
-@click.option("--source", "-s", default="moex", help="Источник данных")
+@click.option("--source", "-s", default=None, help="Источник данных")
 @click.option("--data", "-d", default="all", help="Тип данных")
+@click.option("--tf", "-t", default=None, help="Таймфреймы: 1m,10m,d")
 @click.option("--year", "-y", help="Год")
-def download(source, instrument, data, year):
+@click.option("--from", "begin", default=None, help="Дата начала (МСК)")
+@click.option("--till", "end", default=None, help="Дата конца (МСК)")
+def download(source, instrument, data, tf, year, begin, end):
     """Загрузка рыночных данных
 
     Примеры:
@@ -122,20 +224,426 @@ def download(source, instrument, data, year):
     4. Загрузить все типы данных Яндекс за все годы:
 
         avin-data download -i moex_share_ydex
+
+    5. Загрузить минутные бары BTCUSDT с Binance за 2024г:
+
+        avin-data download -i binance_spot_btcusdt -d 1m -y 2024
+
+    6. Без подписки Algopack - 10M бары Сбер банка из бесплатного ISS:
+
+        avin-data download -i moex_share_sber -s iss -d 10m -y 2025
+
+    7. Тиковые данные Сбер банка за последний час из Tinkoff:
+
+        avin-data download -i moex_share_sber -s tinkoff -d tic
+
+    8. Только дневные и часовые бары Сбер банка с 2020г:
+
+        avin-data download -i moex_share_sber -t d,1h --from 2020-01-01
+
+    Если источник не указан - выбирается по бирже инструмента.
+    Несколько типов данных (-d или -t) - через запятую. Диапазон
+    --from/--till - полуоткрытый [from, till), даты московские, без
+    --till - до текущего момента. Загруженные за диапазон бары
+    объединяются с сохраненными, без диапазона и года загружаются
+    все доступные данные. Тики сохраняются по дням:
+    TIC/<год>/<YYYY-MM-DD>.parquet, для накопления истории
+    запускайте update (или daemon) в течение дня.
     """
     ALL = ["TIC", "1M", "10M", "1H", "D", "W", "M"]
 
     try:
+        iid = Manager.find(instrument)
+        if source is None:
+            source = Source.from_exchange(iid.exchange())
+        else:
+            source = Source.from_str(source)
+
+        data = tf or data
+        data = ALL if data == "all" else data.split(",")
+        market_data_list = [MarketData.from_str(i.strip()) for i in data]
+
+        period = dict()
+        if begin is not None or end is not None:
+            if year is not None:
+                raise ValueError("Use --year or --from/--till, not both")
+            if begin is None:
+                raise ValueError("--till requires --from")
+            period["begin"] = str_to_utc(begin)
+            period["end"] = now() if end is None else str_to_utc(end)
+        elif year is not None:
+            period["year"] = int(year)
+
+        for market_data in market_data_list:
+            Manager.download(source, iid, market_data, **period)
+
+    except SourceNotFound as e:
+        log.error(e)
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--instrument", "-i", help="Идентификатор инструмента")
+@click.option("--tf", "-t", "data", help="Таймфрейм баров или tic")
+@click.option("--format", "-f", "fmt", default="csv", help="csv или json")
+@click.option("--from", "begin", help="Дата начала (МСК)")
+@click.option("--till", "end", default=None, help="Дата конца (МСК)")
+@click.option("--out", "-o", default=None, help="Файл, иначе в консоль")
+def export(instrument, data, fmt, begin, end, out):
+    """Выгрузка сохраненных данных в csv или json
+
+    Данные выгружаются в полуоткрытом диапазоне [from, till), даты
+    и время - московские, как в str_to_utc. Если till не указан -
+    до текущего момента. В выгрузку добавляется колонка dt - время
+    UTC в формате ISO 8601.
+
+    Примеры:
+
+    1. Дневные бары Сбер банка за 2024г в csv файл:
+
+        avin-data export -i moex_share_sber -t d --from 2024-01-01
+        --till 2025-01-01 -o sber_d.csv
+
+    2. Минутные бары за день в консоль в формате json:
+
+        avin-data export -i moex_share_sber -t 1m -f json
+        --from 2025-06-02 --till 2025-06-03
+    """
+
+    try:
+        iid = Manager.find(instrument)
+        market_data = MarketData.from_str(data)
+        begin = str_to_utc(begin)
+        end = now() if end is None else str_to_utc(end)
+
+        text = Manager.export(
+            iid,
+            market_data,
+            fmt,
+            begin=begin,
+            end=end,
+            path=None if out is None else Path(out),
+        )
+        if out is None:
+            print(text)
+        else:
+            log.info(f"Export saved: {out}")
+
+    except InvalidData as e:
+        log.error(e)
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command("import")
+@click.option("--file", "-f", "file", help="Путь к csv файлу")
+@click.option("--iid", "-i", "instrument", help="Идентификатор инструмента")
+@click.option("--tf", "-t", "data", help="Таймфрейм баров")
+@click.option("--map", "-m", "mapping", help="Соответствие колонок")
+@click.option("--sep", default=",", help="Разделитель колонок")
+@click.option("--dt-format", default=None, help="Формат даты и времени")
+@click.option("--tz", default="Europe/Moscow", help="Таймзона времени в csv")
+def import_(file, instrument, data, mapping, sep, dt_format, tz):
+    """Импорт баров из csv файла
+
+    Бары проверяются, переводятся в формат avin и сохраняются рядом
+    с загруженными данными. При совпадении времени бар из csv заменяет
+    имеющийся.
+
+    Соответствие колонок: поле_avin=колонка_csv через запятую. Поля:
+    time, open, high, low, close, volume - обязательные; date - если
+    дата в отдельной колонке; value - оборот, необязательное.
+
+    Примеры:
+
+    1. Экспорт из QUIK:
+
+        avin-data import -f bars.csv -i moex_share_sber -t 1m
+        --map "time=dt,open=o,high=h,low=l,close=c,volume=v"
+
+    2. Экспорт из Finam:
+
+        avin-data import -f SBER.txt -i moex_share_sber -t 1h --sep ";"
+        --map "date=<DATE>,time=<TIME>,open=<OPEN>,high=<HIGH>,
+        low=<LOW>,close=<CLOSE>,volume=<VOL>" --dt-format "%Y%m%d %H%M%S"
+    """
+
+    try:
+        iid = Manager.find(instrument)
+        market_data = MarketData.from_str(data)
+        columns = CsvImport.parse_map(mapping)
+        Manager.import_csv(
+            iid,
+            market_data,
+            Path(file),
+            columns,
+            separator=sep,
+            dt_format=dt_format,
+            tz=tz,
+        )
+
+    except InvalidData as e:
+        log.error(e)
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--instrument", "-i", help="Идентификатор инструмента")
+def actions(instrument):
+    """Загрузка сплитов и дивидендов
+
+    Загружает корпоративные события акции из ISS Мос.биржи и
+    сохраняет их в ACTIONS.json в папке инструмента. По ним avin
+    корректирует исторические бары, если в конфиге включен флаг
+    data.adjusted (дивиденды - data.adjust_dividends).
+
+    Файл можно дополнить вручную, формат события:
+
+        {"ts_nanos": ..., "kind": "split", "value": 100.0}
+
+    ts_nanos - начало первого дня торгов после события, value -
+    коэффициент сплита (новых акций за одну старую) или дивиденд
+    на акцию.
+
+    Пример:
+
+        avin-data actions -i moex_share_sber
+    """
+
+    try:
+        iid = Manager.find(instrument)
+        result = Manager.update_actions(iid)
+        print(Cmd.to_json_str(result, indent=4))
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--asset", "-a", help="Базовый актив: Si, RI, BR...")
+@click.option("--data", "-d", default="1H", help="Тип данных")
+@click.option("--source", "-s", default="iss", help="Источник данных")
+@click.option("--since", "-y", type=int, help="Экспирация с года")
+@click.option("--roll-days", default=5, help="Переход за N дней")
+@click.option("--adjust", default="difference", help="Корректировка")
+def chain(asset, data, source, since, roll_days, adjust):
+    """Загрузка цепочки фьючерсов
+
+    Загружает бары всех контрактов базового актива, включая
+    истекшие, и печатает секцию [[data.continuous]] для конфига.
+    После добавления секции в конфиг непрерывный фьючерс
+    MOEX_FUTURE_<ASSET> доступен в тестере как обычный инструмент.
+    Переход на следующий контракт - за roll-days дней до
+    экспирации, adjust: none | difference | ratio.
+
+    Пример - часовые бары Si с 2023г:
+
+        avin-data chain -a Si -d 1H -y 2023
+    """
+
+    try:
+        market_data = MarketData.from_str(data)
         source = Source.from_str(source)
+        contracts = Manager.download_chain(
+            asset, market_data, source=source, since=since
+        )
+        if not contracts:
+            return
+
+        # шаг цены известен только у торгующихся контрактов
+        step = contracts[-1]["step"]
+        lines = [
+            "[[data.continuous]]",
+            f'    iid = "MOEX_FUTURE_{asset.upper()}"',
+            f'    name = "{asset} continuous"',
+            f"    step = {float(step)}",
+            f"    roll_days = {roll_days}",
+            f'    adjust = "{adjust}"',
+            "    contracts = [",
+        ]
+        for i in contracts:
+            lines.append(
+                f'        {{ iid = "{i["iid"]}", '
+                f'expiration = "{i["expiration"]}" }},'
+            )
+        lines.append("    ]")
+        print("\n".join(lines))
+
+    except SourceNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--instrument", "-i", default=None, help="Идентификатор")
+@click.option("--source", "-s", default=None, help="Источник данных")
+@click.option("--data", "-d", default=None, help="Тип данных")
+def update(instrument, source, data):
+    """Обновление имеющихся данных
+
+    Для каждого сохраненного типа данных определяет время последнего
+    бара и загружает только недостающие данные, без повторной загрузки
+    всей истории.
+
+    Примеры:
+
+    1. Обновить все имеющиеся данные всех инструментов:
+
+        avin-data update
+
+    2. Обновить все имеющиеся данные Сбер банка:
+
+        avin-data update -i moex_share_sber
+
+    3. Обновить только 10M бары Сбер банка из бесплатного ISS:
+
+        avin-data update -i moex_share_sber -d 10m -s iss
+    """
+
+    if instrument is None:
+        Manager.update_all()
+        return
+
+    try:
         iid = Manager.find(instrument)
+        Manager.update_iid(
+            iid,
+            source=None if source is None else Source.from_str(source),
+            market_data=None if data is None else MarketData.from_str(data),
+        )
 
-        data = ALL if data == "all" else [data]
-        for i in data:
-            market_data = MarketData.from_str(i)
-            if year is None:
-                Manager.download(source, iid, market_data)
-            else:
-                Manager.download(source, iid, market_data, year=int(year))
+    except SourceNotFound as e:
+        log.error(e)
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--instrument", "-i", help="Идентификатор инструмента")
+@click.option("--data", "-d", default=None, help="Тип данных")
+@click.option("--min-gap", default=30, help="Минимальный разрыв, минут")
+@click.option("--out", "-o", default=None, help="Файл для отчета json")
+def check(instrument, data, min_gap, out):
+    """Проверка целостности сохраненных баров
+
+    Ищет пропущенные торговые дни, разрывы внутри дня, повторяющееся
+    и неупорядоченное время баров, бары с нулевым объемом. Отчет
+    выводится в формате json, по нему работает команда repair.
+
+    Примеры:
+
+    1. Проверить все сохраненные бары Сбер банка:
+
+        avin-data check -i moex_share_sber
+
+    2. Проверить 1M бары и сохранить отчет в файл:
+
+        avin-data check -i moex_share_sber -d 1m -o sber_1m.json
+    """
+
+    try:
+        iid = Manager.find(instrument)
+        if data is None:
+            stored = Manager.stored_market_data(iid)
+            data_list = [i for i in stored if i not in NOT_BARS]
+        else:
+            data_list = [MarketData.from_str(data)]
+
+        reports = [
+            DataCheck.check(iid, md, min_gap=TimeDelta(minutes=min_gap))
+            for md in data_list
+        ]
+        for report in reports:
+            _log_check(report)
+
+        if out is None:
+            print(Cmd.to_json_str(reports, indent=4))
+        else:
+            Cmd.write_json(reports, out)
+            log.info(f"Report saved: {out}")
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--instrument", "-i", default=None, help="Идентификатор")
+@click.option("--source", "-s", default=None, help="Источник данных")
+@click.option("--data", "-d", default=None, help="Тип данных")
+@click.option("--min-gap", default=30, help="Минимальный разрыв, минут")
+@click.option("--report", "-r", default=None, help="Отчет команды check")
+def repair(instrument, source, data, min_gap, report):
+    """Исправление пропусков в сохраненных барах
+
+    Загружает из источника только пропущенные торговые дни и разрывы
+    внутри дня, найденные проверкой, и вклеивает их в имеющиеся
+    файлы. Повторяющиеся и неупорядоченные бары исправляются при
+    перезаписи. Файлы заменяются атомарно.
+
+    Примеры:
+
+    1. Проверить и исправить все сохраненные бары Сбер банка:
+
+        avin-data repair -i moex_share_sber
+
+    2. Исправить по ранее сохраненному отчету:
+
+        avin-data check -i moex_share_sber -d 1m -o sber_1m.json
+
+        avin-data repair -r sber_1m.json
+    """
+
+    try:
+        gap = TimeDelta(minutes=min_gap)
+        src = None if source is None else Source.from_str(source)
+
+        if report is not None:
+            for r in Cmd.read_json(report):
+                iid = Manager.find(r["iid"])
+                md = MarketData.from_str(r["market_data"])
+                result = Manager.repair(
+                    iid, md, source=src, report=r, min_gap=gap
+                )
+                _log_check(result)
+            return
+
+        iid = Manager.find(instrument)
+        if data is None:
+            stored = Manager.stored_market_data(iid)
+            data_list = [i for i in stored if i not in NOT_BARS]
+        else:
+            data_list = [MarketData.from_str(data)]
+
+        for md in data_list:
+            result = Manager.repair(iid, md, source=src, min_gap=gap)
+            _log_check(result)
 
     except SourceNotFound as e:
         log.error(e)
@@ -148,10 +656,274 @@ def download(source, instrument, data, year):
 
 
 @cli.command()
-def update():
-    """Обновление имеющихся данных"""
+@click.option("--instrument", "-i", default=None, help="Идентификатор")
+@click.option("--data", "-d", default=None, help="Тип данных")
+def compact(instrument, data):
+    """Перезапись данных с текущими настройками хранения
+
+    Перезаписывает сохраненные файлы со сжатием, размером группы
+    строк и разбиением (по годам или месяцам) из секции конфига
+    data.storage. Без -i - все инструменты с данными на диске, без
+    -d - все сохраненные типы данных.
+
+    Примеры:
+
+        avin-data compact
+
+        avin-data compact -i moex_share_sber -d 1m
+    """
+
+    try:
+        if instrument is None:
+            iids = Manager.tracked_iids()
+        else:
+            iids = [Manager.find(instrument)]
+
+        for iid in iids:
+            if data is None:
+                data_list = Manager.stored_market_data(iid)
+            else:
+                data_list = [MarketData.from_str(data)]
+
+            for md in data_list:
+                r = Manager.compact(iid, md)
+                before = r["size_before"] / 1024 / 1024
+                after = r["size_after"] / 1024 / 1024
+                log.info(
+                    f"{r['iid']} {r['market_data']}: "
+                    f"{r['files_before']} files {before:.1f} MB -> "
+                    f"{r['files_after']} files {after:.1f} MB"
+                )
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command("build-bars")
+@click.option("--instrument", "-i", required=True, help="Идентификатор")
+@click.option(
+    "--tf", "-t", multiple=True, required=True, help="Таймфрейм баров"
+)
+def build_bars(instrument, tf):
+    """Сборка баров из сохраненных тиков
+
+    Собирает бары из тиков на диске, в том числе таймфреймы, которых
+    нет у источников: 1s, 5s. Можно указать несколько -t. Собранные
+    бары объединяются с сохраненными.
+
+        tf: [1s, 5s, 1m, 5m, 10m, 1h]
+
+    Примеры:
+
+        avin-data build-bars -i moex_share_sber -t 1s -t 5s
+
+        avin-data build-bars -i moex_share_sber -t 1m
+    """
+
+    try:
+        iid = Manager.find(instrument)
+        for i in tf:
+            md = MarketData.from_str(i)
+            count = Manager.build_bars(iid, md)
+            log.info(f"{iid} {md.name}: {count} bars")
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--instrument", "-i", default=None, help="Идентификатор")
+@click.option("--dry-run", is_flag=True, help="Только показать файлы")
+def migrate(instrument, dry_run):
+    """Обновление формата сохраненных данных
+
+    Версия схемы хранится в метаданных каждого parquet файла. Команда
+    приводит файлы старых версий к текущей (переименование колонок,
+    единицы времени), перезаписывая их на месте. Без -i - все файлы
+    в папке данных.
+
+    Примеры:
+
+        avin-data migrate --dry-run
+
+        avin-data migrate -i moex_share_sber
+    """
+
+    try:
+        iid = None if instrument is None else Manager.find(instrument)
+        count = Manager.migrate(iid, dry_run=dry_run)
+        if dry_run:
+            log.info(f"Files to migrate: {count}")
+        else:
+            log.info(f"Files migrated: {count}")
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@cli.group()
+def db():
+    """База данных SQLite
+
+    Альтернативное хранилище для avin (в конфиге data.format =
+    "sqlite"): бары и тики всех инструментов в одном файле
+    <data>/avin.sqlite, к которому можно делать SQL запросы.
+    Таблицы: bars (iid, market_data, ts_nanos, open, high, low,
+    close, volume, value) и tics (iid, ts_nanos, direction, lots,
+    price, value, session, tradeno).
+    """
+
+
+@db.command()
+@click.option("--instrument", "-i", default=None, help="Идентификатор")
+@click.option("--data", "-d", default=None, help="Тип данных")
+def sync(instrument, data):
+    """Копирование parquet файлов в базу
+
+    Без -i - все инструменты с данными на диске, без -d - все
+    сохраненные типы данных. Повторный sync обновляет базу.
+
+    Примеры:
+
+        avin-data db sync
+
+        avin-data db sync -i moex_share_sber -d 1m
+    """
+
+    try:
+        storage = SqliteStorage()
+        if instrument is None:
+            iids = Manager.tracked_iids()
+        else:
+            iids = [Manager.find(instrument)]
+
+        for iid in iids:
+            if data is None:
+                data_list = Manager.stored_market_data(iid)
+            else:
+                data_list = [MarketData.from_str(data)]
+
+            for md in data_list:
+                count = storage.sync(iid, md)
+                log.info(f"Sync {iid} {md.name}: {count} rows")
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except Exception as e:
+        log.error(e)
+
+
+@db.command()
+@click.argument("sql")
+def query(sql):
+    """SQL запрос к базе
+
+    Пример:
+
+        avin-data db query "SELECT iid, market_data, COUNT(*) FROM bars
+        GROUP BY iid, market_data"
+    """
+
+    try:
+        df = SqliteStorage().query(sql)
+        with pl.Config(tbl_rows=-1, tbl_cols=-1):
+            print(df)
+
+    except Exception as e:
+        log.error(e)
+
+
+@cli.command()
+@click.option("--instrument", "-i", multiple=True, help="Идентификатор")
+@click.option("--depth", default=None, type=int, help="Глубина стакана")
+@click.option("--interval", default=None, type=float, help="Секунд")
+def record(instrument, depth, interval):
+    """Запись стаканов инструментов на диск
+
+    Подписывается на стаканы (источник Tinkoff) и сохраняет снимки
+    в <инструмент>/BOOK/<год>/<YYYY-MM-DD>.parquet. По умолчанию
+    инструменты, глубина и интервал берутся из секции [data.recorder]
+    конфига. Интервал 0 - сохранять каждое обновление стакана.
+
+    Примеры:
+
+        avin-data record
+
+        avin-data record -i moex_share_sber -i moex_share_gazp --depth 10
+    """
+
+    try:
+        names = instrument or cfg.recorder_instruments
+        if not names:
+            log.error("No instruments to record, see [data.recorder]")
+            return
+
+        if depth is None:
+            depth = cfg.recorder_depth
+        if interval is None:
+            interval = cfg.recorder_interval
+        else:
+            interval = TimeDelta(seconds=interval)
+
+        iids = [Manager.find(i) for i in names]
+        recorder = BookRecorder(iids, depth, interval, cfg.recorder_flush)
+        recorder.run()
+
+    except TickerNotFound as e:
+        log.error(e)
+    except CategoryNotFound as e:
+        log.error(e)
+    except KeyboardInterrupt:
+        log.info("Recorder stopped")
+
+
+@cli.command()
+@click.option("--once", is_flag=True, help="Одно обновление без расписания")
+def daemon(once):
+    """Автоматическое обновление данных по расписанию
+
+    Обновляет все имеющиеся данные по расписанию из конфига (секция
+    [data.daemon], формат cron, время пользователя), повторяет
+    неудачные загрузки и пишет файл статуса <data>/status.json, по
+    которому GUI и трейдер узнают актуальность данных.
+
+    Примеры:
+
+        avin-data daemon
+
+        avin-data daemon --once
+    """
+
+    try:
+        d = Daemon.from_cfg()
+        if once:
+            ok = d.run_once()
+            exit(0 if ok else 1)
+        d.run()
+
+    except ValueError as e:
+        log.error(e)
+    except KeyboardInterrupt:
+        log.info("Daemon stopped")
+
 
-    Manager.update_all()
+def _log_check(report: dict) -> None:
+    status = "ok" if DataCheck.is_ok(report) else "problems found"
+    log.info(f"Check {report['iid']} {report['market_data']}: {status}")
 
 
 if __name__ == "__main__":
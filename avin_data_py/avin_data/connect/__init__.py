@@ -6,10 +6,14 @@
 # LICENSE:      MIT
 # ============================================================================
 
+from avin_data.connect.source_binance import SourceBinance
+from avin_data.connect.source_iss import SourceIss
 from avin_data.connect.source_moex import SourceMoex
 from avin_data.connect.source_tinkoff import SourceTinkoff
 
 __all__ = [
+    "SourceBinance",
+    "SourceIss",
     "SourceMoex",
     "SourceTinkoff",
 ]
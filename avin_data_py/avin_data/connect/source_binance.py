@@ -0,0 +1,415 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+import time
+from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
+
+import httpx
+import polars as pl
+
+from avin_data.manager.category import Category
+from avin_data.manager.iid import Iid
+from avin_data.manager.iid_cache import IidCache
+from avin_data.manager.market_data import MarketData
+from avin_data.manager.source import Source
+from avin_data.utils import (
+    SourceRequestError,
+    TickerNotFound,
+    dt_to_ts,
+    log,
+    now,
+)
+
+SOURCE = Source.BINANCE
+URL = "https://api.binance.com/api/v3"
+MS_TO_NS = 1_000_000
+KLINES_LIMIT = 1000
+TRADES_LIMIT = 1000
+# лимит веса запросов в минуту для IP, при приближении к нему ждем
+# начала следующей минуты, не дожидаясь ответа 429
+WEIGHT_LIMIT = 6000
+WEIGHT_RESERVE = 0.9
+MAX_BACKOFF = 60
+AVAILIBLE = [
+    MarketData.BAR_1M,
+    MarketData.BAR_5M,
+    MarketData.BAR_1H,
+    MarketData.BAR_DAY,
+    MarketData.BAR_WEEK,
+    MarketData.BAR_MONTH,
+    MarketData.TIC,
+]
+SCHEMA = pl.Schema(
+    {
+        "exchange": pl.String,
+        "exchange_specific": pl.String,
+        "category": pl.String,
+        "ticker": pl.String,
+        "figi": pl.String,
+        "country": pl.String,
+        "currency": pl.String,
+        "sector": pl.String,
+        "class_code": pl.String,
+        "isin": pl.String,
+        "uid": pl.String,
+        "name": pl.String,
+        "lot": pl.String,
+        "step": pl.String,
+        "long": pl.String,
+        "short": pl.String,
+        "long_qual": pl.String,
+        "short_qual": pl.String,
+        "first_1m": pl.String,
+        "first_d": pl.String,
+        "qty_step": pl.String,
+    }
+)
+
+
+class SourceBinance:
+    """Binance spot market data, public endpoints without authorization.
+
+    Данные пишутся в тот же формат parquet, что и данные Мос.биржи,
+    поэтому доступны в тестере и анализе без изменений.
+
+    Объем криптовалюты дробный, а в формате avin объем целый. Поэтому
+    объем хранится в минимальных шагах количества (фильтр LOT_SIZE,
+    сохраняется в кэше как "qty_step"), а лот инструмента равен 1.
+    Оборот "value" - в котируемой валюте (для BTCUSDT - в USDT).
+
+    Запросы учитывают вес из заголовка X-MBX-USED-WEIGHT-1M, на 429 и
+    418 ждут Retry-After. Если все попытки неудачны или запрос
+    отклонен - SourceRequestError.
+    """
+
+    # public
+    @classmethod
+    def cache_instruments_info(cls) -> None:
+        log.info("Caching instruments info from Binance")
+
+        response = cls.__try_request("exchangeInfo", {})
+        df = pl.DataFrame(schema=SCHEMA)
+        for symbol in response["symbols"]:
+            if symbol["status"] != "TRADING":
+                continue
+            row = pl.DataFrame(_extract_info(symbol), schema=SCHEMA)
+            df.extend(row)
+
+        cache = IidCache(SOURCE, Category.SPOT, df)
+        IidCache.save(cache)
+
+    @classmethod
+    def find(cls, s: str) -> Iid:
+        """Get instrument id from str.
+
+        Args:
+            s: search querry. Example: "binance_spot_btcusdt".
+
+        Returns:
+            Iid.
+
+        Raises:
+            TickerNotFound if not exists.
+        """
+        exchange_str, category_str, ticker_str = s.upper().split("_")
+        assert exchange_str == "BINANCE", f"Not supported {exchange_str}"
+        category = Category.from_str(category_str)
+
+        cache = IidCache.load(SOURCE, category)
+        df = cache.df().filter(pl.col("ticker") == ticker_str)
+        if df.is_empty():
+            df = IidCache.load_archive(SOURCE, category)
+            if not df.is_empty():
+                df = df.filter(pl.col("ticker") == ticker_str)
+        if len(df) != 1:
+            raise TickerNotFound(f"Cannot find ticker {ticker_str}")
+
+        info = {k: df.item(0, k) for k in df.columns}
+        return Iid(info)
+
+    @classmethod
+    def get_market_data(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        *,
+        begin: DateTime | None = None,
+        end: DateTime | None = None,
+        tradeno: int | None = None,
+    ) -> pl.DataFrame:
+        if market_data not in AVAILIBLE:
+            log.warning(f"Market data unavailible {iid}-{market_data}")
+            return pl.DataFrame()
+
+        match market_data:
+            case MarketData.TIC:
+                df = cls.__get_tics(iid, tradeno)
+            case _:  # bars
+                assert begin is not None
+                assert end is not None
+                df = cls.__get_bars(iid, market_data, begin, end)
+
+        return df
+
+    # private
+    @classmethod
+    def __try_request(cls, method: str, params: dict) -> dict | list:
+        # 429 - превышен лимит запросов, 418 - бан по IP после
+        # повторных 429: ждем сколько сказано в Retry-After. Остальные
+        # ошибки клиента не повторяем, ошибки сервера и сети - повторяем
+        # с растущей паузой
+        MAX_ATTEMPT = 5
+        delay = 3
+
+        for attempt in range(1, MAX_ATTEMPT + 1):
+            try:
+                response = httpx.get(f"{URL}/{method}", params=params)
+            except (httpx.ConnectError, httpx.TimeoutException) as e:
+                error = f"{type(e).__name__}: {e}"
+                wait = delay
+            else:
+                if response.is_success:
+                    cls.__respect_weight(response)
+                    return response.json()
+
+                error = f"HTTP {response.status_code}: {response.text}"
+                if response.status_code in (418, 429):
+                    wait = _retry_after(response)
+                elif response.is_server_error:
+                    wait = delay
+                else:
+                    raise SourceRequestError(
+                        f"Binance {method} {params}: {error}"
+                    )
+
+            if attempt == MAX_ATTEMPT:
+                break
+
+            log.warning(
+                f"Binance {method}: {error}, attempt {attempt}/"
+                f"{MAX_ATTEMPT}, try again after {wait} sec"
+            )
+            time.sleep(wait)
+            delay = min(delay * 2, MAX_BACKOFF)
+
+        raise SourceRequestError(
+            f"Binance {method} {params} failed after {MAX_ATTEMPT} "
+            f"attempts: {error}"
+        )
+
+    @classmethod
+    def __respect_weight(cls, response: httpx.Response) -> None:
+        used = response.headers.get("X-MBX-USED-WEIGHT-1M")
+        if used is None or int(used) < WEIGHT_LIMIT * WEIGHT_RESERVE:
+            return
+
+        wait = 60 - DateTime.now().second
+        log.info(f"Binance weight {used}/{WEIGHT_LIMIT}, wait {wait} sec")
+        time.sleep(wait)
+
+    @classmethod
+    def __get_bars(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        begin: DateTime,
+        end: DateTime,
+    ) -> pl.DataFrame:
+        interval = _to_binance_interval(market_data)
+        # Binance отдает и текущую незавершенную свечу, отбрасываем ее
+        end = min(end, market_data.prev_dt(now()))
+
+        # запрос полузакрытого диапазона [begin, end), частями по 1000
+        rows = list()
+        current = dt_to_ts(begin) // MS_TO_NS
+        end_ms = dt_to_ts(end) // MS_TO_NS - 1
+        while current <= end_ms:
+            params = {
+                "symbol": iid.ticker(),
+                "interval": interval,
+                "startTime": current,
+                "endTime": end_ms,
+                "limit": KLINES_LIMIT,
+            }
+            part = cls.__try_request("klines", params)
+            if not part:
+                break
+
+            rows.extend(part)
+            current = part[-1][0] + 1
+
+        if not rows:
+            return pl.DataFrame()
+
+        return _format_bars_df(rows, _qty_step(iid))
+
+    @classmethod
+    def __get_tics(cls, iid: Iid, tradeno: int | None) -> pl.DataFrame:
+        # без номера сделки - тики за сегодня, начиная с 00:00 UTC.
+        # Binance отдает aggTrades с фильтром по времени только в окне
+        # не больше часа, поэтому первую сделку ищем по часовым окнам
+        # (в пустом окне сделок нет - переходим к следующему), далее
+        # запросы по id
+        by_id = tradeno is not None
+        if not by_id:
+            part = cls.__first_tics(iid)
+        else:
+            params = {
+                "symbol": iid.ticker(),
+                "fromId": tradeno,
+                "limit": TRADES_LIMIT,
+            }
+            part = cls.__try_request("aggTrades", params)
+
+        rows = list()
+        while part:
+            rows.extend(part)
+            # неполная часть по id - сделок больше нет, а часть из
+            # часового окна неполная, если окно кончилось раньше лимита
+            if len(part) < TRADES_LIMIT and by_id:
+                break
+            by_id = True
+
+            params = {
+                "symbol": iid.ticker(),
+                "fromId": part[-1]["a"] + 1,
+                "limit": TRADES_LIMIT,
+            }
+            part = cls.__try_request("aggTrades", params)
+
+        if not rows:
+            return pl.DataFrame()
+
+        return _format_tics_df(rows, _qty_step(iid))
+
+    @classmethod
+    def __first_tics(cls, iid: Iid) -> list:
+        day = now().replace(hour=0, minute=0, second=0, microsecond=0)
+        begin = dt_to_ts(day) // MS_TO_NS
+        end = dt_to_ts(now()) // MS_TO_NS
+        hour = int(TimeDelta(hours=1).total_seconds() * 1000)
+
+        while begin <= end:
+            params = {
+                "symbol": iid.ticker(),
+                "startTime": begin,
+                "endTime": begin + hour - 1,
+                "limit": TRADES_LIMIT,
+            }
+            part = cls.__try_request("aggTrades", params)
+            if part:
+                return part
+
+            begin += hour
+
+        return list()
+
+
+def _retry_after(response: httpx.Response) -> int:
+    value = response.headers.get("Retry-After", "")
+    return int(value) if value.isdigit() else MAX_BACKOFF
+
+
+def _extract_info(symbol: dict) -> dict:
+    filters = {i["filterType"]: i for i in symbol["filters"]}
+    price_step = filters["PRICE_FILTER"]["tickSize"]
+    qty_step = filters["LOT_SIZE"]["stepSize"]
+
+    info = {
+        "exchange": "BINANCE",
+        "exchange_specific": "BINANCE_SPOT",
+        "category": Category.SPOT.name,
+        "ticker": symbol["symbol"],
+        # NOTE: Binance not provide figi... using unique fake value
+        "figi": f"figi_BINANCE_SPOT_{symbol['symbol']}",
+        "country": "",
+        "currency": symbol["quoteAsset"],
+        "sector": "",
+        "class_code": "SPOT",
+        "isin": "",
+        "uid": "",
+        "name": f"{symbol['baseAsset']}/{symbol['quoteAsset']}",
+        "lot": "1",
+        "step": str(float(price_step)),
+        "long": "",
+        "short": "",
+        "long_qual": "",
+        "short_qual": "",
+        "first_1m": "",
+        "first_d": "",
+        "qty_step": str(float(qty_step)),
+    }
+
+    return info
+
+
+def _qty_step(iid: Iid) -> float:
+    return float(iid.info()["qty_step"])
+
+
+def _to_binance_interval(market_data: MarketData) -> str:
+    intervals = {
+        MarketData.BAR_1M: "1m",
+        MarketData.BAR_5M: "5m",
+        MarketData.BAR_1H: "1h",
+        MarketData.BAR_DAY: "1d",
+        MarketData.BAR_WEEK: "1w",
+        MarketData.BAR_MONTH: "1M",
+    }
+
+    return intervals[market_data]
+
+
+def _format_bars_df(rows: list, qty_step: float) -> pl.DataFrame:
+    # kline: [open_time, open, high, low, close, volume, close_time,
+    #         quote_volume, trades, taker_base, taker_quote, ignore]
+    df = pl.DataFrame(
+        {
+            "ts_nanos": [int(i[0]) * MS_TO_NS for i in rows],
+            "open": [float(i[1]) for i in rows],
+            "high": [float(i[2]) for i in rows],
+            "low": [float(i[3]) for i in rows],
+            "close": [float(i[4]) for i in rows],
+            "volume": [round(float(i[5]) / qty_step) for i in rows],
+            "value": [float(i[7]) for i in rows],
+        },
+        schema_overrides={"ts_nanos": pl.Int64, "volume": pl.Int64},
+    )
+
+    return df
+
+
+def _format_tics_df(rows: list, qty_step: float) -> pl.DataFrame:
+    # aggTrade: {a: id, p: price, q: qty, T: time, m: is_buyer_maker}
+    # если покупатель - мейкер, значит агрессор продавец
+    df = pl.DataFrame(
+        {
+            "ts_nanos": [int(i["T"]) * MS_TO_NS for i in rows],
+            "direction": ["S" if i["m"] else "B" for i in rows],
+            "lots": [round(float(i["q"]) / qty_step) for i in rows],
+            "price": [float(i["p"]) for i in rows],
+            "value": [float(i["p"]) * float(i["q"]) for i in rows],
+            "session": [None for _ in rows],
+            "tradeno": [int(i["a"]) for i in rows],
+        },
+        schema_overrides={
+            "ts_nanos": pl.Int64,
+            "lots": pl.Int64,
+            "session": pl.Int8,
+            "tradeno": pl.Int64,
+        },
+    )
+
+    return df
+
+
+if __name__ == "__main__":
+    ...
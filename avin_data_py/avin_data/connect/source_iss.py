@@ -0,0 +1,324 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+import time
+from datetime import UTC
+from datetime import date as Date
+from datetime import datetime as DateTime
+from datetime import time as Time
+from datetime import timedelta as TimeDelta
+
+import httpx
+import polars as pl
+
+from avin_data.manager.category import Category
+from avin_data.manager.iid import Iid
+from avin_data.manager.iid_cache import IidCache
+from avin_data.manager.market_data import MarketData
+from avin_data.manager.source import Source
+from avin_data.utils import dt_to_ts, log, now
+
+URL = "https://iss.moex.com/iss"
+MSK_OFFSET = TimeDelta(hours=3)
+DELAY = TimeDelta(minutes=15)
+T1_SINCE = Date(2023, 7, 31)
+AVAILIBLE = [
+    MarketData.BAR_10M,
+    MarketData.BAR_1H,
+    MarketData.BAR_DAY,
+    MarketData.BAR_WEEK,
+    MarketData.BAR_MONTH,
+]
+BOARDS = {
+    Category.SHARE: ("stock", "shares", "TQBR"),
+    Category.INDEX: ("stock", "index", "SNDX"),
+    Category.FUTURE: ("futures", "forts", "RFUD"),
+    Category.CURRENCY: ("currency", "selt", "CETS"),
+}
+
+
+class SourceIss:
+    """Public MOEX ISS, free market data without Algopack.
+
+    Бесплатный источник данных Мос.биржи: не требует ни токена, ни
+    логина. Позволяет кэшировать информацию об инструментах и загрузить
+    бары 10M, 1H, D, W, M, чтобы попробовать тестер до подписки на
+    Algopack. Ограничения ISS: внутридневные данные приходят с
+    задержкой 15 минут, минутных баров и тиков нет.
+
+    Информация об инструментах сохраняется в тот же кэш, что и у
+    источника MOEX, поэтому поиск инструментов работает одинаково.
+    """
+
+    # public
+    @classmethod
+    def cache_instruments_info(cls) -> None:
+        log.info("Caching instruments info from MOEX ISS")
+
+        for category in BOARDS:
+            df = cls.__request_instruments(category)
+            cache = IidCache(Source.MOEX, category, df)
+            IidCache.save(cache)
+
+    @classmethod
+    def get_market_data(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        *,
+        begin: DateTime | None = None,
+        end: DateTime | None = None,
+        tradeno: int | None = None,
+    ) -> pl.DataFrame:
+        if market_data not in AVAILIBLE:
+            log.warning(f"Market data unavailible on ISS {market_data}")
+            return pl.DataFrame()
+
+        assert begin is not None
+        assert end is not None
+        df = cls.__get_bars(iid, market_data, begin, end)
+
+        return df
+
+    @classmethod
+    def get_corporate_actions(cls, iid: Iid) -> list[dict]:
+        """Request splits and dividends of share.
+
+        Возвращает события в формате ACTIONS.json (см. avin_core
+        CorporateActions): ts_nanos - начало первого дня торгов после
+        события, kind - "split" | "dividend", value - коэффициент
+        сплита (after / before) или дивиденд на акцию.
+        """
+        if iid.category() != Category.SHARE:
+            return list()
+
+        actions = list()
+
+        path = f"statistics/engines/stock/splits/{iid.ticker()}"
+        splits = _to_df(cls.__try_request(path, {})["splits"])
+        for row in splits.iter_rows(named=True):
+            day = Date.fromisoformat(row["tradedate"])
+            actions.append(
+                {
+                    "ts_nanos": _msk_day_ts(day),
+                    "kind": "split",
+                    "value": row["after"] / row["before"],
+                }
+            )
+
+        path = f"securities/{iid.ticker()}/dividends"
+        dividends = _to_df(cls.__try_request(path, {})["dividends"])
+        for row in dividends.iter_rows(named=True):
+            registry = Date.fromisoformat(row["registryclosedate"])
+            actions.append(
+                {
+                    "ts_nanos": _msk_day_ts(_ex_date(registry)),
+                    "kind": "dividend",
+                    "value": float(row["value"]),
+                }
+            )
+
+        return sorted(actions, key=lambda i: i["ts_nanos"])
+
+    @classmethod
+    def get_futures_chain(cls, asset: str) -> pl.DataFrame:
+        """Request all futures contracts of asset, including expired.
+
+        Возвращает датафрейм с колонками ticker, name, expiration
+        (дата экспирации, строка YYYY-MM-DD), отсортированный по дате
+        экспирации. asset - код базового актива: Si, RI, BR...
+        """
+        path = "statistics/engines/futures/markets/forts/series"
+        params = {"asset_code": asset, "show_expired": 1}
+        df = _to_df(cls.__try_request(path, params)["series"])
+        if df.is_empty():
+            return pl.DataFrame(
+                schema={
+                    "ticker": pl.String,
+                    "name": pl.String,
+                    "expiration": pl.String,
+                }
+            )
+
+        df = df.rename({i: i.lower() for i in df.columns})
+        df = df.select(
+            pl.col("secid").alias("ticker"),
+            pl.col("name"),
+            pl.col("expiration_date").cast(pl.String).alias("expiration"),
+        )
+
+        return df.sort("expiration")
+
+    # private
+    @classmethod
+    def __try_request(cls, path: str, params: dict) -> dict:
+        MAX_ATTEMPT = 5
+        attempt = 0
+
+        params = {"iss.meta": "off", **params}
+        while attempt < MAX_ATTEMPT:
+            try:
+                response = httpx.get(f"{URL}/{path}.json", params=params)
+                response.raise_for_status()
+                return response.json()
+
+            except httpx.ConnectError as e:
+                log.warning(f"ConnectError: {e}. Try again after 3 sec")
+                time.sleep(3)
+
+            except httpx.ConnectTimeout as e:
+                log.warning(f"ConnectTimeout: {e}. Try again after 3 sec")
+                time.sleep(3)
+
+            except httpx.HTTPStatusError as e:
+                log.warning(f"HTTPStatusError: {e}. Try again after 3 sec")
+                time.sleep(3)
+
+            attempt += 1
+
+        log.error(f"Request failed: {path} {params}")
+        exit(1)
+
+    @classmethod
+    def __request_instruments(cls, category: Category) -> pl.DataFrame:
+        engine, market, board = BOARDS[category]
+        path = (
+            f"engines/{engine}/markets/{market}/boards/{board}/securities"
+        )
+        response = cls.__try_request(path, {})
+        df = _to_df(response["securities"])
+
+        # приводим к формату кэша источника MOEX
+        df = df.rename({i: i.lower() for i in df.columns})
+        df = df.rename({"secid": "ticker"})
+        columns = ["lotsize", "decimals", "minstep"]
+        for name in columns:
+            if name in df.columns:
+                df = df.with_columns(pl.col(name).cast(pl.String))
+
+        return df
+
+    @classmethod
+    def __get_bars(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        begin: DateTime,
+        end: DateTime,
+    ) -> pl.DataFrame:
+        engine, market, board = BOARDS[iid.category()]
+        path = (
+            f"engines/{engine}/markets/{market}/boards/{board}"
+            f"/securities/{iid.ticker()}/candles"
+        )
+
+        # ISS отдает свечи включая края [from, till], дата без времени,
+        # поэтому лишнее отбрасывается после запроса
+        params = {
+            "from": str((begin + MSK_OFFSET).date()),
+            "till": str((end + MSK_OFFSET).date()),
+            "interval": _to_iss_interval(market_data),
+        }
+
+        # ответ частями по 500 свечей
+        parts = list()
+        start = 0
+        while True:
+            response = cls.__try_request(path, {**params, "start": start})
+            part = _to_df(response["candles"])
+            if part.is_empty():
+                break
+
+            parts.append(part)
+            start += len(part)
+
+        if not parts:
+            return pl.DataFrame()
+
+        df = _format_bars_df(pl.concat(parts))
+
+        # полузакрытый диапазон [begin, end), без незавершенной свечи
+        # и без свечей, попадающих в задержку данных ISS. Начало текущей
+        # свечи считается по московскому времени, так как дневные и
+        # более крупные свечи начинаются в 00:00 МСК
+        msk_now = now() - DELAY + MSK_OFFSET
+        available = market_data.prev_dt(msk_now) - MSK_OFFSET
+        till = min(end, available)
+        df = df.filter(
+            (pl.col("ts_nanos") >= dt_to_ts(begin))
+            & (pl.col("ts_nanos") < dt_to_ts(till))
+        )
+
+        return df
+
+
+def _to_df(block: dict) -> pl.DataFrame:
+    columns = block["columns"]
+    rows = block["data"]
+    if not rows:
+        return pl.DataFrame()
+
+    return pl.DataFrame(rows, schema=columns, orient="row")
+
+
+def _msk_day_ts(day: Date) -> int:
+    msk = DateTime.combine(day, Time(), UTC)
+    return dt_to_ts(msk - MSK_OFFSET)
+
+
+def _ex_date(registry: Date) -> Date:
+    # дата без дивиденда - рабочий день перед датой закрытия реестра
+    # (режим T+1 с 31.07.2023, до этого T+2). Праздники не учитываются
+    days = 1 if registry >= T1_SINCE else 2
+    ex = registry
+    while days > 0:
+        ex -= TimeDelta(days=1)
+        if ex.weekday() < 5:
+            days -= 1
+
+    return ex
+
+
+def _to_iss_interval(market_data: MarketData) -> int:
+    intervals = {
+        MarketData.BAR_10M: 10,
+        MarketData.BAR_1H: 60,
+        MarketData.BAR_DAY: 24,
+        MarketData.BAR_WEEK: 7,
+        MarketData.BAR_MONTH: 31,
+    }
+
+    return intervals[market_data]
+
+
+def _format_bars_df(bars: pl.DataFrame) -> pl.DataFrame:
+    # begin: "2025-01-03 10:00:00" московское время
+    timestamps = list()
+    for s in bars["begin"]:
+        msk = DateTime.fromisoformat(s).replace(tzinfo=UTC)
+        timestamps.append(dt_to_ts(msk - MSK_OFFSET))
+
+    df = pl.DataFrame(
+        {
+            "ts_nanos": pl.Series(timestamps, dtype=pl.Int64),
+            "open": bars["open"].cast(pl.Float64),
+            "high": bars["high"].cast(pl.Float64),
+            "low": bars["low"].cast(pl.Float64),
+            "close": bars["close"].cast(pl.Float64),
+            "volume": bars["volume"].cast(pl.Int64),
+            "value": bars["value"].cast(pl.Float64),
+        }
+    )
+
+    return df
+
+
+if __name__ == "__main__":
+    ...
@@ -9,10 +9,12 @@
 from __future__ import annotations
 
 import time
+from collections.abc import Callable
 from datetime import UTC
 from datetime import date as Date
 from datetime import datetime as DateTime
 from datetime import timedelta as TimeDelta
+from typing import TypeVar
 
 import httpx
 import moexalgo
@@ -26,6 +28,8 @@ from avin_data.manager.source import Source
 from avin_data.utils import (
     CategoryNotFound,
     Cmd,
+    SourceAuthError,
+    SourceRequestError,
     TickerNotFound,
     cfg,
     dt_to_ts,
@@ -49,11 +53,24 @@ AVAILIBLE = [
     MarketData.ORDER_STATS,
     MarketData.OB_STATS,
 ]
+MAX_BACKOFF = 60  # seconds
+T = TypeVar("T")
 
 
 class SourceMoex:
+    """MOEX Algopack market data.
+
+    Все запросы идут через __request: не чаще connect.moex_rate в
+    секунду, при таймауте, ошибке соединения, 429 или 5xx - повтор
+    с паузой connect.moex_backoff, удваивающейся после каждой неудачи,
+    до connect.moex_retries попыток. Отказ в доступе (401/403, нет
+    подписки Algopack) не повторяется - SourceAuthError. Если все
+    попытки неудачны - SourceRequestError со сводкой ошибок.
+    """
+
     __token = None
     __account = None
+    __last_request = 0.0
 
     # public
     @classmethod
@@ -66,19 +83,12 @@ class SourceMoex:
         # moex_categories = ["index", "shares", "currency", "futures"]
         moex_categories = ["index", "shares", "futures", "currency"]
         for i in moex_categories:
-            try:
-                df = cls.__request_instruments(i)
-                category = cls.__to_avin_category(i)
-                cache = IidCache(SOURCE, category, df)
-                IidCache.save(cache)
-
-            except httpx.ConnectError as e:
-                log.warning(f"ConnectError: {e}. Try again after 5 sec")
-                time.sleep(5)
-
-            except httpx.ConnectTimeout as e:
-                log.warning(f"ConnectTimeout: {e}. Try again after 5 sec")
-                time.sleep(5)
+            df = cls.__request(
+                f"instruments {i}", lambda: cls.__request_instruments(i)
+            )
+            category = cls.__to_avin_category(i)
+            cache = IidCache(SOURCE, category, df)
+            IidCache.save(cache)
 
     @classmethod
     def find(
@@ -107,8 +117,12 @@ class SourceMoex:
         cache = IidCache.load(SOURCE, category)
         df = cache.df()
 
-        # try find ticker
+        # try find ticker, then in archive of delisted
         df = df.filter(pl.col("ticker") == ticker_str)
+        if df.is_empty():
+            df = IidCache.load_archive(SOURCE, category)
+            if not df.is_empty():
+                df = df.filter(pl.col("ticker") == ticker_str)
         if len(df) != 1:
             raise TickerNotFound(f"Cannot find ticker {ticker_str}")
 
@@ -137,6 +151,8 @@ class SourceMoex:
             "lot": lotsize,
             "step": step,
         }
+        if "delisted" in df.columns:
+            info["delisted"] = df.item(0, "delisted")
 
         return Iid(info)
 
@@ -243,7 +259,9 @@ class SourceMoex:
             "MOEX not exist account file, operations with "
             "market data unavailible. Register and put the file with "
             f"login and password in '{account_path}'. Read more:\n"
-            "https://passport.moex.com/registration"
+            "https://passport.moex.com/registration\n"
+            "Without Algopack use free source MOEX ISS (delayed, no 1M "
+            "bars and tics): avin-data download -s iss ..."
         )
         exit(1)
 
@@ -289,27 +307,60 @@ class SourceMoex:
         return names[name]
 
     @classmethod
-    def __to_moex_ticker(cls, iid: Iid) -> moexalgo.AnyTickers:
-        MAX_ATTEMPT = 5
-        attempt = 0
+    def __request(cls, what: str, func: Callable[[], T]) -> T:
+        retries = max(cfg.moex_retries, 1)
+        delay = cfg.moex_backoff
+        failures: dict[str, int] = dict()
 
-        while attempt < MAX_ATTEMPT:
+        for attempt in range(1, retries + 1):
+            cls.__throttle()
             try:
-                moex_ticker = moexalgo.Ticker(iid.ticker())
-                return moex_ticker
+                return func()
+
+            except Exception as e:
+                kind = _failure_kind(e)
+                if kind == "auth":
+                    raise SourceAuthError(
+                        f"MOEX access denied: {what} ({e}). Check account "
+                        "and Algopack subscription: "
+                        "https://data.moex.com/products/algopack"
+                    ) from e
+                if kind is None:
+                    raise
+
+                failures[kind] = failures.get(kind, 0) + 1
+                if attempt == retries:
+                    break
+
+                log.warning(
+                    f"MOEX {what}: {kind} ({e}), attempt {attempt}/"
+                    f"{retries}, try again after {delay} sec"
+                )
+                time.sleep(delay)
+                delay = min(delay * 2, MAX_BACKOFF)
 
-            except httpx.ConnectError as e:
-                log.warning(f"ConnectError: {e}. Try again after 3 sec")
-                time.sleep(3)
+        summary = ", ".join(f"{k} x{n}" for k, n in failures.items())
+        raise SourceRequestError(
+            f"MOEX {what} failed after {retries} attempts: {summary}. "
+            "Server or network problem, try again later"
+        )
 
-            except httpx.ConnectTimeout as e:
-                log.warning(f"ConnectTimeout: {e}. Try again after 3 sec")
-                time.sleep(3)
+    @classmethod
+    def __throttle(cls) -> None:
+        if cfg.moex_rate <= 0:
+            return
 
-            attempt += 1
+        interval = 1 / cfg.moex_rate
+        wait = cls.__last_request + interval - time.monotonic()
+        if wait > 0:
+            time.sleep(wait)
+        cls.__last_request = time.monotonic()
 
-        log.error(f"Request ticker failed: {iid}")
-        exit(1)
+    @classmethod
+    def __to_moex_ticker(cls, iid: Iid) -> moexalgo.AnyTickers:
+        return cls.__request(
+            f"ticker {iid}", lambda: moexalgo.Ticker(iid.ticker())
+        )
 
     @classmethod
     def __to_moex_period(
@@ -421,31 +472,17 @@ class SourceMoex:
         begin: DateTime,
         end: DateTime,
     ) -> pl.DataFrame:
-        MAX_ATTEMPT = 5
-        attempt = 0
-
-        while attempt < MAX_ATTEMPT:
-            try:
-                df = moex_ticker.candles(
-                    start=begin,
-                    end=end,
-                    period=period,
-                    use_dataframe=True,
-                )
-                return pl.from_pandas(df)
-
-            except httpx.ConnectError as e:
-                log.warning(f"ConnectError: {e}. Try again after 3 sec")
-                time.sleep(3)
-
-            except httpx.ConnectTimeout as e:
-                log.warning(f"ConnectTimeout: {e}. Try again after 3 sec")
-                time.sleep(3)
-
-            attempt += 1
+        df = cls.__request(
+            f"candles {period} {begin}",
+            lambda: moex_ticker.candles(
+                start=begin,
+                end=end,
+                period=period,
+                use_dataframe=True,
+            ),
+        )
 
-        log.error("Request data failed")
-        exit(1)
+        return pl.from_pandas(df)
 
     @classmethod
     def __get_tics(
@@ -486,29 +523,35 @@ class SourceMoex:
         moex_ticker: moexalgo.AnyTickers,
         tradeno: int | None,
     ) -> pl.DataFrame:
-        MAX_ATTEMPT = 5
-        attempt = 0
+        df = cls.__request(
+            f"trades from {tradeno}",
+            lambda: moex_ticker.trades(
+                tradeno=tradeno,
+                use_dataframe=True,
+            ),
+        )
 
-        while attempt < MAX_ATTEMPT:
-            try:
-                df = moex_ticker.trades(
-                    tradeno=tradeno,
-                    use_dataframe=True,
-                )
-                return pl.from_pandas(df)
+        return pl.from_pandas(df)
 
-            except httpx.ConnectError as e:
-                log.warning(f"ConnectError: {e}. Try again after 3 sec")
-                time.sleep(3)
 
-            except httpx.ConnectTimeout as e:
-                log.warning(f"ConnectTimeout: {e}. Try again after 3 sec")
-                time.sleep(3)
+def _failure_kind(e: Exception) -> str | None:
+    """Kind of request failure: "auth", transient kind or None.
 
-            attempt += 1
+    None - ошибка не связана с сетью или сервером, повтор не поможет.
+    """
+    if isinstance(e, httpx.HTTPStatusError):
+        code = e.response.status_code
+        if code in (401, 403):
+            return "auth"
+        if code == 429 or code >= 500:
+            return f"http {code}"
+        return None
+    if isinstance(e, httpx.TimeoutException):
+        return "timeout"
+    if isinstance(e, httpx.TransportError):
+        return "connection"
 
-        log.error("Request data failed")
-        exit(1)
+    return None
 
 
 def _format_indexes_info(df: pl.DataFrame) -> pl.DataFrame:
@@ -8,16 +8,27 @@
 
 from __future__ import annotations
 
+from collections.abc import Iterator
+from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
+
 import polars as pl
 import tinkoff.invest as ti
 
 from avin_data.manager.category import Category
+from avin_data.manager.iid import Iid
 from avin_data.manager.iid_cache import IidCache
+from avin_data.manager.market_data import MarketData
 from avin_data.manager.source import Source
-from avin_data.utils import Cmd, cfg, dt_to_ts, log
+from avin_data.utils import Cmd, TickerNotFound, cfg, dt_to_ts, log, now
 
 SOURCE = Source.TINKOFF
 TARGET = ti.constants.INVEST_GRPC_API
+AVAILIBLE = [
+    MarketData.TIC,
+]
+# GetLastTrades отдает обезличенные сделки только за последний час
+TRADES_WINDOW = TimeDelta(hours=1)
 SCHEMA = pl.Schema(
     {
         "exchange": pl.String,
@@ -64,6 +75,61 @@ class SourceTinkoff:
 
             IidCache.save(cache)
 
+    @classmethod
+    def get_market_data(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        *,
+        begin: DateTime | None = None,
+        end: DateTime | None = None,
+        tradeno: int | None = None,
+    ) -> pl.DataFrame:
+        if market_data not in AVAILIBLE:
+            log.error(f"Market data unavailible {iid}-{market_data}")
+            exit(1)
+
+        # Without authorization - not work
+        cls.__ensure_auth()
+
+        match market_data:
+            case MarketData.TIC:
+                df = cls.__get_tics(iid, begin, end)
+
+        return df
+
+    @classmethod
+    def stream_order_books(
+        cls, iids: list[Iid], depth: int
+    ) -> Iterator[tuple[Iid, dict]]:
+        """Subscribe order books and yield (iid, snapshot) forever.
+
+        Тинькофф присылает стакан целиком при каждом изменении, поэтому
+        каждое сообщение - полный снимок. Неконсистентные стаканы
+        пропускаются.
+        """
+        cls.__ensure_auth()
+
+        by_uid = {cls.__find_uid(i): i for i in iids}
+        instruments = [
+            ti.OrderBookInstrument(instrument_id=uid, depth=depth)
+            for uid in by_uid
+        ]
+
+        with ti.Client(cls.__token) as client:
+            stream = client.create_market_data_stream()
+            stream.order_book.subscribe(instruments)
+            for response in stream:
+                book = response.orderbook
+                if book is None or not book.is_consistent:
+                    continue
+
+                iid = by_uid.get(book.instrument_uid)
+                if iid is None:
+                    continue
+
+                yield iid, _format_book(book)
+
     # private
     @classmethod
     def __ensure_auth(cls) -> None:
@@ -121,6 +187,45 @@ class SourceTinkoff:
 
         return df_info
 
+    @classmethod
+    def __get_tics(
+        cls, iid: Iid, begin: DateTime | None, end: DateTime | None
+    ) -> pl.DataFrame:
+        # у Тинькофф нет номеров сделок, поэтому продолжение загрузки
+        # идет по времени последнего тика. Старше часа данных нет.
+        end = now() if end is None else end
+        earliest = now() - TRADES_WINDOW
+        if begin is None or begin < earliest:
+            begin = earliest
+
+        uid = cls.__find_uid(iid)
+        with ti.Client(cls.__token) as client:
+            response = client.market_data.get_last_trades(
+                instrument_id=uid,
+                from_=begin,
+                to=end,
+            )
+
+        if not response.trades:
+            return pl.DataFrame()
+
+        return _format_tics_df(response.trades, iid.lot())
+
+    @classmethod
+    def __find_uid(cls, iid: Iid) -> str:
+        cache = IidCache.load(SOURCE, iid.category())
+        df = cache.df().filter(
+            pl.col("exchange") == iid.exchange().name,
+            pl.col("ticker") == iid.ticker(),
+        )
+        if df.is_empty():
+            raise TickerNotFound(
+                f"Cannot find {iid} in Tinkoff cache, "
+                "run: avin-data cache -s tinkoff"
+            )
+
+        return df.item(0, "uid")
+
     @classmethod
     def __extract_info(cls, i: ti.Instrument) -> dict:
         # define short alias
@@ -214,5 +319,46 @@ class SourceTinkoff:
         return names[name]
 
 
+def _format_tics_df(trades: list[ti.Trade], lot: int) -> pl.DataFrame:
+    dec = ti.utils.quotation_to_decimal
+    buy = ti.TradeDirection.TRADE_DIRECTION_BUY
+
+    price = [float(dec(i.price)) for i in trades]
+    df = pl.DataFrame(
+        {
+            "ts_nanos": [dt_to_ts(i.time) for i in trades],
+            "direction": ["B" if i.direction == buy else "S" for i in trades],
+            "lots": [i.quantity for i in trades],
+            "price": price,
+            "value": [
+                p * i.quantity * lot
+                for p, i in zip(price, trades, strict=True)
+            ],
+            "session": [None for _ in trades],
+            "tradeno": [None for _ in trades],
+        },
+        schema_overrides={
+            "ts_nanos": pl.Int64,
+            "lots": pl.Int64,
+            "session": pl.Int8,
+            "tradeno": pl.Int64,
+        },
+    )
+
+    return df.sort("ts_nanos")
+
+
+def _format_book(book: ti.OrderBook) -> dict:
+    dec = ti.utils.quotation_to_decimal
+
+    return {
+        "ts_nanos": dt_to_ts(book.time),
+        "bid_price": [float(dec(i.price)) for i in book.bids],
+        "bid_lots": [i.quantity for i in book.bids],
+        "ask_price": [float(dec(i.price)) for i in book.asks],
+        "ask_lots": [i.quantity for i in book.asks],
+    }
+
+
 if __name__ == "__main__":
     ...
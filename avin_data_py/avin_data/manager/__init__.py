@@ -6,18 +6,32 @@
 # LICENSE:      MIT
 # ============================================================================
 
+from avin_data.manager.bar_builder import BarBuilder
+from avin_data.manager.book_recorder import BookRecorder
 from avin_data.manager.category import Category
+from avin_data.manager.csv_import import CsvImport
+from avin_data.manager.daemon import Daemon
+from avin_data.manager.data_check import DataCheck
 from avin_data.manager.exchange import Exchange
 from avin_data.manager.iid import Iid
 from avin_data.manager.manager import Manager
 from avin_data.manager.market_data import MarketData
+from avin_data.manager.migration import Migration
 from avin_data.manager.source import Source
+from avin_data.manager.sqlite_storage import SqliteStorage
 
 __all__ = (
+    "BarBuilder",
+    "BookRecorder",
     "Category",
+    "CsvImport",
+    "Daemon",
+    "DataCheck",
     "Manager",
     "Exchange",
     "Iid",
     "Source",
     "MarketData",
+    "Migration",
+    "SqliteStorage",
 )
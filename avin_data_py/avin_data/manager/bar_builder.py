@@ -0,0 +1,122 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+from datetime import timedelta as TimeDelta
+from pathlib import Path
+
+import polars as pl
+
+from avin_data.manager.data_file_bar import DataFileBar
+from avin_data.manager.data_file_tic import SCHEMA as TIC_SCHEMA
+from avin_data.manager.iid import Iid
+from avin_data.manager.market_data import MarketData
+from avin_data.utils import Cmd, log
+
+# Таймфреймы, которые можно собрать из тиков: период фиксированный,
+# бар начинается на границе периода от начала эпохи (UTC)
+BUILDABLE = (
+    MarketData.BAR_1S,
+    MarketData.BAR_5S,
+    MarketData.BAR_1M,
+    MarketData.BAR_5M,
+    MarketData.BAR_10M,
+    MarketData.BAR_1H,
+)
+# Источники не дают секундных баров, они только собираются из тиков
+TIC_ONLY = (
+    MarketData.BAR_1S,
+    MarketData.BAR_5S,
+)
+
+
+class BarBuilder:
+    """Offline aggregation of stored tics into bars.
+
+    Бар собирается из сделок, попавших в интервал [ts, ts + период):
+    open/close - цена первой/последней сделки, high/low - экстремумы,
+    volume - количество бумаг (лоты * размер лота), как в барах
+    источников, value - сумма объемов сделок в валюте. Интервалы без
+    сделок баров не образуют.
+    """
+
+    @classmethod
+    def aggregate(
+        cls, tics: pl.DataFrame, market_data: MarketData, lot: int
+    ) -> pl.DataFrame:
+        """Aggregate tics dataframe into bars dataframe."""
+
+        if market_data not in BUILDABLE:
+            raise ValueError(f"Cannot build {market_data} from tics")
+
+        period = market_data.timedelta() // TimeDelta(microseconds=1)
+        period *= 1000  # nanoseconds
+
+        bars = (
+            tics.sort("ts_nanos")
+            .with_columns(
+                (pl.col("ts_nanos") // period * period).alias("bar_ts")
+            )
+            .group_by("bar_ts", maintain_order=True)
+            .agg(
+                pl.col("price").first().alias("open"),
+                pl.col("price").max().alias("high"),
+                pl.col("price").min().alias("low"),
+                pl.col("price").last().alias("close"),
+                (pl.col("lots").sum() * lot).alias("volume"),
+                pl.col("value").sum().alias("value"),
+            )
+            .rename({"bar_ts": "ts_nanos"})
+            .with_columns(
+                pl.col("ts_nanos").cast(pl.Int64),
+                pl.col("volume").cast(pl.Int64),
+            )
+        )
+
+        return bars
+
+    @classmethod
+    def build(cls, iid: Iid, market_data: MarketData) -> int:
+        """Build bars from all stored tics of instrument.
+
+        Тики читаются по файлам дней, собранные бары объединяются с
+        сохраненными - пересобранные бары заменяют старые. Возвращает
+        количество собранных баров.
+        """
+        assert isinstance(iid, Iid)
+        assert isinstance(market_data, MarketData)
+
+        dir_path = Cmd.path(iid.path(), MarketData.TIC.name)
+        if not Cmd.is_exist(Path(dir_path)):
+            log.warning(f"No stored tics: {iid}, run: avin-data download")
+            return 0
+
+        files = Cmd.get_files(dir_path, full_path=True, include_sub_dir=True)
+        files = sorted(i for i in files if i.endswith(".parquet"))
+
+        parts = list()
+        for file in files:
+            tics = Cmd.read_pqt(Path(file)).cast(TIC_SCHEMA)
+            if tics.is_empty():
+                continue
+
+            parts.append(cls.aggregate(tics, market_data, iid.lot()))
+
+        if not parts:
+            return 0
+
+        bars = pl.concat(parts)
+        DataFileBar.merge(iid, market_data, bars)
+        log.info(f"Build {iid.ticker()} {market_data.name}: {len(bars)}")
+
+        return len(bars)
+
+
+if __name__ == "__main__":
+    ...
@@ -0,0 +1,107 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+from datetime import timedelta as TimeDelta
+
+import polars as pl
+
+from avin_data.connect import SourceTinkoff
+from avin_data.manager.data_file_book import SCHEMA, DataFileBook
+from avin_data.manager.iid import Iid
+from avin_data.utils import cfg, log, now
+
+
+class BookRecorder:
+    """Record order book snapshots of instruments to disk.
+
+    Подписывается на стаканы инструментов (источник Тинькофф) и
+    сохраняет снимки не чаще одного раза в interval на инструмент,
+    interval = 0 - каждое обновление стакана. Снимки копятся в
+    памяти и сбрасываются на диск раз в flush, а также при остановке.
+    Формат хранения - см. DataFileBook.
+    """
+
+    def __init__(
+        self,
+        iids: list[Iid],
+        depth: int,
+        interval: TimeDelta,
+        flush: TimeDelta,
+    ):
+        assert len(iids) > 0
+        assert depth > 0
+
+        self.__iids = iids
+        self.__depth = depth
+        self.__interval = _nanos(interval)
+        self.__flush = flush
+        self.__buffer: dict[Iid, list[dict]] = {i: list() for i in iids}
+        self.__last_ts: dict[Iid, int] = dict()
+
+    @classmethod
+    def from_cfg(cls, iids: list[Iid]) -> BookRecorder:
+        return BookRecorder(
+            iids,
+            cfg.recorder_depth,
+            cfg.recorder_interval,
+            cfg.recorder_flush,
+        )
+
+    def run(self) -> None:
+        """Record until interrupted, then flush the rest."""
+
+        names = ", ".join(str(i) for i in self.__iids)
+        log.info(f"Record order books: {names}, depth={self.__depth}")
+
+        last_flush = now()
+        try:
+            stream = SourceTinkoff.stream_order_books(
+                self.__iids, self.__depth
+            )
+            for iid, snapshot in stream:
+                self.add(iid, snapshot)
+
+                if now() - last_flush >= self.__flush:
+                    self.flush()
+                    last_flush = now()
+        finally:
+            self.flush()
+
+    def add(self, iid: Iid, snapshot: dict) -> bool:
+        """Add snapshot to buffer, return False if skipped by interval."""
+
+        ts = snapshot["ts_nanos"]
+        last = self.__last_ts.get(iid)
+        if last is not None and ts - last < self.__interval:
+            return False
+
+        self.__last_ts[iid] = ts
+        self.__buffer[iid].append(snapshot)
+
+        return True
+
+    def flush(self) -> None:
+        for iid, snapshots in self.__buffer.items():
+            if not snapshots:
+                continue
+
+            df = pl.DataFrame(snapshots, schema=SCHEMA)
+            DataFileBook.save(DataFileBook(iid, df))
+            log.info(f"Save {len(df)} order books {iid}")
+
+            self.__buffer[iid] = list()
+
+
+def _nanos(td: TimeDelta) -> int:
+    return td // TimeDelta(microseconds=1) * 1000
+
+
+if __name__ == "__main__":
+    ...
@@ -23,6 +23,7 @@ class Category(enum.Enum):
     FUTURE = 5
     OPTION = 6
     ETF = 7
+    SPOT = 8
 
     @classmethod
     def from_str(cls, string: str) -> Category:
@@ -0,0 +1,193 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+from datetime import timedelta as TimeDelta
+from pathlib import Path
+
+import polars as pl
+
+from avin_data.manager.market_data import MarketData
+from avin_data.utils import InvalidData
+
+FIELDS = [
+    "date",
+    "time",
+    "open",
+    "high",
+    "low",
+    "close",
+    "volume",
+    "value",
+]
+REQUIRED = ["time", "open", "high", "low", "close", "volume"]
+
+
+class CsvImport:
+    """Import bars from external csv: QUIK, Finam, other terminals.
+
+    Соответствие колонок задается строкой вида
+    "time=dt,open=o,high=h,low=l,close=c,volume=v", где слева поле
+    avin, а справа название колонки в csv. Если дата и время лежат в
+    разных колонках (экспорт Finam: <DATE>, <TIME>), укажите обе:
+    "date=<DATE>,time=<TIME>,...". Колонка "value" необязательна, если
+    ее нет - оборот считается как close * volume.
+
+    Время в csv считается временем биржи (по умолчанию Europe/Moscow)
+    и переводится в UTC. Время бара - время его открытия.
+    """
+
+    @classmethod
+    def parse_map(cls, string: str) -> dict[str, str]:
+        """Parse columns mapping "field=column,...".
+
+        Raises:
+            InvalidData if unknown field or required field missed.
+        """
+        mapping = dict()
+        for pair in string.split(","):
+            if not pair.strip():
+                continue
+            if "=" not in pair:
+                raise InvalidData(f"Invalid mapping '{pair}', use a=b")
+
+            field, column = pair.split("=", 1)
+            field, column = field.strip().lower(), column.strip()
+            if field not in FIELDS:
+                raise InvalidData(f"Unknown field '{field}', use {FIELDS}")
+            mapping[field] = column
+
+        missed = [i for i in REQUIRED if i not in mapping]
+        if missed:
+            raise InvalidData(f"Mapping missed fields: {missed}")
+
+        return mapping
+
+    @classmethod
+    def read(
+        cls,
+        path: Path,
+        mapping: dict[str, str],
+        *,
+        separator: str = ",",
+        dt_format: str | None = None,
+        tz: str = "Europe/Moscow",
+    ) -> pl.DataFrame:
+        """Read csv file and convert it to avin bars format."""
+
+        raw = pl.read_csv(path, separator=separator, infer_schema=False)
+        return cls.convert(raw, mapping, dt_format=dt_format, tz=tz)
+
+    @classmethod
+    def convert(
+        cls,
+        raw: pl.DataFrame,
+        mapping: dict[str, str],
+        *,
+        dt_format: str | None = None,
+        tz: str = "Europe/Moscow",
+    ) -> pl.DataFrame:
+        """Convert raw string columns to avin bars format.
+
+        Raises:
+            InvalidData if column not found or values can't be parsed.
+        """
+        missed = [i for i in mapping.values() if i not in raw.columns]
+        if missed:
+            raise InvalidData(
+                f"Columns not found in csv: {missed}, exist: {raw.columns}"
+            )
+
+        def col(field: str) -> pl.Expr:
+            return pl.col(mapping[field]).str.strip_chars()
+
+        if "date" in mapping:
+            parts = [col("date"), col("time")]
+            dt_str = pl.concat_str(parts, separator=" ")
+        else:
+            dt_str = col("time")
+
+        try:
+            df = raw.select(
+                dt_str.str.to_datetime(format=dt_format, time_unit="ns")
+                .dt.replace_time_zone(tz)
+                .dt.convert_time_zone("UTC")
+                .cast(pl.Int64)
+                .alias("ts_nanos"),
+                col("open").cast(pl.Float64).alias("open"),
+                col("high").cast(pl.Float64).alias("high"),
+                col("low").cast(pl.Float64).alias("low"),
+                col("close").cast(pl.Float64).alias("close"),
+                col("volume")
+                .cast(pl.Float64)
+                .cast(pl.Int64)
+                .alias("volume"),
+            )
+            if "value" in mapping:
+                value = raw.select(col("value").cast(pl.Float64))
+                df = df.with_columns(value.to_series().alias("value"))
+            else:
+                value = pl.col("close") * pl.col("volume")
+                df = df.with_columns(value.alias("value"))
+        except pl.exceptions.PolarsError as e:
+            raise InvalidData(f"Cannot convert csv: {e}") from e
+
+        return df.sort("ts_nanos")
+
+    @classmethod
+    def validate(cls, df: pl.DataFrame, market_data: MarketData) -> None:
+        """Check bars consistency.
+
+        Raises:
+            InvalidData with description of first found problems.
+        """
+        errors = list()
+
+        if df.is_empty():
+            errors.append("no bars")
+        if df.null_count().sum_horizontal().item() > 0:
+            errors.append("empty values")
+
+        n = df.filter(pl.col("ts_nanos").is_duplicated()).height
+        if n:
+            errors.append(f"{n} bars with duplicate time")
+
+        n = df.filter(
+            (pl.col("high") < pl.max_horizontal("open", "close", "low"))
+            | (pl.col("low") > pl.min_horizontal("open", "close"))
+        ).height
+        if n:
+            errors.append(f"{n} bars with invalid high/low")
+
+        n = df.filter(pl.col("volume") < 0).height
+        if n:
+            errors.append(f"{n} bars with negative volume")
+
+        # внутридневные бары должны начинаться ровно на границе периода
+        if market_data in (
+            MarketData.BAR_1M,
+            MarketData.BAR_5M,
+            MarketData.BAR_10M,
+            MarketData.BAR_1H,
+        ):
+            period = market_data.timedelta() // TimeDelta(microseconds=1)
+            period *= 1000  # nanoseconds
+            n = df.filter(pl.col("ts_nanos") % period != 0).height
+            if n:
+                errors.append(
+                    f"{n} bars not aligned to {market_data}, "
+                    "maybe time is bar close time?"
+                )
+
+        if errors:
+            raise InvalidData("Invalid csv data: " + "; ".join(errors))
+
+
+if __name__ == "__main__":
+    ...
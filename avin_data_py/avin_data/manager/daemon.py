@@ -0,0 +1,172 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+import time
+from datetime import UTC
+from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
+from pathlib import Path
+
+from avin_data.manager.manager import Manager
+from avin_data.utils import Cmd, Cron, cfg, log, now, ts_to_dt
+
+
+class Daemon:
+    """Scheduled update of all tracked market data.
+
+    Демон обновляет все имеющиеся на диске данные по расписанию
+    (cron, во времени пользователя usr.offset), повторяет неудачные
+    загрузки и после каждого шага пишет файл статуса в json:
+
+        {
+            "state": "ok" | "failed" | "running",
+            "updated": "2025-06-05T16:00:00+00:00",
+            "next_run": "2025-06-05T20:55:00+00:00",
+            "instruments": {
+                "MOEX_SHARE_SBER": {
+                    "BAR_1M": {
+                        "last_ts": 1749137940000000000,
+                        "last_dt": "2025-06-05T15:39:00+00:00",
+                        "status": "ok",
+                        "attempts": 1,
+                        "error": null
+                    }
+                }
+            }
+        }
+
+    По нему GUI и трейдер могут узнать актуальность данных.
+    """
+
+    def __init__(
+        self,
+        schedule: list[Cron],
+        retries: int,
+        retry_delay: TimeDelta,
+        status_path: Path,
+    ):
+        assert len(schedule) > 0
+        assert retries >= 1
+
+        self.__schedule = schedule
+        self.__retries = retries
+        self.__retry_delay = retry_delay
+        self.__status_path = status_path
+        self.__status = {
+            "state": "ok",
+            "updated": None,
+            "next_run": None,
+            "instruments": dict(),
+        }
+
+    @classmethod
+    def from_cfg(cls) -> Daemon:
+        schedule = [Cron(i) for i in cfg.daemon_schedule]
+        return Daemon(
+            schedule,
+            cfg.daemon_retries,
+            cfg.daemon_retry_delay,
+            cfg.daemon_status,
+        )
+
+    def next_run(self, dt: DateTime) -> DateTime:
+        """Return UTC datetime of next scheduled run after dt."""
+
+        local = (dt + cfg.offset).replace(tzinfo=None)
+        nearest = min(cron.next(local) for cron in self.__schedule)
+
+        return (nearest - cfg.offset).replace(tzinfo=UTC)
+
+    def run(self) -> None:
+        """Run forever: wait schedule, update all, write status."""
+
+        log.info(f"Daemon started, schedule: {self.__schedule_str()}")
+        while True:
+            next_run = self.next_run(now())
+            self.__status["next_run"] = next_run.isoformat()
+            self.__save_status()
+            log.info(f"Daemon next run: {next_run}")
+
+            seconds = (next_run - now()).total_seconds()
+            if seconds > 0:
+                time.sleep(seconds)
+
+            self.run_once()
+
+    def run_once(self) -> bool:
+        """Update all tracked market data, return True if no failures."""
+
+        log.info("Daemon update all market data")
+        self.__status["state"] = "running"
+        self.__save_status()
+
+        failed = False
+        for iid in Manager.tracked_iids():
+            for md in Manager.stored_market_data(iid):
+                ok = self.__update(iid, md)
+                failed |= not ok
+
+        self.__status["state"] = "failed" if failed else "ok"
+        self.__save_status()
+
+        return not failed
+
+    # private
+    def __update(self, iid, md) -> bool:
+        attempt = 0
+        error = None
+        while attempt < self.__retries:
+            attempt += 1
+            try:
+                Manager.update_iid(iid, market_data=md)
+                error = None
+                break
+
+            # источники данных при ошибке завершают процесс через exit(1),
+            # демон не должен падать из-за одного инструмента
+            except (Exception, SystemExit) as e:
+                error = f"{type(e).__name__}: {e}"
+                log.warning(f"Update {iid} {md.name} failed: {error}")
+                if attempt < self.__retries:
+                    time.sleep(self.__retry_delay.total_seconds())
+
+        self.__set_status(iid, md, attempt, error)
+
+        return error is None
+
+    def __set_status(self, iid, md, attempts: int, error: str | None):
+        ts = Manager.last_ts(iid, md)
+        info = {
+            "last_ts": ts,
+            "last_dt": None if ts is None else ts_to_dt(ts).isoformat(),
+            "status": "ok" if error is None else "failed",
+            "attempts": attempts,
+            "error": error,
+        }
+
+        instruments = self.__status["instruments"]
+        instruments.setdefault(str(iid), dict())[md.name] = info
+        self.__save_status()
+
+    def __save_status(self) -> None:
+        self.__status["updated"] = now().isoformat()
+
+        # пишем во временный файл и переименовываем, чтобы читатель
+        # никогда не увидел наполовину записанный файл
+        tmp = f"{self.__status_path}.tmp"
+        Cmd.write_json(self.__status, tmp)
+        Cmd.rename(tmp, str(self.__status_path))
+
+    def __schedule_str(self) -> str:
+        return ", ".join(str(i) for i in self.__schedule)
+
+
+if __name__ == "__main__":
+    ...
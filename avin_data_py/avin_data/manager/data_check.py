@@ -0,0 +1,227 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+from datetime import UTC
+from datetime import date as Date
+from datetime import datetime as DateTime
+from datetime import time as Time
+from datetime import timedelta as TimeDelta
+from pathlib import Path
+
+import polars as pl
+
+from avin_data.manager.exchange import Exchange
+from avin_data.manager.iid import Iid
+from avin_data.manager.market_data import MarketData
+from avin_data.utils import Cmd, ts_to_dt
+
+MSK_OFFSET = TimeDelta(hours=3)
+INTRADAY = [
+    MarketData.BAR_1M,
+    MarketData.BAR_5M,
+    MarketData.BAR_10M,
+    MarketData.BAR_1H,
+]
+
+
+class DataCheck:
+    """Integrity check of stored bars.
+
+    Проверяет сохраненные бары и возвращает отчет в виде словаря,
+    пригодного для записи в json:
+
+        {
+            "iid": "MOEX_SHARE_SBER",
+            "market_data": "BAR_1M",
+            "bars": 123456,
+            "first_ts": ..., "last_ts": ...,
+            "duplicates": [ts, ...],
+            "non_monotonic": [ts, ...],
+            "zero_volume": [ts, ...],
+            "missing_days": ["2025-01-06", ...],
+            "gaps": [{"begin": ts, "end": ts, "missing": n}, ...],
+        }
+
+    missing_days - торговые дни без единого бара (для Мос.биржи
+    рабочие дни, праздники тоже попадут сюда). gaps - разрывы внутри
+    дня длиннее min_gap, begin/end - полуоткрытый диапазон [begin, end)
+    пропущенных баров. По этим диапазонам работает repair, см.
+    broken_ranges.
+    """
+
+    @classmethod
+    def load(cls, iid: Iid, market_data: MarketData) -> pl.DataFrame:
+        """Load all stored bars of instrument as is, without sorting."""
+
+        dir_path = Cmd.path(iid.path(), market_data.name)
+        if not Cmd.is_exist(Path(dir_path)):
+            return pl.DataFrame()
+
+        # бары могут быть разбиты по годам или по месяцам
+        files = Cmd.get_files(dir_path, full_path=True, include_sub_dir=True)
+        files = sorted(i for i in files if i.endswith(".parquet"))
+        if not files:
+            return pl.DataFrame()
+
+        parts = [Cmd.read_pqt(Path(i)) for i in files]
+        return pl.concat(parts, how="vertical_relaxed")
+
+    @classmethod
+    def check(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        *,
+        min_gap: TimeDelta = TimeDelta(minutes=30),
+    ) -> dict:
+        df = cls.load(iid, market_data)
+        return cls.check_df(iid, market_data, df, min_gap=min_gap)
+
+    @classmethod
+    def check_df(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        df: pl.DataFrame,
+        *,
+        min_gap: TimeDelta = TimeDelta(minutes=30),
+    ) -> dict:
+        report = {
+            "iid": str(iid),
+            "market_data": market_data.name,
+            "bars": len(df),
+            "first_ts": None,
+            "last_ts": None,
+            "duplicates": [],
+            "non_monotonic": [],
+            "zero_volume": [],
+            "missing_days": [],
+            "gaps": [],
+        }
+        if df.is_empty():
+            return report
+
+        ts = df["ts_nanos"]
+        report["first_ts"] = ts.min()
+        report["last_ts"] = ts.max()
+
+        # порядок как в файлах: время должно строго возрастать
+        non_monotonic = df.filter(pl.col("ts_nanos").diff() <= 0)
+        report["non_monotonic"] = non_monotonic["ts_nanos"].to_list()
+
+        duplicates = df.filter(pl.col("ts_nanos").is_duplicated())
+        report["duplicates"] = duplicates["ts_nanos"].unique().to_list()
+
+        zero = df.filter(pl.col("volume") == 0)
+        report["zero_volume"] = zero["ts_nanos"].to_list()
+
+        # дальше проверка пропусков на отсортированных уникальных барах
+        df = df.unique("ts_nanos").sort("ts_nanos")
+        if market_data in INTRADAY or market_data == MarketData.BAR_DAY:
+            report["missing_days"] = cls.__missing_days(iid, df)
+        if market_data in INTRADAY:
+            report["gaps"] = cls.__gaps(df, market_data, min_gap)
+
+        return report
+
+    @classmethod
+    def is_ok(cls, report: dict) -> bool:
+        keys = [
+            "duplicates",
+            "non_monotonic",
+            "zero_volume",
+            "missing_days",
+            "gaps",
+        ]
+        return all(len(report[i]) == 0 for i in keys)
+
+    @classmethod
+    def broken_ranges(
+        cls, report: dict
+    ) -> list[tuple[DateTime, DateTime]]:
+        """Return sorted merged UTC ranges [begin, end) to download again.
+
+        Пропущенный день - диапазон от 00:00 до 24:00 МСК, разрыв -
+        диапазон пропущенных баров из отчета. Пересекающиеся диапазоны
+        объединяются.
+        """
+        ranges = list()
+        for day in report["missing_days"]:
+            msk = DateTime.combine(Date.fromisoformat(day), Time(), UTC)
+            begin = msk - MSK_OFFSET
+            ranges.append((begin, begin + TimeDelta(days=1)))
+        for gap in report["gaps"]:
+            ranges.append((ts_to_dt(gap["begin"]), ts_to_dt(gap["end"])))
+
+        merged = list()
+        for begin, end in sorted(ranges):
+            if merged and begin <= merged[-1][1]:
+                prev_begin, prev_end = merged[-1]
+                merged[-1] = (prev_begin, max(prev_end, end))
+            else:
+                merged.append((begin, end))
+
+        return merged
+
+    # private
+    @classmethod
+    def __missing_days(cls, iid: Iid, df: pl.DataFrame) -> list[str]:
+        # дни считаются по московскому времени: дневной бар Мос.биржи
+        # начинается в 00:00 МСК = 21:00 UTC предыдущего дня
+        days = {_msk_date(i) for i in df["ts_nanos"]}
+        first = _msk_date(df.item(0, "ts_nanos"))
+        last = _msk_date(df.item(-1, "ts_nanos"))
+
+        # криптовалюты торгуются без выходных
+        weekends = iid.exchange() != Exchange.BINANCE
+
+        missing = list()
+        day = first
+        while day <= last:
+            if day not in days and not (weekends and day.weekday() >= 5):
+                missing.append(str(day))
+            day += TimeDelta(days=1)
+
+        return missing
+
+    @classmethod
+    def __gaps(
+        cls, df: pl.DataFrame, market_data: MarketData, min_gap: TimeDelta
+    ) -> list[dict]:
+        period = _nanos(market_data.timedelta())
+        threshold = max(period, _nanos(min_gap))
+
+        gaps = list()
+        prev = df.item(0, "ts_nanos")
+        for ts in df["ts_nanos"][1:]:
+            same_day = _msk_date(prev) == _msk_date(ts)
+            if same_day and ts - prev > threshold:
+                gaps.append(
+                    {
+                        "begin": prev + period,
+                        "end": ts,
+                        "missing": (ts - prev) // period - 1,
+                    }
+                )
+            prev = ts
+
+        return gaps
+
+
+def _nanos(td: TimeDelta) -> int:
+    return td // TimeDelta(microseconds=1) * 1000
+
+
+def _msk_date(ts: int) -> Date:
+    return (ts_to_dt(ts) + MSK_OFFSET).date()
+
+
+if __name__ == "__main__":
+    ...
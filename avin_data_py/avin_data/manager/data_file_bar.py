@@ -16,10 +16,20 @@ import polars as pl
 
 from avin_data.manager.iid import Iid
 from avin_data.manager.market_data import MarketData
-from avin_data.utils import Cmd, dt_to_ts, log, ts_to_dt
+from avin_data.utils import Cmd, cfg, dt_to_ts, log, ts_to_dt
 
 
 class DataFileBar:
+    """Bars of instrument for one year.
+
+    Бары хранятся по годам (UTC) в папке <data>/<exchange>/<category>/
+    <ticker>/<BAR>, раскладка задается в конфиге data.storage.partition:
+        year  - <year>.parquet
+        month - <year>/<MM>.parquet
+    Загрузка понимает обе раскладки, сохранение пишет в текущую и
+    удаляет файлы года в другой раскладке. Сжатие - data.storage.
+    """
+
     def __init__(self, iid: Iid, market_data: MarketData, df: pl.DataFrame):
         assert isinstance(iid, Iid)
         assert isinstance(market_data, MarketData)
@@ -68,18 +78,81 @@ class DataFileBar:
                 pl.col("ts_nanos") < end_ts,
             )
 
-            path = cls.__create_file_path(iid, market_data, year)
-            Cmd.write_pqt(year_df, path)
-            log.info(f"Save bars: {path}")
-
+            cls.__save_year(iid, market_data, year, year_df)
             year += 1
 
+    @classmethod
+    def merge(
+        cls, iid: Iid, market_data: MarketData, df: pl.DataFrame
+    ) -> None:
+        """Merge bars into stored files, new bars replace old ones.
+
+        Бары могут быть за несколько лет, каждый год объединяется со
+        своим файлом. При совпадении времени остается новый бар.
+        """
+        assert isinstance(df, pl.DataFrame)
+        if df.is_empty():
+            return
+
+        df = df.sort("ts_nanos")
+        first = ts_to_dt(df.item(0, "ts_nanos")).year
+        last = ts_to_dt(df.item(-1, "ts_nanos")).year
+        for year in range(first, last + 1):
+            begin_ts = dt_to_ts(DateTime(year, 1, 1, tzinfo=UTC))
+            end_ts = dt_to_ts(DateTime(year + 1, 1, 1, tzinfo=UTC))
+            year_df = df.filter(
+                pl.col("ts_nanos") >= begin_ts,
+                pl.col("ts_nanos") < end_ts,
+            )
+            if year_df.is_empty():
+                continue
+
+            if cls.exists(iid, market_data, year):
+                old = cls.load(iid, market_data, year).df()
+                year_df = pl.concat([old, year_df], how="vertical_relaxed")
+                year_df = year_df.unique("ts_nanos", keep="last")
+                year_df = year_df.sort("ts_nanos")
+
+            cls.save(DataFileBar(iid, market_data, year_df))
+
+    @classmethod
+    def exists(cls, iid: Iid, market_data: MarketData, year: int) -> bool:
+        path = cls.__create_file_path(iid, market_data, year)
+        year_dir = cls.__create_year_dir_path(iid, market_data, year)
+        return Cmd.is_exist(path) or Cmd.is_exist(year_dir)
+
+    @classmethod
+    def years(cls, iid: Iid, market_data: MarketData) -> list[int]:
+        """Return sorted years of stored bars in any partition."""
+
+        dir_path = cls.__create_dir_path(iid, market_data)
+        if not Path(dir_path).exists():
+            return list()
+
+        years = set()
+        for name in Cmd.content(dir_path):
+            year = name.removesuffix(".parquet")
+            if year.isdigit():
+                years.add(int(year))
+
+        return sorted(years)
+
     @classmethod
     def load(
         cls, iid: Iid, market_data: MarketData, year: int
     ) -> DataFileBar:
+        # при смене раскладки год может быть в обеих, пока compact
+        # не удалил старые файлы - файл года приоритетнее
         path = cls.__create_file_path(iid, market_data, year)
-        df = Cmd.read_pqt(path)
+        if Cmd.is_exist(path):
+            df = Cmd.read_pqt(path)
+        else:
+            year_dir = cls.__create_year_dir_path(iid, market_data, year)
+            files = Cmd.get_files(str(year_dir), full_path=True)
+            files = sorted(i for i in files if i.endswith(".parquet"))
+            parts = [Cmd.read_pqt(Path(i)) for i in files]
+            df = pl.concat(parts, how="vertical_relaxed")
+
         data = DataFileBar(iid, market_data, df)
 
         return data
@@ -88,21 +161,54 @@ class DataFileBar:
     def load_last(cls, iid: Iid, market_data: MarketData) -> DataFileBar:
         dir_path = cls.__create_dir_path(iid, market_data)
 
-        if not Path(dir_path).exists():
+        years = cls.years(iid, market_data)
+        if len(years) == 0:
             log.error(f"Data not found: {iid} {market_data} ({dir_path})")
             exit(1)
 
-        files = sorted(Cmd.get_files(dir_path, full_path=True))
-        if len(files) == 0:
-            log.error(f"Data not found: {iid} {market_data} ({dir_path})")
-            exit(1)
+        return cls.load(iid, market_data, years[-1])
 
-        last_file = files[-1]
-
-        df = Cmd.read_pqt(Path(last_file))
-        data = DataFileBar(iid, market_data, df)
+    # private
+    @classmethod
+    def __save_year(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        year: int,
+        df: pl.DataFrame,
+    ) -> None:
+        year_path = cls.__create_file_path(iid, market_data, year)
+        year_dir = cls.__create_year_dir_path(iid, market_data, year)
+
+        if cfg.storage_partition == "month":
+            months = df["ts_nanos"].map_elements(
+                lambda ts: ts_to_dt(ts).month, return_dtype=pl.Int32
+            )
+            written = set()
+            for month in months.unique().sort():
+                path = Path(Cmd.path(str(year_dir), f"{month:02}.parquet"))
+                cls.__write(df.filter(months == month), path)
+                written.add(str(path))
+
+            # месяцы, которых больше нет в данных года, и файл года
+            for i in Cmd.get_files(str(year_dir), full_path=True):
+                if i not in written:
+                    Cmd.delete(i)
+            if Cmd.is_exist(year_path):
+                Cmd.delete(str(year_path))
+        else:
+            cls.__write(df, year_path)
+            if Cmd.is_exist(year_dir):
+                Cmd.delete_dir(str(year_dir))
 
-        return data
+    @classmethod
+    def __write(cls, df: pl.DataFrame, path: Path) -> None:
+        # пишем во временный файл и переименовываем, чтобы при
+        # сбое не остался наполовину записанный файл
+        tmp = Path(f"{path}.tmp")
+        Cmd.write_pqt(df, tmp, **cfg.storage_options)
+        Cmd.replace(str(tmp), str(path))
+        log.info(f"Save bars: {path}")
 
     @classmethod
     def __create_dir_path(cls, iid: Iid, market_data: MarketData) -> str:
@@ -124,3 +230,10 @@ class DataFileBar:
         )
 
         return Path(file_path)
+
+    @classmethod
+    def __create_year_dir_path(
+        cls, iid: Iid, market_data: MarketData, year: int
+    ) -> Path:
+        dir_path = cls.__create_dir_path(iid, market_data)
+        return Path(Cmd.path(dir_path, f"{year}"))
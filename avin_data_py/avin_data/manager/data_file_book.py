@@ -0,0 +1,115 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+from datetime import date as Date
+from pathlib import Path
+
+import polars as pl
+
+from avin_data.manager.iid import Iid
+from avin_data.manager.market_data import MarketData
+from avin_data.utils import Cmd, cfg, log, ts_to_dt
+
+# ts_nanos  - время снимка стакана, UTC, наносекунды
+# bid_price - цены заявок на покупку, от лучшей к худшей
+# bid_lots  - количество лотов на уровнях покупки
+# ask_price - цены заявок на продажу, от лучшей к худшей
+# ask_lots  - количество лотов на уровнях продажи
+SCHEMA = pl.Schema(
+    {
+        "ts_nanos": pl.Int64,
+        "bid_price": pl.List(pl.Float64),
+        "bid_lots": pl.List(pl.Int64),
+        "ask_price": pl.List(pl.Float64),
+        "ask_lots": pl.List(pl.Int64),
+    }
+)
+
+
+class DataFileBook:
+    """Order book snapshots of instrument for one day.
+
+    Снимки стакана хранятся по дням (UTC), один parquet файл на день:
+    <data>/<exchange>/<category>/<ticker>/BOOK/<year>/<YYYY-MM-DD>.parquet
+    Одна строка - один снимок, уровни лежат в колонках-списках,
+    см. SCHEMA. При сохранении новые снимки дописываются к файлу дня.
+    """
+
+    def __init__(self, iid: Iid, df: pl.DataFrame):
+        assert isinstance(iid, Iid)
+        assert isinstance(df, pl.DataFrame)
+
+        self.__iid = iid
+        self.__df = df
+
+    def iid(self) -> Iid:
+        return self.__iid
+
+    def df(self) -> pl.DataFrame:
+        return self.__df
+
+    @classmethod
+    def save(cls, data: DataFileBook) -> None:
+        assert isinstance(data, DataFileBook)
+
+        iid = data.iid()
+        df = data.df().cast(SCHEMA).sort("ts_nanos")
+        if df.is_empty():
+            return
+
+        # снимки могут попасть на два дня при записи через полночь UTC
+        days = df["ts_nanos"].map_elements(
+            lambda ts: str(ts_to_dt(ts).date()), return_dtype=pl.String
+        )
+        for day_str in days.unique(maintain_order=True):
+            part = df.filter(days == day_str)
+            date = Date.fromisoformat(day_str)
+            cls.__save_day(iid, date, part)
+
+    @classmethod
+    def load(cls, iid: Iid, date: Date) -> DataFileBook | None:
+        assert isinstance(iid, Iid)
+        assert isinstance(date, Date)
+
+        path = cls.__create_file_path(iid, date)
+        if not Cmd.is_exist(path):
+            return None
+
+        df = Cmd.read_pqt(path)
+        return DataFileBook(iid, df)
+
+    @classmethod
+    def __save_day(cls, iid: Iid, date: Date, part: pl.DataFrame) -> None:
+        path = cls.__create_file_path(iid, date)
+        if Cmd.is_exist(path):
+            old = Cmd.read_pqt(path).cast(SCHEMA)
+            part = pl.concat([old, part])
+            part = part.unique("ts_nanos", keep="last").sort("ts_nanos")
+
+        tmp = Path(f"{path}.tmp")
+        Cmd.write_pqt(part, tmp, **cfg.storage_options)
+        Cmd.replace(str(tmp), str(path))
+
+        log.debug(f"Save order book: {path}")
+
+    @classmethod
+    def __create_file_path(cls, iid: Iid, date: Date) -> Path:
+        file_path = Cmd.path(
+            iid.path(),
+            MarketData.BOOK.name,
+            f"{date.year}",
+            f"{date}.parquet",
+        )
+
+        return Path(file_path)
+
+
+if __name__ == "__main__":
+    ...
@@ -15,10 +15,36 @@ import polars as pl
 
 from avin_data.manager.iid import Iid
 from avin_data.manager.market_data import MarketData
-from avin_data.utils import Cmd, log, ts_to_dt
+from avin_data.utils import Cmd, cfg, log, ts_to_dt
+
+# ts_nanos  - время сделки, UTC, наносекунды
+# direction - сторона агрессора: "B" покупка, "S" продажа
+# lots      - количество лотов
+# price     - цена
+# value     - объем сделки в валюте
+# session   - торговая сессия, null если неизвестна
+# tradeno   - номер сделки на бирже, null если неизвестен
+SCHEMA = pl.Schema(
+    {
+        "ts_nanos": pl.Int64,
+        "direction": pl.String,
+        "lots": pl.Int64,
+        "price": pl.Float64,
+        "value": pl.Float64,
+        "session": pl.Int8,
+        "tradeno": pl.Int64,
+    }
+)
 
 
 class DataFileTic:
+    """Tics of instrument for one day.
+
+    Тики хранятся по дням (UTC), один parquet файл на день:
+    <data>/<exchange>/<category>/<ticker>/TIC/<year>/<YYYY-MM-DD>.parquet
+    Колонки файла - см. SCHEMA, такую же схему ожидает avin_core.
+    """
+
     def __init__(self, iid: Iid, market_data: MarketData, df: pl.DataFrame):
         assert isinstance(iid, Iid)
         assert isinstance(market_data, MarketData)
@@ -46,13 +72,19 @@ class DataFileTic:
 
         iid = data.iid()
         market_data = data.market_data()
-        df = data.df()
-        date = ts_to_dt(df.item(0, "ts_nanos")).date()
-
-        path = cls.__create_file_path(iid, market_data, date)
-        Cmd.write_pqt(df, path)
-
-        log.info(f"Save tics: {path}")
+        df = _format(data.df()).sort("ts_nanos")
+        if df.is_empty():
+            return
+
+        # тики могут быть за несколько дней, например при загрузке
+        # через полночь UTC - сохраняем каждый день в свой файл
+        days = df["ts_nanos"].map_elements(
+            lambda ts: str(ts_to_dt(ts).date()), return_dtype=pl.String
+        )
+        for day_str in days.unique(maintain_order=True):
+            part = df.filter(days == day_str)
+            date = Date.fromisoformat(day_str)
+            cls.__save_day(iid, market_data, date, part)
 
     @classmethod
     def load(
@@ -71,6 +103,33 @@ class DataFileTic:
 
         return data
 
+    @classmethod
+    def __save_day(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        date: Date,
+        part: pl.DataFrame,
+    ) -> None:
+        path = cls.__create_file_path(iid, market_data, date)
+
+        # если файл за этот день уже есть - тики в интервале нового
+        # куска заменяются, остальные сохраняются
+        if Cmd.is_exist(path):
+            old = _format(Cmd.read_pqt(path))
+            first = part.item(0, "ts_nanos")
+            last = part.item(-1, "ts_nanos")
+            old = old.filter(
+                (pl.col("ts_nanos") < first) | (pl.col("ts_nanos") > last)
+            )
+            part = pl.concat([old, part]).sort("ts_nanos")
+
+        tmp = Path(f"{path}.tmp")
+        Cmd.write_pqt(part, tmp, **cfg.storage_options)
+        Cmd.replace(str(tmp), str(path))
+
+        log.info(f"Save tics: {path}")
+
     @classmethod
     def __create_file_path(
         cls, iid: Iid, market_data: MarketData, date: Date
@@ -16,12 +16,14 @@ from datetime import time as Time
 class Exchange(enum.Enum):
     MOEX = 1
     SPB = 2
+    BINANCE = 3
 
     @classmethod
     def from_str(cls, string: str) -> Exchange:
         types = {
             "MOEX": Exchange.MOEX,
             "SPB": Exchange.SPB,
+            "BINANCE": Exchange.BINANCE,
         }
         return types[string]
 
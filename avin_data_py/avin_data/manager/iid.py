@@ -62,6 +62,11 @@ class Iid:
     def step(self) -> float:
         return float(self.__info["step"])
 
+    def delisted(self) -> str | None:
+        """Return delisting date for archived instrument, else None."""
+
+        return self.__info.get("delisted")
+
     def path(self) -> str:
         path = Cmd.path(
             cfg.data,
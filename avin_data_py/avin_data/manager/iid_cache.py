@@ -8,16 +8,27 @@
 
 from __future__ import annotations
 
+from datetime import UTC
+from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
 from pathlib import Path
 
 import polars as pl
 
 from avin_data.manager.category import Category
 from avin_data.manager.source import Source
-from avin_data.utils import Cmd, cfg, log
+from avin_data.utils import Cmd, cfg, log, now
 
 
 class IidCache:
+    """Instruments info cache of one source and category.
+
+    При сохранении нового кэша инструменты, пропавшие из него (делистинг),
+    переносятся в архив <cache>/<source>/ARCHIVE/<category>.parquet с
+    датой в колонке delisted - чтобы старые бэктесты продолжали находить
+    исторические инструменты. Вернувшиеся в кэш из архива удаляются.
+    """
+
     def __init__(
         self,
         source: Source,
@@ -51,7 +62,13 @@ class IidCache:
 
         path = cache.path()
         df = cache.df()
-        Cmd.write_pqt(df, Path(path))
+        cls.__archive_delisted(cache)
+
+        # запущенные программы avin перечитывают кэш по времени
+        # изменения файла, пишем атомарно
+        tmp = Path(f"{path}.tmp")
+        Cmd.write_pqt(df, tmp)
+        Cmd.replace(str(tmp), path)
 
         log.info(f"Cache save: {path}")
 
@@ -61,8 +78,83 @@ class IidCache:
         df = Cmd.read_pqt(Path(path))
         cache = IidCache(source, category, df)
 
+        age = cls.age(source, category)
+        if age > TimeDelta(days=cfg.cache_max_age):
+            log.warning(
+                f"Instrument cache {path} is {age.days} days old, "
+                "update it: avin-data cache"
+            )
+
         return cache
 
+    @classmethod
+    def load_archive(
+        cls, source: Source, category: Category
+    ) -> pl.DataFrame:
+        """Return archived (delisted) instruments, empty if none."""
+
+        path = Path(cls.__create_archive_path(source, category))
+        if not Cmd.is_exist(path):
+            return pl.DataFrame()
+
+        return Cmd.read_pqt(path)
+
+    @classmethod
+    def age(cls, source: Source, category: Category) -> TimeDelta:
+        """Return age of cache file, raise FileNotFoundError if none."""
+
+        path = Path(cls.__create_file_path(source, category))
+        modified = DateTime.fromtimestamp(path.stat().st_mtime, UTC)
+
+        return now() - modified
+
+    @classmethod
+    def __archive_delisted(cls, cache: IidCache) -> None:
+        old_path = Path(cache.path())
+        if not Cmd.is_exist(old_path):
+            return
+
+        source, category = cache.source(), cache.category()
+        new_df = cache.df()
+        old_df = Cmd.read_pqt(old_path)
+        archive = cls.load_archive(source, category)
+
+        tickers = new_df["ticker"]
+        delisted = old_df.filter(~pl.col("ticker").is_in(tickers))
+        archived = len(archive)
+        if archived:
+            # вернувшиеся на биржу убираем из архива
+            archive = archive.filter(~pl.col("ticker").is_in(tickers))
+        if delisted.is_empty() and len(archive) == archived:
+            return
+
+        if not delisted.is_empty():
+            today = now().date().isoformat()
+            delisted = delisted.with_columns(delisted=pl.lit(today))
+            for i in delisted["ticker"]:
+                log.info(f"Instrument delisted: {i}, moved to archive")
+            archive = pl.concat(
+                [archive, delisted], how="diagonal_relaxed"
+            ).unique(subset="ticker", keep="first", maintain_order=True)
+
+        path = Path(cls.__create_archive_path(source, category))
+        tmp = Path(f"{path}.tmp")
+        Cmd.write_pqt(archive, tmp)
+        Cmd.replace(str(tmp), str(path))
+
+    @classmethod
+    def __create_archive_path(
+        cls, source: Source, category: Category
+    ) -> str:
+        archive_path = Cmd.path(
+            cfg.cache,
+            source.name,
+            "ARCHIVE",
+            f"{category.name}.parquet",
+        )
+
+        return archive_path
+
     @classmethod
     def __create_file_path(cls, source: Source, category: Category) -> str:
         cache_path = Cmd.path(
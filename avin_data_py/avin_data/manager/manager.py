@@ -11,17 +11,38 @@ from __future__ import annotations
 from datetime import UTC
 from datetime import datetime as Date
 from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
 from pathlib import Path
 
-from avin_data.connect import SourceMoex, SourceTinkoff
+import polars as pl
+
+from avin_data.connect import (
+    SourceBinance,
+    SourceIss,
+    SourceMoex,
+    SourceTinkoff,
+)
+from avin_data.manager.bar_builder import TIC_ONLY, BarBuilder
 from avin_data.manager.category import Category
+from avin_data.manager.csv_import import CsvImport
+from avin_data.manager.data_check import DataCheck
 from avin_data.manager.data_file_bar import DataFileBar
 from avin_data.manager.data_file_tic import DataFileTic
 from avin_data.manager.exchange import Exchange
 from avin_data.manager.iid import Iid
 from avin_data.manager.market_data import MarketData
+from avin_data.manager.migration import Migration
 from avin_data.manager.source import Source
-from avin_data.utils import Cmd, cfg, log, now, ts_to_dt
+from avin_data.utils import (
+    Cmd,
+    InvalidData,
+    TickerNotFound,
+    cfg,
+    dt_to_ts,
+    log,
+    now,
+    ts_to_dt,
+)
 
 
 class Manager:
@@ -36,6 +57,10 @@ class Manager:
                 SourceMoex.cache_instruments_info()
             case Source.TINKOFF:
                 SourceTinkoff.cache_instruments_info()
+            case Source.BINANCE:
+                SourceBinance.cache_instruments_info()
+            case Source.ISS:
+                SourceIss.cache_instruments_info()
             case _:
                 log.error("Not implemented")
                 exit(1)
@@ -44,9 +69,63 @@ class Manager:
     def find(cls, s: str) -> Iid:
         """Find instrument id"""
 
+        if s.upper().startswith("BINANCE_"):
+            return SourceBinance.find(s)
+
         iid_opt = SourceMoex.find(s)
         return iid_opt
 
+    @classmethod
+    def search(cls, query: str) -> list[Iid]:
+        """Fuzzy search of instruments by ticker, name, FIGI or ISIN.
+
+        Ищет по всем кэшам инструментов, результаты отсортированы по
+        релевантности: точное совпадение тикера, FIGI, ISIN или uid,
+        затем тикер начинается с запроса, тикер содержит запрос,
+        название содержит запрос. Регистр не учитывается.
+        """
+        query = query.strip().upper()
+        if not query:
+            return list()
+
+        # тикеры Мос.биржи есть в кэшах MOEX и Тинькофф
+        sources = {
+            Source.MOEX: Exchange.MOEX,
+            Source.TINKOFF: Exchange.MOEX,
+            Source.BINANCE: Exchange.BINANCE,
+        }
+        found: dict[str, tuple] = dict()
+        for source, exchange in sources.items():
+            dir_path = Cmd.path(cfg.cache, source.name)
+            if not Cmd.is_exist(Path(dir_path)):
+                continue
+
+            for file in Cmd.get_files(dir_path, full_path=True):
+                if not file.endswith(".parquet"):
+                    continue
+
+                category = Cmd.name(file)
+                df = Cmd.read_pqt(Path(file))
+                for row in df.iter_rows(named=True):
+                    rank = _search_rank(query, row)
+                    if rank is None:
+                        continue
+
+                    ticker = str(row["ticker"])
+                    key = f"{exchange.name}_{category}_{ticker}"
+                    order = (rank, len(ticker), key)
+                    found[key] = min(order, found.get(key, order))
+
+        iids = list()
+        for key in sorted(found, key=lambda i: found[i]):
+            try:
+                iids.append(cls.find(key))
+            except Exception:
+                # категории, которые find пока не поддерживает
+                continue
+
+        return iids
+
     @classmethod
     def download(
         cls,
@@ -55,18 +134,251 @@ class Manager:
         market_data: MarketData,
         *,
         year: int | None = None,
+        begin: DateTime | None = None,
+        end: DateTime | None = None,
     ) -> None:
+        """Download market data and save it.
+
+        Бары: за год year, за диапазон [begin, end) или, если ничего
+        не задано, все доступные. Бары диапазона объединяются с уже
+        сохраненными, год перезаписывается целиком. Тики - те, что
+        отдает источник (за текущий день), диапазон для них не
+        используется.
+        """
         assert isinstance(source, Source)
         assert isinstance(iid, Iid)
         assert isinstance(market_data, MarketData)
+        assert (begin is None) == (end is None)
+        assert year is None or begin is None
         log.info(f"Download {iid.ticker()} {market_data.name}")
 
         match market_data:
             case MarketData.TIC:
+                if begin is not None or year is not None:
+                    log.warning("Tics period is defined by source")
                 cls.__download_tics(source, iid, market_data)
+            case _ if begin is not None and end is not None:
+                cls.__download_bars_range(
+                    source, iid, market_data, begin, end
+                )
             case _:  # bars
                 cls.__download_bars(source, iid, market_data, year)
 
+    @classmethod
+    def load(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        begin: DateTime,
+        end: DateTime,
+    ) -> pl.DataFrame:
+        """Load stored bars or tics in range [begin, end)."""
+
+        assert isinstance(iid, Iid)
+        assert market_data != MarketData.BOOK
+
+        parts = list()
+        if market_data == MarketData.TIC:
+            day = begin.date()
+            while day <= end.date():
+                data = DataFileTic.load(iid, market_data, day)
+                if data is not None:
+                    parts.append(data.df())
+                day += TimeDelta(days=1)
+        else:
+            for year in range(begin.year, end.year + 1):
+                if DataFileBar.exists(iid, market_data, year):
+                    data = DataFileBar.load(iid, market_data, year)
+                    parts.append(data.df())
+
+        if not parts:
+            return pl.DataFrame()
+
+        df = pl.concat(parts, how="vertical_relaxed")
+        df = df.filter(
+            pl.col("ts_nanos") >= dt_to_ts(begin),
+            pl.col("ts_nanos") < dt_to_ts(end),
+        )
+
+        return df
+
+    @classmethod
+    def export(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        fmt: str,
+        *,
+        begin: DateTime,
+        end: DateTime,
+        path: Path | None = None,
+    ) -> str | None:
+        """Export stored data to csv or json.
+
+        Перед ts_nanos добавляется колонка dt - время UTC в формате
+        ISO 8601. Если path не задан - возвращает текст, иначе пишет
+        файл и возвращает None.
+
+        Raises:
+            InvalidData if format unknown or no data in range.
+        """
+        df = cls.load(iid, market_data, begin, end)
+        if df.is_empty():
+            raise InvalidData(f"No data {iid} {market_data} in range")
+
+        dt = pl.from_epoch("ts_nanos", time_unit="ns")
+        dt = dt.dt.replace_time_zone("UTC")
+        df = df.select(dt.alias("dt"), pl.all())
+
+        match fmt.lower():
+            case "csv":
+                return df.write_csv(path)
+            case "json":
+                return df.write_json(path)
+            case _:
+                raise InvalidData(f"Unknown format '{fmt}', use csv, json")
+
+    @classmethod
+    def import_csv(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        path: Path,
+        mapping: dict[str, str],
+        *,
+        separator: str = ",",
+        dt_format: str | None = None,
+        tz: str = "Europe/Moscow",
+    ) -> None:
+        """Import bars from csv file into avin parquet layout.
+
+        Бары из csv проверяются и объединяются с уже имеющимися
+        данными: при совпадении времени бар из csv заменяет старый.
+
+        Raises:
+            InvalidData if csv can't be converted or bars are invalid.
+        """
+        assert isinstance(iid, Iid)
+        assert isinstance(market_data, MarketData)
+        log.info(f"Import {iid.ticker()} {market_data.name} from {path}")
+
+        df = CsvImport.read(
+            path, mapping, separator=separator, dt_format=dt_format, tz=tz
+        )
+        CsvImport.validate(df, market_data)
+
+        log.info(f"Imported {len(df)} bars")
+        DataFileBar.merge(iid, market_data, df)
+
+    @classmethod
+    def repair(
+        cls,
+        iid: Iid,
+        market_data: MarketData,
+        *,
+        source: Source | None = None,
+        report: dict | None = None,
+        min_gap: TimeDelta = TimeDelta(minutes=30),
+    ) -> dict:
+        """Download again broken ranges of stored bars.
+
+        По отчету проверки (если не задан - проверка выполняется
+        заново) загружает из источника только пропущенные дни и
+        разрывы внутри дня и вклеивает их в имеющиеся файлы.
+        Повторяющиеся и неупорядоченные бары исправляются при
+        перезаписи файлов. Возвращает отчет повторной проверки.
+        """
+        assert isinstance(iid, Iid)
+        assert isinstance(market_data, MarketData)
+        assert market_data != MarketData.TIC
+        if source is None:
+            source = Source.from_exchange(iid.exchange())
+        if report is None:
+            report = DataCheck.check(iid, market_data, min_gap=min_gap)
+        log.info(f"Repair {iid.ticker()} {market_data.name}")
+
+        if report["duplicates"] or report["non_monotonic"]:
+            df = DataCheck.load(iid, market_data)
+            df = df.unique("ts_nanos", keep="first")
+            log.info("Remove duplicates and sort bars")
+            DataFileBar.merge(iid, market_data, df)
+
+        for begin, end in DataCheck.broken_ranges(report):
+            df = cls.__get_market_data(
+                source, iid, market_data, begin=begin, end=end
+            )
+            df = df.filter(
+                pl.col("ts_nanos") >= dt_to_ts(begin),
+                pl.col("ts_nanos") < dt_to_ts(end),
+            )
+            if df.is_empty():
+                log.info(f"[{begin}, {end}) no data")
+                continue
+
+            log.info(f"[{begin}, {end}) receved {len(df)} bars")
+            DataFileBar.merge(iid, market_data, df)
+
+        return DataCheck.check(iid, market_data, min_gap=min_gap)
+
+    @classmethod
+    def build_bars(cls, iid: Iid, market_data: MarketData) -> int:
+        """Build bars from stored tics, return count of bars.
+
+        Собирает бары из сохраненных тиков - так получаются таймфреймы,
+        которых нет у источника (1S, 5S), для бэктеста внутри минуты.
+        """
+        return BarBuilder.build(iid, market_data)
+
+    @classmethod
+    def migrate(cls, iid: Iid | None = None, *, dry_run=False) -> int:
+        """Upgrade stored files to current schema version.
+
+        Обновляет формат файлов инструмента, без iid - всех файлов в
+        папке данных. Возвращает количество обновленных файлов, при
+        dry_run - количество файлов, которым нужно обновление.
+        """
+        dir_path = cfg.data if iid is None else iid.path()
+        count = Migration.migrate_dir(str(dir_path), dry_run=dry_run)
+
+        return count
+
+    @classmethod
+    def compact(cls, iid: Iid, market_data: MarketData) -> dict:
+        """Rewrite stored data with current storage settings.
+
+        Перезаписывает файлы с текущими настройками data.storage:
+        сжатие, размер группы строк, для баров - разбиение по годам
+        или месяцам. Сами данные не меняются. Возвращает количество
+        и размер файлов в байтах до и после.
+        """
+        assert isinstance(iid, Iid)
+        assert isinstance(market_data, MarketData)
+        log.info(f"Compact {iid.ticker()} {market_data.name}")
+
+        files_before, size_before = cls.__disk_usage(iid, market_data)
+        if market_data in (MarketData.TIC, MarketData.BOOK):
+            # тики и стаканы хранятся по дням, раскладка не меняется
+            for file in cls.__data_files(iid, market_data):
+                tmp = Path(f"{file}.tmp")
+                df = Cmd.read_pqt(Path(file))
+                Cmd.write_pqt(df, tmp, **cfg.storage_options)
+                Cmd.replace(str(tmp), file)
+        else:
+            for year in DataFileBar.years(iid, market_data):
+                data = DataFileBar.load(iid, market_data, year)
+                if not data.df().is_empty():
+                    DataFileBar.save(data)
+        files_after, size_after = cls.__disk_usage(iid, market_data)
+
+        return {
+            "iid": str(iid),
+            "market_data": market_data.name,
+            "files_before": files_before,
+            "size_before": size_before,
+            "files_after": files_after,
+            "size_after": size_after,
+        }
+
     @classmethod
     def update(
         cls,
@@ -77,27 +389,161 @@ class Manager:
         assert isinstance(source, Source)
         assert isinstance(iid, Iid)
         assert isinstance(market_data, MarketData)
-        assert source == Source.MOEX
+        assert source in (
+            Source.MOEX,
+            Source.TINKOFF,
+            Source.ISS,
+            Source.BINANCE,
+        )
         log.info(f"Update {iid.ticker()} {market_data.name}")
 
         match market_data:
             case MarketData.TIC:
                 cls.__update_tics(source, iid, market_data)
+            case MarketData.BOOK:
+                log.info("Order book is written by 'avin-data record'")
             case MarketData.TRADE_STATS:
                 log.error(f"Not implemented: {market_data}")
             case MarketData.ORDER_STATS:
                 log.error(f"Not implemented: {market_data}")
             case MarketData.OB_STATS:
                 log.error(f"Not implemented: {market_data}")
+            case md if md in TIC_ONLY:
+                cls.build_bars(iid, market_data)
             case _:  # bars
                 cls.__update_bars(source, iid, market_data)
 
+    @classmethod
+    def update_iid(
+        cls,
+        iid: Iid,
+        *,
+        source: Source | None = None,
+        market_data: MarketData | None = None,
+    ) -> None:
+        """Download missing tail of all stored market data of instrument.
+
+        Для каждого типа данных, который уже есть на диске, определяет
+        время последнего сохраненного бара (тика) и загружает только
+        недостающие данные. Если market_data задан - обновляется только
+        он. Источник по умолчанию выбирается по бирже инструмента.
+        """
+        assert isinstance(iid, Iid)
+        if source is None:
+            source = Source.from_exchange(iid.exchange())
+
+        # бары из тиков собираются после обновления тиков
+        stored = cls.stored_market_data(iid)
+        stored.sort(key=lambda md: md in TIC_ONLY)
+
+        updated = False
+        for md in stored:
+            if market_data is not None and md != market_data:
+                continue
+
+            cls.update(source, iid, md)
+            updated = True
+
+        if not updated:
+            log.warning(f"No stored data to update: {iid}")
+
     @classmethod
     def update_all(
         cls,
     ) -> None:
         log.info("Update all market data")
 
+        for iid in cls.tracked_iids():
+            cls.update_iid(iid)
+
+    @classmethod
+    def update_actions(cls, iid: Iid) -> list[dict]:
+        """Download splits and dividends and save ACTIONS.json.
+
+        События загружаются из ISS Мос.биржи и объединяются с уже
+        сохраненными: события, добавленные вручную, остаются. Для
+        дивидендов, если есть дневные бары, сохраняется цена закрытия
+        перед событием. По этому файлу avin_core корректирует бары.
+        """
+        assert isinstance(iid, Iid)
+        log.info(f"Update corporate actions {iid}")
+
+        path = cls.actions_path(iid)
+        old = Cmd.read_json(path) if Cmd.is_exist(Path(path)) else list()
+        new = SourceIss.get_corporate_actions(iid)
+
+        for action in new:
+            if action["kind"] == "dividend":
+                action["close"] = cls.__close_before(iid, action["ts_nanos"])
+
+        keys = {(i["ts_nanos"], i["kind"]) for i in new}
+        kept = [i for i in old if (i["ts_nanos"], i["kind"]) not in keys]
+        actions = sorted(kept + new, key=lambda i: i["ts_nanos"])
+
+        Cmd.write_json(actions, path)
+        log.info(f"Save {len(actions)} actions: {path}")
+
+        return actions
+
+    @classmethod
+    def actions_path(cls, iid: Iid) -> str:
+        return Cmd.path(iid.path(), "ACTIONS.json")
+
+    @classmethod
+    def download_chain(
+        cls,
+        asset: str,
+        market_data: MarketData,
+        *,
+        source: Source = Source.ISS,
+        since: int | None = None,
+    ) -> list[dict]:
+        """Download bars of all futures contracts of asset.
+
+        Загружает бары всех контрактов цепочки (включая истекшие) с
+        экспирацией не раньше года since. Контракт торгуется не больше
+        года, поэтому загружаются год экспирации и предыдущий.
+        Возвращает контракты в порядке экспирации:
+        [{"iid": "MOEX_FUTURE_SIH5", "expiration": "2025-03-20",
+        "step": 1.0}, ...], по ним строится секция [[data.continuous]]
+        для avin_core. Шаг цены известен только для торгующихся
+        контрактов (из кэша), для истекших - 1.
+        """
+        assert market_data not in (MarketData.TIC, MarketData.BOOK)
+
+        chain = SourceIss.get_futures_chain(asset)
+        if since is not None:
+            chain = chain.filter(
+                pl.col("expiration").str.slice(0, 4).cast(pl.Int32) >= since
+            )
+        if chain.is_empty():
+            log.warning(f"Futures chain {asset} not found")
+            return list()
+
+        contracts = list()
+        for row in chain.iter_rows(named=True):
+            iid = cls.__contract_iid(row["ticker"], row["name"])
+            expiration = int(row["expiration"][:4])
+            for year in (expiration - 1, expiration):
+                if year > now().year:
+                    continue
+                log.info(f"Download {iid.ticker()} {market_data.name}")
+                cls.__download_bars_one_year(source, iid, market_data, year)
+
+            contracts.append(
+                {
+                    "iid": str(iid),
+                    "expiration": row["expiration"],
+                    "step": iid.step(),
+                }
+            )
+
+        return contracts
+
+    @classmethod
+    def tracked_iids(cls) -> list[Iid]:
+        """Return instruments that have stored market data."""
+
         # check data dir
         data_dir = cfg.data
         if not Cmd.is_exist(data_dir):
@@ -105,6 +551,7 @@ class Manager:
             exit(1)
 
         # for each exchange & for each category
+        iids = list()
         for e in Exchange:
             for c in Category:
                 instrument_path = Cmd.path(data_dir, e.name, c.name)
@@ -116,16 +563,149 @@ class Manager:
                 for ticker in dir_names:
                     iid = cls.find(f"{e.name}_{c.name}_{ticker}")
                     assert iid is not None
+                    iids.append(iid)
+
+        return iids
+
+    @classmethod
+    def catalog(cls, iid: Iid | None = None) -> list[dict]:
+        """Return overview of stored market data.
+
+        Одна запись на инструмент и тип данных: количество и размер
+        файлов в байтах, время первого и последнего бара (тика), время
+        последнего изменения файлов (UTC, ISO 8601).
+        """
+        iids = cls.tracked_iids() if iid is None else [iid]
+
+        rows = list()
+        for i in iids:
+            for md in cls.stored_market_data(i):
+                dir_path = Cmd.path(i.path(), md.name)
+                files = Cmd.get_files(
+                    dir_path, full_path=True, include_sub_dir=True
+                )
+                files = sorted(f for f in files if f.endswith(".parquet"))
+                if not files:
+                    continue
+
+                first = pl.read_parquet(files[0], columns=["ts_nanos"])
+                last = pl.read_parquet(files[-1], columns=["ts_nanos"])
+                stats = [Path(f).stat() for f in files]
+                mtime = max(st.st_mtime for st in stats)
+                rows.append(
+                    {
+                        "iid": str(i),
+                        "market_data": md.name,
+                        "files": len(files),
+                        "size": sum(st.st_size for st in stats),
+                        "first_ts": first["ts_nanos"].min(),
+                        "last_ts": last["ts_nanos"].max(),
+                        "updated": DateTime.fromtimestamp(
+                            mtime, UTC
+                        ).isoformat(),
+                    }
+                )
+
+        return rows
+
+    @classmethod
+    def stored_market_data(cls, iid: Iid) -> list[MarketData]:
+        """Return market data types stored on disk for instrument."""
+
+        stored = list()
+        for md in MarketData:
+            path = Cmd.path(iid.path(), md.name)
+            if Cmd.is_exist(Path(path)):
+                stored.append(md)
+
+        return stored
+
+    @classmethod
+    def last_ts(cls, iid: Iid, market_data: MarketData) -> int | None:
+        """Return timestamp of last stored bar or tic, None if no data."""
+
+        dir_path = Cmd.path(iid.path(), market_data.name)
+        if not Cmd.is_exist(Path(dir_path)):
+            return None
 
-                    # for each market data if exist
-                    for md in MarketData:
-                        path = Cmd.path(instrument_path, ticker, md.name)
-                        if not Cmd.is_exist(Path(path)):
-                            continue
+        files = Cmd.get_files(dir_path, full_path=True, include_sub_dir=True)
+        files = sorted(i for i in files if i.endswith(".parquet"))
+        if not files:
+            return None
 
-                        cls.update(Source.MOEX, iid, md)
+        df = Cmd.read_pqt(Path(files[-1]))
+        if df.is_empty():
+            return None
+
+        return df.item(-1, "ts_nanos")
 
     # private
+    @classmethod
+    def __get_market_data(
+        cls, source: Source, iid: Iid, market_data: MarketData, **kwargs
+    ):
+        match source:
+            case Source.MOEX:
+                return SourceMoex.get_market_data(iid, market_data, **kwargs)
+            case Source.ISS:
+                return SourceIss.get_market_data(iid, market_data, **kwargs)
+            case Source.TINKOFF:
+                return SourceTinkoff.get_market_data(
+                    iid, market_data, **kwargs
+                )
+            case Source.BINANCE:
+                return SourceBinance.get_market_data(
+                    iid, market_data, **kwargs
+                )
+            case _:
+                log.error(f"Not implemented: download from {source}")
+                exit(1)
+
+    @classmethod
+    def __data_files(cls, iid: Iid, market_data: MarketData) -> list[str]:
+        dir_path = Cmd.path(iid.path(), market_data.name)
+        if not Cmd.is_exist(Path(dir_path)):
+            return list()
+
+        files = Cmd.get_files(dir_path, full_path=True, include_sub_dir=True)
+        return sorted(i for i in files if i.endswith(".parquet"))
+
+    @classmethod
+    def __disk_usage(
+        cls, iid: Iid, market_data: MarketData
+    ) -> tuple[int, int]:
+        files = cls.__data_files(iid, market_data)
+        return len(files), sum(Path(i).stat().st_size for i in files)
+
+    @classmethod
+    def __contract_iid(cls, ticker: str, name: str) -> Iid:
+        # истекших контрактов нет в кэше инструментов
+        try:
+            return SourceMoex.find(f"MOEX_FUTURE_{ticker}")
+        except TickerNotFound:
+            pass
+
+        info = {
+            "exchange": Exchange.MOEX.name,
+            "category": Category.FUTURE.name,
+            "ticker": ticker,
+            "figi": f"figi_MOEX_FUTURE_{ticker}",
+            "name": name,
+            "lot": 1,
+            "step": 1,
+        }
+        return Iid(info)
+
+    @classmethod
+    def __close_before(cls, iid: Iid, ts: int) -> float | None:
+        end = ts_to_dt(ts)
+        begin = end - TimeDelta(days=14)
+        df = cls.load(iid, MarketData.BAR_DAY, begin, end)
+        if df.is_empty():
+            return None
+
+        return df.item(-1, "close")
+
     @classmethod
     def __download_bars(
         cls, source: Source, iid: Iid, market_data: MarketData, year
@@ -149,12 +729,10 @@ class Manager:
     def __download_bars_one_year(
         cls, source: Source, iid: Iid, market_data: MarketData, year: int
     ) -> None:
-        assert source == Source.MOEX
-
         b = DateTime(year, 1, 1, tzinfo=UTC)
         e = DateTime(year + 1, 1, 1, tzinfo=UTC)
 
-        df = SourceMoex.get_market_data(iid, market_data, begin=b, end=e)
+        df = cls.__get_market_data(source, iid, market_data, begin=b, end=e)
         if df.is_empty():
             log.info(f"{year} no data")
             return
@@ -162,14 +740,43 @@ class Manager:
         file = DataFileBar(iid, market_data, df)
         DataFileBar.save(file)
 
+    @classmethod
+    def __download_bars_range(
+        cls,
+        source: Source,
+        iid: Iid,
+        market_data: MarketData,
+        begin: DateTime,
+        end: DateTime,
+    ) -> None:
+        # запрос по годам, чтобы не упираться в лимиты источника
+        for year in range(begin.year, end.year + 1):
+            b = max(begin, DateTime(year, 1, 1, tzinfo=UTC))
+            e = min(end, DateTime(year + 1, 1, 1, tzinfo=UTC))
+            if b >= e:
+                continue
+
+            df = cls.__get_market_data(
+                source, iid, market_data, begin=b, end=e
+            )
+            if df.is_empty():
+                log.info(f"[{b}, {e}) no data")
+                continue
+
+            df = df.filter(
+                pl.col("ts_nanos") >= dt_to_ts(b),
+                pl.col("ts_nanos") < dt_to_ts(e),
+            )
+            log.info(f"[{b}, {e}) receved {len(df)} bars")
+            DataFileBar.merge(iid, market_data, df)
+
     @classmethod
     def __download_tics(
         cls, source: Source, iid: Iid, market_data: MarketData
     ) -> None:
-        assert source == Source.MOEX
         assert market_data == MarketData.TIC
 
-        df = SourceMoex.get_market_data(iid, market_data)
+        df = cls.__get_market_data(source, iid, market_data)
         if df.is_empty():
             log.info(f"{Date.today()} no data")
             return
@@ -189,7 +796,9 @@ class Manager:
         dt = ts_to_dt(ts)
 
         # request [last, now()]
-        df = SourceMoex.get_market_data(iid, market_data, begin=dt, end=now())
+        df = cls.__get_market_data(
+            source, iid, market_data, begin=dt, end=now()
+        )
         df = df[1:]  # remove first duplicate item
 
         if df.is_empty():
@@ -209,19 +818,45 @@ class Manager:
             cls.download(source, iid, market_data)
             return
 
-        # get last tradeno
+        # get last tradeno, if source has no trade numbers - last time
         n = last_data.df().item(-1, "tradeno")
+        ts = last_data.df().item(-1, "ts_nanos")
 
-        # request from trade n
-        df = SourceMoex.get_market_data(iid, market_data, tradeno=n)
-        df = df[1:]  # remove first duplicate item
+        # request from trade n or from time of last tic
+        if n is None:
+            df = cls.__get_market_data(
+                source, iid, market_data, begin=ts_to_dt(ts), end=now()
+            )
+            if not df.is_empty():
+                df = df.filter(pl.col("ts_nanos") > ts)
+        else:
+            df = cls.__get_market_data(source, iid, market_data, tradeno=n)
+            df = df[1:]  # remove first duplicate item
 
         if df.is_empty():
             log.info("no new tics")
         else:
             log.info(f"receved {len(df)} tics")
-            last_data.add(df)
-            DataFileTic.save(last_data)
+            DataFileTic.save(DataFileTic(iid, market_data, df))
+
+
+def _search_rank(query: str, row: dict) -> int | None:
+    ticker = str(row.get("ticker") or "").upper()
+    ids = [row.get(i) for i in ("figi", "isin", "uid")]
+    ids = [str(i).upper() for i in ids if i]
+    names = [row.get(i) for i in ("name", "shortname", "secname")]
+    names = [str(i).upper() for i in names if i]
+
+    if ticker == query or query in ids:
+        return 0
+    if ticker.startswith(query):
+        return 1
+    if query in ticker:
+        return 2
+    if any(query in i for i in names):
+        return 3
+
+    return None
 
 
 if __name__ == "__main__":
@@ -18,6 +18,8 @@ from avin_data.utils import InvalidMarketData, log
 class MarketData(enum.Enum):
     """Enum for selet what data type to download."""
 
+    BAR_1S = "1S"
+    BAR_5S = "5S"
     BAR_1M = "1M"
     BAR_5M = "5M"
     BAR_10M = "10M"
@@ -39,6 +41,8 @@ class MarketData(enum.Enum):
 
     def timedelta(self) -> TimeDelta:
         periods = {
+            "1S": TimeDelta(seconds=1),
+            "5S": TimeDelta(seconds=5),
             "1M": TimeDelta(minutes=1),
             "5M": TimeDelta(minutes=5),
             "10M": TimeDelta(minutes=10),
@@ -54,6 +58,14 @@ class MarketData(enum.Enum):
 
     def prev_dt(self, dt: DateTime) -> DateTime:
         match self:
+            case MarketData.BAR_1S:
+                prev = dt.replace(microsecond=0)
+
+            case MarketData.BAR_5S:
+                prev = dt.replace(microsecond=0)
+                past = dt.second % 5
+                prev -= TimeDelta(seconds=past)
+
             case MarketData.BAR_1M:
                 prev = dt.replace(second=0, microsecond=0)
 
@@ -103,6 +115,15 @@ class MarketData(enum.Enum):
 
     def next_dt(self, dt: DateTime) -> DateTime:
         match self:
+            case MarketData.BAR_1S:
+                next = dt.replace(microsecond=0)
+                next += TimeDelta(seconds=1)
+
+            case MarketData.BAR_5S:
+                next = dt.replace(microsecond=0)
+                need_seconds = 5 - (dt.second % 5)
+                next += TimeDelta(seconds=need_seconds)
+
             case MarketData.BAR_1M:
                 next = dt.replace(second=0, microsecond=0)
                 next += TimeDelta(minutes=1)
@@ -176,6 +197,8 @@ class MarketData(enum.Enum):
             exit(1)
 
         types = {
+            "1S": MarketData.BAR_1S,
+            "5S": MarketData.BAR_5S,
             "1M": MarketData.BAR_1M,
             "5M": MarketData.BAR_5M,
             "10M": MarketData.BAR_10M,
@@ -183,6 +206,8 @@ class MarketData(enum.Enum):
             "D": MarketData.BAR_DAY,
             "W": MarketData.BAR_WEEK,
             "M": MarketData.BAR_MONTH,
+            "BAR_1S": MarketData.BAR_1S,
+            "BAR_5S": MarketData.BAR_5S,
             "BAR_1M": MarketData.BAR_1M,
             "BAR_5M": MarketData.BAR_5M,
             "BAR_10M": MarketData.BAR_10M,
@@ -0,0 +1,144 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+from pathlib import Path
+
+import polars as pl
+
+from avin_data.utils import SCHEMA_KEY, SCHEMA_VERSION, Cmd, cfg, log
+
+# Старые имена колонки времени, в версии 2 - ts_nanos
+LEGACY_TS_COLUMNS = ("dt", "datetime", "ts")
+# Границы для определения единиц целочисленного времени:
+# наносекунды с 1970 года больше 1e17, микросекунды больше 1e14,
+# миллисекунды больше 1e11, иначе секунды
+NANOS_MIN = 10**17
+MICROS_MIN = 10**14
+MILLIS_MIN = 10**11
+
+
+class Migration:
+    """Upgrade stored parquet files to current schema version.
+
+    Версия схемы хранится в метаданных parquet файла (SCHEMA_KEY),
+    файлы без нее - версия 1. Каждый шаг миграции переводит датафрейм
+    с версии N на N + 1, файл перезаписывается атомарно с текущими
+    настройками data.storage и новой версией. Файлы новее текущей
+    версии не трогаются - нужно обновить avin-data.
+
+    Версии:
+        1 - до версионирования: колонка времени могла называться
+            dt, datetime или ts, быть datetime или числом в секундах,
+            мили- или микросекундах.
+        2 - время в колонке ts_nanos, Int64, UTC наносекунды.
+    """
+
+    @classmethod
+    def version(cls, path: Path) -> int:
+        metadata = Cmd.read_pqt_metadata(path)
+        return int(metadata.get(SCHEMA_KEY, 1))
+
+    @classmethod
+    def upgrade(cls, df: pl.DataFrame, version: int) -> pl.DataFrame:
+        """Apply migration steps from version to SCHEMA_VERSION."""
+
+        steps = {
+            1: cls.__v1_to_v2,
+        }
+        while version < SCHEMA_VERSION:
+            df = steps[version](df)
+            version += 1
+
+        return df
+
+    @classmethod
+    def migrate_file(cls, path: Path, *, dry_run: bool = False) -> bool:
+        """Migrate one file, return True if it needs (or got) upgrade."""
+
+        version = cls.version(path)
+        if version > SCHEMA_VERSION:
+            log.warning(
+                f"{path} schema version {version} is newer than "
+                f"supported {SCHEMA_VERSION}, update avin-data"
+            )
+            return False
+        if version == SCHEMA_VERSION:
+            return False
+
+        log.info(f"Migrate {path}: v{version} -> v{SCHEMA_VERSION}")
+        if dry_run:
+            return True
+
+        df = cls.upgrade(Cmd.read_pqt(path), version)
+        tmp = Path(f"{path}.tmp")
+        Cmd.write_pqt(df, tmp, **cfg.storage_options)
+        Cmd.replace(str(tmp), str(path))
+
+        return True
+
+    @classmethod
+    def migrate_dir(cls, dir_path: str, *, dry_run: bool = False) -> int:
+        """Migrate all parquet files in dir, return count of upgraded."""
+
+        if not Cmd.is_exist(Path(dir_path)):
+            return 0
+
+        files = Cmd.get_files(dir_path, full_path=True, include_sub_dir=True)
+        count = 0
+        for file in sorted(i for i in files if i.endswith(".parquet")):
+            try:
+                if cls.migrate_file(Path(file), dry_run=dry_run):
+                    count += 1
+            except Exception as e:
+                log.error(f"Migrate {file} failed: {e}")
+
+        return count
+
+    # private
+    @classmethod
+    def __v1_to_v2(cls, df: pl.DataFrame) -> pl.DataFrame:
+        if "ts_nanos" not in df.columns:
+            for name in LEGACY_TS_COLUMNS:
+                if name in df.columns:
+                    df = df.rename({name: "ts_nanos"})
+                    break
+            else:
+                return df
+
+        dtype = df.schema["ts_nanos"]
+        if dtype == pl.Date:
+            ts = pl.col("ts_nanos").cast(pl.Datetime("ns"))
+            df = df.with_columns(ts.dt.replace_time_zone("UTC"))
+            dtype = df.schema["ts_nanos"]
+        if dtype == pl.Datetime:
+            # время без часового пояса считаем UTC
+            ts = pl.col("ts_nanos")
+            if getattr(dtype, "time_zone", None) is None:
+                ts = ts.dt.replace_time_zone("UTC")
+            return df.with_columns(ts.dt.epoch("ns").alias("ts_nanos"))
+
+        # целое время - приводим к наносекундам по величине значений
+        largest = df["ts_nanos"].drop_nulls().abs().max()
+        if largest is None or largest >= NANOS_MIN:
+            factor = 1
+        elif largest >= MICROS_MIN:
+            factor = 1_000
+        elif largest >= MILLIS_MIN:
+            factor = 1_000_000
+        else:
+            factor = 1_000_000_000
+
+        return df.with_columns(
+            (pl.col("ts_nanos").cast(pl.Int64) * factor).alias("ts_nanos")
+        )
+
+
+if __name__ == "__main__":
+    ...
@@ -10,6 +10,7 @@ from __future__ import annotations
 
 import enum
 
+from avin_data.manager.exchange import Exchange
 from avin_data.utils import SourceNotFound
 
 
@@ -18,6 +19,8 @@ class Source(enum.Enum):
 
     MOEX = 1
     TINKOFF = 2
+    BINANCE = 3
+    ISS = 4
 
     @classmethod
     def from_str(cls, string: str) -> Source:
@@ -38,6 +41,16 @@ class Source(enum.Enum):
             f"Source not found. Choice from {Source._member_names_}"
         )
 
+    @classmethod
+    def from_exchange(cls, exchange: Exchange) -> Source:
+        """Get default market data source for exchange."""
+
+        match exchange:
+            case Exchange.BINANCE:
+                return Source.BINANCE
+            case _:
+                return Source.MOEX
+
 
 if __name__ == "__main__":
     ...
@@ -0,0 +1,152 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+import sqlite3
+from pathlib import Path
+
+import polars as pl
+
+from avin_data.manager.data_file_bar import DataFileBar
+from avin_data.manager.data_file_tic import SCHEMA as TIC_SCHEMA
+from avin_data.manager.iid import Iid
+from avin_data.manager.market_data import MarketData
+from avin_data.utils import Cmd, cfg, log
+
+# Схема базы, такую же создает avin_core SqliteStorage.
+# iid - идентификатор в верхнем регистре: "MOEX_SHARE_SBER",
+# market_data - имя типа данных: "BAR_1M", остальные колонки как в
+# parquet файлах.
+SCHEMA_SQL = """
+    CREATE TABLE IF NOT EXISTS bars (
+        iid TEXT NOT NULL,
+        market_data TEXT NOT NULL,
+        ts_nanos INTEGER NOT NULL,
+        open REAL NOT NULL,
+        high REAL NOT NULL,
+        low REAL NOT NULL,
+        close REAL NOT NULL,
+        volume INTEGER NOT NULL,
+        value REAL,
+        PRIMARY KEY (iid, market_data, ts_nanos)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS tics (
+        iid TEXT NOT NULL,
+        ts_nanos INTEGER NOT NULL,
+        direction TEXT NOT NULL,
+        lots INTEGER NOT NULL,
+        price REAL NOT NULL,
+        value REAL,
+        session INTEGER,
+        tradeno INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS tics_iid_ts ON tics (iid, ts_nanos);
+"""
+BAR_COLUMNS = ["ts_nanos", "open", "high", "low", "close", "volume", "value"]
+
+
+class SqliteStorage:
+    """Market data in single SQLite database file.
+
+    Альтернативное хранилище для avin_core (data.format = "sqlite"):
+    бары и тики всех инструментов в одном файле <data>/avin.sqlite.
+    Основное хранилище avin-data - parquet файлы, sync копирует их в
+    базу, после загрузки новых данных sync нужно повторить.
+    """
+
+    def __init__(self, path: Path | None = None):
+        self.__path = path or Path(cfg.data, "avin.sqlite")
+
+    def path(self) -> Path:
+        return self.__path
+
+    def sync(self, iid: Iid, market_data: MarketData) -> int:
+        """Copy stored parquet data to database, return rows count."""
+
+        assert isinstance(iid, Iid)
+        assert isinstance(market_data, MarketData)
+
+        match market_data:
+            case MarketData.TIC:
+                return self.__sync_tics(iid)
+            case MarketData.BOOK:
+                log.warning(f"Not supported in database: {market_data}")
+                return 0
+            case _:
+                return self.__sync_bars(iid, market_data)
+
+    def query(self, sql: str) -> pl.DataFrame:
+        with self.__connect() as conn:
+            cursor = conn.execute(sql)
+            rows = cursor.fetchall()
+            names = [i[0] for i in cursor.description or []]
+
+        return pl.DataFrame(rows, schema=names, orient="row")
+
+    # private
+    def __connect(self) -> sqlite3.Connection:
+        Cmd.make_dirs(str(self.__path.parent))
+        conn = sqlite3.connect(self.__path)
+        conn.executescript(SCHEMA_SQL)
+
+        return conn
+
+    def __sync_bars(self, iid: Iid, market_data: MarketData) -> int:
+        key = str(iid).upper()
+        count = 0
+        with self.__connect() as conn:
+            for year in DataFileBar.years(iid, market_data):
+                df = DataFileBar.load(iid, market_data, year).df()
+                rows = [
+                    (key, market_data.name, *i)
+                    for i in df.select(BAR_COLUMNS).iter_rows()
+                ]
+                conn.executemany(
+                    "INSERT OR REPLACE INTO bars VALUES "
+                    "(?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    rows,
+                )
+                count += len(rows)
+
+        return count
+
+    def __sync_tics(self, iid: Iid) -> int:
+        key = str(iid).upper()
+        dir_path = Cmd.path(iid.path(), MarketData.TIC.name)
+        if not Cmd.is_exist(Path(dir_path)):
+            return 0
+
+        files = Cmd.get_files(dir_path, full_path=True, include_sub_dir=True)
+        files = sorted(i for i in files if i.endswith(".parquet"))
+
+        count = 0
+        with self.__connect() as conn:
+            for file in files:
+                df = Cmd.read_pqt(Path(file)).cast(TIC_SCHEMA)
+                if df.is_empty():
+                    continue
+
+                # тики файла дня заменяют тики этого интервала в базе
+                conn.execute(
+                    "DELETE FROM tics "
+                    "WHERE iid = ? AND ts_nanos >= ? AND ts_nanos <= ?",
+                    (key, df["ts_nanos"].min(), df["ts_nanos"].max()),
+                )
+                rows = [(key, *i) for i in df.iter_rows()]
+                conn.executemany(
+                    "INSERT INTO tics VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                    rows,
+                )
+                count += len(rows)
+
+        return count
+
+
+if __name__ == "__main__":
+    ...
@@ -7,12 +7,16 @@
 # ============================================================================
 
 from avin_data.utils.cmd import Cmd
-from avin_data.utils.conf import cfg
+from avin_data.utils.conf import SCHEMA_KEY, SCHEMA_VERSION, cfg
+from avin_data.utils.cron import Cron
 from avin_data.utils.exceptions import (
     CategoryNotFound,
     ConfigNotFound,
+    InvalidData,
     InvalidMarketData,
+    SourceAuthError,
     SourceNotFound,
+    SourceRequestError,
     TickerNotFound,
 )
 from avin_data.utils.logger import configure_log, log
@@ -30,10 +34,16 @@ from avin_data.utils.misc import (
 __all__ = (
     "Cmd",
     "cfg",
+    "SCHEMA_KEY",
+    "SCHEMA_VERSION",
+    "Cron",
     "CategoryNotFound",
     "ConfigNotFound",
+    "InvalidData",
     "InvalidMarketData",
+    "SourceAuthError",
     "SourceNotFound",
+    "SourceRequestError",
     "TickerNotFound",
     "configure_log",
     "dt_to_ts",
@@ -16,6 +16,7 @@ from collections import deque
 from pathlib import Path
 
 import polars as pl
+import pyarrow.parquet as pq
 
 
 class Cmd:
@@ -301,14 +302,54 @@ class Cmd:
 
         return df
 
+    @staticmethod
+    def read_pqt_metadata(path: Path) -> dict[str, str]:
+        """Key-value metadata of parquet file, without arrow schema"""
+
+        metadata = pq.read_schema(path).metadata or dict()
+        return {
+            k.decode(): v.decode()
+            for k, v in metadata.items()
+            if k != b"ARROW:schema"
+        }
+
     @staticmethod
     def write_pqt(
-        df: pl.DataFrame, path: Path, create_dirs: bool = True
+        df: pl.DataFrame,
+        path: Path,
+        create_dirs: bool = True,
+        *,
+        compression: str = "zstd",
+        compression_level: int | None = None,
+        row_group_size: int | None = None,
+        metadata: dict[str, str] | None = None,
     ) -> None:
         if create_dirs:
             Cmd.__create_dirs_for_filepath(path)
 
-        df.write_parquet(path)
+        if not metadata:
+            df.write_parquet(
+                path,
+                compression=compression,
+                compression_level=compression_level,
+                row_group_size=row_group_size,
+            )
+            return
+
+        # метаданные ключ-значение пишем через pyarrow
+        if compression == "uncompressed":
+            compression = "none"
+        table = df.to_arrow()
+        table = table.replace_schema_metadata(
+            {**(table.schema.metadata or dict()), **metadata}
+        )
+        pq.write_table(
+            table,
+            path,
+            compression=compression,
+            compression_level=compression_level,
+            row_group_size=row_group_size,
+        )
 
     @staticmethod
     def subprocess(command: list[str]) -> None:
@@ -14,6 +14,13 @@ from pathlib import Path
 from avin_data.utils.cmd import Cmd
 from avin_data.utils.exceptions import ConfigNotFound
 
+# Версия схемы хранимых данных, пишется в метаданные каждого parquet
+# файла под ключом SCHEMA_KEY. При изменении формата версия
+# увеличивается, а в manager/migration.py добавляется шаг миграции.
+# Файлы без версии - версия 1, формат до версионирования.
+SCHEMA_VERSION = 2
+SCHEMA_KEY = "avin_schema_version"
+
 __all__ = "cfg"
 
 
@@ -42,6 +49,19 @@ class Configuration:
     def moex_token(self) -> Path:
         return Path.home() / self.__cfg["connect"]["moex_token"]
 
+    @property
+    def moex_rate(self) -> float:
+        # запросов в секунду, 0 - без ограничения
+        return self.__cfg["connect"].get("moex_rate", 5)
+
+    @property
+    def moex_retries(self) -> int:
+        return self.__cfg["connect"].get("moex_retries", 5)
+
+    @property
+    def moex_backoff(self) -> float:
+        return self.__cfg["connect"].get("moex_backoff", 1)
+
     @property
     def log(self) -> Path:
         return Path(self.root, "log")
@@ -62,6 +82,71 @@ class Configuration:
     def cache(self) -> Path:
         return Path(self.data, "cache")
 
+    @property
+    def cache_max_age(self) -> int:
+        return self.__cfg.get("data", {}).get("cache_max_age", 7)
+
+    @property
+    def daemon_schedule(self) -> list[str]:
+        default = ["0 19 * * 1-5", "55 23 * * 1-5"]
+        return self.__daemon().get("schedule", default)
+
+    @property
+    def daemon_retries(self) -> int:
+        return self.__daemon().get("retries", 3)
+
+    @property
+    def daemon_retry_delay(self) -> TimeDelta:
+        return TimeDelta(seconds=self.__daemon().get("retry_delay", 300))
+
+    @property
+    def daemon_status(self) -> Path:
+        return Path(self.data, "status.json")
+
+    @property
+    def recorder_instruments(self) -> list[str]:
+        return self.__recorder().get("instruments", [])
+
+    @property
+    def recorder_depth(self) -> int:
+        return self.__recorder().get("depth", 20)
+
+    @property
+    def recorder_interval(self) -> TimeDelta:
+        return TimeDelta(seconds=self.__recorder().get("interval", 1))
+
+    @property
+    def recorder_flush(self) -> TimeDelta:
+        return TimeDelta(seconds=self.__recorder().get("flush", 60))
+
+    @property
+    def storage_compression(self) -> str:
+        return self.__storage().get("compression", "zstd")
+
+    @property
+    def storage_compression_level(self) -> int | None:
+        return self.__storage().get("compression_level")
+
+    @property
+    def storage_row_group_size(self) -> int | None:
+        # 0 - размер группы по умолчанию
+        return self.__storage().get("row_group_size") or None
+
+    @property
+    def storage_options(self) -> dict:
+        """Keyword arguments of Cmd.write_pqt for stored market data."""
+
+        return {
+            "compression": self.storage_compression,
+            "compression_level": self.storage_compression_level,
+            "row_group_size": self.storage_row_group_size,
+            "metadata": {SCHEMA_KEY: str(SCHEMA_VERSION)},
+        }
+
+    @property
+    def storage_partition(self) -> str:
+        return self.__storage().get("partition", "year")
+
     @property
     def log_history(self) -> int:
         return self.__cfg["log"]["history"]
@@ -82,6 +167,15 @@ class Configuration:
     def dt_fmt(self) -> str:
         return self.__cfg["usr"]["dt_fmt"]
 
+    def __daemon(self) -> dict:
+        return self.__cfg.get("data", {}).get("daemon", {})
+
+    def __recorder(self) -> dict:
+        return self.__cfg.get("data", {}).get("recorder", {})
+
+    def __storage(self) -> dict:
+        return self.__cfg.get("data", {}).get("storage", {})
+
     @classmethod
     def read_config(cls) -> Configuration:
         """Try find and read config
@@ -0,0 +1,92 @@
+#!/usr/bin/env  python3
+# ============================================================================
+# URL:          http://avin.info
+# AUTHOR:       Alex Avin
+# E-MAIL:       mr.alexavin@gmail.com
+# LICENSE:      MIT
+# ============================================================================
+
+from __future__ import annotations
+
+from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
+
+# minute hour day month weekday
+LIMITS = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 6)]
+
+
+class Cron:
+    """Cron-like schedule: "minute hour day month weekday".
+
+    Поддерживаются: "*", числа, списки "1,3,5", диапазоны "1-5" и
+    шаги "*/10", "0-30/5". День недели: 0 - воскресенье ... 6 -
+    суббота, как в cron. Пример: "55 23 * * 1-5" - в 23:55 по будням.
+    """
+
+    def __init__(self, expr: str):
+        fields = expr.split()
+        if len(fields) != 5:
+            raise ValueError(f"Invalid cron expression: '{expr}'")
+
+        self.__expr = expr
+        self.__sets = [
+            _parse_field(f, lo, hi)
+            for f, (lo, hi) in zip(fields, LIMITS, strict=True)
+        ]
+
+    def __str__(self) -> str:
+        return self.__expr
+
+    def matches(self, dt: DateTime) -> bool:
+        minutes, hours, days, months, weekdays = self.__sets
+        weekday = (dt.weekday() + 1) % 7  # python: 0 - monday
+
+        return (
+            dt.minute in minutes
+            and dt.hour in hours
+            and dt.day in days
+            and dt.month in months
+            and weekday in weekdays
+        )
+
+    def next(self, dt: DateTime) -> DateTime:
+        """Return next matching minute strictly after dt."""
+
+        candidate = dt.replace(second=0, microsecond=0)
+        candidate += TimeDelta(minutes=1)
+
+        # перебор по минутам, не больше года
+        limit = candidate + TimeDelta(days=366)
+        while candidate < limit:
+            if self.matches(candidate):
+                return candidate
+            candidate += TimeDelta(minutes=1)
+
+        raise ValueError(f"Cron expression never matches: '{self.__expr}'")
+
+
+def _parse_field(field: str, lo: int, hi: int) -> set[int]:
+    values = set()
+    for part in field.split(","):
+        step = 1
+        if "/" in part:
+            part, step_str = part.split("/", 1)
+            step = int(step_str)
+
+        if part == "*":
+            begin, end = lo, hi
+        elif "-" in part:
+            begin_str, end_str = part.split("-", 1)
+            begin, end = int(begin_str), int(end_str)
+        else:
+            begin = end = int(part)
+
+        if begin < lo or end > hi or begin > end or step < 1:
+            raise ValueError(f"Invalid cron field: '{field}'")
+        values.update(range(begin, end + 1, step))
+
+    return values
+
+
+if __name__ == "__main__":
+    ...
@@ -19,3 +19,15 @@ class TickerNotFound(Exception):
 
 class InvalidMarketData(Exception):
     """Invalid market data name exception."""
+
+
+class InvalidData(Exception):
+    """Invalid market data values exception."""
+
+
+class SourceAuthError(Exception):
+    """Source access denied exception: no account or subscription."""
+
+
+class SourceRequestError(Exception):
+    """Source request failed after retries exception."""
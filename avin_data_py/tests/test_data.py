@@ -7,7 +7,11 @@
 # ============================================================================
 
 import sys
+from datetime import UTC
 from datetime import datetime as DateTime
+from datetime import timedelta as TimeDelta
+
+import polars as pl
 
 sys.path.append("/home/alex/avin/avin_data_py")
 from avin_data import *
@@ -88,3 +92,210 @@ def test_iid():
     assert iid.lot() == 10
     assert iid.step() == 0.01
     assert iid.path() == "/home/alex/trading/usr/data/MOEX/SHARE/SBER"
+
+
+def test_csv_import_map():
+    mapping = CsvImport.parse_map(
+        "time=dt, open=o,high=h,low=l,close=c,volume=v"
+    )
+    assert mapping["time"] == "dt"
+    assert mapping["open"] == "o"
+    assert "value" not in mapping
+
+    try:
+        CsvImport.parse_map("time=dt,open=o")
+        assert False, "required fields missed"
+    except InvalidData:
+        pass
+
+
+def test_data_check():
+    info = {
+        "exchange": "MOEX",
+        "category": "SHARE",
+        "ticker": "SBER",
+        "figi": "BBG004730N88",
+        "name": "Сбер Банк",
+        "lot": "10",
+        "step": "0.01",
+    }
+    iid = Iid(info)
+
+    def ts(day, hour, minute):
+        dt = DateTime(2025, 6, day, hour, minute, tzinfo=UTC)
+        return int(dt.timestamp()) * 1_000_000_000
+
+    # 2025-06-03 пропущен, в 2025-06-02 разрыв 07:02 - 08:00
+    times = [
+        ts(2, 7, 0),
+        ts(2, 7, 1),
+        ts(2, 8, 0),
+        ts(2, 8, 0),
+        ts(4, 7, 0),
+        ts(4, 6, 59),
+    ]
+    df = pl.DataFrame(
+        {
+            "ts_nanos": times,
+            "open": [1.0] * 6,
+            "high": [1.0] * 6,
+            "low": [1.0] * 6,
+            "close": [1.0] * 6,
+            "volume": [10, 10, 0, 10, 10, 10],
+            "value": [10.0] * 6,
+        }
+    )
+
+    report = DataCheck.check_df(iid, MarketData.BAR_1M, df)
+    assert report["bars"] == 6
+    assert report["duplicates"] == [ts(2, 8, 0)]
+    assert report["non_monotonic"] == [ts(2, 8, 0), ts(4, 6, 59)]
+    assert report["zero_volume"] == [ts(2, 8, 0)]
+    assert report["missing_days"] == ["2025-06-03"]
+    assert report["gaps"] == [
+        {"begin": ts(2, 7, 2), "end": ts(2, 8, 0), "missing": 58}
+    ]
+    assert not DataCheck.is_ok(report)
+
+    ranges = DataCheck.broken_ranges(report)
+    assert ranges == [
+        (
+            DateTime(2025, 6, 2, 7, 2, tzinfo=UTC),
+            DateTime(2025, 6, 2, 8, 0, tzinfo=UTC),
+        ),
+        (
+            DateTime(2025, 6, 2, 21, 0, tzinfo=UTC),
+            DateTime(2025, 6, 3, 21, 0, tzinfo=UTC),
+        ),
+    ]
+
+
+def test_book_recorder_interval():
+    info = {
+        "exchange": "MOEX",
+        "category": "SHARE",
+        "ticker": "SBER",
+        "figi": "BBG004730N88",
+        "name": "Сбер Банк",
+        "lot": "10",
+        "step": "0.01",
+    }
+    iid = Iid(info)
+    recorder = BookRecorder(
+        [iid], 10, TimeDelta(seconds=1), TimeDelta(seconds=60)
+    )
+
+    def book(ts):
+        return {
+            "ts_nanos": ts,
+            "bid_price": [100.0],
+            "bid_lots": [5],
+            "ask_price": [100.1],
+            "ask_lots": [7],
+        }
+
+    second = 1_000_000_000
+    assert recorder.add(iid, book(0))
+    assert not recorder.add(iid, book(second // 2))
+    assert recorder.add(iid, book(second))
+
+
+def test_sqlite_storage(tmp_path):
+    storage = SqliteStorage(tmp_path / "avin.sqlite")
+
+    df = storage.query("SELECT COUNT(*) AS n FROM bars")
+    assert df.item(0, "n") == 0
+
+    df = storage.query("SELECT name FROM sqlite_master WHERE type='table'")
+    assert set(df["name"]) == {"bars", "tics"}
+
+
+def test_search_rank():
+    from avin_data.manager.manager import _search_rank
+
+    row = {
+        "ticker": "SBERP",
+        "figi": "BBG0047315Y7",
+        "isin": "RU0009029557",
+        "name": "Сбербанк России - привилегированные акции",
+    }
+    assert _search_rank("SBERP", row) == 0
+    assert _search_rank("RU0009029557", row) == 0
+    assert _search_rank("SBER", row) == 1
+    assert _search_rank("BERP", row) == 2
+    assert _search_rank("СБЕРБАНК", row) == 3
+    assert _search_rank("GAZP", row) is None
+
+
+def test_migration(tmp_path):
+    ms = 1_700_000_000_000
+    old = pl.DataFrame({"ts": [ms, ms + 60_000], "close": [1.0, 2.0]})
+    df = Migration.upgrade(old, 1)
+    assert df.columns == ["ts_nanos", "close"]
+    assert df.item(0, "ts_nanos") == ms * 1_000_000
+
+    dt = DateTime(2024, 1, 1)
+    old = pl.DataFrame({"dt": [dt]})
+    df = Migration.upgrade(old, 1)
+    assert df.item(0, "ts_nanos") == int(
+        dt.replace(tzinfo=UTC).timestamp() * 1_000_000_000
+    )
+
+    path = tmp_path / "2024.parquet"
+    Cmd.write_pqt(old, path)
+    assert Migration.version(path) == 1
+    assert Migration.migrate_file(path)
+    assert Migration.version(path) == SCHEMA_VERSION
+    assert not Migration.migrate_file(path)
+    assert Cmd.read_pqt(path).item(0, "ts_nanos") == df.item(0, "ts_nanos")
+
+
+def test_bar_builder():
+    second = 1_000_000_000
+    ts = 1_700_000_000 * second
+    tics = pl.DataFrame(
+        {
+            "ts_nanos": [ts, ts + second // 2, ts + 2 * second],
+            "direction": ["B", "S", "B"],
+            "lots": [1, 2, 3],
+            "price": [100.0, 99.0, 101.0],
+            "value": [1000.0, 1980.0, 3030.0],
+        }
+    )
+
+    bars = BarBuilder.aggregate(tics, MarketData.BAR_1S, lot=10)
+    assert bars["ts_nanos"].to_list() == [ts, ts + 2 * second]
+    assert bars.row(0, named=True) == {
+        "ts_nanos": ts,
+        "open": 100.0,
+        "high": 100.0,
+        "low": 99.0,
+        "close": 99.0,
+        "volume": 30,
+        "value": 2980.0,
+    }
+
+    bars = BarBuilder.aggregate(tics, MarketData.BAR_5S, lot=10)
+    assert len(bars) == 1
+    assert bars.item(0, "close") == 101.0
+    assert bars.item(0, "volume") == 60
+
+
+def test_moex_failure_kind():
+    import httpx
+
+    from avin_data.connect.source_moex import _failure_kind
+
+    request = httpx.Request("GET", "https://apim.moex.com")
+
+    def status(code):
+        response = httpx.Response(code, request=request)
+        return httpx.HTTPStatusError("", request=request, response=response)
+
+    assert _failure_kind(status(403)) == "auth"
+    assert _failure_kind(status(502)) == "http 502"
+    assert _failure_kind(status(429)) == "http 429"
+    assert _failure_kind(status(404)) is None
+    assert _failure_kind(httpx.ReadTimeout("", request=request)) == "timeout"
+    assert _failure_kind(httpx.ConnectError("")) == "connection"
+    assert _failure_kind(ValueError()) is None
@@ -82,3 +82,21 @@ def test_configuration():
     assert cfg.log_info
     assert cfg.offset == TimeDelta(hours=3)
     assert cfg.dt_fmt == "%Y-%m-%d %H:%M:%S"
+    assert cfg.storage_compression == "zstd"
+    assert cfg.storage_row_group_size is None
+    assert cfg.storage_partition == "year"
+
+
+def test_cron():
+    cron = Cron("55 23 * * 1-5")
+    assert cron.matches(DateTime(2025, 6, 5, 23, 55))  # thursday
+    assert not cron.matches(DateTime(2025, 6, 7, 23, 55))  # saturday
+
+    # friday -> next monday
+    dt = cron.next(DateTime(2025, 6, 6, 23, 55))
+    assert dt == DateTime(2025, 6, 9, 23, 55)
+
+    cron = Cron("*/15 10-11 * * *")
+    assert cron.next(DateTime(2025, 6, 5, 10, 50)) == DateTime(
+        2025, 6, 5, 11, 0
+    )
@@ -0,0 +1,187 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Derive macros of 'avin' library, reexported by the crates that
+//! define the traits: use `avin_strategy::StrategyParams`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Expr, Fields, parse_macro_input};
+
+/// Derive `avin_strategy::Params` from field attributes.
+///
+/// # ru
+/// Реализует `avin_strategy::Params` по атрибутам полей стратегии:
+///
+/// ```ignore
+/// #[derive(StrategyParams)]
+/// pub struct MyStrategy {
+///     #[param(begin = 0.97, end = 0.995, step = 0.005)]
+///     stop: f64,
+///     #[param(values = [5, 10, 20])]
+///     lots: u32,
+///     ...
+/// }
+/// ```
+///
+/// Поле с атрибутом `param` - параметр с именем поля: диапазон
+/// begin..=end с шагом step или явный список values. Тип поля -
+/// число, приводится из f64 через `as`. Стратегия должна
+/// реализовать Default - значения параметров, которых нет в наборе,
+/// и остальные поля берутся из него. Текущие значения полей-параметров
+/// возвращает `Params::values`, стратегия отдает их в
+/// `Strategy::param_values`.
+#[proc_macro_derive(StrategyParams, attributes(param))]
+pub fn derive_strategy_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match strategy_params(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn strategy_params(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "StrategyParams: only struct is supported",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "StrategyParams: struct must have named fields",
+        ));
+    };
+
+    let mut params = Vec::new();
+    let mut assigns = Vec::new();
+    let mut values = Vec::new();
+    for field in fields.named.iter() {
+        let Some(attr) =
+            field.attrs.iter().find(|a| a.path().is_ident("param"))
+        else {
+            continue;
+        };
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let key = ident.to_string();
+
+        let range = Range::parse(attr)?;
+        params.push(range.param(&key));
+        assigns.push(quote! {
+            if let Some(value) = params.get(#key) {
+                strategy.#ident = value as #ty;
+            }
+        });
+        values.push(quote! {
+            values.set(#key, self.#ident as f64);
+        });
+    }
+
+    if params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "StrategyParams: no field with #[param(...)]",
+        ));
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::avin_strategy::Params for #name #ty_generics
+        #where_clause
+        {
+            fn params() -> Vec<::avin_strategy::Param> {
+                vec![#(#params),*]
+            }
+            fn with_params(params: &::avin_strategy::ParamSet) -> Self {
+                let mut strategy =
+                    <Self as ::std::default::Default>::default();
+                #(#assigns)*
+
+                strategy
+            }
+            fn values(&self) -> ::avin_strategy::ParamSet {
+                let mut values = ::avin_strategy::ParamSet::new();
+                #(#values)*
+
+                values
+            }
+        }
+    })
+}
+
+/// Значения параметра из атрибута `#[param(...)]`.
+enum Range {
+    /// begin, end, step
+    Step(Box<(Expr, Expr, Expr)>),
+    Values(Vec<Expr>),
+}
+impl Range {
+    fn parse(attr: &syn::Attribute) -> syn::Result<Self> {
+        let mut begin = None;
+        let mut end = None;
+        let mut step = None;
+        let mut values = None;
+
+        attr.parse_nested_meta(|meta| {
+            let key = meta.path.get_ident().map(|i| i.to_string());
+            let expr: Expr = meta.value()?.parse()?;
+            match key.as_deref() {
+                Some("begin") => begin = Some(expr),
+                Some("end") => end = Some(expr),
+                Some("step") => step = Some(expr),
+                Some("values") => match expr {
+                    Expr::Array(array) => {
+                        values = Some(array.elems.into_iter().collect())
+                    }
+                    _ => return Err(meta.error("values must be array")),
+                },
+                _ => return Err(meta.error("unknown param key")),
+            }
+
+            Ok(())
+        })?;
+
+        match (begin, end, step, values) {
+            (Some(begin), Some(end), Some(step), None) => {
+                Ok(Self::Step(Box::new((begin, end, step))))
+            }
+            (None, None, None, Some(values)) => Ok(Self::Values(values)),
+            _ => Err(syn::Error::new_spanned(
+                attr,
+                "param needs begin, end, step or values",
+            )),
+        }
+    }
+    fn param(&self, key: &str) -> TokenStream2 {
+        match self {
+            Self::Step(range) => {
+                let (begin, end, step) = range.as_ref();
+                quote! {
+                    ::avin_strategy::Param::range(
+                        #key,
+                        (#begin) as f64,
+                        (#end) as f64,
+                        (#step) as f64,
+                    )
+                }
+            }
+            Self::Values(values) => quote! {
+                ::avin_strategy::Param::list(
+                    #key,
+                    &[#((#values) as f64),*],
+                )
+            },
+        }
+    }
+}
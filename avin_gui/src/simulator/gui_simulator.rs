@@ -6,7 +6,7 @@
  ****************************************************************************/
 
 use avin_core::Asset;
-use avin_simulator::Simulator;
+use avin_simulator::{Command, Simulator, Speed};
 use avin_utils as utils;
 use eframe::egui;
 
@@ -18,6 +18,7 @@ pub struct GuiSimulator {
     #[serde(skip)]
     simulator: Simulator,
     step: usize,
+    jump: String,
 
     chart_widget: ChartWidget,
 }
@@ -29,6 +30,7 @@ impl Default for GuiSimulator {
         Self {
             simulator: Simulator::new(asset.iid(), begin, end),
             step: 1,
+            jump: String::new(),
 
             chart_widget: ChartWidget::default(),
         }
@@ -52,6 +54,11 @@ impl GuiSimulator {
 }
 impl eframe::App for GuiSimulator {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.simulator.is_playing() {
+            self.simulator.update();
+            ctx.request_repaint();
+        }
+
         ui_top(self, ctx);
         ui_center(self, ctx);
     }
@@ -90,6 +97,37 @@ fn ui_top(app: &mut GuiSimulator, ctx: &egui::Context) {
                 app.simulator.restart();
             }
             ui.separator();
+
+            ui.label("Replay: ");
+            let speed = app.simulator.speed();
+            for s in Speed::all() {
+                if ui.selectable_label(speed == s, s.to_string()).clicked() {
+                    app.simulator.execute(Command::Speed(s));
+                }
+            }
+            if app.simulator.is_playing() {
+                if ui.button("Pause").clicked() {
+                    app.simulator.execute(Command::Pause);
+                }
+            } else if ui.button("Play").clicked() {
+                app.simulator.execute(Command::Resume);
+            }
+            ui.separator();
+
+            // "2024-03-01 10:00", московское время
+            ui.add(
+                egui::TextEdit::singleline(&mut app.jump)
+                    .hint_text("YYYY-MM-DD HH:MM")
+                    .desired_width(120.0),
+            );
+            if ui.button("Jump").clicked() {
+                match Command::try_from(format!("jump {}", app.jump).as_str())
+                {
+                    Ok(command) => app.simulator.execute(command),
+                    Err(e) => log::error!("{e}"),
+                }
+            }
+            ui.separator();
         });
     });
 }
@@ -64,7 +64,23 @@ impl AssetWidget {
             if ui.button(self.asset_list.name()).clicked() {
                 self.file_dialog.pick_file();
             }
-            let _ = ui.button("...");
+            // списки папки активов, среди них списки наблюдения сканера
+            ui.menu_button("...", |ui| {
+                let files = Cmd::get_files(&CFG.dir.asset()).unwrap();
+                for path in files.iter() {
+                    let name = Cmd::name(path).unwrap();
+                    if ui.button(&name).clicked() {
+                        match AssetList::load(path) {
+                            Ok(asset_list) => {
+                                self.asset_list = asset_list;
+                                self.current_index = 0;
+                            }
+                            Err(e) => log::error!("Asset list {name}: {e}"),
+                        }
+                        ui.close();
+                    }
+                }
+            });
 
             // Update the dialog
             self.file_dialog.update(ctx);
@@ -330,6 +346,8 @@ impl AssetWidget {
                     asset.bar_event(e)
                 }
                 Event::Tic(e) => todo!("{:?}", e),
+                // стакан виджет не показывает
+                Event::Book(_) => {}
                 Event::Order(e) => todo!("{:?}", e),
             }
         }
@@ -9,6 +9,7 @@ use eframe::egui;
 
 use avin_connect::Tinkoff;
 use avin_core::Action;
+use avin_utils::{CFG, Cmd};
 
 use crate::chart_widget::ChartWidget;
 use crate::terminal::asset_widget::AssetWidget;
@@ -23,6 +24,14 @@ pub struct Terminal {
     #[serde(skip)]
     is_active_mode: bool,
     #[serde(skip)]
+    is_risk_open: bool,
+    #[serde(skip)]
+    risk_events: Vec<String>,
+    #[serde(skip)]
+    is_notify_open: bool,
+    #[serde(skip)]
+    notices: Vec<String>,
+    #[serde(skip)]
     action_tx: tokio::sync::mpsc::UnboundedSender<Action>,
     #[serde(skip)]
     tokio_runtime: tokio::runtime::Runtime,
@@ -49,6 +58,10 @@ impl Default for Terminal {
             chart_widget: ChartWidget::default(),
 
             is_active_mode: false,
+            is_risk_open: false,
+            risk_events: Vec::new(),
+            is_notify_open: false,
+            notices: Vec::new(),
             action_tx,
             tokio_runtime,
         }
@@ -69,6 +82,34 @@ impl Terminal {
 
         Terminal::default()
     }
+
+    // private
+    fn load_risk_events(&mut self) {
+        self.risk_events.clear();
+        if CFG.trader.risk.log.is_empty() {
+            return;
+        }
+
+        // лог пишет риск менеджер трейдера, новые события внизу
+        let path = CFG.dir.root().join(&CFG.trader.risk.log);
+        if let Ok(lines) = Cmd::read_lines(&path) {
+            self.risk_events = lines.map_while(Result::ok).collect();
+            self.risk_events.reverse();
+        }
+    }
+    fn load_notices(&mut self) {
+        self.notices.clear();
+        if CFG.trader.notify.log.is_empty() {
+            return;
+        }
+
+        // уведомления стратегий пишет трейдер, новые внизу
+        let path = CFG.dir.root().join(&CFG.trader.notify.log);
+        if let Ok(lines) = Cmd::read_lines(&path) {
+            self.notices = lines.map_while(Result::ok).collect();
+            self.notices.reverse();
+        }
+    }
 }
 impl eframe::App for Terminal {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -77,6 +118,8 @@ impl eframe::App for Terminal {
         ui_top(self, ctx);
         ui_left(self, ctx);
         ui_center(self, ctx);
+        ui_risk(self, ctx);
+        ui_notify(self, ctx);
 
         if self.is_active_mode {
             ctx.request_repaint();
@@ -105,6 +148,16 @@ fn ui_top(app: &mut Terminal, ctx: &egui::Context) {
             ui.separator();
             ui.label("Active:");
             ui.add(toggle(&mut app.is_active_mode));
+
+            ui.separator();
+            if ui.button("Risk").clicked() {
+                app.is_risk_open = !app.is_risk_open;
+                app.load_risk_events();
+            }
+            if ui.button("Notify").clicked() {
+                app.is_notify_open = !app.is_notify_open;
+                app.load_notices();
+            }
         });
     });
 }
@@ -119,6 +172,54 @@ fn ui_center(app: &mut Terminal, ctx: &egui::Context) {
         app.chart_widget.ui(ui, asset);
     });
 }
+fn ui_risk(app: &mut Terminal, ctx: &egui::Context) {
+    let mut is_open = app.is_risk_open;
+    egui::Window::new("Risk")
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            if ui.button("Reload").clicked() {
+                app.load_risk_events();
+            }
+            ui.separator();
+
+            if CFG.trader.risk.log.is_empty() {
+                ui.label("Risk log is off: set trader.risk.log in config");
+            } else if app.risk_events.is_empty() {
+                ui.label("No risk events");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for e in app.risk_events.iter() {
+                    ui.label(e);
+                }
+            });
+        });
+    app.is_risk_open = is_open;
+}
+fn ui_notify(app: &mut Terminal, ctx: &egui::Context) {
+    let mut is_open = app.is_notify_open;
+    egui::Window::new("Notify")
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            if ui.button("Reload").clicked() {
+                app.load_notices();
+            }
+            ui.separator();
+
+            if CFG.trader.notify.log.is_empty() {
+                ui.label(
+                    "Notify log is off: set trader.notify.log in config",
+                );
+            } else if app.notices.is_empty() {
+                ui.label("No notifications");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for i in app.notices.iter() {
+                    ui.label(i);
+                }
+            });
+        });
+    app.is_notify_open = is_open;
+}
 
 pub fn toggle_ui(ui: &mut egui::Ui, on: &mut bool) -> egui::Response {
     // Widget code can be broken up in four steps:
@@ -32,6 +32,7 @@ impl SummaryTable {
             .column(Column::remainder())
             .column(Column::remainder())
             .column(Column::remainder())
+            .column(Column::remainder())
             .min_scrolled_height(0.0)
             .max_scroll_height(available_height);
         table = table.sense(egui::Sense::click());
@@ -75,6 +76,9 @@ impl SummaryTable {
                 header.col(|ui| {
                     ui.strong("gross loss");
                 });
+                header.col(|ui| {
+                    ui.strong("commission");
+                });
             })
             .body(|body| {
                 body.rows(text_height, 1, |mut row| {
@@ -114,6 +118,9 @@ impl SummaryTable {
                     row.col(|ui| {
                         ui.label(summary.gross_loss.to_string());
                     });
+                    row.col(|ui| {
+                        ui.label(summary.commission.to_string());
+                    });
                 });
             });
     }
@@ -0,0 +1,49 @@
+"""Пробой максимума прошлого часа, выход по стопу или тейку.
+
+Сборка модуля: cd avin_py && maturin develop --release
+"""
+
+from datetime import UTC, datetime
+
+import avin_py
+
+
+class Breakout:
+    name = "PyBreakout"
+    timeframes = ["1M", "1H"]
+    history = 50
+
+    def init(self, ctx):
+        self.entered = False
+
+    def process(self, ctx):
+        hours = ctx.bars("1H")
+        if len(hours) < 2 or ctx.position != 0 or self.entered:
+            return
+
+        price = ctx.price()
+        if price > hours[-2].high:
+            ctx.buy(1)
+            self.entered = True
+
+    def order_event(self, ctx, event):
+        if event.status != "filled":
+            return
+
+        if ctx.position > 0:
+            price = ctx.price()
+            ctx.stop_loss("sell", 1, round(price * 0.99, 2))
+            ctx.take_profit("sell", 1, round(price * 1.03, 2))
+        elif ctx.position == 0:
+            for order_id in ctx.orders():
+                ctx.cancel(order_id)
+            self.entered = False
+
+
+if __name__ == "__main__":
+    begin = datetime(2024, 1, 1, tzinfo=UTC)
+    end = datetime(2024, 7, 1, tzinfo=UTC)
+    result = avin_py.backtest(Breakout(), "moex_share_sber", begin, end)
+
+    print(f"trades={result['total_trades']} profit={result['profit']}")
+    print(f"saved to {result['dir']}")
@@ -0,0 +1,251 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, Utc};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use avin_core::{
+    Bar, Chart, ExtremumIndicator, Manager, Term, TimeFrame, Trend,
+};
+
+/// Bar of chart.
+///
+/// # ru
+/// Бар графика, поля только для чтения.
+#[pyclass(name = "Bar", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyBar {
+    pub bar: Bar,
+}
+#[pymethods]
+impl PyBar {
+    #[getter]
+    fn ts(&self) -> i64 {
+        self.bar.ts
+    }
+    #[getter]
+    fn dt(&self) -> DateTime<Utc> {
+        self.bar.dt()
+    }
+    #[getter]
+    fn open(&self) -> f64 {
+        self.bar.o
+    }
+    #[getter]
+    fn high(&self) -> f64 {
+        self.bar.h
+    }
+    #[getter]
+    fn low(&self) -> f64 {
+        self.bar.l
+    }
+    #[getter]
+    fn close(&self) -> f64 {
+        self.bar.c
+    }
+    #[getter]
+    fn volume(&self) -> u64 {
+        self.bar.v
+    }
+    fn is_bull(&self) -> bool {
+        self.bar.is_bull()
+    }
+    fn is_bear(&self) -> bool {
+        self.bar.is_bear()
+    }
+    fn __repr__(&self) -> String {
+        format!("{}", self.bar)
+    }
+}
+
+/// Trend between two extremums.
+///
+/// # ru
+/// Тренд индикатора экстремумов.
+#[pyclass(name = "Trend", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyTrend {
+    pub trend: Trend,
+}
+#[pymethods]
+impl PyTrend {
+    #[getter]
+    fn term(&self) -> String {
+        self.trend.term().to_string()
+    }
+    #[getter]
+    fn begin_ts(&self) -> i64 {
+        self.trend.begin().ts
+    }
+    #[getter]
+    fn begin_price(&self) -> f64 {
+        self.trend.begin().price
+    }
+    #[getter]
+    fn end_ts(&self) -> i64 {
+        self.trend.end().ts
+    }
+    #[getter]
+    fn end_price(&self) -> f64 {
+        self.trend.end().price
+    }
+    fn is_bull(&self) -> bool {
+        self.trend.is_bull()
+    }
+    fn is_bear(&self) -> bool {
+        self.trend.is_bear()
+    }
+    /// Длина тренда в барах.
+    fn len(&self) -> u32 {
+        self.trend.len()
+    }
+    fn abs_p(&self) -> f64 {
+        self.trend.abs_p()
+    }
+    fn speed_p(&self) -> f64 {
+        self.trend.speed_p()
+    }
+    fn __repr__(&self) -> String {
+        format!("{}", self.trend)
+    }
+}
+
+/// Chart of instrument with indicators.
+///
+/// # ru
+/// График инструмента. Загружается из хранилища рыночных данных
+/// (`Chart.load`) или приходит в стратегию из `Context.chart`.
+/// Индикатор экстремумов подключается через `add_extremum`.
+#[pyclass(name = "Chart", unsendable)]
+pub struct PyChart {
+    pub chart: Chart,
+}
+#[pymethods]
+impl PyChart {
+    /// Загружает график из хранилища, ticker как в `Asset::new`,
+    /// например "moex_share_sber", tf - "1M", "10M", "1H", "D", "W",
+    /// "M".
+    #[staticmethod]
+    fn load(
+        ticker: &str,
+        tf: &str,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> PyResult<Self> {
+        let iid = Manager::find_iid(ticker)
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        let tf = timeframe(tf)?;
+        let chart = Chart::load(&iid, tf, begin, end)
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+
+        Ok(Self { chart })
+    }
+    #[getter]
+    fn ticker(&self) -> String {
+        self.chart.ticker().clone()
+    }
+    #[getter]
+    fn tf(&self) -> String {
+        self.chart.tf().to_string()
+    }
+    fn __len__(&self) -> usize {
+        self.chart.bars().len()
+    }
+    fn bars(&self) -> Vec<PyBar> {
+        self.chart.bars().iter().map(|i| PyBar { bar: i }).collect()
+    }
+    /// Бар по номеру как в Pine: 0 - текущий, 1 - последний
+    /// исторический и тд.
+    fn bar(&self, n: usize) -> Option<PyBar> {
+        self.chart.bar(n).map(|i| PyBar { bar: i })
+    }
+    fn now(&self) -> Option<PyBar> {
+        self.chart.now().map(|i| PyBar { bar: i })
+    }
+    fn last_n(&self, n: usize) -> Vec<PyBar> {
+        let n = n.min(self.chart.bars().len());
+        self.chart
+            .last_n(n)
+            .iter()
+            .map(|i| PyBar { bar: i })
+            .collect()
+    }
+    /// Колонки баров: словарь списков ts, open, high, low, close,
+    /// volume - для pandas.DataFrame или polars.DataFrame.
+    fn columns<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let bars = self.chart.bars();
+        let dict = PyDict::new(py);
+        dict.set_item("ts", bars.ts)?;
+        dict.set_item("open", bars.o)?;
+        dict.set_item("high", bars.h)?;
+        dict.set_item("low", bars.l)?;
+        dict.set_item("close", bars.c)?;
+        dict.set_item("volume", bars.v)?;
+
+        Ok(dict)
+    }
+    /// Подключает индикатор экстремумов.
+    fn add_extremum(&mut self) {
+        if !self.chart.has_extremum() {
+            self.chart.init();
+        }
+    }
+    /// Тренд term ("T1".."T5"), n - номер с конца, 0 - текущий.
+    fn trend(&self, term: &str, n: usize) -> PyResult<Option<PyTrend>> {
+        let term = parse_term(term)?;
+        if !self.chart.has_extremum() {
+            return Err(PyRuntimeError::new_err(
+                "chart has no extremum indicator, call add_extremum()",
+            ));
+        }
+        let trend = self.chart.trend(term, n);
+
+        Ok(trend.map(|i| PyTrend { trend: i.clone() }))
+    }
+    fn __repr__(&self) -> String {
+        format!(
+            "Chart={} {} bars={}",
+            self.chart.ticker(),
+            self.chart.tf(),
+            self.chart.bars().len()
+        )
+    }
+}
+
+/// Таймфрейм по строке как в его Display: "1M", "1H", "D"...
+pub fn timeframe(s: &str) -> PyResult<TimeFrame> {
+    TimeFrame::all()
+        .into_iter()
+        .find(|i| i.to_string() == s)
+        .ok_or_else(|| PyValueError::new_err(format!("invalid tf: {s}")))
+}
+fn parse_term(s: &str) -> PyResult<Term> {
+    match s {
+        "T1" => Ok(Term::T1),
+        "T2" => Ok(Term::T2),
+        "T3" => Ok(Term::T3),
+        "T4" => Ok(Term::T4),
+        "T5" => Ok(Term::T5),
+        _ => Err(PyValueError::new_err(format!("invalid term: {s}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(timeframe("1H").unwrap(), TimeFrame::H1);
+        assert_eq!(timeframe("D").unwrap(), TimeFrame::Day);
+        assert!(timeframe("5M").is_err());
+        assert_eq!(parse_term("T3").unwrap(), Term::T3);
+        assert!(parse_term("T9").is_err());
+    }
+}
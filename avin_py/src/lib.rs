@@ -0,0 +1,48 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! # AVIN  -  Ars Vincere
+//!
+//! Python bindings for strategy authors.
+//!
+//! # ru
+//! Python модуль avin_py: графики, индикаторы, ордера, тестер и
+//! трейдер avin для стратегий на python, чтобы прототип стратегии
+//! запускался в тестере и трейдере без переписывания на Rust.
+//! Собирается maturin, см. pyproject.toml:
+//!
+//! ```bash
+//! cd avin_py && maturin develop --release
+//! ```
+//!
+//! ```python
+//! import avin_py
+//! result = avin_py.backtest(Breakout(), "moex_share_sber", begin, end)
+//! ```
+
+mod chart;
+mod run;
+mod strategy;
+
+pub use chart::{PyBar, PyChart, PyTrend};
+pub use run::{backtest, trade};
+pub use strategy::{Context, OrderInfo, PyStrategy};
+
+use pyo3::prelude::*;
+
+#[pymodule]
+fn avin_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyBar>()?;
+    m.add_class::<PyChart>()?;
+    m.add_class::<PyTrend>()?;
+    m.add_class::<Context>()?;
+    m.add_class::<OrderInfo>()?;
+    m.add_function(wrap_pyfunction!(backtest, m)?)?;
+    m.add_function(wrap_pyfunction!(trade, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, Utc};
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use avin_core::{Manager, Summary, Trade};
+use avin_strategy::Strategy;
+use avin_tester::{Test, Tester};
+use avin_trader::Trader;
+
+use crate::strategy::PyStrategy;
+
+/// Run backtest of python strategy.
+///
+/// # ru
+/// Тест стратегии на python в тестере avin на истории инструмента
+/// ticker (например "moex_share_sber") за период [begin, end).
+/// Результат сохраняется как обычный тест, возвращается словарь с
+/// итогами [`Summary`], списком трейдов и путем к папке теста.
+#[pyfunction]
+#[pyo3(signature = (strategy, ticker, begin, end, deposit = 100_000.0))]
+pub fn backtest<'py>(
+    py: Python<'py>,
+    strategy: Py<PyAny>,
+    ticker: &str,
+    begin: DateTime<Utc>,
+    end: DateTime<Utc>,
+    deposit: f64,
+) -> PyResult<Bound<'py, PyDict>> {
+    let iid = Manager::find_iid(ticker)
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    let strategy = PyStrategy::new(py, strategy)?;
+
+    let mut test = Test::new(&strategy, &iid);
+    test.deposit = deposit;
+    test.set_begin(&begin);
+    test.set_end(&end);
+
+    // стратегия берет GIL сама на каждом событии
+    py.detach(|| {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut tester = Tester::new();
+        runtime.block_on(tester.run(strategy, &mut test));
+    });
+
+    result(py, &test)
+}
+
+/// Start trader with python strategies.
+///
+/// # ru
+/// Запускает трейдер по конфигу, strategies - словарь имя -> класс
+/// стратегии на python. Для каждой стратегии списка работ с таким
+/// именем создается экземпляр класса. Работает до остановки процесса.
+#[pyfunction]
+pub fn trade(py: Python<'_>, strategies: Bound<'_, PyDict>) -> PyResult<()> {
+    let mut trader = Trader::new();
+    for (name, class) in strategies.iter() {
+        let name: String = name.extract()?;
+        let class: Py<PyAny> = class.unbind();
+        trader.add_strategy(&name, move || {
+            Python::attach(|py| {
+                let obj = class.bind(py).call0().unwrap().unbind();
+                let strategy = PyStrategy::new(py, obj).unwrap();
+                Box::new(strategy) as Box<dyn Strategy>
+            })
+        });
+    }
+
+    py.detach(|| {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(trader.start())
+    })
+    .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+fn result<'py>(py: Python<'py>, test: &Test) -> PyResult<Bound<'py, PyDict>> {
+    let s = Summary::new(&test.trade_list);
+    let dict = PyDict::new(py);
+    dict.set_item("name", test.name())?;
+    dict.set_item("dir", test.dir())?;
+    dict.set_item("profit", s.profit)?;
+    dict.set_item("percent_profitable", s.percent_profitable)?;
+    dict.set_item("total_trades", s.total_trades)?;
+    dict.set_item("win_trades", s.win_trades)?;
+    dict.set_item("loss_trades", s.loss_trades)?;
+    dict.set_item("ratio", s.ratio)?;
+    dict.set_item("average_trade", s.average_trade)?;
+    dict.set_item("max_win", s.max_win)?;
+    dict.set_item("max_loss", s.max_loss)?;
+    dict.set_item("commission", s.commission)?;
+
+    let trades = PyList::empty(py);
+    for trade in test.trade_list.trades() {
+        let Trade::Closed(t) = trade else {
+            continue;
+        };
+        let item = PyDict::new(py);
+        item.set_item("ts", t.ts)?;
+        item.set_item("kind", t.kind.to_string())?;
+        item.set_item("result", t.result())?;
+        trades.append(item)?;
+    }
+    dict.set_item("trades", trades)?;
+
+    Ok(dict)
+}
@@ -0,0 +1,604 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+
+use avin_core::{
+    Account, Action, Asset, Bar, Chart, Direction, Iid, LimitOrder,
+    MarketOrder, NotifyAction, NotifyLevel, Order, OrderAction, OrderEvent,
+    StopOrder, StopOrderKind, TimeFrame, Trade, TradeKind,
+};
+use avin_strategy::Strategy;
+
+use crate::chart::{PyBar, PyChart, timeframe};
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+// Сколько последних баров каждого таймфрейма видит стратегия
+const DEFAULT_HISTORY: usize = 200;
+
+/// Order event for python strategy.
+///
+/// # ru
+/// Событие ордера для стратегии на python: id ордера у брокера,
+/// тип, статус, направление, лоты и цена (лимитной или стоп).
+#[pyclass(name = "OrderInfo", frozen)]
+#[derive(Debug, Clone)]
+pub struct OrderInfo {
+    #[pyo3(get)]
+    pub id: Option<String>,
+    /// "market", "limit", "stop".
+    #[pyo3(get)]
+    pub kind: &'static str,
+    /// "posted", "filled", "canceled", "rejected", "triggered", ...
+    #[pyo3(get)]
+    pub status: &'static str,
+    /// "buy" или "sell".
+    #[pyo3(get)]
+    pub direction: &'static str,
+    #[pyo3(get)]
+    pub lots: u32,
+    #[pyo3(get)]
+    pub price: Option<f64>,
+}
+impl OrderInfo {
+    fn new(order: &Order) -> Self {
+        let (kind, status, price) = match order {
+            Order::Market(o) => ("market", market_status(o), None),
+            Order::Limit(o) => ("limit", limit_status(o), limit_price(o)),
+            Order::Stop(o) => ("stop", stop_status(o), stop_price(o)),
+        };
+        let direction = match order.direction() {
+            Direction::Buy => "buy",
+            Direction::Sell => "sell",
+        };
+        // сработавший стоп передает id стопа, не порожденного ордера
+        let id = match order {
+            Order::Stop(StopOrder::Triggered(o)) => Some(o.stop_id().clone()),
+            _ => order.broker_id().cloned(),
+        };
+
+        Self {
+            id,
+            kind,
+            status,
+            direction,
+            lots: order.lots(),
+            price,
+        }
+    }
+}
+#[pymethods]
+impl OrderInfo {
+    fn __repr__(&self) -> String {
+        format!(
+            "OrderInfo={} {} {} {} lots={}",
+            self.id.as_deref().unwrap_or("-"),
+            self.kind,
+            self.status,
+            self.direction,
+            self.lots
+        )
+    }
+}
+
+/// Access of python strategy to market data and orders.
+///
+/// # ru
+/// Контекст стратегии на python: последние бары графиков ее
+/// таймфреймов, позиция, выставление ордеров и уведомления
+/// `ctx.notify("info", "entered long")`. Передается в методы init,
+/// process и order_event стратегии.
+#[pyclass(name = "Context")]
+pub struct Context {
+    name: &'static str,
+    trader: Trader,
+    account: Account,
+    iid: Iid,
+    history: usize,
+    charts: Vec<(TimeFrame, Vec<Bar>)>,
+    /// Позиция в штуках.
+    position: i64,
+    /// Выставленные ордера по id брокера.
+    orders: HashMap<String, Order>,
+}
+impl Context {
+    fn update(&mut self, asset: &Asset, timeframes: &[TimeFrame]) {
+        self.charts.clear();
+        for tf in timeframes {
+            let Some(chart) = asset.chart(*tf) else {
+                continue;
+            };
+            let n = self.history.min(chart.bars().len());
+            self.charts.push((*tf, chart.last_n(n).to_vec()));
+        }
+    }
+    fn bars_of(&self, tf: TimeFrame) -> &[Bar] {
+        self.charts
+            .iter()
+            .find(|i| i.0 == tf)
+            .map_or(&[], |i| i.1.as_slice())
+    }
+    fn post(&self, order: Order) {
+        let a = OrderAction::new(
+            self.account.clone(),
+            self.iid.clone(),
+            self.name,
+            order,
+        );
+        self.trader.send(Action::Post(a)).unwrap();
+    }
+}
+#[pymethods]
+impl Context {
+    #[getter]
+    fn ticker(&self) -> String {
+        self.iid.ticker().clone()
+    }
+    /// Количество штук в лоте.
+    #[getter]
+    fn lot(&self) -> u32 {
+        self.iid.lot()
+    }
+    /// Позиция в штуках, шорт - отрицательная.
+    #[getter]
+    fn position(&self) -> i64 {
+        self.position
+    }
+    /// Время текущего 1М бара, ts nanos.
+    #[getter]
+    fn ts(&self) -> Option<i64> {
+        self.bars_of(TimeFrame::M1).last().map(|i| i.ts)
+    }
+    /// Цена последней сделки.
+    fn price(&self) -> Option<f64> {
+        self.bars_of(TimeFrame::M1).last().map(|i| i.c)
+    }
+    /// Последние бары таймфрейма, последний - текущий.
+    fn bars(&self, tf: &str) -> PyResult<Vec<PyBar>> {
+        let tf = timeframe(tf)?;
+        let bars = self.bars_of(tf);
+
+        Ok(bars.iter().map(|i| PyBar { bar: *i }).collect())
+    }
+    /// График таймфрейма из последних баров, к нему можно подключить
+    /// индикаторы.
+    fn chart(&self, tf: &str) -> PyResult<PyChart> {
+        let tf = timeframe(tf)?;
+        let chart = Chart::new(&self.iid, tf, self.bars_of(tf).to_vec());
+
+        Ok(PyChart { chart })
+    }
+    /// Id выставленных ордеров.
+    fn orders(&self) -> Vec<String> {
+        self.orders.keys().cloned().collect()
+    }
+    fn buy(&self, lots: u32) {
+        let order = MarketOrder::new(Direction::Buy, lots);
+        self.post(Order::Market(MarketOrder::New(order)));
+    }
+    fn sell(&self, lots: u32) {
+        let order = MarketOrder::new(Direction::Sell, lots);
+        self.post(Order::Market(MarketOrder::New(order)));
+    }
+    fn limit(&self, direction: &str, lots: u32, price: f64) -> PyResult<()> {
+        let order = LimitOrder::new(parse_direction(direction)?, lots, price);
+        self.post(Order::Limit(LimitOrder::New(order)));
+
+        Ok(())
+    }
+    fn stop_loss(
+        &self,
+        direction: &str,
+        lots: u32,
+        price: f64,
+    ) -> PyResult<()> {
+        self.stop(StopOrderKind::StopLoss, direction, lots, price)
+    }
+    fn take_profit(
+        &self,
+        direction: &str,
+        lots: u32,
+        price: f64,
+    ) -> PyResult<()> {
+        self.stop(StopOrderKind::TakeProfit, direction, lots, price)
+    }
+    fn cancel(&self, id: &str) -> PyResult<()> {
+        let Some(order) = self.orders.get(id) else {
+            return Err(PyKeyError::new_err(format!("no order {id}")));
+        };
+        let a = OrderAction::new(
+            self.account.clone(),
+            self.iid.clone(),
+            self.name,
+            order.clone(),
+        );
+        self.trader.send(Action::Cancel(a)).unwrap();
+
+        Ok(())
+    }
+    fn notify(&self, level: &str, message: &str) -> PyResult<()> {
+        let level = NotifyLevel::try_from(level)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let a = NotifyAction::new(self.name, level, message);
+        self.trader.send(Action::Notify(a)).unwrap();
+
+        Ok(())
+    }
+}
+impl Context {
+    fn stop(
+        &self,
+        kind: StopOrderKind,
+        direction: &str,
+        lots: u32,
+        price: f64,
+    ) -> PyResult<()> {
+        let direction = parse_direction(direction)?;
+        let order = StopOrder::new(kind, direction, lots, price, None);
+        self.post(Order::Stop(StopOrder::New(order)));
+
+        Ok(())
+    }
+}
+
+/// Strategy written in python.
+///
+/// # ru
+/// Стратегия, написанная на python, для тестера и трейдера. Объект
+/// стратегии должен иметь атрибут `name` и методы `init(ctx)`,
+/// `process(ctx)`, по желанию `order_event(ctx, event)`, атрибуты
+/// `timeframes` (список, по умолчанию все) и `history` (сколько
+/// последних баров видно в [`Context`], по умолчанию 200).
+///
+/// Стратегия на python только выставляет ордера, трейды из
+/// исполненных ордеров собирает обертка: трейд открывается первой
+/// сделкой и закрывается, когда позиция снова равна нулю. Ошибки
+/// python пишутся в лог, работа продолжается.
+///
+/// ```python
+/// class Breakout:
+///     name = "Breakout"
+///     timeframes = ["1M", "1H"]
+///
+///     def init(self, ctx):
+///         pass
+///
+///     def process(self, ctx):
+///         bars = ctx.bars("1H")
+///         if ctx.position == 0 and ctx.price() > bars[-2].high:
+///             ctx.buy(1)
+/// ```
+pub struct PyStrategy {
+    obj: Py<PyAny>,
+    name: &'static str,
+    timeframes: Vec<TimeFrame>,
+    history: usize,
+    ctx: Option<Py<Context>>,
+    trade: Option<Trade>,
+}
+impl PyStrategy {
+    pub fn new(py: Python<'_>, obj: Py<PyAny>) -> PyResult<Self> {
+        let bound = obj.bind(py);
+        let name: String = bound.getattr("name")?.extract()?;
+        // имя живет столько же, сколько стратегия - до конца работы
+        let name: &'static str = Box::leak(name.into_boxed_str());
+
+        let timeframes = match bound.getattr("timeframes") {
+            Ok(list) => {
+                let list: Vec<String> = list.extract()?;
+                let mut timeframes = vec![TimeFrame::M1];
+                for tf in list {
+                    let tf = timeframe(&tf)?;
+                    if !timeframes.contains(&tf) {
+                        timeframes.push(tf);
+                    }
+                }
+                timeframes
+            }
+            Err(_) => TimeFrame::all(),
+        };
+        let history = match bound.getattr("history") {
+            Ok(n) => n.extract()?,
+            Err(_) => DEFAULT_HISTORY,
+        };
+
+        Ok(Self {
+            obj,
+            name,
+            timeframes,
+            history,
+            ctx: None,
+            trade: None,
+        })
+    }
+
+    // private
+    fn call(&self, py: Python<'_>, method: &str, event: Option<OrderInfo>) {
+        let bound = self.obj.bind(py);
+        if !bound.hasattr(method).unwrap_or(false) {
+            return;
+        }
+        let ctx = self.ctx.as_ref().unwrap().clone_ref(py);
+        let result = match event {
+            Some(event) => bound.call_method1(method, (ctx, event)),
+            None => bound.call_method1(method, (ctx,)),
+        };
+        if let Err(e) = result {
+            log::error!("{}.{method}: {e}", self.name);
+        }
+    }
+    fn filled(&mut self, py: Python<'_>, order: Order) {
+        let quantity = order.operation().unwrap().quantity as i64;
+        let ctx = self.ctx.as_ref().unwrap();
+        let position = {
+            let mut ctx = ctx.borrow_mut(py);
+            match order.direction() {
+                Direction::Buy => ctx.position += quantity,
+                Direction::Sell => ctx.position -= quantity,
+            }
+            ctx.position
+        };
+
+        match self.trade.take() {
+            None => {
+                let ctx = ctx.borrow(py);
+                let kind = match order.direction() {
+                    Direction::Buy => TradeKind::Long,
+                    Direction::Sell => TradeKind::Short,
+                };
+                let ts = order.operation().unwrap().ts;
+                let trade = Trade::new(ts, self.name, kind, ctx.iid.clone());
+                self.trade = Some(Trade::Opened(trade.open(order)));
+            }
+            Some(Trade::Opened(mut trade)) => {
+                trade.add_order(order);
+                if position != 0 {
+                    self.trade = Some(Trade::Opened(trade));
+                    return;
+                }
+                let a = Action::TradeClosed(Trade::Closed(trade.close()));
+                ctx.borrow(py).trader.send(a).unwrap();
+            }
+            other => self.trade = other,
+        }
+    }
+    fn track(&self, py: Python<'_>, order: &Order) {
+        let mut ctx = self.ctx.as_ref().unwrap().borrow_mut(py);
+        match order {
+            Order::Stop(StopOrder::Triggered(o)) => {
+                ctx.orders.remove(o.stop_id());
+            }
+            _ if order.is_posted() || order.is_partially_filled() => {
+                if let Some(id) = order.broker_id() {
+                    ctx.orders.insert(id.clone(), order.clone());
+                }
+            }
+            _ => {
+                if let Some(id) = order.broker_id() {
+                    ctx.orders.remove(id);
+                }
+            }
+        }
+    }
+}
+impl Strategy for PyStrategy {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        self.timeframes.clone()
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        Python::attach(|py| {
+            let mut ctx = Context {
+                name: self.name,
+                trader,
+                account,
+                iid: asset.iid().clone(),
+                history: self.history,
+                charts: Vec::new(),
+                position: 0,
+                orders: HashMap::new(),
+            };
+            ctx.update(asset, &self.timeframes);
+            self.ctx = Some(Py::new(py, ctx).unwrap());
+            self.call(py, "init", None);
+        });
+    }
+    fn process(&mut self, asset: &Asset) {
+        Python::attach(|py| {
+            let ctx = self.ctx.as_ref().unwrap();
+            ctx.borrow_mut(py).update(asset, &self.timeframes);
+            self.call(py, "process", None);
+        });
+    }
+    fn order_event(&mut self, e: OrderEvent) {
+        Python::attach(|py| {
+            let order = e.order;
+            let info = OrderInfo::new(&order);
+
+            self.track(py, &order);
+            if !order.is_stop() && order.is_filled() {
+                self.filled(py, order);
+            }
+            self.call(py, "order_event", Some(info));
+        });
+    }
+}
+
+fn parse_direction(s: &str) -> PyResult<Direction> {
+    match s {
+        "buy" => Ok(Direction::Buy),
+        "sell" => Ok(Direction::Sell),
+        _ => Err(PyValueError::new_err(format!("invalid direction: {s}"))),
+    }
+}
+fn market_status(order: &MarketOrder) -> &'static str {
+    match order {
+        MarketOrder::New(_) => "new",
+        MarketOrder::Posted(_) => "posted",
+        MarketOrder::PartiallyFilled(_) => "partially_filled",
+        MarketOrder::Filled(_) => "filled",
+        MarketOrder::Rejected(_) => "rejected",
+    }
+}
+fn limit_status(order: &LimitOrder) -> &'static str {
+    match order {
+        LimitOrder::New(_) => "new",
+        LimitOrder::Posted(_) => "posted",
+        LimitOrder::PartiallyFilled(_) => "partially_filled",
+        LimitOrder::Filled(_) => "filled",
+        LimitOrder::Rejected(_) => "rejected",
+        LimitOrder::Canceled(_) => "canceled",
+    }
+}
+fn stop_status(order: &StopOrder) -> &'static str {
+    match order {
+        StopOrder::New(_) => "new",
+        StopOrder::Posted(_) => "posted",
+        StopOrder::Triggered(_) => "triggered",
+        StopOrder::Rejected(_) => "rejected",
+        StopOrder::Canceled(_) => "canceled",
+    }
+}
+fn limit_price(order: &LimitOrder) -> Option<f64> {
+    match order {
+        LimitOrder::New(o) => Some(o.price),
+        LimitOrder::Posted(o) => Some(o.price),
+        LimitOrder::PartiallyFilled(o) => Some(o.price),
+        _ => None,
+    }
+}
+fn stop_price(order: &StopOrder) -> Option<f64> {
+    match order {
+        StopOrder::New(o) => Some(o.stop_price),
+        StopOrder::Posted(o) => Some(o.stop_price),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use avin_core::fixture::iid;
+    use avin_core::{BarEvent, Transaction};
+
+    use super::*;
+
+    const TS: i64 = 1_700_000_040_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+
+    const CODE: &str = r#"
+class Up:
+    name = "Up"
+    timeframes = ["1M"]
+    history = 3
+
+    def __init__(self):
+        self.events = []
+
+    def process(self, ctx):
+        bars = ctx.bars("1M")
+        if ctx.position == 0 and len(bars) == 3:
+            ctx.buy(2)
+        elif ctx.position > 0 and bars[-1].close < bars[-2].close:
+            ctx.sell(2)
+
+    def order_event(self, ctx, event):
+        self.events.append(event.status)
+"#;
+
+    struct Run {
+        strategy: PyStrategy,
+        asset: Asset,
+        rx: UnboundedReceiver<Action>,
+    }
+    impl Run {
+        fn new(py: Python<'_>) -> Self {
+            let code = CString::new(CODE).unwrap();
+            let module =
+                PyModule::from_code(py, &code, c"up.py", c"up").unwrap();
+            let obj = module.getattr("Up").unwrap().call0().unwrap();
+            let mut strategy = PyStrategy::new(py, obj.unbind()).unwrap();
+
+            let mut asset = Asset::from_iid(iid());
+            asset.load_chart_empty(TimeFrame::M1);
+            let (tx, rx) = mpsc::unbounded_channel();
+            let account = Account::new("Test", "Test_ID");
+            strategy.init(tx, account, &mut asset);
+
+            Self {
+                strategy,
+                asset,
+                rx,
+            }
+        }
+        /// Бар n с закрытием price, исполняет рыночный ордер стратегии.
+        fn bar(&mut self, n: i64, price: f64) -> Option<Direction> {
+            let bar =
+                Bar::new(TS + n * MINUTE, price, price, price, price, 1);
+            let figi = iid().figi().clone();
+            self.asset
+                .bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+            self.strategy.process(&self.asset);
+
+            let Ok(Action::Post(a)) = self.rx.try_recv() else {
+                return None;
+            };
+            let Order::Market(MarketOrder::New(order)) = a.order else {
+                panic!();
+            };
+            let direction = order.direction.clone();
+
+            let mut order = order.post(&n.to_string());
+            let quantity = (order.lots * iid().lot()) as i32;
+            order.add_transaction(Transaction::new(quantity, price));
+            let order =
+                Order::Market(MarketOrder::Filled(order.fill(bar.ts, 0.0)));
+            let e = OrderEvent::new(a.account, a.iid, a.owner, order);
+            self.strategy.order_event(e);
+
+            Some(direction)
+        }
+    }
+
+    #[test]
+    fn python_strategy() {
+        Python::initialize();
+        Python::attach(|py| {
+            let mut run = Run::new(py);
+            assert_eq!(run.strategy.name(), "Up");
+            assert_eq!(run.strategy.timeframes(), vec![TimeFrame::M1]);
+
+            assert_eq!(run.bar(0, 100.0), None);
+            assert_eq!(run.bar(1, 101.0), None);
+            assert_eq!(run.bar(2, 102.0), Some(Direction::Buy));
+            assert_eq!(run.bar(3, 103.0), None);
+            assert_eq!(run.bar(4, 102.5), Some(Direction::Sell));
+
+            let Ok(Action::TradeClosed(Trade::Closed(trade))) =
+                run.rx.try_recv()
+            else {
+                panic!();
+            };
+            assert_eq!(trade.strategy, "Up");
+            assert_eq!(trade.result(), 10.0);
+
+            let obj = run.strategy.obj.bind(py);
+            let events: Vec<String> =
+                obj.getattr("events").unwrap().extract().unwrap();
+            assert_eq!(events, ["filled", "filled"]);
+        });
+    }
+}
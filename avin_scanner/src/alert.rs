@@ -0,0 +1,175 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use serde::Deserialize;
+use tokio::runtime::Handle;
+
+use avin_core::{Alert, AlertSinks, NotifyLevel};
+use avin_utils::{AlertSinkCfg, AvinError};
+
+use super::{ScanHit, ScanTransition};
+
+/// Alerts settings of scan file.
+///
+/// # ru
+/// Настройки оповещений в файле сканов: каналы доставки
+/// [`AlertSinkCfg`] (Telegram, вебхук, почта) и chart_url - шаблон
+/// ссылки на график в сообщении, подстановки {exchange}, {ticker},
+/// {tf}; пустой - без ссылки.
+///
+/// ```toml
+/// [alert]
+/// telegram_token = "123456:ABC..."
+/// telegram_chat = "123456789"
+/// webhook_url = "http://localhost:8080/scan"
+/// chart_url = "https://www.tradingview.com/chart/?symbol={exchange}:{ticker}"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlertCfg {
+    #[serde(flatten)]
+    pub sinks: AlertSinkCfg,
+    #[serde(default)]
+    pub chart_url: String,
+}
+
+/// Alerts of scan hits.
+///
+/// # ru
+/// Оповещения о срабатываниях сканов в каналы доставки: тикер, цена,
+/// скан с условием, московское время бара и ссылка на график.
+#[derive(Debug)]
+pub struct ScanAlert {
+    sinks: AlertSinks,
+    chart_url: String,
+}
+impl ScanAlert {
+    pub fn new(sinks: AlertSinks) -> Self {
+        Self {
+            sinks,
+            chart_url: String::new(),
+        }
+    }
+    /// Create from config, None if no sink is set.
+    ///
+    /// # ru
+    /// Создает по настройкам, None - если не задан ни один канал.
+    /// rt - рантайм tokio для отправки в каналы доставки.
+    pub fn from_cfg(
+        cfg: &AlertCfg,
+        rt: &Handle,
+    ) -> Result<Option<Self>, AvinError> {
+        let sinks = AlertSinks::from_cfg(&cfg.sinks, rt)?;
+        if sinks.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(sinks).chart_url(&cfg.chart_url)))
+    }
+    pub fn chart_url(mut self, template: &str) -> Self {
+        self.chart_url = template.to_string();
+        self
+    }
+    /// Alert of hit.
+    ///
+    /// # ru
+    /// Оповещение о срабатывании, важность - alert. Выход из условия
+    /// (Leave) - с важностью info и пометкой "left" у скана.
+    pub fn alert(&self, hit: &ScanHit) -> Alert {
+        let iid = &hit.iid;
+        let title = format!("{} {} {}", iid.ticker(), hit.price, hit.tf);
+        let (level, scan) = match hit.transition {
+            ScanTransition::Enter => (NotifyLevel::Alert, hit.scan.clone()),
+            ScanTransition::Leave => {
+                (NotifyLevel::Info, format!("{} left", hit.scan))
+            }
+        };
+        let mut text = format!("{scan}: {}\n{}", hit.condition, iid.name());
+
+        if !self.chart_url.is_empty() {
+            let url = self
+                .chart_url
+                .replace("{exchange}", iid.exchange())
+                .replace("{ticker}", iid.ticker())
+                .replace("{tf}", &hit.tf.to_string());
+            text.push('\n');
+            text.push_str(&url);
+        }
+
+        Alert::new(&hit.scan, level, &title, &text, hit.ts)
+    }
+    /// Send alert of hit.
+    ///
+    /// # ru
+    /// Отправляет оповещение, сканер не ждет доставки, ошибки только
+    /// пишутся в лог.
+    pub fn send(&self, hit: &ScanHit) {
+        self.sinks.send(&self.alert(hit));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::TimeFrame;
+    use avin_core::fixture::iid;
+
+    use super::*;
+    use crate::{Marker, MarkerColor, MarkerShape, MarkerSize};
+
+    #[tokio::test]
+    async fn alert() {
+        let hit = ScanHit {
+            scan: "volume_breakout".to_string(),
+            condition: "close > sma(20)".to_string(),
+            iid: iid(),
+            tf: TimeFrame::Day,
+            marker: Marker::new(
+                MarkerShape::Up,
+                MarkerColor::Green,
+                MarkerSize::Medium,
+            ),
+            transition: ScanTransition::Enter,
+            ts: 1_700_000_000_000_000_000,
+            price: 280.5,
+        };
+        let mut cfg = AlertCfg {
+            chart_url: "https://chart/{exchange}:{ticker}?tf={tf}"
+                .to_string(),
+            ..Default::default()
+        };
+        assert!(
+            ScanAlert::from_cfg(&cfg, &Handle::current())
+                .unwrap()
+                .is_none()
+        );
+        cfg.sinks.webhook_url = "http://localhost/hook".to_string();
+        let alert = ScanAlert::from_cfg(&cfg, &Handle::current())
+            .unwrap()
+            .unwrap();
+
+        let a = alert.alert(&hit);
+        assert_eq!(a.source, "volume_breakout");
+        assert_eq!(a.level, NotifyLevel::Alert);
+        assert_eq!(a.ts, hit.ts);
+        assert_eq!(a.title, format!("SBER 280.5 {}", hit.tf));
+        let lines: Vec<&str> = a.text.lines().collect();
+        assert_eq!(lines[0], "volume_breakout: close > sma(20)");
+        assert_eq!(lines[1], "Сбер");
+        assert_eq!(
+            lines[2],
+            format!("https://chart/MOEX:SBER?tf={}", hit.tf)
+        );
+
+        let leave = ScanHit {
+            transition: ScanTransition::Leave,
+            ..hit
+        };
+        let a = alert.alert(&leave);
+        assert_eq!(a.source, "volume_breakout");
+        assert_eq!(a.level, NotifyLevel::Info);
+        assert!(a.text.starts_with("volume_breakout left: close > sma(20)"));
+    }
+}
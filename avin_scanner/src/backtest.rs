@@ -0,0 +1,412 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use polars::prelude::{Column, DataFrame, df};
+use rayon::prelude::*;
+
+use avin_core::{Asset, BarSlice};
+use avin_utils::{self as utils, AvinError, CFG, MSK_OFFSET};
+
+use super::live::Scan;
+use super::{ScanConfig, ScanHit};
+
+/// Hit of scan on history with forward returns.
+///
+/// # ru
+/// Срабатывание скана на истории и доходности после него в процентах,
+/// по одной на каждый горизонт бэктеста. None - данных после
+/// срабатывания на горизонт не хватило.
+#[derive(Debug, Clone)]
+pub struct ScanSignal {
+    pub hit: ScanHit,
+    pub returns_p: Vec<Option<f64>>,
+}
+
+/// Forward returns statistics of scan.
+///
+/// # ru
+/// Статистика скана по горизонтам: число срабатываний с известной
+/// доходностью, средняя доходность в процентах и доля
+/// положительных.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanStats {
+    pub scan: String,
+    pub signals: usize,
+    pub horizons: Vec<usize>,
+    pub count: Vec<usize>,
+    pub mean_p: Vec<f64>,
+    pub win_rate: Vec<f64>,
+}
+impl std::fmt::Display for ScanStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ScanStats={} signals={}", self.scan, self.signals)?;
+        for i in 0..self.horizons.len() {
+            write!(
+                f,
+                " {}d: mean={:.2}% win={:.0}%",
+                self.horizons[i],
+                self.mean_p[i],
+                self.win_rate[i] * 100.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Backtest of scan signals on history.
+///
+/// # ru
+/// Бэктест сигналов скана: за исторический период отмечает, где
+/// условие сработало бы, и считает доходность после срабатывания за
+/// 1, 5 и 20 торговых дней (горизонты можно задать свои). Так до
+/// торговли по скану видно, есть ли у него предсказательная сила.
+///
+/// Бары графика добавляются по одному, как в живом сканере, и
+/// срабатывание - по фронту: пока условие выполняется, новых
+/// сигналов нет. Цена входа - закрытие бара срабатывания, выхода -
+/// закрытие последнего бара n-го торгового дня после дня
+/// срабатывания, торговые дни - дни с барами на графике скана.
+///
+/// ```ignore
+/// let bt = ScanBacktest::new(&file.scan)?;
+/// let signals = bt.run(&file.universe, begin, end);
+/// for stats in ScanBacktest::stats(&signals, bt.horizons()) {
+///     log::info!(":: {stats}");
+/// }
+/// ```
+pub struct ScanBacktest {
+    scans: Vec<Scan>,
+    horizons: Vec<usize>,
+}
+impl ScanBacktest {
+    pub fn new(scans: &[ScanConfig]) -> Result<Self, AvinError> {
+        Ok(Self {
+            scans: Scan::from_configs(scans)?,
+            horizons: vec![1, 5, 20],
+        })
+    }
+    /// Set horizons of forward returns in trading days.
+    ///
+    /// # ru
+    /// Горизонты доходности в торговых днях, по умолчанию 1, 5, 20.
+    pub fn with_horizons(mut self, days: &[usize]) -> Self {
+        self.horizons = days.to_vec();
+        self
+    }
+    pub fn horizons(&self) -> &[usize] {
+        &self.horizons
+    }
+    /// Backtest scans on instruments in period [begin, end).
+    ///
+    /// # ru
+    /// Бэктест сканов на инструментах universe (строки iid как в
+    /// конфиге) за период [begin, end). Графики загружаются с запасом
+    /// в CFG.core.default_bars_count баров до begin, чтобы функциям
+    /// условий хватало истории, и с запасом после end для
+    /// доходностей. Инструменты проверяются параллельно, без данных -
+    /// пропускаются с записью в лог. Сигналы - в порядке universe.
+    pub fn run(
+        &self,
+        universe: &[String],
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<ScanSignal> {
+        universe
+            .par_iter()
+            .map(|s| self.run_one(s, begin, end))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+    /// Backtest scans on loaded charts of asset.
+    ///
+    /// # ru
+    /// Бэктест сканов на уже загруженных графиках инструмента,
+    /// сигналы только с баров в [begin, end).
+    pub fn run_asset(
+        &self,
+        asset: &Asset,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<ScanSignal> {
+        let begin = begin.timestamp_nanos_opt().unwrap();
+        let end = end.timestamp_nanos_opt().unwrap();
+        let mut signals = Vec::new();
+
+        for tf in Scan::timeframes(&self.scans) {
+            let Some(chart) = asset.chart(tf) else {
+                continue;
+            };
+            let bars = chart.bars();
+            let days = DayCloses::new(bars);
+
+            // графики инструмента растут бар за баром, как в живом
+            // сканере
+            let mut replay = Asset::from_iid(asset.iid().clone());
+            replay.load_chart_empty(tf);
+            Scan::init(&self.scans, &mut replay);
+            let mut active = HashSet::new();
+
+            for bar in bars.iter() {
+                replay.chart_mut(tf).unwrap().add_bar(bar);
+
+                for (i, scan) in self.scans.iter().enumerate() {
+                    if scan.tf() != tf {
+                        continue;
+                    }
+                    let Some(hit) = scan.hit(&replay) else {
+                        active.remove(&i);
+                        continue;
+                    };
+                    if !active.insert(i) || hit.ts < begin || hit.ts >= end {
+                        continue;
+                    }
+
+                    let returns_p = self
+                        .horizons
+                        .iter()
+                        .map(|n| days.return_p(hit.ts, hit.price, *n))
+                        .collect();
+                    signals.push(ScanSignal { hit, returns_p });
+                }
+            }
+        }
+
+        signals
+    }
+    /// Statistics of signals by scan.
+    ///
+    /// # ru
+    /// Статистика сигналов по сканам, в порядке первого сигнала скана.
+    pub fn stats(
+        signals: &[ScanSignal],
+        horizons: &[usize],
+    ) -> Vec<ScanStats> {
+        let mut stats: Vec<ScanStats> = Vec::new();
+
+        for signal in signals.iter() {
+            let i = match stats.iter().position(|s| s.scan == signal.hit.scan)
+            {
+                Some(i) => i,
+                None => {
+                    stats.push(ScanStats {
+                        scan: signal.hit.scan.clone(),
+                        signals: 0,
+                        horizons: horizons.to_vec(),
+                        count: vec![0; horizons.len()],
+                        mean_p: vec![0.0; horizons.len()],
+                        win_rate: vec![0.0; horizons.len()],
+                    });
+                    stats.len() - 1
+                }
+            };
+
+            let s = &mut stats[i];
+            s.signals += 1;
+            for (j, r) in signal.returns_p.iter().enumerate() {
+                if let Some(r) = r {
+                    s.count[j] += 1;
+                    s.mean_p[j] += r;
+                    if *r > 0.0 {
+                        s.win_rate[j] += 1.0;
+                    }
+                }
+            }
+        }
+
+        for s in stats.iter_mut() {
+            for j in 0..horizons.len() {
+                if s.count[j] > 0 {
+                    s.mean_p[j] /= s.count[j] as f64;
+                    s.win_rate[j] /= s.count[j] as f64;
+                }
+            }
+        }
+
+        stats
+    }
+    /// Signals as dataframe.
+    ///
+    /// # ru
+    /// Таблица сигналов: колонки как у [`super::ScanHistory`] без
+    /// времени запуска и по колонке "return_<n>d" на горизонт.
+    pub fn df(signals: &[ScanSignal], horizons: &[usize]) -> DataFrame {
+        let s = signals;
+
+        let mut df = df!(
+            "scan" =>
+                s.iter().map(|i| i.hit.scan.clone()).collect::<Vec<_>>(),
+            "ticker" => s
+                .iter()
+                .map(|i| i.hit.iid.ticker().clone())
+                .collect::<Vec<_>>(),
+            "figi" => s
+                .iter()
+                .map(|i| i.hit.iid.figi().clone())
+                .collect::<Vec<_>>(),
+            "tf" =>
+                s.iter().map(|i| i.hit.tf.to_string()).collect::<Vec<_>>(),
+            "ts_nanos" => s.iter().map(|i| i.hit.ts).collect::<Vec<_>>(),
+            "price" => s.iter().map(|i| i.hit.price).collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        for (j, n) in horizons.iter().enumerate() {
+            let values: Vec<Option<f64>> =
+                s.iter().map(|i| i.returns_p[j]).collect();
+            let column = Column::new(format!("return_{n}d").into(), values);
+            df.with_column(column).unwrap();
+        }
+
+        df
+    }
+
+    // private
+    fn run_one(
+        &self,
+        s: &str,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<ScanSignal> {
+        let mut asset = match Asset::new(s) {
+            Ok(asset) => asset,
+            Err(e) => {
+                log::error!("ScanBacktest skip {s}: {e}");
+                return Vec::new();
+            }
+        };
+
+        // после end нужны бары на самый дальний горизонт, с запасом
+        // на выходные и праздники
+        let max = self.horizons.iter().max().copied().unwrap_or(0) as i64;
+        let till = end + TimeDelta::days(max * 2 + 10);
+        let count = CFG.core.default_bars_count as i32;
+        for tf in Scan::timeframes(&self.scans) {
+            let from = begin - TimeDelta::nanoseconds(tf.nanos()) * count;
+            if let Err(e) = asset.load_chart_period(tf, from, till) {
+                log::warn!("ScanBacktest {s} {tf}: {e}");
+            }
+        }
+
+        self.run_asset(&asset, begin, end)
+    }
+}
+
+/// Закрытия торговых дней графика, дни по московскому времени.
+struct DayCloses {
+    days: Vec<(NaiveDate, f64)>,
+}
+impl DayCloses {
+    fn new(bars: BarSlice) -> Self {
+        let mut days: Vec<(NaiveDate, f64)> = Vec::new();
+        for bar in bars.iter() {
+            let day = date(bar.ts);
+            match days.last_mut() {
+                Some(last) if last.0 == day => last.1 = bar.c,
+                _ => days.push((day, bar.c)),
+            }
+        }
+
+        Self { days }
+    }
+    /// Доходность в процентах от price до закрытия n-го торгового дня
+    /// после дня бара ts.
+    fn return_p(&self, ts: i64, price: f64, n: usize) -> Option<f64> {
+        let day = date(ts);
+        let i = self.days.binary_search_by(|d| d.0.cmp(&day)).ok()?;
+        let (_, close) = self.days.get(i + n)?;
+
+        Some((close / price - 1.0) * 100.0)
+    }
+}
+
+fn date(ts: i64) -> NaiveDate {
+    (utils::dt(ts) + MSK_OFFSET).date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarColumns, TimeFrame};
+
+    use super::*;
+    use crate::{Marker, MarkerColor, MarkerShape, MarkerSize};
+
+    #[test]
+    fn signals_and_returns() {
+        let scan = ScanConfig {
+            name: "above_105".to_string(),
+            tf: TimeFrame::Day,
+            filter: "close > 105".to_string(),
+            marker: Marker::new(
+                MarkerShape::Up,
+                MarkerColor::Green,
+                MarkerSize::Medium,
+            ),
+        };
+        let bt = ScanBacktest::new(&[scan]).unwrap().with_horizons(&[1, 2]);
+
+        let ts = 1_700_000_000_000_000_000;
+        let day = TimeFrame::Day.nanos();
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::Day);
+        let chart = asset.chart_mut(TimeFrame::Day).unwrap();
+        let prices = [100.0, 106.0, 110.0, 100.0, 110.0, 99.0, 107.0, 107.0];
+        for (i, c) in prices.iter().enumerate() {
+            let bar = Bar::new(ts + i as i64 * day, *c, *c, *c, *c, 1);
+            chart.add_bar(bar);
+        }
+
+        // сигнал на баре 0 не может быть, бар 6 вне периода
+        let begin = utils::dt(ts + day);
+        let end = utils::dt(ts + 6 * day);
+        let signals = bt.run_asset(&asset, begin, end);
+
+        // по фронту: бары 1 и 4, бар 2 продолжение условия
+        assert_eq!(signals.len(), 2);
+        assert_eq!(signals[0].hit.ts, ts + day);
+        assert_eq!(signals[0].hit.price, 106.0);
+        let r = signals[0].returns_p[0].unwrap();
+        assert!((r - (110.0 / 106.0 - 1.0) * 100.0).abs() < 1e-9);
+        let r = signals[0].returns_p[1].unwrap();
+        assert!((r - (100.0 / 106.0 - 1.0) * 100.0).abs() < 1e-9);
+        assert_eq!(signals[1].hit.ts, ts + 4 * day);
+        let r = signals[1].returns_p[0].unwrap();
+        assert!((r + 10.0).abs() < 1e-9);
+
+        let stats = ScanBacktest::stats(&signals, bt.horizons());
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].scan, "above_105");
+        assert_eq!(stats[0].signals, 2);
+        assert_eq!(stats[0].count, vec![2, 2]);
+        assert_eq!(stats[0].win_rate, vec![0.5, 0.0]);
+
+        let df = ScanBacktest::df(&signals, bt.horizons());
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.get_column_names().len(), 8);
+        assert!(df.column("return_2d").is_ok());
+    }
+
+    #[test]
+    fn returns_out_of_data() {
+        let ts = 1_700_000_000_000_000_000;
+        let day = TimeFrame::Day.nanos();
+        let bars: Vec<Bar> = (0..3)
+            .map(|i| Bar::new(ts + i * day, 1.0, 1.0, 1.0, 2.0, 1))
+            .collect();
+        let days = DayCloses::new(BarColumns::from_bars(&bars).view());
+
+        assert_eq!(days.return_p(ts, 1.0, 2), Some(100.0));
+        assert_eq!(days.return_p(ts, 1.0, 3), None);
+        assert_eq!(days.return_p(ts - 5 * day, 1.0, 1), None);
+    }
+}
@@ -0,0 +1,926 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use avin_core::{
+    Bar, BarSlice, Chart, ExtremumIndicator, ExtremumKind, Term, TimeFrame,
+    Trend,
+};
+use avin_utils::{AvinError, Cmd};
+
+use super::{AlertCfg, CronSchedule, Filter, Marker, ScanLibrary};
+
+/// Scan condition written as expression.
+///
+/// # ru
+/// Условие сканера, записанное выражением, а не кодом на Rust:
+///
+/// ```text
+/// close > sma(20) && volume > 2 * avg_volume(20) && trend(T3).is_bull()
+/// ```
+///
+/// Значения бара: `open high low close volume`, со сдвигом назад в
+/// скобках - `close(1)` это закрытие прошлого бара. Функции по
+/// последним n барам, включая текущий: `sma(n)` (или `ma(n)`) -
+/// средняя закрытий, `avg_volume(n)` - средний объем, `highest(n)` и
+/// `lowest(n)` - максимум и минимум цены, `atr(n)` - средний истинный
+/// диапазон. Индикаторы по всей истории графика: `ema(n)` -
+/// экспоненциальная средняя закрытий, `rsi(n)` - RSI Уайлдера от 0
+/// до 100.
+///
+/// Экстремумы термов T1..T5: `trend(T3).is_bull()` и `.is_bear()` -
+/// направление последнего тренда, `.len()` - длина в барах, `.vol()` -
+/// объем, `.abs_p()` - размах в процентах, `.speed_p()` - процентов
+/// за бар. `extr_max(T2)` и `extr_min(T2)` - цена последнего
+/// максимума и минимума терма. Арифметика `+ - * /`, сравнения
+/// `< <= > >= == !=`, логика `&& || !` и скобки. Имена не зависят от
+/// регистра, `RSI(14) < 30` то же что `rsi(14) < 30`; таймфрейм
+/// задает скан.
+///
+/// Выражение проверяется при разборе: неизвестное имя или сравнение
+/// условия с числом - ошибка сразу, а не на середине скана. Если на
+/// графике не хватает баров для функции или нет индикатора
+/// экстремумов, условие не выполнено.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    text: String,
+    node: Node,
+}
+impl Expr {
+    /// Parse expression, it must be condition.
+    ///
+    /// # ru
+    /// Разбирает выражение, результат должен быть условием, а не
+    /// числом.
+    pub fn parse(text: &str) -> Result<Self, AvinError> {
+        let err = |msg: String| {
+            AvinError::InvalidValue(format!("Scan expr '{text}': {msg}"))
+        };
+
+        let tokens = tokenize(text).map_err(err)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let node = parser.expr().map_err(err)?;
+        if let Some(token) = parser.peek() {
+            return Err(err(format!("unexpected '{token}'")));
+        }
+        if node.ty() != Ty::Bool {
+            return Err(err("expression is not condition".to_string()));
+        }
+
+        Ok(Self {
+            text: text.to_string(),
+            node,
+        })
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// Evaluate condition on current bar of chart.
+    ///
+    /// # ru
+    /// Проверяет условие на текущем баре графика. Не хватает данных -
+    /// false.
+    pub fn eval(&self, chart: &Chart) -> bool {
+        self.node.test(chart).unwrap_or(false)
+    }
+}
+
+/// Scanner filter from expression.
+///
+/// # ru
+/// Фильтр сканера из выражения, см. [`Expr`].
+#[derive(Debug, Clone)]
+pub struct ExprFilter {
+    name: String,
+    expr: Expr,
+}
+impl ExprFilter {
+    pub fn new(name: &str, expr: &str) -> Result<Self, AvinError> {
+        Ok(Self {
+            name: name.to_string(),
+            expr: Expr::parse(expr)?,
+        })
+    }
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
+impl Filter for ExprFilter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn apply(&self, chart: &Chart) -> bool {
+        self.expr.eval(chart)
+    }
+}
+
+/// Scan defined in config file.
+///
+/// # ru
+/// Скан из файла настроек: имя, таймфрейм, выражение фильтра и
+/// маркер. Так новый скан добавляется без перекомпиляции:
+///
+/// ```toml
+/// [[scan]]
+/// name = "volume_breakout"
+/// tf = "Day"
+/// filter = "close > highest(20) * 0.99 && volume > 2 * avg_volume(20)"
+/// marker = { shape = "Up", color = "Green", size = "Medium" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScanConfig {
+    pub name: String,
+    pub tf: TimeFrame,
+    pub filter: String,
+    pub marker: Marker,
+}
+impl ScanConfig {
+    /// Load scans from toml file.
+    ///
+    /// # ru
+    /// Загружает сканы из toml файла, выражения всех сканов сразу
+    /// проверяются.
+    pub fn load(path: &Path) -> Result<Vec<ScanConfig>, AvinError> {
+        Ok(ScanFile::load(path)?.scan)
+    }
+    pub fn filter(&self) -> Result<ExprFilter, AvinError> {
+        ExprFilter::new(&self.name, &self.filter)
+    }
+}
+
+/// File of scans with universe and optional schedule.
+///
+/// # ru
+/// Файл сканов: список инструментов для сканирования (строки iid как
+/// в конфиге), расписание запусков в формате cron для
+/// [`ScheduledScanner`](crate::ScheduledScanner), оповещения
+/// [`AlertCfg`], готовые сканы [`ScanLibrary`] по именам и свои
+/// сканы, см. [`ScanConfig`]. Готовые сканы при загрузке добавляются
+/// к своим. watchlists = true - запуски по расписанию пишут списки
+/// наблюдения сканов, см. [`Watchlists`](crate::Watchlists).
+///
+/// ```toml
+/// universe = ["moex_share_sber", "moex_share_gazp"]
+/// schedule = "0 19 * * 1-5"
+/// library = ["gap_up", "volume_spike"]
+/// watchlists = true
+///
+/// [alert]
+/// ...
+///
+/// [[scan]]
+/// ...
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScanFile {
+    #[serde(default)]
+    pub universe: Vec<String>,
+    #[serde(default)]
+    pub schedule: String,
+    #[serde(default)]
+    pub alert: AlertCfg,
+    #[serde(default)]
+    pub library: Vec<String>,
+    #[serde(default)]
+    pub watchlists: bool,
+    #[serde(default)]
+    pub scan: Vec<ScanConfig>,
+}
+impl ScanFile {
+    pub fn load(path: &Path) -> Result<Self, AvinError> {
+        let text = Cmd::read(path)?;
+        let mut file: ScanFile = toml::from_str(&text)
+            .map_err(|e| AvinError::InvalidValue(e.to_string()))?;
+        for name in file.library.iter() {
+            file.scan.push(ScanLibrary::get(name)?);
+        }
+        for scan in file.scan.iter() {
+            scan.filter()?;
+        }
+        if !file.schedule.is_empty() {
+            CronSchedule::parse(&file.schedule)?;
+        }
+
+        Ok(file)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ty {
+    Num,
+    Bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "open" => Some(Self::Open),
+            "high" => Some(Self::High),
+            "low" => Some(Self::Low),
+            "close" => Some(Self::Close),
+            "volume" => Some(Self::Volume),
+            _ => None,
+        }
+    }
+    fn value(&self, bar: &Bar) -> f64 {
+        match self {
+            Self::Open => bar.o,
+            Self::High => bar.h,
+            Self::Low => bar.l,
+            Self::Close => bar.c,
+            Self::Volume => bar.v as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Sma,
+    AvgVolume,
+    Highest,
+    Lowest,
+    Atr,
+    Ema,
+    Rsi,
+}
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sma" | "ma" => Some(Self::Sma),
+            "avg_volume" => Some(Self::AvgVolume),
+            "highest" => Some(Self::Highest),
+            "lowest" => Some(Self::Lowest),
+            "atr" => Some(Self::Atr),
+            "ema" => Some(Self::Ema),
+            "rsi" => Some(Self::Rsi),
+            _ => None,
+        }
+    }
+    fn value(&self, chart: &Chart, n: usize) -> Option<f64> {
+        let bars = last(chart, n)?;
+
+        let value = match self {
+            Self::Sma => bars.c.iter().sum::<f64>() / n as f64,
+            Self::AvgVolume => {
+                bars.v.iter().map(|v| *v as f64).sum::<f64>() / n as f64
+            }
+            Self::Highest => bars.h.iter().copied().fold(f64::MIN, f64::max),
+            Self::Lowest => bars.l.iter().copied().fold(f64::MAX, f64::min),
+            Self::Atr => {
+                // истинный диапазон считается от закрытия прошлого бара
+                let bars = last(chart, n + 1)?;
+                let sum: f64 = (1..bars.len())
+                    .map(|i| {
+                        let (prev, h, l) =
+                            (bars.c[i - 1], bars.h[i], bars.l[i]);
+                        (h - l).max((h - prev).abs()).max((l - prev).abs())
+                    })
+                    .sum();
+                sum / n as f64
+            }
+            Self::Ema => {
+                // начальное значение - средняя первых n закрытий
+                let c = chart.bars().c;
+                let k = 2.0 / (n + 1) as f64;
+                let sma = c[..n].iter().sum::<f64>() / n as f64;
+                c[n..].iter().fold(sma, |ema, c| ema + k * (c - ema))
+            }
+            Self::Rsi => {
+                // сглаживание Уайлдера, начальное - средние первых n
+                // изменений закрытия
+                let bars = chart.bars();
+                if bars.len() <= n {
+                    return None;
+                }
+                let (mut gain, mut loss) = (0.0, 0.0);
+                for (i, w) in bars.c.windows(2).enumerate() {
+                    let change = w[1] - w[0];
+                    let (g, l) = (change.max(0.0), (-change).max(0.0));
+                    if i < n {
+                        gain += g / n as f64;
+                        loss += l / n as f64;
+                    } else {
+                        gain = (gain * (n - 1) as f64 + g) / n as f64;
+                        loss = (loss * (n - 1) as f64 + l) / n as f64;
+                    }
+                }
+                if loss == 0.0 {
+                    if gain == 0.0 { 50.0 } else { 100.0 }
+                } else {
+                    100.0 - 100.0 / (1.0 + gain / loss)
+                }
+            }
+        };
+
+        Some(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TrendValue {
+    Len,
+    Vol,
+    AbsP,
+    SpeedP,
+}
+impl TrendValue {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "len" => Some(Self::Len),
+            "vol" => Some(Self::Vol),
+            "abs_p" => Some(Self::AbsP),
+            "speed_p" => Some(Self::SpeedP),
+            _ => None,
+        }
+    }
+    fn value(&self, trend: &Trend) -> f64 {
+        match self {
+            Self::Len => trend.len() as f64,
+            Self::Vol => trend.vol() as f64,
+            Self::AbsP => trend.abs_p(),
+            Self::SpeedP => trend.speed_p(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Num(f64),
+    Field(Field, usize),
+    Func(Func, usize),
+    Trend(Term, bool),
+    TrendValue(Term, TrendValue),
+    Extr(Term, ExtremumKind),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Bin(Op, Box<Node>, Box<Node>),
+}
+impl Node {
+    fn ty(&self) -> Ty {
+        match self {
+            Node::Num(_)
+            | Node::Field(..)
+            | Node::Func(..)
+            | Node::TrendValue(..)
+            | Node::Extr(..)
+            | Node::Neg(_) => Ty::Num,
+            Node::Trend(..) | Node::Not(_) => Ty::Bool,
+            Node::Bin(op, ..) => match op {
+                Op::Add | Op::Sub | Op::Mul | Op::Div => Ty::Num,
+                _ => Ty::Bool,
+            },
+        }
+    }
+    fn value(&self, chart: &Chart) -> Option<f64> {
+        match self {
+            Node::Num(n) => Some(*n),
+            Node::Field(field, n) => {
+                let bar = chart.bar(*n)?;
+                Some(field.value(&bar))
+            }
+            Node::Func(func, n) => func.value(chart, *n),
+            Node::TrendValue(term, value) => {
+                if !chart.has_extremum() {
+                    return None;
+                }
+                Some(value.value(chart.trend(*term, 0)?))
+            }
+            Node::Extr(term, kind) => {
+                if !chart.has_extremum() {
+                    return None;
+                }
+                let extr = chart
+                    .all_extr(*term)
+                    .iter()
+                    .rev()
+                    .find(|e| e.kind == *kind)?;
+                Some(extr.price)
+            }
+            Node::Neg(a) => Some(-a.value(chart)?),
+            Node::Bin(op, a, b) => {
+                let (a, b) = (a.value(chart)?, b.value(chart)?);
+                match op {
+                    Op::Add => Some(a + b),
+                    Op::Sub => Some(a - b),
+                    Op::Mul => Some(a * b),
+                    Op::Div if b == 0.0 => None,
+                    Op::Div => Some(a / b),
+                    _ => unreachable!(),
+                }
+            }
+            Node::Trend(..) | Node::Not(_) => unreachable!(),
+        }
+    }
+    fn test(&self, chart: &Chart) -> Option<bool> {
+        match self {
+            Node::Trend(term, bull) => {
+                if !chart.has_extremum() {
+                    return None;
+                }
+                let trend = chart.trend(*term, 0)?;
+                Some(trend.is_bull() == *bull)
+            }
+            Node::Not(a) => Some(!a.test(chart)?),
+            Node::Bin(Op::And, a, b) => {
+                match (a.test(chart), b.test(chart)) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }
+            }
+            Node::Bin(Op::Or, a, b) => match (a.test(chart), b.test(chart)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Node::Bin(op, a, b) => {
+                let (a, b) = (a.value(chart)?, b.value(chart)?);
+                let result = match op {
+                    Op::Lt => a < b,
+                    Op::Le => a <= b,
+                    Op::Gt => a > b,
+                    Op::Ge => a >= b,
+                    Op::Eq => a == b,
+                    Op::Ne => a != b,
+                    _ => unreachable!(),
+                };
+                Some(result)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Последние n баров графика, включая текущий.
+fn last(chart: &Chart, n: usize) -> Option<BarSlice<'_>> {
+    if n == 0 || chart.bars().len() < n {
+        return None;
+    }
+
+    Some(chart.last_n(n))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Sym(&'static str),
+}
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{n}"),
+            Token::Ident(s) => write!(f, "{s}"),
+            Token::Sym(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 17] = [
+    "&&", "||", "<=", ">=", "==", "!=", "<", ">", "!", "+", "-", "*", "/",
+    "(", ")", ",", ".",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let num = rest[..len]
+                .parse()
+                .map_err(|_| format!("invalid number '{}'", &rest[..len]))?;
+            tokens.push(Token::Num(num));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else {
+            let Some(sym) = SYMBOLS.iter().find(|s| rest.starts_with(**s))
+            else {
+                return Err(format!("unexpected '{c}'"));
+            };
+            tokens.push(Token::Sym(sym));
+            sym.len()
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Разбор с приоритетом: || ниже &&, ниже сравнений, ниже + -, ниже
+/// * /, ниже унарных ! и -.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+
+        token
+    }
+    fn eat(&mut self, sym: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Sym(s)) if *s == sym) {
+            self.pos += 1;
+            return true;
+        }
+
+        false
+    }
+    fn expect(&mut self, sym: &str) -> Result<(), String> {
+        if self.eat(sym) {
+            return Ok(());
+        }
+
+        match self.peek() {
+            Some(token) => Err(format!("expected '{sym}', found '{token}'")),
+            None => Err(format!("expected '{sym}' at end")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.eat("||") {
+            node = logic(Op::Or, node, self.and()?)?;
+        }
+
+        Ok(node)
+    }
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.cmp()?;
+        while self.eat("&&") {
+            node = logic(Op::And, node, self.cmp()?)?;
+        }
+
+        Ok(node)
+    }
+    fn cmp(&mut self) -> Result<Node, String> {
+        let node = self.sum()?;
+        for (sym, op) in [
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ] {
+            if self.eat(sym) {
+                return arith(op, node, self.sum()?);
+            }
+        }
+
+        Ok(node)
+    }
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            if self.eat("+") {
+                node = arith(Op::Add, node, self.product()?)?;
+            } else if self.eat("-") {
+                node = arith(Op::Sub, node, self.product()?)?;
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            if self.eat("*") {
+                node = arith(Op::Mul, node, self.unary()?)?;
+            } else if self.eat("/") {
+                node = arith(Op::Div, node, self.unary()?)?;
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat("!") {
+            let node = self.unary()?;
+            if node.ty() != Ty::Bool {
+                return Err("'!' needs condition".to_string());
+            }
+            return Ok(Node::Not(Box::new(node)));
+        }
+        if self.eat("-") {
+            let node = self.unary()?;
+            if node.ty() != Ty::Num {
+                return Err("'-' needs number".to_string());
+            }
+            return Ok(Node::Neg(Box::new(node)));
+        }
+
+        self.primary()
+    }
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Node::Num(n)),
+            Some(Token::Sym("(")) => {
+                let node = self.expr()?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Some(Token::Ident(name)) => self.ident(&name),
+            Some(token) => Err(format!("unexpected '{token}'")),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+    fn ident(&mut self, name: &str) -> Result<Node, String> {
+        let name = name.to_lowercase();
+        let name = name.as_str();
+
+        if let Some(field) = Field::from_name(name) {
+            let n = if self.eat("(") { self.count(true)? } else { 0 };
+            return Ok(Node::Field(field, n));
+        }
+        if let Some(func) = Func::from_name(name) {
+            self.expect("(")?;
+            return Ok(Node::Func(func, self.count(false)?));
+        }
+        if name == "trend" {
+            let term = self.term(name)?;
+            self.expect(".")?;
+            let method = match self.next() {
+                Some(Token::Ident(m)) => m.to_lowercase(),
+                _ => String::new(),
+            };
+            let node = match method.as_str() {
+                "is_bull" => Node::Trend(term, true),
+                "is_bear" => Node::Trend(term, false),
+                m => match TrendValue::from_name(m) {
+                    Some(value) => Node::TrendValue(term, value),
+                    None => {
+                        return Err(format!(
+                            "trend needs is_bull(), is_bear(), len(), \
+                            vol(), abs_p() or speed_p(), found '{m}'"
+                        ));
+                    }
+                },
+            };
+            self.expect("(")?;
+            self.expect(")")?;
+            return Ok(node);
+        }
+        if name == "extr_max" || name == "extr_min" {
+            let term = self.term(name)?;
+            let kind = if name == "extr_max" {
+                ExtremumKind::Max
+            } else {
+                ExtremumKind::Min
+            };
+            return Ok(Node::Extr(term, kind));
+        }
+
+        Err(format!("unknown name '{name}'"))
+    }
+    /// Терм экстремумов в скобках: `(T3)`.
+    fn term(&mut self, name: &str) -> Result<Term, String> {
+        self.expect("(")?;
+        let term = match self.next() {
+            Some(Token::Ident(t)) => term(&t.to_uppercase())?,
+            _ => return Err(format!("{name} needs term T1..T5")),
+        };
+        self.expect(")")?;
+
+        Ok(term)
+    }
+    /// Целое число и закрывающая скобка: сдвиг бара или период.
+    fn count(&mut self, zero: bool) -> Result<usize, String> {
+        let n = match self.next() {
+            Some(Token::Num(n)) if n.fract() == 0.0 && n >= 0.0 => n as usize,
+            _ => return Err("expected whole number in '()'".to_string()),
+        };
+        if n == 0 && !zero {
+            return Err("period must be positive".to_string());
+        }
+        self.expect(")")?;
+
+        Ok(n)
+    }
+}
+
+fn arith(op: Op, a: Node, b: Node) -> Result<Node, String> {
+    if a.ty() != Ty::Num || b.ty() != Ty::Num {
+        return Err(format!("{op:?} needs numbers"));
+    }
+
+    Ok(Node::Bin(op, Box::new(a), Box::new(b)))
+}
+fn logic(op: Op, a: Node, b: Node) -> Result<Node, String> {
+    if a.ty() != Ty::Bool || b.ty() != Ty::Bool {
+        return Err(format!("{op:?} needs conditions"));
+    }
+
+    Ok(Node::Bin(op, Box::new(a), Box::new(b)))
+}
+fn term(name: &str) -> Result<Term, String> {
+    match name {
+        "T1" => Ok(Term::T1),
+        "T2" => Ok(Term::T2),
+        "T3" => Ok(Term::T3),
+        "T4" => Ok(Term::T4),
+        "T5" => Ok(Term::T5),
+        _ => Err(format!("unknown term '{name}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+
+    use super::*;
+
+    fn chart(prices: &[(f64, u64)]) -> Chart {
+        let iid = iid();
+
+        let ts = 1_700_000_000_000_000_000;
+        let day = TimeFrame::Day.nanos();
+        let bars = prices
+            .iter()
+            .enumerate()
+            .map(|(i, (c, v))| {
+                Bar::new(ts + i as i64 * day, *c, c + 1.0, c - 1.0, *c, *v)
+            })
+            .collect();
+
+        Chart::new(&iid, TimeFrame::Day, bars)
+    }
+
+    #[test]
+    fn parse_errors() {
+        for text in [
+            "close",
+            "close > ",
+            "close > sma(0)",
+            "close > sma(2.5)",
+            "close && volume > 1",
+            "!close",
+            "trend(T9).is_bull()",
+            "trend(T1).is_up()",
+            "rsi(14)",
+            "extr_max(T9) > 1",
+            "extr_min > 1",
+            "trend(T1).speed() > 1",
+            "close > 1 )",
+            "close # 1",
+        ] {
+            assert!(Expr::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn eval() {
+        let c = chart(&[(100.0, 10), (102.0, 10), (104.0, 10), (110.0, 40)]);
+
+        let expr = |text: &str| Expr::parse(text).unwrap().eval(&c);
+        assert!(expr("close > sma(3)"));
+        assert!(expr("close == 110 && close(1) == 104"));
+        assert!(expr("volume > 2 * avg_volume(4)"));
+        assert!(expr("high - low == 2 && highest(4) == 111"));
+        assert!(expr("lowest(2) == 103 && -open < 0"));
+        assert!(expr("atr(2) == (105 - 103 + 111 - 103) / 2"));
+        assert!(expr("!(close < open) || volume / 0 > 1"));
+        assert!(!expr("close < sma(3) || close > 1000"));
+
+        // не хватает баров или индикатора - условие не выполнено
+        assert!(!expr("close > sma(5)"));
+        assert!(!expr("close(4) > 0"));
+        assert!(!expr("!(close > sma(5))"));
+        assert!(!expr("trend(T1).is_bull()"));
+        assert!(expr("trend(T1).is_bull() || close > 100"));
+    }
+
+    #[test]
+    fn indicators() {
+        let c = chart(&[(100.0, 1), (102.0, 1), (101.0, 1), (104.0, 1)]);
+
+        let expr = |text: &str| Expr::parse(text).unwrap().eval(&c);
+        // ema(2): (100 + 102) / 2 = 101, 101 + 2/3 * 0 = 101,
+        // 101 + 2/3 * 3 = 103
+        assert!(expr("ema(2) == 103 && ema(4) == sma(4)"));
+        assert!(expr("EMA(2) == 103 && MA(2) == Sma(2)"));
+        // rsi(2): прирост (2 + 0) / 2 = 1, падение (0 + 1) / 2 = 0.5,
+        // затем прирост (1 + 3) / 2 = 2, падение 0.5 / 2 = 0.25
+        assert!(expr("rsi(2) > 88.88 && rsi(2) < 88.89"));
+        assert!(expr("RSI(3) > 70"));
+        assert!(!expr("rsi(4) > 0"));
+
+        let up = chart(&[(100.0, 1), (101.0, 1), (102.0, 1)]);
+        assert!(Expr::parse("rsi(2) == 100").unwrap().eval(&up));
+        let flat = chart(&[(100.0, 1), (100.0, 1), (100.0, 1)]);
+        assert!(Expr::parse("rsi(2) == 50").unwrap().eval(&flat));
+
+        // без индикатора экстремумов значений тренда нет
+        assert!(!expr("trend(T1).len() > 0"));
+        assert!(!expr("extr_max(T1) > 0"));
+    }
+
+    #[test]
+    fn trend() {
+        let mut c = chart(&[
+            (100.0, 1),
+            (104.0, 1),
+            (108.0, 1),
+            (104.0, 1),
+            (100.0, 1),
+            (96.0, 1),
+        ]);
+        ExtremumIndicator::init(&mut c);
+
+        let filter =
+            ExprFilter::new("bear_t1", "trend(T1).is_bear()").unwrap();
+        assert_eq!(filter.name(), "bear_t1");
+        assert!(filter.apply(&c));
+        let expr = Expr::parse("trend(T1).is_bull()").unwrap();
+        assert!(!expr.eval(&c));
+
+        let expr = |text: &str| Expr::parse(text).unwrap().eval(&c);
+        let t = c.trend(Term::T1, 0).unwrap();
+        assert!(expr(&format!("trend(T1).len() == {}", t.len())));
+        assert!(expr(&format!("TREND(t1).abs_p() == {}", t.abs_p())));
+        assert!(expr(&format!("trend(T1).speed_p() == {}", t.speed_p())));
+        assert!(expr(&format!("trend(T1).vol() == {}", t.vol())));
+        assert!(expr("extr_max(T1) == 109"));
+        let min = c.all_extr(Term::T1).iter().rfind(|e| e.is_min());
+        assert!(expr(&format!("extr_min(T1) == {}", min.unwrap().price)));
+    }
+
+    #[test]
+    fn scan_config() {
+        let text = r#"
+            universe = ["moex_share_sber"]
+            schedule = "0 19 * * 1-5"
+            library = ["gap_up"]
+
+            [[scan]]
+            name = "volume_breakout"
+            tf = "Day"
+            filter = "close > sma(20) && volume > 2 * avg_volume(20)"
+            marker = { shape = "Up", color = "Green", size = "Medium" }
+        "#;
+        let path = std::env::temp_dir().join("avin_scan_config_test.toml");
+        std::fs::write(&path, text).unwrap();
+
+        let scans = ScanConfig::load(&path).unwrap();
+        assert_eq!(scans.len(), 2);
+        assert_eq!(scans[0].tf, TimeFrame::Day);
+        assert_eq!(scans[0].filter().unwrap().name(), "volume_breakout");
+        let file = ScanFile::load(&path).unwrap();
+        assert_eq!(file.universe, vec!["moex_share_sber".to_string()]);
+        assert_eq!(file.schedule, "0 19 * * 1-5");
+        assert_eq!(file.scan[1].name, "gap_up");
+
+        std::fs::write(&path, text.replace("sma(20)", "sma(")).unwrap();
+        assert!(ScanConfig::load(&path).is_err());
+        std::fs::write(&path, text.replace("1-5", "1-9")).unwrap();
+        assert!(ScanFile::load(&path).is_err());
+        std::fs::write(&path, text.replace("gap_up", "gap_sideways"))
+            .unwrap();
+        assert!(ScanFile::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
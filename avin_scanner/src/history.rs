@@ -0,0 +1,151 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::{Path, PathBuf};
+
+use polars::prelude::{DataFrame, df};
+
+use avin_utils::{AvinError, Cmd};
+
+use super::ScanHit;
+
+/// History of scan runs in parquet file.
+///
+/// # ru
+/// История запусков сканов в parquet файле: каждая строка -
+/// срабатывание со временем запуска, сканом, инструментом, временем и
+/// ценой бара. Запуски дописываются в конец, так по истории видно,
+/// как часто и на чем срабатывал скан.
+#[derive(Debug, Clone)]
+pub struct ScanHistory {
+    path: PathBuf,
+}
+impl ScanHistory {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Append hits of run at run_ts.
+    ///
+    /// # ru
+    /// Дописывает срабатывания запуска во время run_ts. Запуск без
+    /// срабатываний файл не меняет.
+    pub fn append(
+        &self,
+        run_ts: i64,
+        hits: &[ScanHit],
+    ) -> Result<(), AvinError> {
+        if hits.is_empty() {
+            return Ok(());
+        }
+
+        let mut df = Self::df(run_ts, hits);
+        if Cmd::is_exist(&self.path) {
+            let mut old = Cmd::read_pqt(&self.path)?;
+            old.vstack_mut(&df)
+                .map_err(|e| AvinError::InvalidValue(e.to_string()))?;
+            df = old;
+        }
+
+        Cmd::write_pqt(&mut df, &self.path)
+    }
+    /// Load all runs.
+    ///
+    /// # ru
+    /// Загружает всю историю запусков.
+    pub fn load(&self) -> Result<DataFrame, AvinError> {
+        if !Cmd::is_exist(&self.path) {
+            let path = self.path.display();
+            return Err(AvinError::NotFound(format!("{path}")));
+        }
+
+        Cmd::read_pqt(&self.path)
+    }
+    /// Hits of run as dataframe.
+    ///
+    /// # ru
+    /// Таблица срабатываний одного запуска.
+    pub fn df(run_ts: i64, hits: &[ScanHit]) -> DataFrame {
+        let h = hits;
+
+        df!(
+            "run_nanos" => vec![run_ts; h.len()],
+            "scan" => h.iter().map(|i| i.scan.clone()).collect::<Vec<_>>(),
+            "ticker" =>
+                h.iter().map(|i| i.iid.ticker().clone()).collect::<Vec<_>>(),
+            "figi" =>
+                h.iter().map(|i| i.iid.figi().clone()).collect::<Vec<_>>(),
+            "tf" => h.iter().map(|i| i.tf.to_string()).collect::<Vec<_>>(),
+            "ts_nanos" => h.iter().map(|i| i.ts).collect::<Vec<_>>(),
+            "price" => h.iter().map(|i| i.price).collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::TimeFrame;
+    use avin_core::fixture::iid;
+
+    use super::*;
+    use crate::{
+        Marker, MarkerColor, MarkerShape, MarkerSize, ScanTransition,
+    };
+
+    fn hit(scan: &str, ts: i64, price: f64) -> ScanHit {
+        ScanHit {
+            scan: scan.to_string(),
+            condition: "close > 105".to_string(),
+            iid: iid(),
+            tf: TimeFrame::Day,
+            marker: Marker::new(
+                MarkerShape::Up,
+                MarkerColor::Green,
+                MarkerSize::Medium,
+            ),
+            transition: ScanTransition::Enter,
+            ts,
+            price,
+        }
+    }
+
+    #[test]
+    fn append_and_load() {
+        let path = std::env::temp_dir().join("avin_scan_history_test.pqt");
+        let _ = std::fs::remove_file(&path);
+        let history = ScanHistory::new(&path);
+        assert!(history.load().is_err());
+
+        history.append(100, &[hit("a", 10, 280.0)]).unwrap();
+        history.append(200, &[]).unwrap();
+        history
+            .append(300, &[hit("a", 20, 281.0), hit("b", 20, 281.0)])
+            .unwrap();
+
+        let df = history.load().unwrap();
+        assert_eq!(df.height(), 3);
+        let runs: Vec<i64> = df
+            .column("run_nanos")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(runs, vec![100, 300, 300]);
+        let scan = df.column("scan").unwrap().str().unwrap();
+        assert_eq!(scan.get(2), Some("b"));
+        let tf = df.column("tf").unwrap().str().unwrap();
+        assert_eq!(tf.get(0), Some(TimeFrame::Day.to_string().as_str()));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -5,11 +5,29 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+mod alert;
+mod backtest;
 mod example;
+mod expr;
+mod history;
+mod library;
+mod live;
 mod scanner;
+mod schedule;
+mod universe;
+mod watchlist;
 
+pub use alert::{AlertCfg, ScanAlert};
+pub use backtest::{ScanBacktest, ScanSignal, ScanStats};
 pub use example::MyFilter;
+pub use expr::{Expr, ExprFilter, ScanConfig, ScanFile};
+pub use history::ScanHistory;
+pub use library::ScanLibrary;
+pub use live::{LiveScanner, ScanHit, ScanTransition};
 pub use scanner::{
     Filter, Marker, MarkerColor, MarkerShape, MarkerSize, Scanner,
     ScannerResult, ScannerResultList,
 };
+pub use schedule::{CronSchedule, ScheduledScanner};
+pub use universe::UniverseScanner;
+pub use watchlist::Watchlists;
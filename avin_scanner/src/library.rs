@@ -0,0 +1,170 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::TimeFrame;
+use avin_utils::AvinError;
+
+use super::{Marker, MarkerColor, MarkerShape, MarkerSize, ScanConfig};
+
+use MarkerColor::*;
+use MarkerShape::*;
+
+/// Имя, выражение, форма и цвет маркера сканов библиотеки, все на
+/// дневках.
+const LIBRARY: [(&str, &str, MarkerShape, MarkerColor); 9] = [
+    (
+        "volume_spike",
+        "volume > 3 * avg_volume(20)",
+        Asterisk,
+        Yellow,
+    ),
+    ("gap_up", "open > high(1)", Up, Green),
+    ("gap_down", "open < low(1)", Down, Red),
+    (
+        "new_high_52w",
+        "high >= highest(250) && high > high(1)",
+        Up,
+        Cyan,
+    ),
+    (
+        "new_low_52w",
+        "low <= lowest(250) && low < low(1)",
+        Down,
+        Violet,
+    ),
+    ("inside_day", "high < high(1) && low > low(1)", Square, Grey),
+    ("squeeze", "atr(10) < 0.5 * atr(100)", Diamond, Orange),
+    ("rsi_oversold", "rsi(14) < 30", Circle, Green),
+    ("rsi_overbought", "rsi(14) > 70", Circle, Red),
+];
+
+/// Ready-made scans.
+///
+/// # ru
+/// Готовые сканы, с ними сканер полезен сразу, без своих условий.
+/// Все на дневных барах:
+///
+/// - volume_spike - объем больше среднего за 20 дней в 3 раза;
+/// - gap_up, gap_down - открытие выше максимума (ниже минимума)
+///   прошлого дня;
+/// - new_high_52w, new_low_52w - новый максимум (минимум) за 250
+///   торговых дней;
+/// - inside_day - диапазон дня внутри диапазона прошлого дня;
+/// - squeeze - сжатие волатильности, ATR за 10 дней меньше половины
+///   ATR за 100;
+/// - rsi_oversold, rsi_overbought - RSI(14) ниже 30 (выше 70).
+///
+/// В файле сканов подключаются по имени, выражения можно взять
+/// за основу своих сканов:
+///
+/// ```toml
+/// library = ["gap_up", "volume_spike"]
+/// ```
+pub struct ScanLibrary;
+impl ScanLibrary {
+    /// Names of library scans.
+    ///
+    /// # ru
+    /// Имена сканов библиотеки.
+    pub fn names() -> Vec<&'static str> {
+        LIBRARY.iter().map(|(name, ..)| *name).collect()
+    }
+    /// All library scans.
+    ///
+    /// # ru
+    /// Все сканы библиотеки.
+    pub fn all() -> Vec<ScanConfig> {
+        LIBRARY.iter().map(Self::config).collect()
+    }
+    /// Library scan by name.
+    ///
+    /// # ru
+    /// Скан библиотеки по имени.
+    pub fn get(name: &str) -> Result<ScanConfig, AvinError> {
+        match LIBRARY.iter().find(|i| i.0 == name) {
+            Some(item) => Ok(Self::config(item)),
+            None => {
+                let names = Self::names().join(", ");
+                let msg = format!("library scan '{name}', expected {names}");
+                Err(AvinError::NotFound(msg))
+            }
+        }
+    }
+
+    // private
+    fn config(item: &(&str, &str, MarkerShape, MarkerColor)) -> ScanConfig {
+        let (name, filter, shape, color) = *item;
+
+        ScanConfig {
+            name: name.to_string(),
+            tf: TimeFrame::Day,
+            filter: filter.to_string(),
+            marker: Marker::new(shape, color, MarkerSize::Medium),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, Chart};
+
+    use super::*;
+    use crate::Filter;
+
+    fn chart(bars: &[(f64, f64, f64, f64, u64)]) -> Chart {
+        let ts = 1_700_000_000_000_000_000;
+        let day = TimeFrame::Day.nanos();
+        let bars = bars
+            .iter()
+            .enumerate()
+            .map(|(i, (o, h, l, c, v))| {
+                Bar::new(ts + i as i64 * day, *o, *h, *l, *c, *v)
+            })
+            .collect();
+
+        Chart::new(&iid(), TimeFrame::Day, bars)
+    }
+    fn apply(name: &str, chart: &Chart) -> bool {
+        ScanLibrary::get(name)
+            .unwrap()
+            .filter()
+            .unwrap()
+            .apply(chart)
+    }
+
+    #[test]
+    fn library() {
+        assert_eq!(ScanLibrary::all().len(), ScanLibrary::names().len());
+        for scan in ScanLibrary::all() {
+            assert!(scan.filter().is_ok(), "{}", scan.name);
+        }
+        assert!(ScanLibrary::get("moon").is_err());
+
+        let c = chart(&[
+            (100.0, 105.0, 95.0, 102.0, 10),
+            (106.0, 108.0, 104.0, 107.0, 10),
+        ]);
+        assert!(apply("gap_up", &c));
+        assert!(!apply("gap_down", &c));
+        assert!(!apply("inside_day", &c));
+
+        let c = chart(&[
+            (100.0, 105.0, 95.0, 102.0, 10),
+            (101.0, 104.0, 96.0, 99.0, 10),
+        ]);
+        assert!(apply("inside_day", &c));
+        assert!(!apply("gap_up", &c));
+
+        // на 20 барах объем 10, на последнем 100
+        let mut bars = vec![(100.0, 101.0, 99.0, 100.0, 10); 20];
+        bars.push((100.0, 101.0, 99.0, 100.0, 100));
+        assert!(apply("volume_spike", &chart(&bars)));
+        // данных меньше 250 дней - нового максимума года нет
+        assert!(!apply("new_high_52w", &chart(&bars)));
+    }
+}
@@ -0,0 +1,520 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::HashMap;
+
+use chrono::{TimeDelta, Utc};
+
+use avin_core::{
+    Action, Asset, Event, ExtremumIndicator, GetBarsAction, Iid, MarketData,
+    StreamAction, TimeFrame,
+};
+use avin_utils::{AvinError, CFG};
+
+use super::{ExprFilter, Filter, Marker, ScanAlert, ScanConfig};
+
+type ActionSender = tokio::sync::mpsc::UnboundedSender<Action>;
+type EventReceiver = tokio::sync::mpsc::UnboundedReceiver<Event>;
+type HitSender = tokio::sync::mpsc::UnboundedSender<ScanHit>;
+
+/// Condition of scan entered or left.
+///
+/// # ru
+/// Переход условия скана: Enter - условие стало выполняться, Leave -
+/// перестало. Сканы по расписанию и по вселенной дают только Enter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanTransition {
+    Enter,
+    Leave,
+}
+impl std::fmt::Display for ScanTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Enter => write!(f, "enter"),
+            Self::Leave => write!(f, "leave"),
+        }
+    }
+}
+
+/// Condition of scan became true on instrument.
+///
+/// # ru
+/// Срабатывание скана: условие стало выполняться на инструменте (или
+/// перестало, см. transition). condition - текст выражения фильтра
+/// скана. Время и цена - текущего бара графика скана в момент
+/// срабатывания.
+#[derive(Debug, Clone)]
+pub struct ScanHit {
+    pub scan: String,
+    pub condition: String,
+    pub iid: Iid,
+    pub tf: TimeFrame,
+    pub marker: Marker,
+    pub transition: ScanTransition,
+    pub ts: i64,
+    pub price: f64,
+}
+impl std::fmt::Display for ScanHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "ScanHit={} {} {} {} price={}",
+            self.scan,
+            self.iid.ticker(),
+            self.tf,
+            self.transition,
+            self.price
+        )
+    }
+}
+
+/// Scanner on live market data.
+///
+/// # ru
+/// Сканер на живых рыночных данных. Загружает историю графиков
+/// таймфреймов сканов для инструментов из списка, подписывается у
+/// брокера на бары 1М (и тики, если включены) и проверяет условия на
+/// каждом событии - на незакрытом баре тоже, поэтому срабатывание
+/// приходит через секунды после того, как условие стало выполняться.
+///
+/// Срабатывание - по фронту: когда условие стало выполняться,
+/// приходит Enter, пока выполняется - скан молчит, когда перестало -
+/// Leave. С cooldown повторный Enter того же скана на том же
+/// инструменте раньше, чем через cooldown от прошлого Enter (по
+/// времени баров), подавляется вместе со своим Leave - так условие,
+/// которое мигает на границе, не шлет оповещение каждую минуту.
+/// Условия, выполненные уже при запуске, не срабатывают.
+///
+/// ```ignore
+/// let file = ScanFile::load(&path)?;
+/// let mut scanner = LiveScanner::new(&file.scan)?
+///     .tics(true)
+///     .cooldown(TimeDelta::minutes(30))
+///     .alert(ScanAlert::from_cfg(&file.alert, &Handle::current())?);
+/// tokio::spawn(async move {
+///     scanner.start(&file.universe, broker_tx, event_rx, hit_tx).await
+/// });
+/// while let Some(hit) = hit_rx.recv().await {
+///     log::info!(":: {hit}");
+/// }
+/// ```
+pub struct LiveScanner {
+    scans: Vec<Scan>,
+    assets: HashMap<String, Asset>,
+    /// Состояние условий по скану и figi.
+    states: HashMap<(usize, String), ScanState>,
+    tics: bool,
+    cooldown: TimeDelta,
+    alert: Option<ScanAlert>,
+}
+impl LiveScanner {
+    pub fn new(scans: &[ScanConfig]) -> Result<Self, AvinError> {
+        Ok(Self {
+            scans: Scan::from_configs(scans)?,
+            assets: HashMap::new(),
+            states: HashMap::new(),
+            tics: false,
+            cooldown: TimeDelta::zero(),
+            alert: None,
+        })
+    }
+    /// Update real-time bars by tics too.
+    ///
+    /// # ru
+    /// Подписаться и на тики: текущие бары графиков обновляются каждой
+    /// сделкой, а не только обновлениями бара 1М от брокера.
+    pub fn tics(mut self, tics: bool) -> Self {
+        self.tics = tics;
+        self
+    }
+    /// Minimal time between Enter of scan on instrument.
+    ///
+    /// # ru
+    /// Минимальное время между Enter одного скана на одном
+    /// инструменте, по умолчанию ноль - без подавления.
+    pub fn cooldown(mut self, cooldown: TimeDelta) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+    /// Send alerts of hits.
+    ///
+    /// # ru
+    /// Отправлять оповещения о срабатываниях.
+    pub fn alert(mut self, alert: Option<ScanAlert>) -> Self {
+        self.alert = alert;
+        self
+    }
+    /// Timeframes of scans.
+    ///
+    /// # ru
+    /// Таймфреймы сканов без повторов, их графики нужны инструментам.
+    pub fn timeframes(&self) -> Vec<TimeFrame> {
+        Scan::timeframes(&self.scans)
+    }
+    /// Add asset with loaded charts of scans timeframes.
+    ///
+    /// # ru
+    /// Добавляет инструмент с загруженными графиками таймфреймов
+    /// сканов: подключает индикатор экстремумов и запоминает, какие
+    /// условия уже выполняются.
+    pub fn add(&mut self, mut asset: Asset) {
+        Scan::init(&self.scans, &mut asset);
+
+        let figi = asset.figi().clone();
+        for (i, scan) in self.scans.iter().enumerate() {
+            let state = ScanState {
+                inside: scan.apply(&asset),
+                ..Default::default()
+            };
+            self.states.insert((i, figi.clone()), state);
+        }
+        self.assets.insert(figi, asset);
+    }
+    /// Process market event, returns new hits.
+    ///
+    /// # ru
+    /// Обрабатывает рыночное событие и возвращает новые срабатывания,
+    /// Enter и Leave.
+    pub fn event(&mut self, e: Event) -> Vec<ScanHit> {
+        let figi = e.figi().clone();
+        let Some(asset) = self.assets.get_mut(&figi) else {
+            return Vec::new();
+        };
+
+        match e {
+            Event::Bar(e) if e.tf == TimeFrame::M1 => asset.bar_event(e),
+            Event::Tic(e) if self.tics => {
+                for scan in self.scans.iter() {
+                    if let Some(chart) = asset.chart_mut(scan.tf) {
+                        chart.add_tic(&e.tic);
+                    }
+                }
+            }
+            _ => return Vec::new(),
+        }
+
+        self.check(&figi)
+    }
+    /// Load history, subscribe and scan until events stream ends.
+    ///
+    /// # ru
+    /// Загружает у брокера историю графиков инструментов universe
+    /// (строки iid как в конфиге), подписывается на данные и сканирует,
+    /// пока не закроется поток событий. Срабатывания отправляются в
+    /// hits, пишутся в лог и, если заданы, в оповещения.
+    pub async fn start(
+        &mut self,
+        universe: &[String],
+        broker: ActionSender,
+        mut events: EventReceiver,
+        hits: HitSender,
+    ) {
+        log::info!(":: LiveScanner start, {} scans", self.scans.len());
+
+        for s in universe.iter() {
+            let asset = match Asset::new(s) {
+                Ok(asset) => asset,
+                Err(e) => {
+                    log::error!("LiveScanner skip {s}: {e}");
+                    continue;
+                }
+            };
+            let asset = self.load(asset, &broker).await;
+            let iid = asset.iid().clone();
+            self.add(asset);
+
+            let mut market_data = vec![MarketData::BAR_1M];
+            if self.tics {
+                market_data.push(MarketData::TIC);
+            }
+            let a = StreamAction::new(iid, market_data);
+            broker.send(Action::Subscribe(a)).unwrap();
+        }
+
+        while let Some(e) = events.recv().await {
+            for hit in self.event(e) {
+                log::info!(":: {hit}");
+                if let Some(alert) = &self.alert {
+                    alert.send(&hit);
+                }
+                if hits.send(hit).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    // private
+    async fn load(&self, mut asset: Asset, broker: &ActionSender) -> Asset {
+        let count = CFG.core.default_bars_count as i32;
+        let till = Utc::now();
+        for tf in self.timeframes() {
+            asset.load_chart_empty(tf);
+
+            let from = till - TimeDelta::nanoseconds(tf.nanos()) * count;
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let a =
+                GetBarsAction::new(asset.iid().clone(), tf, from, till, tx);
+            broker.send(Action::GetBars(a)).unwrap();
+
+            let Ok(bars) = rx.await else {
+                log::warn!("LiveScanner {} {tf} without history", asset);
+                continue;
+            };
+            let chart = asset.chart_mut(tf).unwrap();
+            for bar in bars {
+                chart.add_bar(bar);
+            }
+        }
+
+        asset
+    }
+    /// Проверяет сканы инструмента, срабатывание - по фронту.
+    fn check(&mut self, figi: &String) -> Vec<ScanHit> {
+        let asset = &self.assets[figi];
+        let mut hits = Vec::new();
+
+        for (i, scan) in self.scans.iter().enumerate() {
+            let state = self.states.entry((i, figi.clone())).or_default();
+            let inside = scan.apply(asset);
+            if inside == state.inside {
+                continue;
+            }
+            state.inside = inside;
+
+            let transition = if inside {
+                ScanTransition::Enter
+            } else {
+                ScanTransition::Leave
+            };
+            let Some(hit) = scan.make_hit(asset, transition) else {
+                continue;
+            };
+            if inside {
+                let cooldown = self.cooldown.num_nanoseconds().unwrap();
+                if state.last_enter.is_some_and(|ts| hit.ts - ts < cooldown) {
+                    continue;
+                }
+                state.last_enter = Some(hit.ts);
+                state.reported = true;
+                hits.push(hit);
+            } else if state.reported {
+                state.reported = false;
+                hits.push(hit);
+            }
+        }
+
+        hits
+    }
+}
+
+/// Состояние условия скана на инструменте: выполняется ли сейчас,
+/// был ли о входе Enter без Leave и время бара последнего Enter.
+#[derive(Debug, Default)]
+struct ScanState {
+    inside: bool,
+    reported: bool,
+    last_enter: Option<i64>,
+}
+
+/// Скан с разобранным фильтром, общий для живого сканера и сканов по
+/// расписанию.
+pub(crate) struct Scan {
+    filter: ExprFilter,
+    tf: TimeFrame,
+    marker: Marker,
+}
+impl Scan {
+    pub fn from_configs(
+        scans: &[ScanConfig],
+    ) -> Result<Vec<Scan>, AvinError> {
+        let mut parsed = Vec::new();
+        for scan in scans.iter() {
+            parsed.push(Scan {
+                filter: scan.filter()?,
+                tf: scan.tf,
+                marker: scan.marker,
+            });
+        }
+
+        Ok(parsed)
+    }
+    pub fn name(&self) -> &str {
+        self.filter.name()
+    }
+    pub fn tf(&self) -> TimeFrame {
+        self.tf
+    }
+    /// Таймфреймы сканов без повторов.
+    pub fn timeframes(scans: &[Scan]) -> Vec<TimeFrame> {
+        let mut timeframes = Vec::new();
+        for scan in scans.iter() {
+            if !timeframes.contains(&scan.tf) {
+                timeframes.push(scan.tf);
+            }
+        }
+
+        timeframes
+    }
+    /// Индикатор экстремумов на графики сканов, если его там нет.
+    pub fn init(scans: &[Scan], asset: &mut Asset) {
+        for tf in Self::timeframes(scans) {
+            if let Some(chart) = asset.chart_mut(tf)
+                && !chart.has_extremum()
+            {
+                ExtremumIndicator::init(chart);
+            }
+        }
+    }
+    /// Срабатывание, если условие выполняется на текущем баре.
+    pub fn hit(&self, asset: &Asset) -> Option<ScanHit> {
+        if !self.apply(asset) {
+            return None;
+        }
+
+        self.make_hit(asset, ScanTransition::Enter)
+    }
+    /// Выполняется ли условие на текущем баре.
+    pub fn apply(&self, asset: &Asset) -> bool {
+        asset.chart(self.tf).is_some_and(|c| self.filter.apply(c))
+    }
+    /// Срабатывание на текущем баре без проверки условия.
+    pub fn make_hit(
+        &self,
+        asset: &Asset,
+        transition: ScanTransition,
+    ) -> Option<ScanHit> {
+        let bar = asset.chart(self.tf)?.now()?;
+
+        Some(ScanHit {
+            scan: self.filter.name().to_string(),
+            condition: self.filter.expr().text().to_string(),
+            iid: asset.iid().clone(),
+            tf: self.tf,
+            marker: self.marker,
+            transition,
+            ts: bar.ts,
+            price: bar.c,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarEvent, Direction, Tic, TicEvent};
+
+    use super::*;
+    use crate::{MarkerColor, MarkerShape, MarkerSize};
+
+    // начало минуты
+    const TS: i64 = 1_699_999_980_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+
+    fn bar(n: i64, price: f64) -> Event {
+        let bar = Bar::new(TS + n * MINUTE, price, price, price, price, 1);
+
+        Event::Bar(BarEvent::new(iid().figi().clone(), TimeFrame::M1, bar))
+    }
+
+    #[test]
+    fn hits_on_edge() {
+        let scan = ScanConfig {
+            name: "above_105".to_string(),
+            tf: TimeFrame::M1,
+            filter: "close > 105".to_string(),
+            marker: Marker::new(
+                MarkerShape::Up,
+                MarkerColor::Green,
+                MarkerSize::Medium,
+            ),
+        };
+        let mut scanner = LiveScanner::new(&[scan]).unwrap().tics(true);
+        assert_eq!(scanner.timeframes(), vec![TimeFrame::M1]);
+
+        // условие выполнено уже при запуске - не срабатывает
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::M1);
+        asset.bar_event(match bar(0, 106.0) {
+            Event::Bar(e) => e,
+            _ => unreachable!(),
+        });
+        scanner.add(asset);
+        assert!(scanner.event(bar(1, 107.0)).is_empty());
+
+        assert!(scanner.event(bar(2, 100.0)).is_empty());
+        // срабатывание на незакрытом баре, пока он еще растет
+        let hits = scanner.event(bar(3, 106.0));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].scan, "above_105");
+        assert_eq!(hits[0].transition, ScanTransition::Enter);
+        assert_eq!(hits[0].condition, "close > 105");
+        assert_eq!(hits[0].ts, TS + 3 * MINUTE);
+        assert_eq!(hits[0].price, 106.0);
+        assert!(scanner.event(bar(3, 108.0)).is_empty());
+
+        // тик опускает текущий бар ниже условия, следующий - выше
+        let tic = |price: f64| {
+            let tic =
+                Tic::new(TS + 3 * MINUTE + 1, Direction::Sell, 1, price, 0.0);
+            Event::Tic(TicEvent::new(iid().figi().clone(), tic))
+        };
+        let hits = scanner.event(tic(104.0));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].transition, ScanTransition::Leave);
+        assert_eq!(hits[0].price, 104.0);
+        let hits = scanner.event(tic(106.0));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].transition, ScanTransition::Enter);
+
+        // события других инструментов не сканируются
+        let other = Event::Bar(BarEvent::new(
+            "other".to_string(),
+            TimeFrame::M1,
+            Bar::new(TS, 1.0, 1.0, 1.0, 1.0, 1),
+        ));
+        assert!(scanner.event(other).is_empty());
+    }
+
+    #[test]
+    fn cooldown() {
+        let scan = ScanConfig {
+            name: "above_105".to_string(),
+            tf: TimeFrame::M1,
+            filter: "close > 105".to_string(),
+            marker: Marker::new(
+                MarkerShape::Up,
+                MarkerColor::Green,
+                MarkerSize::Medium,
+            ),
+        };
+        let mut scanner = LiveScanner::new(&[scan])
+            .unwrap()
+            .cooldown(TimeDelta::minutes(5));
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::M1);
+        scanner.add(asset);
+
+        assert!(scanner.event(bar(0, 100.0)).is_empty());
+        assert_eq!(scanner.event(bar(1, 106.0)).len(), 1);
+        assert_eq!(scanner.event(bar(2, 100.0)).len(), 1);
+
+        // условие мигает чаще cooldown - ни Enter, ни Leave
+        assert!(scanner.event(bar(3, 106.0)).is_empty());
+        assert!(scanner.event(bar(4, 100.0)).is_empty());
+        assert!(scanner.event(bar(5, 106.0)).is_empty());
+        assert!(scanner.event(bar(6, 100.0)).is_empty());
+
+        // через 5 минут от прошлого Enter - снова срабатывает
+        let hits = scanner.event(bar(7, 106.0));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].transition, ScanTransition::Enter);
+        assert_eq!(hits[0].ts, TS + 7 * MINUTE);
+        let hits = scanner.event(bar(8, 100.0));
+        assert_eq!(hits[0].transition, ScanTransition::Leave);
+    }
+}
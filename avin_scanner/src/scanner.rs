@@ -15,7 +15,7 @@ use avin_core::{Chart, ExtremumIndicator, TimeFrame};
 use avin_utils::{AvinError, CFG, Cmd};
 
 pub trait Filter {
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
     fn apply(&self, chart: &Chart) -> bool;
 }
 
@@ -284,7 +284,7 @@ impl Scanner {
 
         // добавляем эти бары поштучно в пустой график и чекаем фильтр
         for bar in bars.iter() {
-            new_chart.add_bar(*bar);
+            new_chart.add_bar(bar);
 
             let result = filter.apply(&new_chart);
 
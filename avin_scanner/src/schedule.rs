@@ -0,0 +1,381 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{
+    DateTime, Datelike, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc,
+};
+use tokio::runtime::Handle;
+
+use avin_core::Asset;
+use avin_utils::{AvinError, MSK_OFFSET};
+
+use super::live::Scan;
+use super::{
+    ScanAlert, ScanFile, ScanHistory, ScanHit, UniverseScanner, Watchlists,
+};
+
+/// Cron-like schedule in Moscow time.
+///
+/// # ru
+/// Расписание в формате cron по московскому времени: пять полей -
+/// минута, час, день месяца, месяц, день недели (0 и 7 - воскресенье).
+/// Поле - "*", число, диапазон "a-b", шаг "*/n", "a-b/n" или "a/n"
+/// (от a до конца поля), или список этого через запятую. Имена
+/// месяцев и дней недели ("jan", "mon") и прочие расширения cron не
+/// поддерживаются - это ошибка разбора. Как в cron, если заданы и
+/// день месяца, и день недели (поле не начинается с "*"), подходит
+/// любой из них.
+///
+/// ```ignore
+/// // по будням в 19:00, после основной сессии
+/// let schedule = CronSchedule::parse("0 19 * * 1-5")?;
+/// let next = schedule.next(Utc::now()).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    text: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+impl CronSchedule {
+    pub fn parse(text: &str) -> Result<Self, AvinError> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        if fields.len() != 5 {
+            let msg = format!("cron '{text}': expected 5 fields");
+            return Err(AvinError::InvalidValue(msg));
+        }
+
+        let field = |i: usize, min: u32, max: u32| {
+            parse_field(fields[i], min, max).map_err(|e| {
+                AvinError::InvalidValue(format!("cron '{text}': {e}"))
+            })
+        };
+        let mut weekdays = field(4, 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+
+        Ok(Self {
+            text: text.to_string(),
+            minutes: field(0, 0, 59)?,
+            hours: field(1, 0, 23)?,
+            days: field(2, 1, 31)?,
+            months: field(3, 1, 12)?,
+            weekdays,
+            any_day: fields[2].starts_with('*'),
+            any_weekday: fields[4].starts_with('*'),
+        })
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// Next run time strictly after given time.
+    ///
+    /// # ru
+    /// Ближайшее время запуска строго после after, None - если за
+    /// несколько лет вперед подходящего времени нет (например,
+    /// 31 февраля).
+    pub fn next(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let msk = (after + MSK_OFFSET).naive_utc();
+        let start =
+            msk.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+
+        let mut day = start.date();
+        for _ in 0..366 * 5 {
+            if self.match_day(day) {
+                for h in (0..24).filter(|h| has(self.hours, *h)) {
+                    for m in (0..60).filter(|m| has(self.minutes, *m)) {
+                        let time = NaiveTime::from_hms_opt(h, m, 0)?;
+                        let dt = NaiveDateTime::new(day, time);
+                        if dt >= start {
+                            return Some(dt.and_utc() - MSK_OFFSET);
+                        }
+                    }
+                }
+            }
+            day = day.succ_opt()?;
+        }
+
+        None
+    }
+
+    // private
+    fn match_day(&self, day: chrono::NaiveDate) -> bool {
+        if !has(self.months, day.month()) {
+            return false;
+        }
+
+        let d = has(self.days, day.day());
+        let w = has(self.weekdays, day.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => w,
+            (false, true) => d,
+            (false, false) => d || w,
+        }
+    }
+}
+impl std::fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "CronSchedule={}", self.text)
+    }
+}
+
+/// Scans run by schedule with history of results.
+///
+/// # ru
+/// Сканы по расписанию: в назначенное время загружает графики
+/// инструментов из локальных данных, проверяет условия сканов на
+/// последнем баре и дописывает срабатывания в историю
+/// [`ScanHistory`].
+///
+/// ```ignore
+/// let file = ScanFile::load(&path)?;
+/// let history = CFG.dir.scan().join("history.parquet");
+/// let scanner = ScheduledScanner::new(&file, &history, &Handle::current())?;
+/// scanner.start().await;
+/// ```
+pub struct ScheduledScanner {
+    schedule: CronSchedule,
+    universe: Vec<String>,
+    scanner: UniverseScanner,
+    history: ScanHistory,
+    alert: Option<ScanAlert>,
+    watchlists: Option<Watchlists>,
+}
+impl ScheduledScanner {
+    pub fn new(
+        file: &ScanFile,
+        history: &Path,
+        rt: &Handle,
+    ) -> Result<Self, AvinError> {
+        if file.schedule.is_empty() {
+            let msg = "scan file without schedule".to_string();
+            return Err(AvinError::InvalidValue(msg));
+        }
+
+        Ok(Self {
+            schedule: CronSchedule::parse(&file.schedule)?,
+            universe: file.universe.clone(),
+            scanner: UniverseScanner::new(&file.scan)?,
+            history: ScanHistory::new(history),
+            alert: ScanAlert::from_cfg(&file.alert, rt)?,
+            watchlists: file.watchlists.then(Watchlists::default),
+        })
+    }
+    pub fn schedule(&self) -> &CronSchedule {
+        &self.schedule
+    }
+    pub fn history(&self) -> &ScanHistory {
+        &self.history
+    }
+    /// Write watchlists of scans on every run.
+    ///
+    /// # ru
+    /// Писать списки наблюдения сканов при каждом запуске, None - не
+    /// писать. По умолчанию - как задано в файле сканов, в папку
+    /// списков активов.
+    pub fn set_watchlists(&mut self, watchlists: Option<Watchlists>) {
+        self.watchlists = watchlists;
+    }
+    /// Check scans on last bar of asset charts.
+    ///
+    /// # ru
+    /// Проверяет условия сканов на последнем баре загруженных графиков
+    /// инструмента.
+    pub fn scan(&self, asset: &mut Asset) -> Vec<ScanHit> {
+        let scans = self.scanner.scans();
+        Scan::init(scans, asset);
+
+        scans.iter().filter_map(|scan| scan.hit(asset)).collect()
+    }
+    /// Run scans at given time and save hits to history.
+    ///
+    /// # ru
+    /// Запуск сканов: инструменты сканируются параллельно
+    /// [`UniverseScanner`] по барам до now, графики между запусками
+    /// остаются в кэше. Срабатывания дописываются в историю с временем
+    /// запуска now и, если включены, заменяют списки наблюдения сканов.
+    pub fn run(&self, now: DateTime<Utc>) -> Result<Vec<ScanHit>, AvinError> {
+        let hits = self.scanner.scan(&self.universe, now);
+
+        self.history
+            .append(now.timestamp_nanos_opt().unwrap(), &hits)?;
+        if let Some(watchlists) = &self.watchlists {
+            for scan in self.scanner.scans().iter() {
+                watchlists.write(scan.name(), &hits)?;
+            }
+        }
+
+        Ok(hits)
+    }
+    /// Run scans by schedule forever.
+    ///
+    /// # ru
+    /// Запускает сканы по расписанию, между запусками спит. Сам запуск
+    /// (чтение данных и расчет) блокирующий, он идет в
+    /// `spawn_blocking` и не занимает поток рантайма. Срабатывания
+    /// пишутся в лог и, если в файле сканов заданы оповещения, уходят
+    /// в их каналы.
+    pub async fn start(self) {
+        log::info!(":: ScheduledScanner start, {}", self.schedule);
+        let scanner = Arc::new(self);
+
+        loop {
+            let Some(next) = scanner.schedule.next(Utc::now()) else {
+                log::error!("ScheduledScanner: no next run, stop");
+                return;
+            };
+            log::info!(":: ScheduledScanner next run {next}");
+            let wait = (next - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            let s = scanner.clone();
+            let run = tokio::task::spawn_blocking(move || s.run(Utc::now()));
+            match run.await {
+                Ok(Ok(hits)) => {
+                    for hit in hits.iter() {
+                        log::info!(":: {hit}");
+                        if let Some(alert) = &scanner.alert {
+                            alert.send(hit);
+                        }
+                    }
+                }
+                Ok(Err(e)) => log::error!("ScheduledScanner: {e}"),
+                Err(e) => log::error!("ScheduledScanner: run failed, {e}"),
+            }
+        }
+    }
+}
+
+fn has(mask: u64, n: u32) -> bool {
+    mask & (1 << n) != 0
+}
+fn parse_field(text: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0;
+
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 =
+                    step.parse().map_err(|_| format!("step '{part}'"))?;
+                if step == 0 {
+                    return Err(format!("step '{part}'"));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let number = |s: &str| -> Result<u32, String> {
+            match s.parse::<u32>() {
+                Ok(n) if (min..=max).contains(&n) => Ok(n),
+                _ => Err(format!("value '{s}' not in {min}-{max}")),
+            }
+        };
+        let (from, till) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (number(a)?, number(b)?)
+        } else if part.contains('/') {
+            // "a/n" - как в cron, от a до конца поля
+            (number(range)?, max)
+        } else {
+            let n = number(range)?;
+            (n, n)
+        };
+        if from > till {
+            return Err(format!("range '{range}'"));
+        }
+
+        for n in (from..=till).step_by(step as usize) {
+            mask |= 1 << n;
+        }
+    }
+
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn parse() {
+        assert!(CronSchedule::parse("0 19 * * 1-5").is_ok());
+        assert!(CronSchedule::parse("*/15 10-18 * * *").is_ok());
+        assert!(CronSchedule::parse("0 19 1,15 * 7").is_ok());
+        assert!(CronSchedule::parse("5/20 9-12,15-18/3 * 1-6,9 1-5").is_ok());
+
+        assert!(CronSchedule::parse("0 19 * *").is_err());
+        assert!(CronSchedule::parse("60 19 * * *").is_err());
+        assert!(CronSchedule::parse("0 19 * * 8").is_err());
+        assert!(CronSchedule::parse("0 19 5-1 * *").is_err());
+        assert!(CronSchedule::parse("*/0 19 * * *").is_err());
+        assert!(CronSchedule::parse("a 19 * * *").is_err());
+        assert!(CronSchedule::parse("0 19 * jan mon").is_err());
+        assert!(CronSchedule::parse("0 19 1,,2 * *").is_err());
+        assert!(CronSchedule::parse("0 19 * * 1-").is_err());
+        assert!(CronSchedule::parse("0 19 * * ?").is_err());
+        assert!(CronSchedule::parse("0/x 19 * * *").is_err());
+    }
+    #[test]
+    fn fields() {
+        assert_eq!(parse_field("*", 0, 5), Ok(0b111111));
+        assert_eq!(parse_field("1-3", 0, 5), Ok(0b1110));
+        assert_eq!(parse_field("0,2,5", 0, 5), Ok(0b100101));
+        assert_eq!(parse_field("*/2", 0, 5), Ok(0b10101));
+        assert_eq!(parse_field("1-5/2", 0, 5), Ok(0b101010));
+        assert_eq!(parse_field("3/2", 0, 7), Ok(0b10101000));
+        assert_eq!(parse_field("0-1,4/3", 0, 7), Ok(0b10010011));
+    }
+
+    #[test]
+    fn next() {
+        // время в utc, расписание по москве (utc+3)
+        let s = CronSchedule::parse("0 19 * * 1-5").unwrap();
+        // пятница 2025-01-10 15:00 utc = 18:00 мск
+        let t = dt("2025-01-10 15:00:00");
+        assert_eq!(s.next(t), Some(dt("2025-01-10 16:00:00")));
+        // ровно в момент запуска - следующий, через выходные
+        let t = dt("2025-01-10 16:00:00");
+        assert_eq!(s.next(t), Some(dt("2025-01-13 16:00:00")));
+
+        let s = CronSchedule::parse("*/15 * * * *").unwrap();
+        let t = dt("2025-01-10 15:07:30");
+        assert_eq!(s.next(t), Some(dt("2025-01-10 15:15:00")));
+
+        // день месяца или воскресенье
+        let s = CronSchedule::parse("30 10 20 * 0").unwrap();
+        let t = dt("2025-01-10 15:00:00");
+        assert_eq!(s.next(t), Some(dt("2025-01-12 07:30:00")));
+        let t = dt("2025-01-19 08:00:00");
+        assert_eq!(s.next(t), Some(dt("2025-01-20 07:30:00")));
+
+        // шаг в дне месяца не отменяет дня недели: "*/2" как "*"
+        let s = CronSchedule::parse("0 10 */2 * 1").unwrap();
+        let t = dt("2025-01-10 15:00:00");
+        assert_eq!(s.next(t), Some(dt("2025-01-13 07:00:00")));
+
+        let s = CronSchedule::parse("0 0 31 2 *").unwrap();
+        assert_eq!(s.next(t), None);
+    }
+}
@@ -0,0 +1,153 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use rayon::prelude::*;
+
+use avin_core::{Asset, Bar, Chart, Manager, TimeFrame};
+use avin_utils::{self as utils, AvinError, CFG};
+
+use super::live::Scan;
+use super::{ScanConfig, ScanHit};
+
+/// Parallel scan of many instruments with cache of charts.
+///
+/// # ru
+/// Параллельный скан многих инструментов, например всех акций MOEX.
+/// Загрузка графиков и проверка условий идут в пуле потоков rayon,
+/// каждый инструмент - отдельная задача. Загруженные графики
+/// остаются в общем кэше, и следующий скан догружает только новые
+/// бары, поэтому повторный скан всей вселенной занимает секунды.
+///
+/// ```ignore
+/// let scanner = UniverseScanner::new(&file.scan)?;
+/// let hits = scanner.scan(&file.universe, Utc::now());
+/// ```
+pub struct UniverseScanner {
+    scans: Vec<Scan>,
+    /// Инструменты с графиками по строке iid из universe.
+    cache: Mutex<HashMap<String, Asset>>,
+}
+impl UniverseScanner {
+    pub fn new(scans: &[ScanConfig]) -> Result<Self, AvinError> {
+        Ok(Self::from_scans(Scan::from_configs(scans)?))
+    }
+    /// Scan instruments on bars until now.
+    ///
+    /// # ru
+    /// Проверяет сканы на инструментах universe (строки iid как в
+    /// конфиге) по барам до now. Первый раз графики загружаются по
+    /// CFG.core.default_bars_count баров, потом из кэша с догрузкой
+    /// новых баров. Инструмент без данных пропускается с записью в
+    /// лог. Срабатывания - в порядке universe.
+    pub fn scan(
+        &self,
+        universe: &[String],
+        now: DateTime<Utc>,
+    ) -> Vec<ScanHit> {
+        universe
+            .par_iter()
+            .map(|s| self.scan_one(s, now))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+    /// Count of instruments in cache.
+    ///
+    /// # ru
+    /// Количество инструментов в кэше графиков.
+    pub fn cached(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+    /// Clear cache of charts.
+    ///
+    /// # ru
+    /// Очищает кэш, следующий скан загрузит графики заново.
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    // private
+    pub(crate) fn scans(&self) -> &[Scan] {
+        &self.scans
+    }
+    pub(crate) fn from_scans(scans: Vec<Scan>) -> Self {
+        Self {
+            scans,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+    fn scan_one(&self, s: &str, now: DateTime<Utc>) -> Vec<ScanHit> {
+        // инструмент забирается из кэша на время скана, так потоки
+        // не ждут друг друга на загрузке данных
+        let cached = self.cache.lock().unwrap().remove(s);
+        let mut asset = match cached {
+            Some(asset) => asset,
+            None => match Asset::new(s) {
+                Ok(asset) => asset,
+                Err(e) => {
+                    log::error!("UniverseScanner skip {s}: {e}");
+                    return Vec::new();
+                }
+            },
+        };
+
+        for tf in Scan::timeframes(&self.scans) {
+            if let Err(e) = update(&mut asset, tf, now) {
+                log::warn!("UniverseScanner {s} {tf}: {e}");
+            }
+        }
+        Scan::init(&self.scans, &mut asset);
+        let hits = self
+            .scans
+            .iter()
+            .filter_map(|scan| scan.hit(&asset))
+            .collect();
+
+        self.cache.lock().unwrap().insert(s.to_string(), asset);
+
+        hits
+    }
+}
+
+/// Загружает график или догружает в него бары с последнего.
+fn update(
+    asset: &mut Asset,
+    tf: TimeFrame,
+    now: DateTime<Utc>,
+) -> Result<(), AvinError> {
+    let last = asset.chart(tf).and_then(|c| c.now()).map(|b| b.ts);
+    let begin = match last {
+        Some(ts) => utils::dt(ts),
+        None => {
+            let count = CFG.core.default_bars_count as i32;
+            now - TimeDelta::nanoseconds(tf.nanos()) * count
+        }
+    };
+
+    let df = Manager::load(asset.iid(), tf.market_data(), begin, now)?;
+    let bars = Bar::from_df(&df).map_err(AvinError::InvalidValue)?;
+
+    match (last, asset.chart_mut(tf)) {
+        (Some(ts), Some(chart)) => {
+            for bar in bars.into_iter().filter(|b| b.ts >= ts) {
+                chart.add_bar(bar);
+            }
+        }
+        _ => {
+            let chart = Chart::new(asset.iid(), tf, bars);
+            asset.load_chart_empty(tf);
+            *asset.chart_mut(tf).unwrap() = chart;
+        }
+    }
+
+    Ok(())
+}
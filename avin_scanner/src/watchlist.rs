@@ -0,0 +1,117 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::{Path, PathBuf};
+
+use avin_core::{Asset, AssetList};
+use avin_utils::{AvinError, CFG};
+
+use super::ScanHit;
+
+/// Watchlists of scans.
+///
+/// # ru
+/// Списки наблюдения сканов: каждый скан пишет инструменты, на
+/// которых сработал, в свой список активов "scan_<имя скана>.csv".
+/// Это обычный [`AssetList`] в папке списков активов, поэтому его
+/// открывает панель активов GUI терминала, а трейдер запускает на
+/// нем стратегии через trader.watch_list - так скан становится
+/// списком наблюдения, а затем торговли.
+#[derive(Debug, Clone)]
+pub struct Watchlists {
+    dir: PathBuf,
+}
+impl Watchlists {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+    /// Name of watchlist of scan.
+    ///
+    /// # ru
+    /// Имя списка наблюдения скана.
+    pub fn name(scan: &str) -> String {
+        format!("scan_{scan}")
+    }
+    /// Path of watchlist of scan.
+    ///
+    /// # ru
+    /// Путь файла списка наблюдения скана.
+    pub fn path(&self, scan: &str) -> PathBuf {
+        self.dir.join(format!("{}.csv", Self::name(scan)))
+    }
+    /// Replace watchlist of scan by instruments of its hits.
+    ///
+    /// # ru
+    /// Заменяет список наблюдения скана инструментами его
+    /// срабатываний, срабатывания других сканов пропускаются. Без
+    /// срабатываний список становится пустым, старые инструменты в нем
+    /// не остаются.
+    pub fn write(
+        &self,
+        scan: &str,
+        hits: &[ScanHit],
+    ) -> Result<AssetList, AvinError> {
+        let mut list = AssetList::new(Self::name(scan));
+        for hit in hits.iter().filter(|i| i.scan == scan) {
+            list.add(Asset::from_iid(hit.iid.clone()));
+        }
+        list.save(&self.path(scan))?;
+
+        Ok(list)
+    }
+}
+impl Default for Watchlists {
+    fn default() -> Self {
+        Self::new(&CFG.dir.asset())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::TimeFrame;
+    use avin_core::fixture::iid;
+
+    use super::*;
+    use crate::{
+        Marker, MarkerColor, MarkerShape, MarkerSize, ScanTransition,
+    };
+
+    #[test]
+    fn write() {
+        let hit = |scan: &str| ScanHit {
+            scan: scan.to_string(),
+            condition: "open > high(1)".to_string(),
+            iid: iid(),
+            tf: TimeFrame::Day,
+            marker: Marker::new(
+                MarkerShape::Up,
+                MarkerColor::Green,
+                MarkerSize::Medium,
+            ),
+            transition: ScanTransition::Enter,
+            ts: 0,
+            price: 280.0,
+        };
+        let dir = std::env::temp_dir().join("avin_watchlist_test");
+        let lists = Watchlists::new(&dir);
+        let path = lists.path("gap_up");
+        assert_eq!(path, dir.join("scan_gap_up.csv"));
+
+        let hits = [hit("gap_up"), hit("gap_up"), hit("inside_day")];
+        let list = lists.write("gap_up", &hits).unwrap();
+        assert_eq!(list.name(), "scan_gap_up");
+        assert_eq!(list.len(), 1);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "MOEX;SHARE;SBER;\n");
+
+        lists.write("gap_up", &[]).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
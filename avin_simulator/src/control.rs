@@ -0,0 +1,234 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+use avin_core::{Direction, OrderAction};
+use avin_utils::{AvinError, MSK_OFFSET};
+
+use crate::Speed;
+
+/// Control command of running simulation.
+///
+/// # ru
+/// Команда управления запущенной симуляцией: воспроизведением
+/// [`crate::ReplayBroker`] или симулятором GUI [`crate::Simulator`].
+#[derive(Debug)]
+pub enum Command {
+    Pause,
+    Resume,
+    Speed(Speed),
+    /// Перемотка вперед: события до этого момента отдаются сразу.
+    Jump(DateTime<Utc>),
+    /// Ордер от имени управления, например из интеграционного теста.
+    Post(Box<OrderAction>),
+    /// Рыночный (без цены) или лимитный ордер по инструменту
+    /// воспроизведения, для текстовых команд.
+    Order {
+        direction: Direction,
+        lots: u32,
+        price: Option<f64>,
+    },
+    /// Сохранить сессию в файл, см. [`crate::Session`].
+    Save(PathBuf),
+    Status(oneshot::Sender<ControlStatus>),
+}
+impl TryFrom<&str> for Command {
+    type Error = AvinError;
+
+    /// Parse text command, time is Moscow.
+    ///
+    /// # ru
+    /// Создает команду из текста, например из stdin:
+    /// "pause", "resume", "speed 10x", "speed max",
+    /// "jump 2024-03-01 10:00" (московское время), "buy 10",
+    /// "sell 10 280.5" (лимитный ордер), "save session.bin". Команда
+    /// статуса текстом не создается - ей нужен канал ответа, см.
+    /// [`Control::status`].
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let err = || AvinError::InvalidValue(format!("command {value}"));
+        let parts: Vec<&str> = value.split_whitespace().collect();
+
+        let command = match parts.as_slice() {
+            ["pause"] => Self::Pause,
+            ["resume"] => Self::Resume,
+            ["speed", s] => {
+                let speed = Speed::all()
+                    .into_iter()
+                    .find(|i| i.to_string() == *s)
+                    .ok_or_else(err)?;
+                Self::Speed(speed)
+            }
+            ["jump", date, time] => {
+                let dt = format!("{date} {time}");
+                let dt = NaiveDateTime::parse_from_str(&dt, "%Y-%m-%d %H:%M")
+                    .map_err(|_| err())?;
+                Self::Jump((dt - MSK_OFFSET).and_utc())
+            }
+            [side @ ("buy" | "sell"), lots, price @ ..]
+                if price.len() < 2 =>
+            {
+                let direction = match *side {
+                    "buy" => Direction::Buy,
+                    _ => Direction::Sell,
+                };
+                let lots = lots.parse().map_err(|_| err())?;
+                let price = match price.first() {
+                    Some(p) => Some(p.parse().map_err(|_| err())?),
+                    None => None,
+                };
+                Self::Order {
+                    direction,
+                    lots,
+                    price,
+                }
+            }
+            ["save", path] => Self::Save(PathBuf::from(path)),
+            _ => return Err(err()),
+        };
+
+        Ok(command)
+    }
+}
+
+/// State of running simulation.
+///
+/// # ru
+/// Состояние симуляции в ответ на [`Command::Status`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlStatus {
+    /// Время рынка.
+    pub dt: DateTime<Utc>,
+    pub speed: Speed,
+    pub paused: bool,
+    /// Сколько событий осталось воспроизвести.
+    pub remaining: usize,
+}
+impl std::fmt::Display for ControlStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let state = if self.paused { "paused" } else { "playing" };
+        write!(
+            f,
+            "{} {} {state}, remaining {}",
+            self.dt.format("%Y-%m-%d %H:%M:%S"),
+            self.speed,
+            self.remaining
+        )
+    }
+}
+
+/// Handle to control running simulation.
+///
+/// # ru
+/// Пульт управления запущенной симуляцией - стабильная точка
+/// управления для кнопок GUI, команд из командной строки и
+/// интеграционных тестов. Клоны пульта управляют той же симуляцией.
+/// Ошибка NotLoaded - симуляция уже завершена.
+#[derive(Debug, Clone)]
+pub struct Control {
+    tx: UnboundedSender<Command>,
+}
+impl Control {
+    /// Create control and receiver of its commands.
+    ///
+    /// # ru
+    /// Создает пульт и приемник его команд для симуляции.
+    pub fn new() -> (Self, UnboundedReceiver<Command>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        (Self { tx }, rx)
+    }
+    pub fn send(&self, command: Command) -> Result<(), AvinError> {
+        self.tx
+            .send(command)
+            .map_err(|_| AvinError::NotLoaded("simulation stopped".into()))
+    }
+    pub fn pause(&self) -> Result<(), AvinError> {
+        self.send(Command::Pause)
+    }
+    pub fn resume(&self) -> Result<(), AvinError> {
+        self.send(Command::Resume)
+    }
+    pub fn set_speed(&self, speed: Speed) -> Result<(), AvinError> {
+        self.send(Command::Speed(speed))
+    }
+    pub fn jump(&self, dt: DateTime<Utc>) -> Result<(), AvinError> {
+        self.send(Command::Jump(dt))
+    }
+    pub fn post(&self, a: OrderAction) -> Result<(), AvinError> {
+        self.send(Command::Post(Box::new(a)))
+    }
+    pub fn save(&self, path: PathBuf) -> Result<(), AvinError> {
+        self.send(Command::Save(path))
+    }
+    pub async fn status(&self) -> Result<ControlStatus, AvinError> {
+        let (tx, rx) = oneshot::channel();
+        self.send(Command::Status(tx))?;
+
+        rx.await
+            .map_err(|_| AvinError::NotLoaded("simulation stopped".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let c = |s| Command::try_from(s).unwrap();
+        assert!(matches!(c("pause"), Command::Pause));
+        assert!(matches!(c("resume"), Command::Resume));
+        assert!(matches!(c("speed 10x"), Command::Speed(Speed::X10)));
+        assert!(matches!(c("speed max"), Command::Speed(Speed::Max)));
+
+        let dt = Utc.with_ymd_and_hms(2024, 3, 1, 7, 0, 0).unwrap();
+        assert!(
+            matches!(c("jump 2024-03-01 10:00"), Command::Jump(d) if d == dt)
+        );
+
+        assert!(matches!(
+            c("buy 10"),
+            Command::Order {
+                direction: Direction::Buy,
+                lots: 10,
+                price: None
+            }
+        ));
+        assert!(matches!(
+            c("sell 2 280.5"),
+            Command::Order {
+                direction: Direction::Sell,
+                lots: 2,
+                price: Some(280.5)
+            }
+        ));
+
+        let save = c("save tmp/s.bin");
+        assert!(matches!(save, Command::Save(p) if p.ends_with("s.bin")));
+
+        for bad in ["", "speed 3x", "jump 2024-03-01", "buy", "buy x", "stop"]
+        {
+            assert!(Command::try_from(bad).is_err(), "{bad}");
+        }
+    }
+    #[tokio::test]
+    async fn stopped() {
+        let (control, rx) = Control::new();
+        assert!(control.pause().is_ok());
+
+        drop(rx);
+        assert!(control.resume().is_err());
+        assert!(control.status().await.is_err());
+    }
+}
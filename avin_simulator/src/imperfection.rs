@@ -0,0 +1,205 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{Direction, Iid};
+use avin_utils::{AvinError, Random, round_price};
+
+/// Random execution imperfections of simulated broker.
+///
+/// # ru
+/// Случайные несовершенства исполнения симулятора: брокер отклоняет
+/// часть ордеров, переспрашивает цену рыночного ордера (реквот) и
+/// подтверждает действия с задержкой. Нужны, чтобы проверить
+/// стратегию и обработку ошибок трейдера на поведении настоящего
+/// брокера. По умолчанию все выключено. Последовательность случайных
+/// чисел задается seed, повторный прогон дает тот же результат.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Imperfection {
+    /// Доля ордеров, отклоненных брокером, 0.01 == 1%.
+    pub reject: f64,
+    /// Доля рыночных ордеров, отклоненных с реквотом, 0.01 == 1%.
+    pub requote: f64,
+    /// Максимальный сдвиг цены реквота, шагов цены.
+    pub requote_steps: u32,
+    /// Задержка подтверждения, равномерно в [min, max], миллисекунды.
+    pub ack_delay: (u64, u64),
+    pub seed: u64,
+}
+impl Imperfection {
+    pub fn is_off(&self) -> bool {
+        self.reject == 0.0 && self.requote == 0.0 && self.ack_delay.1 == 0
+    }
+    /// Random reject reason of new order, None - order accepted.
+    ///
+    /// # ru
+    /// Причина случайного отклонения нового ордера, None - ордер
+    /// принят. Реквот возможен только у рыночного ордера, market -
+    /// его направление и последняя цена: новая цена хуже на
+    /// 1..=requote_steps шагов.
+    pub fn reject(
+        &self,
+        rng: &mut Random,
+        iid: &Iid,
+        market: Option<(&Direction, f64)>,
+    ) -> Option<String> {
+        if rng.chance(self.reject) {
+            return Some(SIMULATED_REJECT.to_string());
+        }
+
+        let (direction, last) = market?;
+        if !rng.chance(self.requote) {
+            return None;
+        }
+        let steps = 1 + rng.below(self.requote_steps.max(1) as usize);
+        let shift = steps as f64 * iid.step();
+        let price = match direction {
+            Direction::Buy => last + shift,
+            Direction::Sell => last - shift,
+        };
+        let price = round_price(price, iid.step());
+
+        Some(format!("{REQUOTE} {price}"))
+    }
+    /// Delay of next acknowledgement in nanoseconds.
+    ///
+    /// # ru
+    /// Задержка очередного подтверждения в наносекундах.
+    pub fn ack_delay(&self, rng: &mut Random) -> i64 {
+        let (min, max) = self.ack_delay;
+        let span = max.saturating_sub(min) + 1;
+        let ms = min + rng.next_u64() % span;
+
+        ms as i64 * 1_000_000
+    }
+}
+impl TryFrom<&str> for Imperfection {
+    type Error = AvinError;
+
+    /// Parse "reject=0.01 requote=0.05/3 ack=50-200ms seed=42".
+    ///
+    /// # ru
+    /// Создает модель из строки пар ключ=значение через пробел:
+    /// reject - доля отклонений, requote - доля реквотов и через "/"
+    /// максимальный сдвиг в шагах цены, ack - задержка подтверждения
+    /// "50ms" или "50-200ms", seed. Пропущенные ключи - ноль.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let err = || AvinError::InvalidValue(format!("imperfection {value}"));
+        let mut model = Imperfection::default();
+
+        for pair in value.split_whitespace() {
+            let (key, v) = pair.split_once('=').ok_or_else(err)?;
+            match key {
+                "reject" => model.reject = v.parse().map_err(|_| err())?,
+                "requote" => {
+                    let (rate, steps) = v.split_once('/').unwrap_or((v, "1"));
+                    model.requote = rate.parse().map_err(|_| err())?;
+                    model.requote_steps = steps.parse().map_err(|_| err())?;
+                }
+                "ack" => {
+                    let ms = v.trim_end_matches("ms");
+                    let (min, max) = ms.split_once('-').unwrap_or((ms, ms));
+                    let min = min.parse().map_err(|_| err())?;
+                    let max = max.parse().map_err(|_| err())?;
+                    if min > max {
+                        return Err(err());
+                    }
+                    model.ack_delay = (min, max);
+                }
+                "seed" => model.seed = v.parse().map_err(|_| err())?,
+                _ => return Err(err()),
+            }
+        }
+
+        let rates = [model.reject, model.requote];
+        if rates.iter().any(|p| !(0.0..=1.0).contains(p)) {
+            return Err(err());
+        }
+
+        Ok(model)
+    }
+}
+
+/// Пояснение в ордере, случайно отклоненном симулятором.
+pub const SIMULATED_REJECT: &str = "simulated reject";
+/// Начало пояснения в ордере, отклоненном с реквотом, дальше через
+/// пробел новая цена.
+pub const REQUOTE: &str = "requote";
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+
+    use super::*;
+
+    #[test]
+    fn reject_and_requote() {
+        let iid = iid();
+        let mut rng = Random::new(1);
+
+        let off = Imperfection::default();
+        assert!(off.is_off());
+        let r = off.reject(&mut rng, &iid, Some((&Direction::Buy, 100.0)));
+        assert_eq!(r, None);
+
+        let always = Imperfection {
+            reject: 1.0,
+            ..Default::default()
+        };
+        let r = always.reject(&mut rng, &iid, None);
+        assert_eq!(r.unwrap(), SIMULATED_REJECT);
+
+        let requote = Imperfection {
+            requote: 1.0,
+            requote_steps: 3,
+            ..Default::default()
+        };
+        // лимитки и ордера без цены реквот не получают
+        let r = requote.reject(&mut rng, &iid, None);
+        assert_eq!(r, None);
+        for _ in 0..20 {
+            let r = requote.reject(
+                &mut rng,
+                &iid,
+                Some((&Direction::Sell, 100.0)),
+            );
+            let r = r.unwrap();
+            let price: f64 =
+                r.strip_prefix("requote ").unwrap().parse().unwrap();
+            assert!((99.965..100.0).contains(&price));
+        }
+    }
+    #[test]
+    fn ack_delay() {
+        let mut rng = Random::new(1);
+        let model = Imperfection {
+            ack_delay: (50, 200),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let ns = model.ack_delay(&mut rng);
+            assert!((50_000_000..=200_000_000).contains(&ns));
+        }
+    }
+    #[test]
+    fn parse() {
+        let model =
+            Imperfection::try_from("reject=0.01 requote=0.05/3 ack=50-200ms")
+                .unwrap();
+        assert_eq!(model.reject, 0.01);
+        assert_eq!(model.requote, 0.05);
+        assert_eq!(model.requote_steps, 3);
+        assert_eq!(model.ack_delay, (50, 200));
+
+        let model = Imperfection::try_from("ack=100ms seed=7").unwrap();
+        assert_eq!(model.ack_delay, (100, 100));
+        assert_eq!(model.seed, 7);
+
+        assert!(Imperfection::try_from("reject=2").is_err());
+        assert!(Imperfection::try_from("ack=200-50ms").is_err());
+        assert!(Imperfection::try_from("slow").is_err());
+    }
+}
@@ -5,6 +5,22 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+mod control;
+mod imperfection;
+mod paper_accounts;
+mod paper_book;
+mod paper_broker;
+mod queue;
+mod replay;
+mod scenario;
+mod session;
 mod simulator;
 
+pub use control::{Command, Control, ControlStatus};
+pub use imperfection::{Imperfection, REQUOTE, SIMULATED_REJECT};
+pub use paper_broker::PaperBroker;
+pub use queue::QueuePosition;
+pub use replay::{Replay, ReplayBroker, ReplayClock, Speed};
+pub use scenario::{Scenario, Shock};
+pub use session::{Session, SessionAccount};
 pub use simulator::Simulator;
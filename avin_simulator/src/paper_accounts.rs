@@ -0,0 +1,240 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::{BTreeMap, HashMap};
+
+use avin_core::{Account, Event, Iid, Margin, Order, OrderAction, TimeFrame};
+
+use crate::paper_book::PaperBook;
+use crate::{Imperfection, Session};
+
+/// Независимые виртуальные счета симулятора на одном потоке рыночных
+/// данных. Действие идет на счет по имени своего account, счет
+/// открывается при первом обращении с депозитом по умолчанию или
+/// заранее через [`PaperAccounts::add`]. Рыночное событие проверяет
+/// ордера всех счетов, счета перебираются по имени, новый счет
+/// получает последние цены и стаканы. Так стратегии,
+/// привязанные к разным счетам, торгуют на одних данных независимо -
+/// например, два варианта стратегии для сравнения (A/B).
+#[derive(Debug)]
+pub(crate) struct PaperAccounts {
+    deposit: f64,
+    commission: f64,
+    margin: Option<Margin>,
+    imperfection: Imperfection,
+    books: BTreeMap<String, PaperBook>,
+    /// Последние цены и стаканы по figi, для новых счетов.
+    prices: HashMap<String, Event>,
+    order_books: HashMap<String, Event>,
+}
+impl PaperAccounts {
+    pub fn new(deposit: f64, commission: f64) -> Self {
+        Self {
+            deposit,
+            commission,
+            margin: None,
+            imperfection: Imperfection::default(),
+            books: BTreeMap::new(),
+            prices: HashMap::new(),
+            order_books: HashMap::new(),
+        }
+    }
+    pub fn deposit(&self) -> f64 {
+        self.deposit
+    }
+    /// Депозит счетов, которые откроются после вызова.
+    pub fn set_deposit(&mut self, deposit: f64) {
+        self.deposit = deposit;
+    }
+    pub fn set_commission(&mut self, commission: f64) {
+        self.commission = commission;
+        for book in self.books.values_mut() {
+            book.commission = commission;
+        }
+    }
+    pub fn set_margin(&mut self, margin: Margin) {
+        self.margin = Some(margin);
+        for book in self.books.values_mut() {
+            book.margin = Some(margin);
+        }
+    }
+    pub fn set_imperfection(&mut self, imperfection: Imperfection) {
+        self.imperfection = imperfection;
+        for book in self.books.values_mut() {
+            book.set_imperfection(imperfection);
+        }
+    }
+    /// Открывает счет name с депозитом deposit, прежний счет с этим
+    /// именем заменяется.
+    pub fn add(&mut self, name: &str, deposit: f64) {
+        let mut book = self.open(name);
+        book.cash = deposit;
+        self.books.insert(name.to_string(), book);
+    }
+    pub fn has_acks(&self) -> bool {
+        self.books.values().any(|book| book.has_acks())
+    }
+    pub fn release(&mut self, ts: i64) -> Vec<Event> {
+        self.books
+            .values_mut()
+            .flat_map(|book| book.release(ts))
+            .collect()
+    }
+    pub fn account(&mut self, name: &str, ts: i64) -> Account {
+        self.book(name).account(name, ts)
+    }
+    pub fn post(&mut self, a: OrderAction, ts: i64) -> Vec<Event> {
+        let name = a.account.name().clone();
+
+        self.book(&name).post(a, ts)
+    }
+    pub fn cancel(&mut self, a: OrderAction, ts: i64) -> Vec<Event> {
+        let name = a.account.name().clone();
+
+        self.book(&name).cancel(a, ts)
+    }
+    pub fn orders(&mut self, name: &str, iid: &Iid) -> Vec<Order> {
+        self.book(name).orders(iid)
+    }
+    pub fn position(&mut self, name: &str, iid: &Iid) -> i64 {
+        self.book(name).position(iid)
+    }
+    pub fn market(&mut self, e: &Event, ts: i64) -> Vec<Event> {
+        match e {
+            Event::Tic(_) => {
+                self.prices.insert(e.figi().clone(), e.clone());
+            }
+            Event::Bar(bar) if bar.tf == TimeFrame::M1 => {
+                self.prices.insert(e.figi().clone(), e.clone());
+            }
+            Event::Book(_) => {
+                self.order_books.insert(e.figi().clone(), e.clone());
+            }
+            _ => (),
+        }
+
+        self.books
+            .values_mut()
+            .flat_map(|book| book.market(e, ts))
+            .collect()
+    }
+    /// Снимок всех счетов на время рынка ts.
+    pub fn session(&self, ts: i64) -> Session {
+        let accounts = self
+            .books
+            .iter()
+            .map(|(name, book)| (name.clone(), book.session()))
+            .collect();
+
+        Session { ts, accounts }
+    }
+    /// Восстанавливает счета из снимка, прежние счета закрываются.
+    pub fn restore(&mut self, session: Session) {
+        self.books.clear();
+        for (name, account) in session.accounts {
+            let mut book = self.open(&name);
+            book.restore(account);
+            self.books.insert(name, book);
+        }
+    }
+
+    // private
+    fn book(&mut self, name: &str) -> &mut PaperBook {
+        if !self.books.contains_key(name) {
+            log::info!("Paper account {name} open, cash={}", self.deposit);
+            self.add(name, self.deposit);
+        }
+
+        self.books.get_mut(name).unwrap()
+    }
+    fn open(&self, name: &str) -> PaperBook {
+        let mut book = PaperBook::new(self.deposit, self.commission);
+        book.margin = self.margin;
+        book.prefix = format!("paper_{name}");
+        book.set_imperfection(self.imperfection);
+        // ордеров еще нет, событий не будет
+        for e in self.prices.values().chain(self.order_books.values()) {
+            book.market(e, 0);
+        }
+
+        book
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::{self, iid};
+    use avin_core::{Bar, Direction, MarketOrder, Order};
+
+    use super::*;
+
+    fn buy(account: &str, lots: u32) -> OrderAction {
+        let account = Account::new(account, "Paper_ID");
+        let order = MarketOrder::new(Direction::Buy, lots);
+
+        OrderAction::new(
+            account,
+            iid(),
+            "Test",
+            Order::Market(MarketOrder::New(order)),
+        )
+    }
+    fn bar(price: f64) -> Event {
+        fixture::bar_event(Bar::new(0, price, price, price, price, 1))
+    }
+
+    #[test]
+    fn accounts() {
+        let mut accounts = PaperAccounts::new(100_000.0, 0.0);
+        accounts.add("B", 20_000.0);
+
+        // рыночные ордера ждут первую цену, у каждого счета свои
+        let a = accounts.post(buy("A", 10), 1);
+        let b = accounts.post(buy("B", 10), 1);
+        assert_eq!(a.len(), 1);
+        assert_eq!(b.len(), 1);
+        let session = accounts.session(1);
+        let names: Vec<&String> =
+            session.accounts().iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["A", "B"]);
+
+        // одна цена исполняет ордера обоих счетов
+        let events = accounts.market(&bar(100.0), 2);
+        assert_eq!(events.len(), 2);
+        assert_eq!(accounts.account("A", 3).cash(), 90_000.0);
+        assert_eq!(accounts.account("B", 3).cash(), 10_000.0);
+
+        // номера ордеров не пересекаются
+        let Event::Order(e) = &events[0] else {
+            panic!()
+        };
+        assert_eq!(e.order.broker_id().unwrap(), "paper_A_1");
+        let Event::Order(e) = &events[1] else {
+            panic!()
+        };
+        assert_eq!(e.order.broker_id().unwrap(), "paper_B_1");
+
+        // новый счет исполняется сразу по последней цене
+        let c = accounts.post(buy("C", 1), 3);
+        assert_eq!(c.len(), 2);
+
+        // счету B не хватает денег, счет A независим
+        let b = accounts.post(buy("B", 20), 4);
+        let Event::Order(e) = &b[0] else { panic!() };
+        assert!(!e.order.is_posted());
+        let a = accounts.post(buy("A", 20), 4);
+        assert_eq!(a.len(), 2);
+
+        // снимок восстанавливает все счета
+        let session = accounts.session(5);
+        let mut restored = PaperAccounts::new(0.0, 0.0);
+        restored.restore(session.clone());
+        assert_eq!(restored.session(5), session);
+        let b = session.account("B").unwrap();
+        assert_eq!(b.position(iid().figi()), 100);
+    }
+}
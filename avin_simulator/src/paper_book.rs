@@ -0,0 +1,1052 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::{HashMap, VecDeque};
+
+use avin_core::{
+    Account, Direction, Event, Iid, LimitOrder, Margin, MarketOrder, Order,
+    OrderAction, OrderBook, OrderEvent, PostedMarketOrder, PostedStopOrder,
+    StopOrder, StopOrderKind, TimeFrame, Transaction, TriggeredStopOrder,
+};
+
+use avin_utils::Random;
+
+use crate::session::Origin;
+use crate::{Imperfection, QueuePosition, SessionAccount};
+
+// Пояснение в отклоненном ордере, когда не хватает денег
+const CASH_REJECT: &str = "insufficient cash";
+// Пояснение в отклоненном ордере, когда не хватает маржи
+const MARGIN_REJECT: &str = "insufficient margin";
+
+/// Ордер, ожидающий исполнения, с данными для события.
+#[derive(Debug)]
+struct Pending<T> {
+    account: Account,
+    iid: Iid,
+    owner: String,
+    order: T,
+}
+impl<T> Pending<T> {
+    fn event(&self, order: Order) -> Event {
+        let e = OrderEvent::new(
+            self.account.clone(),
+            self.iid.clone(),
+            self.owner.clone(),
+            order,
+        );
+
+        Event::Order(e)
+    }
+}
+
+/// Виртуальный счет бумажной торговли: деньги, позиции и активные
+/// ордера. Не зависит от сети, каждый метод возвращает события,
+/// которые брокер отправляет трейдеру. Лимитные ордера хранятся
+/// выставленными или частично исполненными, очередь ордера в
+/// стакане - по его broker_id. С задержкой подтверждения события
+/// ждут в очереди, брокер забирает их через [`PaperBook::release`].
+#[derive(Debug)]
+pub(crate) struct PaperBook {
+    pub cash: f64,
+    pub commission: f64,
+    pub margin: Option<Margin>,
+    /// Начало broker_id ордеров, у каждого счета свое.
+    pub prefix: String,
+    positions: HashMap<String, i64>,
+    /// Владелец последней сделки по инструменту, для margin call.
+    owners: HashMap<String, Pending<()>>,
+    prices: HashMap<String, f64>,
+    books: HashMap<String, OrderBook>,
+    queues: HashMap<String, QueuePosition>,
+    market_orders: Vec<Pending<PostedMarketOrder>>,
+    limit_orders: Vec<Pending<LimitOrder>>,
+    stop_orders: Vec<Pending<PostedStopOrder>>,
+    next_id: u64,
+    imperfection: Imperfection,
+    rng: Random,
+    /// Неподтвержденные события и время их отправки.
+    acks: VecDeque<(i64, Event)>,
+}
+impl PaperBook {
+    pub fn new(cash: f64, commission: f64) -> Self {
+        Self {
+            cash,
+            commission,
+            margin: None,
+            prefix: "paper".to_string(),
+            positions: HashMap::new(),
+            owners: HashMap::new(),
+            prices: HashMap::new(),
+            books: HashMap::new(),
+            queues: HashMap::new(),
+            market_orders: Vec::new(),
+            limit_orders: Vec::new(),
+            stop_orders: Vec::new(),
+            next_id: 0,
+            imperfection: Imperfection::default(),
+            rng: Random::new(0),
+            acks: VecDeque::new(),
+        }
+    }
+    pub fn set_imperfection(&mut self, imperfection: Imperfection) {
+        self.rng = Random::new(imperfection.seed);
+        self.imperfection = imperfection;
+    }
+    pub fn has_acks(&self) -> bool {
+        !self.acks.is_empty()
+    }
+    /// Отложенные подтверждения, время которых наступило к ts.
+    pub fn release(&mut self, ts: i64) -> Vec<Event> {
+        let mut events = Vec::new();
+        while let Some((due, _)) = self.acks.front()
+            && *due <= ts
+        {
+            events.push(self.acks.pop_front().unwrap().1);
+        }
+
+        events
+    }
+    pub fn account(&self, name: &str, ts: i64) -> Account {
+        let mut account = Account::new(name, "Paper_ID");
+        account.set_balance(ts, "rub", self.cash, 0.0);
+
+        account
+    }
+    pub fn post(&mut self, a: OrderAction, ts: i64) -> Vec<Event> {
+        // реквот бывает только у рыночного ордера с известной ценой
+        let last = self.prices.get(a.iid.figi()).copied();
+        let market = match &a.order {
+            Order::Market(MarketOrder::New(o)) => {
+                last.map(|price| (&o.direction, price))
+            }
+            _ => None,
+        };
+        let reason = self.imperfection.reject(&mut self.rng, &a.iid, market);
+
+        let events = match reason {
+            Some(reason) => vec![reject(&a, &reason)],
+            None => self.exec_post(a, ts),
+        };
+
+        self.acknowledge(events, ts)
+    }
+    pub fn cancel(&mut self, a: OrderAction, ts: i64) -> Vec<Event> {
+        let events = self.exec_cancel(a);
+
+        self.acknowledge(events, ts)
+    }
+    fn exec_post(&mut self, a: OrderAction, ts: i64) -> Vec<Event> {
+        let mut events = Vec::new();
+        let figi = a.iid.figi().clone();
+        let last = self.prices.get(&figi).copied();
+
+        match a.order.clone() {
+            Order::Market(order) => {
+                let new = order.as_new().expect("Order must be 'New'");
+                let check = last.map(|price| {
+                    self.check(&a.iid, &new.direction, new.lots, price)
+                });
+                if let Some(Err(reason)) = check {
+                    let order = MarketOrder::Rejected(new.reject(reason));
+                    events.push(event(&a, Order::Market(order)));
+                    return events;
+                }
+
+                let posted = new.post(&self.broker_id());
+                let pending = pending(&a, posted.clone());
+                events.push(
+                    pending.event(Order::Market(MarketOrder::Posted(posted))),
+                );
+                match last {
+                    Some(price) => {
+                        self.exec_market(pending, price, ts, &mut events)
+                    }
+                    None => self.market_orders.push(pending),
+                }
+            }
+            Order::Limit(order) => {
+                let new = order.as_new().expect("Order must be 'New'");
+                let check =
+                    self.check(&a.iid, &new.direction, new.lots, new.price);
+                if let Err(reason) = check {
+                    let order = LimitOrder::Rejected(new.reject(reason));
+                    events.push(event(&a, Order::Limit(order)));
+                    return events;
+                }
+
+                let order = LimitOrder::Posted(new.post(&self.broker_id()));
+                let pending = pending(&a, order.clone());
+                events.push(pending.event(Order::Limit(order)));
+
+                // лимитка "в рынок" исполняется сразу по последней цене
+                match last {
+                    Some(price) if crossed(&pending.order, price) => {
+                        self.exec_limit(
+                            pending,
+                            price,
+                            u32::MAX,
+                            ts,
+                            &mut events,
+                        );
+                    }
+                    _ => self.push_limit(pending),
+                }
+            }
+            Order::Stop(order) => {
+                let new = order.as_new().expect("Order must be 'New'");
+                let posted = new.post(&self.broker_id());
+                let pending = pending(&a, posted.clone());
+                events.push(
+                    pending.event(Order::Stop(StopOrder::Posted(posted))),
+                );
+                self.stop_orders.push(pending);
+            }
+        }
+
+        events
+    }
+    fn exec_cancel(&mut self, a: OrderAction) -> Vec<Event> {
+        let id = a.order.broker_id().cloned();
+        let is_id = |broker_id: &String| Some(broker_id) == id.as_ref();
+
+        if let Some(i) = self
+            .limit_orders
+            .iter()
+            .position(|p| p.order.broker_id().is_some_and(is_id))
+        {
+            let pending = self.limit_orders.remove(i);
+            self.queues.remove(pending.order.broker_id().unwrap());
+            let order = match pending.order.clone() {
+                LimitOrder::Posted(o) => o.cancel(),
+                LimitOrder::PartiallyFilled(o) => o.cancel(),
+                _ => unreachable!("cancel not active limit order"),
+            };
+            let order = Order::Limit(LimitOrder::Canceled(order));
+            return vec![pending.event(order)];
+        }
+        if let Some(i) = self
+            .stop_orders
+            .iter()
+            .position(|p| is_id(&p.order.broker_id))
+        {
+            let pending = self.stop_orders.remove(i);
+            let order = StopOrder::Canceled(pending.order.clone().cancel());
+            return vec![pending.event(Order::Stop(order))];
+        }
+
+        log::error!("PaperBroker cancel not active order: {a}");
+        Vec::new()
+    }
+    /// Обновляет последнюю цену по тику или 1М бару и проверяет
+    /// активные ордера инструмента, по стакану - очереди ордеров.
+    pub fn market(&mut self, e: &Event, ts: i64) -> Vec<Event> {
+        let price = match e {
+            Event::Tic(e) => e.tic.price,
+            Event::Bar(e) if e.tf == TimeFrame::M1 => e.bar.c,
+            Event::Book(e) => {
+                self.update_queues(&e.figi, &e.book);
+                self.books.insert(e.figi.clone(), e.book.clone());
+                return Vec::new();
+            }
+            _ => return Vec::new(),
+        };
+        let figi = e.figi();
+        self.prices.insert(figi.clone(), price);
+
+        let mut events = Vec::new();
+        for pending in take(&mut self.market_orders, figi) {
+            self.exec_market(pending, price, ts, &mut events);
+        }
+        for pending in take(&mut self.stop_orders, figi) {
+            if triggered(&pending.order, price) {
+                self.trigger_stop(pending, price, ts, &mut events);
+            } else {
+                self.stop_orders.push(pending);
+            }
+        }
+        for pending in take(&mut self.limit_orders, figi) {
+            let (direction, limit, _) = active(&pending.order);
+            let id = pending.order.broker_id().unwrap();
+
+            // с очередью ордер исполняют только сделки на его уровне,
+            // без стакана и по бару (сделок в нем не видно) - касание
+            // цены
+            let lots = match (self.queues.get_mut(id), e) {
+                (Some(queue), Event::Tic(e)) => {
+                    queue.trade(&direction, limit, &e.tic)
+                }
+                _ if crossed(&pending.order, price) => u32::MAX,
+                _ => 0,
+            };
+
+            if lots == 0 {
+                self.limit_orders.push(pending);
+            } else {
+                self.exec_limit(pending, limit, lots, ts, &mut events);
+            }
+        }
+        self.check_margin(ts, &mut events);
+
+        // пока есть неподтвержденные события, исполнения идут после них
+        if let Some((due, _)) = self.acks.back() {
+            let due = *due;
+            self.acks.extend(events.into_iter().map(|e| (due, e)));
+            return Vec::new();
+        }
+
+        events
+    }
+    /// Хватает ли денег или маржи на ордер: без маржи покупка
+    /// ограничена деньгами, с маржой - уменьшение позиции разрешено
+    /// всегда, для новой позиции нужна начальная маржа по всему счету.
+    fn check(
+        &self,
+        iid: &Iid,
+        direction: &Direction,
+        lots: u32,
+        price: f64,
+    ) -> Result<(), &'static str> {
+        let Some(margin) = self.margin else {
+            let cost = self.cost(iid, lots, price);
+            if *direction == Direction::Buy && cost > self.cash {
+                return Err(CASH_REJECT);
+            }
+            return Ok(());
+        };
+
+        let figi = iid.figi();
+        let position = self.positions.get(figi).copied().unwrap_or(0);
+        let quantity = (lots * iid.lot()) as i64;
+        let new = match direction {
+            Direction::Buy => position + quantity,
+            Direction::Sell => position - quantity,
+        };
+        if new.abs() <= position.abs() && new * position >= 0 {
+            return Ok(());
+        }
+
+        let others: f64 = self
+            .positions
+            .iter()
+            .filter(|(f, _)| *f != figi)
+            .map(|(f, pos)| self.margin_of(f).initial(*pos, self.price(f)))
+            .sum();
+        let required = others + instrument(iid, margin).initial(new, price);
+        if required > self.equity() {
+            return Err(MARGIN_REJECT);
+        }
+
+        Ok(())
+    }
+    /// Капитал ниже минимальной маржи по всем позициям - отмена всех
+    /// активных ордеров и закрытие позиций по рынку.
+    fn check_margin(&mut self, ts: i64, events: &mut Vec<Event>) {
+        if self.margin.is_none() {
+            return;
+        }
+        let minimal: f64 = self
+            .positions
+            .iter()
+            .map(|(f, pos)| self.margin_of(f).minimal(*pos, self.price(f)))
+            .sum();
+        let equity = self.equity();
+        if minimal == 0.0 || equity >= minimal {
+            return;
+        }
+
+        log::warn!("PaperBroker margin call: equity={equity} min={minimal}");
+        for pending in std::mem::take(&mut self.limit_orders) {
+            let order = match pending.order.clone() {
+                LimitOrder::Posted(o) => o.cancel(),
+                LimitOrder::PartiallyFilled(o) => o.cancel(),
+                _ => unreachable!("cancel not active limit order"),
+            };
+            let order = Order::Limit(LimitOrder::Canceled(order));
+            events.push(pending.event(order));
+        }
+        for pending in std::mem::take(&mut self.stop_orders) {
+            let order = StopOrder::Canceled(pending.order.clone().cancel());
+            events.push(pending.event(Order::Stop(order)));
+        }
+        self.queues.clear();
+
+        let mut positions: Vec<(String, i64)> = self
+            .positions
+            .iter()
+            .filter(|(_, pos)| **pos != 0)
+            .map(|(f, pos)| (f.clone(), *pos))
+            .collect();
+        positions.sort();
+        for (figi, position) in positions {
+            let owner = pending_as(&self.owners[&figi], ());
+            let direction = if position > 0 {
+                Direction::Sell
+            } else {
+                Direction::Buy
+            };
+            let lots = position.unsigned_abs() / owner.iid.lot() as u64;
+            let order = MarketOrder::new(direction, lots as u32);
+            let order = order.post(&self.broker_id());
+            let pending = pending_as(&owner, order);
+            let price = self.price(&figi);
+            self.exec_market(pending, price, ts, events);
+        }
+    }
+    /// Капитал: деньги и стоимость позиций по последним ценам.
+    fn equity(&self) -> f64 {
+        let positions: f64 = self
+            .positions
+            .iter()
+            .map(|(figi, pos)| *pos as f64 * self.price(figi))
+            .sum();
+
+        self.cash + positions
+    }
+    fn price(&self, figi: &String) -> f64 {
+        self.prices.get(figi).copied().unwrap_or(0.0)
+    }
+    fn margin_of(&self, figi: &String) -> Margin {
+        let margin = self.margin.unwrap();
+        match self.owners.get(figi) {
+            Some(owner) => instrument(&owner.iid, margin),
+            None => margin,
+        }
+    }
+    /// Ставит лимитный ордер в ожидание, при известном стакане - в
+    /// конец очереди его уровня.
+    fn push_limit(&mut self, pending: Pending<LimitOrder>) {
+        let (direction, price, _) = active(&pending.order);
+        if let Some(book) = self.books.get(pending.iid.figi()) {
+            let id = pending.order.broker_id().unwrap().clone();
+            let lots = book.lots(&direction, price);
+            self.queues.insert(id, QueuePosition::new(lots));
+        }
+
+        self.limit_orders.push(pending);
+    }
+    fn update_queues(&mut self, figi: &String, book: &OrderBook) {
+        for pending in self.limit_orders.iter() {
+            if pending.iid.figi() != figi {
+                continue;
+            }
+
+            // ордер выставлен до первого стакана - в конец очереди
+            let (direction, price, _) = active(&pending.order);
+            let lots = book.lots(&direction, price);
+            let id = pending.order.broker_id().unwrap().clone();
+            self.queues
+                .entry(id)
+                .or_insert_with(|| QueuePosition::new(lots))
+                .level(lots);
+        }
+    }
+    fn trigger_stop(
+        &mut self,
+        pending: Pending<PostedStopOrder>,
+        price: f64,
+        ts: i64,
+        events: &mut Vec<Event>,
+    ) {
+        let triggered = pending.order.clone().trigger(&self.broker_id());
+        let order = Order::Stop(StopOrder::Triggered(triggered.clone()));
+        events.push(pending.event(order));
+
+        match triggered {
+            TriggeredStopOrder::Market { order, .. } => {
+                let pending = pending_as(&pending, order);
+                self.exec_market(pending, price, ts, events);
+            }
+            TriggeredStopOrder::Limit { order, .. } => {
+                let pending = pending_as(&pending, LimitOrder::Posted(order));
+                if crossed(&pending.order, price) {
+                    self.exec_limit(pending, price, u32::MAX, ts, events);
+                } else {
+                    self.push_limit(pending);
+                }
+            }
+        }
+    }
+    fn exec_market(
+        &mut self,
+        mut pending: Pending<PostedMarketOrder>,
+        price: f64,
+        ts: i64,
+        events: &mut Vec<Event>,
+    ) {
+        let (direction, lots) =
+            (pending.order.direction.clone(), pending.order.lots);
+        let transaction = self.fill(&pending, &direction, lots, price);
+        let order = &mut pending.order;
+        let commission = self.commission * transaction.value().abs();
+        self.cash -= commission;
+        order.add_transaction(transaction);
+
+        let order = pending.order.clone().fill(ts, commission);
+        log::info!("PaperBroker fill {} {order}", pending.iid.ticker());
+        events.push(pending.event(Order::Market(MarketOrder::Filled(order))));
+    }
+    /// Исполняет до lots лотов лимитного ордера по цене price, если
+    /// ордер исполнен не полностью - оставляет его в ожидании.
+    fn exec_limit(
+        &mut self,
+        pending: Pending<LimitOrder>,
+        price: f64,
+        lots: u32,
+        ts: i64,
+        events: &mut Vec<Event>,
+    ) {
+        let (direction, _, remaining) = active(&pending.order);
+        let lots = lots.min(remaining);
+        let transaction = self.fill(&pending, &direction, lots, price);
+        self.cash -= self.commission * transaction.value().abs();
+
+        let order = match pending.order.clone() {
+            LimitOrder::Posted(mut o) if lots == o.lots => {
+                o.add_transaction(transaction);
+                let commission = self.order_commission(&o.transactions);
+                LimitOrder::Filled(o.fill(ts, commission))
+            }
+            LimitOrder::Posted(o) => {
+                LimitOrder::PartiallyFilled(o.partial_fill(lots, transaction))
+            }
+            LimitOrder::PartiallyFilled(mut o) => {
+                o.add_fill(lots, transaction);
+                if o.is_complete() {
+                    let commission = self.order_commission(&o.transactions);
+                    LimitOrder::Filled(o.fill(ts, commission))
+                } else {
+                    LimitOrder::PartiallyFilled(o)
+                }
+            }
+            _ => unreachable!("exec not active limit order"),
+        };
+
+        log::info!("PaperBroker fill {} {order}", pending.iid.ticker());
+        events.push(pending.event(Order::Limit(order.clone())));
+        if order.is_partially_filled() {
+            self.limit_orders.push(pending_as(&pending, order));
+        } else if let Some(id) = order.broker_id() {
+            self.queues.remove(id);
+        }
+    }
+    fn order_commission(&self, transactions: &[Transaction]) -> f64 {
+        let value: f64 = transactions.iter().map(|t| t.value().abs()).sum();
+
+        self.commission * value
+    }
+    /// Сделка по цене price: меняет деньги и позицию счета.
+    fn fill<T>(
+        &mut self,
+        pending: &Pending<T>,
+        direction: &Direction,
+        lots: u32,
+        price: f64,
+    ) -> Transaction {
+        let iid = &pending.iid;
+        self.owners
+            .insert(iid.figi().clone(), pending_as(pending, ()));
+        let quantity = lots * iid.lot();
+        let transaction = Transaction::new(quantity as i32, price);
+        let position = self.positions.entry(iid.figi().clone()).or_default();
+        match direction {
+            Direction::Buy => {
+                self.cash -= transaction.value().abs();
+                *position += quantity as i64;
+            }
+            Direction::Sell => {
+                self.cash += transaction.value().abs();
+                *position -= quantity as i64;
+            }
+        }
+
+        transaction
+    }
+    fn cost(&self, iid: &Iid, lots: u32, price: f64) -> f64 {
+        let value = (lots * iid.lot()) as f64 * price;
+
+        value * (1.0 + self.commission)
+    }
+    fn broker_id(&mut self) -> String {
+        self.next_id += 1;
+
+        format!("{}_{}", self.prefix, self.next_id)
+    }
+}
+
+impl PaperBook {
+    /// Активные ордера инструмента: выставленные и частично
+    /// исполненные лимитки, выставленные стоп ордера.
+    pub fn orders(&self, iid: &Iid) -> Vec<Order> {
+        let mut orders = Vec::new();
+        for p in self.limit_orders.iter().filter(|p| p.iid == *iid) {
+            orders.push(Order::Limit(p.order.clone()));
+        }
+        for p in self.stop_orders.iter().filter(|p| p.iid == *iid) {
+            orders.push(Order::Stop(StopOrder::Posted(p.order.clone())));
+        }
+
+        orders
+    }
+    /// Позиция инструмента в штуках (не лотах).
+    pub fn position(&self, iid: &Iid) -> i64 {
+        self.positions.get(iid.figi()).copied().unwrap_or(0)
+    }
+    /// Снимок счета и активных ордеров.
+    pub fn session(&self) -> SessionAccount {
+        let mut orders = Vec::new();
+        for p in self.market_orders.iter() {
+            let order = MarketOrder::Posted(p.order.clone());
+            orders.push((origin(p), Order::Market(order)));
+        }
+        for p in self.limit_orders.iter() {
+            orders.push((origin(p), Order::Limit(p.order.clone())));
+        }
+        for p in self.stop_orders.iter() {
+            let order = StopOrder::Posted(p.order.clone());
+            orders.push((origin(p), Order::Stop(order)));
+        }
+
+        let mut session = SessionAccount {
+            cash: self.cash,
+            commission: self.commission,
+            margin: self.margin,
+            positions: self.positions.clone().into_iter().collect(),
+            prices: self.prices.clone().into_iter().collect(),
+            owners: self
+                .owners
+                .iter()
+                .map(|(figi, p)| (figi.clone(), origin(p)))
+                .collect(),
+            orders,
+            queues: self
+                .queues
+                .iter()
+                .map(|(id, queue)| (id.clone(), queue.ahead()))
+                .collect(),
+            next_id: self.next_id,
+        };
+        // порядок HashMap случайный, файл одного состояния - одинаковый
+        session.positions.sort_by(|a, b| a.0.cmp(&b.0));
+        session.prices.sort_by(|a, b| a.0.cmp(&b.0));
+        session.owners.sort_by(|a, b| a.0.cmp(&b.0));
+        session.queues.sort();
+
+        session
+    }
+    /// Восстанавливает счет и активные ордера из снимка, прежнее
+    /// состояние и неотправленные подтверждения отбрасываются.
+    pub fn restore(&mut self, session: SessionAccount) {
+        self.cash = session.cash;
+        self.commission = session.commission;
+        self.margin = session.margin;
+        self.positions = session.positions.into_iter().collect();
+        self.prices = session.prices.into_iter().collect();
+        self.owners = session
+            .owners
+            .into_iter()
+            .map(|(figi, o)| (figi, restored(o, ())))
+            .collect();
+        self.books.clear();
+        self.queues = session
+            .queues
+            .into_iter()
+            .map(|(id, ahead)| (id, QueuePosition::new(ahead)))
+            .collect();
+        self.market_orders.clear();
+        self.limit_orders.clear();
+        self.stop_orders.clear();
+        for (o, order) in session.orders {
+            match order {
+                Order::Market(MarketOrder::Posted(order)) => {
+                    self.market_orders.push(restored(o, order))
+                }
+                Order::Limit(order) => {
+                    self.limit_orders.push(restored(o, order))
+                }
+                Order::Stop(StopOrder::Posted(order)) => {
+                    self.stop_orders.push(restored(o, order))
+                }
+                order => log::error!("PaperBook restore skip {order}"),
+            }
+        }
+        self.next_id = session.next_id;
+        self.acks.clear();
+    }
+}
+
+impl PaperBook {
+    /// С задержкой подтверждения события ставятся в очередь, порядок
+    /// событий сохраняется.
+    fn acknowledge(&mut self, events: Vec<Event>, ts: i64) -> Vec<Event> {
+        if self.imperfection.ack_delay.1 == 0 || events.is_empty() {
+            return events;
+        }
+
+        let mut due = ts + self.imperfection.ack_delay(&mut self.rng);
+        if let Some((last, _)) = self.acks.back() {
+            due = due.max(*last);
+        }
+        self.acks.extend(events.into_iter().map(|e| (due, e)));
+
+        Vec::new()
+    }
+}
+
+fn event(a: &OrderAction, order: Order) -> Event {
+    let e = OrderEvent::new(
+        a.account.clone(),
+        a.iid.clone(),
+        a.owner.clone(),
+        order,
+    );
+
+    Event::Order(e)
+}
+/// Отклоненный брокером новый ордер.
+fn reject(a: &OrderAction, reason: &str) -> Event {
+    let order = match a.order.clone() {
+        Order::Market(MarketOrder::New(o)) => {
+            Order::Market(MarketOrder::Rejected(o.reject(reason)))
+        }
+        Order::Limit(LimitOrder::New(o)) => {
+            Order::Limit(LimitOrder::Rejected(o.reject(reason)))
+        }
+        Order::Stop(StopOrder::New(o)) => {
+            Order::Stop(StopOrder::Rejected(o.reject(reason)))
+        }
+        _ => panic!("Order must be 'New'"),
+    };
+
+    event(a, order)
+}
+fn pending<T>(a: &OrderAction, order: T) -> Pending<T> {
+    Pending {
+        account: a.account.clone(),
+        iid: a.iid.clone(),
+        owner: a.owner.clone(),
+        order,
+    }
+}
+fn pending_as<T, U>(p: &Pending<T>, order: U) -> Pending<U> {
+    Pending {
+        account: p.account.clone(),
+        iid: p.iid.clone(),
+        owner: p.owner.clone(),
+        order,
+    }
+}
+fn origin<T>(p: &Pending<T>) -> Origin {
+    Origin {
+        account: p.account.name().clone(),
+        account_id: p.account.id().clone(),
+        iid: p.iid.clone(),
+        owner: p.owner.clone(),
+    }
+}
+fn restored<T>(o: Origin, order: T) -> Pending<T> {
+    Pending {
+        account: Account::new(&o.account, &o.account_id),
+        iid: o.iid,
+        owner: o.owner,
+        order,
+    }
+}
+/// Забирает из списка ожидающие ордера инструмента figi.
+fn take<T>(orders: &mut Vec<Pending<T>>, figi: &String) -> Vec<Pending<T>> {
+    let (taken, rest) = std::mem::take(orders)
+        .into_iter()
+        .partition(|p| p.iid.figi() == figi);
+    *orders = rest;
+
+    taken
+}
+/// Модель маржи инструмента: ставки риска из информации об
+/// инструменте, если есть, иначе общая модель счета.
+fn instrument(iid: &Iid, margin: Margin) -> Margin {
+    let mut instrument = Margin::from_iid(iid, margin.rate).unwrap_or(margin);
+    instrument.short_rate = margin.short_rate;
+
+    instrument
+}
+/// Направление, цена и неисполненный остаток активного лимитного
+/// ордера.
+fn active(order: &LimitOrder) -> (Direction, f64, u32) {
+    match order {
+        LimitOrder::Posted(o) => (o.direction.clone(), o.price, o.lots),
+        LimitOrder::PartiallyFilled(o) => {
+            (o.direction.clone(), o.price, o.remaining())
+        }
+        _ => unreachable!("not active limit order"),
+    }
+}
+/// Цена дошла до лимитного ордера.
+fn crossed(order: &LimitOrder, price: f64) -> bool {
+    let (direction, limit, _) = active(order);
+
+    match direction {
+        Direction::Buy => price <= limit,
+        Direction::Sell => price >= limit,
+    }
+}
+/// Цена дошла до стоп ордера: стоп лосс на продажу и тейк профит на
+/// покупку - снизу, остальные - сверху.
+fn triggered(order: &PostedStopOrder, price: f64) -> bool {
+    let below = price <= order.stop_price;
+    let above = price >= order.stop_price;
+
+    match (&order.kind, &order.direction) {
+        (StopOrderKind::StopLoss, Direction::Sell) => below,
+        (StopOrderKind::StopLoss, Direction::Buy) => above,
+        (StopOrderKind::TakeProfit, Direction::Sell) => above,
+        (StopOrderKind::TakeProfit, Direction::Buy) => below,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::{self, iid};
+    use avin_core::{Bar, BookEvent, Tic, TicEvent};
+
+    use super::*;
+    use crate::{REQUOTE, SIMULATED_REJECT};
+
+    fn action(order: Order) -> OrderAction {
+        let account = Account::new("Paper", "Paper_ID");
+
+        OrderAction::new(account, iid(), "Test", order)
+    }
+    fn bar(price: f64) -> Event {
+        fixture::bar_event(Bar::new(0, price, price, price, price, 1))
+    }
+    fn tic(direction: Direction, lots: u32, price: f64) -> Event {
+        let tic = Tic::new(0, direction, lots, price, 0.0);
+
+        Event::Tic(TicEvent::new(iid().figi().clone(), tic))
+    }
+    fn orders(events: &[Event]) -> Vec<Order> {
+        events
+            .iter()
+            .map(|e| match e {
+                Event::Order(e) => e.order.clone(),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn market_and_limit() {
+        let mut book = PaperBook::new(100_000.0, 0.001);
+
+        // без цены рыночный ордер ждет первую цену
+        let order = MarketOrder::new(Direction::Buy, 10);
+        let events =
+            book.post(action(Order::Market(MarketOrder::New(order))), 1);
+        assert!(orders(&events)[0].is_posted());
+        let events = book.market(&bar(100.0), 2);
+        assert!(orders(&events)[0].is_filled());
+        assert_eq!(book.positions[iid().figi()], 100);
+        assert_eq!(book.cash, 100_000.0 - 10_000.0 - 10.0);
+
+        // лимитка ниже рынка исполняется по своей цене
+        let order = LimitOrder::new(Direction::Sell, 10, 105.0);
+        let events =
+            book.post(action(Order::Limit(LimitOrder::New(order))), 3);
+        assert_eq!(events.len(), 1);
+        assert!(book.market(&bar(104.0), 4).is_empty());
+        let events = book.market(&bar(106.0), 5);
+        let filled = orders(&events)[0].clone().as_limit().unwrap();
+        let filled = filled.as_filled().unwrap();
+        assert_eq!(filled.transactions[0].price, 105.0);
+        assert_eq!(book.positions[iid().figi()], 0);
+
+        // не хватает денег
+        let order = MarketOrder::new(Direction::Buy, 1000);
+        let events =
+            book.post(action(Order::Market(MarketOrder::New(order))), 6);
+        assert!(events.len() == 1 && !orders(&events)[0].is_posted());
+    }
+    #[test]
+    fn stop_and_cancel() {
+        let mut book = PaperBook::new(100_000.0, 0.0);
+        book.market(&bar(100.0), 1);
+
+        let stop = StopOrder::new(
+            StopOrderKind::StopLoss,
+            Direction::Sell,
+            1,
+            95.0,
+            None,
+        );
+        let events = book.post(action(Order::Stop(StopOrder::New(stop))), 2);
+        let posted = orders(&events)[0].clone();
+        assert!(book.market(&bar(96.0), 3).is_empty());
+
+        let events = book.market(&bar(94.0), 4);
+        let events = orders(&events);
+        assert_eq!(events.len(), 2);
+        assert!(events[1].is_filled());
+        assert_eq!(book.positions[iid().figi()], -10);
+
+        // отмена уже сработавшего стопа - нет события
+        assert!(book.cancel(action(posted), 2).is_empty());
+        let limit = LimitOrder::new(Direction::Buy, 1, 90.0);
+        let events =
+            book.post(action(Order::Limit(LimitOrder::New(limit))), 5);
+        let events = book.cancel(action(orders(&events)[0].clone()), 2);
+        assert!(orders(&events)[0].is_canceled());
+        assert!(book.limit_orders.is_empty());
+    }
+    #[test]
+    fn queue_fill() {
+        let mut book = PaperBook::new(100_000.0, 0.0);
+        let figi = iid().figi().clone();
+        let ob = OrderBook::new(0, vec![(100.0, 20)], vec![(100.1, 5)]);
+        book.market(&Event::Book(BookEvent::new(figi.clone(), ob)), 1);
+        book.market(&tic(Direction::Buy, 1, 100.1), 2);
+
+        // покупка 10 лотов по 100, в очереди перед ней 20 лотов
+        let order = LimitOrder::new(Direction::Buy, 10, 100.0);
+        book.post(action(Order::Limit(LimitOrder::New(order))), 3);
+        assert_eq!(book.queues.values().next().unwrap().ahead(), 20);
+
+        // цена рядом с уровнем, сделок на уровне нет
+        assert!(book.market(&tic(Direction::Buy, 1, 100.1), 4).is_empty());
+
+        // 25 лотов продали в уровень: 20 очередь + 5 наши
+        let events = book.market(&tic(Direction::Sell, 25, 100.0), 5);
+        assert!(orders(&events)[0].is_partially_filled());
+        assert_eq!(book.positions[&figi], 50);
+
+        // остаток
+        let events = book.market(&tic(Direction::Sell, 10, 100.0), 6);
+        assert!(orders(&events)[0].is_filled());
+        assert_eq!(book.positions[&figi], 100);
+        assert!(book.limit_orders.is_empty() && book.queues.is_empty());
+
+        // по бару сделок не видно: цена дошла до уровня - исполнение
+        book.market(&tic(Direction::Buy, 1, 100.1), 7);
+        let order = LimitOrder::new(Direction::Buy, 10, 100.0);
+        book.post(action(Order::Limit(LimitOrder::New(order))), 7);
+        assert_eq!(book.queues.len(), 1);
+        let events = book.market(&bar(100.0), 8);
+        assert!(orders(&events)[0].is_filled());
+        assert!(book.limit_orders.is_empty() && book.queues.is_empty());
+    }
+    #[test]
+    fn margin_call() {
+        let mut book = PaperBook::new(1000.0, 0.0);
+        book.margin = Some(Margin::new(0.25, 0.5, 0.0));
+        let figi = iid().figi().clone();
+        book.market(&bar(10.0), 1);
+
+        // плечо 4: 400 бумаг по 10 на 1000 капитала, больше нельзя
+        let order = MarketOrder::new(Direction::Buy, 41);
+        let events =
+            book.post(action(Order::Market(MarketOrder::New(order))), 2);
+        let rejected = orders(&events)[0].clone().as_market().unwrap();
+        assert_eq!(rejected.as_rejected().unwrap().meta, MARGIN_REJECT);
+        let order = MarketOrder::new(Direction::Buy, 40);
+        book.post(action(Order::Market(MarketOrder::New(order))), 3);
+        assert_eq!(book.positions[&figi], 400);
+
+        let stop = StopOrder::new(
+            StopOrderKind::StopLoss,
+            Direction::Sell,
+            40,
+            5.0,
+            None,
+        );
+        book.post(action(Order::Stop(StopOrder::New(stop))), 4);
+
+        // по 8.5 капитал 400 < 425 минимальной маржи
+        assert!(book.market(&bar(9.0), 5).is_empty());
+        let events = orders(&book.market(&bar(8.5), 6));
+        assert!(events[0].is_canceled());
+        assert!(events[1].is_filled());
+        assert_eq!(book.positions[&figi], 0);
+        assert!(book.stop_orders.is_empty());
+        assert_eq!(book.cash, 400.0);
+    }
+    #[test]
+    fn imperfection() {
+        const MS: i64 = 1_000_000;
+        let mut book = PaperBook::new(100_000.0, 0.0);
+        book.market(&bar(100.0), 0);
+
+        book.set_imperfection(Imperfection {
+            reject: 1.0,
+            ..Default::default()
+        });
+        let order = MarketOrder::new(Direction::Buy, 1);
+        let events =
+            book.post(action(Order::Market(MarketOrder::New(order))), 1);
+        let rejected = orders(&events)[0].clone().as_market().unwrap();
+        let meta = &rejected.as_rejected().unwrap().meta;
+        assert_eq!(meta, SIMULATED_REJECT);
+
+        // реквот: новая цена хуже последней
+        book.set_imperfection(Imperfection {
+            requote: 1.0,
+            requote_steps: 1,
+            ..Default::default()
+        });
+        let order = MarketOrder::new(Direction::Buy, 1);
+        let events =
+            book.post(action(Order::Market(MarketOrder::New(order))), 1);
+        let rejected = orders(&events)[0].clone().as_market().unwrap();
+        let meta = &rejected.as_rejected().unwrap().meta;
+        assert_eq!(meta, &format!("{REQUOTE} 100.01"));
+
+        // подтверждение через 50 мс, исполнение после подтверждения
+        book.set_imperfection(Imperfection {
+            ack_delay: (50, 50),
+            ..Default::default()
+        });
+        let order = LimitOrder::new(Direction::Buy, 1, 99.0);
+        let events =
+            book.post(action(Order::Limit(LimitOrder::New(order))), 0);
+        assert!(events.is_empty() && book.has_acks());
+        assert!(book.market(&bar(98.0), 10 * MS).is_empty());
+        assert!(book.release(49 * MS).is_empty());
+
+        let events = book.release(50 * MS);
+        let orders = orders(&events);
+        assert!(orders[0].is_posted() && orders[1].is_filled());
+        assert!(!book.has_acks());
+    }
+    #[test]
+    fn session() {
+        let mut book = PaperBook::new(100_000.0, 0.001);
+        book.market(&bar(100.0), 1);
+        let order = MarketOrder::new(Direction::Buy, 10);
+        book.post(action(Order::Market(MarketOrder::New(order))), 2);
+        let order = StopOrder::new(
+            StopOrderKind::StopLoss,
+            Direction::Sell,
+            10,
+            95.0,
+            None,
+        );
+        book.post(action(Order::Stop(StopOrder::New(order))), 3);
+
+        // восстановленный счет совпадает с исходным
+        let session = book.session();
+        let mut restored = PaperBook::new(0.0, 0.0);
+        restored.restore(session.clone());
+        assert_eq!(restored.session(), session);
+        assert_eq!(session.position(iid().figi()), 100);
+        assert_eq!(session.orders().len(), 1);
+
+        // стоп срабатывает, новые ордера продолжают нумерацию
+        let events = restored.market(&bar(94.0), 5);
+        let orders = orders(&events);
+        assert!(orders[1].is_filled());
+        assert_eq!(orders[1].broker_id().unwrap(), "paper_3");
+        assert_eq!(restored.positions[iid().figi()], 0);
+    }
+}
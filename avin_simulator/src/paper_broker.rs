@@ -0,0 +1,329 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use avin_connect::TinkoffClient;
+use avin_core::{
+    Action, Clock, Event, GetAccountAction, GetBarsAction, Margin,
+    MarketData, RealClock, StreamAction, TimeFrame,
+};
+use avin_utils::{AvinError, CFG};
+
+use crate::paper_accounts::PaperAccounts;
+use crate::{Imperfection, Session};
+
+// Как часто брокер проверяет отложенные подтверждения
+const ACK_TICK: Duration = Duration::from_millis(10);
+// Глубина стакана, на которую подписывается брокер вместе с тиками
+const BOOK_DEPTH: i32 = 50;
+
+/// Paper trading broker: live market data, simulated execution.
+///
+/// # ru
+/// Брокер для бумажной торговли. Принимает те же действия и отправляет
+/// те же события, что и [`avin_connect::Tinkoff`], поэтому трейдер и
+/// стратегии работают с ним без изменений. Рыночные данные идут из
+/// живого потока Тинькофф, а ордера на биржу не отправляются - они
+/// исполняются на виртуальном счете по последней цене (тик или
+/// закрытие 1М бара):
+/// - рыночный ордер - сразу по последней цене, если цены еще нет -
+///   по первой пришедшей;
+/// - лимитный ордер - по последней цене, если он выставлен "в рынок",
+///   иначе по цене ордера, когда рынок до нее дойдет;
+/// - стоп ордер срабатывает, когда последняя цена доходит до стопа.
+///
+/// При подписке на тики брокер подписывается и на стакан. Когда стакан
+/// есть, лимитные ордера исполняются с учетом места в очереди уровня и
+/// объема сделок на нем, см. [`crate::QueuePosition`], в том числе частично.
+///
+/// Комиссия - процент от оборота, по умолчанию из конфига
+/// "tester.default_commission". Позволяет проверить стратегию вживую
+/// без риска, перед включением реального исполнения.
+///
+/// С моделью маржи [`Margin`] счет маржинальный, как у брокера:
+/// ордер, увеличивающий позицию, отклоняется без начальной маржи, а
+/// когда капитал падает ниже минимальной маржи по всем позициям -
+/// активные ордера отменяются и все позиции закрываются по рынку
+/// (margin call). Ставки риска берутся из информации об инструменте,
+/// если их там нет - из заданной модели.
+///
+/// Несовершенства настоящего брокера - случайные отклонения, реквоты
+/// и задержку подтверждений - включает [`PaperBroker::set_imperfection`].
+///
+/// Файл сессии [`PaperBroker::set_session`] хранит счет между
+/// перезапусками: при запуске счет восстанавливается из него, после
+/// каждого изменения по ордерам - сохраняется.
+pub struct PaperBroker {
+    event_tx: UnboundedSender<Event>,
+    action_rx: UnboundedReceiver<Action>,
+    data_rx: UnboundedReceiver<Event>,
+    client: TinkoffClient,
+    accounts: PaperAccounts,
+    session: Option<PathBuf>,
+}
+impl PaperBroker {
+    pub fn new(
+        action_rx: UnboundedReceiver<Action>,
+        event_tx: UnboundedSender<Event>,
+    ) -> Self {
+        // рыночные данные клиента идут сначала в бумажный счет
+        let (data_tx, data_rx) = mpsc::unbounded_channel();
+        let commission = CFG.tester.default_commission / 100.0;
+
+        Self {
+            event_tx,
+            action_rx,
+            data_rx,
+            client: TinkoffClient::new(data_tx),
+            accounts: PaperAccounts::new(0.0, commission),
+            session: None,
+        }
+    }
+    /// Set deposit of virtual accounts opened later.
+    ///
+    /// # ru
+    /// Устанавливает депозит виртуальных счетов, которые откроются
+    /// после вызова.
+    pub fn set_deposit(&mut self, deposit: f64) {
+        self.accounts.set_deposit(deposit);
+    }
+    /// Open virtual account with own deposit.
+    ///
+    /// # ru
+    /// Открывает виртуальный счет name со своим депозитом. Счета
+    /// независимы: у каждого свои деньги, позиции и ордера, а
+    /// рыночные данные общие. Ордер идет на счет по имени его
+    /// account, неизвестный счет открывается сам с депозитом
+    /// [`PaperBroker::set_deposit`]. Стратегии, привязанные к разным
+    /// счетам, можно сравнить на одних данных (A/B).
+    pub fn add_account(&mut self, name: &str, deposit: f64) {
+        self.accounts.add(name, deposit);
+    }
+    /// Set commission, share of turnover: 0.0005 == 0.05%.
+    ///
+    /// # ru
+    /// Устанавливает комиссию всех счетов, доля от оборота:
+    /// 0.0005 == 0.05%.
+    pub fn set_commission(&mut self, commission: f64) {
+        self.accounts.set_commission(commission);
+    }
+    /// Enable margin account.
+    ///
+    /// # ru
+    /// Включает маржинальные счета с моделью margin.
+    pub fn set_margin(&mut self, margin: Margin) {
+        self.accounts.set_margin(margin);
+    }
+    /// Enable random execution imperfections.
+    ///
+    /// # ru
+    /// Включает случайные отклонения, реквоты и задержку
+    /// подтверждений, см. [`Imperfection`].
+    pub fn set_imperfection(&mut self, imperfection: Imperfection) {
+        self.accounts.set_imperfection(imperfection);
+    }
+    /// Keep virtual account in session file, restore if file exists.
+    ///
+    /// # ru
+    /// Хранит виртуальный счет в файле сессии: если файл уже есть -
+    /// счет, позиции и активные ордера восстанавливаются из него
+    /// (вместо депозита, комиссии и маржи из настроек), дальше файл
+    /// перезаписывается после каждого изменения по ордерам.
+    pub fn set_session(&mut self, path: &Path) -> Result<(), AvinError> {
+        if path.exists() {
+            let session = Session::load(path)?;
+            log::info!("PaperBroker restore session from {}", session.dt());
+            self.accounts.restore(session);
+        }
+        self.session = Some(path.to_path_buf());
+
+        Ok(())
+    }
+    pub async fn connect(&mut self) -> Result<(), AvinError> {
+        self.client
+            .connect()
+            .await
+            .map_err(|e| AvinError::NotLoaded(e.to_string()))
+    }
+    pub async fn start(&mut self) {
+        log::info!(":: PaperBroker start, cash={}", self.accounts.deposit());
+
+        loop {
+            let has_acks = self.accounts.has_acks();
+            tokio::select! {
+                a = self.action_rx.recv() => match a {
+                    Some(a) => self.process_action(a).await,
+                    None => break,
+                },
+                e = self.data_rx.recv() => match e {
+                    Some(e) => self.process_event(e),
+                    None => break,
+                },
+                _ = tokio::time::sleep(ACK_TICK), if has_acks => {
+                    let events = self.accounts.release(RealClock.ts());
+                    self.send(events);
+                }
+            }
+        }
+    }
+
+    // private
+    async fn process_action(&mut self, a: Action) {
+        let events = match a {
+            Action::GetAccount(a) => {
+                self.get_account_action(a);
+                return;
+            }
+            Action::GetBars(a) => {
+                self.get_bars_action(a).await;
+                return;
+            }
+            Action::GetOrders(a) => {
+                let orders = self.accounts.orders(a.account.name(), &a.iid);
+                a.tx.send(orders).unwrap();
+                return;
+            }
+            Action::GetPosition(a) => {
+                let position =
+                    self.accounts.position(a.account.name(), &a.iid);
+                a.tx.send(position).unwrap();
+                return;
+            }
+            Action::GetOperations(a) => {
+                // история операций бумажных счетов не хранится
+                a.tx.send(Vec::new()).unwrap();
+                return;
+            }
+            Action::GetCashOperations(a) => {
+                // у бумажных счетов нет неторгового движения денег
+                a.tx.send(Vec::new()).unwrap();
+                return;
+            }
+            Action::Subscribe(a) => {
+                self.subscribe_action(a).await;
+                return;
+            }
+            Action::Post(a) => self.accounts.post(a, RealClock.ts()),
+            Action::Cancel(a) => self.accounts.cancel(a, RealClock.ts()),
+            Action::Unsubscribe(a) => {
+                self.unsubscribe_action(a).await;
+                return;
+            }
+            Action::TradeClosed(_) => unreachable!(),
+            Action::TradeOpened(_) => unreachable!(),
+            Action::Signal(_) => unreachable!(),
+            Action::Notify(_) => unreachable!(),
+        };
+
+        self.send(events);
+    }
+    fn process_event(&mut self, e: Event) {
+        // события по ордерам реального счета в бумажной торговле не нужны
+        if let Event::Order(_) = e {
+            return;
+        }
+
+        let events = self.accounts.market(&e, RealClock.ts());
+        // стакан подписан брокером для себя, трейдеру он не нужен
+        if !matches!(e, Event::Book(_)) {
+            self.event_tx.send(e).unwrap();
+        }
+        self.send(events);
+    }
+    fn send(&self, events: Vec<Event>) {
+        if events.is_empty() {
+            return;
+        }
+        for e in events {
+            self.event_tx.send(e).unwrap();
+        }
+
+        if let Some(path) = &self.session
+            && let Err(e) = self.accounts.session(RealClock.ts()).save(path)
+        {
+            log::error!("PaperBroker save session: {e}");
+        }
+    }
+    fn get_account_action(&mut self, a: GetAccountAction) {
+        let account = self.accounts.account(&a.name, RealClock.ts());
+
+        a.tx.send(account).unwrap();
+    }
+    async fn get_bars_action(&mut self, a: GetBarsAction) {
+        let bars = self
+            .client
+            .get_bars(&a.iid, a.tf, a.from, a.till)
+            .await
+            .unwrap();
+
+        a.tx.send(bars).unwrap();
+    }
+    async fn subscribe_action(&mut self, a: StreamAction) {
+        log::info!("PaperBroker.subscribe_action({a})");
+
+        for md in a.market_data_kinds {
+            let result = match md {
+                MarketData::TIC => {
+                    // стакан нужен для исполнения с учетом очереди
+                    let book =
+                        self.client.subscribe_book(&a.iid, BOOK_DEPTH).await;
+                    if let Err(e) = book {
+                        log::error!("PaperBroker.subscribe_action: {e}");
+                    }
+                    self.client.subscribe_tic(&a.iid).await
+                }
+                md => {
+                    let tf = TimeFrame::all()
+                        .into_iter()
+                        .find(|tf| tf.market_data() == md);
+                    let Some(tf) = tf else {
+                        log::error!("PaperBroker not provide {md} stream");
+                        continue;
+                    };
+                    self.client.subscribe_bar(&a.iid, &tf).await
+                }
+            };
+            if let Err(e) = result {
+                log::error!("PaperBroker.subscribe_action: {e}");
+            }
+        }
+    }
+    async fn unsubscribe_action(&mut self, a: StreamAction) {
+        log::info!("PaperBroker.unsubscribe_action({a})");
+
+        for md in a.market_data_kinds {
+            let result = match md {
+                MarketData::TIC => {
+                    let book = self
+                        .client
+                        .unsubscribe_book(&a.iid, BOOK_DEPTH)
+                        .await;
+                    if let Err(e) = book {
+                        log::error!("PaperBroker.unsubscribe_action: {e}");
+                    }
+                    self.client.unsubscribe_tic(&a.iid).await
+                }
+                md => {
+                    let tf = TimeFrame::all()
+                        .into_iter()
+                        .find(|tf| tf.market_data() == md);
+                    let Some(tf) = tf else {
+                        continue;
+                    };
+                    self.client.unsubscribe_bar(&a.iid, &tf).await
+                }
+            };
+            if let Err(e) = result {
+                log::error!("PaperBroker.unsubscribe_action: {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{Direction, Tic};
+
+/// Position of simulated limit order in the queue of its price level.
+///
+/// # ru
+/// Место симулируемого лимитного ордера в очереди заявок своего
+/// ценового уровня стакана. Вместо наивного "цена коснулась - ордер
+/// исполнен" ордер исполняется только после того, как сделки на его
+/// уровне съедят все заявки, стоявшие в очереди раньше него:
+/// - ордер встает в конец очереди: перед ним все лоты уровня;
+/// - при обновлении стакана очередь перед ордером может только
+///   уменьшиться (заявки снимают), выставленные позже встают за ним;
+/// - сделка по цене ордера в его сторону (продавец бьет в биды для
+///   покупки, покупатель в аски для продажи) уменьшает очередь, а
+///   объем сверх очереди исполняет ордер;
+/// - сделка хуже цены ордера (уровень пробит) исполняет его целиком.
+///
+/// Дает реалистичную долю исполнения пассивных ордеров для стратегий
+/// на лимитных заявках.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuePosition {
+    ahead: u32,
+}
+impl QueuePosition {
+    /// Order joins the back of level with lots resting.
+    ///
+    /// # ru
+    /// Ордер встает в конец уровня, на котором стоит lots лотов.
+    pub fn new(lots: u32) -> Self {
+        Self { ahead: lots }
+    }
+    /// Lots in the queue before the order.
+    ///
+    /// # ru
+    /// Количество лотов в очереди перед ордером.
+    pub fn ahead(&self) -> u32 {
+        self.ahead
+    }
+    /// Update by new lots of order level from order book.
+    ///
+    /// # ru
+    /// Обновление по стакану: lots - лоты на уровне ордера сейчас.
+    pub fn level(&mut self, lots: u32) {
+        self.ahead = self.ahead.min(lots);
+    }
+    /// Process trade, return lots of order filled.
+    ///
+    /// # ru
+    /// Обрабатывает сделку для ордера direction по цене price,
+    /// возвращает количество исполненных лотов ордера. Пробой уровня
+    /// возвращает u32::MAX - исполнить весь остаток.
+    pub fn trade(
+        &mut self,
+        direction: &Direction,
+        price: f64,
+        tic: &Tic,
+    ) -> u32 {
+        let (through, hit) = match direction {
+            Direction::Buy => (tic.price < price, Direction::Sell),
+            Direction::Sell => (tic.price > price, Direction::Buy),
+        };
+        if through {
+            self.ahead = 0;
+            return u32::MAX;
+        }
+        if tic.price != price || tic.direction != hit {
+            return 0;
+        }
+
+        let filled = tic.lots.saturating_sub(self.ahead);
+        self.ahead = self.ahead.saturating_sub(tic.lots);
+
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tic(direction: Direction, lots: u32, price: f64) -> Tic {
+        Tic::new(0, direction, lots, price, 0.0)
+    }
+
+    #[test]
+    fn queue() {
+        // покупка по 100, в очереди 50 лотов
+        let mut q = QueuePosition::new(50);
+        let buy = Direction::Buy;
+
+        // покупки и сделки выше цены не двигают очередь
+        assert_eq!(q.trade(&buy, 100.0, &tic(Direction::Buy, 30, 100.0)), 0);
+        assert_eq!(q.trade(&buy, 100.0, &tic(Direction::Sell, 9, 100.1)), 0);
+        assert_eq!(q.ahead(), 50);
+
+        // 30 лотов продали в уровень, 10 заявок сняли
+        assert_eq!(q.trade(&buy, 100.0, &tic(Direction::Sell, 30, 100.0)), 0);
+        q.level(10);
+        q.level(40);
+        assert_eq!(q.ahead(), 10);
+
+        // сделка съедает очередь, остаток исполняет ордер
+        assert_eq!(q.trade(&buy, 100.0, &tic(Direction::Sell, 14, 100.0)), 4);
+        assert_eq!(q.ahead(), 0);
+
+        // пробой уровня
+        let mut q = QueuePosition::new(1000);
+        let sell = Direction::Sell;
+        let filled = q.trade(&sell, 100.0, &tic(Direction::Buy, 1, 100.1));
+        assert_eq!(filled, u32::MAX);
+    }
+}
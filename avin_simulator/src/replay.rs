@@ -0,0 +1,901 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use avin_core::{
+    Action, Bar, BarEvent, Clock, Event, GetAccountAction, GetBarsAction,
+    Iid, LimitOrder, Manager, Margin, MarketData, MarketOrder, Order,
+    OrderAction, Tic, TicEvent, TimeFrame,
+};
+use avin_utils::{AvinError, CFG};
+
+use crate::paper_accounts::PaperAccounts;
+use crate::{
+    Command, Control, ControlStatus, Imperfection, Scenario, Session,
+};
+
+// Владелец и счет ордеров, выставленных с пульта управления
+const CONTROL: &str = "Control";
+// Максимальная пауза ожидания следующего события: чтобы пауза и
+// смена скорости применялись без заметной задержки
+const MAX_WAIT: Duration = Duration::from_millis(100);
+
+/// Speed of historical replay.
+///
+/// # ru
+/// Скорость воспроизведения истории относительно реального времени.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speed {
+    X1,
+    X10,
+    X100,
+    /// Без задержек - следующее событие сразу за предыдущим.
+    Max,
+}
+impl Speed {
+    pub fn all() -> [Speed; 4] {
+        [Speed::X1, Speed::X10, Speed::X100, Speed::Max]
+    }
+    /// Market seconds in one real second, None - max speed.
+    ///
+    /// # ru
+    /// Сколько секунд рынка проходит за секунду реального времени,
+    /// None - максимальная скорость.
+    pub fn factor(&self) -> Option<f64> {
+        match self {
+            Speed::X1 => Some(1.0),
+            Speed::X10 => Some(10.0),
+            Speed::X100 => Some(100.0),
+            Speed::Max => None,
+        }
+    }
+}
+impl std::fmt::Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Speed::X1 => write!(f, "1x"),
+            Speed::X10 => write!(f, "10x"),
+            Speed::X100 => write!(f, "100x"),
+            Speed::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// Controllable market clock of historical replay.
+///
+/// # ru
+/// Управляемые часы воспроизведения истории: время рынка идет от
+/// момента первого события со скоростью [`Speed`], его можно
+/// поставить на паузу и менять скорость на ходу. Клоны часов общие -
+/// GUI управляет часами, пока брокер воспроизводит по ним события.
+#[derive(Debug, Clone)]
+pub struct ReplayClock {
+    state: Arc<Mutex<ClockState>>,
+}
+impl ReplayClock {
+    pub fn new(speed: Speed) -> Self {
+        let state = ClockState {
+            speed,
+            paused: false,
+            market: None,
+            anchor: Instant::now(),
+        };
+
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+    pub fn speed(&self) -> Speed {
+        self.state.lock().unwrap().speed
+    }
+    pub fn set_speed(&self, speed: Speed) {
+        let mut state = self.state.lock().unwrap();
+        state.rebase();
+        state.speed = speed;
+    }
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.rebase();
+        state.paused = true;
+    }
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.anchor = Instant::now();
+        state.paused = false;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+    /// Check that clock synchronized with market.
+    ///
+    /// # ru
+    /// Проверка, что часы уже привязаны ко времени рынка.
+    pub fn is_started(&self) -> bool {
+        self.state.lock().unwrap().market.is_some()
+    }
+    /// Current market time, ts nanos. None - clock not started.
+    ///
+    /// # ru
+    /// Текущее время рынка, ts nanos. None - часы еще не привязаны к
+    /// рынку. На максимальной скорости время не ограничено: i64::MAX.
+    pub fn market(&self) -> Option<i64> {
+        self.state.lock().unwrap().now()
+    }
+    /// Set market time, clock continues from ts.
+    ///
+    /// # ru
+    /// Устанавливает время рынка, дальше часы идут от ts.
+    pub fn sync(&self, ts: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.market = Some(ts);
+        state.anchor = Instant::now();
+    }
+    /// Real time until market time ts at current speed.
+    ///
+    /// # ru
+    /// Сколько реального времени осталось до момента рынка ts при
+    /// текущей скорости. На паузе и до старта часов - None.
+    pub fn until(&self, ts: i64) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        if state.paused {
+            return None;
+        }
+        let Some(factor) = state.speed.factor() else {
+            return Some(Duration::ZERO);
+        };
+
+        let now = state.now()?;
+        let nanos = ((ts - now).max(0) as f64 / factor) as u64;
+
+        Some(Duration::from_nanos(nanos))
+    }
+}
+impl Clock for ReplayClock {
+    /// Market time of replay; at max speed - time of last released
+    /// event, before start - 0.
+    ///
+    /// # ru
+    /// Время рынка воспроизведения; на максимальной скорости - время
+    /// последнего отданного события, до старта - 0.
+    fn ts(&self) -> i64 {
+        let state = self.state.lock().unwrap();
+        match state.now() {
+            Some(i64::MAX) => state.market.unwrap(),
+            Some(ts) => ts,
+            None => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClockState {
+    speed: Speed,
+    paused: bool,
+    market: Option<i64>,
+    anchor: Instant,
+}
+impl ClockState {
+    fn now(&self) -> Option<i64> {
+        let market = self.market?;
+        if self.paused {
+            return Some(market);
+        }
+        let Some(factor) = self.speed.factor() else {
+            return Some(i64::MAX);
+        };
+
+        let elapsed = self.anchor.elapsed().as_nanos() as f64 * factor;
+
+        Some(market + elapsed as i64)
+    }
+    // фиксирует текущее время рынка перед сменой режима
+    fn rebase(&mut self) {
+        if self.speed.factor().is_some()
+            && let Some(now) = self.now()
+        {
+            self.market = Some(now);
+        }
+        self.anchor = Instant::now();
+    }
+}
+
+/// Historical market data stream with controllable clock.
+///
+/// # ru
+/// Воспроизведение сохраненных рыночных данных: 1М бары и тики
+/// отдаются обычными событиями [`Event`] в том темпе, в каком шли
+/// на бирже, ускоренно или без задержек, см. [`ReplayClock`]. Бар
+/// отдается в момент своего закрытия, тик - в момент сделки.
+#[derive(Debug)]
+pub struct Replay {
+    iid: Option<Iid>,
+    events: VecDeque<Event>,
+    clock: ReplayClock,
+}
+impl Replay {
+    /// Load 1M bars (and tics) of instrument for period [begin, end).
+    ///
+    /// # ru
+    /// Загружает 1М бары инструмента за период [begin, end), при
+    /// with_tics и тики.
+    pub fn new(
+        iid: &Iid,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+        with_tics: bool,
+    ) -> Result<Self, AvinError> {
+        let figi = iid.figi();
+        let mut events = Vec::new();
+
+        let df = Manager::load(iid, MarketData::BAR_1M, begin, end)?;
+        let bars = Bar::from_df(&df).map_err(AvinError::InvalidValue)?;
+        for bar in bars {
+            let e = BarEvent::new(figi.clone(), TimeFrame::M1, bar);
+            events.push(Event::Bar(e));
+        }
+
+        if with_tics {
+            let df = Manager::load(iid, MarketData::TIC, begin, end)?;
+            let tics = Tic::from_df(&df).map_err(AvinError::InvalidValue)?;
+            for tic in tics {
+                events.push(Event::Tic(TicEvent::new(figi.clone(), tic)));
+            }
+        }
+
+        let mut replay = Self::from_events(events, Speed::X1);
+        replay.iid = Some(iid.clone());
+
+        Ok(replay)
+    }
+    /// Replay of prepared events.
+    ///
+    /// # ru
+    /// Воспроизведение готовых событий, упорядочиваются по времени.
+    pub fn from_events(mut events: Vec<Event>, speed: Speed) -> Self {
+        events.sort_by_key(release_ts);
+
+        Self {
+            iid: None,
+            events: VecDeque::from(events),
+            clock: ReplayClock::new(speed),
+        }
+    }
+    /// Inject stress scenario into not yet replayed events.
+    ///
+    /// # ru
+    /// Накладывает стресс-сценарий на еще не воспроизведенные события,
+    /// см. [`Scenario`].
+    pub fn inject(&mut self, scenario: &Scenario) {
+        let events = self.events.drain(..).collect();
+        let mut events = scenario.apply(events);
+        events.sort_by_key(release_ts);
+
+        self.events = VecDeque::from(events);
+    }
+    /// Clock of replay, clones share control.
+    ///
+    /// # ru
+    /// Часы воспроизведения, клон управляет этими же часами.
+    pub fn clock(&self) -> ReplayClock {
+        self.clock.clone()
+    }
+    /// Instrument of replay, None - replay of prepared events.
+    ///
+    /// # ru
+    /// Инструмент воспроизведения, None - воспроизведение готовых
+    /// событий.
+    pub fn iid(&self) -> Option<&Iid> {
+        self.iid.as_ref()
+    }
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+    /// Jump forward to dt, return skipped events.
+    ///
+    /// # ru
+    /// Перемотка вперед: возвращает все события до момента dt, часы
+    /// продолжают идти от dt. Назад перемотать нельзя - события уже
+    /// отданы, тогда ничего не происходит.
+    pub fn jump(&mut self, dt: DateTime<Utc>) -> Vec<Event> {
+        let ts = dt.timestamp_nanos_opt().unwrap();
+        if self.clock.is_started() && self.clock.ts() >= ts {
+            log::warn!("Replay can't jump back to {dt}");
+            return Vec::new();
+        }
+
+        let mut skipped = Vec::new();
+        while let Some(e) = self.events.front()
+            && release_ts(e) <= ts
+        {
+            skipped.push(self.events.pop_front().unwrap());
+        }
+        self.clock.sync(ts);
+
+        skipped
+    }
+    /// Events due by replay clock.
+    ///
+    /// # ru
+    /// События, время которых по часам воспроизведения наступило. При
+    /// первом вызове часы привязываются к первому событию.
+    pub fn poll(&mut self) -> Vec<Event> {
+        let Some(first) = self.events.front() else {
+            return Vec::new();
+        };
+        if !self.clock.is_started() {
+            self.clock.sync(release_ts(first));
+        }
+
+        let now = self.clock.market().unwrap();
+        let mut due = Vec::new();
+        while let Some(e) = self.events.front()
+            && release_ts(e) <= now
+        {
+            due.push(self.events.pop_front().unwrap());
+        }
+
+        // на максимальной скорости часы идут по событиям, чтобы при
+        // переходе на обычную скорость продолжить с того же места
+        if self.clock.speed() == Speed::Max
+            && let Some(e) = due.last()
+        {
+            self.clock.sync(release_ts(e));
+        }
+
+        due
+    }
+    /// Real time until next event.
+    ///
+    /// # ru
+    /// Сколько ждать следующего события, не больше 100 мс - чтобы
+    /// вовремя заметить паузу или смену скорости.
+    pub fn wait(&self) -> Duration {
+        let next = self.events.front().map(release_ts);
+        let until = next.and_then(|ts| self.clock.until(ts));
+
+        until.unwrap_or(MAX_WAIT).min(MAX_WAIT)
+    }
+    /// Stream all events to channel in pace of replay clock.
+    ///
+    /// # ru
+    /// Отправляет все события в канал в темпе часов воспроизведения,
+    /// например в канал событий трейдера или GUI.
+    pub async fn run(mut self, tx: UnboundedSender<Event>) {
+        while !self.is_finished() {
+            for e in self.poll() {
+                if tx.send(e).is_err() {
+                    return;
+                }
+            }
+            tokio::time::sleep(self.wait()).await;
+        }
+    }
+}
+
+/// Broker replaying past session with simulated execution.
+///
+/// # ru
+/// Брокер воспроизведения прошлой сессии. Принимает те же действия и
+/// отправляет те же события, что и [`crate::PaperBroker`], но рыночные
+/// данные берутся из [`Replay`], а ордера исполняются на виртуальном
+/// счете по времени рынка. Так можно "прожить заново" прошлую сессию:
+/// трейдер, стратегии и GUI работают как вживую, а скорость и паузу
+/// задают часы [`Replay::clock`]. Когда данные кончаются, брокер
+/// завершает работу.
+pub struct ReplayBroker {
+    event_tx: UnboundedSender<Event>,
+    action_rx: UnboundedReceiver<Action>,
+    replay: Replay,
+    accounts: PaperAccounts,
+    control: Control,
+    control_rx: UnboundedReceiver<Command>,
+}
+impl ReplayBroker {
+    pub fn new(
+        action_rx: UnboundedReceiver<Action>,
+        event_tx: UnboundedSender<Event>,
+        replay: Replay,
+    ) -> Self {
+        let commission = CFG.tester.default_commission / 100.0;
+        let (control, control_rx) = Control::new();
+
+        Self {
+            event_tx,
+            action_rx,
+            replay,
+            accounts: PaperAccounts::new(0.0, commission),
+            control,
+            control_rx,
+        }
+    }
+    /// Set deposit of virtual accounts opened later.
+    ///
+    /// # ru
+    /// Устанавливает депозит виртуальных счетов, которые откроются
+    /// после вызова.
+    pub fn set_deposit(&mut self, deposit: f64) {
+        self.accounts.set_deposit(deposit);
+    }
+    /// Open virtual account with own deposit.
+    ///
+    /// # ru
+    /// Открывает виртуальный счет name со своим депозитом. Счета
+    /// независимы: у каждого свои деньги, позиции и ордера, а
+    /// рыночные данные общие. Ордер идет на счет по имени его
+    /// account, неизвестный счет открывается сам с депозитом
+    /// [`ReplayBroker::set_deposit`]. Стратегии, привязанные к разным
+    /// счетам, можно сравнить на одних данных (A/B).
+    pub fn add_account(&mut self, name: &str, deposit: f64) {
+        self.accounts.add(name, deposit);
+    }
+    /// Set commission, share of turnover: 0.0005 == 0.05%.
+    ///
+    /// # ru
+    /// Устанавливает комиссию всех счетов, доля от оборота:
+    /// 0.0005 == 0.05%.
+    pub fn set_commission(&mut self, commission: f64) {
+        self.accounts.set_commission(commission);
+    }
+    /// Enable margin account.
+    ///
+    /// # ru
+    /// Включает маржинальные счета с моделью margin.
+    pub fn set_margin(&mut self, margin: Margin) {
+        self.accounts.set_margin(margin);
+    }
+    /// Enable random execution imperfections.
+    ///
+    /// # ru
+    /// Включает случайные отклонения, реквоты и задержку
+    /// подтверждений, см. [`Imperfection`].
+    pub fn set_imperfection(&mut self, imperfection: Imperfection) {
+        self.accounts.set_imperfection(imperfection);
+    }
+    pub fn clock(&self) -> ReplayClock {
+        self.replay.clock()
+    }
+    /// Control of replay, see [`Control`].
+    ///
+    /// # ru
+    /// Пульт управления воспроизведением: пауза, скорость, перемотка,
+    /// ордера и состояние, см. [`Control`].
+    pub fn control(&self) -> Control {
+        self.control.clone()
+    }
+    /// Current state of virtual account and replay position.
+    ///
+    /// # ru
+    /// Текущее состояние виртуального счета и позиция
+    /// воспроизведения, см. [`Session`].
+    pub fn session(&self) -> Session {
+        self.accounts.session(self.now())
+    }
+    pub fn save(&self, path: &Path) -> Result<(), AvinError> {
+        self.session().save(path)
+    }
+    /// Restore saved session: account, orders and replay position.
+    ///
+    /// # ru
+    /// Восстанавливает сохраненную сессию: счет, активные ордера и
+    /// позицию воспроизведения - события до времени сохранения
+    /// пропускаются без отправки. Вызывается до запуска брокера, на
+    /// воспроизведении тех же данных, что и при сохранении.
+    pub fn restore(&mut self, session: Session) {
+        self.replay.jump(session.dt());
+        self.accounts.restore(session);
+    }
+    pub async fn start(&mut self) {
+        log::info!(":: ReplayBroker start, cash={}", self.accounts.deposit());
+
+        loop {
+            // действия раньше данных: ордер, выставленный до события,
+            // участвует в нем
+            loop {
+                match self.action_rx.try_recv() {
+                    Ok(a) => self.process_action(a),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            }
+            self.release();
+            if self.replay.is_finished() {
+                // неподтвержденные события отдаются в конце
+                let events = self.accounts.release(i64::MAX);
+                self.send(events);
+                log::info!(":: ReplayBroker replay finished");
+                for (name, account) in self.session().accounts() {
+                    log::info!("- account {name}, cash={}", account.cash());
+                }
+                break;
+            }
+
+            tokio::select! {
+                a = self.action_rx.recv() => match a {
+                    Some(a) => self.process_action(a),
+                    None => break,
+                },
+                Some(c) = self.control_rx.recv() => self.process_command(c),
+                _ = tokio::time::sleep(self.replay.wait()) => (),
+            }
+        }
+    }
+
+    // private
+    fn release(&mut self) {
+        let due = self.replay.poll();
+        self.play(due);
+    }
+    fn play(&mut self, market: Vec<Event>) {
+        for e in market {
+            let events = self.accounts.market(&e, release_ts(&e));
+            self.event_tx.send(e).unwrap();
+            self.send(events);
+        }
+        let events = self.accounts.release(self.now());
+        self.send(events);
+    }
+    fn process_command(&mut self, c: Command) {
+        let clock = self.replay.clock();
+        match c {
+            Command::Pause => clock.pause(),
+            Command::Resume => clock.resume(),
+            Command::Speed(speed) => clock.set_speed(speed),
+            Command::Jump(dt) => {
+                let skipped = self.replay.jump(dt);
+                self.play(skipped);
+            }
+            Command::Post(a) => self.process_action(Action::Post(*a)),
+            Command::Order {
+                direction,
+                lots,
+                price,
+            } => {
+                let Some(iid) = self.replay.iid().cloned() else {
+                    log::error!("ReplayBroker: order without instrument");
+                    return;
+                };
+                let order = match price {
+                    Some(price) => {
+                        let order = LimitOrder::new(direction, lots, price);
+                        Order::Limit(LimitOrder::New(order))
+                    }
+                    None => {
+                        let order = MarketOrder::new(direction, lots);
+                        Order::Market(MarketOrder::New(order))
+                    }
+                };
+                let account = self.accounts.account(CONTROL, self.now());
+                let a = OrderAction::new(account, iid, CONTROL, order);
+                self.process_action(Action::Post(a));
+            }
+            Command::Save(path) => {
+                if let Err(e) = self.save(&path) {
+                    log::error!("ReplayBroker save session: {e}");
+                }
+            }
+            Command::Status(tx) => {
+                let status = ControlStatus {
+                    dt: clock.now(),
+                    speed: clock.speed(),
+                    paused: clock.is_paused(),
+                    remaining: self.replay.remaining(),
+                };
+                let _ = tx.send(status);
+            }
+        }
+    }
+    fn process_action(&mut self, a: Action) {
+        let events = match a {
+            Action::GetAccount(a) => {
+                self.get_account_action(a);
+                return;
+            }
+            Action::GetBars(a) => {
+                self.get_bars_action(a);
+                return;
+            }
+            Action::GetOrders(a) => {
+                let orders = self.accounts.orders(a.account.name(), &a.iid);
+                a.tx.send(orders).unwrap();
+                return;
+            }
+            Action::GetPosition(a) => {
+                let position =
+                    self.accounts.position(a.account.name(), &a.iid);
+                a.tx.send(position).unwrap();
+                return;
+            }
+            Action::GetOperations(a) => {
+                // история операций бумажных счетов не хранится
+                a.tx.send(Vec::new()).unwrap();
+                return;
+            }
+            Action::GetCashOperations(a) => {
+                // у бумажных счетов нет неторгового движения денег
+                a.tx.send(Vec::new()).unwrap();
+                return;
+            }
+            Action::Subscribe(a) => {
+                // поток данных задан воспроизведением
+                log::info!("ReplayBroker.subscribe_action({a}) skip");
+                return;
+            }
+            Action::Post(a) => self.accounts.post(a, self.now()),
+            Action::Cancel(a) => self.accounts.cancel(a, self.now()),
+            Action::Unsubscribe(a) => {
+                log::info!("ReplayBroker.unsubscribe_action({a}) skip");
+                return;
+            }
+            Action::TradeClosed(_) => unreachable!(),
+            Action::TradeOpened(_) => unreachable!(),
+            Action::Signal(_) => unreachable!(),
+            Action::Notify(_) => unreachable!(),
+        };
+
+        self.send(events);
+    }
+    fn send(&self, events: Vec<Event>) {
+        for e in events {
+            self.event_tx.send(e).unwrap();
+        }
+    }
+    fn now(&self) -> i64 {
+        self.replay.clock().ts()
+    }
+    fn get_account_action(&mut self, a: GetAccountAction) {
+        let account = self.accounts.account(&a.name, self.now());
+
+        a.tx.send(account).unwrap();
+    }
+    fn get_bars_action(&mut self, a: GetBarsAction) {
+        let bars = Manager::load(&a.iid, a.tf.market_data(), a.from, a.till)
+            .and_then(|df| Bar::from_df(&df).map_err(AvinError::InvalidValue))
+            .unwrap_or_else(|e| {
+                log::error!("ReplayBroker.get_bars_action: {e}");
+                Vec::new()
+            });
+
+        a.tx.send(bars).unwrap();
+    }
+}
+
+/// Момент, когда событие появляется в потоке: бар - при закрытии,
+/// остальные события - в момент своего времени.
+fn release_ts(e: &Event) -> i64 {
+    match e {
+        Event::Bar(e) => e.bar.ts + e.tf.nanos(),
+        Event::Tic(e) => e.tic.ts,
+        Event::Book(e) => e.book.ts,
+        Event::Order(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::{self, iid};
+    use avin_core::{Direction, MarketOrder, Order, OrderAction, OrderEvent};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    const MIN: i64 = 60_000_000_000;
+
+    fn broker(
+        replay: Replay,
+        action_rx: UnboundedReceiver<Action>,
+        event_tx: UnboundedSender<Event>,
+    ) -> ReplayBroker {
+        // без конфига: счет создается напрямую
+        let (control, control_rx) = Control::new();
+
+        ReplayBroker {
+            event_tx,
+            action_rx,
+            replay,
+            accounts: PaperAccounts::new(100_000.0, 0.0),
+            control,
+            control_rx,
+        }
+    }
+    fn market_order(direction: Direction, lots: u32) -> OrderAction {
+        let account = avin_core::Account::new("Replay", "Paper_ID");
+        let order = MarketOrder::new(direction, lots);
+
+        OrderAction::new(
+            account,
+            iid(),
+            "test",
+            Order::Market(MarketOrder::New(order)),
+        )
+    }
+    fn fills(event_rx: &mut UnboundedReceiver<Event>) -> (usize, Vec<f64>) {
+        let mut bars = 0;
+        let mut prices = Vec::new();
+        while let Ok(e) = event_rx.try_recv() {
+            match e {
+                Event::Bar(_) => bars += 1,
+                Event::Order(OrderEvent {
+                    order: Order::Market(MarketOrder::Filled(o)),
+                    ..
+                }) => prices.push(o.transactions[0].price),
+                _ => (),
+            }
+        }
+
+        (bars, prices)
+    }
+    fn bar(ts: i64, c: f64) -> Event {
+        fixture::bar_event(Bar::new(ts, c, c, c, c, 100))
+    }
+
+    #[test]
+    fn clock() {
+        let clock = ReplayClock::new(Speed::X1);
+        assert!(!clock.is_started());
+        assert_eq!(clock.market(), None);
+
+        clock.sync(1000);
+        clock.pause();
+        assert!(clock.is_paused());
+        let paused = clock.market().unwrap();
+        assert!(paused >= 1000);
+        assert_eq!(clock.market(), Some(paused));
+        assert_eq!(clock.until(paused + MIN), None);
+
+        // клон управляет теми же часами
+        let clone = clock.clone();
+        clone.set_speed(Speed::Max);
+        assert_eq!(clock.speed(), Speed::Max);
+        assert_eq!(clock.market(), Some(paused));
+
+        clone.resume();
+        // как Clock часы показывают время последнего события
+        assert_eq!(clock.ts(), paused);
+        assert_eq!(clock.market(), Some(i64::MAX));
+        assert_eq!(clock.until(paused + MIN), Some(Duration::ZERO));
+
+        clone.set_speed(Speed::X10);
+        let wait = clock.until(clock.market().unwrap() + MIN).unwrap();
+        assert!(wait <= Duration::from_secs(6));
+        assert!(wait > Duration::from_secs(5));
+    }
+    #[test]
+    fn poll() {
+        let events = vec![bar(2 * MIN, 101.0), bar(MIN, 100.0)];
+        let mut replay = Replay::from_events(events, Speed::X1);
+
+        // бар 1 минуты отдается сразу, следующий - через минуту
+        let due = replay.poll();
+        assert_eq!(due.len(), 1);
+        assert!(!replay.is_finished());
+        assert!(replay.wait() <= MAX_WAIT);
+
+        replay.clock().set_speed(Speed::Max);
+        let due = replay.poll();
+        assert_eq!(due.len(), 1);
+        assert!(replay.is_finished());
+
+        // часы остались на последнем событии
+        replay.clock().set_speed(Speed::X1);
+        let now = replay.clock().market().unwrap();
+        assert!((3 * MIN..4 * MIN).contains(&now));
+    }
+    #[tokio::test]
+    async fn replay_broker() {
+        let events = vec![bar(0, 100.0), bar(MIN, 101.0)];
+        let mut replay = Replay::from_events(events, Speed::Max);
+        replay.clock().pause();
+        // первый бар уже воспроизведен, брокер продолжит со второго
+        replay.poll();
+        replay.clock().resume();
+
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let mut broker = broker(replay, action_rx, event_tx);
+
+        let a = market_order(Direction::Buy, 1);
+        action_tx.send(Action::Post(a)).unwrap();
+        broker.start().await;
+
+        let (_, prices) = fills(&mut event_rx);
+        assert_eq!(prices, [101.0]);
+    }
+    #[tokio::test]
+    async fn control() {
+        let events = (0..10).map(|i| bar(i * MIN, 100.0 + i as f64));
+        let replay = Replay::from_events(events.collect(), Speed::X1);
+        let (_action_tx, action_rx) = mpsc::unbounded_channel();
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let broker = broker(replay, action_rx, event_tx);
+        let control = broker.control();
+        let task = tokio::spawn(async move {
+            let mut broker = broker;
+            broker.start().await
+        });
+
+        // на старте сразу отдается первый бар, дальше - раз в минуту
+        control.pause().unwrap();
+        let status = control.status().await.unwrap();
+        assert!(status.paused);
+        assert_eq!(status.remaining, 9);
+
+        // перемотка: бары, закрытые к 5 минуте, отданы сразу
+        let dt = DateTime::from_timestamp_nanos(5 * MIN);
+        control.jump(dt).unwrap();
+        let status = control.status().await.unwrap();
+        assert_eq!(status.remaining, 5);
+        assert_eq!(status.dt, dt);
+
+        // ордер с пульта исполняется по последней цене
+        control.post(market_order(Direction::Buy, 1)).unwrap();
+        control.set_speed(Speed::Max).unwrap();
+        control.resume().unwrap();
+        task.await.unwrap();
+
+        let (bars, prices) = fills(&mut event_rx);
+        assert_eq!(bars, 10);
+        assert_eq!(prices, [104.0]);
+        assert!(control.pause().is_err());
+    }
+    #[test]
+    fn session() {
+        let events = || (0..10).map(|i| bar(i * MIN, 100.0 + i as f64));
+        let replay = Replay::from_events(events().collect(), Speed::Max);
+        let (_action_tx, action_rx) = mpsc::unbounded_channel();
+        let (event_tx, _event_rx) = mpsc::unbounded_channel();
+        let mut saved = broker(replay, action_rx, event_tx);
+
+        // позиция и лимитка, дальше сохранение на 4 минуте
+        let dt = DateTime::from_timestamp_nanos(4 * MIN);
+        saved.process_command(Command::Jump(dt));
+        saved.process_action(Action::Post(market_order(Direction::Buy, 2)));
+        let order = LimitOrder::new(Direction::Sell, 2, 106.0);
+        let a = market_order(Direction::Sell, 2);
+        let a = OrderAction::new(
+            a.account,
+            a.iid,
+            "Test",
+            Order::Limit(LimitOrder::New(order)),
+        );
+        saved.process_action(Action::Post(a));
+        let session = Session::from_bin(&saved.session().to_bin()).unwrap();
+        assert_eq!(session.dt(), dt);
+        let account = session.account("Replay").unwrap();
+        assert_eq!(account.position(iid().figi()), 20);
+        assert_eq!(account.orders().len(), 1);
+
+        // новый брокер продолжает с места сохранения
+        let replay = Replay::from_events(events().collect(), Speed::Max);
+        let (_action_tx, action_rx) = mpsc::unbounded_channel();
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let mut restored = broker(replay, action_rx, event_tx);
+        restored.restore(session.clone());
+        assert_eq!(restored.replay.remaining(), 6);
+        assert_eq!(restored.session(), session);
+
+        // лимитка исполняется на восстановленном счете
+        restored.release();
+        restored.replay.clock().resume();
+        restored.release();
+        let (bars, _) = fills(&mut event_rx);
+        assert_eq!(bars, 6);
+        let session = restored.session();
+        let account = session.account("Replay").unwrap();
+        assert_eq!(account.position(iid().figi()), 0);
+    }
+}
@@ -0,0 +1,383 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+
+use avin_core::{Bar, BarEvent, Event, TimeFrame};
+use avin_utils::{AvinError, MSK_OFFSET};
+
+/// Stress event of scenario.
+///
+/// # ru
+/// Стрессовое событие сценария.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shock {
+    /// Остановка торгов на minutes минут: данных за это время нет.
+    Halt { minutes: u32 },
+    /// Гэп: все цены с этого момента сдвигаются на долю, -0.08 == -8%.
+    Gap(f64),
+    /// Выброс: первый бар с этого момента получает тень до цены
+    /// открытия, сдвинутой на долю, перед первым тиком - сделка по
+    /// этой цене. Дальше цены прежние.
+    Spike(f64),
+    /// Исчезновение ликвидности на minutes минут: объемы баров,
+    /// сделок и стакана умножаются на долю share.
+    Liquidity { share: f64, minutes: u32 },
+}
+impl std::fmt::Display for Shock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Halt { minutes } => write!(f, "halt {minutes}m"),
+            Self::Gap(pct) => write!(f, "gap {}%", pct * 100.0),
+            Self::Spike(pct) => write!(f, "spike {}%", pct * 100.0),
+            Self::Liquidity { share, minutes } => {
+                write!(f, "liquidity {}% {minutes}m", share * 100.0)
+            }
+        }
+    }
+}
+
+/// Scripted stress scenario for replay and simulation.
+///
+/// # ru
+/// Сценарий стрессовых событий: остановка торгов, гэп, выброс цены,
+/// исчезновение ликвидности в заданные моменты. Сценарий
+/// накладывается на поток исторических данных
+/// ([`crate::Replay::inject`], [`crate::Simulator::inject`]), так
+/// риск-логику и стопы можно проверить на событиях, которых почти нет
+/// в сохраненной истории.
+///
+/// Текстовый вид - по событию на строку, время московское:
+/// ```text
+/// 2024-03-01 14:00 halt 30m
+/// 2024-03-04 10:00 gap -8%
+/// 2024-03-05 12:30 spike +5%
+/// 2024-03-06 11:00 liquidity 10% 60m
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    shocks: Vec<(DateTime<Utc>, Shock)>,
+}
+impl Scenario {
+    pub fn new() -> Self {
+        Self { shocks: Vec::new() }
+    }
+    pub fn add(&mut self, dt: DateTime<Utc>, shock: Shock) {
+        self.shocks.push((dt, shock));
+        self.shocks.sort_by_key(|(dt, _)| *dt);
+    }
+    pub fn shocks(&self) -> &Vec<(DateTime<Utc>, Shock)> {
+        &self.shocks
+    }
+    /// Apply scenario to market data events.
+    ///
+    /// # ru
+    /// Накладывает сценарий на события рыночных данных, события должны
+    /// быть упорядочены по времени. Ордерные события не меняются.
+    pub fn apply(&self, events: Vec<Event>) -> Vec<Event> {
+        let mut events = events;
+        for (dt, shock) in self.shocks.iter() {
+            let ts = dt.timestamp_nanos_opt().unwrap();
+            events = match shock {
+                Shock::Halt { minutes } => {
+                    let end = ts + minutes_nanos(*minutes);
+                    events
+                        .into_iter()
+                        .filter(|e| !(ts..end).contains(&event_ts(e)))
+                        .collect()
+                }
+                Shock::Gap(pct) => {
+                    for e in events.iter_mut() {
+                        if event_ts(e) >= ts {
+                            scale_prices(e, 1.0 + pct);
+                        }
+                    }
+                    events
+                }
+                Shock::Spike(pct) => spike(events, ts, *pct),
+                Shock::Liquidity { share, minutes } => {
+                    let end = ts + minutes_nanos(*minutes);
+                    let mut events = events;
+                    for e in events.iter_mut() {
+                        if (ts..end).contains(&event_ts(e)) {
+                            scale_volume(e, *share);
+                        }
+                    }
+                    // сделки, от которых ничего не осталось, пропадают
+                    events.retain(
+                        |e| !matches!(e, Event::Tic(t) if t.tic.lots == 0),
+                    );
+                    events
+                }
+            };
+        }
+
+        events
+    }
+    /// Apply scenario to 1M bars.
+    ///
+    /// # ru
+    /// Накладывает сценарий на 1М бары инструмента.
+    pub fn apply_bars(&self, bars: Vec<Bar>) -> Vec<Bar> {
+        let events = bars
+            .into_iter()
+            .map(|bar| {
+                Event::Bar(BarEvent::new(String::new(), TimeFrame::M1, bar))
+            })
+            .collect();
+
+        self.apply(events)
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::Bar(e) => Some(e.bar),
+                _ => None,
+            })
+            .collect()
+    }
+}
+impl TryFrom<&str> for Scenario {
+    type Error = AvinError;
+
+    /// Parse scenario text, one shock per line, Moscow time.
+    ///
+    /// # ru
+    /// Создает сценарий из текста: по событию на строку, пустые строки
+    /// и строки с "#" пропускаются.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut scenario = Scenario::new();
+
+        for line in value.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = || AvinError::InvalidValue(format!("scenario {line}"));
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 3 {
+                return Err(err());
+            }
+            let dt = format!("{} {}", parts[0], parts[1]);
+            let dt = NaiveDateTime::parse_from_str(&dt, "%Y-%m-%d %H:%M")
+                .map_err(|_| err())?;
+            let dt = (dt - MSK_OFFSET).and_utc();
+
+            let args = &parts[3..];
+            let shock = match (parts[2], args) {
+                ("halt", [m]) => Shock::Halt {
+                    minutes: parse_minutes(m).ok_or_else(err)?,
+                },
+                ("gap", [p]) => Shock::Gap(parse_pct(p).ok_or_else(err)?),
+                ("spike", [p]) => Shock::Spike(parse_pct(p).ok_or_else(err)?),
+                ("liquidity", [p, m]) => Shock::Liquidity {
+                    share: parse_pct(p).ok_or_else(err)?,
+                    minutes: parse_minutes(m).ok_or_else(err)?,
+                },
+                _ => return Err(err()),
+            };
+
+            scenario.add(dt, shock);
+        }
+
+        Ok(scenario)
+    }
+}
+
+/// Время события для сценария: бар - время открытия.
+fn event_ts(e: &Event) -> i64 {
+    match e {
+        Event::Bar(e) => e.bar.ts,
+        Event::Tic(e) => e.tic.ts,
+        Event::Book(e) => e.book.ts,
+        Event::Order(_) => i64::MIN,
+    }
+}
+fn minutes_nanos(minutes: u32) -> i64 {
+    TimeDelta::minutes(minutes as i64)
+        .num_nanoseconds()
+        .unwrap()
+}
+fn scale_prices(e: &mut Event, k: f64) {
+    match e {
+        Event::Bar(e) => {
+            e.bar.o *= k;
+            e.bar.h *= k;
+            e.bar.l *= k;
+            e.bar.c *= k;
+        }
+        Event::Tic(e) => {
+            e.tic.price *= k;
+            e.tic.value *= k;
+        }
+        Event::Book(e) => {
+            let book = &mut e.book;
+            for (price, _) in book.bids.iter_mut().chain(book.asks.iter_mut())
+            {
+                *price *= k;
+            }
+        }
+        Event::Order(_) => (),
+    }
+}
+fn scale_volume(e: &mut Event, share: f64) {
+    match e {
+        Event::Bar(e) => e.bar.v = (e.bar.v as f64 * share) as u64,
+        Event::Tic(e) => {
+            let lots = (e.tic.lots as f64 * share) as u32;
+            e.tic.value *= lots as f64 / e.tic.lots as f64;
+            e.tic.lots = lots;
+        }
+        Event::Book(e) => {
+            let book = &mut e.book;
+            for (_, lots) in book.bids.iter_mut().chain(book.asks.iter_mut())
+            {
+                *lots = (*lots as f64 * share) as u32;
+            }
+        }
+        Event::Order(_) => (),
+    }
+}
+/// Выброс на первом баре и первом тике не раньше ts.
+fn spike(mut events: Vec<Event>, ts: i64, pct: f64) -> Vec<Event> {
+    if let Some(Event::Bar(e)) = events
+        .iter_mut()
+        .find(|e| matches!(e, Event::Bar(_)) && event_ts(e) >= ts)
+    {
+        let price = e.bar.o * (1.0 + pct);
+        e.bar.h = e.bar.h.max(price);
+        e.bar.l = e.bar.l.min(price);
+    }
+
+    let first_tic = events
+        .iter()
+        .position(|e| matches!(e, Event::Tic(_)) && event_ts(e) >= ts);
+    if let Some(i) = first_tic
+        && let Event::Tic(e) = &events[i]
+    {
+        let mut spike = e.clone();
+        spike.tic.value *= 1.0 + pct;
+        spike.tic.price *= 1.0 + pct;
+        events.insert(i, Event::Tic(spike));
+    }
+
+    events
+}
+/// "+5%", "-8%", "10%" -> доля.
+fn parse_pct(s: &str) -> Option<f64> {
+    let pct: f64 = s.strip_suffix('%')?.parse().ok()?;
+
+    Some(pct / 100.0)
+}
+/// "30m" -> минуты.
+fn parse_minutes(s: &str) -> Option<u32> {
+    s.strip_suffix('m')?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::{Direction, Tic, TicEvent};
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const MIN: i64 = 60_000_000_000;
+
+    fn bar(ts: i64, price: f64) -> Event {
+        let bar = Bar::new(ts, price, price + 1.0, price - 1.0, price, 1000);
+
+        Event::Bar(BarEvent::new("figi".to_string(), TimeFrame::M1, bar))
+    }
+    fn tic(ts: i64, price: f64) -> Event {
+        let tic = Tic::new(ts, Direction::Buy, 10, price, price * 10.0);
+
+        Event::Tic(TicEvent::new("figi".to_string(), tic))
+    }
+    fn at(ts: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(ts)
+    }
+    fn bars(events: &[Event]) -> Vec<Bar> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Bar(e) => Some(e.bar),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn halt_and_gap() {
+        let events: Vec<Event> =
+            (0..10).map(|i| bar(i * MIN, 100.0)).collect();
+
+        let mut scenario = Scenario::new();
+        scenario.add(at(2 * MIN), Shock::Halt { minutes: 3 });
+        scenario.add(at(7 * MIN), Shock::Gap(-0.08));
+        let result = bars(&scenario.apply(events));
+
+        let ts: Vec<i64> = result.iter().map(|b| b.ts / MIN).collect();
+        assert_eq!(ts, [0, 1, 5, 6, 7, 8, 9]);
+        assert_eq!(result[3].c, 100.0);
+        assert!((result[4].c - 92.0).abs() < 1e-9);
+        assert!((result[6].o - 92.0).abs() < 1e-9);
+    }
+    #[test]
+    fn spike_and_liquidity() {
+        let events = vec![bar(0, 100.0), tic(MIN, 100.0), bar(MIN, 100.0)];
+
+        let mut scenario = Scenario::new();
+        scenario.add(at(MIN), Shock::Spike(0.05));
+        scenario.add(
+            at(0),
+            Shock::Liquidity {
+                share: 0.1,
+                minutes: 1,
+            },
+        );
+        let result = scenario.apply(events);
+
+        let b = bars(&result);
+        assert_eq!(b[0].v, 100);
+        assert_eq!(b[0].h, 101.0);
+        assert_eq!(b[1].v, 1000);
+        assert!((b[1].h - 105.0).abs() < 1e-9);
+
+        // перед первым тиком - сделка по цене выброса
+        let prices: Vec<f64> = result
+            .iter()
+            .filter_map(|e| match e {
+                Event::Tic(e) => Some(e.tic.price),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(prices.len(), 2);
+        assert!((prices[0] - 105.0).abs() < 1e-9);
+        assert_eq!(prices[1], 100.0);
+    }
+    #[test]
+    fn parse() {
+        let text = "
+            # стресс-тест
+            2024-03-01 14:00 halt 30m
+            2024-03-04 10:00 gap -8%
+            2024-03-06 11:00 liquidity 10% 60m
+        ";
+        let scenario = Scenario::try_from(text).unwrap();
+        let shocks = scenario.shocks();
+        assert_eq!(shocks.len(), 3);
+        assert_eq!(
+            shocks[0].0,
+            Utc.with_ymd_and_hms(2024, 3, 1, 11, 0, 0).unwrap()
+        );
+        assert_eq!(shocks[0].1, Shock::Halt { minutes: 30 });
+        assert_eq!(shocks[1].1, Shock::Gap(-0.08));
+        assert_eq!(shocks[1].1.to_string(), "gap -8%");
+
+        assert!(Scenario::try_from("2024-03-01 14:00 halt").is_err());
+        assert!(Scenario::try_from("2024-03-01 halt 30m").is_err());
+        assert!(Scenario::try_from("2024-03-01 14:00 crash 5%").is_err());
+    }
+}
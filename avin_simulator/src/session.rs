@@ -0,0 +1,155 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::Path;
+
+use bitcode::{Decode, Encode};
+use chrono::{DateTime, Utc};
+
+use avin_core::{Iid, Margin, Order};
+use avin_utils::{AvinError, Cmd};
+
+/// Saved state of simulated accounts.
+///
+/// # ru
+/// Сохраненное состояние симулятора: все виртуальные счета
+/// [`SessionAccount`] и позиция воспроизведения - время рынка в
+/// момент сохранения. Нужно, чтобы долгий эксперимент бумажной
+/// торговли пережил перезапуск, см. [`crate::PaperBroker::set_session`]
+/// и [`crate::ReplayBroker::restore`].
+///
+/// Не сохраняются: стаканы (придут с рынка заново), настройки
+/// несовершенств исполнения и еще не отправленные задержанные
+/// подтверждения - их состояние уже учтено в счетах и ордерах.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Session {
+    pub(crate) ts: i64,
+    pub(crate) accounts: Vec<(String, SessionAccount)>,
+}
+impl Session {
+    pub fn from_bin(bytes: &[u8]) -> Result<Self, AvinError> {
+        bitcode::decode(bytes)
+            .map_err(|e| AvinError::InvalidValue(format!("session: {e}")))
+    }
+    pub fn to_bin(&self) -> Vec<u8> {
+        bitcode::encode(self)
+    }
+    pub fn save(&self, path: &Path) -> Result<(), AvinError> {
+        Cmd::write_bin(&self.to_bin(), path)
+    }
+    pub fn load(path: &Path) -> Result<Self, AvinError> {
+        if !Cmd::is_exist(path) {
+            return Err(AvinError::NotFound(path.display().to_string()));
+        }
+        let bytes = Cmd::read_bin(path)?;
+
+        Session::from_bin(&bytes)
+    }
+
+    /// Market time of save.
+    ///
+    /// # ru
+    /// Время рынка в момент сохранения.
+    pub fn dt(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(self.ts)
+    }
+    /// Accounts sorted by name.
+    ///
+    /// # ru
+    /// Счета и их имена, упорядочены по имени.
+    pub fn accounts(&self) -> &[(String, SessionAccount)] {
+        &self.accounts
+    }
+    pub fn account(&self, name: &str) -> Option<&SessionAccount> {
+        self.accounts
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, account)| account)
+    }
+}
+
+/// Saved state of one simulated account.
+///
+/// # ru
+/// Сохраненное состояние одного виртуального счета: деньги, комиссия,
+/// маржа, позиции и активные ордера с местом в очереди.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct SessionAccount {
+    pub(crate) cash: f64,
+    pub(crate) commission: f64,
+    pub(crate) margin: Option<Margin>,
+    pub(crate) positions: Vec<(String, i64)>,
+    pub(crate) prices: Vec<(String, f64)>,
+    /// Владелец последней сделки по инструменту.
+    pub(crate) owners: Vec<(String, Origin)>,
+    pub(crate) orders: Vec<(Origin, Order)>,
+    /// Место в очереди лимитных ордеров по broker_id.
+    pub(crate) queues: Vec<(String, u32)>,
+    pub(crate) next_id: u64,
+}
+impl SessionAccount {
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+    /// Position in units (not lots) by figi.
+    ///
+    /// # ru
+    /// Позиция по figi в штуках (не лотах), 0 - позиции нет.
+    pub fn position(&self, figi: &str) -> i64 {
+        self.positions
+            .iter()
+            .find(|(f, _)| f == figi)
+            .map_or(0, |(_, pos)| *pos)
+    }
+    /// Active orders.
+    ///
+    /// # ru
+    /// Активные ордера счета.
+    pub fn orders(&self) -> Vec<&Order> {
+        self.orders.iter().map(|(_, order)| order).collect()
+    }
+}
+
+/// Откуда ордер: счет, инструмент и владелец - все, что нужно для
+/// событий по нему после восстановления.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub(crate) struct Origin {
+    pub account: String,
+    pub account_id: String,
+    pub iid: Iid,
+    pub owner: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::paper_accounts::PaperAccounts;
+
+    use super::*;
+
+    #[test]
+    fn save_load() {
+        let mut accounts = PaperAccounts::new(100_000.0, 0.001);
+        accounts.set_margin(Margin::new(0.25, 0.25, 0.2));
+        accounts.add("A", 50_000.0);
+        accounts.add("B", 100_000.0);
+        let session = accounts.session(100500);
+
+        let path = Path::new("tmp/paper_session.bin");
+        session.save(path).unwrap();
+        let loaded = Session::load(path).unwrap();
+        assert_eq!(loaded, session);
+        assert_eq!(loaded.accounts().len(), 2);
+        assert_eq!(loaded.account("A").unwrap().cash(), 50_000.0);
+        assert!(loaded.account("C").is_none());
+        std::fs::remove_file(path).unwrap();
+
+        assert!(Session::load(path).is_err());
+        assert!(Session::from_bin(&[1, 2, 3]).is_err());
+    }
+}
@@ -9,16 +9,23 @@ use std::collections::VecDeque;
 
 use avin_analyse::TrendAnalytic;
 use avin_core::{
-    Asset, Bar, BarEvent, ExtremumIndicator, Iid, Manager, MarketData,
+    Asset, Bar, BarEvent, Clock, ExtremumIndicator, Iid, Manager, MarketData,
     TimeFrame,
 };
 use chrono::{DateTime, Utc};
 
+use crate::{Command, ControlStatus, ReplayClock, Scenario, Speed};
+
+// Сколько баров максимум добавляется за один вызов update, чтобы на
+// максимальной скорости GUI не зависал на весь период
+const MAX_BARS_PER_UPDATE: usize = 1000;
+
 pub struct Simulator {
     asset: Asset,
     begin: DateTime<Utc>,
     end: DateTime<Utc>,
     bars_1m: VecDeque<Bar>,
+    clock: ReplayClock,
 }
 impl Simulator {
     pub fn new(iid: &Iid, begin: DateTime<Utc>, end: DateTime<Utc>) -> Self {
@@ -36,6 +43,7 @@ impl Simulator {
             begin,
             end,
             bars_1m: load_bars(iid, begin, end),
+            clock: paused_clock(),
         }
     }
 
@@ -72,18 +80,137 @@ impl Simulator {
     }
     pub fn next_bar(&mut self) -> Option<&Asset> {
         let bar = self.bars_1m.pop_front()?;
+        if self.clock.is_paused() {
+            self.clock.sync(bar.ts + TimeFrame::M1.nanos());
+        }
         let figi = self.asset.figi().clone();
         let e = BarEvent::new(figi, TimeFrame::M1, bar);
         self.asset.bar_event(e);
 
         Some(&self.asset)
     }
+    /// Inject stress scenario into not yet added bars.
+    ///
+    /// # ru
+    /// Накладывает стресс-сценарий на еще не добавленные бары, см.
+    /// [`Scenario`]. После restart сценарий нужно наложить заново.
+    pub fn inject(&mut self, scenario: &Scenario) {
+        let bars = self.bars_1m.drain(..).collect();
+        self.bars_1m = VecDeque::from(scenario.apply_bars(bars));
+    }
     pub fn restart(&mut self) {
         self.asset.clear();
         self.bars_1m = load_bars(self.asset.iid(), self.begin, self.end);
+        self.clock = paused_clock();
+    }
+
+    /// Start replay of bars in pace of clock.
+    ///
+    /// # ru
+    /// Запускает воспроизведение: бары добавляются по часам
+    /// [`ReplayClock`] при вызовах [`Simulator::update`].
+    pub fn play(&mut self) {
+        self.clock.resume();
+    }
+    pub fn pause(&mut self) {
+        self.clock.pause();
+    }
+    pub fn is_playing(&self) -> bool {
+        !self.clock.is_paused()
+    }
+    pub fn speed(&self) -> Speed {
+        self.clock.speed()
+    }
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.clock.set_speed(speed);
+    }
+    /// Execute control command, see [`Command`].
+    ///
+    /// # ru
+    /// Выполняет команду управления, те же команды принимает
+    /// [`crate::ReplayBroker`]. Ордеров и счета у симулятора нет,
+    /// команды ордеров и сохранения пропускаются с ошибкой в логе.
+    pub fn execute(&mut self, command: Command) {
+        match command {
+            Command::Pause => self.pause(),
+            Command::Resume => self.play(),
+            Command::Speed(speed) => self.set_speed(speed),
+            Command::Jump(dt) => self.jump(dt),
+            Command::Post(_) | Command::Order { .. } => {
+                log::error!("Simulator has no broker, order skipped");
+            }
+            Command::Save(_) => {
+                log::error!("Simulator has no account, save skipped");
+            }
+            Command::Status(tx) => {
+                let status = ControlStatus {
+                    dt: self.clock.now(),
+                    speed: self.speed(),
+                    paused: !self.is_playing(),
+                    remaining: self.bars_1m.len(),
+                };
+                let _ = tx.send(status);
+            }
+        }
+    }
+    /// Add all bars closed by dt.
+    ///
+    /// # ru
+    /// Перемотка вперед: добавляет все бары, закрытые к моменту dt,
+    /// часы продолжают идти от dt.
+    pub fn jump(&mut self, dt: DateTime<Utc>) {
+        let ts = dt.timestamp_nanos_opt().unwrap();
+        if self.clock.is_started() && self.clock.ts() >= ts {
+            log::warn!("Simulator can't jump back to {dt}");
+            return;
+        }
+
+        while let Some(bar) = self.bars_1m.front()
+            && bar.ts + TimeFrame::M1.nanos() <= ts
+        {
+            self.next_bar();
+        }
+        self.clock.sync(ts);
+    }
+    /// Add bars closed by replay clock, return true if any added.
+    ///
+    /// # ru
+    /// Добавляет бары, закрывшиеся по часам воспроизведения, к
+    /// текущему времени. GUI вызывает на каждом кадре. Ручной шаг
+    /// [`Simulator::step`] сдвигает часы на время последнего бара.
+    pub fn update(&mut self) -> bool {
+        let Some(bar) = self.bars_1m.front() else {
+            self.clock.pause();
+            return false;
+        };
+        if !self.clock.is_started() {
+            self.clock.sync(bar.ts);
+        }
+
+        let now = self.clock.market().unwrap();
+        let mut count = 0;
+        while count < MAX_BARS_PER_UPDATE
+            && let Some(bar) = self.bars_1m.front()
+            && bar.ts + TimeFrame::M1.nanos() <= now
+        {
+            let ts = bar.ts;
+            self.next_bar();
+            count += 1;
+            if self.speed() == Speed::Max {
+                self.clock.sync(ts + TimeFrame::M1.nanos());
+            }
+        }
+
+        count > 0
     }
 }
 
+fn paused_clock() -> ReplayClock {
+    let clock = ReplayClock::new(Speed::X1);
+    clock.pause();
+
+    clock
+}
 fn load_bars(iid: &Iid, b: DateTime<Utc>, e: DateTime<Utc>) -> VecDeque<Bar> {
     let df = Manager::load(iid, MarketData::BAR_1M, b, e).unwrap();
     let vec_bars = Bar::from_df(&df).unwrap();
@@ -122,7 +249,7 @@ mod tests {
         let expect_ts = expect_dt.timestamp_nanos_opt().unwrap();
         let expect_bar =
             Bar::new(expect_ts, 267.52, 267.61, 266.84, 267.07, 1304400);
-        assert_eq!(*now_bar, expect_bar);
+        assert_eq!(now_bar, expect_bar);
     }
     #[test]
     fn simulate_1m_step() {
@@ -148,7 +275,7 @@ mod tests {
         let expect_ts = expect_dt.timestamp_nanos_opt().unwrap();
         let expect_bar =
             Bar::new(expect_ts, 267.52, 267.61, 266.84, 267.07, 1304400);
-        assert_eq!(*now_bar, expect_bar);
+        assert_eq!(now_bar, expect_bar);
     }
     #[test]
     fn simulate_10m() {
@@ -169,7 +296,7 @@ mod tests {
         let expect_ts = expect_dt.timestamp_nanos_opt().unwrap();
         let expect_bar =
             Bar::new(expect_ts, 267.29, 267.35, 266.33, 266.5, 521050);
-        assert_eq!(*now_bar, expect_bar);
+        assert_eq!(now_bar, expect_bar);
     }
     #[test]
     fn simulate_1h() {
@@ -190,6 +317,6 @@ mod tests {
         let expect_ts = expect_dt.timestamp_nanos_opt().unwrap();
         let expect_bar =
             Bar::new(expect_ts, 267.89, 268.39, 267.83, 268.31, 245660);
-        assert_eq!(*now_bar, expect_bar);
+        assert_eq!(now_bar, expect_bar);
     }
 }
@@ -0,0 +1,77 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! ABI string of strategy plugins, see PLUGIN_ABI in src/plugin.rs.
+//!
+//! # ru
+//! Строка ABI плагинов стратегий: версия avin, версия rustc, целевая
+//! платформа, профиль сборки и хэш исходников крейтов, типы которых
+//! проходят через границу плагина, и Cargo.lock. Плагин и трейдер
+//! совместимы, только если строки совпадают.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Крейты, типы которых плагин и трейдер передают друг другу
+const CRATES: [&str; 3] = ["avin_core", "avin_strategy", "avin_utils"];
+
+fn main() {
+    let manifest = PathBuf::from(env("CARGO_MANIFEST_DIR"));
+    let root = manifest.parent().unwrap().to_path_buf();
+
+    let mut files = Vec::new();
+    for name in CRATES {
+        let dir = root.join(name).join("src");
+        println!("cargo:rerun-if-changed={}", dir.display());
+        collect(&dir, &mut files);
+    }
+    let lock = root.join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock.display());
+    files.push(lock);
+    files.sort();
+
+    // FNV-1a 64: пути относительно корня и содержимое файлов
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for path in files.iter() {
+        let name = path.strip_prefix(&root).unwrap_or(path);
+        let data = std::fs::read(path).unwrap_or_default();
+        for byte in name.to_string_lossy().bytes().chain(data) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    let rustc = Command::new(env("RUSTC"))
+        .arg("-V")
+        .output()
+        .map(|i| String::from_utf8_lossy(&i.stdout).trim().to_string())
+        .unwrap_or_else(|_| "rustc unknown".to_string());
+    let abi = format!(
+        "avin-{} {rustc} {} {} src-{hash:016x}",
+        env("CARGO_PKG_VERSION"),
+        env("TARGET"),
+        env("PROFILE"),
+    );
+    println!("cargo:rustc-env=AVIN_PLUGIN_ABI={abi}");
+}
+
+fn env(name: &str) -> String {
+    std::env::var(name).unwrap_or_default()
+}
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else if path.extension().is_some_and(|i| i == "rs") {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Strategy plugin for trader.
+//!
+//! # ru
+//! Плагин стратегий для трейдера. Сборка:
+//! `cargo build --release --example plugin`, файл библиотеки
+//! `target/release/examples/libplugin.so` указывается в конфиге:
+//! `[trader] plugins = ["plugins/libplugin.so"]`, имена стратегий
+//! плагина - в списке работ. После пересборки и замены файла трейдер
+//! перезагружает стратегии плагина сам.
+
+use avin_strategy::{Executor, Grid, GridSide, MaCross};
+
+avin_strategy::plugin! {
+    "MaCross" => Executor::<MaCross>::default(),
+    "GridLong" => Grid::new(GridSide::Long),
+}
@@ -5,17 +5,141 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+use std::sync::Arc;
+
 use avin_core::{
-    Account, Action, Asset, Direction, LimitOrder, Order, OrderEvent,
+    Account, Action, Asset, Clock, Direction, LimitOrder, MarketData,
+    NotifyAction, NotifyLevel, Order, OrderEvent, TimeFrame,
 };
 
+use crate::{ParamSet, StrategyMeta};
+
 type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
 
 pub trait Strategy: Send + 'static {
     fn name(&self) -> &'static str;
+    /// Version of strategy logic, part of backtest storage key.
+    ///
+    /// # ru
+    /// Версия логики стратегии, входит в ключ хранения результатов
+    /// тестов: после изменения логики стоит поднять версию, чтобы
+    /// новые результаты не смешивались со старыми.
+    fn version(&self) -> &'static str {
+        "1"
+    }
+    /// Parameter values of this instance.
+    ///
+    /// # ru
+    /// Значения параметров этого экземпляра стратегии. Стратегии с
+    /// derive StrategyParams возвращают `Params::values(self)`. По
+    /// умолчанию пусто - параметров нет.
+    fn param_values(&self) -> ParamSet {
+        ParamSet::new()
+    }
+    /// Name, version and parameters of instance.
+    ///
+    /// # ru
+    /// Паспорт экземпляра: имя, версия и хэш параметров, см.
+    /// [`StrategyMeta`]. Тестер пишет его в манифест запуска, а
+    /// трейдер в журнал сделок.
+    fn meta(&self) -> StrategyMeta {
+        StrategyMeta::new(self.name(), self.version(), &self.param_values())
+    }
+    /// Clock of run mode, set before init.
+    ///
+    /// # ru
+    /// Часы режима работы, тестер и трейдер передают их до init:
+    /// время рынка в бэктесте и воспроизведении, реальное - вживую.
+    /// Стратегии, которым нужно "сейчас" (расписание, снятие ордеров
+    /// по времени), сохраняют часы и не вызывают `Utc::now()`.
+    fn set_clock(&mut self, _clock: Arc<dyn Clock>) {}
+    /// Timeframes of charts used by strategy.
+    ///
+    /// # ru
+    /// Таймфреймы графиков инструмента, которые нужны стратегии.
+    /// Тестер и трейдер до init загружают их историю (прогрев), трейдер
+    /// сам подписывается на нужные данные. Все графики обновляются
+    /// барами 1М, и на каждом событии стратегия видит в process
+    /// актуальные графики всех своих таймфреймов:
+    /// `asset.chart(TimeFrame::H1)`. График 1М загружается всегда.
+    /// По умолчанию - все таймфреймы.
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        TimeFrame::all()
+    }
+    /// Instruments of strategy, iid strings as in config.
+    ///
+    /// # ru
+    /// Инструменты, на которых работает стратегия, строки iid как в
+    /// конфиге: "moex_share_sber". Трейдер запускает стратегии из
+    /// списка trader.strategy_list на каждом инструменте их списка,
+    /// вместе со списком работ конфига. По умолчанию пусто - стратегия
+    /// работает только там, где указана в списке работ.
+    fn universe(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Market data streams besides 1M bars.
+    ///
+    /// # ru
+    /// Потоки рыночных данных кроме баров 1М, на которые трейдер
+    /// подписывается всегда, например `MarketData::TIC`. Трейдер
+    /// сверяет потоки всех запущенных стратегий с активными подписками
+    /// брокера и сам подписывается и отписывается.
+    fn market_data(&self) -> Vec<MarketData> {
+        Vec::new()
+    }
     fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset);
+    /// Active broker orders of account and instrument, after init.
+    ///
+    /// # ru
+    /// Активные ордера брокера по счету и инструменту стратегии.
+    /// Трейдер передает их после init, чтобы стратегия после
+    /// перезапуска не выставила те же ордера второй раз, см.
+    /// [`crate::Intents`]. Тестер не вызывает - в начале теста ордеров
+    /// нет. По умолчанию ордера не нужны.
+    fn reconcile(&mut self, _orders: Vec<Order>) {}
     fn process(&mut self, asset: &Asset);
     fn order_event(&mut self, event: OrderEvent);
+    /// Reason of last decision, written to backtest journal.
+    ///
+    /// # ru
+    /// Причина последнего решения стратегии, тестер записывает ее в
+    /// журнал вместе с каждым отправленным ордером. Например "pin bar",
+    /// "stop loss".
+    fn reason(&self) -> String {
+        String::new()
+    }
+    /// Indicator values of last decision, written to backtest journal.
+    ///
+    /// # ru
+    /// Значения индикаторов и прочий контекст решения в виде пар
+    /// имя-значение, тестер записывает их в журнал вместе с каждым
+    /// отправленным ордером. Тренды экстремумов графиков тестер
+    /// записывает сам.
+    fn context(&self, _asset: &Asset) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    /// Planned stop price of entry order, used by position sizing.
+    ///
+    /// # ru
+    /// Цена стопа позиции, которую открывает отправленный ордер входа.
+    /// Тестер с моделью размера позиции (риск на сделку) считает по
+    /// ней количество лотов. None - стоп неизвестен.
+    fn stop(&self) -> Option<f64> {
+        None
+    }
+
+    /// Send notification to user.
+    ///
+    /// # ru
+    /// Отправляет уведомление для пользователя через трейдера, например
+    /// "entered long SBER @ 280, stop 276". Куда оно дойдет (лог,
+    /// GUI, Telegram) - настройка трейдера, стратегия об этом не знает.
+    fn notify(&self, trader: &Trader, level: NotifyLevel, message: &str) {
+        let a = NotifyAction::new(self.name(), level, message);
+        if trader.send(Action::Notify(a)).is_err() {
+            log::warn!("{} notify: trader is gone", self.name());
+        }
+    }
 
     fn limit_order(
         &self,
@@ -29,3 +153,55 @@ pub trait Strategy: Send + 'static {
         Order::Limit(order)
     }
 }
+
+/// Boxed strategy is strategy, so strategies created at runtime (by
+/// name, from python) run in tester and trader.
+///
+/// # ru
+/// Стратегия в коробке - тоже стратегия: так тестер и трейдер
+/// запускают стратегии, созданные во время работы, например по имени
+/// из конфига или написанные на python.
+impl Strategy for Box<dyn Strategy> {
+    fn name(&self) -> &'static str {
+        self.as_ref().name()
+    }
+    fn version(&self) -> &'static str {
+        self.as_ref().version()
+    }
+    fn param_values(&self) -> ParamSet {
+        self.as_ref().param_values()
+    }
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.as_mut().set_clock(clock)
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        self.as_ref().timeframes()
+    }
+    fn universe(&self) -> Vec<String> {
+        self.as_ref().universe()
+    }
+    fn market_data(&self) -> Vec<MarketData> {
+        self.as_ref().market_data()
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        self.as_mut().init(trader, account, asset)
+    }
+    fn reconcile(&mut self, orders: Vec<Order>) {
+        self.as_mut().reconcile(orders)
+    }
+    fn process(&mut self, asset: &Asset) {
+        self.as_mut().process(asset)
+    }
+    fn order_event(&mut self, event: OrderEvent) {
+        self.as_mut().order_event(event)
+    }
+    fn reason(&self) -> String {
+        self.as_ref().reason()
+    }
+    fn context(&self, asset: &Asset) -> Vec<(String, String)> {
+        self.as_ref().context(asset)
+    }
+    fn stop(&self) -> Option<f64> {
+        self.as_ref().stop()
+    }
+}
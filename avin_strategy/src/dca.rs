@@ -0,0 +1,400 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Weekday};
+
+use avin_core::{
+    Account, Action, Asset, Clock, Direction, Iid, MarketOrder, Order,
+    OrderAction, OrderEvent, TimeFrame,
+};
+use avin_utils::MSK_OFFSET;
+
+use crate::{ParamSet, Params, Strategy, StrategyParams};
+
+const NAME: &str = "Dca";
+
+/// Пределы множителя суммы покупки.
+const MIN_K: f64 = 0.5;
+const MAX_K: f64 = 2.0;
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+/// Calendar schedule of purchases.
+///
+/// # ru
+/// Расписание покупок по календарю, даты по Москве. Покупка в первый
+/// торговый день периода, начиная с указанного дня недели или числа
+/// месяца: если в этот день торгов нет - в ближайший следующий.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    Daily,
+    Weekly(Weekday),
+    Monthly(u32),
+}
+impl Schedule {
+    /// Is purchase due on date, last purchase was on last.
+    ///
+    /// # ru
+    /// Пора ли покупать в дату date, если последняя покупка была в
+    /// last.
+    pub fn is_due(&self, date: NaiveDate, last: Option<NaiveDate>) -> bool {
+        if last.is_some_and(|last| last >= date) {
+            return false;
+        }
+
+        match self {
+            Self::Daily => true,
+            Self::Weekly(day) => {
+                date.weekday().num_days_from_monday()
+                    >= day.num_days_from_monday()
+                    && last.is_none_or(|l| l.iso_week() != date.iso_week())
+            }
+            Self::Monthly(day) => {
+                date.day() >= *day
+                    && last.is_none_or(|l| {
+                        (l.year(), l.month()) != (date.year(), date.month())
+                    })
+            }
+        }
+    }
+}
+
+/// Scheduled accumulation, buy amount of rubles on schedule.
+///
+/// # ru
+/// Накопление по расписанию (DCA): покупка на amount рублей в дни по
+/// расписанию, после заданного времени (по умолчанию 10:30 МСК).
+/// Время берется из часов режима работы, так стратегия одинаково
+/// работает в тестере и вживую.
+///
+/// Сумма покупки зависит от оценки: множитель (avg / price) в степени
+/// sensitivity, где avg - средняя цена закрытия за period дней. Ниже
+/// средней - покупает больше, выше - меньше, множитель от 0.5 до 2.
+/// Sensitivity 0 - всегда ровно amount. Деньги, которых не хватило
+/// на целый лот, переходят на следующую покупку. Стратегия только
+/// покупает, трейдов не закрывает - результат виден по счету.
+///
+/// ```ignore
+/// let dca = Dca::new(10_000.0, Schedule::Monthly(1)).sensitivity(1.0);
+/// ```
+#[derive(Debug, StrategyParams)]
+pub struct Dca {
+    trader: Option<Trader>,
+    account: Option<Account>,
+    iid: Option<Iid>,
+    clock: Option<Arc<dyn Clock>>,
+
+    amount: f64,
+    schedule: Schedule,
+    time: NaiveTime,
+    #[param(values = [0, 1, 2])]
+    sensitivity: f64,
+    #[param(values = [50, 100, 200])]
+    period: usize,
+
+    /// День последней покупки, МСК.
+    last: Option<NaiveDate>,
+    /// Деньги на покупки, включая остаток прошлых.
+    budget: f64,
+    /// Зарезервировано под отправленный ордер.
+    reserved: f64,
+    /// Позиция в штуках.
+    position: i64,
+    invested: f64,
+    reason: String,
+}
+impl Dca {
+    /// Create accumulation of amount rubles on schedule.
+    ///
+    /// # ru
+    /// Конструктор: покупка на amount рублей по расписанию, без
+    /// модуляции по оценке, средняя за 200 дней.
+    pub fn new(amount: f64, schedule: Schedule) -> Self {
+        Self {
+            trader: None,
+            account: None,
+            iid: None,
+            clock: None,
+            amount,
+            schedule,
+            time: NaiveTime::from_hms_opt(10, 30, 0).unwrap(),
+            sensitivity: 0.0,
+            period: 200,
+            last: None,
+            budget: 0.0,
+            reserved: 0.0,
+            position: 0,
+            invested: 0.0,
+            reason: String::new(),
+        }
+    }
+    /// Set time of day of purchase, MSK.
+    ///
+    /// # ru
+    /// Время дня, после которого покупать, МСК.
+    pub fn time(mut self, time: NaiveTime) -> Self {
+        self.time = time;
+        self
+    }
+    /// Set sensitivity of amount to valuation.
+    ///
+    /// # ru
+    /// Чувствительность суммы покупки к оценке, 0 - без модуляции.
+    pub fn sensitivity(mut self, sensitivity: f64) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+    /// Set period of average price, days.
+    ///
+    /// # ru
+    /// Период средней цены для оценки, дней.
+    pub fn period(mut self, period: usize) -> Self {
+        self.period = period;
+        self
+    }
+    /// Position in units.
+    ///
+    /// # ru
+    /// Накопленная позиция в штуках.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+    /// Money spent on purchases.
+    ///
+    /// # ru
+    /// Потрачено на покупки, рублей.
+    pub fn invested(&self) -> f64 {
+        self.invested
+    }
+    /// Money left for next purchase.
+    ///
+    /// # ru
+    /// Остаток денег, перешедший на следующую покупку.
+    pub fn budget(&self) -> f64 {
+        self.budget
+    }
+
+    // private
+    fn multiplier(&self, asset: &Asset, price: f64) -> f64 {
+        if self.sensitivity == 0.0 {
+            return 1.0;
+        }
+        let Some(chart) = asset.chart(TimeFrame::Day) else {
+            return 1.0;
+        };
+        let closes: Vec<f64> = chart.bars().iter().map(|i| i.c).collect();
+        let Some(avg) = avin_utils::sma(&closes, self.period).pop() else {
+            return 1.0;
+        };
+
+        (avg / price).powf(self.sensitivity).clamp(MIN_K, MAX_K)
+    }
+    fn buy(&mut self, lots: u32) {
+        let order = MarketOrder::new(Direction::Buy, lots);
+        let a = OrderAction::new(
+            self.account.clone().unwrap(),
+            self.iid.clone().unwrap(),
+            NAME,
+            Order::Market(MarketOrder::New(order)),
+        );
+
+        self.trader.as_ref().unwrap().send(Action::Post(a)).unwrap();
+    }
+}
+impl Default for Dca {
+    fn default() -> Self {
+        Self::new(10_000.0, Schedule::Monthly(1))
+    }
+}
+impl Strategy for Dca {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn param_values(&self) -> ParamSet {
+        Params::values(self)
+    }
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M1, TimeFrame::Day]
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        self.trader = Some(trader);
+        self.account = Some(account);
+        self.iid = Some(asset.iid().clone());
+    }
+    fn process(&mut self, asset: &Asset) {
+        // ордер прошлой покупки еще не исполнен
+        if self.reserved > 0.0 {
+            return;
+        }
+        let Some(bar) = asset.chart(TimeFrame::M1).and_then(|i| i.now())
+        else {
+            return;
+        };
+
+        let ts = self.clock.as_ref().map_or(bar.ts, |i| i.ts());
+        let dt = DateTime::from_timestamp_nanos(ts) + MSK_OFFSET;
+        let date = dt.date_naive();
+        if dt.time() < self.time || !self.schedule.is_due(date, self.last) {
+            return;
+        }
+        self.last = Some(date);
+
+        let k = self.multiplier(asset, bar.c);
+        self.budget += self.amount * k;
+        let lot_price = bar.c * asset.iid().lot() as f64;
+        let lots = (self.budget / lot_price).floor() as u32;
+        if lots == 0 {
+            self.reason = format!("dca x{k:.2}, budget {:.2}", self.budget);
+            return;
+        }
+
+        self.reserved = lots as f64 * lot_price;
+        self.budget -= self.reserved;
+        self.reason = format!("dca x{k:.2}");
+        self.buy(lots);
+    }
+    fn order_event(&mut self, e: OrderEvent) {
+        match e.order {
+            Order::Market(MarketOrder::Filled(_)) => {
+                let operation = e.order.operation().unwrap();
+                self.position += operation.quantity as i64;
+                self.invested += operation.value;
+                // разница цены исполнения и оценки - в бюджет
+                self.budget += self.reserved - operation.value;
+                self.reserved = 0.0;
+            }
+            Order::Market(MarketOrder::Rejected(_)) => {
+                log::warn!("{NAME} order rejected: {}", e.order);
+                self.budget += self.reserved;
+                self.reserved = 0.0;
+            }
+            _ => (),
+        }
+    }
+    fn reason(&self) -> String {
+        self.reason.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarEvent, SimClock};
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use super::*;
+    use crate::ensemble::Book;
+
+    /// 2024-01-10 10:00 MSK, среда
+    const TS: i64 = 1_704_870_000_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+    const DAY: i64 = 24 * 60 * MINUTE;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    struct Run {
+        dca: Dca,
+        asset: Asset,
+        clock: SimClock,
+        rx: UnboundedReceiver<Action>,
+        book: Book,
+    }
+    impl Run {
+        fn new(dca: Dca) -> Self {
+            let mut dca = dca;
+            let mut asset = Asset::from_iid(iid());
+            asset.load_chart_empty(TimeFrame::M1);
+            asset.load_chart_empty(TimeFrame::Day);
+            let clock = SimClock::new(TS);
+            dca.set_clock(Arc::new(clock.clone()));
+            let (tx, rx) = mpsc::unbounded_channel();
+            dca.init(tx, Account::new("Test", "Test_ID"), &mut asset);
+
+            Self {
+                dca,
+                asset,
+                clock,
+                rx,
+                book: Book::default(),
+            }
+        }
+        // бары дня в 10:00 и 10:31 МСК по цене price
+        fn day(&mut self, n: i64, price: f64) {
+            for ts in [TS + n * DAY, TS + n * DAY + 31 * MINUTE] {
+                let bar = Bar::new(ts, price, price, price, price, 1);
+                let figi = iid().figi().clone();
+                self.asset
+                    .bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+                self.clock.set(ts + MINUTE);
+
+                self.dca.process(&self.asset);
+                while let Ok(a) = self.rx.try_recv() {
+                    for order in self.book.action(a, &bar, 10) {
+                        let e = OrderEvent::new(
+                            Account::new("Test", "Test_ID"),
+                            iid(),
+                            NAME.to_string(),
+                            order,
+                        );
+                        self.dca.order_event(e);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn schedule() {
+        let wed = date("2024-01-10");
+        assert!(Schedule::Daily.is_due(wed, None));
+        assert!(!Schedule::Daily.is_due(wed, Some(wed)));
+        assert!(Schedule::Daily.is_due(wed, Some(date("2024-01-09"))));
+
+        // по пятницам: в среду рано, в субботу - если в пятницу не было
+        let weekly = Schedule::Weekly(Weekday::Fri);
+        assert!(!weekly.is_due(wed, None));
+        assert!(weekly.is_due(date("2024-01-13"), Some(date("2024-01-05"))));
+        assert!(!weekly.is_due(date("2024-01-13"), Some(date("2024-01-12"))));
+
+        let monthly = Schedule::Monthly(10);
+        assert!(!monthly.is_due(date("2024-01-09"), None));
+        assert!(monthly.is_due(wed, Some(date("2023-12-11"))));
+        assert!(!monthly.is_due(date("2024-01-11"), Some(wed)));
+    }
+    #[test]
+    fn accumulation() {
+        let dca = Dca::new(3000.0, Schedule::Daily).sensitivity(1.0);
+        let mut run = Run::new(dca);
+
+        // до 10:30 не покупает, средняя 100 - ровно 3 лота
+        run.day(0, 100.0);
+        assert_eq!(run.dca.position(), 30);
+        assert_eq!(run.dca.reason(), "dca x1.00");
+
+        // средняя 75, цена 50 - на 4500, 9 лотов по 500
+        run.day(1, 50.0);
+        assert_eq!(run.dca.position(), 120);
+        assert_eq!(run.dca.invested(), 7500.0);
+
+        // средняя 116.67, цена 200 - 1750 меньше лота, копится
+        run.day(2, 200.0);
+        assert_eq!(run.dca.position(), 120);
+        assert!((run.dca.budget() - 1750.0).abs() < 1e-6);
+
+        // средняя 137.5 - еще 2062.5, хватает на лот
+        run.day(3, 200.0);
+        assert_eq!(run.dca.position(), 130);
+        assert!((run.dca.budget() - 1812.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,771 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use avin_core::{
+    Account, Action, Asset, Bar, Clock, Direction, Iid, LimitOrder,
+    MarketData, MarketOrder, Order, OrderAction, OrderEvent,
+    PostedLimitOrder, PostedMarketOrder, PostedStopOrder, StopOrder,
+    StopOrderKind, TimeFrame, Trade, TradeKind, Transaction,
+    TriggeredStopOrder,
+};
+
+use crate::{ParamSet, Strategy};
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+/// How votes of sub-strategies are merged.
+///
+/// # ru
+/// Способ объединения голосов стратегий ансамбля. Голос стратегии -
+/// ее виртуальная позиция: лонг, шорт или вне рынка.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Merge {
+    /// Направление, за которое больше половины стратегий.
+    Majority,
+    /// Сумма весов лонг минус шорт, деленная на сумму всех весов,
+    /// не меньше threshold по модулю.
+    Weighted { threshold: f64 },
+    /// Все стратегии в одном направлении, любая несогласная (вне рынка
+    /// или против) накладывает вето.
+    Veto,
+}
+impl Merge {
+    fn vote(&self, votes: &[(i64, f64)]) -> i64 {
+        let long: f64 = votes.iter().filter(|i| i.0 > 0).map(|i| i.1).sum();
+        let short: f64 = votes.iter().filter(|i| i.0 < 0).map(|i| i.1).sum();
+        let count = |sign: i64| votes.iter().filter(|i| i.0 == sign).count();
+
+        match self {
+            Self::Majority => {
+                if count(1) * 2 > votes.len() {
+                    1
+                } else if count(-1) * 2 > votes.len() {
+                    -1
+                } else {
+                    0
+                }
+            }
+            Self::Weighted { threshold } => {
+                let total: f64 = votes.iter().map(|i| i.1).sum();
+                if total <= 0.0 {
+                    return 0;
+                }
+                let score = (long - short) / total;
+                if score >= *threshold && score > 0.0 {
+                    1
+                } else if score <= -threshold && score < 0.0 {
+                    -1
+                } else {
+                    0
+                }
+            }
+            Self::Veto => {
+                if votes.is_empty() {
+                    0
+                } else if count(1) == votes.len() {
+                    1
+                } else if count(-1) == votes.len() {
+                    -1
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+impl std::fmt::Display for Merge {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Majority => write!(f, "majority"),
+            Self::Weighted { threshold } => write!(f, "weighted {threshold}"),
+            Self::Veto => write!(f, "veto"),
+        }
+    }
+}
+
+/// Strategy that runs sub-strategies and trades their merged vote.
+///
+/// # ru
+/// Ансамбль: запускает несколько стратегий на одном инструменте и
+/// торгует их объединенным голосом, так можно совместить, например,
+/// фильтр тренда и сигнал входа без переписывания обеих стратегий.
+///
+/// Ордера стратегий ансамбля до брокера не доходят: их исполняет
+/// виртуальная книга ансамбля по барам 1М - рыночные по закрытию
+/// бара, лимитные и стопы при касании цены. Виртуальная позиция
+/// стратегии - ее голос, голоса объединяются по [`Merge`], и ансамбль
+/// рыночными ордерами на lots лотов держит позицию в выбранном
+/// направлении или закрывает ее. Трейды ансамбля отправляются под его
+/// именем, трейды стратегий ансамбля - нет.
+///
+/// ```ignore
+/// let mut ensemble = Ensemble::new("TrendPin", Merge::Veto, 1);
+/// ensemble.add(BigTrendLong::default(), 1.0);
+/// ensemble.add(PinBarLong::default(), 1.0);
+/// ```
+pub struct Ensemble {
+    name: &'static str,
+    merge: Merge,
+    lots: u32,
+    members: Vec<Member>,
+
+    trader: Option<Trader>,
+    account: Option<Account>,
+    iid: Option<Iid>,
+    last_ts: i64,
+    /// Позиция ансамбля у брокера в штуках.
+    position: i64,
+    /// Направление последнего голосования.
+    vote: i64,
+    pending: bool,
+    trade: Option<Trade>,
+}
+impl Ensemble {
+    pub fn new(name: &'static str, merge: Merge, lots: u32) -> Self {
+        Self {
+            name,
+            merge,
+            lots,
+            members: Vec::new(),
+            trader: None,
+            account: None,
+            iid: None,
+            last_ts: 0,
+            position: 0,
+            vote: 0,
+            pending: false,
+            trade: None,
+        }
+    }
+    /// Add sub-strategy with weight of its vote.
+    ///
+    /// # ru
+    /// Добавляет стратегию в ансамбль, weight - вес ее голоса, нужен
+    /// только для [`Merge::Weighted`]. Добавлять до init.
+    pub fn add(&mut self, strategy: impl Strategy, weight: f64) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.members.push(Member {
+            strategy: Box::new(strategy),
+            weight,
+            tx,
+            rx,
+            book: Book::default(),
+        });
+    }
+    /// Position in units of sub-strategies, in order of adding.
+    ///
+    /// # ru
+    /// Виртуальные позиции стратегий ансамбля в штуках, в порядке
+    /// добавления.
+    pub fn votes(&self) -> Vec<i64> {
+        self.members.iter().map(|i| i.book.position).collect()
+    }
+
+    // private
+    fn run_members(&mut self, bar: &Bar, asset: &Asset, new_bar: bool) {
+        let account = self.account.clone().unwrap();
+        let iid = self.iid.clone().unwrap();
+        let lot = iid.lot();
+
+        for member in self.members.iter_mut() {
+            // виртуальные ордера исполняются по бару один раз
+            if new_bar {
+                let events = member.book.market(bar, lot);
+                member.deliver(events, bar, &account, &iid);
+            }
+            member.strategy.process(asset);
+            member.deliver(Vec::new(), bar, &account, &iid);
+        }
+    }
+    fn rebalance(&mut self, ts: i64) {
+        let votes: Vec<(i64, f64)> = self
+            .members
+            .iter()
+            .map(|i| (i.book.position.signum(), i.weight))
+            .collect();
+        self.vote = self.merge.vote(&votes);
+        if self.pending {
+            return;
+        }
+
+        // сначала закрыть позицию не в том направлении
+        if self.position != 0 && self.position.signum() != self.vote {
+            let direction = if self.position > 0 {
+                Direction::Sell
+            } else {
+                Direction::Buy
+            };
+            let lot = self.iid.as_ref().unwrap().lot() as i64;
+            let lots = (self.position.abs() / lot) as u32;
+            self.post(direction, lots);
+            return;
+        }
+
+        if self.position == 0 && self.vote != 0 {
+            let (kind, direction) = if self.vote > 0 {
+                (TradeKind::Long, Direction::Buy)
+            } else {
+                (TradeKind::Short, Direction::Sell)
+            };
+            let iid = self.iid.clone().unwrap();
+            let trade = Trade::new(ts, self.name, kind, iid);
+            self.trade = Some(Trade::New(trade));
+            self.post(direction, self.lots);
+        }
+    }
+    fn post(&mut self, direction: Direction, lots: u32) {
+        let order = MarketOrder::new(direction, lots);
+        let a = OrderAction::new(
+            self.account.clone().unwrap(),
+            self.iid.clone().unwrap(),
+            self.name,
+            Order::Market(MarketOrder::New(order)),
+        );
+
+        self.trader.as_ref().unwrap().send(Action::Post(a)).unwrap();
+        self.pending = true;
+    }
+    fn filled(&mut self, order: Order) {
+        let quantity = order.operation().unwrap().quantity as i64;
+        match order.direction() {
+            Direction::Buy => self.position += quantity,
+            Direction::Sell => self.position -= quantity,
+        }
+
+        match self.trade.take() {
+            Some(Trade::New(trade)) => {
+                self.trade = Some(Trade::Opened(trade.open(order)));
+            }
+            Some(Trade::Opened(mut trade)) => {
+                trade.add_order(order);
+                if self.position != 0 {
+                    self.trade = Some(Trade::Opened(trade));
+                    return;
+                }
+                let a = Action::TradeClosed(Trade::Closed(trade.close()));
+                self.trader.as_ref().unwrap().send(a).unwrap();
+            }
+            other => self.trade = other,
+        }
+    }
+}
+impl Strategy for Ensemble {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    /// Parameters of members, "member.param".
+    ///
+    /// # ru
+    /// Параметры участников в виде "участник.параметр", так хэш
+    /// ансамбля меняется вместе с параметрами любого участника.
+    fn param_values(&self) -> ParamSet {
+        let mut values = ParamSet::new();
+        for member in self.members.iter() {
+            let name = member.strategy.name();
+            for (param, value) in member.strategy.param_values().values() {
+                values.set(&format!("{name}.{param}"), *value);
+            }
+        }
+
+        values
+    }
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        for member in self.members.iter_mut() {
+            member.strategy.set_clock(clock.clone());
+        }
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        let mut timeframes = vec![TimeFrame::M1];
+        for member in self.members.iter() {
+            for tf in member.strategy.timeframes() {
+                if !timeframes.contains(&tf) {
+                    timeframes.push(tf);
+                }
+            }
+        }
+
+        timeframes
+    }
+    fn market_data(&self) -> Vec<MarketData> {
+        let mut market_data = Vec::new();
+        for member in self.members.iter() {
+            for md in member.strategy.market_data() {
+                if !market_data.contains(&md) {
+                    market_data.push(md);
+                }
+            }
+        }
+
+        market_data
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        self.trader = Some(trader);
+        self.account = Some(account.clone());
+        self.iid = Some(asset.iid().clone());
+
+        for member in self.members.iter_mut() {
+            let tx = member.tx.clone();
+            member.strategy.init(tx, account.clone(), asset);
+        }
+    }
+    fn process(&mut self, asset: &Asset) {
+        let Some(bar) = asset.chart(TimeFrame::M1).and_then(|i| i.now())
+        else {
+            return;
+        };
+        let new_bar = bar.ts != self.last_ts;
+        self.last_ts = bar.ts;
+
+        self.run_members(&bar, asset, new_bar);
+        self.rebalance(bar.ts);
+    }
+    fn order_event(&mut self, e: OrderEvent) {
+        let order = e.order;
+
+        if order.is_filled() {
+            self.pending = false;
+            self.filled(order);
+            return;
+        }
+        let rejected = match &order {
+            Order::Market(o) => matches!(o, MarketOrder::Rejected(_)),
+            _ => false,
+        };
+        if rejected {
+            log::warn!("{} order rejected: {order}", self.name);
+            self.pending = false;
+            if let Some(Trade::New(_)) = self.trade {
+                self.trade = None;
+            }
+        }
+    }
+    fn reason(&self) -> String {
+        let direction = match self.vote {
+            1 => "long",
+            -1 => "short",
+            _ => "flat",
+        };
+
+        format!("{} vote {direction}", self.merge)
+    }
+    fn context(&self, _asset: &Asset) -> Vec<(String, String)> {
+        self.members
+            .iter()
+            .map(|i| {
+                let name = i.strategy.name().to_string();
+                (name, i.book.position.to_string())
+            })
+            .collect()
+    }
+}
+
+/// Стратегия ансамбля: ее канал действий и виртуальная книга.
+struct Member {
+    strategy: Box<dyn Strategy>,
+    weight: f64,
+    tx: Trader,
+    rx: UnboundedReceiver<Action>,
+    book: Book,
+}
+impl Member {
+    /// Передает стратегии события ордеров и исполняет ее действия, пока
+    /// ответы порождают новые действия.
+    fn deliver(
+        &mut self,
+        mut events: Vec<Order>,
+        bar: &Bar,
+        account: &Account,
+        iid: &Iid,
+    ) {
+        let name = self.strategy.name();
+        loop {
+            for order in events.drain(..) {
+                let e = OrderEvent::new(
+                    account.clone(),
+                    iid.clone(),
+                    name.to_string(),
+                    order,
+                );
+                self.strategy.order_event(e);
+            }
+            while let Ok(a) = self.rx.try_recv() {
+                events.extend(self.book.action(a, bar, iid.lot()));
+            }
+            if events.is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+/// Виртуальная книга ордеров стратегии ансамбля, без комиссии и
+/// проверки денег. Тесты исполнителя сигналов используют ее как брокера.
+#[derive(Debug, Default)]
+pub(crate) struct Book {
+    /// Позиция в штуках.
+    pub(crate) position: i64,
+    limits: Vec<PostedLimitOrder>,
+    stops: Vec<PostedStopOrder>,
+    next_id: u64,
+}
+impl Book {
+    pub(crate) fn action(
+        &mut self,
+        a: Action,
+        bar: &Bar,
+        lot: u32,
+    ) -> Vec<Order> {
+        match a {
+            Action::Post(a) => self.post(a.order, bar, lot),
+            Action::Cancel(a) => self.cancel(a.order),
+            // трейды стратегий ансамбля виртуальные
+            _ => Vec::new(),
+        }
+    }
+    fn post(&mut self, order: Order, bar: &Bar, lot: u32) -> Vec<Order> {
+        let id = self.id();
+        match order {
+            Order::Market(MarketOrder::New(o)) => {
+                vec![self.fill_market(o.post(&id), bar.ts, bar.c, lot)]
+            }
+            Order::Limit(LimitOrder::New(o)) => {
+                let posted = o.post(&id);
+                self.limits.push(posted.clone());
+                vec![Order::Limit(LimitOrder::Posted(posted))]
+            }
+            Order::Stop(StopOrder::New(o)) => {
+                let posted = o.post(&id);
+                self.stops.push(posted.clone());
+                vec![Order::Stop(StopOrder::Posted(posted))]
+            }
+            other => {
+                log::warn!("Ensemble: post not new order {other}");
+                Vec::new()
+            }
+        }
+    }
+    fn cancel(&mut self, order: Order) -> Vec<Order> {
+        let Some(id) = order.broker_id() else {
+            return Vec::new();
+        };
+
+        if let Some(i) = self.limits.iter().position(|o| o.broker_id == *id) {
+            let order = self.limits.remove(i).cancel();
+            return vec![Order::Limit(LimitOrder::Canceled(order))];
+        }
+        if let Some(i) = self.stops.iter().position(|o| o.broker_id == *id) {
+            let order = self.stops.remove(i).cancel();
+            return vec![Order::Stop(StopOrder::Canceled(order))];
+        }
+
+        Vec::new()
+    }
+    pub(crate) fn market(&mut self, bar: &Bar, lot: u32) -> Vec<Order> {
+        let mut events = Vec::new();
+
+        let stops = std::mem::take(&mut self.stops);
+        for stop in stops {
+            if !triggered(&stop, bar) {
+                self.stops.push(stop);
+                continue;
+            }
+            let price = stop.stop_price;
+            let triggered = stop.trigger(&self.id());
+            events.push(Order::Stop(StopOrder::Triggered(triggered.clone())));
+            match triggered {
+                TriggeredStopOrder::Market { order, .. } => {
+                    events.push(self.fill_market(order, bar.ts, price, lot));
+                }
+                TriggeredStopOrder::Limit { order, .. } => {
+                    self.limits.push(order);
+                }
+            }
+        }
+
+        let limits = std::mem::take(&mut self.limits);
+        for mut order in limits {
+            let touched = match order.direction {
+                Direction::Buy => bar.l <= order.price,
+                Direction::Sell => bar.h >= order.price,
+            };
+            if !touched {
+                self.limits.push(order);
+                continue;
+            }
+            let quantity = order.lots * lot;
+            order.add_transaction(Transaction::new(
+                quantity as i32,
+                order.price,
+            ));
+            self.count(&order.direction, quantity);
+            let filled = order.fill(bar.ts, 0.0);
+            events.push(Order::Limit(LimitOrder::Filled(filled)));
+        }
+
+        events
+    }
+    fn fill_market(
+        &mut self,
+        mut order: PostedMarketOrder,
+        ts: i64,
+        price: f64,
+        lot: u32,
+    ) -> Order {
+        let quantity = order.lots * lot;
+        order.add_transaction(Transaction::new(quantity as i32, price));
+        self.count(&order.direction, quantity);
+
+        Order::Market(MarketOrder::Filled(order.fill(ts, 0.0)))
+    }
+    fn count(&mut self, direction: &Direction, quantity: u32) {
+        match direction {
+            Direction::Buy => self.position += quantity as i64,
+            Direction::Sell => self.position -= quantity as i64,
+        }
+    }
+    fn id(&mut self) -> String {
+        self.next_id += 1;
+
+        format!("ensemble_{}", self.next_id)
+    }
+}
+
+/// Стоп лосс срабатывает при движении против позиции, тейк профит -
+/// при движении в ее сторону.
+fn triggered(stop: &PostedStopOrder, bar: &Bar) -> bool {
+    let price = stop.stop_price;
+    match (&stop.kind, &stop.direction) {
+        (StopOrderKind::StopLoss, Direction::Sell) => bar.l <= price,
+        (StopOrderKind::StopLoss, Direction::Buy) => bar.h >= price,
+        (StopOrderKind::TakeProfit, Direction::Sell) => bar.h >= price,
+        (StopOrderKind::TakeProfit, Direction::Buy) => bar.l <= price,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{BarEvent, StopOrder};
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    use super::*;
+
+    const TS: i64 = 1_700_000_040_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+
+    fn bar(n: i64, price: f64) -> Bar {
+        Bar::new(TS + n * MINUTE, price, price + 1.0, price - 1.0, price, 1)
+    }
+
+    /// Стратегия для тестов: рыночный вход в direction на баре enter,
+    /// выход на баре exit.
+    struct Hold {
+        name: &'static str,
+        direction: Direction,
+        enter: i64,
+        exit: i64,
+        trader: Option<Trader>,
+        account: Option<Account>,
+        iid: Option<Iid>,
+        open: bool,
+        posted: bool,
+    }
+    impl Hold {
+        fn new(
+            name: &'static str,
+            direction: Direction,
+            enter: i64,
+            exit: i64,
+        ) -> Self {
+            Self {
+                name,
+                direction,
+                enter,
+                exit,
+                trader: None,
+                account: None,
+                iid: None,
+                open: false,
+                posted: false,
+            }
+        }
+        fn post(&mut self, direction: Direction) {
+            let order = MarketOrder::new(direction, 1);
+            let a = OrderAction::new(
+                self.account.clone().unwrap(),
+                self.iid.clone().unwrap(),
+                self.name,
+                Order::Market(MarketOrder::New(order)),
+            );
+            self.trader.as_ref().unwrap().send(Action::Post(a)).unwrap();
+            self.posted = true;
+        }
+    }
+    impl Strategy for Hold {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+        fn init(&mut self, trader: Trader, account: Account, a: &mut Asset) {
+            self.trader = Some(trader);
+            self.account = Some(account);
+            self.iid = Some(a.iid().clone());
+        }
+        fn process(&mut self, asset: &Asset) {
+            let chart = asset.chart(TimeFrame::M1).unwrap();
+            let n = (chart.now().unwrap().ts - TS) / MINUTE;
+            if self.posted {
+                return;
+            }
+            if !self.open && n >= self.enter && n < self.exit {
+                self.post(self.direction.clone());
+            } else if self.open && n >= self.exit {
+                let direction = match self.direction {
+                    Direction::Buy => Direction::Sell,
+                    Direction::Sell => Direction::Buy,
+                };
+                self.post(direction);
+            }
+        }
+        fn order_event(&mut self, e: OrderEvent) {
+            if e.order.is_filled() {
+                self.open = !self.open;
+                self.posted = false;
+            }
+        }
+    }
+
+    struct Run {
+        ensemble: Ensemble,
+        asset: Asset,
+        rx: UnboundedReceiver<Action>,
+    }
+    impl Run {
+        fn new(merge: Merge, weights: [f64; 3]) -> Self {
+            let mut ensemble = Ensemble::new("Ensemble", merge, 2);
+            ensemble.add(Hold::new("A", Direction::Buy, 0, 2), weights[0]);
+            ensemble.add(Hold::new("B", Direction::Buy, 1, 9), weights[1]);
+            ensemble.add(Hold::new("C", Direction::Sell, 0, 9), weights[2]);
+
+            let mut asset = Asset::from_iid(iid());
+            asset.load_chart_empty(TimeFrame::M1);
+            let (tx, rx) = mpsc::unbounded_channel();
+            let account = Account::new("Test", "Test_ID");
+            ensemble.init(tx, account, &mut asset);
+
+            Self {
+                ensemble,
+                asset,
+                rx,
+            }
+        }
+        /// Бар n, возвращает ордер ансамбля, если есть, и исполняет его.
+        fn bar(&mut self, n: i64) -> Option<(Direction, u32)> {
+            let bar = bar(n, 100.0);
+            let figi = iid().figi().clone();
+            self.asset
+                .bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+            self.ensemble.process(&self.asset);
+
+            let Ok(Action::Post(a)) = self.rx.try_recv() else {
+                return None;
+            };
+            let Order::Market(MarketOrder::New(order)) = a.order else {
+                panic!();
+            };
+            let result = (order.direction.clone(), order.lots);
+
+            let mut order = order.post("1");
+            let quantity = (order.lots * iid().lot()) as i32;
+            order.add_transaction(Transaction::new(quantity, 100.0));
+            let order =
+                Order::Market(MarketOrder::Filled(order.fill(0, 0.0)));
+            let e = OrderEvent::new(a.account, a.iid, a.owner, order);
+            self.ensemble.order_event(e);
+
+            Some(result)
+        }
+    }
+
+    #[test]
+    fn majority() {
+        let mut run = Run::new(Merge::Majority, [1.0; 3]);
+
+        // A лонг, C шорт, B вне рынка - большинства нет
+        assert_eq!(run.bar(0), None);
+        assert_eq!(run.ensemble.votes(), [10, 0, -10]);
+        // B лонг - 2 из 3
+        assert_eq!(run.bar(1), Some((Direction::Buy, 2)));
+        assert_eq!(run.ensemble.reason(), "majority vote long");
+
+        // A вышла: 1 из 3 - закрытие
+        assert_eq!(run.bar(2), Some((Direction::Sell, 2)));
+        assert_eq!(run.ensemble.votes(), [0, 10, -10]);
+        let Ok(Action::TradeClosed(Trade::Closed(trade))) = run.rx.try_recv()
+        else {
+            panic!();
+        };
+        assert_eq!(trade.strategy, "Ensemble");
+        assert_eq!(run.bar(3), None);
+    }
+    #[test]
+    fn weighted_and_veto() {
+        let mut run =
+            Run::new(Merge::Weighted { threshold: 0.5 }, [1.0, 1.0, 3.0]);
+        assert_eq!(run.bar(0), None);
+        // (1 + 1 - 3) / 5 = -0.2
+        assert_eq!(run.bar(1), None);
+
+        let mut run =
+            Run::new(Merge::Weighted { threshold: 0.5 }, [1.0, 1.0, 0.5]);
+        assert_eq!(run.bar(0), None);
+        // (1 + 1 - 0.5) / 2.5 = 0.6
+        assert_eq!(run.bar(1), Some((Direction::Buy, 2)));
+
+        // C против - вето
+        let mut run = Run::new(Merge::Veto, [1.0; 3]);
+        for n in 0..5 {
+            assert_eq!(run.bar(n), None);
+        }
+    }
+    #[test]
+    fn book() {
+        let mut book = Book::default();
+        let lot = iid().lot();
+        let b = bar(0, 100.0);
+
+        let order = LimitOrder::new(Direction::Buy, 2, 98.5);
+        let events = book.post(Order::Limit(LimitOrder::New(order)), &b, lot);
+        assert!(events[0].is_posted());
+        let stop = StopOrder::new(
+            StopOrderKind::StopLoss,
+            Direction::Sell,
+            2,
+            95.0,
+            None,
+        );
+        let events = book.post(Order::Stop(StopOrder::New(stop)), &b, lot);
+        let stop = events[0].clone();
+
+        // 99..101 - лимитка не исполнена
+        assert!(book.market(&b, lot).is_empty());
+        // 97..99 - исполнена
+        let events = book.market(&bar(1, 98.0), lot);
+        assert!(events[0].is_filled());
+        assert_eq!(book.position, 20);
+
+        // 94..96 - стоп сработал и исполнен
+        let events = book.market(&bar(2, 95.0), lot);
+        assert_eq!(events.len(), 2);
+        assert!(events[1].is_filled());
+        assert_eq!(book.position, 0);
+        assert!(book.cancel(stop).is_empty());
+    }
+}
@@ -15,7 +15,6 @@ use avin_core::{
     StopOrder, StopOrderKind, Term, TimeFrame, Trade, TradeKind,
 };
 use avin_scanner::Filter;
-use avin_utils as utils;
 
 const NAME: &str = "BigTrend-S-1.1";
 const LOTS: u32 = 10;
@@ -61,6 +60,9 @@ impl Strategy for BigTrendShort {
     fn name(&self) -> &'static str {
         NAME
     }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M10, TimeFrame::H1, TimeFrame::Day]
+    }
     fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
         let tf = TimeFrame::Day;
         let chart = asset.chart_mut(tf).unwrap();
@@ -282,8 +284,7 @@ impl BigTrendShort {
         let stop = price * STOP;
 
         // округляем цену до минимального шага цены, иначе не выставится
-        let min_step = self.iid.as_ref().unwrap().step();
-        let stop = utils::round_price(stop, min_step);
+        let stop = self.iid.as_ref().unwrap().round_to_step(stop);
 
         // создаем стоп лосс
         let stop_order = StopOrder::new(
@@ -372,8 +373,7 @@ impl BigTrendShort {
         let stop = price * TAKE;
 
         // округляем цену до минимального шага цены, иначе не выставится
-        let min_step = self.iid.as_ref().unwrap().step();
-        let stop = utils::round_price(stop, min_step);
+        let stop = self.iid.as_ref().unwrap().round_to_step(stop);
 
         // создаем тейк профит
         let stop_order = StopOrder::new(
@@ -47,6 +47,9 @@ impl Strategy for BuySell {
     fn name(&self) -> &'static str {
         NAME
     }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M1]
+    }
     fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
         self.trader = Some(trader);
         self.account = Some(account);
@@ -67,10 +70,10 @@ impl Strategy for BuySell {
         self.last_ts = bar.ts;
 
         match self.status {
-            Status::Observe => self.get_in(bar),
+            Status::Observe => self.get_in(&bar),
             Status::PostingBuy => (),
             Status::Opening => (),
-            Status::Active => self.get_out(bar),
+            Status::Active => self.get_out(&bar),
             Status::PostingSell => (),
             Status::Closing => (),
         }
@@ -0,0 +1,69 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Данные для тестов стратегий библиотеки: актив без хранилища и
+//! прогон источника сигналов по ценам закрытия.
+
+use avin_core::fixture::iid;
+use avin_core::{Asset, Bar, BarEvent, Signal, TimeFrame};
+
+use crate::SignalSource;
+
+/// 2024-01-10 10:00 MSK
+pub const TS: i64 = 1_704_870_000_000_000_000;
+pub const MINUTE: i64 = 60_000_000_000;
+pub const DAY: i64 = 24 * 60 * MINUTE;
+
+pub struct Fixture {
+    pub asset: Asset,
+    ts: i64,
+}
+impl Fixture {
+    pub fn new(tf: TimeFrame) -> Self {
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::M1);
+        if tf != TimeFrame::M1 {
+            asset.load_chart_empty(tf);
+        }
+
+        Self { asset, ts: TS }
+    }
+    /// Пропуск времени, например до следующего дня.
+    pub fn skip(&mut self, nanos: i64) {
+        self.ts += nanos;
+    }
+    /// Минутные бары с закрытием price и диапазоном +-1, после каждого
+    /// бара спрашивает сигнал. Возвращает сигналы с номером бара в
+    /// этом прогоне.
+    pub fn run(
+        &mut self,
+        source: &mut impl SignalSource,
+        prices: &[f64],
+    ) -> Vec<(usize, Signal)> {
+        let mut signals = Vec::new();
+        for (n, price) in prices.iter().enumerate() {
+            let bar = Bar::new(
+                self.ts,
+                *price,
+                price + 1.0,
+                price - 1.0,
+                *price,
+                1,
+            );
+            self.ts += MINUTE;
+
+            let figi = iid().figi().clone();
+            self.asset
+                .bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+            if let Some(signal) = source.signal(&self.asset) {
+                signals.push((n, signal));
+            }
+        }
+
+        signals
+    }
+}
@@ -0,0 +1,154 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Пересечение скользящих средних.
+//!
+//! На закрытии бара таймфрейма tf быстрая SMA пересекла медленную
+//! снизу вверх - сигнал в лонг, сверху вниз - в шорт. Стопа и тейка
+//! нет, позицию переворачивает сигнал в обратную сторону, см.
+//! [`Executor`]. Классический пример трендовой стратегии, не для
+//! заработка.
+
+use avin_core::{Asset, Signal, TimeFrame, TradeKind};
+use avin_utils::sma;
+
+use crate::{Executor, Param, ParamSet, Params, SignalSource};
+
+const NAME: &str = "MaCross";
+const FAST: usize = 10;
+const SLOW: usize = 30;
+
+/// Moving average crossover signals.
+///
+/// # ru
+/// Источник сигналов пересечения быстрой и медленной SMA цен закрытия.
+/// Стратегия для тестера и трейдера - `Executor::<MaCross>::default()`.
+#[derive(Debug)]
+pub struct MaCross {
+    tf: TimeFrame,
+    fast: usize,
+    slow: usize,
+    last_ts: i64,
+}
+impl MaCross {
+    pub fn new(tf: TimeFrame, fast: usize, slow: usize) -> Self {
+        assert!(fast < slow, "fast period must be less than slow");
+
+        Self {
+            tf,
+            fast,
+            slow,
+            last_ts: 0,
+        }
+    }
+}
+impl Default for MaCross {
+    fn default() -> Self {
+        Self::new(TimeFrame::H1, FAST, SLOW)
+    }
+}
+impl SignalSource for MaCross {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn param_values(&self) -> ParamSet {
+        let mut values = ParamSet::new();
+        values.set("fast", self.fast as f64);
+        values.set("slow", self.slow as f64);
+
+        values
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M1, self.tf]
+    }
+    fn signal(&mut self, asset: &Asset) -> Option<Signal> {
+        let chart = asset.chart(self.tf)?;
+        // последний закрытый бар
+        let last = chart.bars().iter().rev().nth(1)?;
+        if last.ts == self.last_ts {
+            return None;
+        }
+        self.last_ts = last.ts;
+
+        // закрытые бары, окно slow + 1 дает полные средние на двух
+        // последних барах
+        let bars = chart.bars();
+        let closed = &bars.c[..bars.len() - 1];
+        if closed.len() < self.slow + 1 {
+            return None;
+        }
+        let closes = &closed[closed.len() - self.slow - 1..];
+        let fast = sma(closes, self.fast);
+        let slow = sma(closes, self.slow);
+
+        let n = closes.len() - 1;
+        let before = fast[n - 1] - slow[n - 1];
+        let now = fast[n] - slow[n];
+        let kind = if before <= 0.0 && now > 0.0 {
+            TradeKind::Long
+        } else if before >= 0.0 && now < 0.0 {
+            TradeKind::Short
+        } else {
+            return None;
+        };
+
+        let ts = last.ts + self.tf.nanos();
+        let iid = asset.iid().clone();
+        let ttl = self.tf.timedelta();
+
+        Some(Signal::new(ts, NAME, iid, kind, 1.0, ttl))
+    }
+}
+impl Params for Executor<MaCross> {
+    fn params() -> Vec<Param> {
+        vec![
+            Param::range("fast", 5.0, 20.0, 5.0),
+            Param::range("slow", 30.0, 60.0, 10.0),
+        ]
+    }
+    fn with_params(params: &ParamSet) -> Self {
+        let fast = params.get("fast").map_or(FAST, |i| i as usize);
+        let slow = params.get("slow").map_or(SLOW, |i| i as usize);
+        let source = MaCross::new(TimeFrame::H1, fast, slow);
+
+        Executor::new(source, 1, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::fixture::Fixture;
+
+    #[test]
+    fn crossover() {
+        let mut f = Fixture::new(TimeFrame::M1);
+        let mut source = MaCross::new(TimeFrame::M1, 2, 4);
+
+        // падение, затем рост - пересечение вверх
+        let prices = [10.0, 9.0, 8.0, 7.0, 6.0, 7.0, 9.0, 11.0];
+        let signals = f.run(&mut source, &prices);
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].0, 7);
+        assert!(signals[0].1.is_long());
+
+        // и снова падение - пересечение вниз
+        let signals = f.run(&mut source, &[10.0, 8.0, 6.0, 5.0]);
+        assert_eq!(signals.len(), 1);
+        assert!(signals[0].1.is_short());
+    }
+    #[test]
+    fn params() {
+        let mut params = ParamSet::new();
+        params.set("fast", 5.0);
+        params.set("slow", 40.0);
+        assert!(Executor::<MaCross>::validate(&params).is_ok());
+
+        let strategy = Executor::<MaCross>::with_params(&params);
+        assert_eq!(strategy.source().slow, 40);
+    }
+}
@@ -8,9 +8,19 @@
 mod big_trend_long;
 mod big_trend_short;
 mod buy_sell;
+#[cfg(test)]
+pub(crate) mod fixture;
+mod ma_cross;
+mod opening_range;
 mod pin_bar;
+mod rsi_reversion;
+mod trend_follow;
 
 pub use big_trend_long::BigTrendLong;
 pub use big_trend_short::BigTrendShort;
 pub use buy_sell::BuySell;
+pub use ma_cross::MaCross;
+pub use opening_range::OpeningRange;
 pub use pin_bar::PinBarLong;
+pub use rsi_reversion::RsiReversion;
+pub use trend_follow::TrendFollow;
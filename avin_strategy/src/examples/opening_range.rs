@@ -0,0 +1,172 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Пробой диапазона открытия.
+//!
+//! Первые minutes минутных баров торгового дня (по Москве) образуют
+//! диапазон открытия. Первое закрытие минутного бара выше диапазона -
+//! сигнал в лонг, ниже - в шорт, не больше одного сигнала в день. Стоп
+//! на противоположной границе, тейк на take высот диапазона от
+//! пробитой границы. Пример внутридневной стратегии, не для заработка.
+
+use chrono::NaiveDate;
+
+use avin_core::{Asset, Signal, TimeFrame, TradeKind};
+use avin_utils::MSK_OFFSET;
+
+use crate::{Executor, Param, ParamSet, Params, SignalSource};
+
+const NAME: &str = "OpeningRange";
+const MINUTES: usize = 30;
+/// Тейк в высотах диапазона
+const TAKE: f64 = 2.0;
+
+/// Opening range breakout signals.
+///
+/// # ru
+/// Источник сигналов пробоя диапазона открытия. Стратегия для тестера
+/// и трейдера - `Executor::<OpeningRange>::default()`.
+#[derive(Debug)]
+pub struct OpeningRange {
+    minutes: usize,
+    take: f64,
+
+    last_ts: i64,
+    day: Option<NaiveDate>,
+    count: usize,
+    high: f64,
+    low: f64,
+    done: bool,
+}
+impl OpeningRange {
+    pub fn new(minutes: usize, take: f64) -> Self {
+        Self {
+            minutes,
+            take,
+            last_ts: 0,
+            day: None,
+            count: 0,
+            high: f64::MIN,
+            low: f64::MAX,
+            done: false,
+        }
+    }
+}
+impl Default for OpeningRange {
+    fn default() -> Self {
+        Self::new(MINUTES, TAKE)
+    }
+}
+impl SignalSource for OpeningRange {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn param_values(&self) -> ParamSet {
+        let mut values = ParamSet::new();
+        values.set("minutes", self.minutes as f64);
+        values.set("take", self.take);
+
+        values
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M1]
+    }
+    fn signal(&mut self, asset: &Asset) -> Option<Signal> {
+        let chart = asset.chart(TimeFrame::M1)?;
+        // последний закрытый бар
+        let bar = chart.bars().iter().rev().nth(1)?;
+        if bar.ts == self.last_ts {
+            return None;
+        }
+        self.last_ts = bar.ts;
+
+        // новый торговый день - новый диапазон
+        let day = (bar.dt() + MSK_OFFSET).date_naive();
+        if self.day != Some(day) {
+            self.day = Some(day);
+            self.count = 0;
+            self.high = f64::MIN;
+            self.low = f64::MAX;
+            self.done = false;
+        }
+        if self.count < self.minutes {
+            self.count += 1;
+            self.high = self.high.max(bar.h);
+            self.low = self.low.min(bar.l);
+            return None;
+        }
+        if self.done {
+            return None;
+        }
+
+        let height = self.high - self.low;
+        let (kind, stop, take) = if bar.c > self.high {
+            let take = self.high + height * self.take;
+            (TradeKind::Long, self.low, take)
+        } else if bar.c < self.low {
+            let take = self.low - height * self.take;
+            (TradeKind::Short, self.high, take)
+        } else {
+            return None;
+        };
+        self.done = true;
+
+        let ts = bar.ts + TimeFrame::M1.nanos();
+        let iid = asset.iid().clone();
+        let ttl = TimeFrame::M1.timedelta();
+        let take = iid.round_to_step(take);
+        let signal = Signal::new(ts, NAME, iid, kind, 1.0, ttl)
+            .stop(stop)
+            .take(take);
+
+        Some(signal)
+    }
+}
+impl Params for Executor<OpeningRange> {
+    fn params() -> Vec<Param> {
+        vec![
+            Param::list("minutes", &[15.0, 30.0, 60.0]),
+            Param::range("take", 1.0, 3.0, 0.5),
+        ]
+    }
+    fn with_params(params: &ParamSet) -> Self {
+        let minutes = params.get("minutes").map_or(MINUTES, |i| i as usize);
+        let take = params.get("take").unwrap_or(TAKE);
+
+        Executor::new(OpeningRange::new(minutes, take), 1, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::fixture::{DAY, Fixture};
+
+    #[test]
+    fn breakout() {
+        let mut f = Fixture::new(TimeFrame::M1);
+        let mut source = OpeningRange::new(3, 2.0);
+
+        // диапазон 99..101 из трех баров, затем пробой вверх, повторный
+        // пробой в тот же день сигнала не дает
+        let prices = [100.0, 100.0, 100.0, 100.5, 102.0, 100.0, 103.0, 103.0];
+        let signals = f.run(&mut source, &prices);
+        assert_eq!(signals.len(), 1);
+        let (n, signal) = &signals[0];
+        assert_eq!(*n, 5);
+        assert!(signal.is_long());
+        assert_eq!(signal.stop, Some(99.0));
+        assert_eq!(signal.take, Some(105.0));
+
+        // следующий день - новый диапазон, пробой вниз
+        f.skip(DAY);
+        let signals = f.run(&mut source, &[100.0, 100.0, 100.0, 100.0, 97.0]);
+        assert_eq!(signals.len(), 0);
+        let signals = f.run(&mut source, &[97.0]);
+        assert!(signals[0].1.is_short());
+    }
+}
@@ -16,16 +16,17 @@ use avin_core::{
     Account, Action, Asset, Direction, Iid, MarketOrder, Order, OrderAction,
     OrderEvent, StopOrder, StopOrderKind, TimeFrame, Trade, TradeKind,
 };
-use avin_utils as utils;
 
-use crate::Strategy;
+use crate::{ParamSet, Params, Strategy, StrategyParams};
 
 /// Имя стратегии для себя, имя должно быть уникальным, используется как
 /// ключ в HashMap. К одному инструменту может быть подключено несколько
 /// стратегий.
 const NAME: &str = "PinBarLong";
 
-/// количество лотов которыми оперирует стратегия
+/// количество лотов которыми оперирует стратегия, тестер с моделью
+/// размера позиции может его пересчитать, поэтому стоп и тейк
+/// выставляются на фактический размер трейда
 const LOTS: u32 = 10;
 
 /// Стоп лосс на 1%
@@ -63,18 +64,51 @@ enum Status {
 /// - trader - сендер к трейдеру
 /// - account - аккаунт на котором работает стратегия
 /// - iid - идентификатор инструмента по которому работает стратегия
-#[derive(Debug, Default)]
+///
+/// Чтобы стратегию можно было оптимизировать в тестере и загружать ее
+/// параметры из TOML, поля-параметры помечаются атрибутом param с
+/// диапазоном значений, а derive StrategyParams реализует Params.
+#[derive(Debug, StrategyParams)]
 pub struct PinBarLong {
     trader: Option<Trader>,
     account: Option<Account>,
     iid: Option<Iid>,
 
+    /// Параметры стратегии, можно оптимизировать в тестере, см. Params
+    #[param(begin = 0.97, end = 0.995, step = 0.005)]
+    stop: f64,
+    #[param(begin = 1.01, end = 1.05, step = 0.01)]
+    take: f64,
+
     status: Status,
     last_ts: i64,
     trade: Option<Trade>,
     buy_order: Option<Order>,
     stop_loss: Option<Order>,
     take_profit: Option<Order>,
+    /// Цена стопа открываемой позиции, для расчета ее размера
+    planned_stop: Option<f64>,
+}
+
+impl Default for PinBarLong {
+    fn default() -> Self {
+        Self {
+            trader: None,
+            account: None,
+            iid: None,
+
+            stop: STOP,
+            take: TAKE,
+
+            status: Status::default(),
+            last_ts: 0,
+            trade: None,
+            buy_order: None,
+            stop_loss: None,
+            take_profit: None,
+            planned_stop: None,
+        }
+    }
 }
 
 /// Чтобы тестер или трейдер могли работать с этой стратегией нужно
@@ -85,6 +119,14 @@ impl Strategy for PinBarLong {
     fn name(&self) -> &'static str {
         NAME
     }
+    fn param_values(&self) -> ParamSet {
+        Params::values(self)
+    }
+    /// Таймфреймы графиков, которые нужны стратегии: тестер и трейдер
+    /// загрузят их до init, в process они уже обновлены.
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::Day]
+    }
     /// Инициализация стратегии - ей присваивается трейдер
     /// (сендер к трейдеру), аккаунт и идентификатор инструмента.
     /// Этот метод вызывается один раз перед запуском стратегии. В нем
@@ -119,6 +161,24 @@ impl Strategy for PinBarLong {
             Status::Canceling => self.on_canceling_event(e),
         }
     }
+    /// Причина решения для журнала тестера: по статусу понятно, какой
+    /// ордер стратегия только что отправила.
+    fn reason(&self) -> String {
+        let reason = match self.status {
+            Status::PostingBuy => "pin bar",
+            Status::PostingStop => "stop loss",
+            Status::PostingTake => "take profit",
+            Status::Canceling => "trade closed",
+            _ => "",
+        };
+
+        reason.to_string()
+    }
+    /// Стоп открываемой позиции - по нему тестер может рассчитать
+    /// размер позиции при фиксированном риске на сделку.
+    fn stop(&self) -> Option<f64> {
+        self.planned_stop
+    }
 }
 /// Собственно пользовательская логика работы стратегии
 impl PinBarLong {
@@ -171,7 +231,8 @@ impl PinBarLong {
             return;
         }
 
-        // все условия выполнены, покупаем по рынку
+        // все условия выполнены, покупаем по рынку, стоп от текущей цены
+        self.planned_stop = chart.last_price().map(|p| p * self.stop);
         self.buy();
     }
     fn buy(&mut self) {
@@ -279,17 +340,16 @@ impl PinBarLong {
         let price = trade.avg();
 
         // рассчитываем цену стопа
-        let stop = price * STOP;
+        let stop = price * self.stop;
 
         // округляем цену до минимального шага цены, иначе не выставится
-        let min_step = self.iid.as_ref().unwrap().step();
-        let stop = utils::round_price(stop, min_step);
+        let stop = self.iid.as_ref().unwrap().round_to_step(stop);
 
         // создаем стоп лосс
         let stop_order = StopOrder::new(
             StopOrderKind::StopLoss,
             Direction::Sell,
-            LOTS,
+            trade.lots() as u32,
             stop,
             None, // цена сработки ордера, None - будет рыночное исполнение
         );
@@ -369,17 +429,16 @@ impl PinBarLong {
         let price = trade.avg();
 
         // рассчитываем цену тейк профита
-        let stop = price * TAKE;
+        let stop = price * self.take;
 
         // округляем цену до минимального шага цены, иначе не выставится
-        let min_step = self.iid.as_ref().unwrap().step();
-        let stop = utils::round_price(stop, min_step);
+        let stop = self.iid.as_ref().unwrap().round_to_step(stop);
 
         // создаем тейк профит
         let stop_order = StopOrder::new(
             StopOrderKind::TakeProfit,
             Direction::Sell,
-            LOTS,
+            trade.lots() as u32,
             stop,
             Some(stop), // цена исполнения ордера == цена сработки
         );
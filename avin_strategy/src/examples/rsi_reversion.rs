@@ -0,0 +1,181 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Возврат к среднему по RSI.
+//!
+//! RSI цен закрытия вышел из зоны перепроданности (пересек lower снизу
+//! вверх) - сигнал в лонг, из зоны перекупленности (пересек upper
+//! сверху вниз) - в шорт. Стоп на stop процентов от цены, тейк на
+//! среднем - SMA mean баров. Пример контртрендовой стратегии, не для
+//! заработка.
+
+use avin_core::{Asset, Signal, TimeFrame, TradeKind};
+use avin_utils::{rsi, sma};
+
+use crate::{Executor, Param, ParamSet, Params, SignalSource};
+
+const NAME: &str = "RsiReversion";
+const PERIOD: usize = 14;
+const LOWER: f64 = 30.0;
+const UPPER: f64 = 70.0;
+/// Стоп в процентах от цены входа
+const STOP: f64 = 2.0;
+/// Период среднего, к которому ждем возврата
+const MEAN: usize = 20;
+/// Сколько баров истории берется для RSI, сглаживание Уайлдера
+/// зависит от начала ряда
+const HISTORY: usize = 10;
+
+/// RSI mean reversion signals.
+///
+/// # ru
+/// Источник сигналов возврата к среднему по RSI. Стратегия для тестера
+/// и трейдера - `Executor::<RsiReversion>::default()`.
+#[derive(Debug)]
+pub struct RsiReversion {
+    tf: TimeFrame,
+    period: usize,
+    lower: f64,
+    upper: f64,
+    stop: f64,
+    mean: usize,
+    last_ts: i64,
+}
+impl RsiReversion {
+    pub fn new(tf: TimeFrame, period: usize, lower: f64, upper: f64) -> Self {
+        assert!(lower < upper, "lower must be less than upper");
+
+        Self {
+            tf,
+            period,
+            lower,
+            upper,
+            stop: STOP,
+            mean: MEAN,
+            last_ts: 0,
+        }
+    }
+}
+impl Default for RsiReversion {
+    fn default() -> Self {
+        Self::new(TimeFrame::H1, PERIOD, LOWER, UPPER)
+    }
+}
+impl SignalSource for RsiReversion {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn param_values(&self) -> ParamSet {
+        let mut values = ParamSet::new();
+        values.set("period", self.period as f64);
+        values.set("lower", self.lower);
+        values.set("upper", self.upper);
+
+        values
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M1, self.tf]
+    }
+    fn signal(&mut self, asset: &Asset) -> Option<Signal> {
+        let chart = asset.chart(self.tf)?;
+        // последний закрытый бар
+        let last = chart.bars().iter().rev().nth(1)?;
+        if last.ts == self.last_ts {
+            return None;
+        }
+        self.last_ts = last.ts;
+
+        let bars = chart.bars();
+        let closed = &bars.c[..bars.len() - 1];
+        let count = (self.period * HISTORY).max(self.mean);
+        if closed.len() < self.period + 2 {
+            return None;
+        }
+        let closes = &closed[closed.len().saturating_sub(count)..];
+        let values = rsi(closes, self.period);
+
+        let n = values.len() - 1;
+        let (before, now) = (values[n - 1], values[n]);
+        let kind = if before < self.lower && now >= self.lower {
+            TradeKind::Long
+        } else if before > self.upper && now <= self.upper {
+            TradeKind::Short
+        } else {
+            return None;
+        };
+
+        let price = last.c;
+        let mean = *sma(closes, self.mean).last().unwrap();
+        let iid = asset.iid().clone();
+        let (stop, take) = match kind {
+            TradeKind::Long => {
+                let take = (mean > price).then_some(mean);
+                (price * (1.0 - self.stop / 100.0), take)
+            }
+            TradeKind::Short => {
+                let take = (mean < price).then_some(mean);
+                (price * (1.0 + self.stop / 100.0), take)
+            }
+        };
+
+        let ts = last.ts + self.tf.nanos();
+        let ttl = self.tf.timedelta();
+        let mut signal = Signal::new(ts, NAME, iid.clone(), kind, 1.0, ttl)
+            .stop(iid.round_to_step(stop));
+        if let Some(take) = take {
+            signal = signal.take(iid.round_to_step(take));
+        }
+
+        Some(signal)
+    }
+}
+impl Params for Executor<RsiReversion> {
+    fn params() -> Vec<Param> {
+        vec![
+            Param::range("period", 7.0, 21.0, 7.0),
+            Param::range("lower", 20.0, 35.0, 5.0),
+            Param::range("upper", 65.0, 80.0, 5.0),
+        ]
+    }
+    fn with_params(params: &ParamSet) -> Self {
+        let period = params.get("period").map_or(PERIOD, |i| i as usize);
+        let lower = params.get("lower").unwrap_or(LOWER);
+        let upper = params.get("upper").unwrap_or(UPPER);
+        let source = RsiReversion::new(TimeFrame::H1, period, lower, upper);
+
+        Executor::new(source, 1, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::fixture::Fixture;
+
+    #[test]
+    fn reversion() {
+        let mut f = Fixture::new(TimeFrame::M1);
+        let mut source = RsiReversion::new(TimeFrame::M1, 3, 30.0, 70.0);
+
+        // падение - перепроданность, отскок - сигнал в лонг
+        let prices = [100.0, 99.0, 98.0, 97.0, 96.0, 99.0, 99.0];
+        let signals = f.run(&mut source, &prices);
+        assert_eq!(signals.len(), 1);
+        let (n, signal) = &signals[0];
+        assert_eq!(*n, 6);
+        assert!(signal.is_long());
+        assert_eq!(signal.stop, Some(97.02));
+        assert!(signal.take.is_none());
+
+        // рост - перекупленность, откат - сигнал в шорт
+        let prices = [101.0, 103.0, 105.0, 107.0, 104.0, 104.0];
+        let signals = f.run(&mut source, &prices);
+        assert_eq!(signals.len(), 1);
+        assert!(signals[0].1.is_short());
+        assert!(signals[0].1.take.unwrap() < 104.0);
+    }
+}
@@ -0,0 +1,144 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Следование за трендом по экстремумам.
+//!
+//! Индикатор экстремумов на графике tf, когда начинается новый тренд
+//! term (по умолчанию T3) - сигнал в его сторону со стопом за
+//! экстремумом начала тренда. Позицию переворачивает новый тренд в
+//! обратную сторону, см. [`Executor`]. Пример стратегии на индикаторе
+//! экстремумов, не для заработка.
+
+use avin_core::{
+    Asset, ExtremumIndicator, Signal, Term, TimeFrame, TradeKind,
+};
+
+use crate::{Executor, Param, ParamSet, Params, SignalSource};
+
+const NAME: &str = "TrendFollow";
+
+/// Trend following signals on extremum trends.
+///
+/// # ru
+/// Источник сигналов начала трендов индикатора экстремумов. Стратегия
+/// для тестера и трейдера - `Executor::<TrendFollow>::default()`.
+#[derive(Debug)]
+pub struct TrendFollow {
+    tf: TimeFrame,
+    term: Term,
+    /// Время экстремума начала последнего тренда.
+    begin_ts: i64,
+}
+impl TrendFollow {
+    pub fn new(tf: TimeFrame, term: Term) -> Self {
+        Self {
+            tf,
+            term,
+            begin_ts: 0,
+        }
+    }
+}
+impl Default for TrendFollow {
+    fn default() -> Self {
+        Self::new(TimeFrame::H1, Term::T3)
+    }
+}
+impl SignalSource for TrendFollow {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn param_values(&self) -> ParamSet {
+        let mut values = ParamSet::new();
+        values.set("term", self.term as u8 as f64);
+
+        values
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M1, self.tf]
+    }
+    fn init(&mut self, asset: &mut Asset) {
+        let chart = asset.chart_mut(self.tf).unwrap();
+        if !chart.has_extremum() {
+            ExtremumIndicator::init(chart);
+        }
+    }
+    fn signal(&mut self, asset: &Asset) -> Option<Signal> {
+        let chart = asset.chart(self.tf)?;
+        let trend = chart.trend(self.term, 0)?;
+
+        // тот же тренд, что и раньше
+        let begin = trend.begin();
+        if begin.ts == self.begin_ts {
+            return None;
+        }
+        let first = self.begin_ts == 0;
+        self.begin_ts = begin.ts;
+        // тренд, который уже шел при запуске, не торгуем
+        if first {
+            return None;
+        }
+
+        let kind = if trend.is_bull() {
+            TradeKind::Long
+        } else {
+            TradeKind::Short
+        };
+        let ts = chart.now()?.ts;
+        let iid = asset.iid().clone();
+        let ttl = self.tf.timedelta();
+        let signal =
+            Signal::new(ts, NAME, iid, kind, 1.0, ttl).stop(begin.price);
+
+        Some(signal)
+    }
+}
+impl Params for Executor<TrendFollow> {
+    fn params() -> Vec<Param> {
+        vec![Param::list("term", &[1.0, 2.0, 3.0])]
+    }
+    fn with_params(params: &ParamSet) -> Self {
+        let term = match params.get("term").map(|i| i as u8) {
+            Some(1) => Term::T1,
+            Some(2) => Term::T2,
+            _ => Term::T3,
+        };
+
+        Executor::new(TrendFollow::new(TimeFrame::H1, term), 1, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::fixture::Fixture;
+
+    #[test]
+    fn trends() {
+        let mut f = Fixture::new(TimeFrame::M1);
+        let mut source = TrendFollow::new(TimeFrame::M1, Term::T1);
+        source.init(&mut f.asset);
+
+        let mut prices = Vec::new();
+        for wave in 0..4 {
+            let base = 100.0 + wave as f64;
+            prices.extend((0..6).map(|i| base + i as f64));
+            prices.extend((0..6).map(|i| base + 6.0 - i as f64));
+        }
+        let signals = f.run(&mut source, &prices);
+
+        // тренды чередуются, стоп за экстремумом начала тренда
+        assert_eq!(signals.len(), 7);
+        let (n, short) = &signals[0];
+        assert_eq!(*n, 7);
+        assert!(short.is_short());
+        assert_eq!(short.stop, Some(107.0));
+        let (n, long) = &signals[1];
+        assert_eq!(*n, 12);
+        assert!(long.is_long());
+        assert_eq!(long.stop, Some(100.0));
+    }
+}
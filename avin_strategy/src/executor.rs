@@ -0,0 +1,794 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{
+    Account, Action, Asset, Direction, Iid, LimitOrder, MarketOrder,
+    NotifyLevel, Order, OrderAction, OrderEvent, PostedStopOrder, Signal,
+    StopOrder, StopOrderKind, TimeFrame, Trade, TradeKind,
+};
+
+use crate::{ParamSet, PartialTake, StopManager, Strategy};
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+/// Source of signals for [`Executor`].
+///
+/// # ru
+/// Источник сигналов - логика стратегии без работы с ордерами: он
+/// только говорит, что думает о рынке, см. [`Signal`]. Как по сигналу
+/// работать ордера решает [`Executor`].
+pub trait SignalSource: Send + 'static {
+    fn name(&self) -> &'static str;
+    /// Version of source logic, see [`Strategy::version`].
+    ///
+    /// # ru
+    /// Версия логики источника, см. [`Strategy::version`].
+    fn version(&self) -> &'static str {
+        "1"
+    }
+    /// Parameter values of source, see [`Strategy::param_values`].
+    ///
+    /// # ru
+    /// Значения параметров источника, см.
+    /// [`Strategy::param_values`].
+    fn param_values(&self) -> ParamSet {
+        ParamSet::new()
+    }
+    /// Timeframes of charts used by source, see
+    /// [`Strategy::timeframes`].
+    ///
+    /// # ru
+    /// Таймфреймы графиков, которые нужны источнику, см.
+    /// [`Strategy::timeframes`].
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        TimeFrame::all()
+    }
+    /// Instruments of source, see [`Strategy::universe`].
+    ///
+    /// # ru
+    /// Инструменты источника, см. [`Strategy::universe`].
+    fn universe(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn init(&mut self, _asset: &mut Asset) {}
+    /// New signal on current data or None.
+    ///
+    /// # ru
+    /// Вызывается на каждом событии рынка, возвращает новый сигнал
+    /// или None, если сказать нечего.
+    fn signal(&mut self, asset: &Asset) -> Option<Signal>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Observe,
+    Entering,
+    Canceling,
+    Active,
+    Exiting,
+}
+
+/// Strategy that works orders by signals of source.
+///
+/// # ru
+/// Исполнитель сигналов: превращает сигналы [`SignalSource`] в ордера.
+/// Каждый сигнал отправляется как [`Action::Signal`], так что трейдер
+/// и тестер видят сигналы отдельно от ордеров.
+///
+/// Вне рынка исполнитель входит по сигналу силой не меньше
+/// min_strength: по рынку или лимиткой по цене входа, количество
+/// лотов - lots, умноженное на силу, но не меньше 1. Лимитка
+/// снимается, если не исполнилась до окончания действия сигнала. После
+/// входа выставляются стоп лосс и тейк профит по ценам сигнала.
+/// Позиция закрывается по стопу, тейку или по рынку на сигнале в
+/// противоположную сторону, после закрытия по такому сигналу
+/// исполнитель входит в его сторону, если сигнал еще действует.
+/// С [`Executor::stops`] стоп позиции сопровождает [`StopManager`]:
+/// безубыток, частичные тейки, трейлинг.
+///
+/// ```ignore
+/// let strategy = Executor::new(MySignals::default(), 4, 0.3)
+///     .stops(StopManager::new(TimeFrame::M10).breakeven(1.0));
+/// ```
+pub struct Executor<S: SignalSource> {
+    source: S,
+    lots: u32,
+    min_strength: f64,
+
+    trader: Option<Trader>,
+    account: Option<Account>,
+    iid: Option<Iid>,
+    status: Status,
+    /// Сигнал текущего трейда.
+    signal: Option<Signal>,
+    /// Сигнал в другую сторону, по которому вход после закрытия.
+    pending: Option<Signal>,
+    trade: Option<Trade>,
+    /// Выставленная лимитка входа.
+    entry: Option<Order>,
+    /// Выставленные стоп лосс и тейк профит.
+    exits: Vec<PostedStopOrder>,
+    /// Позиция в штуках.
+    position: i64,
+    stops: Option<StopManager>,
+    reason: String,
+}
+impl<S: SignalSource> Executor<S> {
+    pub fn new(source: S, lots: u32, min_strength: f64) -> Self {
+        Self {
+            source,
+            lots,
+            min_strength,
+            trader: None,
+            account: None,
+            iid: None,
+            status: Status::Observe,
+            signal: None,
+            pending: None,
+            trade: None,
+            entry: None,
+            exits: Vec::new(),
+            position: 0,
+            stops: None,
+            reason: String::new(),
+        }
+    }
+    /// Manage stop of position, see [`StopManager`].
+    ///
+    /// # ru
+    /// Сопровождение стопа позиции: после входа исполнитель выставляет
+    /// частичные тейки менеджера, а основной тейк сигнала - на
+    /// оставшиеся лоты. Когда менеджер подтягивает стоп или частичный
+    /// тейк уменьшает позицию, стоп лосс переставляется.
+    pub fn stops(mut self, stops: StopManager) -> Self {
+        self.stops = Some(stops);
+
+        self
+    }
+    /// Source of signals.
+    ///
+    /// # ru
+    /// Источник сигналов исполнителя.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+    /// Position in units.
+    ///
+    /// # ru
+    /// Позиция исполнителя в штуках.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+    /// Lots for signal strength.
+    ///
+    /// # ru
+    /// Количество лотов для силы сигнала.
+    pub fn lots_for(&self, strength: f64) -> u32 {
+        let lots = (self.lots as f64 * strength).round() as u32;
+
+        lots.clamp(1, self.lots.max(1))
+    }
+
+    // private
+    fn on_signal(&mut self, signal: Signal) {
+        self.send(Action::Signal(signal.clone()));
+        if signal.strength < self.min_strength {
+            return;
+        }
+
+        match self.status {
+            Status::Observe => self.enter(signal),
+            Status::Active => {
+                let current = self.signal.as_ref().unwrap();
+                if signal.kind != current.kind {
+                    self.reason = "opposite signal".to_string();
+                    self.pending = Some(signal);
+                    self.exit();
+                }
+            }
+            _ => (),
+        }
+    }
+    fn enter(&mut self, signal: Signal) {
+        let (direction, kind) = match signal.kind {
+            TradeKind::Long => (Direction::Buy, "long"),
+            TradeKind::Short => (Direction::Sell, "short"),
+        };
+        let lots = self.lots_for(signal.strength);
+        let order = match signal.entry {
+            Some(price) => Order::Limit(LimitOrder::New(LimitOrder::new(
+                direction, lots, price,
+            ))),
+            None => Order::Market(MarketOrder::New(MarketOrder::new(
+                direction, lots,
+            ))),
+        };
+
+        let trade = Trade::new(
+            signal.ts,
+            self.source.name(),
+            signal.kind.clone(),
+            signal.iid.clone(),
+        );
+        self.trade = Some(Trade::New(trade));
+        self.reason = format!("signal {kind} {:.2}", signal.strength);
+        self.signal = Some(signal);
+        self.status = Status::Entering;
+        self.post(order);
+    }
+    fn expire(&mut self, ts: i64) {
+        let Some(signal) = &self.signal else {
+            return;
+        };
+        if signal.is_valid(ts) {
+            return;
+        }
+        let Some(entry) = self.entry.clone() else {
+            return;
+        };
+
+        self.reason = "signal expired".to_string();
+        self.status = Status::Canceling;
+        self.cancel(entry);
+    }
+    fn exit(&mut self) {
+        self.status = Status::Exiting;
+        for stop in std::mem::take(&mut self.exits) {
+            self.cancel(Order::Stop(StopOrder::Posted(stop)));
+        }
+
+        let direction = if self.position > 0 {
+            Direction::Sell
+        } else {
+            Direction::Buy
+        };
+        let order = MarketOrder::new(direction, self.position_lots());
+        self.post(Order::Market(MarketOrder::New(order)));
+    }
+    fn post_exits(&mut self, partials: Vec<PartialTake>) {
+        let signal = self.signal.as_ref().unwrap();
+        let direction = match signal.kind {
+            TradeKind::Long => Direction::Sell,
+            TradeKind::Short => Direction::Buy,
+        };
+        let lots = self.position_lots();
+        let partial_lots: u32 = partials.iter().map(|i| i.lots).sum();
+
+        let mut orders = Vec::new();
+        if let Some(price) = signal.stop {
+            let kind = StopOrderKind::StopLoss;
+            orders.push(StopOrder::new(
+                kind,
+                direction.clone(),
+                lots,
+                price,
+                None,
+            ));
+        }
+        if let Some(price) = signal.take {
+            let kind = StopOrderKind::TakeProfit;
+            let lots = lots - partial_lots;
+            orders.push(StopOrder::new(
+                kind,
+                direction.clone(),
+                lots,
+                price,
+                None,
+            ));
+        }
+        for take in partials {
+            let kind = StopOrderKind::TakeProfit;
+            orders.push(StopOrder::new(
+                kind,
+                direction.clone(),
+                take.lots,
+                take.price,
+                None,
+            ));
+        }
+
+        for order in orders {
+            self.post(Order::Stop(StopOrder::New(order)));
+        }
+    }
+    fn filled(&mut self, order: Order) {
+        let ts = order.operation().unwrap().ts;
+        let quantity = order.operation().unwrap().quantity as i64;
+        match order.direction() {
+            Direction::Buy => self.position += quantity,
+            Direction::Sell => self.position -= quantity,
+        }
+
+        match self.trade.take() {
+            Some(Trade::New(trade)) => {
+                self.notify_entry(&order);
+                let partials = self.open_stops(&order, ts);
+                self.trade = Some(Trade::Opened(trade.open(order)));
+                self.entry = None;
+                self.status = Status::Active;
+                self.post_exits(partials);
+            }
+            Some(Trade::Opened(mut trade)) => {
+                trade.add_order(order);
+                if self.position != 0 {
+                    self.trade = Some(Trade::Opened(trade));
+                    // частичный тейк - стоп на оставшиеся лоты
+                    if self.status == Status::Active
+                        && let Some(price) = self.current_stop()
+                    {
+                        self.move_stop(price);
+                    }
+                    return;
+                }
+                for stop in std::mem::take(&mut self.exits) {
+                    self.cancel(Order::Stop(StopOrder::Posted(stop)));
+                }
+                let a = Action::TradeClosed(Trade::Closed(trade.close()));
+                self.send(a);
+                self.reset();
+
+                if let Some(signal) = self.pending.take()
+                    && signal.is_valid(ts)
+                {
+                    self.enter(signal);
+                }
+            }
+            other => self.trade = other,
+        }
+    }
+    fn notify_entry(&self, order: &Order) {
+        let operation = order.operation().unwrap();
+        let price = operation.value / operation.quantity as f64;
+        let side = match order.direction() {
+            Direction::Buy => "long",
+            Direction::Sell => "short",
+        };
+        let ticker = self.iid.as_ref().unwrap().ticker();
+        let mut message = format!("entered {side} {ticker} @ {price}");
+        if let Some(stop) = self.signal.as_ref().and_then(|i| i.stop) {
+            message.push_str(&format!(", stop {stop}"));
+        }
+
+        let trader = self.trader.as_ref().unwrap();
+        self.notify(trader, NotifyLevel::Info, &message);
+    }
+    fn posted_stop(&mut self, stop: PostedStopOrder) {
+        if let Some(Trade::Opened(trade)) = &mut self.trade {
+            match stop.kind {
+                StopOrderKind::StopLoss => trade.set_stop(stop.clone()),
+                StopOrderKind::TakeProfit => trade.set_take(stop.clone()),
+            }
+        }
+        self.exits.push(stop);
+    }
+    fn triggered_stop(&mut self, broker_id: &str) {
+        let lots = self.position_lots();
+        let partial = self.exits.iter().any(|i| {
+            i.broker_id == broker_id
+                && i.kind == StopOrderKind::TakeProfit
+                && i.lots < lots
+        });
+        self.exits.retain(|i| i.broker_id != broker_id);
+        if partial {
+            self.reason = "partial take".to_string();
+            return;
+        }
+        if self.status == Status::Active {
+            self.reason = "stop triggered".to_string();
+            self.status = Status::Exiting;
+        }
+    }
+    fn open_stops(&mut self, order: &Order, ts: i64) -> Vec<PartialTake> {
+        let lots = self.position_lots();
+        let signal = self.signal.as_ref().unwrap();
+        let Some(stops) = &mut self.stops else {
+            return Vec::new();
+        };
+        let operation = order.operation().unwrap();
+        let price = operation.value / operation.quantity as f64;
+
+        stops.open(
+            self.iid.as_ref().unwrap(),
+            signal.kind.clone(),
+            price,
+            signal.stop,
+            lots,
+            ts,
+        )
+    }
+    /// Цена стоп лосса: от менеджера стопов или из сигнала.
+    fn current_stop(&self) -> Option<f64> {
+        match &self.stops {
+            Some(stops) => stops.stop(),
+            None => self.signal.as_ref().and_then(|i| i.stop),
+        }
+    }
+    /// Переставляет стоп лосс на цену, на все лоты позиции.
+    fn move_stop(&mut self, price: f64) {
+        let (old, exits) = std::mem::take(&mut self.exits)
+            .into_iter()
+            .partition(|i| i.kind == StopOrderKind::StopLoss);
+        self.exits = exits;
+        for stop in old {
+            self.cancel(Order::Stop(StopOrder::Posted(stop)));
+        }
+
+        let direction = if self.position > 0 {
+            Direction::Sell
+        } else {
+            Direction::Buy
+        };
+        let kind = StopOrderKind::StopLoss;
+        let lots = self.position_lots();
+        let order = StopOrder::new(kind, direction, lots, price, None);
+        self.post(Order::Stop(StopOrder::New(order)));
+    }
+    fn reset(&mut self) {
+        if let Some(stops) = &mut self.stops {
+            stops.close();
+        }
+        self.status = Status::Observe;
+        self.signal = None;
+        self.trade = None;
+        self.entry = None;
+    }
+    fn position_lots(&self) -> u32 {
+        let lot = self.iid.as_ref().unwrap().lot() as i64;
+
+        (self.position.abs() / lot) as u32
+    }
+    fn post(&self, order: Order) {
+        self.send(Action::Post(self.order_action(order)));
+    }
+    fn cancel(&self, order: Order) {
+        self.send(Action::Cancel(self.order_action(order)));
+    }
+    fn order_action(&self, order: Order) -> OrderAction {
+        OrderAction::new(
+            self.account.clone().unwrap(),
+            self.iid.clone().unwrap(),
+            self.source.name(),
+            order,
+        )
+    }
+    fn send(&self, a: Action) {
+        self.trader.as_ref().unwrap().send(a).unwrap();
+    }
+}
+impl<S: SignalSource + Default> Default for Executor<S> {
+    fn default() -> Self {
+        Self::new(S::default(), 1, 0.0)
+    }
+}
+impl<S: SignalSource> Strategy for Executor<S> {
+    fn name(&self) -> &'static str {
+        self.source.name()
+    }
+    fn version(&self) -> &'static str {
+        self.source.version()
+    }
+    fn param_values(&self) -> ParamSet {
+        self.source.param_values()
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        let mut timeframes = self.source.timeframes();
+        if let Some(stops) = &self.stops
+            && !timeframes.contains(&stops.tf())
+        {
+            timeframes.push(stops.tf());
+        }
+
+        timeframes
+    }
+    fn universe(&self) -> Vec<String> {
+        self.source.universe()
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        self.trader = Some(trader);
+        self.account = Some(account);
+        self.iid = Some(asset.iid().clone());
+        self.source.init(asset);
+    }
+    fn process(&mut self, asset: &Asset) {
+        let Some(bar) = asset.chart(TimeFrame::M1).and_then(|i| i.now())
+        else {
+            return;
+        };
+        let ts = bar.ts;
+
+        if self.status == Status::Entering {
+            self.expire(ts);
+        }
+        if self.status == Status::Active
+            && let Some(stops) = &mut self.stops
+            && let Some(price) = stops.update(asset)
+        {
+            self.reason = stops.reason().to_string();
+            self.move_stop(price);
+        }
+        if let Some(signal) = self.source.signal(asset) {
+            self.on_signal(signal);
+        }
+    }
+    fn order_event(&mut self, e: OrderEvent) {
+        let order = e.order;
+
+        if !order.is_stop() && order.is_filled() {
+            self.filled(order);
+            return;
+        }
+        match order {
+            Order::Limit(LimitOrder::Posted(_))
+                if self.status == Status::Entering =>
+            {
+                self.entry = Some(order);
+            }
+            Order::Limit(LimitOrder::Canceled(_))
+                if self.status == Status::Canceling =>
+            {
+                self.reset();
+            }
+            Order::Stop(StopOrder::Posted(stop)) => self.posted_stop(stop),
+            Order::Stop(StopOrder::Triggered(stop)) => {
+                self.triggered_stop(stop.stop_id());
+            }
+            Order::Market(MarketOrder::Rejected(_))
+            | Order::Limit(LimitOrder::Rejected(_)) => {
+                log::warn!("{} order rejected: {order}", self.name());
+                if self.status == Status::Entering {
+                    self.reset();
+                }
+            }
+            _ => (),
+        }
+    }
+    fn reason(&self) -> String {
+        self.reason.clone()
+    }
+    fn context(&self, _asset: &Asset) -> Vec<(String, String)> {
+        let Some(signal) = &self.signal else {
+            return Vec::new();
+        };
+        let price = |p: Option<f64>| match p {
+            Some(p) => p.to_string(),
+            None => "-".to_string(),
+        };
+
+        vec![
+            ("signal".to_string(), signal.kind.to_string()),
+            ("strength".to_string(), format!("{:.2}", signal.strength)),
+            ("entry".to_string(), price(signal.entry)),
+            ("stop".to_string(), price(signal.stop)),
+            ("take".to_string(), price(signal.take)),
+        ]
+    }
+    fn stop(&self) -> Option<f64> {
+        self.signal.as_ref().and_then(|i| i.stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarEvent};
+
+    use super::*;
+    use crate::ensemble::Book;
+
+    const TS: i64 = 1_700_000_040_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+
+    fn bar(n: i64, price: f64) -> Bar {
+        Bar::new(TS + n * MINUTE, price, price + 1.0, price - 1.0, price, 1)
+    }
+
+    /// Источник для тестов: сигналы по номерам баров.
+    struct Script {
+        signals: Vec<(i64, Signal)>,
+    }
+    impl SignalSource for Script {
+        fn name(&self) -> &'static str {
+            "Script"
+        }
+        fn signal(&mut self, asset: &Asset) -> Option<Signal> {
+            let ts = asset.chart(TimeFrame::M1)?.now()?.ts;
+            let n = (ts - TS) / MINUTE;
+            let i = self.signals.iter().position(|i| i.0 == n)?;
+
+            Some(self.signals.remove(i).1)
+        }
+    }
+    fn signal(n: i64, kind: TradeKind, strength: f64, ttl: i64) -> Signal {
+        let ttl = TimeDelta::minutes(ttl);
+        Signal::new(TS + n * MINUTE, "Script", iid(), kind, strength, ttl)
+    }
+
+    /// Исполнитель с виртуальной книгой ордеров вместо брокера.
+    struct Run {
+        executor: Executor<Script>,
+        asset: Asset,
+        rx: UnboundedReceiver<Action>,
+        book: Book,
+        signals: Vec<Signal>,
+        notices: Vec<String>,
+        trades: Vec<Trade>,
+        canceled: usize,
+    }
+    impl Run {
+        fn new(signals: Vec<(i64, Signal)>) -> Self {
+            let script = Script { signals };
+            Self::with(Executor::new(script, 4, 0.3))
+        }
+        fn with(mut executor: Executor<Script>) -> Self {
+            let mut asset = Asset::from_iid(iid());
+            asset.load_chart_empty(TimeFrame::M1);
+            let (tx, rx) = mpsc::unbounded_channel();
+            let account = Account::new("Test", "Test_ID");
+            executor.init(tx, account, &mut asset);
+
+            Self {
+                executor,
+                asset,
+                rx,
+                book: Book::default(),
+                signals: Vec::new(),
+                notices: Vec::new(),
+                trades: Vec::new(),
+                canceled: 0,
+            }
+        }
+        fn bar(&mut self, n: i64, price: f64) {
+            let bar = bar(n, price);
+            let lot = iid().lot();
+            let figi = iid().figi().clone();
+            self.asset
+                .bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+
+            let events = self.book.market(&bar, lot);
+            self.deliver(events, &bar);
+            self.executor.process(&self.asset);
+            self.deliver(Vec::new(), &bar);
+        }
+        fn deliver(&mut self, mut events: Vec<Order>, bar: &Bar) {
+            loop {
+                for order in events.drain(..) {
+                    let e = OrderEvent::new(
+                        Account::new("Test", "Test_ID"),
+                        iid(),
+                        "Script".to_string(),
+                        order,
+                    );
+                    self.executor.order_event(e);
+                }
+                while let Ok(a) = self.rx.try_recv() {
+                    match a {
+                        Action::Signal(s) => self.signals.push(s),
+                        Action::Notify(a) => self.notices.push(a.message),
+                        Action::TradeClosed(t) => self.trades.push(t),
+                        Action::Cancel(_) => {
+                            self.canceled += 1;
+                            events.extend(self.book.action(a, bar, 10));
+                        }
+                        a => events.extend(self.book.action(a, bar, 10)),
+                    }
+                }
+                if events.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn market_entry_and_take() {
+        let s = signal(0, TradeKind::Long, 0.5, 5).stop(95.0).take(104.0);
+        let mut run = Run::new(vec![(0, s)]);
+
+        // вход по рынку по закрытию 100, 0.5 * 4 = 2 лота
+        run.bar(0, 100.0);
+        assert_eq!(run.signals.len(), 1);
+        assert_eq!(run.executor.position(), 20);
+        assert_eq!(run.executor.reason(), "signal long 0.50");
+        assert_eq!(run.executor.stop(), Some(95.0));
+        assert_eq!(run.notices, vec!["entered long SBER @ 100, stop 95"]);
+
+        run.bar(1, 101.0);
+        assert_eq!(run.executor.position(), 20);
+
+        // 102.5..104.5 - тейк 104, стоп снят
+        run.bar(2, 103.5);
+        assert_eq!(run.executor.position(), 0);
+        assert_eq!(run.canceled, 1);
+        let Some(Trade::Closed(trade)) = run.trades.pop() else {
+            panic!();
+        };
+        assert_eq!(trade.strategy, "Script");
+        assert_eq!(trade.result(), 80.0);
+        assert!(run.executor.stop().is_none());
+    }
+    #[test]
+    fn limit_entry_expired() {
+        let s = signal(0, TradeKind::Long, 1.0, 2).entry(90.0);
+        let weak = signal(3, TradeKind::Long, 0.1, 2);
+        let mut run = Run::new(vec![(0, s), (3, weak)]);
+
+        run.bar(0, 100.0);
+        run.bar(1, 100.0);
+        assert_eq!(run.canceled, 0);
+
+        // сигнал истек - лимитка снята
+        run.bar(2, 100.0);
+        assert_eq!(run.canceled, 1);
+        assert_eq!(run.executor.reason(), "signal expired");
+
+        // слабый сигнал записан, но не исполнен
+        run.bar(3, 100.0);
+        assert_eq!(run.signals.len(), 2);
+        assert_eq!(run.executor.position(), 0);
+        run.bar(4, 89.0);
+        assert_eq!(run.executor.position(), 0);
+    }
+    #[test]
+    fn opposite_signal() {
+        let long = signal(0, TradeKind::Long, 1.0, 5);
+        let short = signal(2, TradeKind::Short, 0.5, 5);
+        let mut run = Run::new(vec![(0, long), (2, short)]);
+
+        run.bar(0, 100.0);
+        assert_eq!(run.executor.position(), 40);
+        run.bar(1, 100.0);
+
+        // сигнал в другую сторону - выход по рынку и вход в шорт
+        run.bar(2, 99.0);
+        assert_eq!(run.executor.position(), -20);
+        assert_eq!(run.executor.reason(), "signal short 0.50");
+        let Some(Trade::Closed(trade)) = run.trades.pop() else {
+            panic!();
+        };
+        assert_eq!(trade.result(), -40.0);
+    }
+    #[test]
+    fn stops_partial_and_breakeven() {
+        let s = signal(0, TradeKind::Long, 1.0, 5).stop(95.0).take(110.0);
+        let script = Script {
+            signals: vec![(0, s)],
+        };
+        let stops = StopManager::new(TimeFrame::M1)
+            .breakeven(1.0)
+            .partial(1.0, 0.5);
+        let mut run = Run::with(Executor::new(script, 4, 0.3).stops(stops));
+
+        // вход 4 лота по 100: стоп на 4, тейк 110 на 2, частичный 105 на 2
+        run.bar(0, 100.0);
+        assert_eq!(run.executor.position(), 40);
+        run.bar(1, 103.0);
+        assert_eq!(run.canceled, 0);
+
+        // частичный тейк 105 - стоп 95 переставлен на 2 лота
+        run.bar(2, 105.5);
+        assert_eq!(run.executor.position(), 20);
+        assert_eq!(run.executor.reason(), "partial take");
+        assert_eq!(run.canceled, 1);
+
+        // закрылся бар с хаем 106.5 > +1R - стоп в безубыток
+        run.bar(3, 106.0);
+        assert_eq!(run.executor.reason(), "breakeven");
+        assert_eq!(run.canceled, 2);
+
+        // стоп 100 - позиция закрыта, тейк 110 снят
+        run.bar(4, 100.5);
+        assert_eq!(run.executor.position(), 0);
+        assert_eq!(run.canceled, 3);
+        let Some(Trade::Closed(trade)) = run.trades.pop() else {
+            panic!();
+        };
+        assert_eq!(trade.result(), 100.0);
+    }
+}
@@ -0,0 +1,492 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{
+    Account, Action, Asset, Direction, Iid, LimitOrder, Order, OrderAction,
+    OrderEvent, TimeFrame, Trade, TradeKind,
+};
+
+use crate::{ParamSet, Params, Strategy, StrategyParams};
+
+const NAME: &str = "Grid";
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+/// Side of grid.
+///
+/// # ru
+/// Сторона сетки: Long - уровни покупок ниже центра, Short - уровни
+/// продаж выше центра, Both - обе стороны.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridSide {
+    Long,
+    Short,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LevelState {
+    /// Ордера нет.
+    Idle,
+    /// Выставлена лимитка входа.
+    Entry,
+    /// Вход исполнен, тейк еще не выставлен.
+    Holding,
+    /// Выставлен тейк уровня.
+    Exit,
+}
+
+#[derive(Debug)]
+struct Level {
+    /// Направление входа.
+    direction: Direction,
+    price: f64,
+    take: f64,
+    state: LevelState,
+    trade: Option<Trade>,
+}
+
+/// Grid trading strategy.
+///
+/// # ru
+/// Сетка лимитных ордеров для торговли в боковике. На первом баре
+/// центр сетки - цена закрытия, уровни через step процентов от центра,
+/// на каждом уровне лимитка на lots лотов. Исполненный уровень
+/// закрывается лимиткой тейка на take процентов от цены уровня, после
+/// тейка уровень выставляется снова. Каждый круг уровня - отдельный
+/// трейд.
+///
+/// Объем позиции в одну сторону, включая выставленные входы, не
+/// больше max_lots лотов: уровни сверх лимита ждут, пока закроются
+/// другие. Параметры levels, step и take оптимизируются в тестере.
+///
+/// ```ignore
+/// let grid = Grid::new(GridSide::Both).levels(4).step(0.3).take(0.3);
+/// ```
+#[derive(Debug, StrategyParams)]
+pub struct Grid {
+    trader: Option<Trader>,
+    account: Option<Account>,
+    iid: Option<Iid>,
+
+    side: GridSide,
+    #[param(values = [3, 5, 8])]
+    levels: u32,
+    #[param(begin = 0.25, end = 1.0, step = 0.25)]
+    step: f64,
+    #[param(begin = 0.25, end = 1.0, step = 0.25)]
+    take: f64,
+    lots: u32,
+    max_lots: u32,
+
+    grid: Vec<Level>,
+    reason: String,
+}
+impl Grid {
+    /// Create grid with default settings.
+    ///
+    /// # ru
+    /// Конструктор: 5 уровней через 0.5%, тейк 0.5%, 1 лот на уровень,
+    /// не больше 5 лотов в одну сторону.
+    pub fn new(side: GridSide) -> Self {
+        Self {
+            trader: None,
+            account: None,
+            iid: None,
+            side,
+            levels: 5,
+            step: 0.5,
+            take: 0.5,
+            lots: 1,
+            max_lots: 5,
+            grid: Vec::new(),
+            reason: String::new(),
+        }
+    }
+    /// Set levels count on each side.
+    ///
+    /// # ru
+    /// Количество уровней с каждой стороны центра.
+    pub fn levels(mut self, levels: u32) -> Self {
+        self.levels = levels;
+        self
+    }
+    /// Set distance between levels, percent.
+    ///
+    /// # ru
+    /// Расстояние между уровнями в процентах от центра.
+    pub fn step(mut self, step: f64) -> Self {
+        self.step = step;
+        self
+    }
+    /// Set take profit of level, percent.
+    ///
+    /// # ru
+    /// Тейк профит уровня в процентах от цены уровня.
+    pub fn take(mut self, take: f64) -> Self {
+        self.take = take;
+        self
+    }
+    /// Set lots of each level.
+    ///
+    /// # ru
+    /// Количество лотов на каждом уровне.
+    pub fn lots(mut self, lots: u32) -> Self {
+        self.lots = lots;
+        self
+    }
+    /// Set inventory cap, lots on one side.
+    ///
+    /// # ru
+    /// Лимит позиции в лотах в одну сторону.
+    pub fn max_lots(mut self, max_lots: u32) -> Self {
+        self.max_lots = max_lots;
+        self
+    }
+    /// Position in lots.
+    ///
+    /// # ru
+    /// Позиция сетки в лотах: исполненные и еще не закрытые уровни.
+    pub fn position(&self) -> i64 {
+        self.grid
+            .iter()
+            .filter(|l| {
+                matches!(l.state, LevelState::Holding | LevelState::Exit)
+            })
+            .map(|l| match l.direction {
+                Direction::Buy => self.lots as i64,
+                Direction::Sell => -(self.lots as i64),
+            })
+            .sum()
+    }
+
+    // private
+    fn build(&mut self, center: f64) {
+        let iid = self.iid.as_ref().unwrap();
+        let mut sides = Vec::new();
+        if self.side != GridSide::Short {
+            sides.push(Direction::Buy);
+        }
+        if self.side != GridSide::Long {
+            sides.push(Direction::Sell);
+        }
+
+        for direction in sides {
+            let sign = match direction {
+                Direction::Buy => -1.0,
+                Direction::Sell => 1.0,
+            };
+            for n in 1..=self.levels {
+                let offset = self.step * n as f64 / 100.0;
+                let price = iid.round_to_step(center * (1.0 + sign * offset));
+                let take = price * (1.0 - sign * self.take / 100.0);
+                self.grid.push(Level {
+                    direction: direction.clone(),
+                    price,
+                    take: iid.round_to_step(take),
+                    state: LevelState::Idle,
+                    trade: None,
+                });
+            }
+        }
+    }
+    fn post(&mut self) {
+        for i in 0..self.grid.len() {
+            match self.grid[i].state {
+                LevelState::Idle if self.allowed(i) => self.post_entry(i),
+                LevelState::Holding => self.post_exit(i),
+                _ => (),
+            }
+        }
+    }
+    // лимит позиции в сторону уровня, выставленные входы тоже считаются
+    fn allowed(&self, i: usize) -> bool {
+        let direction = &self.grid[i].direction;
+        let committed = self
+            .grid
+            .iter()
+            .filter(|l| l.direction == *direction)
+            .filter(|l| l.state != LevelState::Idle)
+            .count() as u32;
+
+        (committed + 1) * self.lots <= self.max_lots
+    }
+    fn post_entry(&mut self, i: usize) {
+        let level = &mut self.grid[i];
+        level.state = LevelState::Entry;
+        let direction = level.direction.clone();
+        let order = LimitOrder::new(direction, self.lots, level.price);
+
+        self.send(Order::Limit(LimitOrder::New(order)));
+    }
+    fn post_exit(&mut self, i: usize) {
+        let level = &mut self.grid[i];
+        level.state = LevelState::Exit;
+        let direction = match level.direction {
+            Direction::Buy => Direction::Sell,
+            Direction::Sell => Direction::Buy,
+        };
+        let order = LimitOrder::new(direction, self.lots, level.take);
+
+        self.send(Order::Limit(LimitOrder::New(order)));
+    }
+    // уровень, чей выставленный ордер - direction по price
+    fn find(&self, direction: &Direction, price: f64) -> Option<usize> {
+        self.grid.iter().position(|l| match l.state {
+            LevelState::Entry => {
+                l.direction == *direction && l.price == price
+            }
+            LevelState::Exit => l.direction != *direction && l.take == price,
+            _ => false,
+        })
+    }
+    fn filled(&mut self, i: usize, order: Order) {
+        let ts = order.operation().unwrap().ts;
+        let iid = self.iid.clone().unwrap();
+        let level = &mut self.grid[i];
+
+        match level.state {
+            LevelState::Entry => {
+                let kind = match level.direction {
+                    Direction::Buy => TradeKind::Long,
+                    Direction::Sell => TradeKind::Short,
+                };
+                let trade = Trade::new(ts, NAME, kind, iid).open(order);
+                level.trade = Some(Trade::Opened(trade));
+                level.state = LevelState::Holding;
+                self.reason = format!("grid level {}", level.price);
+                self.post_exit(i);
+            }
+            LevelState::Exit => {
+                let Some(Trade::Opened(mut trade)) = level.trade.take()
+                else {
+                    return;
+                };
+                trade.add_order(order);
+                level.state = LevelState::Idle;
+                self.reason = format!("grid take {}", level.take);
+
+                let a = Action::TradeClosed(Trade::Closed(trade.close()));
+                self.trader.as_ref().unwrap().send(a).unwrap();
+                self.post();
+            }
+            _ => (),
+        }
+    }
+    fn rejected(&mut self, i: usize) {
+        let level = &mut self.grid[i];
+        level.state = match level.state {
+            LevelState::Entry => LevelState::Idle,
+            LevelState::Exit => LevelState::Holding,
+            state => state,
+        };
+    }
+    fn send(&self, order: Order) {
+        let a = OrderAction::new(
+            self.account.clone().unwrap(),
+            self.iid.clone().unwrap(),
+            NAME,
+            order,
+        );
+
+        self.trader.as_ref().unwrap().send(Action::Post(a)).unwrap();
+    }
+}
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new(GridSide::Long)
+    }
+}
+impl Strategy for Grid {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+    fn param_values(&self) -> ParamSet {
+        Params::values(self)
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        vec![TimeFrame::M1]
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        self.trader = Some(trader);
+        self.account = Some(account);
+        self.iid = Some(asset.iid().clone());
+        self.grid.clear();
+    }
+    fn process(&mut self, asset: &Asset) {
+        let Some(bar) = asset.chart(TimeFrame::M1).and_then(|i| i.now())
+        else {
+            return;
+        };
+
+        if self.grid.is_empty() {
+            self.build(bar.c);
+            self.reason = format!("grid center {}", bar.c);
+        }
+        self.post();
+    }
+    fn order_event(&mut self, e: OrderEvent) {
+        match &e.order {
+            Order::Limit(LimitOrder::Filled(o)) => {
+                if let Some(i) = self.find(&o.direction, o.price) {
+                    self.filled(i, e.order);
+                }
+            }
+            Order::Limit(LimitOrder::Rejected(o)) => {
+                log::warn!("{NAME} order rejected: {}", e.order);
+                if let Some(i) = self.find(&o.direction, o.price) {
+                    self.rejected(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    fn reason(&self) -> String {
+        self.reason.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarEvent};
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use super::*;
+    use crate::ensemble::Book;
+    use crate::{ParamSet, Params};
+
+    const TS: i64 = 1_700_000_040_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+
+    struct Run {
+        grid: Grid,
+        asset: Asset,
+        rx: UnboundedReceiver<Action>,
+        book: Book,
+        trades: Vec<Trade>,
+        posted: Vec<f64>,
+    }
+    impl Run {
+        fn new(grid: Grid) -> Self {
+            let mut grid = grid;
+            let mut asset = Asset::from_iid(iid());
+            asset.load_chart_empty(TimeFrame::M1);
+            let (tx, rx) = mpsc::unbounded_channel();
+            grid.init(tx, Account::new("Test", "Test_ID"), &mut asset);
+
+            Self {
+                grid,
+                asset,
+                rx,
+                book: Book::default(),
+                trades: Vec::new(),
+                posted: Vec::new(),
+            }
+        }
+        fn bar(&mut self, n: i64, price: f64) {
+            let bar = Bar::new(
+                TS + n * MINUTE,
+                price,
+                price + 1.0,
+                price - 1.0,
+                price,
+                1,
+            );
+            let figi = iid().figi().clone();
+            self.asset
+                .bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+
+            let events = self.book.market(&bar, 10);
+            self.deliver(events, &bar);
+            self.grid.process(&self.asset);
+            self.deliver(Vec::new(), &bar);
+        }
+        fn deliver(&mut self, mut events: Vec<Order>, bar: &Bar) {
+            loop {
+                for order in events.drain(..) {
+                    let e = OrderEvent::new(
+                        Account::new("Test", "Test_ID"),
+                        iid(),
+                        NAME.to_string(),
+                        order,
+                    );
+                    self.grid.order_event(e);
+                }
+                while let Ok(a) = self.rx.try_recv() {
+                    match a {
+                        Action::TradeClosed(t) => self.trades.push(t),
+                        Action::Post(a) => {
+                            if let Order::Limit(LimitOrder::New(o)) = &a.order
+                            {
+                                self.posted.push(o.price);
+                            }
+                            events.extend(self.book.action(
+                                Action::Post(a),
+                                bar,
+                                10,
+                            ));
+                        }
+                        a => events.extend(self.book.action(a, bar, 10)),
+                    }
+                }
+                if events.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn levels_and_cap() {
+        let grid = Grid::new(GridSide::Long).levels(2).step(1.0).take(1.0);
+        let mut run = Run::new(grid.max_lots(1));
+
+        // центр 100, уровни 99 и 98, лимит - только один уровень
+        run.bar(0, 100.0);
+        assert_eq!(run.posted, vec![99.0]);
+
+        // 98.5..100.5 - уровень 99 исполнен, тейк 99.99
+        run.bar(1, 99.5);
+        assert_eq!(run.grid.position(), 1);
+        assert_eq!(run.posted, vec![99.0, 99.99]);
+        assert_eq!(run.grid.reason(), "grid level 99");
+
+        // тейк исполнен, уровень выставлен снова
+        run.bar(2, 99.5);
+        assert_eq!(run.grid.position(), 0);
+        assert_eq!(run.posted, vec![99.0, 99.99, 99.0]);
+        let Some(Trade::Closed(trade)) = run.trades.pop() else {
+            panic!();
+        };
+        assert_eq!(trade.kind, TradeKind::Long);
+        assert!(trade.result() > 0.0);
+    }
+    #[test]
+    fn both_sides() {
+        let grid = Grid::new(GridSide::Both).levels(2).step(1.0).take(1.0);
+        let mut run = Run::new(grid);
+        run.bar(0, 100.0);
+        assert_eq!(run.posted, vec![99.0, 98.0, 101.0, 102.0]);
+
+        // 100.5..102.5 - продажа на 101 и 102
+        run.bar(1, 101.5);
+        assert_eq!(run.grid.position(), -2);
+    }
+    #[test]
+    fn params() {
+        assert_eq!(Grid::params().len(), 3);
+
+        let mut params = ParamSet::new();
+        params.set("levels", 8.0);
+        params.set("step", 0.25);
+        let grid = Grid::with_params(&params);
+        assert_eq!(grid.levels, 8);
+        assert_eq!(grid.step, 0.25);
+        assert_eq!(grid.take, 0.5);
+    }
+}
@@ -0,0 +1,374 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{Direction, LimitOrder, Order, StopOrder, StopOrderKind};
+
+/// Orders to cancel and post for intent.
+///
+/// # ru
+/// Что отправить брокеру, чтобы у него было то, что хочет стратегия:
+/// ордер на отмену и ордер на выставление. Пустой план - ордер уже
+/// есть, ничего делать не нужно.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IntentPlan {
+    pub cancel: Option<Order>,
+    pub post: Option<Order>,
+}
+impl IntentPlan {
+    pub fn is_empty(&self) -> bool {
+        self.cancel.is_none() && self.post.is_none()
+    }
+}
+
+/// Registry of order intents, idempotent posting across restarts.
+///
+/// # ru
+/// Реестр намерений стратегии по ордерам: "стоп на 276" под ключом
+/// "stop". Стратегия на каждом событии заново решает, какие ордера ей
+/// нужны, и вызывает [`Intents::place`] - повторное то же намерение
+/// ничего не отправляет, измененное отменяет прежний ордер и выставляет
+/// новый.
+///
+/// После перезапуска реестр пуст, а ордера у брокера остались. Трейдер
+/// при запуске передает стратегии активные ордера брокера, см.
+/// [`crate::Strategy::reconcile`], стратегия отдает их в
+/// [`Intents::reconcile`]. Первое же намерение, совпавшее с ордером
+/// брокера по типу, направлению, лотам и цене, забирает этот ордер
+/// себе вместо выставления нового. Ордера, которые так никто и не
+/// забрал, остаются в [`Intents::orphans`] - это могут быть и ордера
+/// другой стратегии на том же счете, отменять их вслепую не стоит.
+///
+/// ```ignore
+/// let order = StopOrder::new(StopOrderKind::StopLoss, Sell, 1, 276.0, None);
+/// let plan = self.intents.place("stop", Order::Stop(StopOrder::New(order)));
+/// if let Some(order) = plan.cancel { self.cancel(order) }
+/// if let Some(order) = plan.post { self.post(order) }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Intents {
+    intents: Vec<Intent>,
+    orphans: Vec<Order>,
+}
+impl Intents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Active broker orders at start.
+    ///
+    /// # ru
+    /// Активные ордера брокера при запуске. Намерения без выставленного
+    /// ордера сразу забирают совпавшие, остальные ордера ждут в orphans.
+    pub fn reconcile(&mut self, orders: Vec<Order>) {
+        self.orphans = orders.into_iter().filter(is_active).collect();
+        for intent in self.intents.iter_mut() {
+            if intent.order.is_none() {
+                intent.order = adopt(&mut self.orphans, &intent.spec);
+            }
+        }
+    }
+    /// Declare intent, order must be new.
+    ///
+    /// # ru
+    /// Объявляет намерение key - новый ордер, который должен стоять у
+    /// брокера. Возвращает, что для этого отправить: то же намерение -
+    /// пустой план, совпавший ордер брокера забирается без
+    /// выставления, измененное намерение - отмена прежнего выставленного
+    /// ордера и новый.
+    pub fn place(&mut self, key: &str, order: Order) -> IntentPlan {
+        let mut plan = IntentPlan::default();
+
+        if let Some(i) = self.find(key) {
+            if same(&self.intents[i].spec, &order) {
+                return plan;
+            }
+            let old = self.intents.remove(i);
+            plan.cancel = old.order;
+        }
+
+        let adopted = adopt(&mut self.orphans, &order);
+        if adopted.is_none() {
+            plan.post = Some(order.clone());
+        }
+        self.intents.push(Intent {
+            key: key.to_string(),
+            spec: order,
+            order: adopted,
+        });
+
+        plan
+    }
+    /// Drop intent, returns broker order to cancel.
+    ///
+    /// # ru
+    /// Снимает намерение. Возвращает выставленный ордер, который нужно
+    /// отменить, None - намерения нет или ордер еще не подтвержден.
+    pub fn cancel(&mut self, key: &str) -> Option<Order> {
+        let i = self.find(key)?;
+
+        self.intents.remove(i).order
+    }
+    /// Update intents by order event, returns stale order to cancel.
+    ///
+    /// # ru
+    /// Обновляет намерения по событию ордера: подтвержденный ордер
+    /// привязывается к своему намерению, исполненный, отмененный,
+    /// отклоненный или сработавший снимает его - следующий place
+    /// выставит ордер заново. Возвращает подтвержденный ордер, который
+    /// уже никому не нужен (намерение изменилось, пока он выставлялся),
+    /// его нужно отменить. Поэтому через реестр должны идти все
+    /// лимитные и стоп ордера стратегии.
+    pub fn order_event(&mut self, order: &Order) -> Option<Order> {
+        if is_active(order) {
+            let id = order.broker_id();
+            if let Some(intent) = self.intents.iter_mut().find(|i| {
+                i.order.as_ref().is_some_and(|o| o.broker_id() == id)
+            }) {
+                intent.order = Some(order.clone());
+                return None;
+            }
+            if let Some(intent) = self
+                .intents
+                .iter_mut()
+                .find(|i| i.order.is_none() && same(&i.spec, order))
+            {
+                intent.order = Some(order.clone());
+                return None;
+            }
+
+            return Some(order.clone());
+        }
+
+        let i = match order.broker_id() {
+            Some(id) => self.intents.iter().position(|i| {
+                i.order.as_ref().is_some_and(|o| o.broker_id() == Some(id))
+            }),
+            // отклоненный ордер без broker_id - по совпадению
+            None => self
+                .intents
+                .iter()
+                .position(|i| i.order.is_none() && same(&i.spec, order)),
+        };
+        if let Some(i) = i {
+            self.intents.remove(i);
+        }
+
+        None
+    }
+    /// Broker order of intent, None - not posted or not confirmed yet.
+    ///
+    /// # ru
+    /// Ордер брокера намерения key, None - намерения нет или ордер еще
+    /// не подтвержден.
+    pub fn get(&self, key: &str) -> Option<&Order> {
+        self.intents[self.find(key)?].order.as_ref()
+    }
+    /// Broker orders not claimed by any intent.
+    ///
+    /// # ru
+    /// Ордера брокера из reconcile, которые не забрало ни одно
+    /// намерение.
+    pub fn orphans(&self) -> &[Order] {
+        &self.orphans
+    }
+    pub fn clear(&mut self) {
+        self.intents.clear();
+        self.orphans.clear();
+    }
+
+    fn find(&self, key: &str) -> Option<usize> {
+        self.intents.iter().position(|i| i.key == key)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Intent {
+    key: String,
+    /// Новый ордер, каким его хочет видеть стратегия.
+    spec: Order,
+    /// Выставленный ордер брокера.
+    order: Option<Order>,
+}
+
+/// Ордер стоит у брокера и может исполниться.
+fn is_active(order: &Order) -> bool {
+    match order {
+        Order::Limit(o) => o.is_posted() || o.is_partially_filled(),
+        Order::Stop(o) => o.is_posted(),
+        Order::Market(_) => false,
+    }
+}
+/// Забирает из списка ордер, совпавший с намерением.
+fn adopt(orders: &mut Vec<Order>, spec: &Order) -> Option<Order> {
+    let i = orders.iter().position(|o| same(spec, o))?;
+
+    Some(orders.remove(i))
+}
+/// Тот же ордер по сути: тип, направление, лоты и цены, статус и
+/// broker_id не важны.
+fn same(a: &Order, b: &Order) -> bool {
+    let (Some(a), Some(b)) = (spec(a), spec(b)) else {
+        return false;
+    };
+
+    a.kind == b.kind
+        && a.direction == b.direction
+        && a.lots == b.lots
+        && eq(a.price, b.price)
+        && match (a.exec_price, b.exec_price) {
+            (Some(x), Some(y)) => eq(x, y),
+            (x, y) => x.is_none() && y.is_none(),
+        }
+}
+// цена ордера от брокера после перевода из его формата может
+// отличаться в последних знаках
+fn eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+/// Суть ордера без статуса, None - ордер не выставляется (рыночный)
+/// или уже не активен.
+struct Spec<'a> {
+    kind: Option<&'a StopOrderKind>,
+    direction: &'a Direction,
+    lots: u32,
+    price: f64,
+    exec_price: Option<f64>,
+}
+fn spec(order: &Order) -> Option<Spec<'_>> {
+    let limit = |direction, lots, price| Spec {
+        kind: None,
+        direction,
+        lots,
+        price,
+        exec_price: None,
+    };
+
+    match order {
+        Order::Limit(LimitOrder::New(o)) => {
+            Some(limit(&o.direction, o.lots, o.price))
+        }
+        Order::Limit(LimitOrder::Posted(o)) => {
+            Some(limit(&o.direction, o.lots, o.price))
+        }
+        Order::Limit(LimitOrder::PartiallyFilled(o)) => {
+            Some(limit(&o.direction, o.lots, o.price))
+        }
+        Order::Stop(StopOrder::New(o)) => Some(Spec {
+            kind: Some(&o.kind),
+            direction: &o.direction,
+            lots: o.lots,
+            price: o.stop_price,
+            exec_price: o.exec_price,
+        }),
+        Order::Stop(StopOrder::Posted(o)) => Some(Spec {
+            kind: Some(&o.kind),
+            direction: &o.direction,
+            lots: o.lots,
+            price: o.stop_price,
+            exec_price: o.exec_price,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(price: f64) -> Order {
+        let order = StopOrder::new(
+            StopOrderKind::StopLoss,
+            Direction::Sell,
+            1,
+            price,
+            None,
+        );
+
+        Order::Stop(StopOrder::New(order))
+    }
+    fn posted(order: &Order, id: &str) -> Order {
+        match order.clone() {
+            Order::Stop(StopOrder::New(o)) => {
+                Order::Stop(StopOrder::Posted(o.post(id)))
+            }
+            Order::Limit(LimitOrder::New(o)) => {
+                Order::Limit(LimitOrder::Posted(o.post(id)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn place_and_replace() {
+        let mut intents = Intents::new();
+
+        // первый раз - выставить
+        let plan = intents.place("stop", stop(276.0));
+        assert_eq!(plan.post, Some(stop(276.0)));
+        assert!(plan.cancel.is_none());
+        // то же намерение, ордер еще не подтвержден - ничего
+        assert!(intents.place("stop", stop(276.0)).is_empty());
+        assert!(intents.get("stop").is_none());
+
+        let order = posted(&stop(276.0), "1");
+        assert!(intents.order_event(&order).is_none());
+        assert_eq!(intents.get("stop"), Some(&order));
+        assert!(intents.place("stop", stop(276.0)).is_empty());
+
+        // новый уровень - отменить прежний и выставить новый
+        let plan = intents.place("stop", stop(278.0));
+        assert_eq!(plan.cancel, Some(order));
+        assert_eq!(plan.post, Some(stop(278.0)));
+
+        // сработавший стоп снимает намерение
+        let order = posted(&stop(278.0), "2");
+        intents.order_event(&order);
+        let Order::Stop(StopOrder::Posted(o)) = order else {
+            unreachable!()
+        };
+        intents
+            .order_event(&Order::Stop(StopOrder::Triggered(o.trigger("3"))));
+        assert!(intents.get("stop").is_none());
+        assert!(!intents.place("stop", stop(278.0)).is_empty());
+    }
+
+    #[test]
+    fn reconcile_after_restart() {
+        let take = Order::Limit(LimitOrder::New(LimitOrder::new(
+            Direction::Sell,
+            1,
+            290.0,
+        )));
+        let foreign = Order::Limit(LimitOrder::New(LimitOrder::new(
+            Direction::Buy,
+            2,
+            250.0,
+        )));
+        let broker = vec![
+            posted(&stop(276.0), "1"),
+            posted(&take, "2"),
+            posted(&foreign, "3"),
+        ];
+
+        // перезапуск: то же решение стратегии не выставляет ордера
+        let mut intents = Intents::new();
+        intents.reconcile(broker);
+        assert!(intents.place("stop", stop(276.0)).is_empty());
+        assert!(intents.place("take", take.clone()).is_empty());
+        assert_eq!(intents.get("take"), Some(&posted(&take, "2")));
+        assert_eq!(intents.orphans(), &[posted(&foreign, "3")]);
+
+        // стоп подтянули - отменяется забранный ордер брокера
+        let plan = intents.place("stop", stop(280.0));
+        assert_eq!(plan.cancel, Some(posted(&stop(276.0), "1")));
+        assert_eq!(plan.post, Some(stop(280.0)));
+
+        // ордер, подтвержденный после смены намерения, лишний
+        let stale = posted(&stop(276.0), "4");
+        assert_eq!(intents.order_event(&stale), Some(stale));
+    }
+}
@@ -5,8 +5,37 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+// макросы avin_derive ссылаются на ::avin_strategy, в том числе
+// внутри этого крейта
+extern crate self as avin_strategy;
+
 mod _strategy;
+mod dca;
+mod ensemble;
 mod examples;
+mod executor;
+mod grid;
+mod intents;
+mod meta;
+mod pair;
+mod params;
+mod plugin;
+mod stops;
+mod swings;
+mod target;
 
 pub use _strategy::Strategy;
+pub use avin_derive::StrategyParams;
+pub use dca::{Dca, Schedule};
+pub use ensemble::{Ensemble, Merge};
 pub use examples::*;
+pub use executor::{Executor, SignalSource};
+pub use grid::{Grid, GridSide};
+pub use intents::{IntentPlan, Intents};
+pub use meta::StrategyMeta;
+pub use pair::{LegsStatus, PairLegs};
+pub use params::{Param, ParamSet, Params};
+pub use plugin::{PLUGIN_ABI, Plugin};
+pub use stops::{PartialTake, StopManager};
+pub use swings::Swings;
+pub use target::{Execution, Rebalancer, TargetSource};
@@ -0,0 +1,98 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use crate::ParamSet;
+
+/// Identity of strategy instance: name, version and parameters.
+///
+/// # ru
+/// Паспорт экземпляра стратегии: имя, версия логики и значения
+/// параметров с их хэшем. Тестер пишет его в манифест запуска, а
+/// трейдер в журнал сделок, так результат каждой сделки можно отнести
+/// к точной версии стратегии и набору параметров, см.
+/// [`crate::Strategy::meta`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyMeta {
+    pub name: String,
+    pub version: String,
+    pub params: String,
+    pub params_hash: String,
+}
+impl StrategyMeta {
+    /// Create meta, empty params - strategy defaults.
+    ///
+    /// # ru
+    /// Создает паспорт, пустой набор параметров - параметры по
+    /// умолчанию, хэш "default" как у тестера.
+    pub fn new(name: &str, version: &str, params: &ParamSet) -> Self {
+        let params_hash = if params.is_empty() {
+            "default".to_string()
+        } else {
+            params.hash()
+        };
+
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            params: params.to_string(),
+            params_hash,
+        }
+    }
+    /// Version as semver (major, minor, patch).
+    ///
+    /// # ru
+    /// Версия как semver: "2" - это 2.0.0, "2.1" - 2.1.0. None -
+    /// версия не в формате semver, например "beta".
+    pub fn semver(&self) -> Option<(u64, u64, u64)> {
+        let mut parts = [0; 3];
+        for (i, part) in self.version.split('.').enumerate() {
+            if i == parts.len() {
+                return None;
+            }
+            parts[i] = part.parse().ok()?;
+        }
+
+        Some((parts[0], parts[1], parts[2]))
+    }
+}
+impl std::fmt::Display for StrategyMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} v{} #{}", self.name, self.version, self.params_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Grid, Strategy};
+
+    #[test]
+    fn semver() {
+        let params = ParamSet::new();
+        let meta = StrategyMeta::new("Grid", "2", &params);
+        assert_eq!(meta.semver(), Some((2, 0, 0)));
+        let meta = StrategyMeta::new("Grid", "2.1", &params);
+        assert_eq!(meta.semver(), Some((2, 1, 0)));
+        let meta = StrategyMeta::new("Grid", "2.1.3", &params);
+        assert_eq!(meta.semver(), Some((2, 1, 3)));
+        let meta = StrategyMeta::new("Grid", "2.1.3.4", &params);
+        assert_eq!(meta.semver(), None);
+        let meta = StrategyMeta::new("Grid", "beta", &params);
+        assert_eq!(meta.semver(), None);
+    }
+    #[test]
+    fn strategy_meta() {
+        let meta = StrategyMeta::new("Grid", "1", &ParamSet::new());
+        assert_eq!(meta.to_string(), "Grid v1 #default");
+
+        // параметры экземпляра, а не только набор оптимизатора
+        let meta = Grid::default().meta();
+        assert_eq!(meta.name, "Grid");
+        assert!(!meta.params.is_empty());
+        assert_ne!(meta.params_hash, "default");
+    }
+}
@@ -0,0 +1,312 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{
+    Account, Action, Direction, Iid, MarketOrder, Order, OrderAction,
+    OrderEvent,
+};
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+/// Status of two-leg position.
+///
+/// # ru
+/// Состояние позиции из двух ног. Broken - нога не закрылась, позиция
+/// требует ручного вмешательства.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegsStatus {
+    Flat,
+    Opening,
+    Open,
+    Closing,
+    Broken,
+}
+
+#[derive(Debug)]
+struct Leg {
+    iid: Iid,
+    /// Позиция в лотах, со знаком.
+    position: i32,
+    pending: bool,
+    rejected: bool,
+}
+impl Leg {
+    fn new(iid: &Iid) -> Self {
+        Self {
+            iid: iid.clone(),
+            position: 0,
+            pending: false,
+            rejected: false,
+        }
+    }
+}
+
+/// Synchronized market execution of two legs of pair.
+///
+/// # ru
+/// Синхронное исполнение двух ног парной позиции рыночными ордерами.
+/// Покупка спреда (Direction::Buy) - покупка a и продажа b, продажа -
+/// наоборот. Ордера обеих ног отправляются сразу, позиция открыта,
+/// когда исполнены обе. Если одну ногу отклонили - исполненная нога
+/// закрывается, чтобы не остаться с непокрытым риском.
+///
+/// Стратегия пары торгует инструментом a, график b держит сама
+/// (загружает в init и обновляет), события ордеров обеих ног передает
+/// в [`PairLegs::order_event`].
+#[derive(Debug)]
+pub struct PairLegs {
+    owner: &'static str,
+    trader: Trader,
+    account: Account,
+    a: Leg,
+    b: Leg,
+    status: LegsStatus,
+}
+impl PairLegs {
+    /// Create flat pair position.
+    ///
+    /// # ru
+    /// Конструктор, позиция пустая. Owner - имя стратегии.
+    pub fn new(
+        owner: &'static str,
+        trader: Trader,
+        account: Account,
+        a: &Iid,
+        b: &Iid,
+    ) -> Self {
+        Self {
+            owner,
+            trader,
+            account,
+            a: Leg::new(a),
+            b: Leg::new(b),
+            status: LegsStatus::Flat,
+        }
+    }
+    pub fn status(&self) -> LegsStatus {
+        self.status
+    }
+    /// Position of legs in lots.
+    ///
+    /// # ru
+    /// Позиции ног a и b в лотах, со знаком.
+    pub fn position(&self) -> (i32, i32) {
+        (self.a.position, self.b.position)
+    }
+    pub fn is_flat(&self) -> bool {
+        self.status == LegsStatus::Flat
+    }
+    /// Open spread position, buy or sell spread.
+    ///
+    /// # ru
+    /// Открывает позицию по спреду: lots_a лотов a в direction и
+    /// lots_b лотов b в обратную сторону. Только из Flat.
+    pub fn open(&mut self, direction: Direction, lots_a: u32, lots_b: u32) {
+        assert_eq!(self.status, LegsStatus::Flat);
+
+        let opposite = match direction {
+            Direction::Buy => Direction::Sell,
+            Direction::Sell => Direction::Buy,
+        };
+        self.status = LegsStatus::Opening;
+        self.send(true, direction, lots_a);
+        self.send(false, opposite, lots_b);
+    }
+    /// Close both legs.
+    ///
+    /// # ru
+    /// Закрывает обе ноги рыночными ордерами.
+    pub fn close(&mut self) {
+        assert_eq!(self.status, LegsStatus::Open);
+
+        self.status = LegsStatus::Closing;
+        self.flatten();
+    }
+    /// Process order event, return false if event is not of pair.
+    ///
+    /// # ru
+    /// Обрабатывает событие ордера ноги. Если инструмент события не
+    /// a и не b - false, событие не этой пары.
+    pub fn order_event(&mut self, e: &OrderEvent) -> bool {
+        let leg = if e.iid == self.a.iid {
+            &mut self.a
+        } else if e.iid == self.b.iid {
+            &mut self.b
+        } else {
+            return false;
+        };
+
+        match &e.order {
+            Order::Market(MarketOrder::Filled(order)) => {
+                let lots = order.lots as i32;
+                match order.direction {
+                    Direction::Buy => leg.position += lots,
+                    Direction::Sell => leg.position -= lots,
+                }
+                leg.pending = false;
+            }
+            Order::Market(MarketOrder::Rejected(_)) => {
+                log::warn!("{} leg rejected: {}", self.owner, e.order);
+                leg.pending = false;
+                leg.rejected = true;
+            }
+            _ => return true,
+        }
+        if !self.a.pending && !self.b.pending {
+            self.complete();
+        }
+
+        true
+    }
+
+    // private
+    fn complete(&mut self) {
+        let rejected = self.a.rejected || self.b.rejected;
+        self.a.rejected = false;
+        self.b.rejected = false;
+        let flat = self.a.position == 0 && self.b.position == 0;
+
+        self.status = match self.status {
+            LegsStatus::Opening if !rejected => LegsStatus::Open,
+            LegsStatus::Opening if flat => LegsStatus::Flat,
+            // одну ногу отклонили - закрываем вторую
+            LegsStatus::Opening => {
+                self.flatten();
+                LegsStatus::Closing
+            }
+            LegsStatus::Closing if flat => LegsStatus::Flat,
+            LegsStatus::Closing => {
+                log::error!(
+                    "{} pair is broken, position {:?}",
+                    self.owner,
+                    self.position()
+                );
+                LegsStatus::Broken
+            }
+            status => status,
+        };
+    }
+    fn flatten(&mut self) {
+        for a_leg in [true, false] {
+            let position = self.leg(a_leg).position;
+            if position > 0 {
+                self.send(a_leg, Direction::Sell, position as u32);
+            } else if position < 0 {
+                self.send(a_leg, Direction::Buy, position.unsigned_abs());
+            }
+        }
+    }
+    fn leg(&mut self, a_leg: bool) -> &mut Leg {
+        if a_leg { &mut self.a } else { &mut self.b }
+    }
+    fn send(&mut self, a_leg: bool, direction: Direction, lots: u32) {
+        let order = MarketOrder::new(direction, lots);
+        let order = Order::Market(MarketOrder::New(order));
+
+        let owner = self.owner;
+        let account = self.account.clone();
+        let leg = self.leg(a_leg);
+        leg.pending = true;
+        let a = OrderAction::new(account, leg.iid.clone(), owner, order);
+
+        self.trader.send(Action::Post(a)).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::Transaction;
+    use avin_core::fixture::{iid, share};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    // исполняет или отклоняет отправленный ордер
+    fn answer(a: Action, fill: bool) -> OrderEvent {
+        let Action::Post(a) = a else { panic!() };
+        let Order::Market(MarketOrder::New(order)) = a.order else {
+            panic!()
+        };
+        let order = if fill {
+            let quantity = order.lots * a.iid.lot();
+            let mut order = order.post("1");
+            order.add_transaction(Transaction::new(quantity as i32, 300.0));
+            MarketOrder::Filled(order.fill(0, 0.0))
+        } else {
+            MarketOrder::Rejected(order.reject("no money"))
+        };
+
+        OrderEvent::new(a.account, a.iid, a.owner, Order::Market(order))
+    }
+
+    #[test]
+    fn open_close() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let account = Account::new("Test", "Test_ID");
+        let a = iid();
+        let b = share("SBERP", "BBG0047315Y7");
+        let mut legs = PairLegs::new("Pair", tx, account, &a, &b);
+
+        legs.open(Direction::Buy, 2, 3);
+        let action_a = rx.try_recv().unwrap();
+        let action_b = rx.try_recv().unwrap();
+        assert_eq!(legs.status(), LegsStatus::Opening);
+
+        // одна нога исполнена - позиция еще открывается
+        assert!(legs.order_event(&answer(action_a, true)));
+        assert_eq!(legs.status(), LegsStatus::Opening);
+        assert!(legs.order_event(&answer(action_b, true)));
+        assert_eq!(legs.status(), LegsStatus::Open);
+        assert_eq!(legs.position(), (2, -3));
+
+        legs.close();
+        let action_a = rx.try_recv().unwrap();
+        let action_b = rx.try_recv().unwrap();
+        legs.order_event(&answer(action_a, true));
+        legs.order_event(&answer(action_b, true));
+        assert!(legs.is_flat());
+        assert_eq!(legs.position(), (0, 0));
+
+        // событие чужого инструмента
+        let other = share("GAZP", "BBG004730RP0");
+        let order = MarketOrder::new(Direction::Buy, 1).reject("");
+        let e = OrderEvent::new(
+            Account::new("Test", "Test_ID"),
+            other,
+            "Pair".to_string(),
+            Order::Market(MarketOrder::Rejected(order)),
+        );
+        assert!(!legs.order_event(&e));
+    }
+    #[test]
+    fn rejected_leg() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let account = Account::new("Test", "Test_ID");
+        let a = iid();
+        let b = share("SBERP", "BBG0047315Y7");
+        let mut legs = PairLegs::new("Pair", tx, account, &a, &b);
+
+        legs.open(Direction::Sell, 2, 3);
+        let action_a = rx.try_recv().unwrap();
+        let action_b = rx.try_recv().unwrap();
+        legs.order_event(&answer(action_a, true));
+        legs.order_event(&answer(action_b, false));
+
+        // исполненная нога a закрывается покупкой
+        assert_eq!(legs.status(), LegsStatus::Closing);
+        let Action::Post(unwind) = rx.try_recv().unwrap() else {
+            panic!()
+        };
+        assert_eq!(unwind.iid, a);
+        assert_eq!(unwind.order.direction(), &Direction::Buy);
+        assert_eq!(unwind.order.lots(), 2);
+        assert!(rx.try_recv().is_err());
+
+        legs.order_event(&answer(Action::Post(unwind), true));
+        assert!(legs.is_flat());
+    }
+}
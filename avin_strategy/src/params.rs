@@ -0,0 +1,306 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::Path;
+
+use bitcode::{Decode, Encode};
+
+use avin_utils::AvinError;
+
+use crate::Strategy;
+
+/// Range of values of one strategy parameter.
+///
+/// # ru
+/// Диапазон значений одного параметра стратегии, по которому проходит
+/// оптимизатор тестера.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: &'static str,
+    pub values: Vec<f64>,
+}
+impl Param {
+    /// Values from begin to end inclusive with step.
+    ///
+    /// # ru
+    /// Значения от begin до end включительно с шагом step.
+    pub fn range(
+        name: &'static str,
+        begin: f64,
+        end: f64,
+        step: f64,
+    ) -> Self {
+        assert!(step > 0.0, "step must be positive");
+
+        // считаем количество шагов, а не прибавляем step, чтобы не
+        // накапливать ошибку округления
+        let count = ((end - begin) / step + 1e-9).floor() as usize + 1;
+        let values = (0..count).map(|i| begin + step * i as f64).collect();
+
+        Self { name, values }
+    }
+    /// Explicit list of values.
+    ///
+    /// # ru
+    /// Явный список значений.
+    pub fn list(name: &'static str, values: &[f64]) -> Self {
+        Self {
+            name,
+            values: values.to_vec(),
+        }
+    }
+    /// Check value is within min and max of values.
+    ///
+    /// # ru
+    /// Проверяет, что значение не выходит за минимум и максимум
+    /// значений параметра.
+    pub fn check(&self, value: f64) -> Result<(), AvinError> {
+        let min = self.values.iter().copied().fold(f64::MAX, f64::min);
+        let max = self.values.iter().copied().fold(f64::MIN, f64::max);
+        if value < min - 1e-9 || value > max + 1e-9 {
+            let msg = format!("{}={value} not in [{min}, {max}]", self.name);
+            return Err(AvinError::InvalidValue(msg));
+        }
+
+        Ok(())
+    }
+}
+
+/// Values of strategy parameters of one run.
+///
+/// # ru
+/// Набор значений параметров стратегии для одного запуска теста,
+/// порядок параметров как в [`Params::params`].
+#[derive(Debug, Clone, Default, PartialEq, Encode, Decode)]
+pub struct ParamSet {
+    values: Vec<(String, f64)>,
+}
+impl ParamSet {
+    pub fn new() -> Self {
+        Self { values: Vec::new() }
+    }
+    /// Parse values from TOML table "name = number".
+    ///
+    /// # ru
+    /// Создает набор из TOML таблицы вида "имя = число", целые числа
+    /// тоже допустимы. Вложенные таблицы - другие экземпляры
+    /// стратегии, пропускаются.
+    pub fn from_toml(table: &toml::Table) -> Result<Self, AvinError> {
+        let mut set = Self::new();
+        for (name, value) in table.iter() {
+            let value = match value {
+                toml::Value::Float(v) => *v,
+                toml::Value::Integer(v) => *v as f64,
+                toml::Value::Table(_) => continue,
+                _ => {
+                    let msg = format!("param {name}={value}");
+                    return Err(AvinError::InvalidValue(msg));
+                }
+            };
+            set.set(name, value);
+        }
+
+        Ok(set)
+    }
+    pub fn set(&mut self, name: &str, value: f64) {
+        match self.values.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.values.push((name.to_string(), value)),
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+    }
+    pub fn values(&self) -> &Vec<(String, f64)> {
+        &self.values
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    /// Stable hash of parameter values, 16 hex digits.
+    ///
+    /// # ru
+    /// Хэш набора (FNV-1a от строкового вида), стабильный между
+    /// запусками и версиями компилятора, в отличие от std Hash.
+    pub fn hash(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.to_string().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+
+        format!("{hash:016x}")
+    }
+}
+impl std::fmt::Display for ParamSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s: Vec<String> = self
+            .values
+            .iter()
+            .map(|(n, v)| format!("{n}={v}"))
+            .collect();
+
+        write!(f, "{}", s.join("_"))
+    }
+}
+
+/// Strategy with parameters, that can be optimized.
+///
+/// # ru
+/// Стратегия с параметрами, которые можно оптимизировать в тестере.
+/// Стратегия объявляет диапазоны своих параметров и умеет создавать
+/// себя из набора значений, остальное делает оптимизатор.
+pub trait Params: Strategy + Sized {
+    /// Parameter ranges for optimization.
+    ///
+    /// # ru
+    /// Диапазоны параметров для оптимизации.
+    fn params() -> Vec<Param>;
+    /// Create strategy with given parameter values.
+    ///
+    /// # ru
+    /// Создает стратегию с заданными значениями параметров, параметры,
+    /// которых нет в наборе, берутся по умолчанию.
+    fn with_params(params: &ParamSet) -> Self;
+    /// Current parameter values of instance.
+    ///
+    /// # ru
+    /// Текущие значения параметров экземпляра, порядок как в
+    /// [`Params::params`]. derive StrategyParams берет их из полей, по
+    /// умолчанию - [`Strategy::param_values`].
+    fn values(&self) -> ParamSet {
+        self.param_values()
+    }
+
+    /// Check that all values are known parameters within ranges.
+    ///
+    /// # ru
+    /// Проверяет набор: каждое значение - известный параметр и не
+    /// выходит за его диапазон из [`Params::params`].
+    fn validate(params: &ParamSet) -> Result<(), AvinError> {
+        let ranges = Self::params();
+        for (name, value) in params.values() {
+            let Some(param) = ranges.iter().find(|p| p.name == name) else {
+                return Err(AvinError::NotFound(format!("param {name}")));
+            };
+            param.check(*value)?;
+        }
+
+        Ok(())
+    }
+    /// Create strategy instance from TOML text.
+    ///
+    /// # ru
+    /// Создает экземпляр стратегии из TOML: instance - имя таблицы со
+    /// значениями параметров, через точку для вложенных таблиц,
+    /// например "PinBarLong.aggressive" для `[PinBarLong.aggressive]`.
+    /// Значения проверяются [`Params::validate`].
+    fn from_toml(text: &str, instance: &str) -> Result<Self, AvinError> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e| AvinError::InvalidValue(format!("toml: {e}")))?;
+
+        let mut section = &table;
+        for key in instance.split('.') {
+            section =
+                section.get(key).and_then(|v| v.as_table()).ok_or_else(
+                    || AvinError::NotFound(format!("params {instance}")),
+                )?;
+        }
+
+        let params = ParamSet::from_toml(section)?;
+        Self::validate(&params)?;
+
+        Ok(Self::with_params(&params))
+    }
+    /// Create strategy instance from TOML file.
+    ///
+    /// # ru
+    /// Создает экземпляр стратегии из TOML файла, см.
+    /// [`Params::from_toml`].
+    fn load(path: &Path, instance: &str) -> Result<Self, AvinError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            AvinError::IOError(format!("{}: {e}", path.display()))
+        })?;
+
+        Self::from_toml(&text, instance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PinBarLong;
+
+    #[test]
+    fn param_range() {
+        let p = Param::range("stop", 0.5, 1.0, 0.1);
+        assert_eq!(p.values.len(), 6);
+        assert_eq!(p.values[0], 0.5);
+        assert!((p.values[5] - 1.0).abs() < 1e-9);
+    }
+    #[test]
+    fn param_set() {
+        let mut set = ParamSet::new();
+        set.set("stop", 0.99);
+        set.set("take", 1.02);
+        set.set("stop", 0.98);
+        assert_eq!(set.get("stop"), Some(0.98));
+        assert_eq!(set.get("lots"), None);
+        assert_eq!(set.to_string(), "stop=0.98_take=1.02");
+    }
+    #[test]
+    fn param_set_hash() {
+        let mut set = ParamSet::new();
+        set.set("stop", 0.99);
+        assert_eq!(set.hash(), "bc55359b170228f8");
+
+        set.set("stop", 0.98);
+        assert_ne!(set.hash(), "bc55359b170228f8");
+    }
+    #[test]
+    fn derive_params() {
+        let params = PinBarLong::params();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].name, "stop");
+        assert_eq!(params[0].values.len(), 6);
+
+        let mut set = ParamSet::new();
+        set.set("take", 1.03);
+        assert!(PinBarLong::validate(&set).is_ok());
+        set.set("take", 1.5);
+        assert!(PinBarLong::validate(&set).is_err());
+
+        let mut set = ParamSet::new();
+        set.set("stop_typo", 0.98);
+        assert!(PinBarLong::validate(&set).is_err());
+    }
+    #[test]
+    fn from_toml() {
+        let text = r#"
+            [PinBarLong]
+            stop = 0.98
+
+            [PinBarLong.aggressive]
+            stop = 0.97
+            take = 1.05
+
+            [PinBarLong.broken]
+            take = 2
+            "#;
+        assert!(PinBarLong::from_toml(text, "PinBarLong").is_ok());
+        assert!(PinBarLong::from_toml(text, "PinBarLong.aggressive").is_ok());
+        assert!(PinBarLong::from_toml(text, "PinBarLong.broken").is_err());
+        assert!(PinBarLong::from_toml(text, "PinBarShort").is_err());
+
+        let table: toml::Table = "stop = 0.98\nlots = 10".parse().unwrap();
+        let set = ParamSet::from_toml(&table).unwrap();
+        assert_eq!(set.get("lots"), Some(10.0));
+        let table: toml::Table = "stop = \"x\"".parse().unwrap();
+        assert!(ParamSet::from_toml(&table).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use avin_core::{
+    Account, Action, Asset, Clock, MarketData, Order, OrderEvent, TimeFrame,
+};
+use avin_utils::AvinError;
+use libloading::Library;
+
+use crate::{ParamSet, Strategy};
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+/// ABI version of strategy plugins.
+///
+/// # ru
+/// Версия ABI плагинов стратегий. Плагин и трейдер обмениваются
+/// `Box<dyn Strategy>`, а у Rust нет стабильного ABI: раскладка типов
+/// одинакова, только если обе стороны собраны одним rustc из одних
+/// исходников. Поэтому строка (см. build.rs) содержит версию avin,
+/// версию rustc, платформу, профиль сборки и хэш исходников
+/// avin_core, avin_strategy, avin_utils и Cargo.lock, а
+/// rust-toolchain.toml фиксирует точную версию компилятора. Плагин с
+/// другой строкой не загружается.
+pub const PLUGIN_ABI: &str = env!("AVIN_PLUGIN_ABI");
+
+type AbiFn = fn() -> &'static str;
+type NamesFn = fn() -> Vec<&'static str>;
+type CreateFn = fn(&str) -> Option<Box<dyn Strategy>>;
+
+/// Export strategies from cdylib crate as plugin.
+///
+/// # ru
+/// Объявляет крейт (`crate-type = ["cdylib"]`) плагином стратегий:
+/// имена и фабрики стратегий, которые трейдер загружает из файла
+/// библиотеки и перезагружает без перезапуска.
+///
+/// ```ignore
+/// avin_strategy::plugin! {
+///     "MyCross" => MyCross::default(),
+///     "MyGrid" => Grid::new(GridSide::Long).levels(4),
+/// }
+/// ```
+#[macro_export]
+macro_rules! plugin {
+    ($($name:literal => $strategy:expr),+ $(,)?) => {
+        #[unsafe(no_mangle)]
+        pub fn avin_plugin_abi() -> &'static str {
+            $crate::PLUGIN_ABI
+        }
+        #[unsafe(no_mangle)]
+        pub fn avin_plugin_names() -> Vec<&'static str> {
+            vec![$($name),+]
+        }
+        #[unsafe(no_mangle)]
+        pub fn avin_plugin_create(
+            name: &str,
+        ) -> Option<Box<dyn $crate::Strategy>> {
+            match name {
+                $($name => Some(Box::new($strategy)),)+
+                _ => None,
+            }
+        }
+    };
+}
+
+/// Loaded library of strategy plugin.
+///
+/// # ru
+/// Загруженная библиотека плагина стратегий, см. [`plugin!`].
+///
+/// Библиотека загружается из копии файла во временном каталоге: так
+/// файл плагина можно пересобрать, пока старая версия работает, а
+/// новая загрузка не получит от загрузчика ОС уже открытую старую.
+/// Стратегии, созданные плагином, держат библиотеку: она выгружается,
+/// когда удалена последняя из них.
+pub struct Plugin {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    names: Vec<String>,
+    create: CreateFn,
+    lib: Arc<Library>,
+}
+impl Plugin {
+    /// Load plugin from library file.
+    ///
+    /// # ru
+    /// Загружает плагин из файла библиотеки, проверяет версию ABI.
+    pub fn load(path: &Path) -> Result<Self, AvinError> {
+        let modified = modified(path);
+        let copy = copy(path)?;
+
+        // SAFETY: библиотека собрана макросом plugin! той же версии
+        // avin, проверка ABI ниже до вызова остальных функций
+        let lib = unsafe { Library::new(&copy) };
+        let _ = std::fs::remove_file(&copy);
+        let lib = lib.map_err(|e| {
+            AvinError::NotLoaded(format!("plugin {}: {e}", path.display()))
+        })?;
+
+        let abi: AbiFn = unsafe { *symbol(&lib, path, b"avin_plugin_abi")? };
+        if abi() != PLUGIN_ABI {
+            return Err(AvinError::InvalidValue(format!(
+                "plugin {}: abi '{}', expected '{PLUGIN_ABI}'",
+                path.display(),
+                abi()
+            )));
+        }
+        let names: NamesFn =
+            unsafe { *symbol(&lib, path, b"avin_plugin_names")? };
+        let create: CreateFn =
+            unsafe { *symbol(&lib, path, b"avin_plugin_create")? };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            modified,
+            names: names().into_iter().map(String::from).collect(),
+            create,
+            lib: Arc::new(lib),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Names of plugin strategies.
+    ///
+    /// # ru
+    /// Имена стратегий плагина, под ними стратегии указываются в
+    /// списке работ трейдера.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|i| i == name)
+    }
+    /// Create strategy by name.
+    ///
+    /// # ru
+    /// Создает стратегию плагина по имени, None - такой нет.
+    pub fn create(&self, name: &str) -> Option<Box<dyn Strategy>> {
+        let strategy = (self.create)(name)?;
+
+        Some(Box::new(PluginStrategy {
+            strategy,
+            _lib: self.lib.clone(),
+        }))
+    }
+    /// Is library file changed or removed after load.
+    ///
+    /// # ru
+    /// Изменился или удален файл библиотеки после загрузки - пора
+    /// перезагрузить или выгрузить плагин.
+    pub fn is_changed(&self) -> bool {
+        modified(&self.path) != self.modified
+    }
+    pub fn is_exist(&self) -> bool {
+        self.path.exists()
+    }
+}
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("path", &self.path)
+            .field("names", &self.names)
+            .finish()
+    }
+}
+
+/// Стратегия плагина вместе с его библиотекой. Порядок полей важен:
+/// стратегия удаляется раньше библиотеки с ее кодом.
+struct PluginStrategy {
+    strategy: Box<dyn Strategy>,
+    _lib: Arc<Library>,
+}
+impl Strategy for PluginStrategy {
+    fn name(&self) -> &'static str {
+        self.strategy.name()
+    }
+    fn version(&self) -> &'static str {
+        self.strategy.version()
+    }
+    fn param_values(&self) -> ParamSet {
+        self.strategy.param_values()
+    }
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.strategy.set_clock(clock)
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        self.strategy.timeframes()
+    }
+    fn universe(&self) -> Vec<String> {
+        self.strategy.universe()
+    }
+    fn market_data(&self) -> Vec<MarketData> {
+        self.strategy.market_data()
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        self.strategy.init(trader, account, asset)
+    }
+    fn reconcile(&mut self, orders: Vec<Order>) {
+        self.strategy.reconcile(orders)
+    }
+    fn process(&mut self, asset: &Asset) {
+        self.strategy.process(asset)
+    }
+    fn order_event(&mut self, event: OrderEvent) {
+        self.strategy.order_event(event)
+    }
+    fn reason(&self) -> String {
+        self.strategy.reason()
+    }
+    fn context(&self, asset: &Asset) -> Vec<(String, String)> {
+        self.strategy.context(asset)
+    }
+    fn stop(&self) -> Option<f64> {
+        self.strategy.stop()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|i| i.modified()).ok()
+}
+/// Копия файла библиотеки с уникальным именем для загрузки.
+fn copy(path: &Path) -> Result<PathBuf, AvinError> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join("avin_plugin");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AvinError::IOError(e.to_string()))?;
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().unwrap_or_default().to_string_lossy();
+    let n = COUNT.fetch_add(1, Ordering::Relaxed);
+    let copy = dir.join(format!("{stem}-{}-{n}.{ext}", std::process::id()));
+
+    std::fs::copy(path, &copy).map_err(|e| {
+        AvinError::NotLoaded(format!("plugin {}: {e}", path.display()))
+    })?;
+
+    Ok(copy)
+}
+unsafe fn symbol<'a, T>(
+    lib: &'a Library,
+    path: &Path,
+    name: &[u8],
+) -> Result<libloading::Symbol<'a, T>, AvinError> {
+    unsafe { lib.get(name) }.map_err(|e| {
+        AvinError::NotLoaded(format!("plugin {}: {e}", path.display()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_missing() {
+        let path = Path::new("/tmp/avin_no_such_plugin.so");
+        let err = Plugin::load(path).unwrap_err();
+        assert!(matches!(err, AvinError::NotLoaded(_)));
+
+        let path = Path::new("Cargo.toml");
+        let err = Plugin::load(path).unwrap_err();
+        assert!(matches!(err, AvinError::NotLoaded(_)));
+    }
+    #[test]
+    fn abi() {
+        let version = concat!("avin-", env!("CARGO_PKG_VERSION"), " rustc ");
+        assert!(PLUGIN_ABI.starts_with(version));
+        assert!(PLUGIN_ABI.contains(" src-"));
+    }
+}
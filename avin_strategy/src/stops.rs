@@ -0,0 +1,399 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{Asset, BarSlice, Iid, TimeFrame, TradeKind};
+use avin_utils as utils;
+
+/// Partial profit order planned at position open.
+///
+/// # ru
+/// Частичная фиксация прибыли: сколько лотов закрыть и по какой цене.
+/// Стратегия выставляет ее тейк профитом сразу после входа, так что
+/// исполняет ее брокер - одинаково в тестере, симуляторе и вживую.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialTake {
+    pub lots: u32,
+    pub price: f64,
+}
+
+/// Exit management of open position: breakeven, partial take, trailing.
+///
+/// # ru
+/// Управление выходом из открытой позиции, общие для любых стратегий
+/// правила:
+/// - breakeven - перенос стопа в безубыток, когда цена прошла after_r
+///   рисков (R - расстояние от входа до начального стопа);
+/// - partial - частичная фиксация share позиции на at_r рисков;
+/// - atr_trail - трейлинг стоп на mult ATR(period) от лучшей цены с
+///   момента входа.
+///
+/// Стоп только подтягивается и никогда не отодвигается. Решения
+/// принимаются по закрытым барам таймфрейма tf, а исполняет стопы и
+/// тейки брокер, поэтому поведение одинаково в тестере, симуляторе и
+/// вживую. Менеджер не работает с ордерами сам: [`StopManager::open`]
+/// возвращает частичные тейки для выставления, а
+/// [`StopManager::update`] новую цену стопа, ордера переставляет
+/// стратегия. Готовая связка с исполнителем сигналов:
+/// [`crate::Executor::stops`].
+///
+/// ```ignore
+/// let stops = StopManager::new(TimeFrame::M10)
+///     .breakeven(1.0)
+///     .partial(2.0, 0.5)
+///     .atr_trail(14, 3.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StopManager {
+    tf: TimeFrame,
+    breakeven: Option<f64>,
+    partials: Vec<(f64, f64)>,
+    trail: Option<(usize, f64)>,
+    position: Option<Position>,
+    reason: &'static str,
+}
+impl StopManager {
+    pub fn new(tf: TimeFrame) -> Self {
+        Self {
+            tf,
+            breakeven: None,
+            partials: Vec::new(),
+            trail: None,
+            position: None,
+            reason: "",
+        }
+    }
+    /// Move stop to entry after profit of after_r risks.
+    ///
+    /// # ru
+    /// Перенос стопа на цену входа, когда цена прошла after_r рисков.
+    /// Нужен начальный стоп.
+    pub fn breakeven(mut self, after_r: f64) -> Self {
+        assert!(after_r > 0.0, "breakeven after_r must be positive");
+        self.breakeven = Some(after_r);
+
+        self
+    }
+    /// Take share of position at profit of at_r risks.
+    ///
+    /// # ru
+    /// Закрытие доли share позиции на at_r рисков, правил может быть
+    /// несколько. Хотя бы один лот всегда остается для стопа и
+    /// основного тейка. Нужен начальный стоп.
+    pub fn partial(mut self, at_r: f64, share: f64) -> Self {
+        assert!(at_r > 0.0, "partial at_r must be positive");
+        assert!(share > 0.0 && share < 1.0, "partial share not in (0, 1)");
+        self.partials.push((at_r, share));
+
+        self
+    }
+    /// Trail stop at mult ATR(period) from best price.
+    ///
+    /// # ru
+    /// Трейлинг стоп на расстоянии mult ATR(period) от лучшей цены
+    /// позиции, ATR по закрытым барам таймфрейма tf.
+    pub fn atr_trail(mut self, period: usize, mult: f64) -> Self {
+        assert!(period > 0, "atr period must be positive");
+        assert!(mult > 0.0, "atr mult must be positive");
+        self.trail = Some((period, mult));
+
+        self
+    }
+
+    pub fn tf(&self) -> TimeFrame {
+        self.tf
+    }
+    /// Current stop price of position.
+    ///
+    /// # ru
+    /// Текущая цена стопа позиции, None - позиции нет или стопа еще
+    /// нет.
+    pub fn stop(&self) -> Option<f64> {
+        self.position.as_ref().and_then(|p| p.stop)
+    }
+    /// Reason of last stop move: "breakeven", "trailing stop".
+    ///
+    /// # ru
+    /// Причина последнего переноса стопа, для журнала стратегии.
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+    pub fn is_open(&self) -> bool {
+        self.position.is_some()
+    }
+    /// Start managing position, return partial takes to post.
+    ///
+    /// # ru
+    /// Начинает сопровождение позиции: направление, цена входа,
+    /// начальный стоп, лоты и время входа. Возвращает частичные тейки,
+    /// которые стратегия выставляет сразу. Без начального стопа риск
+    /// неизвестен, работает только трейлинг.
+    pub fn open(
+        &mut self,
+        iid: &Iid,
+        kind: TradeKind,
+        entry: f64,
+        stop: Option<f64>,
+        lots: u32,
+        ts: i64,
+    ) -> Vec<PartialTake> {
+        let risk = stop.map(|s| (entry - s).abs()).filter(|r| *r > 0.0);
+        self.position = Some(Position {
+            kind: kind.clone(),
+            step: iid.step(),
+            entry,
+            risk,
+            stop,
+            best: entry,
+            open_ts: ts,
+            last_ts: 0,
+        });
+        self.reason = "";
+
+        let Some(risk) = risk else {
+            return Vec::new();
+        };
+        let mut takes = Vec::new();
+        let mut left = lots;
+        for (at_r, share) in self.partials.iter() {
+            let lots = ((lots as f64 * share).floor() as u32).max(1);
+            if lots >= left {
+                break;
+            }
+            left -= lots;
+            let price = match kind {
+                TradeKind::Long => entry + risk * at_r,
+                TradeKind::Short => entry - risk * at_r,
+            };
+            let price = utils::round_price(price, iid.step());
+            takes.push(PartialTake { lots, price });
+        }
+
+        takes
+    }
+    /// Check rules on new closed bar, return new stop if moved.
+    ///
+    /// # ru
+    /// Проверяет правила на новом закрытом баре таймфрейма tf и
+    /// возвращает новую цену стопа, если стоп нужно подтянуть. Между
+    /// закрытиями баров ничего не меняется, вызывать можно на каждом
+    /// событии.
+    pub fn update(&mut self, asset: &Asset) -> Option<f64> {
+        let p = self.position.as_mut()?;
+        let bars = asset.chart(self.tf)?.bars();
+        // последний бар - реал-тайм, остальные закрыты
+        if bars.len() < 2 {
+            return None;
+        }
+        let closed = bars.slice(0..bars.len() - 1);
+        let last = closed.last().unwrap();
+        if last.ts == p.last_ts {
+            return None;
+        }
+        let prev = std::mem::replace(&mut p.last_ts, last.ts);
+
+        // лучшая цена по новым барам с момента входа
+        let tf = self.tf.nanos();
+        for bar in closed.iter().rev() {
+            if bar.ts <= prev || bar.ts + tf <= p.open_ts {
+                break;
+            }
+            p.best = match p.kind {
+                TradeKind::Long => p.best.max(bar.h),
+                TradeKind::Short => p.best.min(bar.l),
+            };
+        }
+
+        let mut moved = None;
+        if let (Some(after_r), Some(risk)) = (self.breakeven, p.risk)
+            && (p.best - p.entry).abs() >= risk * after_r
+            && p.tighten(p.entry)
+        {
+            moved = Some("breakeven");
+        }
+        if let Some((period, mult)) = self.trail
+            && let Some(atr) = atr(closed, period)
+        {
+            let price = match p.kind {
+                TradeKind::Long => p.best - atr * mult,
+                TradeKind::Short => p.best + atr * mult,
+            };
+            if p.tighten(price) {
+                moved = Some("trailing stop");
+            }
+        }
+
+        let reason = moved?;
+        self.reason = reason;
+
+        p.stop
+    }
+    /// Stop managing position.
+    ///
+    /// # ru
+    /// Позиция закрыта, сопровождение закончено.
+    pub fn close(&mut self) {
+        self.position = None;
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Position {
+    kind: TradeKind,
+    step: f64,
+    entry: f64,
+    risk: Option<f64>,
+    stop: Option<f64>,
+    /// Лучшая цена с момента входа.
+    best: f64,
+    open_ts: i64,
+    last_ts: i64,
+}
+impl Position {
+    /// Подтягивает стоп к цене, если она лучше текущего стопа.
+    fn tighten(&mut self, price: f64) -> bool {
+        let price = utils::round_price(price, self.step);
+        let better = match (self.stop, &self.kind) {
+            (None, _) => true,
+            (Some(stop), TradeKind::Long) => price > stop,
+            (Some(stop), TradeKind::Short) => price < stop,
+        };
+        if better {
+            self.stop = Some(price);
+        }
+
+        better
+    }
+}
+
+/// Средний истинный диапазон последних period закрытых баров.
+fn atr(bars: BarSlice, period: usize) -> Option<f64> {
+    if bars.len() < period + 1 {
+        return None;
+    }
+
+    // истинный диапазон последних period баров, по колонкам
+    let sum: f64 = (bars.len() - period..bars.len())
+        .map(|i| {
+            let (prev, h, l) = (bars.c[i - 1], bars.h[i], bars.l[i]);
+            (h - l).max((h - prev).abs()).max((l - prev).abs())
+        })
+        .sum();
+
+    Some(sum / period as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarEvent};
+
+    use super::*;
+    use crate::examples::fixture::{MINUTE, TS};
+
+    fn bars(asset: &mut Asset, ts: &mut i64, prices: &[f64]) {
+        for price in prices.iter() {
+            let bar =
+                Bar::new(*ts, *price, price + 1.0, price - 1.0, *price, 1);
+            *ts += MINUTE;
+            let figi = iid().figi().clone();
+            asset.bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+        }
+    }
+
+    #[test]
+    fn partial_takes() {
+        let mut stops = StopManager::new(TimeFrame::M1)
+            .partial(1.0, 0.3)
+            .partial(2.0, 0.3);
+
+        // риск 5, треть от 10 лотов на +1R и +2R
+        let takes =
+            stops.open(&iid(), TradeKind::Long, 100.0, Some(95.0), 10, TS);
+        assert_eq!(
+            takes,
+            vec![
+                PartialTake {
+                    lots: 3,
+                    price: 105.0
+                },
+                PartialTake {
+                    lots: 3,
+                    price: 110.0
+                },
+            ]
+        );
+
+        // последний лот не отдается частичным тейкам
+        let takes =
+            stops.open(&iid(), TradeKind::Short, 100.0, Some(105.0), 2, TS);
+        assert_eq!(
+            takes,
+            vec![PartialTake {
+                lots: 1,
+                price: 95.0
+            }]
+        );
+
+        // без стопа риск неизвестен
+        let takes = stops.open(&iid(), TradeKind::Long, 100.0, None, 10, TS);
+        assert!(takes.is_empty());
+    }
+    #[test]
+    fn breakeven_and_trail() {
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::M1);
+        let mut ts = TS;
+        bars(&mut asset, &mut ts, &[100.0, 100.0, 100.0]);
+
+        let mut stops = StopManager::new(TimeFrame::M1)
+            .breakeven(1.0)
+            .atr_trail(2, 3.0);
+        stops.open(&iid(), TradeKind::Long, 100.0, Some(90.0), 1, ts);
+        assert_eq!(stops.stop(), Some(90.0));
+
+        // ATR 2, трейлинг 101 - 6 = 95 выше начального стопа
+        bars(&mut asset, &mut ts, &[100.0, 100.0]);
+        assert_eq!(stops.update(&asset), Some(95.0));
+        assert_eq!(stops.reason(), "trailing stop");
+        // тот же закрытый бар - без изменений
+        assert_eq!(stops.update(&asset), None);
+
+        // закрылся бар 100 - лучшая цена прежняя
+        bars(&mut asset, &mut ts, &[110.0]);
+        assert_eq!(stops.update(&asset), None);
+        // лучшая цена 111 = +1.1R: безубыток, трейлинг после скачка ATR
+        // 111 - 19.5 ниже
+        bars(&mut asset, &mut ts, &[110.0]);
+        assert_eq!(stops.update(&asset), Some(100.0));
+        assert_eq!(stops.reason(), "breakeven");
+
+        // откат: стоп не отодвигается
+        bars(&mut asset, &mut ts, &[104.0, 104.0]);
+        assert_eq!(stops.update(&asset), None);
+        assert_eq!(stops.stop(), Some(100.0));
+
+        stops.close();
+        assert!(!stops.is_open());
+        assert_eq!(stops.stop(), None);
+    }
+    #[test]
+    fn breakeven_short() {
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::M1);
+        let mut ts = TS;
+        bars(&mut asset, &mut ts, &[100.0]);
+
+        let mut stops = StopManager::new(TimeFrame::M1).breakeven(1.0);
+        stops.open(&iid(), TradeKind::Short, 100.0, Some(105.0), 1, ts);
+
+        bars(&mut asset, &mut ts, &[97.0, 97.0]);
+        assert_eq!(stops.update(&asset), None);
+        bars(&mut asset, &mut ts, &[94.0, 94.0]);
+        assert_eq!(stops.update(&asset), Some(100.0));
+        assert_eq!(stops.reason(), "breakeven");
+    }
+}
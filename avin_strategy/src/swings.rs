@@ -0,0 +1,264 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{
+    Asset, Chart, Extremum, ExtremumIndicator, Term, TimeFrame, Trend,
+};
+
+/// Extremum and trend queries for strategies.
+///
+/// # ru
+/// Вопросы стратегии к индикатору экстремумов без ручной работы с
+/// графиком: какой сейчас тренд T3 на часовике, сформировался ли
+/// только что новый экстремум T2. Методы не паникуют - если графика
+/// или индикатора нет, возвращают None.
+///
+/// Для отслеживания изменений таймфрейм и период объявляются через
+/// [`Swings::watch`], в init стратегии вызывается [`Swings::init`],
+/// а в начале process - [`Swings::update`]. После этого флаги
+/// [`Swings::is_new_extr`] и [`Swings::is_trend_changed`] говорят,
+/// что изменилось с прошлого события.
+///
+/// ```ignore
+/// // init
+/// self.swings = Swings::new().watch(TimeFrame::H1, Term::T2);
+/// self.swings.init(asset);
+///
+/// // process
+/// self.swings.update(asset);
+/// if self.swings.is_new_extr(TimeFrame::H1, Term::T2)
+///     && Swings::is_bull(asset, TimeFrame::H1, Term::T3)
+/// {
+///     ...
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Swings {
+    watched: Vec<Watch>,
+}
+impl Swings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Watch extremums and trend of timeframe and term.
+    ///
+    /// # ru
+    /// Добавляет таймфрейм и период для отслеживания изменений.
+    pub fn watch(mut self, tf: TimeFrame, term: Term) -> Self {
+        if self.find(tf, term).is_none() {
+            self.watched.push(Watch::new(tf, term));
+        }
+
+        self
+    }
+    /// Timeframes of watched charts.
+    ///
+    /// # ru
+    /// Таймфреймы отслеживаемых графиков, без повторов. Удобно
+    /// добавить их в timeframes стратегии.
+    pub fn timeframes(&self) -> Vec<TimeFrame> {
+        let mut timeframes = Vec::new();
+        for w in self.watched.iter() {
+            if !timeframes.contains(&w.tf) {
+                timeframes.push(w.tf);
+            }
+        }
+
+        timeframes
+    }
+    /// Add indicator to watched charts and remember current state.
+    ///
+    /// # ru
+    /// Добавляет индикатор экстремумов на отслеживаемые графики, если
+    /// его там еще нет, и запоминает текущее состояние - уже
+    /// существующие экстремумы новыми не считаются.
+    pub fn init(&mut self, asset: &mut Asset) {
+        for w in self.watched.iter() {
+            if let Some(chart) = asset.chart_mut(w.tf)
+                && !chart.has_extremum()
+            {
+                ExtremumIndicator::init(chart);
+            }
+        }
+        self.update(asset);
+        for w in self.watched.iter_mut() {
+            w.new_extr = false;
+            w.trend_changed = false;
+        }
+    }
+    /// Compare state with previous event.
+    ///
+    /// # ru
+    /// Сверяет состояние индикатора с прошлым вызовом и выставляет
+    /// флаги изменений. Вызывается один раз на событие, в начале
+    /// process стратегии.
+    pub fn update(&mut self, asset: &Asset) {
+        for w in self.watched.iter_mut() {
+            let extr = Self::extr(asset, w.tf, w.term, 1).map(|e| e.ts);
+            let bull = Self::trend(asset, w.tf, w.term).map(|t| t.is_bull());
+
+            w.new_extr = extr.is_some() && extr != w.last_extr;
+            w.trend_changed = bull.is_some() && bull != w.last_bull;
+            w.last_extr = extr;
+            w.last_bull = bull;
+        }
+    }
+    /// New historical extremum formed since previous update.
+    ///
+    /// # ru
+    /// С прошлого update сформировался новый исторический экстремум.
+    /// Для неотслеживаемой пары всегда false.
+    pub fn is_new_extr(&self, tf: TimeFrame, term: Term) -> bool {
+        self.find(tf, term).is_some_and(|w| w.new_extr)
+    }
+    /// Real-time trend changed direction since previous update.
+    ///
+    /// # ru
+    /// С прошлого update реал-тайм тренд сменил направление. Для
+    /// неотслеживаемой пары всегда false.
+    pub fn is_trend_changed(&self, tf: TimeFrame, term: Term) -> bool {
+        self.find(tf, term).is_some_and(|w| w.trend_changed)
+    }
+
+    /// Extremum, n = 0 real-time, n >= 1 historical.
+    ///
+    /// # ru
+    /// Экстремум: 0 - реал-тайм, 1 и дальше - исторические, от
+    /// последнего к первому. None - нет графика, индикатора или
+    /// экстремума.
+    pub fn extr(
+        asset: &Asset,
+        tf: TimeFrame,
+        term: Term,
+        n: usize,
+    ) -> Option<&Extremum> {
+        chart(asset, tf)?.extr(term, n)
+    }
+    /// Real-time trend.
+    ///
+    /// # ru
+    /// Реал-тайм тренд: от последнего исторического экстремума до
+    /// текущего. None - нет графика, индикатора или тренда.
+    pub fn trend(asset: &Asset, tf: TimeFrame, term: Term) -> Option<&Trend> {
+        chart(asset, tf)?.trend(term, 0)
+    }
+    pub fn is_bull(asset: &Asset, tf: TimeFrame, term: Term) -> bool {
+        Self::trend(asset, tf, term).is_some_and(|t| t.is_bull())
+    }
+    pub fn is_bear(asset: &Asset, tf: TimeFrame, term: Term) -> bool {
+        Self::trend(asset, tf, term).is_some_and(|t| t.is_bear())
+    }
+
+    fn find(&self, tf: TimeFrame, term: Term) -> Option<&Watch> {
+        self.watched.iter().find(|w| w.tf == tf && w.term == term)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Watch {
+    tf: TimeFrame,
+    term: Term,
+    last_extr: Option<i64>,
+    last_bull: Option<bool>,
+    new_extr: bool,
+    trend_changed: bool,
+}
+impl Watch {
+    fn new(tf: TimeFrame, term: Term) -> Self {
+        Self {
+            tf,
+            term,
+            last_extr: None,
+            last_bull: None,
+            new_extr: false,
+            trend_changed: false,
+        }
+    }
+}
+
+fn chart(asset: &Asset, tf: TimeFrame) -> Option<&Chart> {
+    asset.chart(tf).filter(|chart| chart.has_extremum())
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarEvent};
+
+    use super::*;
+    use crate::examples::fixture::{MINUTE, TS};
+
+    fn bars(asset: &mut Asset, ts: &mut i64, prices: &[f64]) {
+        for price in prices.iter() {
+            let bar =
+                Bar::new(*ts, *price, price + 1.0, price - 1.0, *price, 1);
+            *ts += MINUTE;
+            let figi = iid().figi().clone();
+            asset.bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+        }
+    }
+
+    #[test]
+    fn without_indicator() {
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::M1);
+
+        let tf = TimeFrame::M1;
+        assert!(Swings::trend(&asset, tf, Term::T1).is_none());
+        assert!(Swings::extr(&asset, TimeFrame::H1, Term::T1, 0).is_none());
+        assert!(!Swings::is_bull(&asset, tf, Term::T1));
+        assert!(!Swings::is_bear(&asset, tf, Term::T1));
+
+        let swings = Swings::new().watch(tf, Term::T1);
+        assert!(!swings.is_new_extr(tf, Term::T1));
+        assert!(!swings.is_new_extr(tf, Term::T2));
+    }
+
+    #[test]
+    fn change_detection() {
+        let tf = TimeFrame::M1;
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(tf);
+        let mut ts = TS;
+        bars(&mut asset, &mut ts, &[100.0, 102.0, 104.0, 106.0]);
+
+        let mut swings = Swings::new().watch(tf, Term::T1);
+        assert_eq!(swings.timeframes(), vec![tf]);
+        swings.init(&mut asset);
+        assert!(!swings.is_new_extr(tf, Term::T1));
+        assert!(!swings.is_trend_changed(tf, Term::T1));
+
+        // рост продолжается - ничего нового
+        bars(&mut asset, &mut ts, &[108.0]);
+        swings.update(&asset);
+        assert!(!swings.is_new_extr(tf, Term::T1));
+        assert!(!swings.is_trend_changed(tf, Term::T1));
+        assert!(Swings::is_bull(&asset, tf, Term::T1));
+
+        // разворот: вершина 108 стала историческим экстремумом
+        let mut turned = false;
+        for price in [106.0, 104.0, 102.0] {
+            bars(&mut asset, &mut ts, &[price]);
+            swings.update(&asset);
+            if swings.is_new_extr(tf, Term::T1) {
+                assert!(!turned);
+                assert!(swings.is_trend_changed(tf, Term::T1));
+                turned = true;
+            }
+        }
+        assert!(turned);
+        assert!(Swings::is_bear(&asset, tf, Term::T1));
+        let extr = Swings::extr(&asset, tf, Term::T1, 1).unwrap();
+        assert!(extr.is_max());
+
+        // флаги живут одно событие
+        bars(&mut asset, &mut ts, &[100.0]);
+        swings.update(&asset);
+        assert!(!swings.is_new_extr(tf, Term::T1));
+        assert!(!swings.is_trend_changed(tf, Term::T1));
+    }
+}
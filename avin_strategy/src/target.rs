@@ -0,0 +1,543 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::TimeDelta;
+
+use avin_core::{
+    Account, Action, Asset, Direction, Iid, LimitOrder, MarketOrder, Order,
+    OrderAction, OrderEvent, TimeFrame, Trade, TradeKind,
+};
+
+use crate::{ParamSet, Strategy};
+
+type Trader = tokio::sync::mpsc::UnboundedSender<Action>;
+
+/// Source of target position for [`Rebalancer`].
+///
+/// # ru
+/// Источник целевой позиции - логика стратегии, которая говорит не
+/// "купить" или "продать", а "держать столько": +3 лота, -1 лот, 0.
+/// Как дойти до цели решает [`Rebalancer`].
+pub trait TargetSource: Send + 'static {
+    fn name(&self) -> &'static str;
+    /// Version of source logic, see [`Strategy::version`].
+    ///
+    /// # ru
+    /// Версия логики источника, см. [`Strategy::version`].
+    fn version(&self) -> &'static str {
+        "1"
+    }
+    /// Parameter values of source, see [`Strategy::param_values`].
+    ///
+    /// # ru
+    /// Значения параметров источника, см.
+    /// [`Strategy::param_values`].
+    fn param_values(&self) -> ParamSet {
+        ParamSet::new()
+    }
+    /// Timeframes of charts used by source, see
+    /// [`Strategy::timeframes`].
+    ///
+    /// # ru
+    /// Таймфреймы графиков, которые нужны источнику, см.
+    /// [`Strategy::timeframes`].
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        TimeFrame::all()
+    }
+    /// Instruments of source, see [`Strategy::universe`].
+    ///
+    /// # ru
+    /// Инструменты источника, см. [`Strategy::universe`].
+    fn universe(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn init(&mut self, _asset: &mut Asset) {}
+    /// Target position in lots, sign is direction, None - keep target.
+    ///
+    /// # ru
+    /// Вызывается на каждом событии рынка, возвращает целевую позицию
+    /// в лотах со знаком: плюс - лонг, минус - шорт, 0 - вне рынка.
+    /// None - цель прежняя.
+    fn target(&mut self, asset: &Asset) -> Option<i32>;
+}
+
+/// How rebalancer works orders to reach target.
+///
+/// # ru
+/// Способ исполнения разницы между целью и позицией:
+/// - Market - сразу всю разницу по рынку;
+/// - Passive - лимиткой по последней цене, если не исполнилась за
+///   timeout - снимается и выставляется заново по новой цене;
+/// - Twap - по рынку частями, разница делится на slices частей, одна
+///   часть раз в interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Execution {
+    Market,
+    Passive { timeout: TimeDelta },
+    Twap { slices: u32, interval: TimeDelta },
+}
+
+/// Выставленный ордер исполнителя, пока он работает - новых нет.
+#[derive(Debug)]
+enum Working {
+    Market,
+    Limit {
+        ts: i64,
+        order: Option<Order>,
+        canceling: bool,
+    },
+}
+
+/// Strategy that reaches target position of source.
+///
+/// # ru
+/// Исполнитель целевой позиции: источник [`TargetSource`] объявляет,
+/// сколько лотов держать, исполнитель считает разницу с позицией и
+/// работает ордерами способом [`Execution`], пока позиция не равна
+/// цели. Удобно для стратегий ребалансировки: не нужно следить за
+/// ордерами, частичными входами и разворотами.
+///
+/// Одновременно работает один ордер. Разворот идет в два шага: сначала
+/// позиция закрывается, затем открывается в другую сторону - так
+/// каждый трейд от открытия до закрытия позиции записывается целиком.
+/// Отклоненный ордер сбрасывает цель на текущую позицию, до новой
+/// цели источника ордеров больше нет.
+///
+/// ```ignore
+/// let twap = Execution::Twap { slices: 4, interval: TimeDelta::minutes(5) };
+/// let strategy = Rebalancer::new(MyWeights::default(), twap);
+/// ```
+pub struct Rebalancer<S: TargetSource> {
+    source: S,
+    execution: Execution,
+
+    trader: Option<Trader>,
+    account: Option<Account>,
+    iid: Option<Iid>,
+    /// Цель и позиция в лотах, со знаком.
+    target: i32,
+    position: i32,
+    working: Option<Working>,
+    trade: Option<Trade>,
+    /// Размер части и время следующей части TWAP.
+    slice: u32,
+    next_slice: i64,
+    reason: String,
+}
+impl<S: TargetSource> Rebalancer<S> {
+    pub fn new(source: S, execution: Execution) -> Self {
+        Self {
+            source,
+            execution,
+            trader: None,
+            account: None,
+            iid: None,
+            target: 0,
+            position: 0,
+            working: None,
+            trade: None,
+            slice: 0,
+            next_slice: 0,
+            reason: String::new(),
+        }
+    }
+    /// Source of target.
+    ///
+    /// # ru
+    /// Источник целевой позиции.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+    /// Target position in lots.
+    ///
+    /// # ru
+    /// Текущая цель в лотах, со знаком.
+    pub fn target(&self) -> i32 {
+        self.target
+    }
+    /// Position in lots.
+    ///
+    /// # ru
+    /// Позиция исполнителя в лотах, со знаком.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    // private
+    fn set_target(&mut self, target: i32, ts: i64) {
+        if target == self.target {
+            return;
+        }
+        self.target = target;
+        self.reason = format!("target {target:+}");
+
+        if let Execution::Twap { slices, .. } = self.execution {
+            let delta = (target - self.position).unsigned_abs();
+            self.slice = delta.div_ceil(slices.max(1)).max(1);
+            self.next_slice = ts;
+        }
+    }
+    /// Следующий шаг к цели в лотах: через ноль позиция не переходит.
+    fn step(&self) -> i32 {
+        let is_reverse = self.position != 0
+            && self.target.signum() != self.position.signum();
+        if is_reverse {
+            -self.position
+        } else {
+            self.target - self.position
+        }
+    }
+    fn work(&mut self, ts: i64, price: f64) {
+        if let Some(Working::Limit {
+            ts: posted,
+            order: Some(order),
+            canceling,
+        }) = &mut self.working
+            && let Execution::Passive { timeout } = self.execution
+            && !*canceling
+            && ts - *posted >= timeout.num_nanoseconds().unwrap()
+        {
+            *canceling = true;
+            let order = order.clone();
+            self.send(Action::Cancel(self.order_action(order)));
+            return;
+        }
+        if self.working.is_some() {
+            return;
+        }
+
+        let step = self.step();
+        if step == 0 {
+            return;
+        }
+        let direction = if step > 0 {
+            Direction::Buy
+        } else {
+            Direction::Sell
+        };
+        let lots = step.unsigned_abs();
+
+        match self.execution {
+            Execution::Market => {
+                let order = MarketOrder::new(direction, lots);
+                self.post(Order::Market(MarketOrder::New(order)));
+                self.working = Some(Working::Market);
+            }
+            Execution::Passive { .. } => {
+                let order = LimitOrder::new(direction, lots, price);
+                self.post(Order::Limit(LimitOrder::New(order)));
+                self.working = Some(Working::Limit {
+                    ts,
+                    order: None,
+                    canceling: false,
+                });
+            }
+            Execution::Twap { interval, .. } => {
+                if ts < self.next_slice {
+                    return;
+                }
+                let lots = lots.min(self.slice.max(1));
+                let order = MarketOrder::new(direction, lots);
+                self.post(Order::Market(MarketOrder::New(order)));
+                self.working = Some(Working::Market);
+                self.next_slice = ts + interval.num_nanoseconds().unwrap();
+            }
+        }
+    }
+    fn filled(&mut self, order: Order) {
+        self.working = None;
+
+        let lots = order.lots() as i32;
+        let delta = match order.direction() {
+            Direction::Buy => lots,
+            Direction::Sell => -lots,
+        };
+        let was_flat = self.position == 0;
+        self.position += delta;
+
+        if was_flat {
+            let kind = if delta > 0 {
+                TradeKind::Long
+            } else {
+                TradeKind::Short
+            };
+            let ts = order.operation().unwrap().ts;
+            let iid = self.iid.clone().unwrap();
+            let trade = Trade::new(ts, self.source.name(), kind, iid);
+            self.trade = Some(Trade::Opened(trade.open(order)));
+            return;
+        }
+        let Some(Trade::Opened(mut trade)) = self.trade.take() else {
+            return;
+        };
+        trade.add_order(order);
+        if self.position == 0 {
+            let a = Action::TradeClosed(Trade::Closed(trade.close()));
+            self.send(a);
+        } else {
+            self.trade = Some(Trade::Opened(trade));
+        }
+    }
+    fn rejected(&mut self, order: &Order) {
+        log::warn!("{} order rejected: {order}", self.source.name());
+        self.working = None;
+        self.target = self.position;
+        self.reason = "order rejected".to_string();
+    }
+    fn post(&self, order: Order) {
+        self.send(Action::Post(self.order_action(order)));
+    }
+    fn order_action(&self, order: Order) -> OrderAction {
+        OrderAction::new(
+            self.account.clone().unwrap(),
+            self.iid.clone().unwrap(),
+            self.source.name(),
+            order,
+        )
+    }
+    fn send(&self, a: Action) {
+        self.trader.as_ref().unwrap().send(a).unwrap();
+    }
+}
+impl<S: TargetSource + Default> Default for Rebalancer<S> {
+    fn default() -> Self {
+        Self::new(S::default(), Execution::Market)
+    }
+}
+impl<S: TargetSource> Strategy for Rebalancer<S> {
+    fn name(&self) -> &'static str {
+        self.source.name()
+    }
+    fn version(&self) -> &'static str {
+        self.source.version()
+    }
+    fn param_values(&self) -> ParamSet {
+        self.source.param_values()
+    }
+    fn timeframes(&self) -> Vec<TimeFrame> {
+        self.source.timeframes()
+    }
+    fn universe(&self) -> Vec<String> {
+        self.source.universe()
+    }
+    fn init(&mut self, trader: Trader, account: Account, asset: &mut Asset) {
+        self.trader = Some(trader);
+        self.account = Some(account);
+        self.iid = Some(asset.iid().clone());
+        self.source.init(asset);
+    }
+    fn process(&mut self, asset: &Asset) {
+        let Some(bar) = asset.chart(TimeFrame::M1).and_then(|i| i.now())
+        else {
+            return;
+        };
+        let (ts, price) = (bar.ts, bar.c);
+
+        if let Some(target) = self.source.target(asset) {
+            self.set_target(target, ts);
+        }
+        self.work(ts, price);
+    }
+    fn order_event(&mut self, e: OrderEvent) {
+        let order = e.order;
+
+        if order.is_filled() {
+            self.filled(order);
+            return;
+        }
+        match order {
+            Order::Limit(LimitOrder::Posted(_)) => {
+                if let Some(Working::Limit { order: posted, .. }) =
+                    &mut self.working
+                {
+                    *posted = Some(order);
+                }
+            }
+            Order::Limit(LimitOrder::Canceled(_)) => {
+                // следующее событие выставит лимитку по новой цене
+                self.working = None;
+            }
+            Order::Market(MarketOrder::Rejected(_))
+            | Order::Limit(LimitOrder::Rejected(_)) => self.rejected(&order),
+            _ => (),
+        }
+    }
+    fn reason(&self) -> String {
+        self.reason.clone()
+    }
+    fn context(&self, _asset: &Asset) -> Vec<(String, String)> {
+        vec![
+            ("target".to_string(), self.target.to_string()),
+            ("position".to_string(), self.position.to_string()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use avin_core::fixture::iid;
+    use avin_core::{Bar, BarEvent};
+
+    use super::*;
+    use crate::ensemble::Book;
+
+    const TS: i64 = 1_700_000_040_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+
+    fn bar(n: i64, price: f64) -> Bar {
+        Bar::new(TS + n * MINUTE, price, price + 1.0, price - 1.0, price, 1)
+    }
+
+    /// Источник для тестов: цели по номерам баров.
+    struct Script {
+        targets: Vec<(i64, i32)>,
+    }
+    impl TargetSource for Script {
+        fn name(&self) -> &'static str {
+            "Script"
+        }
+        fn target(&mut self, asset: &Asset) -> Option<i32> {
+            let ts = asset.chart(TimeFrame::M1)?.now()?.ts;
+            let n = (ts - TS) / MINUTE;
+            let i = self.targets.iter().position(|i| i.0 == n)?;
+
+            Some(self.targets.remove(i).1)
+        }
+    }
+
+    /// Исполнитель с виртуальной книгой ордеров вместо брокера.
+    struct Run {
+        rebalancer: Rebalancer<Script>,
+        asset: Asset,
+        rx: UnboundedReceiver<Action>,
+        book: Book,
+        posted: Vec<u32>,
+        trades: Vec<Trade>,
+        canceled: usize,
+    }
+    impl Run {
+        fn new(targets: Vec<(i64, i32)>, execution: Execution) -> Self {
+            let mut rebalancer =
+                Rebalancer::new(Script { targets }, execution);
+
+            let mut asset = Asset::from_iid(iid());
+            asset.load_chart_empty(TimeFrame::M1);
+            let (tx, rx) = mpsc::unbounded_channel();
+            let account = Account::new("Test", "Test_ID");
+            rebalancer.init(tx, account, &mut asset);
+
+            Self {
+                rebalancer,
+                asset,
+                rx,
+                book: Book::default(),
+                posted: Vec::new(),
+                trades: Vec::new(),
+                canceled: 0,
+            }
+        }
+        fn bar(&mut self, n: i64, price: f64) {
+            let bar = bar(n, price);
+            let figi = iid().figi().clone();
+            self.asset
+                .bar_event(BarEvent::new(figi, TimeFrame::M1, bar));
+
+            let events = self.book.market(&bar, iid().lot());
+            self.deliver(events, &bar);
+            self.rebalancer.process(&self.asset);
+            self.deliver(Vec::new(), &bar);
+        }
+        fn deliver(&mut self, mut events: Vec<Order>, bar: &Bar) {
+            loop {
+                for order in events.drain(..) {
+                    let e = OrderEvent::new(
+                        Account::new("Test", "Test_ID"),
+                        iid(),
+                        "Script".to_string(),
+                        order,
+                    );
+                    self.rebalancer.order_event(e);
+                }
+                while let Ok(a) = self.rx.try_recv() {
+                    match a {
+                        Action::TradeClosed(t) => self.trades.push(t),
+                        Action::Cancel(_) => {
+                            self.canceled += 1;
+                            events.extend(self.book.action(a, bar, 10));
+                        }
+                        Action::Post(ref post) => {
+                            self.posted.push(post.order.lots());
+                            events.extend(self.book.action(a, bar, 10));
+                        }
+                        _ => (),
+                    }
+                }
+                if events.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn market_reverse() {
+        let mut run = Run::new(vec![(0, 3), (2, -2)], Execution::Market);
+
+        run.bar(0, 100.0);
+        assert_eq!(run.rebalancer.position(), 3);
+        assert_eq!(run.rebalancer.reason(), "target +3");
+
+        // разворот: закрытие 3 лотов, затем шорт 2 лота
+        run.bar(2, 98.0);
+        assert_eq!(run.posted, vec![3, 3]);
+        assert_eq!(run.rebalancer.position(), 0);
+        let Some(Trade::Closed(trade)) = run.trades.pop() else {
+            panic!();
+        };
+        assert_eq!(trade.result(), -60.0);
+
+        run.bar(3, 98.0);
+        assert_eq!(run.posted, vec![3, 3, 2]);
+        assert_eq!(run.rebalancer.position(), -2);
+    }
+    #[test]
+    fn twap() {
+        let twap = Execution::Twap {
+            slices: 3,
+            interval: TimeDelta::minutes(2),
+        };
+        let mut run = Run::new(vec![(0, 5)], twap);
+
+        // 5 лотов частями по 2 лота раз в 2 минуты
+        for n in 0..6 {
+            run.bar(n, 100.0);
+        }
+        assert_eq!(run.posted, vec![2, 2, 1]);
+        assert_eq!(run.rebalancer.position(), 5);
+    }
+    #[test]
+    fn passive_repost() {
+        let passive = Execution::Passive {
+            timeout: TimeDelta::minutes(2),
+        };
+        let mut run = Run::new(vec![(0, 2)], passive);
+
+        // лимитка по 100 не исполняется, через 2 минуты - снята и
+        // выставлена по новой цене
+        run.bar(0, 100.0);
+        run.bar(1, 102.0);
+        run.bar(2, 103.0);
+        assert_eq!(run.canceled, 1);
+        run.bar(3, 103.0);
+        assert_eq!(run.posted, vec![2, 2]);
+        run.bar(4, 102.5);
+        assert_eq!(run.rebalancer.position(), 2);
+        assert!(run.trades.is_empty());
+    }
+}
@@ -7,20 +7,58 @@
 
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
-use avin_core::{Action, Asset, Event, TimeFrame};
+use chrono::{DateTime, TimeDelta, Utc};
+
+use std::sync::Arc;
+
+use avin_core::{
+    Action, Asset, Bar, Direction, Event, Iid, LimitOrder, Manager,
+    MarketOrder, Order, OrderAction, SimClock, TimeFrame, Trade, TradeKind,
+};
 use avin_strategy::Strategy;
 
-use super::{Test, TestStatus, VirtualBroker};
+use super::{
+    CancelToken, Equity, Journal, Manifest, Progress, Sizing, Test,
+    TestStatus, VirtualBroker, trends_context,
+};
+
+// Сколько раз расширять интервал поиска баров прогрева
+const WARMUP_ATTEMPTS: usize = 6;
 
 pub struct Tester {
     tx: UnboundedSender<Action>,
     rx: UnboundedReceiver<Action>,
+    progress: Option<UnboundedSender<Progress>>,
+    cancel: CancelToken,
 }
 impl Tester {
     pub fn new() -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
 
-        Tester { tx, rx }
+        Tester {
+            tx,
+            rx,
+            progress: None,
+            cancel: CancelToken::new(),
+        }
+    }
+    /// Channel of progress of runs.
+    ///
+    /// # ru
+    /// Создает канал прогресса тестов этого тестера: сообщение на
+    /// каждый новый процент периода теста и последнее на 100%.
+    pub fn progress(&mut self) -> UnboundedReceiver<Progress> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.progress = Some(tx);
+
+        rx
+    }
+    /// Token to abort running test.
+    ///
+    /// # ru
+    /// Токен отмены теста, см. [`CancelToken`].
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
     }
 
     pub async fn run(
@@ -37,47 +75,297 @@ impl Tester {
         let account = broker.get_virtual_account();
 
         let mut asset = Asset::from_iid(test.iid.clone());
-        self.load_charts(&mut asset);
+        self.load_charts(&mut asset, test, &strategy.timeframes());
+
+        // время стратегии - время рынка, не реальное
+        let clock = SimClock::new(test.begin_ts_nanos);
+        strategy.set_clock(Arc::new(clock.clone()));
 
         let sender = self.tx.clone();
         strategy.init(sender, account, &mut asset);
 
+        let mut equity = Equity::new(test.deposit);
+        let mut journal = Journal::new();
+        let mut opened = Vec::new();
+        let mut bar = Bar::new(0, 0.0, 0.0, 0.0, 0.0, 0);
+        let mut percent = 0;
+
         test.status = TestStatus::Process;
         while let Some(e) = broker.next_event() {
+            if self.cancel.is_canceled() {
+                log::info!("Tester canceled {}", test.name());
+                test.status = TestStatus::Canceled;
+                return;
+            }
+
+            // брокер закрыл позицию сам: трейд закрывается здесь, без
+            // стратегии - ее состояние больше не совпадает со счетом
+            if let Some(order) = broker.take_margin_call() {
+                margin_call(test, &mut equity, opened, order);
+                break;
+            }
+
             match e {
                 Event::Bar(e) => {
+                    if e.tf == TimeFrame::M1 {
+                        clock.set(e.bar.ts + e.tf.nanos());
+                        equity.bar(e.bar.ts, e.bar.c);
+                        bar = e.bar;
+                        percent = self.send_progress(test, bar.ts, percent);
+                    }
                     asset.bar_event(e);
                     strategy.process(&asset);
                 }
                 Event::Tic(e) => {
+                    clock.set(e.tic.ts);
                     asset.tic_event(e);
                     strategy.process(&asset);
                 }
-                Event::Order(e) => strategy.order_event(e),
+                Event::Book(_) => unreachable!("BookEvent in data stream?"),
+                Event::Order(e) => {
+                    equity.order(&e.order);
+                    track(&mut opened, &e.order, equity.position());
+                    strategy.order_event(e);
+                }
             }
 
             // process actions from strategys
-            while let Ok(a) = self.rx.try_recv() {
+            while let Ok(mut a) = self.rx.try_recv() {
+                if let Some(sizing) = &test.sizing
+                    && let Action::Post(order_action) = &mut a
+                    && equity.position() == 0
+                {
+                    let stop = strategy.stop();
+                    size_entry(
+                        order_action,
+                        sizing,
+                        &equity,
+                        &asset,
+                        &bar,
+                        stop,
+                    );
+                }
+
                 match a {
                     Action::TradeClosed(trade) => {
                         test.trade_list.add(trade);
                     }
+                    Action::Signal(signal) => {
+                        journal.signal(signal);
+                    }
+                    Action::Notify(notify) => {
+                        log::debug!(":: {notify}");
+                    }
+                    Action::Post(ref order_action)
+                    | Action::Cancel(ref order_action) => {
+                        let kind = match a {
+                            Action::Post(_) => "post",
+                            _ => "cancel",
+                        };
+                        let mut context = trends_context(&asset);
+                        context.extend(strategy.context(&asset));
+                        journal.record(
+                            kind,
+                            order_action,
+                            bar,
+                            strategy.reason(),
+                            context,
+                        );
+                        broker_tx.send(a).unwrap();
+                    }
                     other => broker_tx.send(other).unwrap(),
                 }
             }
         }
 
+        if let Some(tx) = &self.progress {
+            let _ = tx.send(Progress::new(
+                test.begin_ts_nanos,
+                test.end_ts_nanos,
+                test.end_ts_nanos,
+                test.trade_list.len(),
+            ));
+        }
+
         test.status = TestStatus::Complete;
         Test::save(test).unwrap();
+        equity.save(&test.equity_path()).unwrap();
+        journal.save(&test.journal_path()).unwrap();
+        journal.save_signals(&test.signals_path()).unwrap();
+        Manifest::new(test, &strategy.meta(), equity.range())
+            .save(&test.manifest_path())
+            .unwrap();
     }
 
     // private
-    fn load_charts(&mut self, asset: &mut Asset) {
+    fn send_progress(&self, test: &Test, ts: i64, sent: u32) -> u32 {
+        let Some(tx) = &self.progress else {
+            return sent;
+        };
+
+        let progress = Progress::new(
+            test.begin_ts_nanos,
+            test.end_ts_nanos,
+            ts,
+            test.trade_list.len(),
+        );
+        let percent = progress.percent as u32;
+        if percent > sent {
+            // получатель мог закрыть канал, тест от этого не зависит
+            let _ = tx.send(progress);
+            return percent;
+        }
+
+        sent
+    }
+    fn load_charts(
+        &mut self,
+        asset: &mut Asset,
+        test: &Test,
+        timeframes: &[TimeFrame],
+    ) {
+        // 1М обновляет остальные графики, дневной нужен модели размера
+        // позиции по волатильности
+        let sizing = matches!(test.sizing, Some(Sizing::Volatility { .. }));
         for tf in TimeFrame::all() {
+            let needed = tf == TimeFrame::M1
+                || timeframes.contains(&tf)
+                || (tf == TimeFrame::Day && sizing);
+            if !needed {
+                continue;
+            }
             asset.load_chart_empty(tf);
+
+            // прогрев: бары до начала теста, стратегия подключает
+            // индикаторы в init уже к заполненным графикам
+            let count = test.warmup_bars(tf) as usize;
+            if count == 0 {
+                continue;
+            }
+
+            let bars = warmup_bars(asset.iid(), tf, test.begin(), count);
+            if bars.len() < count {
+                log::warn!(
+                    "Warm-up {} {tf}: {} of {count} bars available",
+                    asset.ticker(),
+                    bars.len()
+                );
+            }
+
+            let chart = asset.chart_mut(tf).unwrap();
+            for bar in bars {
+                chart.add_bar(bar);
+            }
         }
     }
 }
+/// Ордер входа - рыночный или лимитный ордер без позиции - получает
+/// количество лотов по модели размера позиции. Капитал считается по
+/// закрытию текущего 1М бара, цена входа - цена лимитки или закрытие.
+fn size_entry(
+    order_action: &mut OrderAction,
+    sizing: &Sizing,
+    equity: &Equity,
+    asset: &Asset,
+    bar: &Bar,
+    stop: Option<f64>,
+) {
+    let (lots, price) = match &mut order_action.order {
+        Order::Market(MarketOrder::New(o)) => (&mut o.lots, bar.c),
+        Order::Limit(LimitOrder::New(o)) => (&mut o.lots, o.price),
+        _ => return,
+    };
+
+    let value = equity.value(bar.c);
+    let atr = sizing.atr(asset);
+    match sizing.lots(asset.iid(), value, price, stop, atr) {
+        Some(sized) if sized > 0 => *lots = sized,
+        Some(_) => log::warn!("Sizing {sizing}: < 1 lot, strategy lots"),
+        None => log::warn!("Sizing {sizing}: no data, strategy lots"),
+    }
+}
+/// Исполненные ордера текущей позиции, с последнего момента, когда
+/// позиция была нулевой.
+fn track(opened: &mut Vec<Order>, order: &Order, position: i64) {
+    if matches!(order, Order::Stop(_)) || !order.is_filled() {
+        return;
+    }
+
+    opened.push(order.clone());
+    if position == 0 {
+        opened.clear();
+    }
+}
+/// Закрывает трейд позиции ордером брокера при margin call и
+/// отмечает margin call в тесте.
+fn margin_call(
+    test: &mut Test,
+    equity: &mut Equity,
+    opened: Vec<Order>,
+    order: Order,
+) {
+    let operation = order.operation().unwrap();
+    let (ts, quantity) = (operation.ts, operation.quantity.abs());
+    let price = operation.value.abs() / quantity as f64;
+    log::warn!(
+        "Margin call {}: {} {quantity} by {price}",
+        test.name(),
+        order.direction()
+    );
+    equity.order(&order);
+    equity.bar(ts, price);
+    test.margin_call = Some(ts);
+
+    let mut orders = opened.into_iter();
+    let Some(first) = orders.next() else {
+        return;
+    };
+    let kind = match first.direction() {
+        Direction::Buy => TradeKind::Long,
+        Direction::Sell => TradeKind::Short,
+    };
+    let ts = first.operation().unwrap().ts;
+    let mut trade =
+        Trade::new(ts, &test.strategy_name, kind, test.iid.clone())
+            .open(first);
+    for i in orders.chain(std::iter::once(order)) {
+        trade.add_order(i);
+    }
+
+    // частично исполненные и снятые лимитки в позиции брокера есть,
+    // а в исполненных ордерах нет - такой трейд не закрыть
+    if trade.quantity() != 0 {
+        log::error!("Margin call {}: trade quantity mismatch", test.name());
+        return;
+    }
+    test.trade_list.add(Trade::Closed(trade.close()));
+}
+/// Последние count баров до begin. В данных есть пропуски (ночь,
+/// выходные, праздники), поэтому интервал загрузки расширяется, пока
+/// баров не хватит.
+fn warmup_bars(
+    iid: &Iid,
+    tf: TimeFrame,
+    begin: DateTime<Utc>,
+    count: usize,
+) -> Vec<Bar> {
+    let mut lookback = tf.nanos() * count as i64 * 2;
+    let mut bars = Vec::new();
+
+    for _ in 0..WARMUP_ATTEMPTS {
+        let from = begin - TimeDelta::nanoseconds(lookback);
+        if let Ok(df) = Manager::load(iid, tf.market_data(), from, begin) {
+            bars = Bar::from_df(&df).unwrap();
+            if bars.len() >= count {
+                return bars.split_off(bars.len() - count);
+            }
+        }
+        lookback *= 4;
+    }
+
+    bars
+}
+
 impl Default for Tester {
     fn default() -> Self {
         Tester::new()
@@ -89,8 +377,35 @@ mod tests {
     use chrono::{TimeZone, Utc};
 
     use super::*;
+    use avin_core::Transaction;
     use avin_strategy::BuySell;
 
+    fn filled(direction: Direction, lots: u32) -> Order {
+        let mut order =
+            MarketOrder::new(direction, lots).post("broker_id=100500");
+        order.add_transaction(Transaction::new(lots as i32, 100.0));
+        let order = order.fill(0, 1.0);
+
+        Order::Market(MarketOrder::Filled(order))
+    }
+
+    #[test]
+    fn track_position() {
+        let mut opened = Vec::new();
+
+        track(&mut opened, &filled(Direction::Buy, 2), 2);
+        track(&mut opened, &filled(Direction::Buy, 1), 3);
+        assert_eq!(opened.len(), 2);
+
+        // позиция закрыта - трейда больше нет
+        track(&mut opened, &filled(Direction::Sell, 3), 0);
+        assert!(opened.is_empty());
+
+        let posted = MarketOrder::new(Direction::Sell, 1).post("id");
+        track(&mut opened, &Order::Market(MarketOrder::Posted(posted)), 0);
+        assert!(opened.is_empty());
+    }
+
     #[tokio::test]
     async fn run_test() {
         let strategy = BuySell::default();
@@ -0,0 +1,135 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use bitcode::{Decode, Encode};
+
+use avin_utils::{AvinError, CFG};
+
+/// Commission model of backtest.
+///
+/// # ru
+/// Модель комиссии брокера в тесте. Комиссия считается
+/// [`crate::VirtualBroker`] при исполнении каждого ордера и
+/// записывается в операцию ордера, итоги по комиссии - в
+/// [`avin_core::Summary`].
+///
+/// Лимитные ордера считаются maker (добавляют ликвидность), рыночные
+/// и сработавшие рыночные стоп ордера - taker.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum Commission {
+    /// Доля от оборота сделки: 0.0005 == 0.05%.
+    Percent(f64),
+    /// Фиксированная сумма за лот, например для фьючерсов.
+    PerLot(f64),
+    /// Доля от оборота, отдельно для maker и taker ордеров.
+    MakerTaker { maker: f64, taker: f64 },
+}
+impl Commission {
+    /// T-Bank tariff "Investor": 0.3% of turnover.
+    ///
+    /// # ru
+    /// Тариф Т-Банка "Инвестор": 0.3% от оборота.
+    pub fn tbank_investor() -> Self {
+        Self::Percent(0.003)
+    }
+    /// T-Bank tariff "Trader": 0.05% of turnover.
+    ///
+    /// # ru
+    /// Тариф Т-Банка "Трейдер": 0.05% от оборота.
+    pub fn tbank_trader() -> Self {
+        Self::Percent(0.0005)
+    }
+    /// T-Bank tariff "Premium": 0.04% of turnover.
+    ///
+    /// # ru
+    /// Тариф Т-Банка "Премиум": 0.04% от оборота.
+    pub fn tbank_premium() -> Self {
+        Self::Percent(0.0004)
+    }
+    /// Calculate commission of order execution.
+    ///
+    /// # ru
+    /// Возвращает комиссию за исполнение ордера: value - оборот в
+    /// валюте, lots - количество лотов, maker - лимитный ордер.
+    pub fn calculate(&self, value: f64, lots: u32, maker: bool) -> f64 {
+        match self {
+            Self::Percent(rate) => value.abs() * rate,
+            Self::PerLot(fee) => lots as f64 * fee,
+            Self::MakerTaker { maker: m, taker: t } => {
+                value.abs() * if maker { m } else { t }
+            }
+        }
+    }
+}
+impl Default for Commission {
+    /// Percent from config "tester.default_commission".
+    ///
+    /// # ru
+    /// Процент от оборота из конфига "tester.default_commission".
+    fn default() -> Self {
+        Self::Percent(CFG.tester.default_commission / 100.0)
+    }
+}
+impl TryFrom<&str> for Commission {
+    type Error = AvinError;
+
+    /// Parse preset name: "tbank_investor", "tbank_trader",
+    /// "tbank_premium".
+    ///
+    /// # ru
+    /// Создает модель по имени тарифа.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "tbank_investor" => Ok(Self::tbank_investor()),
+            "tbank_trader" => Ok(Self::tbank_trader()),
+            "tbank_premium" => Ok(Self::tbank_premium()),
+            _ => {
+                let msg = format!("commission preset {value}");
+                Err(AvinError::InvalidValue(msg))
+            }
+        }
+    }
+}
+impl std::fmt::Display for Commission {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Percent(rate) => write!(f, "{}%", rate * 100.0),
+            Self::PerLot(fee) => write!(f, "{fee} per lot"),
+            Self::MakerTaker { maker, taker } => {
+                write!(f, "maker {}% taker {}%", maker * 100.0, taker * 100.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculate() {
+        let c = Commission::Percent(0.001);
+        assert_eq!(c.calculate(10_000.0, 1, false), 10.0);
+        assert_eq!(c.calculate(-10_000.0, 1, true), 10.0);
+
+        let c = Commission::PerLot(2.5);
+        assert_eq!(c.calculate(10_000.0, 4, false), 10.0);
+
+        let c = Commission::MakerTaker {
+            maker: 0.0,
+            taker: 0.0005,
+        };
+        assert_eq!(c.calculate(10_000.0, 1, true), 0.0);
+        assert_eq!(c.calculate(10_000.0, 1, false), 5.0);
+    }
+    #[test]
+    fn preset() {
+        let c = Commission::try_from("tbank_trader").unwrap();
+        assert_eq!(c, Commission::Percent(0.0005));
+        assert!(Commission::try_from("unknown").is_err());
+    }
+}
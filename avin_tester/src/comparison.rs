@@ -0,0 +1,279 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use polars::prelude::{Column, DataFrame};
+
+use avin_core::Summary;
+use avin_strategy::Strategy;
+use avin_utils::{AvinError, CFG, Cmd};
+
+use crate::report::{STYLE, cell, column_f64, escape, svg_lines};
+use crate::{Test, Tester};
+
+const COLORS: [&str; 8] = [
+    "#2a7ab9", "#c0392b", "#1e8449", "#8e44ad", "#d68910", "#17a589",
+    "#34495e", "#e74c3c",
+];
+
+/// Result of one strategy in comparison.
+///
+/// # ru
+/// Результат одной стратегии в сравнении.
+#[derive(Debug)]
+pub struct ComparisonRun {
+    /// Стратегия, версия и параметры: "PinBarLong v1 stop=0.99".
+    pub name: String,
+    pub summary: Summary,
+    pub equity: Vec<f64>,
+    pub drawdown_pct: Vec<f64>,
+    pub path: PathBuf,
+}
+impl ComparisonRun {
+    /// Maximum drawdown in percent, <= 0.
+    ///
+    /// # ru
+    /// Максимальная просадка в процентах, <= 0.
+    pub fn max_drawdown(&self) -> f64 {
+        self.drawdown_pct.iter().copied().fold(0.0, f64::min)
+    }
+}
+
+/// Side-by-side comparison of strategies on the same data.
+///
+/// # ru
+/// Сравнение нескольких стратегий (или версий одной стратегии) на
+/// одних данных: каждая стратегия тестируется с настройками шаблонного
+/// теста (инструмент, период, комиссия, модели исполнения), результат -
+/// таблица итогов рядом и общий график капитала. Сохраняется в
+/// <test_dir>/compare/<TICKER>_<begin>_<end>/: result.parquet и
+/// comparison.html.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    runs: Vec<ComparisonRun>,
+}
+impl Comparison {
+    pub fn new() -> Self {
+        Self { runs: Vec::new() }
+    }
+
+    /// Test strategy with template settings and add it to comparison.
+    ///
+    /// # ru
+    /// Тестирует стратегию с настройками шаблона и добавляет в
+    /// сравнение.
+    pub async fn run(&mut self, strategy: impl Strategy, template: &Test) {
+        let mut test = Test::new(&strategy, &template.iid);
+        test.copy_settings(template);
+
+        Tester::new().run(strategy, &mut test).await;
+
+        let equity = test.load_equity().ok();
+        let column = |name| match &equity {
+            Some(df) => column_f64(df, name),
+            None => Vec::new(),
+        };
+
+        self.runs.push(ComparisonRun {
+            name: run_name(&test),
+            summary: Summary::new(&test.trade_list),
+            equity: column("equity"),
+            drawdown_pct: column("drawdown_pct"),
+            path: test.path(),
+        });
+    }
+    pub fn runs(&self) -> &Vec<ComparisonRun> {
+        &self.runs
+    }
+
+    /// Comparison table, one row per strategy.
+    ///
+    /// # ru
+    /// Таблица сравнения, строка на стратегию, в порядке запуска.
+    pub fn table(&self) -> DataFrame {
+        let runs = &self.runs;
+        let summary = |f: fn(&Summary) -> f64| -> Vec<f64> {
+            runs.iter().map(|r| f(&r.summary)).collect()
+        };
+        let names: Vec<String> =
+            runs.iter().map(|r| r.name.clone()).collect();
+        let trades: Vec<u32> =
+            runs.iter().map(|r| r.summary.total_trades).collect();
+        let drawdown: Vec<f64> =
+            runs.iter().map(|r| r.max_drawdown()).collect();
+        let paths: Vec<String> =
+            runs.iter().map(|r| r.path.display().to_string()).collect();
+
+        DataFrame::new(vec![
+            Column::new("strategy".into(), names),
+            Column::new("profit".into(), summary(|s| s.profit)),
+            Column::new(
+                "percent_profitable".into(),
+                summary(|s| s.percent_profitable),
+            ),
+            Column::new("trades".into(), trades),
+            Column::new("ratio".into(), summary(|s| s.ratio)),
+            Column::new("average_trade".into(), summary(|s| s.average_trade)),
+            Column::new("max_drawdown_pct".into(), drawdown),
+            Column::new("commission".into(), summary(|s| s.commission)),
+            Column::new("path".into(), paths),
+        ])
+        .unwrap()
+    }
+    /// Comparison html: table and combined equity chart.
+    ///
+    /// # ru
+    /// Html сравнения: таблица итогов и общий график капитала, цвет
+    /// линии совпадает с цветом названия в таблице.
+    pub fn html(&self, title: &str) -> String {
+        let title = escape(title);
+        let mut html = String::new();
+        write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n\
+            <body>\n<h1>{title}</h1>\n"
+        )
+        .unwrap();
+
+        html += "<h2>Summary</h2>\n<table>\n<tr><th>Strategy</th>\
+            <th>Profit</th><th>Profitable, %</th><th>Trades</th>\
+            <th>Ratio</th><th>Average trade</th><th>Max drawdown, %</th>\
+            <th>Commission</th></tr>\n";
+        for (i, run) in self.runs.iter().enumerate() {
+            let s = &run.summary;
+            writeln!(
+                html,
+                "<tr><th style=\"color:{}\">{}</th>{}<td>{:.2}</td>\
+                <td>{}</td><td>{:.2}</td>{}{}<td>{:.2}</td></tr>",
+                color(i),
+                escape(&run.name),
+                cell(s.profit),
+                s.percent_profitable,
+                s.total_trades,
+                s.ratio,
+                cell(s.average_trade),
+                cell(run.max_drawdown()),
+                s.commission,
+            )
+            .unwrap();
+        }
+        html += "</table>\n";
+
+        let equity: Vec<(&[f64], &str)> = self
+            .runs
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.equity.as_slice(), color(i)))
+            .collect();
+        html += "<h2>Equity</h2>\n";
+        html += &svg_lines(&equity);
+
+        let drawdown: Vec<(&[f64], &str)> = self
+            .runs
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.drawdown_pct.as_slice(), color(i)))
+            .collect();
+        html += "<h2>Drawdown, %</h2>\n";
+        html += &svg_lines(&drawdown);
+
+        html += "</body>\n</html>\n";
+
+        html
+    }
+    /// Save table and html, return directory.
+    ///
+    /// # ru
+    /// Сохраняет таблицу и html сравнения, возвращает каталог.
+    pub fn save(&self, template: &Test) -> Result<PathBuf, AvinError> {
+        let mut dir = PathBuf::new();
+        dir.push(CFG.dir.test());
+        dir.push("compare");
+        dir.push(format!(
+            "{}_{}_{}",
+            template.iid.ticker(),
+            template.begin().format("%Y%m%d"),
+            template.end().format("%Y%m%d"),
+        ));
+
+        let mut df = self.table();
+        Cmd::write_pqt(&mut df, &dir.join("result.parquet"))?;
+
+        let title = format!(
+            "{} {} - {}",
+            template.iid.ticker(),
+            template.begin().format("%Y-%m-%d"),
+            template.end().format("%Y-%m-%d"),
+        );
+        Cmd::write(&self.html(&title), &dir.join("comparison.html"))?;
+
+        log::info!(":: Comparison save {}", dir.display());
+        Ok(dir)
+    }
+}
+
+fn run_name(test: &Test) -> String {
+    let mut name =
+        format!("{} v{}", test.strategy_name, test.strategy_version);
+    if !test.params.is_empty() {
+        write!(name, " {}", test.params).unwrap();
+    }
+
+    name
+}
+fn color(i: usize) -> &'static str {
+    COLORS[i % COLORS.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::TradeList;
+
+    use super::*;
+
+    fn run(
+        name: &str,
+        equity: Vec<f64>,
+        drawdown: Vec<f64>,
+    ) -> ComparisonRun {
+        ComparisonRun {
+            name: name.to_string(),
+            summary: Summary::new(&TradeList::new(name)),
+            equity,
+            drawdown_pct: drawdown,
+            path: PathBuf::from(name),
+        }
+    }
+
+    #[test]
+    fn table_and_html() {
+        let mut comparison = Comparison::new();
+        comparison.runs.push(run(
+            "A v1",
+            vec![100.0, 110.0, 105.0],
+            vec![0.0, 0.0, -4.5],
+        ));
+        comparison.runs.push(run(
+            "B v2",
+            vec![100.0, 90.0, 120.0],
+            vec![0.0, -10.0, 0.0],
+        ));
+
+        let df = comparison.table();
+        assert_eq!(df.height(), 2);
+        let drawdown = column_f64(&df, "max_drawdown_pct");
+        assert_eq!(drawdown, vec![-4.5, -10.0]);
+
+        let html = comparison.html("SBER <test>");
+        assert!(html.contains("SBER &lt;test&gt;"));
+        assert_eq!(html.matches("<polyline").count(), 4);
+        assert!(html.contains(COLORS[1]));
+    }
+}
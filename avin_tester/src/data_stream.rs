@@ -11,6 +11,8 @@ use chrono::{DateTime, Utc};
 
 use avin_core::{Bar, BarEvent, Event, Iid, Manager, MarketData, TimeFrame};
 
+use crate::Noise;
+
 pub struct DataStream {
     pub iid: Iid,
     bars_1m: VecDeque<Bar>,
@@ -38,6 +40,14 @@ impl DataStream {
         None
     }
 
+    /// Perturb prices of bars, see [`Noise`].
+    ///
+    /// # ru
+    /// Добавляет шум к ценам баров потока.
+    pub fn add_noise(&mut self, noise: &Noise, seed: u64) {
+        noise.apply(&self.iid, seed, self.bars_1m.iter_mut());
+    }
+
     // private
 }
 
@@ -0,0 +1,175 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::Path;
+
+use polars::prelude::{DataFrame, df};
+
+use avin_core::{Direction, Order};
+use avin_utils::{AvinError, Cmd};
+
+/// Bar by bar equity and drawdown of backtest.
+///
+/// # ru
+/// Кривая капитала теста. Тестер передает сюда исполненные ордера и
+/// закрытие каждого 1М бара, на каждом баре записывается капитал:
+/// деньги + позиция по цене закрытия бара, и просадка от предыдущего
+/// максимума капитала. Сохраняется рядом с тестом, см.
+/// [`crate::Test::equity_path`], колонки:
+/// - ts_nanos - время бара;
+/// - equity - капитал в валюте;
+/// - drawdown - просадка в валюте, <= 0;
+/// - drawdown_pct - просадка в процентах от максимума, <= 0.
+#[derive(Debug)]
+pub struct Equity {
+    cash: f64,
+    position: i64,
+    peak: f64,
+
+    ts: Vec<i64>,
+    equity: Vec<f64>,
+    drawdown: Vec<f64>,
+    drawdown_pct: Vec<f64>,
+}
+impl Equity {
+    pub fn new(deposit: f64) -> Self {
+        Self {
+            cash: deposit,
+            position: 0,
+            peak: deposit,
+
+            ts: Vec::new(),
+            equity: Vec::new(),
+            drawdown: Vec::new(),
+            drawdown_pct: Vec::new(),
+        }
+    }
+    pub fn load(path: &Path) -> Result<DataFrame, AvinError> {
+        Cmd::read_pqt(path)
+    }
+
+    /// Apply filled order to cash and position.
+    ///
+    /// # ru
+    /// Учитывает исполненный ордер, остальные статусы игнорируются.
+    pub fn order(&mut self, order: &Order) {
+        let Some(operation) = order.operation() else {
+            return;
+        };
+
+        let quantity = operation.quantity.abs() as i64;
+        match order.direction() {
+            Direction::Buy => {
+                self.cash -= operation.value.abs();
+                self.position += quantity;
+            }
+            Direction::Sell => {
+                self.cash += operation.value.abs();
+                self.position -= quantity;
+            }
+        }
+        self.cash -= operation.commission;
+    }
+    /// Current position, quantity of securities.
+    ///
+    /// # ru
+    /// Текущая позиция, количество бумаг (< 0 - шорт).
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+    /// Equity at price.
+    ///
+    /// # ru
+    /// Капитал: деньги + позиция по цене price.
+    pub fn value(&self, price: f64) -> f64 {
+        self.cash + self.position as f64 * price
+    }
+    /// Record equity at bar close.
+    ///
+    /// # ru
+    /// Записывает капитал по цене закрытия бара.
+    pub fn bar(&mut self, ts_nanos: i64, close: f64) {
+        let equity = self.value(close);
+        self.peak = self.peak.max(equity);
+        let drawdown = equity - self.peak;
+
+        self.ts.push(ts_nanos);
+        self.equity.push(equity);
+        self.drawdown.push(drawdown);
+        self.drawdown_pct.push(drawdown / self.peak * 100.0);
+    }
+    /// Time of first and last recorded bar.
+    ///
+    /// # ru
+    /// Время первого и последнего записанного бара.
+    pub fn range(&self) -> Option<(i64, i64)> {
+        Some((*self.ts.first()?, *self.ts.last()?))
+    }
+    pub fn df(&self) -> DataFrame {
+        df!(
+            "ts_nanos" => &self.ts,
+            "equity" => &self.equity,
+            "drawdown" => &self.drawdown,
+            "drawdown_pct" => &self.drawdown_pct,
+        )
+        .unwrap()
+    }
+    pub fn save(&self, path: &Path) -> Result<(), AvinError> {
+        let mut df = self.df();
+
+        Cmd::write_pqt(&mut df, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use avin_core::{MarketOrder, Transaction};
+
+    fn filled(direction: Direction, price: f64) -> Order {
+        let mut order =
+            MarketOrder::new(direction, 1).post("broker_id=100500");
+        order.add_transaction(Transaction::new(10, price));
+        let order = order.fill(0, 1.0);
+
+        Order::Market(MarketOrder::Filled(order))
+    }
+
+    #[test]
+    fn equity_drawdown() {
+        let mut e = Equity::new(1000.0);
+        e.bar(1, 100.0);
+
+        e.order(&filled(Direction::Buy, 50.0));
+        e.bar(2, 60.0);
+        e.bar(3, 40.0);
+
+        e.order(&filled(Direction::Sell, 45.0));
+        e.bar(4, 100.0);
+
+        let df = e.df();
+        assert_eq!(df.height(), 4);
+
+        let equity: Vec<f64> = df
+            .column("equity")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(equity, vec![1000.0, 1099.0, 899.0, 948.0]);
+
+        let drawdown: Vec<f64> = df
+            .column("drawdown")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(drawdown, vec![0.0, 0.0, -200.0, -151.0]);
+    }
+}
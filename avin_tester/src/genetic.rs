@@ -0,0 +1,349 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::{HashMap, HashSet};
+
+use avin_strategy::{Param, ParamSet, Params};
+use avin_utils::AvinError;
+
+use crate::optimizer::run_parallel;
+use crate::random::{Random, Stream, derive};
+use crate::{Holdout, Metric, OptimizerRun, Test};
+
+/// Genetic optimizer for large parameter spaces.
+///
+/// # ru
+/// Генетический оптимизатор, для пространств параметров, которые долго
+/// перебирать сеткой [`crate::Optimizer`]. Особь - набор индексов
+/// значений параметров из [`Params::params`], приспособленность -
+/// метрика полного теста.
+///
+/// Поколение: новые особи сначала проходят короткий отбор - тест на
+/// первой доле периода (screen), худшая доля (cull) отбрасывается без
+/// полного теста. Остальные тестируются на всем периоде. Лучшие особи
+/// (elite) переходят в следующее поколение без изменений, остальные
+/// получаются скрещиванием победителей турниров и мутацией. Поиск
+/// останавливается после generations поколений, или раньше, если
+/// лучший результат не улучшался patience поколений подряд.
+///
+/// Все случайные решения идут от seed теста-шаблона (или seed,
+/// заданного через set_seed), одинаковые настройки дают одинаковый
+/// результат.
+pub struct Genetic {
+    metric: Metric,
+    threads: usize,
+    seed: Option<u64>,
+    population: usize,
+    generations: usize,
+    elite: usize,
+    mutation: f64,
+    patience: usize,
+    screen: f64,
+    cull: f64,
+}
+impl Genetic {
+    pub fn new(metric: Metric) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        Self {
+            metric,
+            threads,
+            seed: None,
+            population: 20,
+            generations: 10,
+            elite: 2,
+            mutation: 0.1,
+            patience: 3,
+            screen: 0.25,
+            cull: 0.25,
+        }
+    }
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+    pub fn set_population(&mut self, population: usize) {
+        self.population = population.max(2);
+    }
+    pub fn set_generations(&mut self, generations: usize) {
+        self.generations = generations.max(1);
+    }
+    pub fn set_elite(&mut self, elite: usize) {
+        self.elite = elite;
+    }
+    pub fn set_mutation(&mut self, probability: f64) {
+        self.mutation = probability.clamp(0.0, 1.0);
+    }
+    pub fn set_patience(&mut self, generations: usize) {
+        self.patience = generations.max(1);
+    }
+    /// Early stopping: test candidates on first share of period, drop
+    /// the worst cull share of them. screen = 1.0 - disabled.
+    ///
+    /// # ru
+    /// Ранний отсев: кандидаты тестируются на первой доле screen
+    /// периода, худшая доля cull отбрасывается без полного теста.
+    /// screen = 1.0 или cull = 0.0 - отсев выключен.
+    pub fn set_screen(&mut self, screen: f64, cull: f64) {
+        self.screen = screen.clamp(0.0, 1.0);
+        self.cull = cull.clamp(0.0, 1.0);
+    }
+
+    /// Run genetic search over strategy parameters.
+    ///
+    /// # ru
+    /// Запускает поиск, возвращает все полностью протестированные
+    /// запуски, отсортированные по метрике, лучший первый. Таблицу
+    /// можно получить и сохранить через [`crate::Optimizer`]. Период
+    /// шаблона не должен захватывать отложенный период инструмента,
+    /// см. [`crate::Holdout`].
+    pub fn run<S: Params>(
+        &self,
+        template: &Test,
+    ) -> Result<Vec<OptimizerRun>, AvinError> {
+        Holdout::check(template)?;
+
+        let space = S::params();
+        let seed = self.seed.unwrap_or(template.seed);
+        let mut rng = Random::new(derive(seed, Stream::Genetic, 0));
+
+        let mut fitness: HashMap<String, f64> = HashMap::new();
+        let mut culled: HashSet<String> = HashSet::new();
+        let mut runs = Vec::new();
+
+        let mut population = self.first_population(&space, &mut rng);
+        let mut best = f64::NEG_INFINITY;
+        let mut stale = 0;
+
+        for generation in 0..self.generations {
+            // новые особи этого поколения
+            let mut candidates = Vec::new();
+            for genome in population.iter() {
+                let set = param_set(&space, genome);
+                let key = set.to_string();
+                if !fitness.contains_key(&key)
+                    && !culled.contains(&key)
+                    && !candidates.contains(&set)
+                {
+                    candidates.push(set);
+                }
+            }
+
+            // ранний отсев
+            let candidates =
+                self.screen::<S>(template, candidates, &mut culled);
+
+            // полный тест
+            let full = run_parallel::<S>(
+                template,
+                &candidates,
+                template.end_ts_nanos,
+                self.threads,
+            );
+            for run in full {
+                let value = self.metric.value(&run.summary);
+                fitness.insert(run.params.to_string(), value);
+                runs.push(run);
+            }
+
+            // ранняя остановка всего поиска
+            let gen_best =
+                fitness.values().copied().fold(f64::NEG_INFINITY, f64::max);
+            if gen_best > best {
+                best = gen_best;
+                stale = 0;
+            } else {
+                stale += 1;
+            }
+            log::info!(
+                ":: Genetic generation {generation}: tested {}, best {}={best}",
+                candidates.len(),
+                self.metric,
+            );
+            if stale >= self.patience {
+                break;
+            }
+
+            population =
+                self.next_population(&space, &population, &fitness, &mut rng);
+        }
+
+        self.metric.sort(&mut runs);
+
+        Ok(runs)
+    }
+
+    // private
+    fn first_population(
+        &self,
+        space: &[Param],
+        rng: &mut Random,
+    ) -> Vec<Vec<usize>> {
+        let total = space
+            .iter()
+            .map(|p| p.values.len())
+            .fold(1usize, |a, n| a.saturating_mul(n));
+        let size = self.population.min(total);
+
+        // случайные различные особи, но не бесконечно - в маленьком
+        // пространстве могут повторяться
+        let mut population: Vec<Vec<usize>> = Vec::with_capacity(size);
+        let mut attempts = 0;
+        while population.len() < size && attempts < size * 100 {
+            let genome: Vec<usize> =
+                space.iter().map(|p| rng.below(p.values.len())).collect();
+            if !population.contains(&genome) {
+                population.push(genome);
+            }
+            attempts += 1;
+        }
+
+        population
+    }
+    fn next_population(
+        &self,
+        space: &[Param],
+        population: &[Vec<usize>],
+        fitness: &HashMap<String, f64>,
+        rng: &mut Random,
+    ) -> Vec<Vec<usize>> {
+        // отброшенные при отсеве особи - худшие
+        let score = |genome: &Vec<usize>| -> f64 {
+            let key = param_set(space, genome).to_string();
+            fitness.get(&key).copied().unwrap_or(f64::NEG_INFINITY)
+        };
+
+        let mut ranked: Vec<&Vec<usize>> = population.iter().collect();
+        ranked.sort_by(|a, b| score(b).total_cmp(&score(a)));
+
+        let mut next: Vec<Vec<usize>> =
+            ranked.iter().take(self.elite).map(|g| g.to_vec()).collect();
+
+        let tournament = |rng: &mut Random| -> &Vec<usize> {
+            let a = &population[rng.below(population.len())];
+            let b = &population[rng.below(population.len())];
+            if score(a) >= score(b) { a } else { b }
+        };
+
+        while next.len() < self.population {
+            let a = tournament(rng);
+            let b = tournament(rng);
+
+            // равномерное скрещивание и мутация
+            let mut child: Vec<usize> = a
+                .iter()
+                .zip(b.iter())
+                .map(|(x, y)| if rng.chance(0.5) { *x } else { *y })
+                .collect();
+            for (gene, param) in child.iter_mut().zip(space.iter()) {
+                if rng.chance(self.mutation) {
+                    *gene = rng.below(param.values.len());
+                }
+            }
+
+            next.push(child);
+        }
+
+        next
+    }
+    fn screen<S: Params>(
+        &self,
+        template: &Test,
+        candidates: Vec<ParamSet>,
+        culled: &mut HashSet<String>,
+    ) -> Vec<ParamSet> {
+        if self.screen >= 1.0 || self.cull <= 0.0 || candidates.len() < 2 {
+            return candidates;
+        }
+
+        let period = template.end_ts_nanos - template.begin_ts_nanos;
+        let end =
+            template.begin_ts_nanos + (period as f64 * self.screen) as i64;
+        let screened =
+            run_parallel::<S>(template, &candidates, end, self.threads);
+
+        let mut values: Vec<f64> = screened
+            .iter()
+            .map(|r| self.metric.value(&r.summary))
+            .collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        let i = (values.len() as f64 * self.cull) as usize;
+        let threshold = values[i.min(values.len() - 1)];
+
+        // строго хуже порога - если все одинаковые, не отсекаем никого
+        let mut survivors = Vec::new();
+        for run in screened {
+            if self.metric.value(&run.summary) < threshold {
+                culled.insert(run.params.to_string());
+            } else {
+                survivors.push(run.params);
+            }
+        }
+
+        survivors
+    }
+}
+
+fn param_set(space: &[Param], genome: &[usize]) -> ParamSet {
+    let mut set = ParamSet::new();
+    for (param, i) in space.iter().zip(genome.iter()) {
+        set.set(param.name, param.values[*i]);
+    }
+
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space() -> Vec<Param> {
+        vec![
+            Param::range("a", 1.0, 10.0, 1.0),
+            Param::range("b", 1.0, 10.0, 1.0),
+        ]
+    }
+
+    #[test]
+    fn population_reproducible() {
+        let genetic = Genetic::new(Metric::Profit);
+
+        let p1 = genetic.first_population(&space(), &mut Random::new(7));
+        let p2 = genetic.first_population(&space(), &mut Random::new(7));
+        assert_eq!(p1.len(), 20);
+        assert_eq!(p1, p2);
+
+        let p3 = genetic.first_population(&space(), &mut Random::new(8));
+        assert_ne!(p1, p3);
+    }
+    #[test]
+    fn next_population_keeps_elite() {
+        let space = space();
+        let genetic = Genetic::new(Metric::Profit);
+        let mut rng = Random::new(1);
+
+        let population = genetic.first_population(&space, &mut rng);
+        let mut fitness = HashMap::new();
+        for (i, genome) in population.iter().enumerate() {
+            let key = param_set(&space, genome).to_string();
+            fitness.insert(key, i as f64);
+        }
+
+        let next =
+            genetic.next_population(&space, &population, &fitness, &mut rng);
+        assert_eq!(next.len(), 20);
+        assert_eq!(next[0], population[19]);
+        assert_eq!(next[1], population[18]);
+        for genome in next {
+            assert!(genome.iter().all(|i| *i < 10));
+        }
+    }
+}
@@ -0,0 +1,192 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use avin_utils::{AvinError, CFG, str_date_to_utc};
+
+use crate::Test;
+
+/// Locked out-of-sample period of instrument.
+///
+/// # ru
+/// Отложенный (out-of-sample) период инструмента. Оптимизаторам
+/// ([`crate::Optimizer`], [`crate::Genetic`]) запрещено запускать
+/// перебор параметров на тестах, захватывающих этот период - данные
+/// остаются "невидимыми" до финальной проверки, которая делается
+/// обычным [`crate::Tester`]. Периоды задаются в конфиге, секция
+/// [[tester.holdout]]; при tester.holdout_warn = true оптимизатор не
+/// отказывает, а громко пишет предупреждение в лог.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holdout {
+    /// Идентификатор инструмента в нижнем регистре: "moex_share_sber".
+    pub iid: String,
+    pub begin: DateTime<Utc>,
+    /// None - до текущего момента.
+    pub end: Option<DateTime<Utc>>,
+}
+impl Holdout {
+    pub fn new(
+        iid: &str,
+        begin: DateTime<Utc>,
+        end: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            iid: iid.to_lowercase(),
+            begin,
+            end,
+        }
+    }
+    /// All holdout periods from config.
+    ///
+    /// # ru
+    /// Все отложенные периоды из конфига.
+    pub fn all() -> Result<Vec<Holdout>, AvinError> {
+        let mut holdouts = Vec::new();
+        for cfg in CFG.tester.holdout.iter() {
+            let begin = parse_date(&cfg.begin)?;
+            let end = match cfg.end.as_str() {
+                "" => None,
+                end => Some(parse_date(end)?),
+            };
+            holdouts.push(Holdout::new(&cfg.iid, begin, end));
+        }
+
+        Ok(holdouts)
+    }
+    /// Check that period [begin, end) intersects holdout.
+    ///
+    /// # ru
+    /// Пересекается ли период теста [begin, end) с отложенным.
+    pub fn overlaps(&self, begin: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        let before_end = match self.end {
+            Some(holdout_end) => begin < holdout_end,
+            None => true,
+        };
+
+        before_end && end > self.begin
+    }
+    /// Check template test of parameter search against config holdouts.
+    ///
+    /// # ru
+    /// Проверка теста-шаблона перед перебором параметров: если период
+    /// теста захватывает отложенный период инструмента - ошибка, или
+    /// предупреждение при tester.holdout_warn = true.
+    pub fn check(template: &Test) -> Result<(), AvinError> {
+        let holdouts = Self::all()?;
+
+        check(
+            &holdouts,
+            &template.iid.to_string(),
+            template.begin(),
+            template.end(),
+            CFG.tester.holdout_warn,
+        )
+    }
+}
+impl std::fmt::Display for Holdout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let end = match self.end {
+            Some(end) => end.format("%Y-%m-%d").to_string(),
+            None => "now".to_string(),
+        };
+
+        write!(
+            f,
+            "Holdout={} {} - {}",
+            self.iid,
+            self.begin.format("%Y-%m-%d"),
+            end
+        )
+    }
+}
+
+/// Проверка периода теста инструмента iid по списку отложенных
+/// периодов. warn_only - только предупреждение в лог.
+fn check(
+    holdouts: &[Holdout],
+    iid: &str,
+    begin: DateTime<Utc>,
+    end: DateTime<Utc>,
+    warn_only: bool,
+) -> Result<(), AvinError> {
+    let iid = iid.to_lowercase();
+    let found = holdouts
+        .iter()
+        .find(|h| h.iid == iid && h.overlaps(begin, end));
+    let Some(holdout) = found else {
+        return Ok(());
+    };
+
+    let msg = format!(
+        "parameter search {} - {} reads locked out-of-sample data: \
+        {holdout}",
+        begin.format("%Y-%m-%d"),
+        end.format("%Y-%m-%d"),
+    );
+    if warn_only {
+        log::warn!("!!! HOLDOUT VIOLATION !!! {msg}");
+        return Ok(());
+    }
+
+    Err(AvinError::InvalidValue(msg))
+}
+fn parse_date(date: &str) -> Result<DateTime<Utc>, AvinError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        AvinError::InvalidValue(format!("holdout date {date}"))
+    })?;
+
+    Ok(str_date_to_utc(date))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn dt(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn overlaps() {
+        let h = Holdout::new(
+            "MOEX_SHARE_SBER",
+            dt(2025, 1, 1),
+            Some(dt(2025, 7, 1)),
+        );
+        assert_eq!(h.iid, "moex_share_sber");
+
+        assert!(!h.overlaps(dt(2024, 1, 1), dt(2025, 1, 1)));
+        assert!(h.overlaps(dt(2024, 1, 1), dt(2025, 1, 2)));
+        assert!(h.overlaps(dt(2025, 3, 1), dt(2025, 4, 1)));
+        assert!(!h.overlaps(dt(2025, 7, 1), dt(2026, 1, 1)));
+
+        let open = Holdout::new("moex_share_sber", dt(2025, 1, 1), None);
+        assert!(open.overlaps(dt(2030, 1, 1), dt(2031, 1, 1)));
+    }
+    #[test]
+    fn refuse_or_warn() {
+        let holdouts =
+            vec![Holdout::new("moex_share_sber", dt(2025, 1, 1), None)];
+        let (begin, end) = (dt(2024, 1, 1), dt(2025, 6, 1));
+
+        let result = check(&holdouts, "MOEX_SHARE_SBER", begin, end, false);
+        assert!(result.is_err());
+        assert!(
+            check(&holdouts, "MOEX_SHARE_SBER", begin, end, true).is_ok()
+        );
+        assert!(
+            check(&holdouts, "MOEX_SHARE_GAZP", begin, end, false).is_ok()
+        );
+        assert!(
+            check(&holdouts, "MOEX_SHARE_SBER", begin, dt(2025, 1, 1), false)
+                .is_ok()
+        );
+    }
+}
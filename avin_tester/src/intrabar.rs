@@ -0,0 +1,209 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use bitcode::{Decode, Encode};
+
+use avin_core::{Bar, StopOrderKind};
+use avin_utils::AvinError;
+
+/// Assumption how order levels are reached inside OHLC bar.
+///
+/// # ru
+/// Допущение о движении цены внутри 1М бара. По OHLC бара нельзя
+/// узнать, что было раньше - High или Low, поэтому если в одном баре
+/// оказались и стоп лосс и тейк профит, результат теста зависит от
+/// допущения. Прогон одного теста с разными режимами показывает,
+/// насколько стратегия чувствительна к исполнению внутри бара.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum Intrabar {
+    /// Худший случай: сначала срабатывают стоп лоссы, лимитный ордер
+    /// исполняется только если цена прошла через его уровень.
+    Conservative,
+    /// Лучший случай: сначала срабатывают тейк профиты, лимитный
+    /// ордер исполняется при касании уровня.
+    Optimistic,
+    /// Путь цены по OHLC: бычий бар Open-Low-High-Close, медвежий
+    /// Open-High-Low-Close, уровни срабатывают в порядке прохождения.
+    /// Лимитный ордер исполняется при касании уровня.
+    #[default]
+    Path,
+}
+impl Intrabar {
+    pub fn all() -> [Intrabar; 3] {
+        [Self::Conservative, Self::Optimistic, Self::Path]
+    }
+    /// Check limit order at price is filled by bar.
+    ///
+    /// # ru
+    /// Исполняется ли лимитный ордер с ценой price в баре, без учета
+    /// гэпа на открытии.
+    pub fn limit_filled(&self, bar: &Bar, price: f64) -> bool {
+        match self {
+            Self::Conservative => bar.l < price && price < bar.h,
+            Self::Optimistic | Self::Path => bar.contains(price),
+        }
+    }
+    /// Order of triggering stop order in bar, smaller - earlier.
+    ///
+    /// # ru
+    /// Порядок сработки стоп ордера с ценой stop_price в баре, меньше -
+    /// раньше. Тестер проверяет стоп ордера в этом порядке.
+    pub fn stop_order(
+        &self,
+        bar: &Bar,
+        kind: &StopOrderKind,
+        stop_price: f64,
+    ) -> f64 {
+        let loss_first = match kind {
+            StopOrderKind::StopLoss => 0.0,
+            StopOrderKind::TakeProfit => 1.0,
+        };
+
+        match self {
+            Self::Conservative => loss_first,
+            Self::Optimistic => 1.0 - loss_first,
+            Self::Path => path_position(bar, stop_price),
+        }
+    }
+}
+impl TryFrom<&str> for Intrabar {
+    type Error = AvinError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "conservative" => Ok(Self::Conservative),
+            "optimistic" => Ok(Self::Optimistic),
+            "path" => Ok(Self::Path),
+            _ => {
+                let msg = format!("intrabar mode {value}");
+                Err(AvinError::InvalidValue(msg))
+            }
+        }
+    }
+}
+impl std::fmt::Display for Intrabar {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Conservative => write!(f, "conservative"),
+            Self::Optimistic => write!(f, "optimistic"),
+            Self::Path => write!(f, "path"),
+        }
+    }
+}
+
+/// Rest of bar after part of its time passed, by OHLC path.
+///
+/// # ru
+/// Остаток бара после доли времени part (0..1), по пути цены OHLC
+/// (см. [`Intrabar::Path`]): Open - цена на пути в этот момент, High и
+/// Low - экстремумы оставшегося пути, Close и время бара прежние, объем
+/// пропорционален оставшемуся времени. Так ордер, пришедший к брокеру
+/// с задержкой внутри бара, видит только цены после своего прихода.
+pub(crate) fn rest_of_bar(bar: &Bar, part: f64) -> Bar {
+    let path = path(bar);
+    let position = part.clamp(0.0, 1.0) * 3.0;
+    let i = (position.floor() as usize).min(2);
+    let open = path[i] + (path[i + 1] - path[i]) * (position - i as f64);
+
+    let rest = path[i + 1..].iter();
+    let high = rest.clone().fold(open, |a, b| a.max(*b));
+    let low = rest.fold(open, |a, b| a.min(*b));
+    let volume = (bar.v as f64 * (1.0 - part.clamp(0.0, 1.0))) as u64;
+
+    Bar::new(bar.ts, open, high, low, bar.c, volume)
+}
+
+/// Путь цены в баре: бычий бар Open-Low-High-Close, медвежий
+/// Open-High-Low-Close.
+fn path(bar: &Bar) -> [f64; 4] {
+    if bar.c >= bar.o {
+        [bar.o, bar.l, bar.h, bar.c]
+    } else {
+        [bar.o, bar.h, bar.l, bar.c]
+    }
+}
+/// Положение цены на пути OHLC бара: 0 - Open, 1 - первый экстремум,
+/// 2 - второй, 3 - Close. Цена вне бара (гэп) - на открытии.
+fn path_position(bar: &Bar, price: f64) -> f64 {
+    let path = path(bar);
+
+    for i in 0..3 {
+        let (a, b) = (path[i], path[i + 1]);
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        if lo <= price && price <= hi {
+            let span = b - a;
+            let part = if span == 0.0 { 0.0 } else { (price - a) / span };
+            return i as f64 + part;
+        }
+    }
+
+    0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path() {
+        // бычий бар: 100 -> 95 -> 110 -> 105
+        let bar = Bar::new(0, 100.0, 110.0, 95.0, 105.0, 1000);
+        assert_eq!(path_position(&bar, 100.0), 0.0);
+        assert_eq!(path_position(&bar, 95.0), 1.0);
+        assert_eq!(path_position(&bar, 110.0), 2.0);
+        assert!(path_position(&bar, 97.0) < path_position(&bar, 108.0));
+        assert_eq!(path_position(&bar, 120.0), 0.0);
+
+        // медвежий бар - сначала High
+        let bar = Bar::new(0, 100.0, 110.0, 95.0, 98.0, 1000);
+        assert!(path_position(&bar, 108.0) < path_position(&bar, 97.0));
+    }
+    #[test]
+    fn rest() {
+        // бычий бар: 100 -> 95 -> 110 -> 105
+        let bar = Bar::new(0, 100.0, 110.0, 95.0, 105.0, 1200);
+        assert_eq!(rest_of_bar(&bar, 0.0), bar);
+
+        // середина второго отрезка пути: 95 -> 110
+        let rest = rest_of_bar(&bar, 0.5);
+        assert_eq!(rest, Bar::new(0, 102.5, 110.0, 102.5, 105.0, 600));
+
+        // после High осталось только падение к закрытию
+        let rest = rest_of_bar(&bar, 0.75);
+        assert_eq!(rest, Bar::new(0, 108.75, 108.75, 105.0, 105.0, 300));
+
+        let rest = rest_of_bar(&bar, 1.0);
+        assert_eq!(
+            (rest.o, rest.h, rest.l, rest.c),
+            (105.0, 105.0, 105.0, 105.0)
+        );
+    }
+    #[test]
+    fn modes() {
+        let bar = Bar::new(0, 100.0, 110.0, 95.0, 105.0, 1000);
+        let loss = StopOrderKind::StopLoss;
+        let take = StopOrderKind::TakeProfit;
+
+        let m = Intrabar::Conservative;
+        assert!(
+            m.stop_order(&bar, &loss, 96.0)
+                < m.stop_order(&bar, &take, 109.0)
+        );
+        assert!(!m.limit_filled(&bar, 95.0));
+        assert!(m.limit_filled(&bar, 96.0));
+
+        let m = Intrabar::Optimistic;
+        assert!(
+            m.stop_order(&bar, &take, 109.0)
+                < m.stop_order(&bar, &loss, 96.0)
+        );
+        assert!(m.limit_filled(&bar, 95.0));
+
+        assert_eq!(Intrabar::try_from("Path").unwrap(), Intrabar::Path);
+        assert!(Intrabar::try_from("random").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::Path;
+
+use polars::prelude::{DataFrame, df};
+
+use avin_core::{
+    Asset, Bar, ExtremumIndicator, LimitOrder, Order, OrderAction, Signal,
+    StopOrder, Term, TimeFrame,
+};
+use avin_utils::{AvinError, Cmd};
+
+/// Decision of strategy: order with context.
+///
+/// # ru
+/// Запись журнала: ордер, отправленный стратегией, и контекст, в
+/// котором принято решение.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    /// "post" или "cancel".
+    pub action: &'static str,
+    /// "market", "limit", "stop".
+    pub order: &'static str,
+    pub direction: String,
+    pub lots: u32,
+    /// Цена лимитного ордера или цена сработки стоп ордера.
+    pub price: Option<f64>,
+    /// 1М бар, на котором принято решение.
+    pub bar: Bar,
+    pub reason: String,
+    pub context: Vec<(String, String)>,
+}
+
+/// Backtest journal of strategy decisions.
+///
+/// # ru
+/// Журнал решений стратегии в тесте. Тестер записывает каждый ордер,
+/// отправленный стратегией (выставление и отмену), с баром, на котором
+/// принято решение, причиной [`avin_strategy::Strategy::reason`] и
+/// контекстом: текущие тренды T1-T3 графиков с индикатором
+/// экстремумов и значения [`avin_strategy::Strategy::context`].
+/// Сохраняется рядом с тестом, см. [`crate::Test::journal_path`], чтобы
+/// убыточные трейды можно было разобрать после теста в GUI или
+/// ноутбуке. Сигналы стратегии [`Signal`] журнал хранит отдельно и
+/// сохраняет в свой файл, см. [`crate::Test::signals_path`].
+#[derive(Debug, Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+    signals: Vec<Signal>,
+}
+impl Journal {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            signals: Vec::new(),
+        }
+    }
+    pub fn load(path: &Path) -> Result<DataFrame, AvinError> {
+        Cmd::read_pqt(path)
+    }
+
+    /// Record order action of strategy.
+    ///
+    /// # ru
+    /// Записывает действие стратегии с ордером, bar - текущий 1М бар.
+    pub fn record(
+        &mut self,
+        action: &'static str,
+        order_action: &OrderAction,
+        bar: Bar,
+        reason: String,
+        context: Vec<(String, String)>,
+    ) {
+        let order = &order_action.order;
+        let (kind, price) = match order {
+            Order::Market(_) => ("market", None),
+            Order::Limit(o) => ("limit", limit_price(o)),
+            Order::Stop(o) => ("stop", stop_price(o)),
+        };
+
+        self.entries.push(JournalEntry {
+            action,
+            order: kind,
+            direction: order.direction().to_string(),
+            lots: order.lots(),
+            price,
+            bar,
+            reason,
+            context,
+        });
+    }
+    /// Record signal of strategy.
+    ///
+    /// # ru
+    /// Записывает сигнал стратегии.
+    pub fn signal(&mut self, signal: Signal) {
+        self.signals.push(signal);
+    }
+    pub fn entries(&self) -> &Vec<JournalEntry> {
+        &self.entries
+    }
+    pub fn signals(&self) -> &Vec<Signal> {
+        &self.signals
+    }
+    pub fn df(&self) -> DataFrame {
+        let e = &self.entries;
+        let context: Vec<String> = e
+            .iter()
+            .map(|i| {
+                i.context
+                    .iter()
+                    .map(|(k, v)| format!("{k}={v}"))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .collect();
+
+        df!(
+            "ts_nanos" => e.iter().map(|i| i.bar.ts).collect::<Vec<_>>(),
+            "action" => e.iter().map(|i| i.action).collect::<Vec<_>>(),
+            "order" => e.iter().map(|i| i.order).collect::<Vec<_>>(),
+            "direction" =>
+                e.iter().map(|i| i.direction.clone()).collect::<Vec<_>>(),
+            "lots" => e.iter().map(|i| i.lots).collect::<Vec<_>>(),
+            "price" => e.iter().map(|i| i.price).collect::<Vec<_>>(),
+            "open" => e.iter().map(|i| i.bar.o).collect::<Vec<_>>(),
+            "high" => e.iter().map(|i| i.bar.h).collect::<Vec<_>>(),
+            "low" => e.iter().map(|i| i.bar.l).collect::<Vec<_>>(),
+            "close" => e.iter().map(|i| i.bar.c).collect::<Vec<_>>(),
+            "volume" => e.iter().map(|i| i.bar.v).collect::<Vec<_>>(),
+            "reason" =>
+                e.iter().map(|i| i.reason.clone()).collect::<Vec<_>>(),
+            "context" => context,
+        )
+        .unwrap()
+    }
+    pub fn save(&self, path: &Path) -> Result<(), AvinError> {
+        let mut df = self.df();
+
+        Cmd::write_pqt(&mut df, path)
+    }
+    pub fn save_signals(&self, path: &Path) -> Result<(), AvinError> {
+        let mut df = Signal::df(&self.signals);
+
+        Cmd::write_pqt(&mut df, path)
+    }
+}
+
+/// Current trends T1-T3 of asset charts with extremum indicator.
+///
+/// # ru
+/// Текущие тренды T1-T3 графиков актива, на которые добавлен
+/// индикатор экстремумов: "1H T2" = "bull 1.25%".
+pub fn trends_context(asset: &Asset) -> Vec<(String, String)> {
+    let mut context = Vec::new();
+
+    for tf in TimeFrame::all() {
+        let Some(chart) = asset.chart(tf) else {
+            continue;
+        };
+        if !chart.has_extremum() {
+            continue;
+        }
+
+        for term in [Term::T1, Term::T2, Term::T3] {
+            if let Some(trend) = chart.trend(term, 0) {
+                let kind = if trend.is_bull() { "bull" } else { "bear" };
+                let value = format!("{kind} {:.2}%", trend.abs_p());
+                context.push((format!("{tf} {term}"), value));
+            }
+        }
+    }
+
+    context
+}
+
+fn limit_price(order: &LimitOrder) -> Option<f64> {
+    match order {
+        LimitOrder::New(o) => Some(o.price),
+        LimitOrder::Posted(o) => Some(o.price),
+        LimitOrder::PartiallyFilled(o) => Some(o.price),
+        _ => None,
+    }
+}
+fn stop_price(order: &StopOrder) -> Option<f64> {
+    match order {
+        StopOrder::New(o) => Some(o.stop_price),
+        StopOrder::Posted(o) => Some(o.stop_price),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{Account, Direction, MarketOrder};
+
+    use super::*;
+
+    #[test]
+    fn record() {
+        let account = Account::new("VirtualAccount", "Virtual_ID");
+        let bar = Bar::new(100, 10.0, 11.0, 9.0, 10.5, 1000);
+        let mut journal = Journal::new();
+
+        let order = MarketOrder::New(MarketOrder::new(Direction::Buy, 2));
+        let a = OrderAction::new(
+            account.clone(),
+            iid(),
+            "Test",
+            Order::Market(order),
+        );
+        let context = vec![("rsi".to_string(), "70".to_string())];
+        journal.record("post", &a, bar, "pin bar".to_string(), context);
+
+        let order =
+            LimitOrder::New(LimitOrder::new(Direction::Sell, 2, 11.5));
+        let a = OrderAction::new(account, iid(), "Test", Order::Limit(order));
+        journal.record("post", &a, bar, "take".to_string(), Vec::new());
+
+        assert_eq!(journal.entries().len(), 2);
+        assert_eq!(journal.entries()[0].order, "market");
+        assert_eq!(journal.entries()[1].price, Some(11.5));
+
+        let df = journal.df();
+        assert_eq!(df.height(), 2);
+        assert_eq!(
+            df.column("context").unwrap().str().unwrap().get(0),
+            Some("rsi=70")
+        );
+    }
+}
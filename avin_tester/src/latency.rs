@@ -0,0 +1,123 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use bitcode::{Decode, Encode};
+
+use avin_utils::AvinError;
+
+use crate::random::xorshift;
+
+/// Order latency model of backtest.
+///
+/// # ru
+/// Модель задержки между решением стратегии и приходом ордера к
+/// брокеру. [`crate::VirtualBroker`] откладывает каждое действие
+/// стратегии (выставление и отмену ордера) на величину задержки от
+/// закрытия 1М бара, на котором принято решение. Внутри 1М бара, в
+/// который пришел ордер, время прихода переводится в долю пути цены
+/// OHLC (см. [`crate::Intrabar::Path`]): рыночный ордер исполняется
+/// по цене на пути в этот момент, лимитный и стоп ордер проверяются
+/// только по оставшейся части бара. Точнее пути OHLC модель не
+/// бывает: тиков внутри 1М бара у тестера нет. Нулевая задержка -
+/// действие выполняется сразу, как раньше.
+///
+/// Случайная задержка генерируется детерминированно, повторный запуск
+/// теста дает тот же результат.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum Latency {
+    /// Фиксированная задержка, миллисекунды.
+    Fixed(u64),
+    /// Равномерно распределенная задержка в [min, max], миллисекунды.
+    Uniform { min: u64, max: u64 },
+}
+impl Latency {
+    /// Sample delay of next order in nanoseconds.
+    ///
+    /// # ru
+    /// Возвращает задержку очередного ордера в наносекундах, seed -
+    /// состояние генератора, обновляется при каждом вызове.
+    pub fn sample(&self, seed: &mut u64) -> i64 {
+        let ms = match self {
+            Self::Fixed(ms) => *ms,
+            Self::Uniform { min, max } => {
+                let span = max.saturating_sub(*min) + 1;
+                min + xorshift(seed) % span
+            }
+        };
+
+        ms as i64 * 1_000_000
+    }
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Self::Fixed(0) | Self::Uniform { min: 0, max: 0 })
+    }
+}
+impl Default for Latency {
+    fn default() -> Self {
+        Self::Fixed(0)
+    }
+}
+impl TryFrom<&str> for Latency {
+    type Error = AvinError;
+
+    /// Parse "50ms" - fixed, "20-80ms" - uniform.
+    ///
+    /// # ru
+    /// Создает модель из строки: "50ms" - фиксированная задержка,
+    /// "20-80ms" - равномерная в диапазоне.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let err = || AvinError::InvalidValue(format!("latency {value}"));
+
+        let s = value.trim().trim_end_matches("ms");
+        match s.split_once('-') {
+            Some((min, max)) => {
+                let min = min.trim().parse().map_err(|_| err())?;
+                let max = max.trim().parse().map_err(|_| err())?;
+                if min > max {
+                    return Err(err());
+                }
+                Ok(Self::Uniform { min, max })
+            }
+            None => Ok(Self::Fixed(s.parse().map_err(|_| err())?)),
+        }
+    }
+}
+impl std::fmt::Display for Latency {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Fixed(ms) => write!(f, "{ms}ms"),
+            Self::Uniform { min, max } => write!(f, "{min}-{max}ms"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample() {
+        let mut seed = 42;
+        assert_eq!(Latency::Fixed(50).sample(&mut seed), 50_000_000);
+
+        let latency = Latency::Uniform { min: 20, max: 80 };
+        for _ in 0..100 {
+            let ns = latency.sample(&mut seed);
+            assert!((20_000_000..=80_000_000).contains(&ns));
+        }
+    }
+    #[test]
+    fn parse() {
+        assert_eq!(Latency::try_from("50ms").unwrap(), Latency::Fixed(50));
+        assert_eq!(
+            Latency::try_from("20-80ms").unwrap(),
+            Latency::Uniform { min: 20, max: 80 }
+        );
+        assert!(Latency::try_from("80-20ms").is_err());
+        assert!(Latency::try_from("fast").is_err());
+        assert_eq!(Latency::Uniform { min: 1, max: 2 }.to_string(), "1-2ms");
+    }
+}
@@ -6,13 +6,45 @@
  ****************************************************************************/
 
 mod _tester;
+mod commission;
+mod comparison;
 mod data_stream;
+mod equity;
+mod genetic;
+mod holdout;
+mod intrabar;
+mod journal;
+mod latency;
+mod manifest;
+mod margin;
+mod noise;
+mod optimizer;
+mod progress;
+mod random;
+mod report;
+mod sizing;
 mod test;
 mod test_list;
+mod universe;
 mod virtual_broker;
 
 pub use _tester::Tester;
+pub use commission::Commission;
+pub use comparison::{Comparison, ComparisonRun};
 pub use data_stream::DataStream;
-pub use test::{Test, TestStatus};
+pub use equity::Equity;
+pub use genetic::Genetic;
+pub use holdout::Holdout;
+pub use intrabar::Intrabar;
+pub use journal::{Journal, JournalEntry, trends_context};
+pub use latency::Latency;
+pub use manifest::Manifest;
+pub use noise::{Noise, NoiseModel, Robustness};
+pub use optimizer::{Metric, Optimizer, OptimizerRun};
+pub use progress::{CancelToken, Progress};
+pub use report::Report;
+pub use sizing::Sizing;
+pub use test::{DEFAULT_SEED, Test, TestStatus};
 pub use test_list::TestList;
+pub use universe::{Universe, UniverseMember};
 pub use virtual_broker::VirtualBroker;
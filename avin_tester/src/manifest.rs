@@ -0,0 +1,150 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::Path;
+use std::process::Command;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use avin_strategy::StrategyMeta;
+use avin_utils::{AvinError, CFG, Cmd};
+
+use crate::Test;
+
+/// Metadata of backtest run.
+///
+/// # ru
+/// Метаданные запуска теста, сохраняются тестером в manifest.toml
+/// в каталоге запуска [`Test::dir`]. Нужны, чтобы результат можно было
+/// воспроизвести и сравнить с другими: настройки теста, коммит кода,
+/// влияющие на результат секции конфига (data, core, tester) и
+/// фактический диапазон данных, на которых прошел тест.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub strategy: String,
+    pub strategy_version: String,
+    /// Значения и хэш параметров экземпляра стратегии, в отличие от
+    /// params - и для параметров по умолчанию.
+    #[serde(default)]
+    pub strategy_params: String,
+    #[serde(default)]
+    pub strategy_params_hash: String,
+    pub iid: String,
+    pub begin: String,
+    pub end: String,
+    pub params: String,
+    pub params_hash: String,
+    pub deposit: f64,
+    pub commission: String,
+    pub partial_fill: Option<f64>,
+    pub latency: String,
+    pub margin: Option<String>,
+    pub intrabar: String,
+    pub noise: Option<String>,
+    pub sizing: Option<String>,
+    pub seed: u64,
+    pub warmup: String,
+
+    /// Время первого и последнего 1М бара теста.
+    pub data_begin: Option<String>,
+    pub data_end: Option<String>,
+
+    pub created: String,
+    pub avin_version: String,
+    /// Коммит git текущего каталога, None - не git репозиторий.
+    pub git_commit: Option<String>,
+    pub config: toml::Table,
+}
+impl Manifest {
+    /// Create manifest of test, meta - strategy instance of run,
+    /// data_range - first and last bar ts.
+    ///
+    /// # ru
+    /// Создает манифест теста: meta - паспорт экземпляра стратегии
+    /// этого запуска, data_range - время первого и последнего бара
+    /// теста.
+    pub fn new(
+        test: &Test,
+        meta: &StrategyMeta,
+        data_range: Option<(i64, i64)>,
+    ) -> Self {
+        let mut config = toml::Table::new();
+        for (name, value) in [
+            ("data", toml::Value::try_from(&CFG.data)),
+            ("core", toml::Value::try_from(&CFG.core)),
+            ("tester", toml::Value::try_from(&CFG.tester)),
+        ] {
+            if let Ok(value) = value {
+                config.insert(name.to_string(), value);
+            }
+        }
+
+        Self {
+            strategy: test.strategy_name.clone(),
+            strategy_version: test.strategy_version.clone(),
+            strategy_params: meta.params.clone(),
+            strategy_params_hash: meta.params_hash.clone(),
+            iid: test.iid.to_string(),
+            begin: test.begin().to_rfc3339(),
+            end: test.end().to_rfc3339(),
+            params: test.params.to_string(),
+            params_hash: test.params_hash(),
+            deposit: test.deposit,
+            commission: test.commission.to_string(),
+            partial_fill: test.partial_fill,
+            latency: test.latency.to_string(),
+            margin: test.margin.map(|m| m.to_string()),
+            intrabar: test.intrabar.to_string(),
+            noise: test.noise.map(|n| n.to_string()),
+            sizing: test.sizing.map(|s| s.to_string()),
+            seed: test.seed,
+            warmup: test
+                .warmup
+                .iter()
+                .map(|(tf, n)| format!("{tf}={n}"))
+                .collect::<Vec<_>>()
+                .join(" "),
+
+            data_begin: data_range.map(|r| rfc3339(r.0)),
+            data_end: data_range.map(|r| rfc3339(r.1)),
+
+            created: Utc::now().to_rfc3339(),
+            avin_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: git_commit(),
+            config,
+        }
+    }
+    pub fn save(&self, path: &Path) -> Result<(), AvinError> {
+        let text = toml::to_string(self)
+            .map_err(|e| AvinError::InvalidValue(e.to_string()))?;
+
+        Cmd::write(&text, path)
+    }
+    pub fn load(path: &Path) -> Result<Self, AvinError> {
+        let text = Cmd::read(path)?;
+
+        toml::from_str(&text)
+            .map_err(|e| AvinError::InvalidValue(e.to_string()))
+    }
+}
+
+fn rfc3339(ts_nanos: i64) -> String {
+    DateTime::from_timestamp_nanos(ts_nanos).to_rfc3339()
+}
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let commit = String::from_utf8(output.stdout).ok()?;
+    Some(commit.trim().to_string())
+}
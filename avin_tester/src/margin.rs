@@ -0,0 +1,222 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{Direction, Margin, Transaction};
+
+const NANOS_IN_DAY: i64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Счет виртуального брокера при маржинальной торговле.
+#[derive(Debug)]
+pub(crate) struct MarginAccount {
+    margin: Margin,
+    cash: f64,
+    position: i64,
+    day: Option<i64>,
+    fee: f64,
+    /// Фьючерс: деньги не занимаются, позиция переоценивается от
+    /// цены последнего клиринга settle.
+    futures: bool,
+    settle: f64,
+}
+impl MarginAccount {
+    pub fn new(margin: Margin, deposit: f64, futures: bool) -> Self {
+        Self {
+            margin,
+            cash: deposit,
+            position: 0,
+            day: None,
+            fee: 0.0,
+            futures,
+            settle: 0.0,
+        }
+    }
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+    pub fn equity(&self, price: f64) -> f64 {
+        if self.futures {
+            return self.cash + self.position as f64 * (price - self.settle);
+        }
+
+        self.cash + self.position as f64 * price
+    }
+    /// Хватает ли капитала на сделку: уменьшение позиции разрешено
+    /// всегда, для новой позиции нужна начальная маржа.
+    pub fn check(
+        &self,
+        direction: &Direction,
+        quantity: i64,
+        price: f64,
+    ) -> bool {
+        let new = self.position + signed(direction, quantity);
+        if new.abs() <= self.position.abs() && new * self.position >= 0 {
+            return true;
+        }
+
+        self.margin.initial(new, price) <= self.equity(price)
+    }
+    /// Учитывает исполнение transaction ордера direction.
+    pub fn apply(
+        &mut self,
+        direction: &Direction,
+        transaction: &Transaction,
+    ) {
+        let quantity = transaction.quantity.unsigned_abs() as i64;
+        if self.futures {
+            // переоценка по цене сделки, дальше позиция от нее
+            self.settle(transaction.price);
+        } else {
+            match direction {
+                Direction::Buy => self.cash -= transaction.value().abs(),
+                Direction::Sell => self.cash += transaction.value().abs(),
+            }
+        }
+        self.position += signed(direction, quantity);
+    }
+    /// Списывает комиссию.
+    pub fn charge(&mut self, commission: f64) {
+        self.cash -= commission;
+    }
+    /// Капитал ниже минимальной маржи - позицию надо закрывать.
+    pub fn is_margin_call(&self, price: f64) -> bool {
+        self.position != 0
+            && self.equity(price) < self.margin.minimal(self.position, price)
+    }
+    /// При смене дня (позиция перенесена через ночь) начисляет плату
+    /// за заемные деньги и бумаги за прошедшие дни, по фьючерсу -
+    /// вариационную маржу.
+    pub fn accrue(&mut self, ts_nanos: i64, price: f64) {
+        let day = ts_nanos.div_euclid(NANOS_IN_DAY);
+        let Some(prev) = self.day.replace(day) else {
+            return;
+        };
+        if day <= prev {
+            self.day = Some(prev);
+            return;
+        }
+
+        if self.futures {
+            let variation = self.settle(price);
+            if variation != 0.0 {
+                log::debug!("Variation margin {variation}");
+            }
+            return;
+        }
+
+        let money = (-self.cash).max(0.0) * self.margin.rate;
+        let securities = (-self.position).max(0) as f64 * price;
+        let securities = securities * self.margin.short_rate;
+        let days = (day - prev) as f64;
+        self.fee += (money + securities) / 365.0 * days;
+    }
+    /// Забирает накопленную плату, вычитает ее из денег счета.
+    pub fn take_fee(&mut self) -> f64 {
+        let fee = std::mem::take(&mut self.fee);
+        self.cash -= fee;
+
+        fee
+    }
+
+    // private
+    /// Клиринг фьючерса: переоценка позиции по цене, возвращает
+    /// вариационную маржу.
+    fn settle(&mut self, price: f64) -> f64 {
+        let variation = self.position as f64 * (price - self.settle);
+        self.cash += variation;
+        self.settle = price;
+
+        variation
+    }
+}
+
+fn signed(direction: &Direction, quantity: i64) -> i64 {
+    match direction {
+        Direction::Buy => quantity,
+        Direction::Sell => -quantity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_and_margin_call() {
+        let margin = Margin::new(0.25, 0.5, 0.0);
+        let mut a = MarginAccount::new(margin, 1000.0, false);
+
+        // плечо 4 в лонг, 2 в шорт
+        assert!(a.check(&Direction::Buy, 400, 10.0));
+        assert!(!a.check(&Direction::Buy, 401, 10.0));
+        assert!(a.check(&Direction::Sell, 200, 10.0));
+        assert!(!a.check(&Direction::Sell, 201, 10.0));
+
+        a.apply(&Direction::Buy, &Transaction::new(400, 10.0));
+        assert_eq!(a.position(), 400);
+        assert!(a.check(&Direction::Sell, 400, 10.0));
+        assert!(!a.check(&Direction::Buy, 1, 10.0));
+
+        // по 8.5 капитал 1000 - 400 * 1.5 = 400 < 425 минимальной маржи
+        assert!(!a.is_margin_call(9.0));
+        assert!(a.is_margin_call(8.5));
+    }
+    #[test]
+    fn borrow_fee() {
+        let margin = Margin::new(0.5, 0.5, 0.365);
+        let mut a = MarginAccount::new(margin, 1000.0, false);
+        a.apply(&Direction::Buy, &Transaction::new(200, 10.0));
+
+        a.accrue(0, 10.0);
+        a.accrue(NANOS_IN_DAY / 2, 10.0);
+        assert_eq!(a.take_fee(), 0.0);
+
+        // заем 1000, два дня по 0.1%
+        a.accrue(2 * NANOS_IN_DAY, 10.0);
+        assert!((a.take_fee() - 2.0).abs() < 1e-9);
+        assert!((a.equity(10.0) - 998.0).abs() < 1e-9);
+    }
+    #[test]
+    fn short_fee() {
+        let mut margin = Margin::new(0.5, 0.5, 0.365);
+        margin.short_rate = 0.73;
+        assert_eq!(
+            margin.to_string(),
+            "long 0.5 short 0.5 rate 36.5% short rate 73%"
+        );
+        let mut a = MarginAccount::new(margin, 1000.0, false);
+        a.apply(&Direction::Sell, &Transaction::new(-100, 10.0));
+
+        // внутри дня платы нет
+        a.accrue(0, 10.0);
+        a.accrue(NANOS_IN_DAY - 1, 10.0);
+        assert_eq!(a.take_fee(), 0.0);
+
+        // ночь в шорте на 1000 по 0.2%
+        a.accrue(NANOS_IN_DAY, 10.0);
+        assert!((a.take_fee() - 2.0).abs() < 1e-9);
+    }
+    #[test]
+    fn variation_margin() {
+        let margin = Margin::new(0.1, 0.1, 0.365);
+        let mut a = MarginAccount::new(margin, 1000.0, true);
+        a.apply(&Direction::Buy, &Transaction::new(10, 100.0));
+        assert_eq!(a.equity(100.0), 1000.0);
+        assert_eq!(a.equity(105.0), 1050.0);
+
+        // клиринг: +50 вариационной маржи, платы за заем нет
+        a.accrue(0, 100.0);
+        a.accrue(NANOS_IN_DAY, 105.0);
+        assert_eq!(a.take_fee(), 0.0);
+        assert_eq!(a.equity(105.0), 1050.0);
+        assert_eq!(a.equity(95.0), 950.0);
+
+        // закрытие по 95: итог -50 от депозита
+        a.apply(&Direction::Sell, &Transaction::new(-10, 95.0));
+        assert_eq!(a.position(), 0);
+        assert_eq!(a.equity(80.0), 950.0);
+    }
+}
@@ -0,0 +1,305 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::PathBuf;
+
+use bitcode::{Decode, Encode};
+use polars::prelude::{Column, DataFrame};
+
+use avin_core::{Bar, Iid, Summary};
+use avin_strategy::Params;
+use avin_utils::{AvinError, CFG, Cmd};
+
+use crate::optimizer::{parallel, prepare, run_one};
+use crate::random::{Random, Stream, derive};
+use crate::{Metric, OptimizerRun, Test};
+
+/// Distribution of price perturbation.
+///
+/// # ru
+/// Распределение случайного сдвига цены, доля от цены.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum NoiseModel {
+    /// Равномерный сдвиг в [-amplitude, amplitude], 0.001 == 0.1%.
+    Uniform(f64),
+    /// Нормальный сдвиг со стандартным отклонением sigma.
+    Gaussian(f64),
+}
+impl std::fmt::Display for NoiseModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Uniform(a) => write!(f, "uniform {}%", a * 100.0),
+            Self::Gaussian(s) => write!(f, "gaussian {}%", s * 100.0),
+        }
+    }
+}
+
+/// Price noise of backtest.
+///
+/// # ru
+/// Шум цен теста: каждая цена 1М бара (open, high, low, close)
+/// сдвигается на случайную долю по модели, затем high и low
+/// поправляются, чтобы бар оставался баром, цены округляются до шага
+/// цены инструмента. Шум детерминирован seed шума вместе с seed теста,
+/// разные seed - разные версии истории.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct Noise {
+    pub model: NoiseModel,
+    pub seed: u64,
+}
+impl Noise {
+    pub fn new(model: NoiseModel, seed: u64) -> Self {
+        Self { model, seed }
+    }
+    /// Perturb bars prices.
+    ///
+    /// # ru
+    /// Добавляет шум к ценам баров, test_seed - seed теста.
+    pub fn apply<'a>(
+        &self,
+        iid: &Iid,
+        test_seed: u64,
+        bars: impl Iterator<Item = &'a mut Bar>,
+    ) {
+        let seed = derive(test_seed, Stream::Noise, self.seed);
+        let mut rng = Random::new(seed);
+        let step = iid.step();
+        let mut shift = |price: f64| -> f64 {
+            let e = match self.model {
+                NoiseModel::Uniform(a) => (rng.uniform() * 2.0 - 1.0) * a,
+                NoiseModel::Gaussian(s) => rng.gaussian() * s,
+            };
+            let price = price * (1.0 + e);
+            if step > 0.0 {
+                iid.round_to_step(price)
+            } else {
+                price
+            }
+        };
+
+        for bar in bars {
+            let (o, h, l, c) =
+                (shift(bar.o), shift(bar.h), shift(bar.l), shift(bar.c));
+            bar.o = o;
+            bar.c = c;
+            bar.h = h.max(o).max(c);
+            bar.l = l.min(o).min(c);
+        }
+    }
+}
+impl std::fmt::Display for Noise {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} seed {}", self.model, self.seed)
+    }
+}
+
+/// Noise-injection robustness test.
+///
+/// # ru
+/// Проверка устойчивости стратегии к шуму. Тест шаблона запускается
+/// один раз на исторических ценах (seed 0) и runs раз на зашумленных,
+/// с seed 1..=runs, параллельно. Если результат стратегии держится
+/// только на точных исторических принтах - на зашумленных данных он
+/// разваливается, это признак подгонки. Распределение итогов - см.
+/// [`Robustness::distribution`], таблица сохраняется рядом с тестами:
+/// <test_dir>/<strategy>/robustness/<ticker>/result.parquet
+pub struct Robustness {
+    model: NoiseModel,
+    runs: usize,
+    threads: usize,
+}
+impl Robustness {
+    pub fn new(model: NoiseModel, runs: usize) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        Self {
+            model,
+            runs,
+            threads,
+        }
+    }
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Run baseline and noisy tests.
+    ///
+    /// # ru
+    /// Запускает тесты, первый в результате - без шума, далее по
+    /// порядку seed.
+    pub fn run<S: Params>(&self, template: &Test) -> Vec<OptimizerRun> {
+        log::info!(
+            ":: Robustness {} runs {}, {} threads",
+            self.runs,
+            self.model,
+            self.threads
+        );
+
+        let seeds: Vec<u64> = (0..=self.runs as u64).collect();
+        parallel(&seeds, self.threads, |seed| {
+            let (strategy, mut test) = prepare::<S>(
+                template,
+                template.params.clone(),
+                template.end_ts_nanos,
+            );
+            test.noise = match seed {
+                0 => None,
+                seed => Some(Noise::new(self.model, *seed)),
+            };
+            run_one(strategy, test)
+        })
+    }
+    /// Table of runs, seed 0 - baseline.
+    ///
+    /// # ru
+    /// Таблица запусков, seed 0 - без шума.
+    pub fn table(runs: &[OptimizerRun]) -> DataFrame {
+        let seed: Vec<u64> = (0..runs.len() as u64).collect();
+        let summary = |f: fn(&Summary) -> f64| -> Vec<f64> {
+            runs.iter().map(|r| f(&r.summary)).collect()
+        };
+        let trades: Vec<u32> =
+            runs.iter().map(|r| r.summary.total_trades).collect();
+        let paths: Vec<String> =
+            runs.iter().map(|r| r.path.display().to_string()).collect();
+
+        DataFrame::new(vec![
+            Column::new("seed".into(), seed),
+            Column::new("profit".into(), summary(|s| s.profit)),
+            Column::new(
+                "percent_profitable".into(),
+                summary(|s| s.percent_profitable),
+            ),
+            Column::new("trades".into(), trades),
+            Column::new("ratio".into(), summary(|s| s.ratio)),
+            Column::new("average_trade".into(), summary(|s| s.average_trade)),
+            Column::new("path".into(), paths),
+        ])
+        .unwrap()
+    }
+    /// Distribution of metric over noisy runs.
+    ///
+    /// # ru
+    /// Распределение метрики по зашумленным запускам: значение без
+    /// шума, среднее, отклонение, минимум, 5%, медиана, 95%, максимум,
+    /// и доля запусков не хуже половины результата без шума.
+    pub fn distribution(
+        metric: Metric,
+        runs: &[OptimizerRun],
+    ) -> Vec<(&'static str, f64)> {
+        let Some((base, noisy)) = runs.split_first() else {
+            return Vec::new();
+        };
+        let base = metric.value(&base.summary);
+
+        let mut values: Vec<f64> =
+            noisy.iter().map(|r| metric.value(&r.summary)).collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        if values.is_empty() {
+            return vec![("base", base)];
+        }
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>()
+            / n)
+            .sqrt();
+        let held = values.iter().filter(|v| **v >= base / 2.0).count();
+
+        vec![
+            ("base", base),
+            ("mean", mean),
+            ("std", std),
+            ("min", values[0]),
+            ("p05", quantile(&values, 0.05)),
+            ("median", quantile(&values, 0.5)),
+            ("p95", quantile(&values, 0.95)),
+            ("max", values[values.len() - 1]),
+            ("held", held as f64 / n),
+        ]
+    }
+    /// Save table of runs.
+    ///
+    /// # ru
+    /// Сохраняет таблицу запусков, возвращает путь к файлу.
+    pub fn save(
+        template: &Test,
+        runs: &[OptimizerRun],
+    ) -> Result<PathBuf, AvinError> {
+        let mut path = PathBuf::new();
+        path.push(CFG.dir.test());
+        path.push(&template.strategy_name);
+        path.push("robustness");
+        path.push(template.iid.ticker());
+        path.push("result.parquet");
+
+        let mut df = Self::table(runs);
+        Cmd::write_pqt(&mut df, &path)?;
+
+        log::info!(":: Robustness result {}", path.display());
+        Ok(path)
+    }
+}
+
+/// Квантиль отсортированного ряда, линейная интерполяция.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = (sorted.len() - 1) as f64 * q;
+    let i = pos.floor() as usize;
+    let j = pos.ceil() as usize;
+
+    sorted[i] + (sorted[j] - sorted[i]) * (pos - i as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let iid = iid();
+        let bars = vec![Bar::new(0, 100.0, 101.0, 99.0, 100.5, 1000); 100];
+
+        let noise = Noise::new(NoiseModel::Uniform(0.002), 1);
+        let mut a = bars.clone();
+        noise.apply(&iid, 42, a.iter_mut());
+        let mut b = bars.clone();
+        noise.apply(&iid, 42, b.iter_mut());
+        assert_eq!(a, b);
+        assert_ne!(a, bars);
+
+        for bar in a.iter() {
+            assert!(bar.l <= bar.o && bar.o <= bar.h);
+            assert!(bar.l <= bar.c && bar.c <= bar.h);
+            assert!((bar.o - 100.0).abs() <= 0.2 + 1e-9);
+            assert_eq!(iid.round_to_step(bar.c), bar.c);
+        }
+
+        let mut c = bars.clone();
+        Noise::new(NoiseModel::Gaussian(0.002), 2).apply(
+            &iid,
+            42,
+            c.iter_mut(),
+        );
+        assert_ne!(a, c);
+
+        let mut d = bars.clone();
+        noise.apply(&iid, 43, d.iter_mut());
+        assert_ne!(a, d);
+    }
+    #[test]
+    fn quantiles() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(quantile(&values, 0.0), 1.0);
+        assert_eq!(quantile(&values, 0.5), 3.0);
+        assert_eq!(quantile(&values, 1.0), 5.0);
+        assert_eq!(quantile(&values, 0.25), 2.0);
+    }
+}
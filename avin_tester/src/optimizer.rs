@@ -0,0 +1,387 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use polars::prelude::{Column, DataFrame};
+
+use avin_core::Summary;
+use avin_strategy::{Param, ParamSet, Params};
+use avin_utils::{AvinError, CFG, Cmd};
+
+use crate::{Holdout, Test, Tester};
+
+/// Metric for ranking optimizer runs.
+///
+/// # ru
+/// Метрика, по которой ранжируются запуски оптимизатора, больше -
+/// лучше.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Profit,
+    Ratio,
+    AverageTrade,
+    PercentProfitable,
+}
+impl Metric {
+    pub fn value(&self, summary: &Summary) -> f64 {
+        match self {
+            Self::Profit => summary.profit,
+            Self::Ratio => summary.ratio,
+            Self::AverageTrade => summary.average_trade,
+            Self::PercentProfitable => summary.percent_profitable,
+        }
+    }
+    /// Sort runs by metric, best first.
+    ///
+    /// # ru
+    /// Сортирует запуски по метрике, лучший первый.
+    pub fn sort(&self, runs: &mut [OptimizerRun]) {
+        runs.sort_by(|a, b| {
+            let a = self.value(&a.summary);
+            let b = self.value(&b.summary);
+            b.total_cmp(&a)
+        });
+    }
+}
+impl TryFrom<&str> for Metric {
+    type Error = AvinError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "profit" => Ok(Self::Profit),
+            "ratio" => Ok(Self::Ratio),
+            "avg" | "average_trade" => Ok(Self::AverageTrade),
+            "%" | "percent_profitable" => Ok(Self::PercentProfitable),
+            _ => Err(AvinError::InvalidValue(format!("metric {value}"))),
+        }
+    }
+}
+impl std::fmt::Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Profit => write!(f, "profit"),
+            Self::Ratio => write!(f, "ratio"),
+            Self::AverageTrade => write!(f, "average_trade"),
+            Self::PercentProfitable => write!(f, "percent_profitable"),
+        }
+    }
+}
+
+/// Result of one optimizer run.
+///
+/// # ru
+/// Результат одного запуска: параметры, итоги теста и путь к
+/// сохраненному тесту со всеми трейдами.
+#[derive(Debug)]
+pub struct OptimizerRun {
+    pub params: ParamSet,
+    pub summary: Summary,
+    pub path: PathBuf,
+}
+
+/// Parameter grid search optimizer.
+///
+/// # ru
+/// Оптимизатор перебором по сетке параметров. Перебирает все сочетания
+/// значений из [`Params::params`] стратегии, запускает тесты
+/// параллельно в нескольких потоках с настройками шаблонного теста
+/// (период, комиссия, задержка...), каждый запуск сохраняется как
+/// отдельный тест, см. [`Test::dir`]. Результат - запуски,
+/// отсортированные по метрике, таблица сохраняется рядом с тестами:
+/// <test_dir>/<strategy>/optimize/<ticker>/result.parquet
+pub struct Optimizer {
+    metric: Metric,
+    threads: usize,
+}
+impl Optimizer {
+    pub fn new(metric: Metric) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        Self { metric, threads }
+    }
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// All combinations of parameter values.
+    ///
+    /// # ru
+    /// Все сочетания значений параметров (декартово произведение).
+    pub fn grid(params: &[Param]) -> Vec<ParamSet> {
+        let mut grid = vec![ParamSet::new()];
+
+        for param in params {
+            let mut next =
+                Vec::with_capacity(grid.len() * param.values.len());
+            for set in grid.iter() {
+                for value in param.values.iter() {
+                    let mut set = set.clone();
+                    set.set(param.name, *value);
+                    next.push(set);
+                }
+            }
+            grid = next;
+        }
+
+        grid
+    }
+    /// Run tests for all combinations of strategy parameters.
+    ///
+    /// # ru
+    /// Запускает тесты по всей сетке параметров стратегии.
+    pub fn run<S: Params>(
+        &self,
+        template: &Test,
+    ) -> Result<Vec<OptimizerRun>, AvinError> {
+        let grid = Self::grid(&S::params());
+
+        self.run_grid::<S>(template, grid)
+    }
+    /// Run tests for given parameter sets.
+    ///
+    /// # ru
+    /// Запускает тесты для заданных наборов параметров, возвращает
+    /// запуски отсортированные по метрике, лучший первый. Период
+    /// шаблона не должен захватывать отложенный период инструмента,
+    /// см. [`crate::Holdout`].
+    pub fn run_grid<S: Params>(
+        &self,
+        template: &Test,
+        grid: Vec<ParamSet>,
+    ) -> Result<Vec<OptimizerRun>, AvinError> {
+        Holdout::check(template)?;
+
+        log::info!(
+            ":: Optimizer {} runs, {} threads",
+            grid.len(),
+            self.threads
+        );
+
+        let mut runs = run_parallel::<S>(
+            template,
+            &grid,
+            template.end_ts_nanos,
+            self.threads,
+        );
+        self.metric.sort(&mut runs);
+
+        Ok(runs)
+    }
+    /// Ranked table of runs.
+    ///
+    /// # ru
+    /// Таблица запусков: параметры и итоги тестов, в порядке runs.
+    pub fn table(&self, runs: &[OptimizerRun]) -> DataFrame {
+        let mut columns = Vec::new();
+
+        let rank: Vec<u32> = (1..=runs.len() as u32).collect();
+        columns.push(Column::new("rank".into(), rank));
+
+        if let Some(first) = runs.first() {
+            for (name, _) in first.params.values() {
+                let values: Vec<Option<f64>> =
+                    runs.iter().map(|r| r.params.get(name)).collect();
+                columns.push(Column::new(name.into(), values));
+            }
+        }
+
+        let metric: Vec<f64> =
+            runs.iter().map(|r| self.metric.value(&r.summary)).collect();
+        columns.push(Column::new(self.metric.to_string().into(), metric));
+
+        let summary = |f: fn(&Summary) -> f64| -> Vec<f64> {
+            runs.iter().map(|r| f(&r.summary)).collect()
+        };
+        columns.push(Column::new("profit".into(), summary(|s| s.profit)));
+        columns.push(Column::new(
+            "percent_profitable".into(),
+            summary(|s| s.percent_profitable),
+        ));
+        let trades: Vec<u32> =
+            runs.iter().map(|r| r.summary.total_trades).collect();
+        columns.push(Column::new("trades".into(), trades));
+        columns.push(Column::new("ratio".into(), summary(|s| s.ratio)));
+        columns.push(Column::new(
+            "average_trade".into(),
+            summary(|s| s.average_trade),
+        ));
+        columns.push(Column::new(
+            "commission".into(),
+            summary(|s| s.commission),
+        ));
+        let paths: Vec<String> =
+            runs.iter().map(|r| r.path.display().to_string()).collect();
+        columns.push(Column::new("path".into(), paths));
+
+        DataFrame::new(columns).unwrap()
+    }
+    /// Save ranked table of runs.
+    ///
+    /// # ru
+    /// Сохраняет таблицу запусков рядом с тестами оптимизатора,
+    /// возвращает путь к файлу.
+    pub fn save(
+        &self,
+        template: &Test,
+        runs: &[OptimizerRun],
+    ) -> Result<PathBuf, AvinError> {
+        let mut path = PathBuf::new();
+        path.push(CFG.dir.test());
+        path.push(&template.strategy_name);
+        path.push("optimize");
+        path.push(template.iid.ticker());
+        path.push("result.parquet");
+
+        let mut df = self.table(runs);
+        Cmd::write_pqt(&mut df, &path)?;
+
+        log::info!(":: Optimizer result {}", path.display());
+        Ok(path)
+    }
+}
+
+/// Запускает тесты для наборов параметров в threads потоках, тесты
+/// идут с начала периода шаблона до end_ts_nanos. Порядок результатов
+/// как в grid.
+pub(crate) fn run_parallel<S: Params>(
+    template: &Test,
+    grid: &[ParamSet],
+    end_ts_nanos: i64,
+    threads: usize,
+) -> Vec<OptimizerRun> {
+    parallel(grid, threads, |params| {
+        let (strategy, test) =
+            prepare::<S>(template, params.clone(), end_ts_nanos);
+        run_one(strategy, test)
+    })
+}
+/// Выполняет f для всех items в threads потоках, порядок результатов
+/// как в items.
+pub(crate) fn parallel<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    let threads = threads.min(items.len());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+
+                    let result = f(item);
+                    results.lock().unwrap().push((i, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+/// Создает стратегию с параметрами и тест с настройками шаблона.
+pub(crate) fn prepare<S: Params>(
+    template: &Test,
+    params: ParamSet,
+    end_ts_nanos: i64,
+) -> (S, Test) {
+    let strategy = S::with_params(&params);
+
+    let mut test = Test::new(&strategy, &template.iid);
+    test.copy_settings(template);
+    test.end_ts_nanos = end_ts_nanos;
+    test.params = params;
+
+    (strategy, test)
+}
+pub(crate) fn run_one<S: Params>(
+    strategy: S,
+    mut test: Test,
+) -> OptimizerRun {
+    // у каждого потока свой рантайм, тестер внутри не параллелится
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(Tester::new().run(strategy, &mut test));
+
+    OptimizerRun {
+        summary: Summary::new(&test.trade_list),
+        path: test.path(),
+        params: test.params,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use avin_core::Asset;
+    use avin_strategy::PinBarLong;
+
+    #[test]
+    fn grid() {
+        let params = vec![
+            Param::list("a", &[1.0, 2.0]),
+            Param::list("b", &[10.0, 20.0, 30.0]),
+        ];
+        let grid = Optimizer::grid(&params);
+
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid[0].to_string(), "a=1_b=10");
+        assert_eq!(grid[5].to_string(), "a=2_b=30");
+    }
+    #[test]
+    fn metric() {
+        assert_eq!(Metric::try_from("Profit").unwrap(), Metric::Profit);
+        assert_eq!(Metric::try_from("avg").unwrap(), Metric::AverageTrade);
+        assert!(Metric::try_from("sharpe").is_err());
+    }
+    #[test]
+    fn run_grid() {
+        let strategy = PinBarLong::default();
+        let asset = Asset::new("moex_share_sber").unwrap();
+
+        let mut template = Test::new(&strategy, asset.iid());
+        template
+            .set_begin(&Utc.with_ymd_and_hms(2023, 8, 1, 7, 0, 0).unwrap());
+        template.set_end(&Utc.with_ymd_and_hms(2023, 8, 1, 7, 9, 0).unwrap());
+
+        let grid = Optimizer::grid(&[
+            Param::list("stop", &[0.98, 0.99]),
+            Param::list("take", &[1.02]),
+        ]);
+        let mut optimizer = Optimizer::new(Metric::Profit);
+        optimizer.set_threads(2);
+        let runs = optimizer.run_grid::<PinBarLong>(&template, grid).unwrap();
+        assert_eq!(runs.len(), 2);
+
+        let table = optimizer.table(&runs);
+        assert_eq!(table.height(), 2);
+        assert!(table.column("stop").is_ok());
+
+        for run in runs {
+            assert!(Cmd::is_exist(&run.path));
+            Cmd::delete_dir(run.path.parent().unwrap()).unwrap();
+        }
+    }
+}
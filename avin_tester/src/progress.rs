@@ -0,0 +1,105 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Utc};
+
+/// Progress of running backtest.
+///
+/// # ru
+/// Прогресс выполняемого теста, тестер отправляет его в канал
+/// [`crate::Tester::progress`] при каждом новом проценте периода.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Пройденная доля периода теста, 0..=100.
+    pub percent: f64,
+    /// Время текущего 1М бара.
+    pub dt: DateTime<Utc>,
+    /// Количество закрытых трейдов на текущий момент.
+    pub trades: usize,
+}
+impl Progress {
+    /// Progress of bar ts in test period [begin, end).
+    ///
+    /// # ru
+    /// Прогресс на баре ts в периоде теста [begin, end).
+    pub fn new(begin: i64, end: i64, ts: i64, trades: usize) -> Self {
+        let percent = if end > begin {
+            ((ts - begin) as f64 / (end - begin) as f64 * 100.0)
+                .clamp(0.0, 100.0)
+        } else {
+            100.0
+        };
+
+        Self {
+            percent,
+            dt: DateTime::from_timestamp_nanos(ts),
+            trades,
+        }
+    }
+}
+impl std::fmt::Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:5.1}% {} trades {}",
+            self.percent,
+            self.dt.format("%Y-%m-%d"),
+            self.trades
+        )
+    }
+}
+
+/// Token to abort running backtest.
+///
+/// # ru
+/// Токен отмены теста. Клоны токена общие: отмена из любого потока
+/// (GUI, обработчик Ctrl-C) останавливает тест на следующем событии,
+/// тестер ничего не сохраняет и ставит статус
+/// [`crate::TestStatus::Canceled`].
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    canceled: Arc<AtomicBool>,
+}
+impl CancelToken {
+    pub fn new() -> Self {
+        Self {
+            canceled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    pub fn cancel(&self) {
+        self.canceled.store(true, Ordering::Relaxed);
+    }
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress() {
+        let p = Progress::new(1000, 2000, 1250, 3);
+        assert_eq!(p.percent, 25.0);
+        assert_eq!(p.trades, 3);
+        assert_eq!(Progress::new(1000, 2000, 3000, 0).percent, 100.0);
+        assert_eq!(Progress::new(1000, 1000, 1000, 0).percent, 100.0);
+    }
+    #[test]
+    fn cancel() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(!token.is_canceled());
+
+        clone.cancel();
+        assert!(token.is_canceled());
+    }
+}
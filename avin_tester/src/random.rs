@@ -0,0 +1,46 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+pub(crate) use avin_utils::{Random, xorshift};
+
+/// Независимые потоки случайных чисел одного теста: каждый компонент
+/// получает свой seed из общего seed теста, поэтому добавление
+/// случайности в один компонент не меняет последовательности других.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Stream {
+    Latency = 1,
+    BrokerId = 2,
+    Noise = 3,
+    Genetic = 4,
+}
+
+/// Seed компонента stream из общего seed теста, index - номер
+/// экземпляра компонента (например номер зашумленного прогона).
+/// Перемешивание splitmix64, результат не бывает нулем.
+pub(crate) fn derive(seed: u64, stream: Stream, index: u64) -> u64 {
+    let mut x = seed
+        ^ (stream as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ index.wrapping_mul(0xD1B5_4A32_D192_ED03);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+
+    x.max(1)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_streams() {
+        let a = derive(42, Stream::Latency, 0);
+        assert_eq!(a, derive(42, Stream::Latency, 0));
+        assert_ne!(a, derive(43, Stream::Latency, 0));
+        assert_ne!(a, derive(42, Stream::BrokerId, 0));
+        assert_ne!(a, derive(42, Stream::Latency, 1));
+    }
+}
@@ -0,0 +1,398 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Datelike};
+use polars::prelude::DataFrame;
+
+use avin_core::{Summary, Trade};
+use avin_utils::{AvinError, Cmd};
+
+use crate::Test;
+
+/// Максимум точек на графике, длинные ряды прореживаются.
+const MAX_POINTS: usize = 2000;
+const CHART_WIDTH: f64 = 1000.0;
+const CHART_HEIGHT: f64 = 250.0;
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+    "Nov", "Dec",
+];
+
+/// HTML backtest report.
+///
+/// # ru
+/// Отчет по тесту одним HTML файлом без внешних зависимостей: блок
+/// параметров теста, итоги, кривая капитала и просадки (inline SVG),
+/// таблица доходности по месяцам и список трейдов. Файл можно
+/// открыть в браузере и переслать, GUI не нужен.
+pub struct Report;
+impl Report {
+    /// Build report html.
+    ///
+    /// # ru
+    /// Создает html отчета, equity - кривая капитала теста, см.
+    /// [`crate::Equity`].
+    pub fn html(test: &Test, equity: &DataFrame) -> String {
+        let ts = column_i64(equity, "ts_nanos");
+        let values = column_f64(equity, "equity");
+        let drawdown = column_f64(equity, "drawdown_pct");
+
+        let mut html = String::new();
+        let title = escape(&test.name());
+        write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n\
+            <body>\n<h1>{title}</h1>\n"
+        )
+        .unwrap();
+
+        html += &params_block(test);
+        html += &summary_block(&Summary::new(&test.trade_list));
+
+        html += "<h2>Equity</h2>\n";
+        html += &svg_chart(&values, "#2a7ab9");
+        html += "<h2>Drawdown, %</h2>\n";
+        html += &svg_chart(&drawdown, "#c0392b");
+
+        html += &monthly_block(&monthly_returns(&ts, &values));
+        html += &trades_block(test);
+
+        html += "</body>\n</html>\n";
+
+        html
+    }
+    /// Build and save report next to test.
+    ///
+    /// # ru
+    /// Создает отчет и сохраняет рядом с тестом, возвращает путь.
+    pub fn save(test: &Test) -> Result<PathBuf, AvinError> {
+        let equity = test.load_equity()?;
+        let path = test.report_path();
+        Cmd::write(&Self::html(test, &equity), &path)?;
+
+        log::info!(":: Report save {}", path.display());
+        Ok(path)
+    }
+}
+
+pub(crate) const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+    table{border-collapse:collapse;margin-bottom:1em}\
+    td,th{border:1px solid #ccc;padding:2px 8px;text-align:right}\
+    th{background:#eee}.pos{color:#1e8449}.neg{color:#c0392b}\
+    svg{border:1px solid #ccc;background:#fafafa}";
+
+fn params_block(test: &Test) -> String {
+    let partial_fill = match test.partial_fill {
+        Some(ratio) => format!("{}% of bar volume", ratio * 100.0),
+        None => "off".to_string(),
+    };
+    let margin = match test.margin {
+        Some(margin) => margin.to_string(),
+        None => "off".to_string(),
+    };
+    let noise = match test.noise {
+        Some(noise) => noise.to_string(),
+        None => "off".to_string(),
+    };
+    let sizing = match test.sizing {
+        Some(sizing) => sizing.to_string(),
+        None => "strategy lots".to_string(),
+    };
+    let params = if test.params.is_empty() {
+        "default".to_string()
+    } else {
+        test.params.to_string()
+    };
+
+    let rows = [
+        ("Strategy", test.strategy_name.clone()),
+        ("Instrument", test.iid.to_string()),
+        ("Begin", test.begin().to_string()),
+        ("End", test.end().to_string()),
+        ("Deposit", test.deposit.to_string()),
+        ("Commission", test.commission.to_string()),
+        ("Partial fill", partial_fill),
+        ("Latency", test.latency.to_string()),
+        ("Margin", margin),
+        ("Intrabar", test.intrabar.to_string()),
+        ("Noise", noise),
+        ("Sizing", sizing),
+        ("Seed", test.seed.to_string()),
+        ("Params", params),
+    ];
+
+    let mut html = String::from("<h2>Parameters</h2>\n<table>\n");
+    for (name, value) in rows {
+        writeln!(html, "<tr><th>{name}</th><td>{}</td></tr>", escape(&value))
+            .unwrap();
+    }
+    html += "</table>\n";
+
+    html
+}
+fn summary_block(s: &Summary) -> String {
+    let rows = [
+        ("Profit", format!("{:.2}", s.profit)),
+        ("Profitable, %", format!("{:.2}", s.percent_profitable)),
+        (
+            "Trades",
+            format!("{}/{}/{}", s.total_trades, s.win_trades, s.loss_trades),
+        ),
+        ("Ratio", format!("{:.2}", s.ratio)),
+        ("Average trade", format!("{:.2}", s.average_trade)),
+        (
+            "Max win / loss",
+            format!("{:.2} / {:.2}", s.max_win, s.max_loss),
+        ),
+        (
+            "Win / loss series",
+            format!("{} / {}", s.win_seq, s.loss_seq),
+        ),
+        ("Commission", format!("{:.2}", s.commission)),
+    ];
+
+    let mut html = String::from("<h2>Summary</h2>\n<table>\n");
+    for (name, value) in rows {
+        writeln!(html, "<tr><th>{name}</th><td>{value}</td></tr>").unwrap();
+    }
+    html += "</table>\n";
+
+    html
+}
+fn svg_chart(values: &[f64], color: &str) -> String {
+    svg_lines(&[(values, color)])
+}
+/// Несколько рядов на одном SVG графике в общем масштабе.
+pub(crate) fn svg_lines(series: &[(&[f64], &str)]) -> String {
+    let series: Vec<_> = series.iter().filter(|s| !s.0.is_empty()).collect();
+    if series.is_empty() {
+        return "<p>No data</p>\n".to_string();
+    }
+
+    let len = series.iter().map(|s| s.0.len()).max().unwrap();
+    let step = len.div_ceil(MAX_POINTS);
+    let points: Vec<(Vec<f64>, &str)> = series
+        .iter()
+        .map(|(values, color)| {
+            (values.iter().step_by(step).copied().collect(), *color)
+        })
+        .collect();
+
+    let all = points.iter().flat_map(|(p, _)| p.iter().copied());
+    let min = all.clone().fold(f64::INFINITY, f64::min);
+    let max = all.fold(f64::NEG_INFINITY, f64::max);
+    let span = if max > min { max - min } else { 1.0 };
+    let count = len.div_ceil(step);
+    let dx = CHART_WIDTH / (count.max(2) - 1) as f64;
+
+    let mut lines = String::new();
+    for (values, color) in points.iter() {
+        let mut path = String::new();
+        for (i, v) in values.iter().enumerate() {
+            let x = i as f64 * dx;
+            let y = CHART_HEIGHT - (v - min) / span * CHART_HEIGHT;
+            write!(path, "{x:.1},{y:.1} ").unwrap();
+        }
+        writeln!(
+            lines,
+            "<polyline fill=\"none\" stroke=\"{color}\" \
+            stroke-width=\"1.5\" points=\"{}\"/>",
+            path.trim_end()
+        )
+        .unwrap();
+    }
+
+    format!(
+        "<svg width=\"{CHART_WIDTH}\" height=\"{}\" \
+        viewBox=\"0 -10 {CHART_WIDTH} {}\">\n\
+        {lines}\
+        <text x=\"4\" y=\"4\" font-size=\"12\">{max:.2}</text>\n\
+        <text x=\"4\" y=\"{CHART_HEIGHT}\" font-size=\"12\">{min:.2}</text>\n\
+        </svg>\n",
+        CHART_HEIGHT + 20.0,
+        CHART_HEIGHT + 20.0,
+    )
+}
+
+/// Доходность по месяцам в процентах: капитал на конец месяца к
+/// капиталу на конец предыдущего (для первого - к начальному).
+fn monthly_returns(
+    ts: &[i64],
+    equity: &[f64],
+) -> Vec<(i32, [Option<f64>; 12])> {
+    let mut years: Vec<(i32, [Option<f64>; 12])> = Vec::new();
+    let Some(mut prev_end) = equity.first().copied() else {
+        return years;
+    };
+
+    let mut i = 0;
+    while i < ts.len() {
+        let dt = DateTime::from_timestamp_nanos(ts[i]);
+        let (year, month) = (dt.year(), dt.month0() as usize);
+
+        // последняя точка этого месяца
+        let mut j = i;
+        while j + 1 < ts.len() {
+            let next = DateTime::from_timestamp_nanos(ts[j + 1]);
+            if next.year() != year || next.month0() as usize != month {
+                break;
+            }
+            j += 1;
+        }
+
+        let end = equity[j];
+        let r = (end / prev_end - 1.0) * 100.0;
+        prev_end = end;
+
+        if years.last().map(|(y, _)| *y) != Some(year) {
+            years.push((year, [None; 12]));
+        }
+        years.last_mut().unwrap().1[month] = Some(r);
+
+        i = j + 1;
+    }
+
+    years
+}
+fn monthly_block(years: &[(i32, [Option<f64>; 12])]) -> String {
+    let mut html = String::from(
+        "<h2>Monthly returns, %</h2>\n<table>\n<tr><th>Year</th>",
+    );
+    for m in MONTHS {
+        write!(html, "<th>{m}</th>").unwrap();
+    }
+    html += "<th>Year</th></tr>\n";
+
+    for (year, months) in years {
+        write!(html, "<tr><th>{year}</th>").unwrap();
+        let mut total = 1.0;
+        for r in months {
+            match r {
+                Some(r) => {
+                    total *= 1.0 + r / 100.0;
+                    html += &cell(*r);
+                }
+                None => html += "<td></td>",
+            }
+        }
+        html += &cell((total - 1.0) * 100.0);
+        html += "</tr>\n";
+    }
+    html += "</table>\n";
+
+    html
+}
+fn trades_block(test: &Test) -> String {
+    let mut html = String::from(
+        "<h2>Trades</h2>\n<table>\n<tr><th>#</th><th>Kind</th>\
+        <th>Open</th><th>Close</th><th>Quantity</th><th>Buy avg</th>\
+        <th>Sell avg</th><th>Commission</th><th>Result</th>\
+        <th>Result, %</th></tr>\n",
+    );
+
+    let closed = test.trade_list.trades().iter().filter_map(|t| match t {
+        Trade::Closed(t) => Some(t),
+        _ => None,
+    });
+    for (n, t) in closed.enumerate() {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+            <td>{:.2}</td><td>{:.2}</td><td>{:.2}</td>{}{}</tr>",
+            n + 1,
+            t.kind,
+            t.open_dt().format("%Y-%m-%d %H:%M"),
+            t.close_dt().format("%Y-%m-%d %H:%M"),
+            t.buy_quantity(),
+            t.buy_avg(),
+            t.sell_avg(),
+            t.commission(),
+            cell(t.result()),
+            cell(t.result_p()),
+        )
+        .unwrap();
+    }
+    html += "</table>\n";
+
+    html
+}
+
+pub(crate) fn cell(value: f64) -> String {
+    let class = if value > 0.0 {
+        "pos"
+    } else if value < 0.0 {
+        "neg"
+    } else {
+        ""
+    };
+
+    format!("<td class=\"{class}\">{value:.2}</td>")
+}
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+fn column_i64(df: &DataFrame, name: &str) -> Vec<i64> {
+    match df.column(name) {
+        Ok(c) => c.i64().unwrap().into_no_null_iter().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+pub(crate) fn column_f64(df: &DataFrame, name: &str) -> Vec<f64> {
+    match df.column(name) {
+        Ok(c) => c.f64().unwrap().into_no_null_iter().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn ts(y: i32, m: u32, d: u32) -> i64 {
+        Utc.with_ymd_and_hms(y, m, d, 10, 0, 0)
+            .unwrap()
+            .timestamp_nanos_opt()
+            .unwrap()
+    }
+
+    #[test]
+    fn monthly() {
+        let ts = [
+            ts(2024, 11, 1),
+            ts(2024, 11, 30),
+            ts(2024, 12, 15),
+            ts(2025, 1, 10),
+        ];
+        let equity = [100.0, 110.0, 99.0, 99.0];
+
+        let years = monthly_returns(&ts, &equity);
+        assert_eq!(years.len(), 2);
+        assert_eq!(years[0].0, 2024);
+        assert!((years[0].1[10].unwrap() - 10.0).abs() < 1e-9);
+        assert!((years[0].1[11].unwrap() + 10.0).abs() < 1e-9);
+        assert_eq!(years[0].1[0], None);
+        assert_eq!(years[1].1[0], Some(0.0));
+    }
+    #[test]
+    fn chart_and_escape() {
+        let svg = svg_chart(&[1.0, 2.0, 3.0], "red");
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("polyline"));
+        assert_eq!(svg_chart(&[], "red"), "<p>No data</p>\n");
+
+        assert_eq!(escape("<a & b>"), "&lt;a &amp; b&gt;");
+    }
+}
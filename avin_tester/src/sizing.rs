@@ -0,0 +1,142 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use bitcode::{Decode, Encode};
+
+use avin_core::{Asset, Iid, TimeFrame};
+use avin_utils::true_range;
+
+/// Position sizing model of backtest account.
+///
+/// # ru
+/// Модель размера позиции. Если задана в тесте, стратегии достаточно
+/// решить направление и стоп: тестер пересчитывает количество лотов
+/// ордера входа (рыночного или лимитного ордера, выставленного без
+/// позиции) от текущего капитала счета. Стоп стратегия сообщает через
+/// [`avin_strategy::Strategy::stop`], волатильность - средний истинный
+/// диапазон дневных баров. Расчет [`Sizing::lots`] не зависит от
+/// тестера, тот же расчет можно использовать в боевом режиме.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum Sizing {
+    /// Стоимость позиции - доля капитала, 0.5 == 50%.
+    PercentOfEquity(f64),
+    /// Риск на сделку - доля капитала, которая теряется при сработке
+    /// стопа, 0.01 == 1%. Без стопа размер не пересчитывается.
+    FixedRisk(f64),
+    /// Дневная волатильность позиции (ATR * количество бумаг) - доля
+    /// капитала target, ATR за period дневных баров.
+    Volatility { target: f64, period: usize },
+}
+impl Sizing {
+    /// Lots of entry order, None - not enough data.
+    ///
+    /// # ru
+    /// Количество лотов ордера входа по цене price при капитале equity.
+    /// stop - цена стопа, atr - средний истинный диапазон дня. None -
+    /// для модели не хватает данных (нет стопа, стоп на цене входа,
+    /// нет баров для ATR), тогда остается размер ордера стратегии.
+    pub fn lots(
+        &self,
+        iid: &Iid,
+        equity: f64,
+        price: f64,
+        stop: Option<f64>,
+        atr: Option<f64>,
+    ) -> Option<u32> {
+        let lot = iid.lot() as f64;
+        let quantity = match self {
+            Self::PercentOfEquity(share) => equity * share / price,
+            Self::FixedRisk(risk) => {
+                let distance = (price - stop?).abs();
+                if distance == 0.0 {
+                    return None;
+                }
+                equity * risk / distance
+            }
+            Self::Volatility { target, .. } => {
+                let atr = atr.filter(|i| *i > 0.0)?;
+                equity * target / atr
+            }
+        };
+
+        Some((quantity / lot).floor().max(0.0) as u32)
+    }
+    /// Average true range of asset daily bars for volatility model.
+    ///
+    /// # ru
+    /// Средний истинный диапазон последних period дневных баров актива
+    /// для модели Volatility, для остальных моделей None.
+    pub fn atr(&self, asset: &Asset) -> Option<f64> {
+        let Self::Volatility { period, .. } = self else {
+            return None;
+        };
+        let bars = asset.chart(TimeFrame::Day)?.bars();
+        if bars.len() < 2 {
+            return None;
+        }
+
+        let h: Vec<f64> = bars.iter().map(|b| b.h).collect();
+        let l: Vec<f64> = bars.iter().map(|b| b.l).collect();
+        let c: Vec<f64> = bars.iter().map(|b| b.c).collect();
+        let tr = true_range(&h, &l, &c);
+        let tail = &tr[tr.len().saturating_sub((*period).max(1))..];
+
+        Some(tail.iter().sum::<f64>() / tail.len() as f64)
+    }
+}
+impl std::fmt::Display for Sizing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::PercentOfEquity(share) => {
+                write!(f, "percent of equity {}%", share * 100.0)
+            }
+            Self::FixedRisk(risk) => {
+                write!(f, "fixed risk {}%", risk * 100.0)
+            }
+            Self::Volatility { target, period } => {
+                write!(f, "volatility {}% atr {period}", target * 100.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+
+    use super::*;
+
+    #[test]
+    fn lots() {
+        let iid = iid();
+
+        // 50% от 100_000 по 100 = 500 бумаг = 50 лотов
+        let s = Sizing::PercentOfEquity(0.5);
+        assert_eq!(s.lots(&iid, 100_000.0, 100.0, None, None), Some(50));
+
+        // риск 1% = 1000, стоп 2 рубля = 500 бумаг
+        let s = Sizing::FixedRisk(0.01);
+        assert_eq!(
+            s.lots(&iid, 100_000.0, 100.0, Some(98.0), None),
+            Some(50)
+        );
+        assert_eq!(
+            s.lots(&iid, 100_000.0, 100.0, Some(102.0), None),
+            Some(50)
+        );
+        assert_eq!(s.lots(&iid, 100_000.0, 100.0, None, None), None);
+        assert_eq!(s.lots(&iid, 100_000.0, 100.0, Some(100.0), None), None);
+
+        // 1% = 1000 в день при ATR 3 = 333 бумаги = 33 лота
+        let s = Sizing::Volatility {
+            target: 0.01,
+            period: 14,
+        };
+        assert_eq!(s.lots(&iid, 100_000.0, 100.0, None, Some(3.0)), Some(33));
+        assert_eq!(s.lots(&iid, 100_000.0, 100.0, None, None), None);
+    }
+}
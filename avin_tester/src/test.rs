@@ -7,12 +7,32 @@
 
 use std::path::{Path, PathBuf};
 
-use avin_strategy::Strategy;
+use avin_strategy::{ParamSet, Strategy};
 use bitcode::{Decode, Encode};
 use chrono::{DateTime, TimeZone, Utc};
+use polars::prelude::DataFrame;
 
-use avin_core::{Iid, TradeList};
-use avin_utils::{CFG, Cmd};
+use avin_core::{Iid, Margin, TimeFrame, TradeList};
+use avin_utils::{AvinError, CFG, Cmd};
+
+use crate::{Commission, Equity, Intrabar, Latency, Noise, Sizing};
+
+/// Seed of test by default.
+///
+/// # ru
+/// Seed теста по умолчанию.
+pub const DEFAULT_SEED: u64 = 42;
+
+// Версия формата test.bin: файл начинается с метки FORMAT_MAGIC и
+// версии, дальше тест в bitcode. bitcode не хранит имена полей, поэтому
+// новое поле в Test меняет формат: старая структура сохраняется как
+// TestV<N>, версия увеличивается, а в Test::migrate добавляется ветка,
+// которая читает старую структуру и заполняет новые поля значениями
+// по умолчанию.
+const FORMAT_VERSION: u16 = 1;
+// Метка в начале test.bin, по ней отличаются файлы с версией от
+// сохраненных до появления версии формата.
+const FORMAT_MAGIC: &[u8; 4] = b"AVTS";
 
 #[derive(Debug, PartialEq, Encode, Decode)]
 pub enum TestStatus {
@@ -20,18 +40,87 @@ pub enum TestStatus {
     Edit,
     Process,
     Complete,
+    Canceled,
 }
 
 #[derive(Debug, PartialEq, Encode, Decode)]
 pub struct Test {
     pub strategy_name: String,
+    pub strategy_version: String,
     pub iid: Iid,
     pub deposit: f64,
-    pub commission: f64,
+    pub commission: Commission,
+    /// Share of bar volume available to limit order at its price,
+    /// None - limit order is filled entirely.
+    ///
+    /// # ru
+    /// Доля объема бара, доступная лимитному ордеру по его цене.
+    /// Если задана, лимитка исполняется частями по нескольким барам,
+    /// как у живого брокера. None - исполнение целиком в одном баре.
+    pub partial_fill: Option<f64>,
+    /// Delay between strategy decision and order arrival to broker.
+    ///
+    /// # ru
+    /// Задержка между решением стратегии и приходом ордера к брокеру,
+    /// см. [`Latency`].
+    pub latency: Latency,
+    /// Margin trading model, None - unlimited account.
+    ///
+    /// # ru
+    /// Модель маржинальной торговли: шорт, плечо, маржин колл, см.
+    /// [`Margin`]. None - счет не ограничен, как раньше.
+    pub margin: Option<Margin>,
+    /// Assumption how stop and limit levels are reached inside bar.
+    ///
+    /// # ru
+    /// Допущение о движении цены внутри бара, см. [`Intrabar`].
+    pub intrabar: Intrabar,
+    /// Price noise, None - historical prices.
+    ///
+    /// # ru
+    /// Шум цен для проверки устойчивости, см. [`Noise`] и
+    /// [`crate::Robustness`]. None - исторические цены.
+    pub noise: Option<Noise>,
+    /// Position sizing model, None - lots of strategy orders.
+    ///
+    /// # ru
+    /// Модель размера позиции, см. [`Sizing`]. None - ордера
+    /// исполняются с количеством лотов, которое задала стратегия.
+    pub sizing: Option<Sizing>,
+    /// Seed of all random components of test.
+    ///
+    /// # ru
+    /// Общий seed всех случайных компонентов теста: задержки ордеров,
+    /// идентификаторов ордеров брокера, шума цен, генетического поиска.
+    /// Каждый компонент получает из него свой независимый поток, тест
+    /// с одним seed повторяется побитово. Исполнение частями от seed
+    /// не зависит, оно детерминировано объемом бара.
+    pub seed: u64,
+    /// Strategy parameters of run, empty - strategy defaults.
+    ///
+    /// # ru
+    /// Параметры стратегии в этом запуске, пустой набор - параметры
+    /// по умолчанию. Заполняется оптимизатором.
+    pub params: ParamSet,
+    /// Bars of timeframes preloaded before test begin.
+    ///
+    /// # ru
+    /// Количество баров каждого таймфрейма, которые загружаются в
+    /// графики до начала теста, чтобы индикаторы (экстремумы, длинные
+    /// средние) были сформированы уже на первом баре теста. Бары
+    /// прогрева не тестируются, ордера по ним не выставляются.
+    pub warmup: Vec<(TimeFrame, u32)>,
     pub begin_ts_nanos: i64,
     pub end_ts_nanos: i64,
     pub status: TestStatus,
     pub trade_list: TradeList,
+    /// Time of margin call, None - no margin call.
+    ///
+    /// # ru
+    /// Время margin call: брокер закрыл позицию по рынку, тест на этом
+    /// остановлен. Трейд с этим временем закрытия в trade_list закрыт
+    /// брокером, а не стратегией. None - margin call не было.
+    pub margin_call: Option<i64>,
 }
 impl Test {
     pub fn new(strategy: &impl Strategy, iid: &Iid) -> Self {
@@ -39,9 +128,19 @@ impl Test {
 
         Self {
             strategy_name: strategy.name().to_string(),
+            strategy_version: strategy.version().to_string(),
             iid: iid.clone(),
             deposit: 100_000.0,
-            commission: 0.0005,
+            commission: Commission::default(),
+            partial_fill: None,
+            latency: Latency::default(),
+            margin: None,
+            intrabar: Intrabar::default(),
+            noise: None,
+            sizing: None,
+            seed: DEFAULT_SEED,
+            params: ParamSet::new(),
+            warmup: Vec::new(),
             begin_ts_nanos: Utc
                 .with_ymd_and_hms(2020, 1, 1, 0, 0, 0)
                 .unwrap()
@@ -54,13 +153,34 @@ impl Test {
                 .unwrap(),
             status: TestStatus::New,
             trade_list: TradeList::new(&trade_list_name),
+            margin_call: None,
         }
     }
-    pub fn from_bin(bytes: &[u8]) -> Self {
-        bitcode::decode(bytes).unwrap()
+    /// Decode test of any known format version.
+    ///
+    /// # ru
+    /// Читает тест любой известной версии формата, старые версии
+    /// переводятся в текущую. Файл без метки сохранен до появления
+    /// версии формата и читается как версия 1. Поврежденный файл или
+    /// файл более новой версии - ошибка.
+    pub fn from_bin(bytes: &[u8]) -> Result<Test, AvinError> {
+        let Some(rest) = bytes.strip_prefix(FORMAT_MAGIC) else {
+            return Self::migrate(1, bytes);
+        };
+        let Some((version, payload)) = rest.split_first_chunk::<2>() else {
+            return Err(AvinError::InvalidValue(
+                "test.bin: no format version".to_string(),
+            ));
+        };
+
+        Self::migrate(u16::from_le_bytes(*version), payload)
     }
     pub fn to_bin(&self) -> Vec<u8> {
-        bitcode::encode(self)
+        let mut bytes = FORMAT_MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&bitcode::encode(self));
+
+        bytes
     }
     pub fn save(test: &Test) -> Result<(), String> {
         let bytes = test.to_bin();
@@ -71,25 +191,27 @@ impl Test {
         Ok(())
     }
     pub fn load(path: &Path) -> Result<Test, String> {
-        let bytes = Cmd::read_bin(path).unwrap();
-        let test = Test::from_bin(&bytes);
+        let bytes = Cmd::read_bin(path).map_err(|e| e.to_string())?;
+        let test = Test::from_bin(&bytes)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
 
         log::info!(":: Test load {}", path.display());
         Ok(test)
     }
     pub fn delete(test: &Test) -> Result<(), String> {
-        let path = test.path();
+        let dir_path = test.dir();
 
-        // delete file if exist
-        if Cmd::is_exist(&path) {
-            Cmd::delete(&path).unwrap();
-            log::info!(":: Test delete {}", path.display());
+        // delete run directory if exist
+        if Cmd::is_exist(&dir_path) {
+            Cmd::delete_dir(&dir_path).unwrap();
+            log::info!(":: Test delete {}", dir_path.display());
         }
 
-        // delete directory too if empty
-        let dir_path = path.parent().unwrap();
-        if Cmd::is_empty(dir_path) {
-            Cmd::delete_dir(dir_path).unwrap();
+        // delete version and strategy directories too if empty
+        for dir_path in dir_path.ancestors().skip(1).take(2) {
+            if Cmd::is_exist(dir_path) && Cmd::is_empty(dir_path) {
+                Cmd::delete_dir(dir_path).unwrap();
+            }
         }
 
         Ok(())
@@ -111,16 +233,148 @@ impl Test {
         self.end_ts_nanos = dt.timestamp_nanos_opt().unwrap();
     }
 
-    pub fn path(&self) -> PathBuf {
+    /// Copy run settings of other test: period, deposit, commission,
+    /// execution and sizing models, warm-up.
+    ///
+    /// # ru
+    /// Копирует настройки запуска другого теста: период, депозит,
+    /// комиссию, модели исполнения и размера позиции, прогрев.
+    /// Стратегия, параметры и трейды не копируются.
+    pub fn copy_settings(&mut self, other: &Test) {
+        self.deposit = other.deposit;
+        self.commission = other.commission;
+        self.partial_fill = other.partial_fill;
+        self.latency = other.latency;
+        self.margin = other.margin;
+        self.intrabar = other.intrabar;
+        self.noise = other.noise;
+        self.sizing = other.sizing;
+        self.seed = other.seed;
+        self.warmup = other.warmup.clone();
+        self.begin_ts_nanos = other.begin_ts_nanos;
+        self.end_ts_nanos = other.end_ts_nanos;
+    }
+    /// Number of warm-up bars of timeframe.
+    ///
+    /// # ru
+    /// Количество баров прогрева таймфрейма, 0 - без прогрева.
+    pub fn warmup_bars(&self, tf: TimeFrame) -> u32 {
+        self.warmup
+            .iter()
+            .find(|(i, _)| *i == tf)
+            .map(|(_, n)| *n)
+            .unwrap_or(0)
+    }
+    pub fn set_warmup(&mut self, tf: TimeFrame, bars: u32) {
+        self.warmup.retain(|(i, _)| *i != tf);
+        if bars > 0 {
+            self.warmup.push((tf, bars));
+        }
+    }
+
+    /// Directory of test run.
+    ///
+    /// # ru
+    /// Каталог запуска теста, ключ: стратегия, версия стратегии,
+    /// инструмент, период и хэш параметров:
+    /// <test_dir>/<strategy>/v<version>/<TICKER>_<begin>_<end>_<params>/
+    /// Для теста с шумом цен добавляется _noise<seed>, для seed теста
+    /// не по умолчанию - _seed<seed>.
+    /// Внутри: test.bin - тест с трейдами, equity.parquet - кривая
+    /// капитала, manifest.toml - метаданные запуска, report.html.
+    pub fn dir(&self) -> PathBuf {
         let mut p = PathBuf::new();
         p.push(CFG.dir.test());
         p.push(&self.strategy_name);
-        p.push(format!("{}.bin", &self.iid.ticker()));
+        p.push(format!("v{}", self.strategy_version));
+        p.push(format!(
+            "{}_{}_{}_{}",
+            self.iid.ticker(),
+            self.begin().format("%Y%m%d"),
+            self.end().format("%Y%m%d"),
+            self.params_hash(),
+        ));
+        if let Some(noise) = self.noise {
+            let name = p.file_name().unwrap().to_string_lossy();
+            let name = format!("{name}_noise{}", noise.seed);
+            p.set_file_name(name);
+        }
+        if self.seed != DEFAULT_SEED {
+            let name = p.file_name().unwrap().to_string_lossy();
+            let name = format!("{name}_seed{}", self.seed);
+            p.set_file_name(name);
+        }
 
         p
     }
+    pub fn path(&self) -> PathBuf {
+        self.dir().join("test.bin")
+    }
+    /// Path of equity and drawdown series, saved by tester.
+    ///
+    /// # ru
+    /// Путь к кривой капитала теста, см. [`Equity`].
+    pub fn equity_path(&self) -> PathBuf {
+        self.dir().join("equity.parquet")
+    }
+    /// Path of strategy decisions journal, saved by tester.
+    ///
+    /// # ru
+    /// Путь к журналу решений стратегии, см. [`crate::Journal`].
+    pub fn journal_path(&self) -> PathBuf {
+        self.dir().join("journal.parquet")
+    }
+    /// Path of strategy signals, saved by tester.
+    ///
+    /// # ru
+    /// Путь к сигналам стратегии, см. [`avin_core::Signal`].
+    pub fn signals_path(&self) -> PathBuf {
+        self.dir().join("signals.parquet")
+    }
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir().join("manifest.toml")
+    }
+    pub fn report_path(&self) -> PathBuf {
+        self.dir().join("report.html")
+    }
+    /// Hash of strategy parameters, "default" if empty.
+    ///
+    /// # ru
+    /// Хэш параметров стратегии, для параметров по умолчанию -
+    /// "default".
+    pub fn params_hash(&self) -> String {
+        if self.params.is_empty() {
+            return "default".to_string();
+        }
+
+        self.params.hash()
+    }
+    pub fn load_equity(&self) -> Result<DataFrame, AvinError> {
+        Equity::load(&self.equity_path())
+    }
     pub fn clear(&mut self) {
         self.trade_list.clear();
+        self.margin_call = None;
+    }
+
+    // private
+    /// Переводит тест версии формата version в текущую.
+    fn migrate(version: u16, payload: &[u8]) -> Result<Test, AvinError> {
+        let decode_err = |e: bitcode::Error| {
+            AvinError::InvalidValue(format!("test.bin v{version}: {e}"))
+        };
+
+        match version {
+            // при смене формата здесь появится ветка старой версии:
+            // N => bitcode::decode::<TestVN>(payload)
+            //     .map(Test::from)
+            //     .map_err(decode_err),
+            FORMAT_VERSION => bitcode::decode(payload).map_err(decode_err),
+            _ => Err(AvinError::InvalidValue(format!(
+                "test.bin v{version}: unknown format version, \
+                supported up to v{FORMAT_VERSION}"
+            ))),
+        }
     }
 }
 
@@ -128,6 +382,7 @@ impl Test {
 mod tests {
     use super::*;
     use avin_core::Asset;
+    use avin_core::fixture;
     use avin_strategy::PinBarLong;
 
     #[test]
@@ -138,9 +393,17 @@ mod tests {
 
         assert_eq!(test.name(), "PinBarLong-YDEX");
         assert_eq!(test.strategy_name, "PinBarLong");
+        assert_eq!(test.strategy_version, "1");
         assert_eq!(test.iid, *asset.iid());
         assert_eq!(test.deposit, 100_000.0);
-        assert_eq!(test.commission, 0.0005);
+        assert_eq!(test.commission, Commission::default());
+        assert_eq!(test.partial_fill, None);
+        assert_eq!(test.latency, Latency::Fixed(0));
+        assert_eq!(test.margin, None);
+        assert_eq!(test.sizing, None);
+        assert_eq!(test.seed, DEFAULT_SEED);
+        assert_eq!(test.intrabar, Intrabar::Path);
+        assert_eq!(test.noise, None);
         assert_eq!(
             test.begin_ts_nanos,
             Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0)
@@ -156,8 +419,33 @@ mod tests {
                 .unwrap()
         );
         assert_eq!(test.status, TestStatus::New);
+        assert_eq!(test.warmup_bars(TimeFrame::Day), 0);
     }
 
+    #[test]
+    fn format_version() {
+        let strategy = PinBarLong::default();
+        let mut test = Test::new(&strategy, &fixture::iid());
+        test.partial_fill = Some(0.1);
+        test.set_warmup(TimeFrame::Day, 20);
+
+        let bytes = test.to_bin();
+        assert!(bytes.starts_with(FORMAT_MAGIC));
+        assert_eq!(Test::from_bin(&bytes).unwrap(), test);
+
+        // файл без версии, сохраненный до ее появления
+        let legacy = bitcode::encode(&test);
+        assert_eq!(Test::from_bin(&legacy).unwrap(), test);
+
+        // более новая версия и поврежденные данные - ошибка, не panic
+        let mut newer = FORMAT_MAGIC.to_vec();
+        newer.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        newer.extend_from_slice(&bitcode::encode(&test));
+        assert!(Test::from_bin(&newer).is_err());
+        assert!(Test::from_bin(&bytes[..bytes.len() / 2]).is_err());
+        assert!(Test::from_bin(&FORMAT_MAGIC[..]).is_err());
+        assert!(Test::from_bin(&[]).is_err());
+    }
     #[test]
     fn save_load_delete() {
         let strategy = PinBarLong::default();
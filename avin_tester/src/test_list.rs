@@ -5,7 +5,7 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use bitcode::{Decode, Encode};
 
@@ -40,7 +40,7 @@ impl TestList {
         // create dir path
         let mut dir_path = CFG.dir.test();
         dir_path.push(name);
-        let files = Cmd::get_files(&dir_path).unwrap();
+        let files = find_tests(&dir_path);
 
         // load test files
         for file in files {
@@ -55,7 +55,7 @@ impl TestList {
         let mut test_list = TestList::new();
 
         // get test paths of test files
-        let files = Cmd::get_files(path).unwrap();
+        let files = find_tests(path);
 
         // load test files
         for file in files {
@@ -107,6 +107,22 @@ impl TestList {
     }
 }
 
+/// Файлы тестов в каталоге и подкаталогах, каждый запуск теста
+/// хранится в своем каталоге, см. [`Test::dir`].
+fn find_tests(dir_path: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Cmd::get_files(dir_path)
+        .unwrap()
+        .into_iter()
+        .filter(|i| i.file_name().is_some_and(|n| n == "test.bin"))
+        .collect();
+
+    for dir in Cmd::get_dirs(dir_path).unwrap() {
+        files.extend(find_tests(&dir));
+    }
+
+    files
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,187 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{DateTime, Utc};
+
+use avin_core::{Category, Exchange, Iid, Manager, MarketData};
+use avin_strategy::Params;
+use avin_utils::AvinError;
+
+use crate::{Test, TestList, Tester};
+
+/// Instrument of universe and its trading period in test.
+///
+/// # ru
+/// Инструмент набора и часть периода теста, когда он торговался.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniverseMember {
+    pub iid: Iid,
+    pub begin: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Survivorship-bias-aware set of instruments for backtest.
+///
+/// # ru
+/// Набор инструментов для бэктеста "по всем акциям" без ошибки
+/// выжившего. Текущий кэш содержит только торгующиеся сейчас бумаги:
+/// тест по нему не видит ушедших с биржи (часто как раз упавших)
+/// компаний. Набор строится из кэша и архива делистинга, каждый
+/// инструмент тестируется только на периоде своих торгов - от
+/// листинга до делистинга, см. [`Iid::trading_period`]. Инструменты,
+/// не торговавшиеся в периоде теста, исключаются.
+#[derive(Debug)]
+pub struct Universe {
+    members: Vec<UniverseMember>,
+    excluded: Vec<Iid>,
+}
+impl Universe {
+    /// Create universe of instruments for period [begin, end).
+    ///
+    /// # ru
+    /// Создает набор из инструментов на период теста [begin, end).
+    pub fn new(
+        iids: Vec<Iid>,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Self {
+        let mut members = Vec::new();
+        let mut excluded = Vec::new();
+
+        for iid in iids {
+            match iid.trading_period(begin, end) {
+                Some((begin, end)) => {
+                    members.push(UniverseMember { iid, begin, end })
+                }
+                None => excluded.push(iid),
+            }
+        }
+
+        Self { members, excluded }
+    }
+    /// All instruments of category, including delisted.
+    ///
+    /// # ru
+    /// Набор всех инструментов биржи и категории, включая ушедшие с
+    /// биржи, см. [`Manager::universe`].
+    pub fn load(
+        exchange: Exchange,
+        category: Category,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Self, AvinError> {
+        let iids = Manager::universe(exchange, category)?;
+
+        Ok(Self::new(iids, begin, end))
+    }
+    pub fn members(&self) -> &Vec<UniverseMember> {
+        &self.members
+    }
+    /// Instruments not traded in test period.
+    ///
+    /// # ru
+    /// Инструменты, не торговавшиеся в периоде теста.
+    pub fn excluded(&self) -> &Vec<Iid> {
+        &self.excluded
+    }
+
+    /// Test of universe member with template settings.
+    ///
+    /// # ru
+    /// Тест инструмента набора: настройки и параметры шаблона, период
+    /// обрезан по периоду торгов инструмента.
+    pub fn test<S: Params>(
+        member: &UniverseMember,
+        strategy: &S,
+        template: &Test,
+    ) -> Test {
+        let mut test = Test::new(strategy, &member.iid);
+        test.copy_settings(template);
+        test.params = template.params.clone();
+        test.set_begin(&member.begin);
+        test.set_end(&member.end);
+
+        test
+    }
+    /// Run template test on all members.
+    ///
+    /// # ru
+    /// Запускает тест шаблона по всем инструментам набора, каждый на
+    /// своем периоде торгов. Инструменты без рыночных данных в
+    /// периоде пропускаются с предупреждением.
+    pub async fn run<S: Params>(&self, template: &Test) -> TestList {
+        log::info!(
+            ":: Universe {} instruments, {} excluded",
+            self.members.len(),
+            self.excluded.len()
+        );
+
+        let mut test_list = TestList::new();
+        for member in self.members.iter() {
+            let data = Manager::load(
+                &member.iid,
+                MarketData::BAR_DAY,
+                member.begin,
+                member.end,
+            );
+            if data.is_err() {
+                log::warn!("Universe skip {}: no market data", member.iid);
+                continue;
+            }
+
+            let strategy = S::with_params(&template.params);
+            let mut test = Self::test(member, &strategy, template);
+            Tester::new().run(strategy, &mut test).await;
+            test_list.add(test);
+        }
+
+        test_list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture;
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn iid(ticker: &str, listed: &str, delisted: Option<&str>) -> Iid {
+        let mut fields = vec![("ticker", ticker), ("first_1m", listed)];
+        if let Some(date) = delisted {
+            fields.push(("delisted", date));
+        }
+
+        fixture::iid_with(&fields)
+    }
+
+    #[test]
+    fn survivorship() {
+        let dt = |y| Utc.with_ymd_and_hms(y, 1, 1, 0, 0, 0).unwrap();
+        let iids = vec![
+            iid("SBER", "2010-01-01T00:00:00Z", None),
+            iid("YNDX", "2014-01-01T00:00:00Z", Some("2024-06-17")),
+            iid("OLD", "2005-01-01T00:00:00Z", Some("2019-06-01")),
+            iid("NEW", "2025-01-01T00:00:00Z", None),
+            iid("IPO", "2022-01-01T00:00:00Z", None),
+        ];
+        let universe = Universe::new(iids, dt(2020), dt(2025));
+
+        let tickers: Vec<&String> =
+            universe.members().iter().map(|m| m.iid.ticker()).collect();
+        assert_eq!(tickers, ["SBER", "YNDX", "IPO"]);
+        assert_eq!(universe.excluded().len(), 2);
+
+        let yndx = &universe.members()[1];
+        assert_eq!(yndx.begin, dt(2020));
+        assert_eq!(
+            yndx.end,
+            Utc.with_ymd_and_hms(2024, 6, 17, 0, 0, 0).unwrap()
+        );
+        assert_eq!(universe.members()[2].begin, dt(2022));
+    }
+}
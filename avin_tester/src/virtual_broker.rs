@@ -5,22 +5,29 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use chrono::{DateTime, Utc};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 use avin_core::{
-    Account, Action, Bar,
+    Account, Action, Bar, Category,
     Direction::{self, Sell},
-    Event, Iid, LimitOrder, MarketOrder, Order, OrderAction, OrderEvent,
-    PostedLimitOrder, PostedMarketOrder, PostedStopOrder, StopOrder,
+    Event, Iid, LimitOrder, Margin, MarketOrder, Operation, Order,
+    OrderAction, OrderEvent, PostedMarketOrder, PostedStopOrder, StopOrder,
     StopOrderKind::{StopLoss, TakeProfit},
     TimeFrame, Transaction, TriggeredStopOrder,
 };
 
 use super::data_stream::DataStream;
 use super::test::Test;
+use crate::intrabar::rest_of_bar;
+use crate::margin::MarginAccount;
+use crate::random::{Random, Stream, derive};
+use crate::{Commission, Intrabar, Latency};
+
+// Пояснение в отклоненном ордере, когда не хватает маржи
+const MARGIN_REJECT: &str = "insufficient margin";
 
 pub struct VirtualBroker {
     tx: UnboundedSender<Action>,
@@ -28,7 +35,17 @@ pub struct VirtualBroker {
     data_stream: DataStream,
     account: Account,
     strategy_name: String,
-    commission: f64,
+    commission: Commission,
+    partial_fill: Option<f64>,
+    latency: Latency,
+    seed: u64,
+    ids: Random,
+    delayed: VecDeque<(i64, Action)>,
+    arrival: Option<f64>,
+    arrived: HashMap<String, f64>,
+    margin: Option<MarginAccount>,
+    margin_call: Option<Order>,
+    intrabar: Intrabar,
 
     current_bar: Bar,
     queue: VecDeque<Event>,
@@ -36,16 +53,21 @@ pub struct VirtualBroker {
     limit_orders: Vec<LimitOrder>,
     stop_orders: Vec<StopOrder>,
     need_check_orders: bool,
+    position: i64,
+    operations: Vec<Operation>,
 }
 impl VirtualBroker {
     pub fn new(test: &Test) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
-        let data_stream = Self::create_marketdata_stream(
+        let mut data_stream = Self::create_marketdata_stream(
             &test.iid,
             test.begin(),
             test.end(),
         )
         .unwrap();
+        if let Some(noise) = &test.noise {
+            data_stream.add_noise(noise, test.seed);
+        }
 
         VirtualBroker {
             tx,
@@ -54,6 +76,23 @@ impl VirtualBroker {
             account: Account::new("VirtualAccount", "Virtual_ID"),
             strategy_name: test.strategy_name.clone(),
             commission: test.commission,
+            partial_fill: test.partial_fill,
+            latency: test.latency,
+            seed: derive(test.seed, Stream::Latency, 0),
+            ids: Random::new(derive(test.seed, Stream::BrokerId, 0)),
+            delayed: VecDeque::new(),
+            arrival: None,
+            arrived: HashMap::new(),
+            margin: test.margin.map(|m| {
+                // ставки риска инструмента, если есть, как в PaperBroker
+                let mut margin =
+                    Margin::from_iid(&test.iid, m.rate).unwrap_or(m);
+                margin.short_rate = m.short_rate;
+                let futures = test.iid.category() == Category::FUTURE.name();
+                MarginAccount::new(margin, test.deposit, futures)
+            }),
+            margin_call: None,
+            intrabar: test.intrabar,
 
             current_bar: Bar::new(0, 0.0, 0.0, 0.0, 0.0, 0),
             queue: VecDeque::new(),
@@ -61,6 +100,8 @@ impl VirtualBroker {
             limit_orders: Vec::new(),
             stop_orders: Vec::new(),
             need_check_orders: false,
+            position: 0,
+            operations: Vec::new(),
         }
     }
 
@@ -70,21 +111,38 @@ impl VirtualBroker {
     pub fn get_sender(&self) -> UnboundedSender<Action> {
         self.tx.clone()
     }
+    /// Take liquidation order of margin call.
+    ///
+    /// # ru
+    /// Забирает исполненный ордер принудительного закрытия позиции
+    /// при margin call. Стратегия этот ордер не выставляла, поэтому
+    /// он не попадает в очередь событий, тестер обрабатывает его сам.
+    pub fn take_margin_call(&mut self) -> Option<Order> {
+        self.margin_call.take()
+    }
     pub fn next_event(&mut self) -> Option<Event> {
         // process actions from strategys
         while let Ok(a) = self.rx.try_recv() {
-            match a {
-                Action::GetAccount(_) => todo!(),
-                Action::GetBars(_) => todo!(),
-                Action::Post(a) => self.post_action(a),
-                Action::Cancel(a) => self.cancel_action(a),
-                Action::TradeOpened(_) => unreachable!(),
-                Action::TradeClosed(_) => unreachable!(),
-                Action::Subscribe(_) => unreachable!(),
-                Action::Unsubscribe(_) => unreachable!(),
+            if self.latency.is_zero() {
+                self.process_action(a);
+            } else {
+                self.delay_action(a);
             }
         }
 
+        // отложенные действия, пришедшие к брокеру до конца текущего
+        // 1М бара, ордер запоминает долю бара на момент прихода
+        let minute = TimeFrame::M1.nanos();
+        while let Some((arrival, _)) = self.delayed.front()
+            && *arrival < self.current_bar.ts + minute
+        {
+            let (arrival, a) = self.delayed.pop_front().unwrap();
+            let part = (arrival - self.current_bar.ts) as f64 / minute as f64;
+            self.arrival = Some(part.max(0.0));
+            self.process_action(a);
+            self.arrival = None;
+        }
+
         // если в очереди есть event (а там будут Event::Order) - выдать его
         let e = self.queue.pop_front();
         if e.is_some() {
@@ -114,12 +172,15 @@ impl VirtualBroker {
                     // обновляется только на 1М
                     if e.tf == TimeFrame::M1 {
                         self.current_bar = e.bar;
+                        self.arrived.clear();
                         self.need_check_orders = true;
+                        self.check_margin();
                     } else {
                         self.need_check_orders = false;
                     };
                 }
                 Event::Tic(_) => todo!("Обработка тиков виртуал брокером..."),
+                Event::Book(_) => unreachable!("BookEvent in data stream?"),
                 Event::Order(_) => unreachable!("OrderEvent in data stream?"),
             }
 
@@ -131,6 +192,64 @@ impl VirtualBroker {
     }
 
     // private
+    fn process_action(&mut self, a: Action) {
+        match a {
+            Action::GetAccount(_) => todo!(),
+            Action::GetBars(_) => todo!(),
+            Action::GetOrders(a) => {
+                let _ = a.tx.send(self.active_orders(&a.iid));
+            }
+            Action::GetPosition(a) => {
+                // виртуальный брокер торгует одним инструментом
+                let position = if a.iid == self.data_stream.iid {
+                    self.position
+                } else {
+                    0
+                };
+                let _ = a.tx.send(position);
+            }
+            Action::GetOperations(a) => {
+                let _ = a.tx.send(self.operations_from(&a.iid, a.from));
+            }
+            Action::GetCashOperations(a) => {
+                // у виртуального счета нет неторгового движения денег,
+                // плата за заем входит в комиссию ордеров
+                let _ = a.tx.send(Vec::new());
+            }
+            Action::Post(a) => self.post_action(a),
+            Action::Cancel(a) => self.cancel_action(a),
+            Action::TradeOpened(_) => unreachable!(),
+            Action::TradeClosed(_) => unreachable!(),
+            Action::Signal(_) => unreachable!(),
+            Action::Notify(_) => unreachable!(),
+            Action::Subscribe(_) => unreachable!(),
+            Action::Unsubscribe(_) => unreachable!(),
+        }
+    }
+    fn delay_action(&mut self, a: Action) {
+        // действие приходит к брокеру через задержку от закрытия бара,
+        // на котором стратегия приняла решение. Очередь упорядочена по
+        // времени прихода, при случайной задержке ордера могут прийти
+        // не в том порядке, в котором отправлены - как в реальности
+        let decision = self.current_bar.ts + TimeFrame::M1.nanos();
+        let arrival = decision + self.latency.sample(&mut self.seed);
+        let i = self.delayed.partition_point(|(ts, _)| *ts <= arrival);
+        self.delayed.insert(i, (arrival, a));
+    }
+    fn broker_id(&mut self) -> String {
+        // идентификаторы ордеров тоже от seed теста, чтобы повторный
+        // запуск давал побитово тот же результат
+        let (hi, lo) = (self.ids.next_u64(), self.ids.next_u64());
+
+        uuid::Uuid::from_u64_pair(hi, lo).to_string()
+    }
+    /// Запоминает долю бара, на которой пришел к брокеру отложенный
+    /// ордер: в этом баре он проверяется только по остатку бара.
+    fn arrive(&mut self, broker_id: &str) {
+        if let Some(part) = self.arrival {
+            self.arrived.insert(broker_id.to_string(), part);
+        }
+    }
     fn create_marketdata_stream(
         iid: &Iid,
         begin: DateTime<Utc>,
@@ -162,8 +281,16 @@ impl VirtualBroker {
             .as_new()
             .expect("Order must have status 'New', posting failed");
 
+        // check margin
+        let price = self.current_bar.c;
+        if !self.margin_enough(&new_order.direction, new_order.lots, price) {
+            let rejected = new_order.reject(MARGIN_REJECT);
+            return Order::Market(MarketOrder::Rejected(rejected));
+        }
+
         // create broker id
-        let broker_id = uuid::Uuid::new_v4().to_string();
+        let broker_id = self.broker_id();
+        self.arrive(&broker_id);
 
         // change status
         let posted_order = new_order.post(&broker_id);
@@ -183,8 +310,16 @@ impl VirtualBroker {
             .as_new()
             .expect("Order must have status 'New', posting failed");
 
+        // check margin
+        let price = new_order.price;
+        if !self.margin_enough(&new_order.direction, new_order.lots, price) {
+            let rejected = new_order.reject(MARGIN_REJECT);
+            return Order::Limit(LimitOrder::Rejected(rejected));
+        }
+
         // create broker id
-        let broker_id = uuid::Uuid::new_v4().to_string();
+        let broker_id = self.broker_id();
+        self.arrive(&broker_id);
 
         // change status
         let posted_order = new_order.post(&broker_id);
@@ -205,7 +340,8 @@ impl VirtualBroker {
             .expect("Order must have status 'New', posting failed");
 
         // create broker id
-        let broker_id = uuid::Uuid::new_v4().to_string();
+        let broker_id = self.broker_id();
+        self.arrive(&broker_id);
 
         // change status
         let posted_order = new_order.post(&broker_id);
@@ -231,49 +367,80 @@ impl VirtualBroker {
             // unwrap
             let order = order.as_posted().unwrap();
 
-            // exec in current bar
-            self.exec_market(bar.ts, bar.c, order);
+            // exec in current bar, пришедший с задержкой - по цене
+            // в момент прихода
+            let price = match self.arrived.get(&order.broker_id) {
+                Some(part) => rest_of_bar(&bar, *part).o,
+                None => bar.c,
+            };
+            self.exec_market(bar.ts, price, order);
         }
     }
     fn check_all_orders_limit(&mut self) {
-        let bar = self.current_bar;
         let mut i = 0;
 
         while i < self.limit_orders.len() {
-            // unwrap PostedLimitOrder
-            let limit_order = &self.limit_orders[i];
-            let posted = limit_order.clone().as_posted().unwrap();
-
-            // если бар содержит цену лимитки -> по цене лимитки
-            if self.current_bar.contains(posted.price) {
-                self.exec_limit(bar.ts, posted.price, posted);
-                self.limit_orders.remove(i);
-            }
-            // бар открылся под лимиткой на покупку -> по цене открытия
-            else if posted.direction == Direction::Buy {
-                if bar.o < posted.price {
-                    self.exec_limit(bar.ts, posted.price, posted);
-                    self.limit_orders.remove(i);
+            let (direction, price, id) = match &self.limit_orders[i] {
+                LimitOrder::Posted(o) => {
+                    (o.direction.clone(), o.price, &o.broker_id)
                 }
-            }
-            // бар открылся над лимиткой на продажу -> по цене открытия
-            else if posted.direction == Direction::Sell {
-                if bar.o > posted.price {
-                    self.exec_limit(bar.ts, posted.price, posted);
-                    self.limit_orders.remove(i);
+                LimitOrder::PartiallyFilled(o) => {
+                    (o.direction.clone(), o.price, &o.broker_id)
                 }
-            }
+                _ => panic!("WTF??? Тут должны быть только активные ордера"),
+            };
+            let bar = order_bar(&self.arrived, &self.current_bar, id);
+
+            // бар открылся под лимиткой на покупку или над лимиткой
+            // на продажу -> весь остаток исполняется по цене лимитки
+            let gap = match direction {
+                Direction::Buy => bar.o < price,
+                Direction::Sell => bar.o > price,
+            };
+
             // ордер не исполнен, переходим к следующему
-            else {
+            if !gap && !self.intrabar.limit_filled(&bar, price) {
+                i += 1;
+                continue;
+            }
+
+            // если бар содержит цену лимитки -> по цене лимитки, но
+            // не больше доступной доли объема бара
+            let available = if gap {
+                u32::MAX
+            } else {
+                fill_lots(
+                    bar.v,
+                    self.data_stream.iid.lot(),
+                    self.partial_fill,
+                )
+            };
+
+            let order = self.limit_orders.remove(i);
+            if let Some(order) =
+                self.exec_limit(bar.ts, price, order, available)
+            {
+                self.limit_orders.insert(i, order);
                 i += 1;
             }
         }
     }
     fn check_all_orders_stop(&mut self) {
-        let bar = self.current_bar;
-        let ts = bar.ts;
+        let ts = self.current_bar.ts;
         let mut i = 0;
 
+        // стоп ордера проверяются в порядке сработки внутри бара
+        let intrabar = self.intrabar;
+        let (arrived, current) = (&self.arrived, self.current_bar);
+        let key = |order: &StopOrder| match order {
+            StopOrder::Posted(o) => {
+                let bar = order_bar(arrived, &current, &o.broker_id);
+                intrabar.stop_order(&bar, &o.kind, o.stop_price)
+            }
+            _ => panic!("WTF??? Тут должны быть только 'posted' ордера"),
+        };
+        self.stop_orders.sort_by(|a, b| key(a).total_cmp(&key(b)));
+
         while i < self.stop_orders.len() {
             // unwrap PostedStopOrder
             let stop_order = &self.stop_orders[i];
@@ -281,6 +448,11 @@ impl VirtualBroker {
                 StopOrder::Posted(order) => order,
                 _ => panic!("WTF??? Тут должны быть только 'posted' ордера"),
             };
+            let bar = order_bar(
+                &self.arrived,
+                &self.current_bar,
+                &posted.broker_id,
+            );
 
             // если бар содержит цену сработки...
             if bar.contains(posted.stop_price) {
@@ -337,23 +509,8 @@ impl VirtualBroker {
             i += 1;
         }
     }
-    fn exec_market(
-        &mut self,
-        ts: i64,
-        price: f64,
-        mut order: PostedMarketOrder,
-    ) {
-        // create transaction
-        let quantity = order.lots * self.data_stream.iid.lot();
-        let transaction = Transaction::new(quantity as i32, price);
-        let commission = transaction.value() * self.commission;
-        order.add_transaction(transaction);
-
-        // change status
-        let order = order.fill(ts, commission);
-
-        // wrap
-        let order = Order::Market(MarketOrder::Filled(order));
+    fn exec_market(&mut self, ts: i64, price: f64, order: PostedMarketOrder) {
+        let order = self.fill_market(ts, price, order);
 
         // create order event and push in queue
         let e = OrderEvent::new(
@@ -365,33 +522,217 @@ impl VirtualBroker {
         let e = Event::Order(e);
         self.queue.push_back(e);
     }
-    fn exec_limit(
+    fn fill_market(
         &mut self,
         ts: i64,
         price: f64,
-        mut order: PostedLimitOrder,
-    ) {
+        mut order: PostedMarketOrder,
+    ) -> Order {
         // create transaction
         let quantity = order.lots * self.data_stream.iid.lot();
         let transaction = Transaction::new(quantity as i32, price);
-        let commission = transaction.value() * self.commission;
+        let commission =
+            self.commission
+                .calculate(transaction.value(), order.lots, false);
+        self.apply_fill(&order.direction, &transaction);
+        let commission = self.margin_commission(commission);
         order.add_transaction(transaction);
 
         // change status
         let order = order.fill(ts, commission);
+        self.operations.push(order.operation.clone());
 
         // wrap
-        let order = Order::Limit(LimitOrder::Filled(order));
+        Order::Market(MarketOrder::Filled(order))
+    }
+    /// Исполняет available лотов лимитного ордера, возвращает ордер,
+    /// если он исполнен не полностью.
+    fn exec_limit(
+        &mut self,
+        ts: i64,
+        price: f64,
+        order: LimitOrder,
+        available: u32,
+    ) -> Option<LimitOrder> {
+        let (direction, remaining) = match &order {
+            LimitOrder::Posted(o) => (o.direction.clone(), o.lots),
+            LimitOrder::PartiallyFilled(o) => {
+                (o.direction.clone(), o.remaining())
+            }
+            _ => unreachable!("exec not active limit order"),
+        };
+        let lots = remaining.min(available);
+        if lots == 0 {
+            return Some(order);
+        }
+
+        // create transaction
+        let quantity = lots * self.data_stream.iid.lot();
+        let transaction = Transaction::new(quantity as i32, price);
+        self.apply_fill(&direction, &transaction);
+
+        // change status
+        let order = match order {
+            LimitOrder::Posted(mut o) if lots == o.lots => {
+                o.add_transaction(transaction);
+                let commission = self.limit_commission(&o.transactions, lots);
+                LimitOrder::Filled(o.fill(ts, commission))
+            }
+            LimitOrder::Posted(o) => {
+                LimitOrder::PartiallyFilled(o.partial_fill(lots, transaction))
+            }
+            LimitOrder::PartiallyFilled(mut o) => {
+                o.add_fill(lots, transaction);
+                if o.is_complete() {
+                    let commission =
+                        self.limit_commission(&o.transactions, o.lots);
+                    LimitOrder::Filled(o.fill(ts, commission))
+                } else {
+                    LimitOrder::PartiallyFilled(o)
+                }
+            }
+            _ => unreachable!(),
+        };
+        if let LimitOrder::Filled(o) = &order {
+            self.operations.push(o.operation.clone());
+        }
 
         // create order event and push in queue
         let e = OrderEvent::new(
             self.account.clone(),
             self.data_stream.iid.clone(),
             self.strategy_name.clone(),
-            order,
+            Order::Limit(order.clone()),
         );
         let e = Event::Order(e);
         self.queue.push_back(e);
+
+        // wrap
+        if order.is_partially_filled() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+    fn limit_commission(
+        &mut self,
+        transactions: &[Transaction],
+        lots: u32,
+    ) -> f64 {
+        let value = transactions.iter().map(|t| t.value()).sum();
+        let commission = self.commission.calculate(value, lots, true);
+
+        self.margin_commission(commission)
+    }
+    /// Активные ордера инструмента, как их вернул бы брокер.
+    fn active_orders(&self, iid: &Iid) -> Vec<Order> {
+        if *iid != self.data_stream.iid {
+            return Vec::new();
+        }
+
+        let market = self.market_orders.iter().cloned().map(Order::Market);
+        let limit = self.limit_orders.iter().cloned().map(Order::Limit);
+        let stop = self.stop_orders.iter().cloned().map(Order::Stop);
+
+        market.chain(limit).chain(stop).collect()
+    }
+    /// Операции по инструменту начиная с from.
+    fn operations_from(
+        &self,
+        iid: &Iid,
+        from: DateTime<Utc>,
+    ) -> Vec<Operation> {
+        if *iid != self.data_stream.iid {
+            return Vec::new();
+        }
+
+        let from = from.timestamp_nanos_opt().unwrap_or(i64::MIN);
+        self.operations
+            .iter()
+            .filter(|op| op.ts >= from)
+            .cloned()
+            .collect()
+    }
+    fn margin_enough(
+        &self,
+        direction: &Direction,
+        lots: u32,
+        price: f64,
+    ) -> bool {
+        let Some(account) = &self.margin else {
+            return true;
+        };
+        let quantity = lots as i64 * self.data_stream.iid.lot() as i64;
+
+        account.check(direction, quantity, price)
+    }
+    /// Учитывает исполненную сделку в позиции и маржинальном счете.
+    fn apply_fill(
+        &mut self,
+        direction: &Direction,
+        transaction: &Transaction,
+    ) {
+        let quantity = transaction.quantity as i64;
+        match direction {
+            Direction::Buy => self.position += quantity,
+            Direction::Sell => self.position -= quantity,
+        }
+
+        if let Some(account) = &mut self.margin {
+            account.apply(direction, transaction);
+        }
+    }
+    /// Списывает комиссию ордера в маржинальном счете и добавляет к ней
+    /// накопленную плату за заемные деньги и бумаги.
+    fn margin_commission(&mut self, commission: f64) -> f64 {
+        match &mut self.margin {
+            Some(account) => {
+                account.charge(commission);
+                commission + account.take_fee()
+            }
+            None => commission,
+        }
+    }
+    /// На каждом 1М баре начисляет плату за заем, и если капитал упал
+    /// ниже минимальной маржи - снимает активные ордера и закрывает
+    /// позицию по рынку, как брокер при margin call. Ордер закрытия
+    /// забирает тестер, см. [`VirtualBroker::take_margin_call`].
+    fn check_margin(&mut self) {
+        let bar = self.current_bar;
+        let Some(account) = &mut self.margin else {
+            return;
+        };
+
+        account.accrue(bar.ts, bar.c);
+        if !account.is_margin_call(bar.c) {
+            return;
+        }
+
+        let position = account.position();
+        log::warn!(
+            "Margin call {}: position={position} equity={}",
+            bar.dt(),
+            account.equity(bar.c)
+        );
+
+        // активные ордера снимаются без событий стратегии, иначе стоп
+        // может снова открыть позицию
+        self.market_orders.clear();
+        self.limit_orders.clear();
+        self.stop_orders.clear();
+
+        // принудительное закрытие позиции по цене закрытия бара
+        let direction = if position > 0 {
+            Direction::Sell
+        } else {
+            Direction::Buy
+        };
+        let lots =
+            position.unsigned_abs() / self.data_stream.iid.lot() as u64;
+        let broker_id = self.broker_id();
+        let order = MarketOrder::new(direction, lots as u32).post(&broker_id);
+        let order = self.fill_market(bar.ts, bar.c, order);
+        self.margin_call = Some(order);
     }
     fn trigger_stop(
         &mut self,
@@ -399,17 +740,33 @@ impl VirtualBroker {
         _price: f64,
         order: PostedStopOrder,
     ) {
-        let bar = &self.current_bar;
-        let id = order.broker_id.clone();
-        let triggered = order.trigger(&id);
+        let bar = self.current_bar;
+
+        // при срабатывании стопа брокер выставляет новый ордер, в баре
+        // прихода стопа он тоже видит только остаток бара
+        let broker_id = self.broker_id();
+        if let Some(part) = self.arrived.get(&order.broker_id).copied() {
+            self.arrived.insert(broker_id.clone(), part);
+        }
+        let triggered = order.trigger(&broker_id);
+
+        // сначала эвент о срабатывании стопа
+        let e = OrderEvent::new(
+            self.account.clone(),
+            self.data_stream.iid.clone(),
+            self.strategy_name.clone(),
+            Order::Stop(StopOrder::Triggered(triggered.clone())),
+        );
+        self.queue.push_back(Event::Order(e));
 
+        // затем исполнение порожденного ордера
         match triggered {
-            TriggeredStopOrder::Limit(order) => {
+            TriggeredStopOrder::Limit { order, .. } => {
                 let order = LimitOrder::Posted(order);
                 self.limit_orders.push(order);
                 self.check_all_orders_limit();
             }
-            TriggeredStopOrder::Market(order) => {
+            TriggeredStopOrder::Market { order, .. } => {
                 self.exec_market(bar.ts, bar.c, order);
             }
         };
@@ -432,8 +789,10 @@ impl VirtualBroker {
             let e = Event::Order(e);
             self.queue.push_back(e);
         } else {
-            log::error!("WTF?! Action: {action:#?}");
-            unreachable!();
+            // ордер уже исполнен: с задержкой отмена может прийти после
+            // исполнения, как у реального брокера - отмена без эффекта,
+            // стратегия уже получила событие исполнения
+            log::warn!("VirtualBroker cancel not active order: {action}");
         }
     }
     fn cancel_limit(&mut self, order: LimitOrder) -> Option<Order> {
@@ -443,12 +802,13 @@ impl VirtualBroker {
             let posted = &self.limit_orders[i];
 
             if posted.broker_id() == order.broker_id() {
-                // if exist -> remove
-                self.limit_orders.remove(i);
-
-                // unwrap
-                let order = order.as_posted().unwrap();
-                let canceled = order.cancel();
+                // if exist -> remove, отменяется сохраненный ордер, он
+                // может быть уже частично исполнен
+                let canceled = match self.limit_orders.remove(i) {
+                    LimitOrder::Posted(o) => o.cancel(),
+                    LimitOrder::PartiallyFilled(o) => o.cancel(),
+                    _ => unreachable!(),
+                };
 
                 // wrap and return
                 return Some(Order::Limit(LimitOrder::Canceled(canceled)));
@@ -489,3 +849,37 @@ impl VirtualBroker {
         Some(Order::Stop(StopOrder::Canceled(canceled)))
     }
 }
+
+/// Бар, по которому проверяется ордер: остаток бара после прихода
+/// отложенного ордера, иначе весь бар.
+fn order_bar(arrived: &HashMap<String, f64>, bar: &Bar, id: &str) -> Bar {
+    match arrived.get(id) {
+        Some(part) => rest_of_bar(bar, *part),
+        None => *bar,
+    }
+}
+/// Лоты, доступные лимитному ордеру в баре: доля ratio от объема бара
+/// (volume в бумагах). Без ratio ордер исполняется целиком.
+fn fill_lots(volume: u64, lot: u32, ratio: Option<f64>) -> u32 {
+    match ratio {
+        Some(ratio) => {
+            let lots = (volume as f64 * ratio / lot as f64).floor();
+            lots.min(u32::MAX as f64) as u32
+        }
+        None => u32::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_lots_share_of_volume() {
+        assert_eq!(fill_lots(1000, 10, None), u32::MAX);
+        assert_eq!(fill_lots(1000, 10, Some(0.1)), 10);
+        assert_eq!(fill_lots(1000, 10, Some(0.05)), 5);
+        assert_eq!(fill_lots(99, 10, Some(1.0)), 9);
+        assert_eq!(fill_lots(0, 10, Some(0.5)), 0);
+    }
+}
@@ -0,0 +1,276 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta, Weekday};
+
+use avin_utils::{self as utils, AvinError, MSK_OFFSET, SessionCfg};
+
+/// Phase of trading day.
+///
+/// # ru
+/// Фаза торгового дня: закрыто, основная сессия, клиринг (или перерыв
+/// между основной и вечерней сессией), вечерняя сессия, закрытие -
+/// последние минуты дня, когда трейдер закрывает позиции.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionPhase {
+    Closed,
+    Main,
+    Clearing,
+    Evening,
+    Closing,
+}
+impl SessionPhase {
+    /// Strategies are evaluated only in trading phases.
+    ///
+    /// # ru
+    /// Стратегии работают только в основной и вечерней сессии.
+    pub fn is_trading(&self) -> bool {
+        matches!(self, Self::Main | Self::Evening)
+    }
+}
+impl std::fmt::Display for SessionPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "closed"),
+            Self::Main => write!(f, "main"),
+            Self::Clearing => write!(f, "clearing"),
+            Self::Evening => write!(f, "evening"),
+            Self::Closing => write!(f, "closing"),
+        }
+    }
+}
+
+/// Trading calendar of exchange, Moscow time.
+///
+/// # ru
+/// Торговый календарь биржи по московскому времени: основная сессия,
+/// клиринги, вечерняя сессия, выходные и праздники. По умолчанию -
+/// расписание MOEX. Трейдер по фазе календаря включает подписки и
+/// стратегии на открытии, ставит стратегии на паузу в клиринг и
+/// останавливает после закрытия.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradingCalendar {
+    main: Interval,
+    clearing: Vec<Interval>,
+    evening: Option<Interval>,
+    holidays: Vec<NaiveDate>,
+    flatten: Option<TimeDelta>,
+}
+impl Default for TradingCalendar {
+    fn default() -> Self {
+        Self {
+            main: (time(10, 0), time(18, 40)),
+            clearing: vec![(time(14, 0), time(14, 5))],
+            evening: Some((time(19, 5), time(23, 50))),
+            holidays: Vec::new(),
+            flatten: None,
+        }
+    }
+}
+impl TradingCalendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create from config.
+    ///
+    /// # ru
+    /// Создает календарь по конфигу, ошибка - неверный интервал или
+    /// дата.
+    pub fn from_cfg(cfg: &SessionCfg) -> Result<Self, AvinError> {
+        let mut calendar = Self {
+            main: interval(&cfg.main)?,
+            clearing: Vec::new(),
+            evening: None,
+            holidays: Vec::new(),
+            flatten: None,
+        };
+        for i in cfg.clearing.iter() {
+            calendar.clearing.push(interval(i)?);
+        }
+        if !cfg.evening.is_empty() {
+            calendar.evening = Some(interval(&cfg.evening)?);
+        }
+        for i in cfg.holidays.iter() {
+            let date =
+                NaiveDate::parse_from_str(i, "%Y-%m-%d").map_err(|_| {
+                    AvinError::InvalidValue(format!("session holiday {i}"))
+                })?;
+            calendar.add_holiday(date);
+        }
+        if cfg.flatten > 0 {
+            let before = TimeDelta::minutes(cfg.flatten as i64);
+            calendar.set_flatten(Some(before));
+        }
+
+        Ok(calendar)
+    }
+    pub fn add_holiday(&mut self, date: NaiveDate) {
+        self.holidays.push(date);
+    }
+    /// Set time before close to flatten positions, None - no flatten.
+    ///
+    /// # ru
+    /// Задает, за сколько до закрытия дня закрывать позиции, None -
+    /// позиции остаются.
+    pub fn set_flatten(&mut self, before: Option<TimeDelta>) {
+        self.flatten = before;
+    }
+    /// Is exchange open this day (Moscow date).
+    ///
+    /// # ru
+    /// Торговый ли день: не суббота, не воскресенье и не праздник.
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+            && !self.holidays.contains(&date)
+    }
+    /// Phase of trading day at ts.
+    ///
+    /// # ru
+    /// Фаза торгового дня в момент ts.
+    pub fn phase(&self, ts: i64) -> SessionPhase {
+        let dt = utils::dt(ts) + MSK_OFFSET;
+        if !self.is_trading_day(dt.date_naive()) {
+            return SessionPhase::Closed;
+        }
+
+        let t = dt.time();
+        let close = self.evening.map_or(self.main.1, |i| i.1);
+        if t < self.main.0 || t >= close {
+            return SessionPhase::Closed;
+        }
+        if let Some(before) = self.flatten
+            && t >= close - before
+        {
+            return SessionPhase::Closing;
+        }
+        if self.clearing.iter().any(|i| contains(i, t)) {
+            return SessionPhase::Clearing;
+        }
+        if t < self.main.1 {
+            return SessionPhase::Main;
+        }
+        if self.evening.is_some_and(|i| contains(&i, t)) {
+            return SessionPhase::Evening;
+        }
+
+        // перерыв между основной и вечерней сессией
+        SessionPhase::Clearing
+    }
+}
+
+/// Интервал времени [начало, конец).
+type Interval = (NaiveTime, NaiveTime);
+
+fn time(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+fn contains(i: &Interval, t: NaiveTime) -> bool {
+    i.0 <= t && t < i.1
+}
+/// Интервал из строки "10:00-18:40".
+fn interval(s: &str) -> Result<Interval, AvinError> {
+    let err = || AvinError::InvalidValue(format!("session interval {s}"));
+    let (begin, end) = s.split_once('-').ok_or_else(err)?;
+    let begin = NaiveTime::parse_from_str(begin.trim(), "%H:%M")
+        .map_err(|_| err())?;
+    let end =
+        NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| err())?;
+    if begin >= end {
+        return Err(err());
+    }
+
+    Ok((begin, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    // московское время -> ts
+    fn ts(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> i64 {
+        let dt = Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap();
+        utils::ts(dt - MSK_OFFSET)
+    }
+
+    #[test]
+    fn moex() {
+        let calendar = TradingCalendar::new();
+
+        // 2026-10-15 четверг
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 9, 59)),
+            SessionPhase::Closed
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 10, 0)),
+            SessionPhase::Main
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 14, 2)),
+            SessionPhase::Clearing
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 14, 5)),
+            SessionPhase::Main
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 18, 50)),
+            SessionPhase::Clearing
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 19, 5)),
+            SessionPhase::Evening
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 23, 50)),
+            SessionPhase::Closed
+        );
+
+        // суббота
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 17, 12, 0)),
+            SessionPhase::Closed
+        );
+    }
+    #[test]
+    fn from_cfg() {
+        let cfg = SessionCfg {
+            enabled: true,
+            evening: String::new(),
+            holidays: vec!["2026-10-16".to_string()],
+            flatten: 5,
+            ..Default::default()
+        };
+        let calendar = TradingCalendar::from_cfg(&cfg).unwrap();
+
+        // без вечерней сессии день закрывается в 18:40
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 18, 34)),
+            SessionPhase::Main
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 18, 35)),
+            SessionPhase::Closing
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 15, 19, 10)),
+            SessionPhase::Closed
+        );
+        assert_eq!(
+            calendar.phase(ts(2026, 10, 16, 12, 0)),
+            SessionPhase::Closed
+        );
+
+        let cfg = SessionCfg {
+            main: "18:40-10:00".to_string(),
+            ..Default::default()
+        };
+        assert!(TradingCalendar::from_cfg(&cfg).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use avin_core::ClosedTrade;
+use avin_strategy::StrategyMeta;
+use avin_utils::{self as utils, Cmd, MSK_OFFSET};
+
+const HEADER: &str = "close_dt,strategy,version,params_hash,params,\
+    ticker,kind,result";
+
+/// Live trade journal with strategy versions.
+///
+/// # ru
+/// Журнал сделок трейдера: каждая закрытая сделка пишется строкой CSV
+/// вместе с версией и хэшем параметров стратегии, которая ее сделала,
+/// см. [`StrategyMeta`]. По нему результат можно разложить по точным
+/// версиям стратегий и сравнить с манифестами тестов. Без пути в
+/// конфиге журнал не пишется.
+#[derive(Debug, Default)]
+pub struct TradeJournal {
+    path: Option<PathBuf>,
+}
+impl TradeJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create from config path relative to root, empty - no journal.
+    ///
+    /// # ru
+    /// Создает по пути из конфига относительно root, пустой путь -
+    /// журнал не пишется.
+    pub fn from_cfg(path: &str, root: &Path) -> Self {
+        let mut journal = Self::new();
+        if !path.is_empty() {
+            journal.set_path(&root.join(path));
+        }
+
+        journal
+    }
+    pub fn set_path(&mut self, path: &Path) {
+        self.path = Some(path.to_path_buf());
+    }
+    /// Record closed trade, meta - strategy of trade if known.
+    ///
+    /// # ru
+    /// Записывает закрытую сделку. meta - паспорт стратегии сделки,
+    /// None - стратегия уже не запущена, пишется только имя из сделки.
+    pub fn record(&self, trade: &ClosedTrade, meta: Option<&StrategyMeta>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(err) = append(path, &line(trade, meta)) {
+            log::error!("Trade journal {}: {err}", path.display());
+        }
+    }
+}
+
+/// Строка журнала: московское время закрытия, стратегия, сделка.
+fn line(trade: &ClosedTrade, meta: Option<&StrategyMeta>) -> String {
+    let dt = utils::dt(trade.close_ts()) + MSK_OFFSET;
+    let (version, params_hash, params) = match meta {
+        Some(m) => (m.version.as_str(), m.params_hash.as_str(), &m.params),
+        None => ("", "", &String::new()),
+    };
+
+    format!(
+        "{},{},{},{},{},{},{},{:.2}",
+        dt.format("%Y-%m-%d %H:%M:%S"),
+        trade.strategy,
+        version,
+        params_hash,
+        params,
+        trade.iid.ticker(),
+        trade.kind.to_str(),
+        trade.result(),
+    )
+}
+fn append(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent()
+        && !Cmd::is_exist(dir)
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+    let is_new = !path.exists();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    if is_new {
+        writeln!(file, "{HEADER}")?;
+    }
+
+    writeln!(file, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{
+        Direction, LimitOrder, Order, Trade, TradeKind, Transaction,
+    };
+    use avin_strategy::ParamSet;
+
+    use super::*;
+
+    fn closed() -> ClosedTrade {
+        let ts = 1_700_000_000_000_000_000;
+        let trade = Trade::new(ts, "MaCross", TradeKind::Long, iid());
+
+        let mut orders = Vec::new();
+        for (direction, price, ts) in [
+            (Direction::Buy, 280.0, ts),
+            (Direction::Sell, 285.0, ts + 60_000_000_000),
+        ] {
+            let order = LimitOrder::new(direction, 1, price);
+            let mut order = order.post("broker_id");
+            order.add_transaction(Transaction::new(10, price));
+            let order = order.fill(ts, 0.0);
+            orders.push(Order::Limit(LimitOrder::Filled(order)));
+        }
+        let mut orders = orders.into_iter();
+        let mut trade = trade.open(orders.next().unwrap());
+        trade.add_order(orders.next().unwrap());
+
+        trade.close()
+    }
+
+    #[test]
+    fn record() {
+        let path = std::env::temp_dir().join("avin_trade_journal_test.csv");
+        let _ = std::fs::remove_file(&path);
+
+        let mut journal = TradeJournal::new();
+        journal.set_path(&path);
+        let trade = closed();
+        let mut params = ParamSet::new();
+        params.set("fast", 10.0);
+        let meta = StrategyMeta::new("MaCross", "2.1", &params);
+        journal.record(&trade, Some(&meta));
+        journal.record(&trade, None);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "2023-11-15 01:14:20,MaCross,2.1,{},fast=10,SBER,L,50.00",
+                params.hash()
+            )
+        );
+        assert!(lines[2].starts_with("2023-11-15 01:14:20,MaCross,,,,SBER"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -5,8 +5,22 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
+mod calendar;
+mod journal;
+mod notify;
+mod reconcile;
+mod risk;
+mod subscriptions;
 mod trader;
 mod work;
 
-pub use trader::Trader;
-pub use work::Work;
+pub use calendar::{SessionPhase, TradingCalendar};
+pub use journal::TradeJournal;
+pub use notify::Notifier;
+pub use reconcile::{
+    OrphanPolicy, RECONCILE_OWNER, Reconciler, Reconciliation,
+};
+pub use risk::{Breach, RISK_OWNER, RiskEvent, RiskManager, RiskRules};
+pub use subscriptions::Subscriptions;
+pub use trader::{StrategyFactory, Trader};
+pub use work::{Work, WorkCmd, WorkFault};
@@ -0,0 +1,128 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tokio::runtime::Handle;
+
+use avin_core::{Alert, AlertSinks, NotifyAction, NotifyLevel};
+use avin_utils::{self as utils, Cmd, MSK_OFFSET, NotifyCfg};
+
+/// Delivery of strategy notifications.
+///
+/// # ru
+/// Доставка уведомлений стратегий. Каждое уведомление пишется в лог
+/// программы, а если заданы в конфиге - в файл (его показывает GUI
+/// терминал) и в каналы доставки [`AlertSinks`]: Telegram, вебхук,
+/// почту, в каждый канал только не ниже его уровня.
+#[derive(Debug, Default)]
+pub struct Notifier {
+    log: Option<PathBuf>,
+    sinks: AlertSinks,
+}
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create from config, log path is relative to root.
+    ///
+    /// # ru
+    /// Создает по конфигу, путь файла уведомлений относительно root,
+    /// rt - рантайм tokio для отправки в каналы доставки.
+    pub fn from_cfg(cfg: &NotifyCfg, root: &Path, rt: &Handle) -> Self {
+        let mut notifier = Self::new();
+        if !cfg.log.is_empty() {
+            notifier.set_log(&root.join(&cfg.log));
+        }
+        notifier.set_sinks(AlertSinks::from_cfg(&cfg.sinks, rt).unwrap());
+
+        notifier
+    }
+    pub fn set_log(&mut self, path: &Path) {
+        self.log = Some(path.to_path_buf());
+    }
+    pub fn set_sinks(&mut self, sinks: AlertSinks) {
+        self.sinks = sinks;
+    }
+    /// Deliver notification, ts - time of trader.
+    ///
+    /// # ru
+    /// Доставляет уведомление. Сетевые каналы отправляют в отдельной
+    /// задаче tokio и не задерживают главный цикл трейдера, ошибки
+    /// доставки только пишутся в лог.
+    pub fn notify(&self, a: &NotifyAction, ts: i64) {
+        match a.level {
+            NotifyLevel::Info => log::info!(":: {a}"),
+            NotifyLevel::Warning | NotifyLevel::Alert => log::warn!(":: {a}"),
+        }
+
+        if let Some(path) = &self.log
+            && let Err(err) = append(path, &line(a, ts))
+        {
+            log::error!("Notify log {}: {err}", path.display());
+        }
+
+        let title = format!("[{}] {}", a.level, a.strategy);
+        let alert = Alert::new(&a.strategy, a.level, &title, &a.message, ts);
+        self.sinks.send(&alert);
+    }
+}
+
+/// Строка файла уведомлений: московское время, важность, стратегия.
+fn line(a: &NotifyAction, ts: i64) -> String {
+    let dt = utils::dt(ts) + MSK_OFFSET;
+
+    format!(
+        "{} [{}] {}: {}",
+        dt.format("%Y-%m-%d %H:%M:%S"),
+        a.level,
+        a.strategy,
+        a.message
+    )
+}
+fn append(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent()
+        && !Cmd::is_exist(dir)
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    writeln!(file, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_log() {
+        let path = std::env::temp_dir().join("avin_notify_test.log");
+        let _ = std::fs::remove_file(&path);
+
+        let mut notifier = Notifier::new();
+        notifier.set_log(&path);
+        let a = NotifyAction::new(
+            "MaCross",
+            NotifyLevel::Info,
+            "entered long SBER @ 280, stop 276",
+        );
+        notifier.notify(&a, 1_700_000_000_000_000_000);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            text,
+            "2023-11-15 01:13:20 [info] MaCross: \
+            entered long SBER @ 280, stop 276\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,525 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::path::{Path, PathBuf};
+
+use bitcode::{Decode, Encode};
+
+use avin_core::{Account, Direction, Iid, Operation, Order, OrderEvent};
+use avin_utils::{AvinError, Cmd, ReconcileCfg};
+
+/// Owner of orders canceled by startup reconciliation.
+///
+/// # ru
+/// Владелец отмен, которыми трейдер при запуске снимает ордера-сироты.
+/// Таких стратегий нет, события по этим ордерам стратегиям не приходят.
+pub const RECONCILE_OWNER: &str = "Reconciler";
+
+/// What to do with unknown broker orders at start.
+///
+/// # ru
+/// Что делать при запуске с ордерами брокера, которых нет в состоянии
+/// трейдера (сироты): Adopt - отдать стратегиям инструмента, они
+/// забирают совпавшие со своими намерениями, см.
+/// [`avin_strategy::Intents`]; Cancel - отменить у брокера; Keep -
+/// оставить у брокера, стратегиям не отдавать.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    #[default]
+    Adopt,
+    Cancel,
+    Keep,
+}
+impl TryFrom<&str> for OrphanPolicy {
+    type Error = AvinError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "" | "adopt" => Ok(Self::Adopt),
+            "cancel" => Ok(Self::Cancel),
+            "keep" => Ok(Self::Keep),
+            _ => Err(AvinError::InvalidValue(format!(
+                "orphans policy '{value}', expected adopt, cancel, keep"
+            ))),
+        }
+    }
+}
+impl std::fmt::Display for OrphanPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Adopt => write!(f, "adopt"),
+            Self::Cancel => write!(f, "cancel"),
+            Self::Keep => write!(f, "keep"),
+        }
+    }
+}
+
+/// Result of reconciliation of account and instrument.
+///
+/// # ru
+/// Итог сверки счета по инструменту: ордера брокера, известные по
+/// состоянию, с их стратегиями; сироты; ордера из состояния, которых
+/// у брокера уже нет (исполнены или отменены, пока трейдер не
+/// работал); позиция брокера и ожидаемая по состоянию; операции
+/// брокера с прошлого запуска.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation {
+    pub owned: Vec<(String, Order)>,
+    pub orphans: Vec<Order>,
+    pub gone: Vec<(String, Order)>,
+    pub position: Option<i64>,
+    pub expected: Option<i64>,
+    pub operations: Vec<Operation>,
+    pub policy: OrphanPolicy,
+}
+impl Reconciliation {
+    /// Broker orders for strategy reconcile.
+    ///
+    /// # ru
+    /// Ордера брокера для [`avin_strategy::Strategy::reconcile`]
+    /// стратегии owner: ее собственные и, при Adopt, сироты.
+    pub fn orders_of(&self, owner: &str) -> Vec<Order> {
+        let mut orders: Vec<Order> = self
+            .owned
+            .iter()
+            .filter(|(o, _)| o == owner)
+            .map(|(_, order)| order.clone())
+            .collect();
+        if self.policy == OrphanPolicy::Adopt {
+            orders.extend(self.orphans.iter().cloned());
+        }
+
+        orders
+    }
+    /// Orphans to cancel at broker.
+    ///
+    /// # ru
+    /// Сироты, которые нужно отменить у брокера, только при Cancel.
+    pub fn to_cancel(&self) -> &[Order] {
+        match self.policy {
+            OrphanPolicy::Cancel => &self.orphans,
+            _ => &[],
+        }
+    }
+    /// Broker position differs from expected.
+    ///
+    /// # ru
+    /// Позиция брокера не совпадает с ожидаемой по состоянию. Без
+    /// состояния или без ответа брокера сравнивать не с чем - false.
+    pub fn is_position_mismatch(&self) -> bool {
+        match (self.position, self.expected) {
+            (Some(position), Some(expected)) => position != expected,
+            _ => false,
+        }
+    }
+}
+impl std::fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let opt =
+            |v: Option<i64>| v.map_or("?".to_string(), |v| v.to_string());
+        write!(
+            f,
+            "Reconciliation owned={} orphans={} ({}) gone={} position={} \
+            expected={} operations={}",
+            self.owned.len(),
+            self.orphans.len(),
+            self.policy,
+            self.gone.len(),
+            opt(self.position),
+            opt(self.expected),
+            self.operations.len()
+        )
+    }
+}
+
+/// Startup reconciliation of trader state with broker.
+///
+/// # ru
+/// Сверка трейдера с брокером при запуске. Трейдер ведет состояние:
+/// активные лимитные и стоп ордера стратегий (кто владелец) и
+/// ожидаемые позиции по исполненным ордерам, и сохраняет его в файл на
+/// каждом событии ордера. При запуске, до старта стратегий, для
+/// каждого счета и инструмента работы он запрашивает у брокера
+/// позицию, активные ордера и операции с прошлого запуска и сверяет их
+/// с состоянием, см. [`Reconciliation`]. Известные ордера получает их
+/// стратегия, сироты - по [`OrphanPolicy`], расхождение позиции пишется
+/// в лог, дальше состояние верит брокеру. Так после падения у брокера
+/// не остаются ордера, о которых никто не знает.
+///
+/// Без файла состояния все ордера брокера - сироты, с политикой Adopt
+/// стратегии получают их все, как до появления сверки.
+#[derive(Debug, Default)]
+pub struct Reconciler {
+    policy: OrphanPolicy,
+    path: Option<PathBuf>,
+    state: State,
+    /// Состояние было - загружено из файла или уже сохранялось.
+    loaded: bool,
+}
+impl Reconciler {
+    pub fn new(policy: OrphanPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+    /// Create from config, state path is relative to root.
+    ///
+    /// # ru
+    /// Создает по конфигу, путь файла состояния относительно root.
+    /// Неизвестная политика - ошибка в лог и Adopt, нечитаемый файл -
+    /// ошибка в лог и пустое состояние.
+    pub fn from_cfg(cfg: &ReconcileCfg, root: &Path) -> Self {
+        let policy = OrphanPolicy::try_from(cfg.orphans.as_str())
+            .unwrap_or_else(|e| {
+                log::error!("Reconciler: {e}, use adopt");
+                OrphanPolicy::Adopt
+            });
+        let mut reconciler = Self::new(policy);
+        if !cfg.state.is_empty() {
+            let path = root.join(&cfg.state);
+            if let Err(e) = reconciler.set_state(&path) {
+                log::error!("Reconciler: {e}, start with empty state");
+            }
+        }
+
+        reconciler
+    }
+    /// Set state file, load it if exists.
+    ///
+    /// # ru
+    /// Задает файл состояния и загружает его, если он есть. Путь
+    /// задается даже при ошибке загрузки, состояние тогда пустое.
+    pub fn set_state(&mut self, path: &Path) -> Result<(), AvinError> {
+        self.path = Some(path.to_path_buf());
+        self.state = State::default();
+        self.loaded = false;
+        if !Cmd::is_exist(path) {
+            return Ok(());
+        }
+
+        let bytes = Cmd::read_bin(path)?;
+        self.state = bitcode::decode(&bytes).map_err(|e| {
+            AvinError::InvalidValue(format!("trader state: {e}"))
+        })?;
+        self.loaded = true;
+
+        Ok(())
+    }
+    pub fn policy(&self) -> OrphanPolicy {
+        self.policy
+    }
+    /// Time of last state save, 0 - no state.
+    ///
+    /// # ru
+    /// Время последнего сохранения состояния, 0 - состояния нет. С
+    /// него трейдер запрашивает операции брокера.
+    pub fn ts(&self) -> i64 {
+        self.state.ts
+    }
+    /// Expected position of instrument in units.
+    ///
+    /// # ru
+    /// Ожидаемая позиция счета по figi в штуках.
+    pub fn position(&self, account: &str, figi: &str) -> i64 {
+        self.state
+            .positions
+            .iter()
+            .find(|(a, f, _)| a == account && f == figi)
+            .map_or(0, |(_, _, pos)| *pos)
+    }
+    /// Reconcile state with broker data of account and instrument.
+    ///
+    /// # ru
+    /// Сверяет состояние с данными брокера по счету и инструменту:
+    /// активными ордерами, позицией (None - брокер не ответил) и
+    /// операциями. Ушедшие ордера удаляются из состояния, сироты при
+    /// Adopt и Keep записываются без владельца, позиция становится
+    /// брокерской. Состояние сохраняется.
+    pub fn reconcile(
+        &mut self,
+        account: &Account,
+        iid: &Iid,
+        orders: Vec<Order>,
+        position: Option<i64>,
+        operations: Vec<Operation>,
+        ts: i64,
+    ) -> Reconciliation {
+        let account = account.name();
+        let figi = iid.figi();
+        let expected = self.loaded.then(|| self.position(account, figi));
+
+        let mut known = self.state.take(account, figi);
+        let mut owned = Vec::new();
+        let mut orphans = Vec::new();
+        for order in orders.into_iter().filter(is_active) {
+            let i = known.iter().position(|o| same_id(&o.order, &order));
+            match i {
+                Some(i) => owned.push((known.remove(i).owner, order)),
+                None => orphans.push(order),
+            }
+        }
+        let gone = known.into_iter().map(|o| (o.owner, o.order)).collect();
+
+        // состояние после сверки - то, что сейчас у брокера
+        for (owner, order) in owned.iter() {
+            self.state
+                .orders
+                .push(StateOrder::new(account, figi, owner, order));
+        }
+        if self.policy != OrphanPolicy::Cancel {
+            for order in orphans.iter() {
+                self.state
+                    .orders
+                    .push(StateOrder::new(account, figi, "", order));
+            }
+        }
+        if let Some(position) = position {
+            self.state.set_position(account, figi, position);
+        }
+        self.save(ts);
+
+        Reconciliation {
+            owned,
+            orphans,
+            gone,
+            position,
+            expected,
+            operations,
+            policy: self.policy,
+        }
+    }
+    /// Update state by broker order event.
+    ///
+    /// # ru
+    /// Обновляет состояние по событию ордера: активный ордер
+    /// запоминается с владельцем, исполненный меняет ожидаемую позицию,
+    /// завершенный удаляется. Состояние сохраняется.
+    pub fn order_event(&mut self, e: &OrderEvent, ts: i64) {
+        let account = e.account.name();
+        let figi = e.iid.figi();
+        let id = e.order.broker_id();
+
+        self.state
+            .orders
+            .retain(|o| !(id.is_some() && o.order.broker_id() == id));
+        if is_active(&e.order) {
+            let order = StateOrder::new(account, figi, &e.owner, &e.order);
+            self.state.orders.push(order);
+        }
+        if let Some(operation) = filled(&e.order) {
+            let quantity = operation.quantity as i64;
+            let quantity = match e.order.direction() {
+                Direction::Buy => quantity,
+                Direction::Sell => -quantity,
+            };
+            let position = self.position(account, figi) + quantity;
+            self.state.set_position(account, figi, position);
+        }
+        self.save(ts);
+    }
+
+    // private
+    fn save(&mut self, ts: i64) {
+        self.state.ts = ts;
+        self.loaded = true;
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = Cmd::write_bin(&bitcode::encode(&self.state), path) {
+            log::error!("Reconciler save {}: {e}", path.display());
+        }
+    }
+}
+
+/// Сохраняемое состояние трейдера: время сохранения, активные ордера
+/// с владельцами и позиции в штуках по (счет, figi).
+#[derive(Debug, Default, Clone, PartialEq, Encode, Decode)]
+struct State {
+    ts: i64,
+    orders: Vec<StateOrder>,
+    positions: Vec<(String, String, i64)>,
+}
+impl State {
+    /// Забирает из состояния ордера счета по инструменту.
+    fn take(&mut self, account: &str, figi: &str) -> Vec<StateOrder> {
+        let (taken, rest) = std::mem::take(&mut self.orders)
+            .into_iter()
+            .partition(|o| o.account == account && o.figi == figi);
+        self.orders = rest;
+
+        taken
+    }
+    fn set_position(&mut self, account: &str, figi: &str, position: i64) {
+        self.positions
+            .retain(|(a, f, _)| !(a == account && f == figi));
+        if position != 0 {
+            let (a, f) = (account.to_string(), figi.to_string());
+            self.positions.push((a, f, position));
+        }
+    }
+}
+
+/// Активный ордер состояния: счет, figi, владелец (пустой - сирота,
+/// оставленный у брокера) и ордер.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+struct StateOrder {
+    account: String,
+    figi: String,
+    owner: String,
+    order: Order,
+}
+impl StateOrder {
+    fn new(account: &str, figi: &str, owner: &str, order: &Order) -> Self {
+        Self {
+            account: account.to_string(),
+            figi: figi.to_string(),
+            owner: owner.to_string(),
+            order: order.clone(),
+        }
+    }
+}
+
+fn is_active(order: &Order) -> bool {
+    match order {
+        Order::Limit(o) => o.is_posted() || o.is_partially_filled(),
+        Order::Stop(o) => o.is_posted(),
+        Order::Market(_) => false,
+    }
+}
+fn same_id(a: &Order, b: &Order) -> bool {
+    a.broker_id().is_some() && a.broker_id() == b.broker_id()
+}
+/// Операция исполненного ордера, стоп ордера не исполняются.
+fn filled(order: &Order) -> Option<&Operation> {
+    match order {
+        Order::Stop(_) => None,
+        order if order.is_filled() => order.operation(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::iid;
+    use avin_core::{LimitOrder, StopOrder, StopOrderKind, Transaction};
+
+    use super::*;
+
+    const TS: i64 = 1_700_000_000_000_000_000;
+
+    fn account() -> Account {
+        Account::new("Agni", "Agni_ID")
+    }
+    fn limit(id: &str, price: f64) -> Order {
+        let order = LimitOrder::new(Direction::Buy, 1, price).post(id);
+
+        Order::Limit(LimitOrder::Posted(order))
+    }
+    fn stop(id: &str, price: f64) -> Order {
+        let order = StopOrder::new(
+            StopOrderKind::StopLoss,
+            Direction::Sell,
+            1,
+            price,
+            None,
+        )
+        .post(id);
+
+        Order::Stop(StopOrder::Posted(order))
+    }
+    fn event(owner: &str, order: Order) -> OrderEvent {
+        OrderEvent::new(account(), iid(), owner.to_string(), order)
+    }
+
+    #[test]
+    fn policy() {
+        assert_eq!(OrphanPolicy::try_from("").unwrap(), OrphanPolicy::Adopt);
+        assert_eq!(
+            OrphanPolicy::try_from("cancel").unwrap(),
+            OrphanPolicy::Cancel
+        );
+        assert_eq!(
+            OrphanPolicy::try_from("keep").unwrap(),
+            OrphanPolicy::Keep
+        );
+        assert!(OrphanPolicy::try_from("drop").is_err());
+    }
+
+    #[test]
+    fn without_state() {
+        let mut r = Reconciler::new(OrphanPolicy::Adopt);
+        let orders = vec![limit("1", 270.0), stop("2", 260.0)];
+        let result =
+            r.reconcile(&account(), &iid(), orders, Some(10), vec![], TS);
+
+        // состояния нет: все ордера - сироты, позицию не с чем сравнить
+        assert!(result.owned.is_empty());
+        assert_eq!(result.orphans.len(), 2);
+        assert_eq!(result.expected, None);
+        assert!(!result.is_position_mismatch());
+        assert_eq!(result.orders_of("MaCross").len(), 2);
+        assert!(result.to_cancel().is_empty());
+        assert_eq!(r.position("Agni", iid().figi()), 10);
+        assert_eq!(r.ts(), TS);
+    }
+
+    #[test]
+    fn with_state() {
+        let path = std::env::temp_dir().join("avin_trader_state_test.bin");
+        let _ = std::fs::remove_file(&path);
+
+        let mut r = Reconciler::new(OrphanPolicy::Cancel);
+        r.set_state(&path).unwrap();
+        r.order_event(&event("MaCross", limit("1", 270.0)), TS);
+        r.order_event(&event("Grid", limit("2", 265.0)), TS);
+        r.order_event(&event("MaCross", stop("3", 260.0)), TS);
+        // исполненная лимитка меняет позицию и уходит из ордеров
+        let order = match limit("2", 265.0) {
+            Order::Limit(LimitOrder::Posted(mut o)) => {
+                o.add_transaction(Transaction::new(10, 265.0));
+                Order::Limit(LimitOrder::Filled(o.fill(TS, 0.0)))
+            }
+            _ => unreachable!(),
+        };
+        r.order_event(&event("Grid", order), TS + 1);
+        assert_eq!(r.position("Agni", iid().figi()), 10);
+
+        // перезапуск: у брокера ордер 1, неизвестный 4, стопа 3 нет, а
+        // позиция 20 - что-то исполнилось без трейдера
+        let mut r = Reconciler::new(OrphanPolicy::Cancel);
+        r.set_state(&path).unwrap();
+        assert_eq!(r.ts(), TS + 1);
+        let orders = vec![limit("1", 270.0), limit("4", 250.0)];
+        let result =
+            r.reconcile(&account(), &iid(), orders, Some(20), vec![], TS + 2);
+
+        assert_eq!(result.owned.len(), 1);
+        assert_eq!(result.owned[0].0, "MaCross");
+        assert_eq!(result.orphans, vec![limit("4", 250.0)]);
+        assert_eq!(result.gone.len(), 1);
+        assert_eq!(result.gone[0].1, stop("3", 260.0));
+        assert_eq!(result.expected, Some(10));
+        assert!(result.is_position_mismatch());
+        assert_eq!(result.orders_of("MaCross"), vec![limit("1", 270.0)]);
+        assert!(result.orders_of("Grid").is_empty());
+        assert_eq!(result.to_cancel(), &[limit("4", 250.0)]);
+
+        // состояние после сверки верит брокеру
+        let mut r = Reconciler::new(OrphanPolicy::Keep);
+        r.set_state(&path).unwrap();
+        assert_eq!(r.position("Agni", iid().figi()), 20);
+        let orders = vec![limit("1", 270.0)];
+        let result =
+            r.reconcile(&account(), &iid(), orders, None, vec![], TS + 3);
+        assert_eq!(result.owned.len(), 1);
+        assert!(result.orphans.is_empty() && result.gone.is_empty());
+        assert!(!result.is_position_mismatch());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
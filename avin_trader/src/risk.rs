@@ -0,0 +1,1098 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use avin_core::{
+    Account, Direction, Event, Iid, LimitOrder, MarketOrder, Order,
+    OrderAction, OrderEvent, StopOrder,
+};
+use avin_utils::{self as utils, Cmd, MSK_OFFSET, RiskCfg, RiskRulesCfg};
+
+/// Owner of orders that close positions on kill switch.
+///
+/// # ru
+/// Владелец ордеров, которыми риск менеджер закрывает позиции. Таких
+/// стратегий нет, события по этим ордерам стратегиям не приходят.
+pub const RISK_OWNER: &str = "RiskManager";
+
+const MINUTE: i64 = 60_000_000_000;
+
+/// Limits of risk manager, None - no limit.
+///
+/// # ru
+/// Лимиты риск менеджера, None - без ограничения. Убыток за день и
+/// просадка считаются по закрытым трейдам, день - московский.
+/// Позиция по инструменту - в штуках, отклонение цены лимитного или
+/// стоп ордера от последней цены - в процентах.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RiskRules {
+    pub max_daily_loss: Option<f64>,
+    pub max_drawdown: Option<f64>,
+    pub max_position_value: Option<f64>,
+    pub max_orders_per_minute: Option<u32>,
+    pub max_order_value: Option<f64>,
+    pub max_position: Option<u32>,
+    pub max_price_deviation: Option<f64>,
+}
+impl From<&RiskRulesCfg> for RiskRules {
+    fn from(cfg: &RiskRulesCfg) -> Self {
+        let limit = |value: f64| (value > 0.0).then_some(value);
+        let count = |value: u32| (value > 0).then_some(value);
+
+        Self {
+            max_daily_loss: limit(cfg.max_daily_loss),
+            max_drawdown: limit(cfg.max_drawdown),
+            max_position_value: limit(cfg.max_position_value),
+            max_orders_per_minute: count(cfg.max_orders_per_minute),
+            max_order_value: limit(cfg.max_order_value),
+            max_position: count(cfg.max_position),
+            max_price_deviation: limit(cfg.max_price_deviation),
+        }
+    }
+}
+
+/// Broken rule.
+///
+/// # ru
+/// Нарушенное правило: значение и лимит.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breach {
+    DailyLoss { loss: f64, limit: f64 },
+    Drawdown { drawdown: f64, limit: f64 },
+    PositionValue { value: f64, limit: f64 },
+    OrderRate { count: u32, limit: u32 },
+    OrderValue { value: f64, limit: f64 },
+    Position { quantity: i64, limit: u32 },
+    PriceDeviation { price: f64, last: f64, limit: f64 },
+    Restricted { ticker: String },
+}
+impl std::fmt::Display for Breach {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::DailyLoss { loss, limit } => {
+                write!(f, "daily loss {loss:.2} > {limit:.2}")
+            }
+            Self::Drawdown { drawdown, limit } => {
+                write!(f, "drawdown {drawdown:.2} > {limit:.2}")
+            }
+            Self::PositionValue { value, limit } => {
+                write!(f, "position value {value:.2} > {limit:.2}")
+            }
+            Self::OrderRate { count, limit } => {
+                write!(f, "orders per minute {count} >= {limit}")
+            }
+            Self::OrderValue { value, limit } => {
+                write!(f, "order value {value:.2} > {limit:.2}")
+            }
+            Self::Position { quantity, limit } => {
+                write!(f, "position {quantity} > {limit}")
+            }
+            Self::PriceDeviation { price, last, limit } => {
+                let deviation = (price - last).abs() / last * 100.0;
+                write!(
+                    f,
+                    "price {price} vs last {last}: {deviation:.2}% > \
+                    {limit:.2}%"
+                )
+            }
+            Self::Restricted { ticker } => {
+                write!(f, "{ticker} is restricted")
+            }
+        }
+    }
+}
+
+/// Event of risk manager.
+///
+/// # ru
+/// Событие риск менеджера: время, стратегия (None - общие правила
+/// всех стратегий), нарушение и сработал ли аварийный выключатель.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskEvent {
+    pub ts: i64,
+    pub strategy: Option<String>,
+    pub breach: Breach,
+    pub kill: bool,
+}
+impl std::fmt::Display for RiskEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let dt = utils::dt(self.ts) + MSK_OFFSET;
+        let strategy = self.strategy.as_deref().unwrap_or("all");
+        let action = if self.kill { "kill switch" } else { "rejected" };
+        write!(
+            f,
+            "{} {strategy}: {} - {action}",
+            dt.format("%Y-%m-%d %H:%M:%S"),
+            self.breach
+        )
+    }
+}
+
+/// Risk manager with kill switch.
+///
+/// # ru
+/// Риск менеджер: проверяет ордера стратегий перед отправкой брокеру.
+/// Правила задаются общие для всех стратегий и отдельно для стратегии.
+///
+/// Убыток за день или просадка больше лимита включает аварийный
+/// выключатель: новые ордера стратегии (или всех стратегий, если
+/// нарушено общее правило) блокируются до перезапуска, ордера на
+/// уменьшение позиции проходят. С flatten позиции сразу закрываются
+/// рыночными ордерами от имени [`RISK_OWNER`].
+///
+/// Проверки перед отправкой каждого ордера отклоняют только этот
+/// ордер: запрещенный инструмент, цена лимитного или стоп ордера
+/// далеко от последней (защита от ошибки в цене), слишком большой
+/// ордер, слишком большая позиция по инструменту (в штуках или по
+/// стоимости по последней цене), слишком много ордеров за минуту.
+/// Отказ стратегия получает как событие отклоненного ордера. Ордер
+/// на уменьшение позиции не проверяется на запрет инструмента,
+/// стоимость и размер позиции.
+///
+/// События пишутся в лог программы и в файл, если задан
+/// [`RiskManager::set_log`], его показывает GUI терминала.
+#[derive(Debug, Default)]
+pub struct RiskManager {
+    global: Limits,
+    strategys: HashMap<String, Limits>,
+    flatten: bool,
+    /// Запрещенные инструменты: тикер, figi или iid, в нижнем регистре.
+    restricted: Vec<String>,
+    log: Option<PathBuf>,
+    /// Позиции в штуках по (стратегия, figi).
+    positions: HashMap<(String, String), Position>,
+    /// Уже учтенное исполнение активных ордеров в штуках по broker_id.
+    executed: HashMap<String, u32>,
+    prices: HashMap<String, f64>,
+    events: Vec<RiskEvent>,
+}
+impl RiskManager {
+    pub fn new(rules: RiskRules) -> Self {
+        Self {
+            global: Limits::new(rules),
+            ..Default::default()
+        }
+    }
+    /// Create from config, log path is relative to root.
+    ///
+    /// # ru
+    /// Создает риск менеджер по конфигу, путь лога относительно root.
+    pub fn from_cfg(cfg: &RiskCfg, root: &Path) -> Self {
+        let mut risk = Self::new(RiskRules::from(&cfg.rules));
+        risk.set_flatten(cfg.flatten);
+        risk.set_restricted(&cfg.restricted);
+        if !cfg.log.is_empty() {
+            risk.set_log(&root.join(&cfg.log));
+        }
+        for i in cfg.strategy.iter() {
+            risk.set_rules(&i.name, RiskRules::from(&i.rules));
+        }
+
+        risk
+    }
+    /// Set rules of one strategy.
+    ///
+    /// # ru
+    /// Задает правила отдельной стратегии, они действуют вместе с
+    /// общими.
+    pub fn set_rules(&mut self, strategy: &str, rules: RiskRules) {
+        self.strategys
+            .insert(strategy.to_string(), Limits::new(rules));
+    }
+    pub fn set_flatten(&mut self, flatten: bool) {
+        self.flatten = flatten;
+    }
+    /// Set restricted instruments: ticker, figi or iid.
+    ///
+    /// # ru
+    /// Задает запрещенные инструменты: тикер, figi или iid
+    /// ("moex_share_sber"), регистр не важен. Новые позиции по ним не
+    /// открываются, закрыть имеющуюся можно.
+    pub fn set_restricted(&mut self, list: &[String]) {
+        self.restricted = list.iter().map(|i| i.to_lowercase()).collect();
+    }
+    /// Is instrument restricted.
+    ///
+    /// # ru
+    /// Запрещен ли инструмент.
+    pub fn is_restricted(&self, iid: &Iid) -> bool {
+        let keys = [
+            iid.ticker().to_lowercase(),
+            iid.figi().to_lowercase(),
+            iid.to_string().to_lowercase(),
+        ];
+
+        self.restricted.iter().any(|i| keys.contains(i))
+    }
+    pub fn set_log(&mut self, path: &Path) {
+        self.log = Some(path.to_path_buf());
+    }
+    pub fn events(&self) -> &[RiskEvent] {
+        &self.events
+    }
+    /// Is kill switch of strategy on.
+    ///
+    /// # ru
+    /// Заблокирована ли стратегия - своим или общим выключателем.
+    pub fn is_killed(&self, strategy: &str) -> bool {
+        self.global.killed
+            || self.strategys.get(strategy).is_some_and(|i| i.killed)
+    }
+    /// Position of strategy in units.
+    ///
+    /// # ru
+    /// Позиция стратегии по figi в штуках, по исполненным ордерам.
+    pub fn position(&self, strategy: &str, figi: &str) -> i64 {
+        self.positions
+            .get(&(strategy.to_string(), figi.to_string()))
+            .map_or(0, |i| i.quantity)
+    }
+
+    /// Check order before posting.
+    ///
+    /// # ru
+    /// Проверяет ордер перед отправкой брокеру, ts - текущее время.
+    /// Ошибка - причина отказа, для отклонения ордера.
+    pub fn check(&mut self, a: &OrderAction, ts: i64) -> Result<(), String> {
+        let strategy = a.owner.as_str();
+        let figi = a.iid.figi();
+        let quantity =
+            signed(a.order.direction(), a.order.lots() * a.iid.lot());
+        let position = self.position(strategy, figi);
+        let total = self.total(figi);
+        let reduce = (position + quantity).abs() < position.abs();
+
+        if self.is_killed(strategy) && !reduce {
+            return Err(format!("risk: {strategy} kill switch"));
+        }
+        if self.is_restricted(&a.iid) && !reduce {
+            let ticker = a.iid.ticker().clone();
+            let breach = Breach::Restricted { ticker };
+            return Err(self.reject(ts, None, breach));
+        }
+
+        // цена исполнения против последней цены, защитные стопы и
+        // закрытие позиции не проверяются - их цена далеко по смыслу
+        if !reduce
+            && let Some(price) = exec_price(&a.order)
+            && let Some(last) = self.prices.get(figi).copied()
+        {
+            let deviation = (price - last).abs() / last * 100.0;
+            let limit = self.limits(strategy).rules.max_price_deviation;
+            if let Some(limit) = limit
+                && deviation > limit
+            {
+                let breach = Breach::PriceDeviation { price, last, limit };
+                return Err(self.reject(ts, Some(strategy), breach));
+            }
+            if let Some(limit) = self.global.rules.max_price_deviation
+                && deviation > limit
+            {
+                let breach = Breach::PriceDeviation { price, last, limit };
+                return Err(self.reject(ts, None, breach));
+            }
+        }
+
+        // стоимость ордера, по его цене или последней
+        if !reduce
+            && let Some(price) =
+                order_price(&a.order).or_else(|| self.price(figi, &a.order))
+        {
+            let value = quantity.abs() as f64 * price;
+            let limit = self.limits(strategy).rules.max_order_value;
+            if let Some(limit) = limit
+                && value > limit
+            {
+                let breach = Breach::OrderValue { value, limit };
+                return Err(self.reject(ts, Some(strategy), breach));
+            }
+            if let Some(limit) = self.global.rules.max_order_value
+                && value > limit
+            {
+                let breach = Breach::OrderValue { value, limit };
+                return Err(self.reject(ts, None, breach));
+            }
+        }
+
+        // позиция в штуках после исполнения
+        if !reduce {
+            let after = (position + quantity).abs();
+            let limit = self.limits(strategy).rules.max_position;
+            if let Some(limit) = limit
+                && after > limit as i64
+            {
+                let breach = Breach::Position {
+                    quantity: after,
+                    limit,
+                };
+                return Err(self.reject(ts, Some(strategy), breach));
+            }
+            let after = (total + quantity).abs();
+            if let Some(limit) = self.global.rules.max_position
+                && after > limit as i64
+            {
+                let breach = Breach::Position {
+                    quantity: after,
+                    limit,
+                };
+                return Err(self.reject(ts, None, breach));
+            }
+        }
+
+        // стоимость позиции после исполнения
+        if !reduce && let Some(price) = self.price(figi, &a.order) {
+            let value = (position + quantity).abs() as f64 * price;
+            let limit = self.limits(strategy).rules.max_position_value;
+            if let Some(limit) = limit
+                && value > limit
+            {
+                let breach = Breach::PositionValue { value, limit };
+                return Err(self.reject(ts, Some(strategy), breach));
+            }
+            let value = (total + quantity).abs() as f64 * price;
+            if let Some(limit) = self.global.rules.max_position_value
+                && value > limit
+            {
+                let breach = Breach::PositionValue { value, limit };
+                return Err(self.reject(ts, None, breach));
+            }
+        }
+
+        // частота ордеров
+        if let Some(breach) = self.limits(strategy).rate(ts) {
+            return Err(self.reject(ts, Some(strategy), breach));
+        }
+        if let Some(breach) = self.global.rate(ts) {
+            return Err(self.reject(ts, None, breach));
+        }
+        self.limits(strategy).orders.push_back(ts);
+        self.global.orders.push_back(ts);
+
+        Ok(())
+    }
+    /// Update positions by broker order event.
+    ///
+    /// # ru
+    /// Обновляет позиции по событию ордера от брокера: учитывается
+    /// исполнение из каждого события, в том числе частичное. Исполнение
+    /// ордеров закрытия [`RISK_OWNER`] уменьшает закрываемые позиции,
+    /// отклоненный ордер закрытия оставляет их открытыми.
+    pub fn order_event(&mut self, e: &OrderEvent) {
+        let figi = e.iid.figi();
+        let order = match &e.order {
+            Order::Stop(_) => return,
+            Order::Market(MarketOrder::Rejected(o)) => {
+                if e.owner == RISK_OWNER {
+                    let quantity = o.lots * e.iid.lot();
+                    let quantity = signed(&o.direction, quantity);
+                    self.closing(&e.account, figi, quantity, false);
+                }
+                return;
+            }
+            order => order,
+        };
+        let (Some(id), Some(transactions)) =
+            (order.broker_id(), order.transactions())
+        else {
+            return;
+        };
+
+        // транзакции в ордере накапливаются, новое исполнение - это
+        // разница с уже учтенным
+        let executed: u32 =
+            transactions.iter().map(|t| t.quantity.unsigned_abs()).sum();
+        let counted = self.executed.remove(id).unwrap_or(0);
+        if order.is_posted() || order.is_partially_filled() {
+            self.executed.insert(id.clone(), executed);
+        }
+        let quantity =
+            signed(order.direction(), executed.saturating_sub(counted));
+        if quantity == 0 {
+            return;
+        }
+        if let Some(t) = transactions.last() {
+            self.prices.entry(figi.clone()).or_insert(t.price);
+        }
+
+        if e.owner == RISK_OWNER {
+            self.closing(&e.account, figi, quantity, true);
+            return;
+        }
+        let key = (e.owner.clone(), figi.clone());
+        let position = self.positions.entry(key).or_insert(Position {
+            account: e.account.clone(),
+            iid: e.iid.clone(),
+            quantity: 0,
+            closing: 0,
+        });
+        position.quantity += quantity;
+    }
+    /// Update last prices by market event.
+    ///
+    /// # ru
+    /// Запоминает последнюю цену инструмента по бару или тику.
+    pub fn market(&mut self, e: &Event) {
+        let price = match e {
+            Event::Bar(e) => e.bar.c,
+            Event::Tic(e) => e.tic.price,
+            _ => return,
+        };
+        self.prices.insert(e.figi().clone(), price);
+    }
+    /// Count result of closed trade, check loss and drawdown.
+    ///
+    /// # ru
+    /// Учитывает результат закрытого трейда стратегии и проверяет
+    /// убыток за день и просадку. Возвращает ордера закрытия позиций,
+    /// если сработал выключатель и включен flatten.
+    pub fn trade_closed(
+        &mut self,
+        strategy: &str,
+        result: f64,
+        ts: i64,
+    ) -> Vec<OrderAction> {
+        let mut killed = Vec::new();
+
+        if let Some(breach) = self.limits(strategy).trade(result, ts) {
+            self.kill(ts, Some(strategy), breach);
+            killed.push(strategy.to_string());
+        }
+        if let Some(breach) = self.global.trade(result, ts) {
+            self.kill(ts, None, breach);
+            killed.extend(self.positions.keys().map(|(s, _)| s.clone()));
+        }
+
+        if !self.flatten {
+            return Vec::new();
+        }
+        let mut actions = Vec::new();
+        for ((owner, _), position) in self.positions.iter_mut() {
+            if killed.contains(owner)
+                && let Some(action) = position.close()
+            {
+                actions.push(action);
+            }
+        }
+
+        actions
+    }
+
+    /// Close broker positions by market, e.g. at session close.
+    ///
+    /// # ru
+    /// Закрывает рыночными ордерами от имени [`RISK_OWNER`] позиции
+    /// брокера (счет, инструмент, позиция в штуках), например перед
+    /// закрытием торговой сессии. Закрывается фактическая позиция
+    /// счета за вычетом уже отправленного закрытия, позиции стратегий
+    /// по счету уменьшаются по исполнению. Выключатели не включаются.
+    pub fn close_all(
+        &mut self,
+        broker: &[(Account, Iid, i64)],
+    ) -> Vec<OrderAction> {
+        let mut actions = Vec::new();
+        for (account, iid, position) in broker.iter() {
+            let mut group: Vec<&mut Position> = self
+                .positions
+                .iter_mut()
+                .filter(|((_, figi), i)| {
+                    figi == iid.figi() && i.account.name() == account.name()
+                })
+                .map(|(_, i)| i)
+                .collect();
+            let closing: i64 = group.iter().map(|i| i.closing).sum();
+
+            // встречные позиции стратегий на одном счете закрывают друг
+            // друга без сделок, остальное ждет исполнения закрытия
+            let long: i64 = group.iter().map(|i| i.quantity.max(0)).sum();
+            let short: i64 = group.iter().map(|i| -i.quantity.min(0)).sum();
+            let (mut long, mut short) = (long.min(short), long.min(short));
+            for i in group.iter_mut() {
+                let offset = if i.quantity > 0 {
+                    let offset = i.quantity.min(long);
+                    long -= offset;
+                    offset
+                } else {
+                    let offset = i.quantity.max(-short);
+                    short += offset;
+                    offset
+                };
+                i.quantity -= offset;
+                i.closing = -i.quantity;
+            }
+
+            let open = position + closing;
+            let lots = open.unsigned_abs() as u32 / iid.lot();
+            if lots == 0 {
+                continue;
+            }
+            let direction = if open > 0 {
+                Direction::Sell
+            } else {
+                Direction::Buy
+            };
+            let order = MarketOrder::new(direction, lots);
+            actions.push(OrderAction::new(
+                account.clone(),
+                iid.clone(),
+                RISK_OWNER,
+                Order::Market(MarketOrder::New(order)),
+            ));
+        }
+
+        actions
+    }
+
+    // private
+    fn limits(&mut self, strategy: &str) -> &mut Limits {
+        self.strategys.entry(strategy.to_string()).or_default()
+    }
+    /// Распределяет исполнение (filled) или отказ ордера закрытия по
+    /// закрываемым позициям инструмента.
+    fn closing(
+        &mut self,
+        account: &Account,
+        figi: &str,
+        mut quantity: i64,
+        filled: bool,
+    ) {
+        for ((_, f), position) in self.positions.iter_mut() {
+            if f != figi || position.account.name() != account.name() {
+                continue;
+            }
+            if quantity == 0 {
+                break;
+            }
+            // закрытие в пути направлено так же, как ордер закрытия
+            let part = if quantity > 0 {
+                quantity.min(position.closing.max(0))
+            } else {
+                quantity.max(position.closing.min(0))
+            };
+            position.closing -= part;
+            if filled {
+                position.quantity += part;
+            }
+            quantity -= part;
+        }
+    }
+    fn total(&self, figi: &str) -> i64 {
+        self.positions
+            .iter()
+            .filter(|((_, f), _)| f == figi)
+            .map(|(_, i)| i.quantity)
+            .sum()
+    }
+    fn price(&self, figi: &str, order: &Order) -> Option<f64> {
+        if let Some(price) = self.prices.get(figi) {
+            return Some(*price);
+        }
+
+        match order {
+            Order::Limit(LimitOrder::New(o)) => Some(o.price),
+            Order::Stop(StopOrder::New(o)) => Some(o.stop_price),
+            _ => None,
+        }
+    }
+    fn reject(
+        &mut self,
+        ts: i64,
+        strategy: Option<&str>,
+        breach: Breach,
+    ) -> String {
+        let reason = format!("risk: {breach}");
+        self.event(RiskEvent {
+            ts,
+            strategy: strategy.map(|s| s.to_string()),
+            breach,
+            kill: false,
+        });
+
+        reason
+    }
+    fn kill(&mut self, ts: i64, strategy: Option<&str>, breach: Breach) {
+        self.event(RiskEvent {
+            ts,
+            strategy: strategy.map(|s| s.to_string()),
+            breach,
+            kill: true,
+        });
+    }
+    fn event(&mut self, e: RiskEvent) {
+        log::warn!(":: Risk {e}");
+
+        if let Some(path) = &self.log
+            && let Err(err) = append(path, &e.to_string())
+        {
+            log::error!("Risk log {}: {err}", path.display());
+        }
+        self.events.push(e);
+    }
+}
+
+/// Правила и состояние счетчиков стратегии или общие.
+#[derive(Debug, Default)]
+struct Limits {
+    rules: RiskRules,
+    day: Option<NaiveDate>,
+    day_result: f64,
+    result: f64,
+    peak: f64,
+    /// Время ордеров за последнюю минуту.
+    orders: VecDeque<i64>,
+    killed: bool,
+}
+impl Limits {
+    fn new(rules: RiskRules) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+    fn trade(&mut self, result: f64, ts: i64) -> Option<Breach> {
+        let day = (utils::dt(ts) + MSK_OFFSET).date_naive();
+        if self.day != Some(day) {
+            self.day = Some(day);
+            self.day_result = 0.0;
+        }
+        self.day_result += result;
+        self.result += result;
+        self.peak = self.peak.max(self.result);
+
+        if self.killed {
+            return None;
+        }
+        let loss = -self.day_result;
+        if let Some(limit) = self.rules.max_daily_loss
+            && loss > limit
+        {
+            self.killed = true;
+            return Some(Breach::DailyLoss { loss, limit });
+        }
+        let drawdown = self.peak - self.result;
+        if let Some(limit) = self.rules.max_drawdown
+            && drawdown > limit
+        {
+            self.killed = true;
+            return Some(Breach::Drawdown { drawdown, limit });
+        }
+
+        None
+    }
+    fn rate(&mut self, ts: i64) -> Option<Breach> {
+        while self.orders.front().is_some_and(|i| ts - i >= MINUTE) {
+            self.orders.pop_front();
+        }
+
+        let limit = self.rules.max_orders_per_minute?;
+        let count = self.orders.len() as u32;
+        (count >= limit).then_some(Breach::OrderRate { count, limit })
+    }
+}
+
+#[derive(Debug)]
+struct Position {
+    account: Account,
+    iid: Iid,
+    quantity: i64,
+    /// Отправленное, но еще не исполненное закрытие в штуках, со знаком
+    /// ордера закрытия.
+    closing: i64,
+}
+impl Position {
+    /// Ордер закрытия части позиции, которая еще не закрывается.
+    /// Позиция уменьшается только по исполнению этого ордера.
+    fn close(&mut self) -> Option<OrderAction> {
+        let open = self.quantity + self.closing;
+        let lots = open.unsigned_abs() as u32 / self.iid.lot();
+        if lots == 0 {
+            return None;
+        }
+        let direction = if open > 0 {
+            Direction::Sell
+        } else {
+            Direction::Buy
+        };
+        self.closing += signed(&direction, lots * self.iid.lot());
+        let order = MarketOrder::new(direction, lots);
+
+        Some(OrderAction::new(
+            self.account.clone(),
+            self.iid.clone(),
+            RISK_OWNER,
+            Order::Market(MarketOrder::New(order)),
+        ))
+    }
+}
+
+/// Цена нового лимитного или стоп ордера, у рыночного цены нет.
+fn order_price(order: &Order) -> Option<f64> {
+    match order {
+        Order::Limit(LimitOrder::New(o)) => Some(o.price),
+        Order::Stop(StopOrder::New(o)) => {
+            Some(o.exec_price.unwrap_or(o.stop_price))
+        }
+        _ => None,
+    }
+}
+/// Цена исполнения нового лимитного ордера или лимитки стоп ордера,
+/// цена сработки стопа ценой исполнения не считается.
+fn exec_price(order: &Order) -> Option<f64> {
+    match order {
+        Order::Limit(LimitOrder::New(o)) => Some(o.price),
+        Order::Stop(StopOrder::New(o)) => o.exec_price,
+        _ => None,
+    }
+}
+fn signed(direction: &Direction, quantity: u32) -> i64 {
+    match direction {
+        Direction::Buy => quantity as i64,
+        Direction::Sell => -(quantity as i64),
+    }
+}
+fn append(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent()
+        && !Cmd::is_exist(dir)
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    writeln!(file, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::{self, iid};
+    use avin_core::{
+        Bar, FilledMarketOrder, Operation, PartiallyFilledMarketOrder,
+        RejectedMarketOrder, StopOrderKind, Transaction,
+    };
+
+    use super::*;
+
+    const TS: i64 = 1_700_000_000_000_000_000;
+
+    fn post(owner: &str, direction: Direction, lots: u32) -> OrderAction {
+        let order = MarketOrder::new(direction, lots);
+
+        OrderAction::new(
+            Account::new("Paper", "Paper_ID"),
+            iid(),
+            owner,
+            Order::Market(MarketOrder::New(order)),
+        )
+    }
+    fn stop(
+        owner: &str,
+        direction: Direction,
+        lots: u32,
+        stop_price: f64,
+        exec_price: Option<f64>,
+    ) -> OrderAction {
+        let kind = StopOrderKind::StopLoss;
+        let order =
+            StopOrder::new(kind, direction, lots, stop_price, exec_price);
+
+        OrderAction::new(
+            Account::new("Paper", "Paper_ID"),
+            iid(),
+            owner,
+            Order::Stop(StopOrder::New(order)),
+        )
+    }
+    fn filled(owner: &str, direction: Direction, lots: u32) -> OrderEvent {
+        let quantity = (lots * iid().lot()) as i32;
+        let transactions = vec![Transaction::new(quantity, 100.0)];
+        let order = FilledMarketOrder {
+            direction,
+            lots,
+            broker_id: "1".to_string(),
+            transactions: transactions.clone(),
+            operation: Operation::build(TS, &transactions, 0.0),
+        };
+
+        OrderEvent::new(
+            Account::new("Paper", "Paper_ID"),
+            iid(),
+            owner.to_string(),
+            Order::Market(MarketOrder::Filled(order)),
+        )
+    }
+    fn partial(
+        owner: &str,
+        direction: Direction,
+        lots: u32,
+        fills: &[u32],
+    ) -> OrderEvent {
+        let transactions = fills
+            .iter()
+            .map(|i| Transaction::new((i * iid().lot()) as i32, 100.0))
+            .collect();
+        let order = PartiallyFilledMarketOrder {
+            direction,
+            lots,
+            broker_id: "1".to_string(),
+            executed: fills.iter().sum(),
+            transactions,
+        };
+
+        OrderEvent::new(
+            Account::new("Paper", "Paper_ID"),
+            iid(),
+            owner.to_string(),
+            Order::Market(MarketOrder::PartiallyFilled(order)),
+        )
+    }
+    fn rejected(direction: Direction, lots: u32) -> OrderEvent {
+        let order = RejectedMarketOrder {
+            direction,
+            lots,
+            meta: "rejected".to_string(),
+        };
+
+        OrderEvent::new(
+            Account::new("Paper", "Paper_ID"),
+            iid(),
+            RISK_OWNER.to_string(),
+            Order::Market(MarketOrder::Rejected(order)),
+        )
+    }
+    fn price(risk: &mut RiskManager, price: f64) {
+        let bar = Bar::new(TS, price, price, price, price, 1);
+        risk.market(&fixture::bar_event(bar));
+    }
+
+    #[test]
+    fn position_value() {
+        let mut risk = RiskManager::new(RiskRules {
+            max_position_value: Some(20_000.0),
+            ..Default::default()
+        });
+        risk.set_rules(
+            "A",
+            RiskRules {
+                max_position_value: Some(10_000.0),
+                ..Default::default()
+            },
+        );
+        price(&mut risk, 100.0);
+
+        // 10 лотов * 10 штук * 100 = 10_000
+        assert!(risk.check(&post("A", Direction::Buy, 10), TS).is_ok());
+        assert!(risk.check(&post("A", Direction::Buy, 11), TS).is_err());
+        risk.order_event(&filled("A", Direction::Buy, 10));
+        assert_eq!(risk.position("A", iid().figi()), 100);
+
+        // уменьшение позиции проходит, общий лимит считает все стратегии
+        assert!(risk.check(&post("A", Direction::Sell, 5), TS).is_ok());
+        assert!(risk.check(&post("B", Direction::Buy, 10), TS).is_ok());
+        assert!(risk.check(&post("B", Direction::Buy, 11), TS).is_err());
+        assert_eq!(risk.events().len(), 2);
+        assert_eq!(risk.events()[1].strategy, None);
+        assert!(!risk.events()[1].kill);
+    }
+    fn limit(
+        owner: &str,
+        direction: Direction,
+        lots: u32,
+        price: f64,
+    ) -> OrderAction {
+        let order = LimitOrder::new(direction, lots, price);
+
+        OrderAction::new(
+            Account::new("Paper", "Paper_ID"),
+            iid(),
+            owner,
+            Order::Limit(LimitOrder::New(order)),
+        )
+    }
+
+    #[test]
+    fn pre_trade() {
+        let mut risk = RiskManager::new(RiskRules {
+            max_order_value: Some(5_000.0),
+            max_position: Some(80),
+            max_price_deviation: Some(5.0),
+            ..Default::default()
+        });
+        price(&mut risk, 100.0);
+
+        // цена далеко от последней: ошибка в цене
+        let a = limit("A", Direction::Buy, 1, 110.0);
+        let err = risk.check(&a, TS).unwrap_err();
+        assert_eq!(err, "risk: price 110 vs last 100: 10.00% > 5.00%");
+        assert!(
+            risk.check(&limit("A", Direction::Buy, 1, 104.0), TS)
+                .is_ok()
+        );
+
+        // 6 лотов * 10 штук * 100 = 6_000
+        let err = risk.check(&post("A", Direction::Buy, 6), TS).unwrap_err();
+        assert_eq!(err, "risk: order value 6000.00 > 5000.00");
+        assert!(risk.check(&post("A", Direction::Buy, 5), TS).is_ok());
+
+        // позиция 50 + 40 штук больше 80
+        risk.order_event(&filled("A", Direction::Buy, 5));
+        let err = risk.check(&post("A", Direction::Buy, 4), TS).unwrap_err();
+        assert_eq!(err, "risk: position 90 > 80");
+        assert!(risk.check(&post("A", Direction::Sell, 5), TS).is_ok());
+
+        // защитный стоп далеко от цены и стоп на вход без лимитки
+        let a = stop("A", Direction::Sell, 5, 90.0, Some(89.0));
+        assert!(risk.check(&a, TS).is_ok());
+        let a = stop("B", Direction::Buy, 1, 110.0, None);
+        assert!(risk.check(&a, TS).is_ok());
+        let a = stop("B", Direction::Buy, 1, 110.0, Some(111.0));
+        assert!(risk.check(&a, TS).is_err());
+
+        // запрещенный инструмент: закрыть позицию можно
+        risk.set_restricted(&["SBER".to_string()]);
+        assert!(risk.is_restricted(&iid()));
+        let err = risk.check(&post("A", Direction::Buy, 1), TS).unwrap_err();
+        assert_eq!(err, "risk: SBER is restricted");
+        assert!(risk.check(&post("A", Direction::Sell, 1), TS).is_ok());
+        assert_eq!(risk.events().len(), 5);
+    }
+    #[test]
+    fn order_rate() {
+        let mut risk = RiskManager::new(RiskRules::default());
+        risk.set_rules(
+            "A",
+            RiskRules {
+                max_orders_per_minute: Some(2),
+                ..Default::default()
+            },
+        );
+
+        let a = post("A", Direction::Buy, 1);
+        assert!(risk.check(&a, TS).is_ok());
+        assert!(risk.check(&a, TS + 1).is_ok());
+        let err = risk.check(&a, TS + 2).unwrap_err();
+        assert_eq!(err, "risk: orders per minute 2 >= 2");
+        assert!(risk.check(&post("B", Direction::Buy, 1), TS).is_ok());
+
+        // через минуту окно освобождается
+        assert!(risk.check(&a, TS + MINUTE).is_ok());
+        assert!(!risk.is_killed("A"));
+    }
+    #[test]
+    fn kill_switch() {
+        let mut risk = RiskManager::new(RiskRules {
+            max_drawdown: Some(3000.0),
+            ..Default::default()
+        });
+        risk.set_rules(
+            "A",
+            RiskRules {
+                max_daily_loss: Some(1000.0),
+                ..Default::default()
+            },
+        );
+        risk.set_flatten(true);
+        risk.order_event(&filled("A", Direction::Buy, 2));
+        risk.order_event(&filled("B", Direction::Sell, 3));
+
+        // убыток за день стратегии A: закрывается только ее позиция
+        assert!(risk.trade_closed("A", -600.0, TS).is_empty());
+        let actions = risk.trade_closed("A", -600.0, TS);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].owner, RISK_OWNER);
+        assert_eq!(actions[0].order.direction(), &Direction::Sell);
+        assert_eq!(actions[0].order.lots(), 2);
+        assert!(risk.is_killed("A"));
+        assert!(!risk.is_killed("B"));
+        assert!(risk.check(&post("A", Direction::Buy, 1), TS).is_err());
+        assert!(risk.check(&post("B", Direction::Buy, 1), TS).is_ok());
+
+        // новый день не снимает блокировку
+        let day = 24 * 60 * MINUTE;
+        assert!(risk.trade_closed("A", 0.0, TS + day).is_empty());
+        assert!(risk.is_killed("A"));
+
+        // общая просадка: от пика +2000 до -1300
+        assert!(risk.trade_closed("B", 3200.0, TS + day).is_empty());
+        let actions = risk.trade_closed("B", -3300.0, TS + day);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].order.direction(), &Direction::Buy);
+        assert!(risk.is_killed("B"));
+        assert_eq!(risk.events().len(), 2);
+        assert!(risk.events().iter().all(|e| e.kill));
+        let e = risk.events()[1].to_string();
+        assert!(e.ends_with("all: drawdown 3300.00 > 3000.00 - kill switch"));
+    }
+    #[test]
+    fn close_all() {
+        let mut risk = RiskManager::new(RiskRules::default());
+        let account = Account::new("Paper", "Paper_ID");
+        let lot = iid().lot() as i64;
+        risk.order_event(&filled("A", Direction::Buy, 2));
+        risk.order_event(&filled("B", Direction::Sell, 3));
+        risk.order_event(&filled("C", Direction::Buy, 1));
+        risk.order_event(&filled("C", Direction::Sell, 1));
+
+        // закрывается фактическая позиция брокера, встречные позиции
+        // стратегий закрывают друг друга
+        let broker = [(account.clone(), iid(), -lot)];
+        let actions = risk.close_all(&broker);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].owner, RISK_OWNER);
+        assert_eq!(actions[0].order.direction(), &Direction::Buy);
+        assert_eq!(actions[0].order.lots(), 1);
+        assert_eq!(risk.position("A", iid().figi()), 0);
+        assert_eq!(risk.position("B", iid().figi()), -lot);
+        assert!(risk.close_all(&broker).is_empty());
+        assert!(!risk.is_killed("A"));
+
+        // позиция уменьшается только по исполнению ордера закрытия
+        risk.order_event(&filled(RISK_OWNER, Direction::Buy, 1));
+        assert_eq!(risk.position("B", iid().figi()), 0);
+        assert!(risk.close_all(&[(account.clone(), iid(), 0)]).is_empty());
+
+        // у брокера позиция больше, чем у стратегий
+        risk.order_event(&filled("A", Direction::Buy, 2));
+        let broker = [(account.clone(), iid(), 3 * lot)];
+        let actions = risk.close_all(&broker);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].order.direction(), &Direction::Sell);
+        assert_eq!(actions[0].order.lots(), 3);
+
+        // отклоненный ордер закрытия: позиция закрывается снова
+        risk.order_event(&rejected(Direction::Sell, 3));
+        assert_eq!(risk.position("A", iid().figi()), 2 * lot);
+        let actions = risk.close_all(&broker);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].order.lots(), 3);
+        risk.order_event(&partial(RISK_OWNER, Direction::Sell, 3, &[1]));
+        assert_eq!(risk.position("A", iid().figi()), lot);
+        risk.order_event(&filled(RISK_OWNER, Direction::Sell, 3));
+        assert_eq!(risk.position("A", iid().figi()), 0);
+    }
+    #[test]
+    fn partial_fill() {
+        let mut risk = RiskManager::new(RiskRules::default());
+        let lot = iid().lot() as i64;
+
+        risk.order_event(&partial("A", Direction::Buy, 5, &[2]));
+        assert_eq!(risk.position("A", iid().figi()), 2 * lot);
+        risk.order_event(&partial("A", Direction::Buy, 5, &[2, 1]));
+        assert_eq!(risk.position("A", iid().figi()), 3 * lot);
+        risk.order_event(&filled("A", Direction::Buy, 5));
+        assert_eq!(risk.position("A", iid().figi()), 5 * lot);
+
+        // следующий ордер с тем же broker_id учитывается заново
+        risk.order_event(&filled("A", Direction::Sell, 1));
+        assert_eq!(risk.position("A", iid().figi()), 4 * lot);
+    }
+}
@@ -0,0 +1,145 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+use avin_core::{Action, Iid, MarketData, StreamAction};
+
+/// Active market data subscriptions of trader.
+///
+/// # ru
+/// Активные подписки трейдера на рыночные данные. Трейдер собирает
+/// потоки, которые нужны запущенным стратегиям, а подписки сверяют их
+/// с уже активными и возвращают действия для брокера: подписаться на
+/// новые потоки и отписаться от тех, что больше никому не нужны.
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    active: Vec<(Iid, Vec<MarketData>)>,
+}
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Active streams of instrument.
+    ///
+    /// # ru
+    /// Активные потоки инструмента, пусто - подписок нет.
+    pub fn active(&self, figi: &str) -> &[MarketData] {
+        self.active
+            .iter()
+            .find(|(iid, _)| iid.figi() == figi)
+            .map_or(&[], |(_, md)| md.as_slice())
+    }
+    /// Diff wanted streams with active, return actions for broker.
+    ///
+    /// # ru
+    /// Сверяет нужные потоки с активными, запоминает нужные как
+    /// активные и возвращает действия Subscribe и Unsubscribe для
+    /// брокера. Повторы в нужных потоках допустимы.
+    pub fn update(
+        &mut self,
+        wanted: &[(Iid, Vec<MarketData>)],
+    ) -> Vec<Action> {
+        let mut next: Vec<(Iid, Vec<MarketData>)> = Vec::new();
+        for (iid, kinds) in wanted.iter() {
+            let i =
+                match next.iter().position(|(i, _)| i.figi() == iid.figi()) {
+                    Some(i) => i,
+                    None => {
+                        next.push((iid.clone(), Vec::new()));
+                        next.len() - 1
+                    }
+                };
+            for md in kinds.iter() {
+                if !next[i].1.contains(md) {
+                    next[i].1.push(*md);
+                }
+            }
+        }
+
+        let mut actions = Vec::new();
+        for (iid, kinds) in next.iter() {
+            let active = self.active(iid.figi());
+            let new: Vec<MarketData> = kinds
+                .iter()
+                .filter(|md| !active.contains(md))
+                .copied()
+                .collect();
+            if !new.is_empty() {
+                let a = StreamAction::new(iid.clone(), new);
+                actions.push(Action::Subscribe(a));
+            }
+        }
+        for (iid, active) in self.active.iter() {
+            let kinds = next
+                .iter()
+                .find(|(i, _)| i.figi() == iid.figi())
+                .map_or(&[][..], |(_, md)| md.as_slice());
+            let old: Vec<MarketData> = active
+                .iter()
+                .filter(|md| !kinds.contains(md))
+                .copied()
+                .collect();
+            if !old.is_empty() {
+                let a = StreamAction::new(iid.clone(), old);
+                actions.push(Action::Unsubscribe(a));
+            }
+        }
+        self.active = next;
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::{iid, share};
+
+    use super::*;
+
+    fn streams(actions: &[Action]) -> Vec<String> {
+        actions
+            .iter()
+            .map(|a| match a {
+                Action::Subscribe(a) => {
+                    format!("+{} {:?}", a.iid.ticker(), a.market_data_kinds)
+                }
+                Action::Unsubscribe(a) => {
+                    format!("-{} {:?}", a.iid.ticker(), a.market_data_kinds)
+                }
+                other => panic!("{other}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn update() {
+        let sber = iid();
+        let gazp = share("GAZP", "BBG004730RP0");
+        let mut subs = Subscriptions::new();
+
+        // две стратегии на SBER, одной нужны тики
+        let wanted = vec![
+            (sber.clone(), vec![MarketData::BAR_1M]),
+            (sber.clone(), vec![MarketData::BAR_1M, MarketData::TIC]),
+            (gazp.clone(), vec![MarketData::BAR_1M]),
+        ];
+        let actions = subs.update(&wanted);
+        assert_eq!(
+            streams(&actions),
+            vec!["+SBER [BAR_1M, TIC]", "+GAZP [BAR_1M]"]
+        );
+
+        // без изменений - без действий
+        assert!(subs.update(&wanted).is_empty());
+
+        // стратегия с тиками выгружена, GAZP больше не нужен
+        let wanted = vec![(sber.clone(), vec![MarketData::BAR_1M])];
+        let actions = subs.update(&wanted);
+        assert_eq!(streams(&actions), vec!["-SBER [TIC]", "-GAZP [BAR_1M]"]);
+        assert_eq!(subs.active(sber.figi()), &[MarketData::BAR_1M]);
+        assert!(subs.active(gazp.figi()).is_empty());
+    }
+}
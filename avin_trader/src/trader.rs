@@ -6,20 +6,70 @@
  ****************************************************************************/
 
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio::runtime::Handle;
 
 use avin_connect::Tinkoff;
 use avin_core::{
-    Action, Asset, Event, GetAccountAction, MarketData, StreamAction,
-    TimeFrame, TradeList,
+    Account, Action, Asset, AssetList, CashOperation, Clock, ClosedTrade,
+    Event, GetAccountAction, GetBarsAction, GetCashOperationsAction,
+    GetOperationsAction, GetOrdersAction, GetPositionAction, Iid, LimitOrder,
+    Manager, MarketData, MarketOrder, NotifyAction, NotifyLevel, Operation,
+    Order, OrderAction, OrderEvent, RealClock, StopOrder, TimeFrame, Trade,
+    TradeList,
+};
+use avin_simulator::{Imperfection, PaperBroker};
+use avin_strategy::{
+    BigTrendShort, Dca, Executor, Grid, MaCross, OpeningRange, Plugin,
+    RsiReversion, Strategy, StrategyMeta, TrendFollow,
 };
-use avin_strategy::{BigTrendShort, Strategy};
-use avin_utils::CFG;
+use avin_utils::{self as utils, AvinError, CFG};
+
+use super::calendar::{SessionPhase, TradingCalendar};
+use super::journal::TradeJournal;
+use super::notify::Notifier;
+use super::reconcile::{RECONCILE_OWNER, Reconciler, Reconciliation};
+use super::risk::RiskManager;
+use super::subscriptions::Subscriptions;
+use super::work::{Work, WorkCmd, WorkFault};
 
-use super::work::Work;
+// Счет стратегий, для которых в конфиге счет не указан
+const MAIN_ACCOUNT: &str = "Agni";
+// Как часто трейдер проверяет файлы плагинов
+const PLUGIN_CHECK: Duration = Duration::from_secs(5);
+// Пауза перед первым перезапуском упавшей стратегии, дальше вдвое
+// больше
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+// Сколько главный цикл ждет событие брокера, без событий календарь и
+// плагины все равно проверяются
+const EVENT_WAIT: Duration = Duration::from_secs(1);
+
+type ActionSender = tokio::sync::mpsc::UnboundedSender<Action>;
+type FaultSender = tokio::sync::mpsc::UnboundedSender<WorkFault>;
+
+/// Factory of strategy by name from config.
+///
+/// # ru
+/// Фабрика стратегии по ее имени в конфиге, см.
+/// [`Trader::add_strategy`].
+pub type StrategyFactory = Box<dyn Fn() -> Box<dyn Strategy> + Send + Sync>;
 
 pub struct Trader {
     works: HashMap<String, tokio::sync::mpsc::UnboundedSender<Event>>,
+    cmds: HashMap<String, tokio::sync::mpsc::UnboundedSender<WorkCmd>>,
     trades: TradeList,
+    factories: HashMap<String, StrategyFactory>,
+    plugins: Vec<Plugin>,
+    running: Vec<Running>,
+    accounts: HashMap<String, Account>,
+    subscriptions: Subscriptions,
+    reconciler: Reconciler,
+    fault_tx: Option<FaultSender>,
+    phase: SessionPhase,
 }
 impl Default for Trader {
     fn default() -> Self {
@@ -28,15 +78,91 @@ impl Default for Trader {
 }
 impl Trader {
     pub fn new() -> Self {
-        Self {
+        let mut trader = Self {
             works: HashMap::new(),
+            cmds: HashMap::new(),
             trades: TradeList::new("Trader_unittest"),
-        }
+            factories: HashMap::new(),
+            plugins: Vec::new(),
+            running: Vec::new(),
+            accounts: HashMap::new(),
+            subscriptions: Subscriptions::new(),
+            reconciler: Reconciler::default(),
+            fault_tx: None,
+            phase: SessionPhase::Closed,
+        };
+
+        // библиотека примеров стратегий avin_strategy
+        trader.add_strategy("BigTrendShort", || {
+            Box::new(BigTrendShort::default())
+        });
+        trader.add_strategy("MaCross", || {
+            Box::new(Executor::<MaCross>::default())
+        });
+        trader.add_strategy("RsiReversion", || {
+            Box::new(Executor::<RsiReversion>::default())
+        });
+        trader.add_strategy("OpeningRange", || {
+            Box::new(Executor::<OpeningRange>::default())
+        });
+        trader.add_strategy("TrendFollow", || {
+            Box::new(Executor::<TrendFollow>::default())
+        });
+        trader.add_strategy("Grid", || Box::new(Grid::default()));
+        trader.add_strategy("Dca", || Box::new(Dca::default()));
+
+        trader
+    }
+    /// Register factory of strategy with name, used in config work list.
+    ///
+    /// # ru
+    /// Регистрирует фабрику стратегии под именем, которое указывается
+    /// в списке работ конфига трейдера. Так трейдер запускает любые
+    /// стратегии, в том числе написанные на python (avin_py).
+    /// Если в конфиге есть незарегистрированное имя, трейдер не
+    /// запускается, см. [`Trader::start`].
+    pub fn add_strategy<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn() -> Box<dyn Strategy> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Box::new(factory));
     }
+    /// Load strategy plugin, its strategies are used by name.
+    ///
+    /// # ru
+    /// Загружает плагин стратегий (cdylib, см.
+    /// [`avin_strategy::plugin!`]). Стратегии плагина указываются в
+    /// списке работ по имени и важнее зарегистрированных фабрик. Во
+    /// время работы трейдер следит за файлом плагина: после пересборки
+    /// стратегии плагина перезагружаются без перезапуска трейдера,
+    /// после удаления файла - выгружаются.
+    pub fn add_plugin(&mut self, path: &Path) -> Result<(), AvinError> {
+        let plugin = Plugin::load(path)?;
+        log::info!("- load plugin {}: {:?}", path.display(), plugin.names());
+        self.plugins.retain(|i| i.path() != path);
+        self.plugins.push(plugin);
 
-    pub async fn start(&mut self) {
+        Ok(())
+    }
+
+    /// Start trader by config, works until the process is stopped.
+    ///
+    /// # ru
+    /// Запускает трейдер по конфигу, работает до остановки процесса.
+    /// Если стратегия из конфига не найдена ни в плагинах, ни среди
+    /// зарегистрированных фабрик - возвращает ошибку до подключения
+    /// к брокеру.
+    pub async fn start(&mut self) -> Result<(), AvinError> {
         log::info!(":: Trader start");
 
+        for path in CFG.trader.plugins.iter() {
+            let path = CFG.dir.root().join(path);
+            if let Err(e) = self.add_plugin(&path) {
+                log::error!("{e}");
+            }
+        }
+        self.check_strategys()?;
+
         // channel from trader to broker (Action)
         let (trader_broker_action_tx, trader_broker_action_rx) =
             tokio::sync::mpsc::unbounded_channel();
@@ -46,73 +172,152 @@ impl Trader {
         // channel from strategy to trader (Action)
         let (strategy_trader_action_tx, mut strategy_trader_action_rx) =
             tokio::sync::mpsc::unbounded_channel();
+        // channel from works to trader (WorkFault)
+        let (work_trader_fault_tx, mut work_trader_fault_rx) =
+            tokio::sync::mpsc::unbounded_channel();
+        self.fault_tx = Some(work_trader_fault_tx);
 
-        log::info!("- load broker");
-        let mut broker =
-            Tinkoff::new(trader_broker_action_rx, broker_trader_event_tx);
-        broker.connect().await.unwrap();
-        tokio::spawn(async move { start_broker(broker).await });
-
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        let a = Action::GetAccount(GetAccountAction::new("Agni", tx));
-        trader_broker_action_tx.send(a).unwrap();
-        let account = match rx.await {
-            Ok(account) => account,
-            Err(_) => todo!(),
-        };
-
-        for node in CFG.trader.work_list.iter() {
-            log::info!("Load asset");
-            let mut asset = Asset::new(&node.iid).unwrap();
-            load_charts(&mut asset);
-
-            // subscribe data stream
-            let a = Action::Subscribe(StreamAction::new(
-                asset.iid().clone(),
-                vec![MarketData::BAR_1M],
-            ));
-            trader_broker_action_tx.send(a).unwrap();
-
-            // load and init strategys
-            let mut strategys = Vec::new();
-            for name in &node.strategy {
-                log::info!("- load strategy {name}");
-                let mut strategy = BigTrendShort::default();
-                strategy.init(
-                    strategy_trader_action_tx.clone(),
-                    account.clone(),
-                    &mut asset,
-                );
-                strategys.push(strategy);
+        if CFG.trader.paper {
+            log::info!("- load paper broker");
+            let mut broker = PaperBroker::new(
+                trader_broker_action_rx,
+                broker_trader_event_tx,
+            );
+            broker.set_deposit(CFG.trader.paper_deposit);
+            if !CFG.trader.paper_imperfection.is_empty() {
+                let imperfection = Imperfection::try_from(
+                    CFG.trader.paper_imperfection.as_str(),
+                )
+                .unwrap();
+                broker.set_imperfection(imperfection);
             }
-
-            // create work, add strategys
-            let mut work = Work::new(asset);
-            for strategy in strategys {
-                work.add_strategy(strategy);
+            if !CFG.trader.paper_session.is_empty() {
+                let path = CFG.dir.root().join(&CFG.trader.paper_session);
+                broker.set_session(&path).unwrap();
             }
+            broker.connect().await.unwrap();
+            tokio::spawn(async move { broker.start().await });
+        } else {
+            log::info!("- load broker");
+            let mut broker =
+                Tinkoff::new(trader_broker_action_rx, broker_trader_event_tx);
+            broker.connect().await.unwrap();
+            tokio::spawn(async move { start_broker(broker).await });
+        }
 
-            log::info!("- start work");
-            self.works.insert(work.figi().clone(), work.get_sender());
-            tokio::spawn(async move { work.start().await });
+        let root = CFG.dir.root();
+        self.reconciler = Reconciler::from_cfg(&CFG.trader.reconcile, &root);
+        for (iid, strategys) in self.plan() {
+            self.start_work(
+                &iid,
+                &strategys,
+                &trader_broker_action_tx,
+                &strategy_trader_action_tx,
+            )
+            .await?;
         }
 
+        let mut risk = RiskManager::from_cfg(&CFG.trader.risk, &root);
+        let notifier =
+            Notifier::from_cfg(&CFG.trader.notify, &root, &Handle::current());
+        let journal = TradeJournal::from_cfg(&CFG.trader.journal, &root);
+
+        // работы запущены на паузе, подписки и стратегии включает фаза
+        // торгового дня; без календаря сессия идет всегда
+        let calendar =
+            CFG.trader.session.enabled.then(|| {
+                TradingCalendar::from_cfg(&CFG.trader.session).unwrap()
+            });
+        let phase = calendar
+            .as_ref()
+            .map_or(SessionPhase::Main, |i| i.phase(RealClock.ts()));
+        self.session(phase, &trader_broker_action_tx, &mut risk)
+            .await;
+
         log::info!("Start main loop");
+        let mut plugin_check = Instant::now();
         loop {
+            if plugin_check.elapsed() > PLUGIN_CHECK {
+                self.check_plugins(
+                    &trader_broker_action_tx,
+                    &strategy_trader_action_tx,
+                )
+                .await;
+                plugin_check = Instant::now();
+            }
+
+            if let Some(calendar) = &calendar {
+                let phase = calendar.phase(RealClock.ts());
+                self.session(phase, &trader_broker_action_tx, &mut risk)
+                    .await;
+            }
+
             // await events from broker -> send to work (asset & strategy)
-            if let Some(e) = broker_trader_event_rx.recv().await {
+            let e = tokio::time::timeout(
+                EVENT_WAIT,
+                broker_trader_event_rx.recv(),
+            );
+            if let Ok(Some(e)) = e.await {
+                risk.market(&e);
+                if let Event::Order(order_event) = &e {
+                    risk.order_event(order_event);
+                    self.reconciler.order_event(order_event, RealClock.ts());
+                }
                 let work = self.works.get(e.figi()).unwrap();
-                work.send(e).unwrap();
+                if work.send(e).is_err() {
+                    log::error!(":: Work is stopped, event is lost");
+                }
             };
 
+            // restart strategys fallen in their tasks
+            while let Ok(fault) = work_trader_fault_rx.try_recv() {
+                self.fault(
+                    fault,
+                    &notifier,
+                    &trader_broker_action_tx,
+                    &strategy_trader_action_tx,
+                );
+            }
+
             // process actions from strategys
             while let Ok(a) = strategy_trader_action_rx.try_recv() {
                 // log::debug!("Trader get {a}");
                 match a {
+                    Action::Post(a) => match risk.check(&a, RealClock.ts()) {
+                        Ok(()) => trader_broker_action_tx
+                            .send(Action::Post(a))
+                            .unwrap(),
+                        Err(reason) => {
+                            // стратегия ждет ответ по ордеру
+                            let work = self.works.get(a.iid.figi()).unwrap();
+                            if let Some(e) = reject(a, &reason) {
+                                let _ = work.send(Event::Order(e));
+                            }
+                        }
+                    },
                     Action::TradeOpened(trade) => {
                         log::info!(":: Trade opened: {trade}")
                     }
+                    Action::Signal(signal) => {
+                        log::info!(":: {signal}")
+                    }
+                    Action::Notify(a) => notifier.notify(&a, RealClock.ts()),
                     Action::TradeClosed(trade) => {
+                        if let Trade::Closed(closed) = &trade {
+                            let ts = RealClock.ts();
+                            let result = closed.result();
+                            for a in risk.trade_closed(
+                                &closed.strategy,
+                                result,
+                                ts,
+                            ) {
+                                log::warn!(":: Risk flatten {a}");
+                                trader_broker_action_tx
+                                    .send(Action::Post(a))
+                                    .unwrap();
+                            }
+                            journal.record(closed, self.meta(closed));
+                        }
                         self.trades.add(trade);
                     }
                     other => trader_broker_action_tx.send(other).unwrap(),
@@ -120,15 +325,736 @@ impl Trader {
             }
         }
     }
+
+    // private
+    fn create(&self, name: &str) -> Result<Box<dyn Strategy>, AvinError> {
+        if let Some(strategy) =
+            self.plugins.iter().find_map(|i| i.create(name))
+        {
+            return Ok(strategy);
+        }
+
+        match self.factories.get(name) {
+            Some(factory) => Ok(factory()),
+            None => Err(AvinError::NotFound(format!("strategy {name}"))),
+        }
+    }
+    fn has_strategy(&self, name: &str) -> bool {
+        self.plugins.iter().any(|i| i.contains(name))
+            || self.factories.contains_key(name)
+    }
+    /// План работ: инструмент и его стратегии со счетами, из списка
+    /// работ конфига, из инструментов стратегий trader.strategy_list и
+    /// из списков активов trader.watch_list (например, списков
+    /// наблюдения сканера).
+    fn plan(&self) -> Vec<(String, Vec<(String, String)>)> {
+        let mut plan: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        let mut add = |iid: &str, name: &str, account: &str| {
+            let iid = iid.to_lowercase();
+            let i = match plan.iter().position(|(i, _)| *i == iid) {
+                Some(i) => i,
+                None => {
+                    plan.push((iid, Vec::new()));
+                    plan.len() - 1
+                }
+            };
+            if !plan[i].1.iter().any(|(n, _)| n == name) {
+                plan[i].1.push((name.to_string(), account.to_string()));
+            }
+        };
+
+        for node in CFG.trader.work_list.iter() {
+            for (i, name) in node.strategy.iter().enumerate() {
+                // у бумажного брокера каждый счет - отдельный
+                // виртуальный счет, так варианты стратегии сравниваются
+                // на одних данных
+                let account = node
+                    .accounts
+                    .get(i)
+                    .map_or(MAIN_ACCOUNT, |name| name.as_str());
+                add(&node.iid, name, account);
+            }
+        }
+        for name in CFG.trader.strategy_list.iter() {
+            // неизвестные стратегии отсеивает check_strategys на старте,
+            // после перезагрузки плагина их просто нет в плане
+            let Ok(strategy) = self.create(name) else {
+                continue;
+            };
+            for iid in strategy.universe() {
+                add(&iid, name, MAIN_ACCOUNT);
+            }
+        }
+        for node in CFG.trader.watch_list.iter() {
+            let file = format!("{}.csv", node.list);
+            let list = match AssetList::load_name(&file) {
+                Ok(list) => list,
+                Err(e) => {
+                    log::warn!("Watch list {}: {e}", node.list);
+                    continue;
+                }
+            };
+            for asset in list.assets().iter() {
+                for name in node.strategy.iter() {
+                    add(&asset.iid().to_string(), name, MAIN_ACCOUNT);
+                }
+            }
+        }
+
+        plan
+    }
+    /// Все стратегии конфига должны быть в плагинах или среди
+    /// зарегистрированных фабрик: неизвестное имя - ошибка конфига.
+    fn check_strategys(&self) -> Result<(), AvinError> {
+        let work =
+            CFG.trader.work_list.iter().flat_map(|i| i.strategy.iter());
+        let watch =
+            CFG.trader.watch_list.iter().flat_map(|i| i.strategy.iter());
+        let mut names =
+            work.chain(CFG.trader.strategy_list.iter()).chain(watch);
+
+        match names.find(|name| !self.has_strategy(name)) {
+            Some(name) => {
+                Err(AvinError::NotFound(format!("strategy {name}")))
+            }
+            None => Ok(()),
+        }
+    }
+    async fn start_work(
+        &mut self,
+        iid: &str,
+        strategys: &[(String, String)],
+        broker: &ActionSender,
+        trader: &ActionSender,
+    ) -> Result<(), AvinError> {
+        log::info!("Load asset {iid}");
+        let mut asset = Asset::new(iid).unwrap();
+
+        // create strategys, charts are loaded by their timeframes
+        let mut created = Vec::new();
+        let mut timeframes = vec![TimeFrame::M1];
+        for (name, _) in strategys.iter() {
+            let strategy = self.create(name)?;
+            for tf in strategy.timeframes() {
+                if !timeframes.contains(&tf) {
+                    timeframes.push(tf);
+                }
+            }
+            created.push(strategy);
+        }
+        load_charts(&mut asset, &timeframes, broker).await;
+
+        // сверка с брокером по счетам стратегий, до их запуска
+        let mut checked: HashMap<String, Reconciliation> = HashMap::new();
+        for (_, account_name) in strategys.iter() {
+            if checked.contains_key(account_name) {
+                continue;
+            }
+            let account = self.account(account_name, broker).await;
+            let r = self.reconcile(&account, asset.iid(), broker).await;
+            checked.insert(account_name.clone(), r);
+        }
+
+        // init strategys
+        for ((name, account_name), strategy) in
+            strategys.iter().zip(created.iter_mut())
+        {
+            let account = self.account(account_name, broker).await;
+            log::info!("- load strategy {name}, account {account_name}");
+            strategy.set_clock(Arc::new(RealClock));
+            strategy.init(trader.clone(), account.clone(), &mut asset);
+            strategy
+                .reconcile(checked[account_name].orders_of(strategy.name()));
+            self.running
+                .push(Running::new(name, strategy, &asset, account));
+        }
+
+        // create work, add strategys
+        let mut work = Work::new(asset);
+        for strategy in created {
+            work.add_strategy(strategy);
+        }
+        if let Some(tx) = &self.fault_tx {
+            work.set_fault_sender(tx.clone());
+        }
+
+        log::info!("- start work");
+        if !self.phase.is_trading() {
+            work.get_cmd_sender().send(WorkCmd::Pause).unwrap();
+        }
+        self.works.insert(work.figi().clone(), work.get_sender());
+        self.cmds.insert(work.figi().clone(), work.get_cmd_sender());
+        tokio::spawn(async move { work.start().await });
+
+        Ok(())
+    }
+    /// Сверяет с брокером счет по инструменту: активные ордера,
+    /// позицию и операции с прошлого запуска. Сироты отменяются, если
+    /// так требует политика, расхождения пишутся в лог.
+    async fn reconcile(
+        &mut self,
+        account: &Account,
+        iid: &Iid,
+        broker: &ActionSender,
+    ) -> Reconciliation {
+        let orders = get_orders(broker, account, iid).await;
+        let position = get_position(broker, account, iid).await;
+        let from = self.since();
+        let operations = get_operations(broker, account, iid, from).await;
+
+        let r = self.reconciler.reconcile(
+            account,
+            iid,
+            orders,
+            position,
+            operations,
+            RealClock.ts(),
+        );
+        log::info!("- {} {}", account.name(), r);
+        for (owner, order) in r.gone.iter() {
+            log::warn!("- order of {owner} is gone at broker: {order}");
+        }
+        for operation in r.operations.iter() {
+            log::info!("- operation since last run: {operation}");
+        }
+        if r.is_position_mismatch() {
+            log::warn!(
+                ":: Position {} {}: broker {}, expected {}",
+                account.name(),
+                iid.ticker(),
+                r.position.unwrap_or_default(),
+                r.expected.unwrap_or_default()
+            );
+        }
+        for order in r.to_cancel() {
+            log::warn!("- cancel orphan {order}");
+            let a = OrderAction::new(
+                account.clone(),
+                iid.clone(),
+                RECONCILE_OWNER,
+                order.clone(),
+            );
+            broker.send(Action::Cancel(a)).unwrap();
+        }
+
+        r
+    }
+    /// Переход торгового дня в новую фазу: на открытии - подписки на
+    /// данные, вне торговых фаз стратегии на паузе, перед закрытием
+    /// (если задано в календаре) позиции брокера по счетам стратегий
+    /// закрываются, после закрытия подписки снимаются до следующего
+    /// открытия.
+    async fn session(
+        &mut self,
+        phase: SessionPhase,
+        broker: &ActionSender,
+        risk: &mut RiskManager,
+    ) {
+        if phase == self.phase {
+            return;
+        }
+        log::info!(":: Session {} -> {phase}", self.phase);
+        let old = std::mem::replace(&mut self.phase, phase);
+
+        if phase == SessionPhase::Closing {
+            let positions = self.positions(broker).await;
+            for a in risk.close_all(&positions) {
+                log::warn!(":: Session flatten {a}");
+                broker.send(Action::Post(a)).unwrap();
+            }
+        }
+        for tx in self.cmds.values() {
+            let cmd = match phase.is_trading() {
+                true => WorkCmd::Resume,
+                false => WorkCmd::Pause,
+            };
+            let _ = tx.send(cmd);
+        }
+        if old == SessionPhase::Closed || phase == SessionPhase::Closed {
+            self.subscribe(broker);
+        }
+    }
+    /// Фактические позиции брокера по счетам и инструментам стратегий.
+    async fn positions(
+        &self,
+        broker: &ActionSender,
+    ) -> Vec<(Account, Iid, i64)> {
+        let mut positions: Vec<(Account, Iid, i64)> = Vec::new();
+        for i in self.running.iter() {
+            if positions.iter().any(|(a, iid, _)| {
+                a.name() == i.account.name() && iid == &i.iid
+            }) {
+                continue;
+            }
+            match get_position(broker, &i.account, &i.iid).await {
+                Some(position) => positions.push((
+                    i.account.clone(),
+                    i.iid.clone(),
+                    position,
+                )),
+                None => log::error!(
+                    ":: Session flatten {} {}: position unknown",
+                    i.account.name(),
+                    i.iid
+                ),
+            }
+        }
+
+        positions
+    }
+    /// Падение стратегии в ее задаче: уведомление и перезапуск новым
+    /// экземпляром с чистым состоянием после паузы, открытые ордера
+    /// она получает от брокера. После trader.max_restarts перезапусков стратегия
+    /// остается остановленной до перезапуска трейдера, так же как если
+    /// ее больше нечем создать (плагин выгружен).
+    fn fault(
+        &mut self,
+        fault: WorkFault,
+        notifier: &Notifier,
+        broker: &ActionSender,
+        trader: &ActionSender,
+    ) {
+        let Some(i) = self
+            .running
+            .iter()
+            .position(|i| i.figi == fault.figi && i.owner == fault.owner)
+        else {
+            return;
+        };
+
+        let restarts = self.running[i].restarts;
+        let stop = restarts >= CFG.trader.max_restarts;
+        let message = match stop {
+            true => format!("{}, stopped", fault.reason),
+            false => format!("{}, restart {}", fault.reason, restarts + 1),
+        };
+        let a = NotifyAction::new(&fault.owner, NotifyLevel::Alert, &message);
+        notifier.notify(&a, RealClock.ts());
+        let strategy = match stop {
+            true => None,
+            false => self
+                .create(&self.running[i].name)
+                .inspect_err(|e| {
+                    log::error!(":: Restart {}: {e}", fault.owner)
+                })
+                .ok(),
+        };
+        let Some(mut strategy) = strategy else {
+            let i = self.running.remove(i);
+            let _ = self.cmds[&i.figi].send(WorkCmd::Remove(i.owner));
+            self.subscribe(broker);
+            return;
+        };
+        strategy.set_clock(Arc::new(RealClock));
+        let running = &mut self.running[i];
+        running.restarts += 1;
+
+        // перезапуск с паузой, растущей вдвое: стратегия, падающая на
+        // каждом баре, не тратит перезапуски за миллисекунды
+        let delay =
+            RESTART_DELAY.saturating_mul(2_u32.saturating_pow(restarts));
+        let cmd_tx = self.cmds[&running.figi].clone();
+        let broker = broker.clone();
+        let trader = trader.clone();
+        let account = running.account.clone();
+        let iid = running.iid.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let cmd = WorkCmd::Replace {
+                strategy,
+                trader,
+                orders: get_orders(&broker, &account, &iid).await,
+                account,
+            };
+            let _ = cmd_tx.send(cmd);
+        });
+    }
+    /// Паспорт запущенной стратегии, закрывшей сделку.
+    fn meta(&self, trade: &ClosedTrade) -> Option<&StrategyMeta> {
+        self.running
+            .iter()
+            .find(|i| {
+                i.figi == *trade.iid.figi() && i.owner == trade.strategy
+            })
+            .map(|i| &i.meta)
+    }
+    async fn account(
+        &mut self,
+        name: &str,
+        broker: &ActionSender,
+    ) -> Account {
+        if !self.accounts.contains_key(name) {
+            let account = get_account(broker, name).await;
+            let from = self.since();
+            for operation in get_cash_operations(broker, &account, from).await
+            {
+                log::info!("- cash operation since last run: {operation}");
+            }
+            self.accounts.insert(name.to_string(), account);
+        }
+
+        self.accounts[name].clone()
+    }
+    /// Начало периода сверки: прошлый запуск, а при первом запуске -
+    /// последние сутки.
+    fn since(&self) -> DateTime<Utc> {
+        match self.reconciler.ts() {
+            0 => Utc::now() - TimeDelta::days(1),
+            ts => utils::dt(ts),
+        }
+    }
+    /// Сверяет потоки данных запущенных стратегий с подписками брокера:
+    /// бары 1М обновляют графики всех таймфреймов, остальные потоки -
+    /// по запросу стратегий. Когда биржа закрыта, подписок нет.
+    fn subscribe(&mut self, broker: &ActionSender) {
+        let wanted: Vec<(Iid, Vec<MarketData>)> = self
+            .running
+            .iter()
+            .filter(|_| self.phase != SessionPhase::Closed)
+            .map(|i| (i.iid.clone(), i.market_data.clone()))
+            .collect();
+        for a in self.subscriptions.update(&wanted) {
+            broker.send(a).unwrap();
+        }
+    }
+    /// Перезагрузка измененных и выгрузка удаленных плагинов.
+    async fn check_plugins(
+        &mut self,
+        broker: &ActionSender,
+        trader: &ActionSender,
+    ) {
+        let mut changed = false;
+        let mut i = 0;
+        while i < self.plugins.len() {
+            if !self.plugins[i].is_changed() {
+                i += 1;
+                continue;
+            }
+
+            let old = self.plugins.remove(i);
+            let path = old.path().to_path_buf();
+            if !old.is_exist() {
+                log::warn!(":: Unload plugin {}", path.display());
+                self.replace(&old, broker, trader).await;
+                changed = true;
+                continue;
+            }
+            match Plugin::load(&path) {
+                Ok(new) => {
+                    log::warn!(":: Reload plugin {}", path.display());
+                    self.plugins.insert(i, new);
+                    self.replace(&old, broker, trader).await;
+                    changed = true;
+                    i += 1;
+                }
+                Err(e) => {
+                    // недособранный файл: старая версия работает
+                    // дальше, попробуем на следующей проверке
+                    log::error!("Reload {e}");
+                    self.plugins.insert(i, old);
+                    i += 1;
+                }
+            }
+        }
+        if !changed {
+            return;
+        }
+
+        // инструменты стратегий могли измениться: новые работы для
+        // новых инструментов, затем сверка подписок
+        for (iid, strategys) in self.plan() {
+            let figi = Manager::find_iid(&iid).unwrap().figi().clone();
+            if self.works.contains_key(&figi) {
+                continue;
+            }
+            if let Err(e) =
+                self.start_work(&iid, &strategys, broker, trader).await
+            {
+                log::error!("Start work {iid}: {e}");
+            }
+        }
+        self.subscribe(broker);
+    }
+    /// Заменяет в работах стратегии старого плагина текущими версиями,
+    /// стратегии, которых больше нет или которые ушли с инструмента,
+    /// удаляются. Стратегии, пришедшие на инструмент с работой,
+    /// добавляются в нее.
+    async fn replace(
+        &mut self,
+        old: &Plugin,
+        broker: &ActionSender,
+        trader: &ActionSender,
+    ) {
+        let plan = self.plan();
+        let planned = |figi: &str, name: &str| {
+            plan.iter().any(|(iid, strategys)| {
+                Manager::find_iid(iid).is_ok_and(|i| i.figi() == figi)
+                    && strategys.iter().any(|(n, _)| n == name)
+            })
+        };
+
+        let running = std::mem::take(&mut self.running);
+        for mut i in running {
+            if !old.contains(&i.name) {
+                self.running.push(i);
+                continue;
+            }
+            let strategy = self
+                .plugins
+                .iter()
+                .find(|p| p.contains(&i.name))
+                .and_then(|p| p.create(&i.name))
+                .filter(|_| planned(&i.figi, &i.name));
+            let cmd = match strategy {
+                Some(mut strategy) => {
+                    strategy.set_clock(Arc::new(RealClock));
+                    i.market_data = market_data(strategy.as_ref());
+                    i.meta = strategy.meta();
+                    let cmd = WorkCmd::Replace {
+                        strategy,
+                        trader: trader.clone(),
+                        account: i.account.clone(),
+                        orders: get_orders(broker, &i.account, &i.iid).await,
+                    };
+                    self.cmds[&i.figi].send(cmd).unwrap();
+                    self.running.push(i);
+                    continue;
+                }
+                None => WorkCmd::Remove(i.owner.clone()),
+            };
+            self.cmds[&i.figi].send(cmd).unwrap();
+        }
+
+        // стратегии, пришедшие на инструменты с работами
+        for (iid, strategys) in plan.iter() {
+            let Ok(iid) = Manager::find_iid(iid) else {
+                continue;
+            };
+            let figi = iid.figi();
+            if !self.cmds.contains_key(figi) {
+                continue;
+            }
+            for (name, account_name) in strategys.iter() {
+                let is_running = self
+                    .running
+                    .iter()
+                    .any(|i| i.figi == *figi && i.name == *name);
+                if is_running || !old.contains(name) {
+                    continue;
+                }
+                let Some(mut strategy) =
+                    self.plugins.iter().find_map(|p| p.create(name))
+                else {
+                    continue;
+                };
+                let Some(account) = self.accounts.get(account_name).cloned()
+                else {
+                    continue;
+                };
+                strategy.set_clock(Arc::new(RealClock));
+                self.running.push(Running {
+                    figi: figi.clone(),
+                    iid: iid.clone(),
+                    name: name.clone(),
+                    owner: strategy.name().to_string(),
+                    meta: strategy.meta(),
+                    account: account.clone(),
+                    market_data: market_data(strategy.as_ref()),
+                    restarts: 0,
+                });
+                let cmd = WorkCmd::Replace {
+                    strategy,
+                    trader: trader.clone(),
+                    orders: get_orders(broker, &account, &iid).await,
+                    account,
+                };
+                self.cmds[figi].send(cmd).unwrap();
+            }
+        }
+    }
 }
 
-fn load_charts(asset: &mut Asset) {
+/// Запущенная стратегия: инструмент работы, имя в конфиге, имя
+/// стратегии (владелец ордеров), ее паспорт, счет и нужные ей потоки
+/// данных, число перезапусков после падений.
+struct Running {
+    figi: String,
+    iid: Iid,
+    name: String,
+    owner: String,
+    meta: StrategyMeta,
+    account: Account,
+    market_data: Vec<MarketData>,
+    restarts: u32,
+}
+impl Running {
+    fn new(
+        name: &str,
+        strategy: &dyn Strategy,
+        asset: &Asset,
+        account: Account,
+    ) -> Self {
+        Self {
+            figi: asset.figi().clone(),
+            iid: asset.iid().clone(),
+            name: name.to_string(),
+            owner: strategy.name().to_string(),
+            meta: strategy.meta(),
+            account,
+            market_data: market_data(strategy),
+            restarts: 0,
+        }
+    }
+}
+/// Потоки данных стратегии: бары 1М всегда и ее собственные.
+fn market_data(strategy: &dyn Strategy) -> Vec<MarketData> {
+    let mut market_data = vec![MarketData::BAR_1M];
+    for md in strategy.market_data() {
+        if !market_data.contains(&md) {
+            market_data.push(md);
+        }
+    }
+
+    market_data
+}
+
+/// Графики таймфреймов стратегий с историей от брокера, чтобы
+/// индикаторы стратегий в init подключались к заполненным графикам.
+async fn load_charts(
+    asset: &mut Asset,
+    timeframes: &[TimeFrame],
+    tx: &tokio::sync::mpsc::UnboundedSender<Action>,
+) {
     log::info!("- load charts");
 
+    let count = CFG.core.default_bars_count as i32;
+    let till = Utc::now();
     for tf in TimeFrame::all() {
+        if !timeframes.contains(&tf) {
+            continue;
+        }
         asset.load_chart_empty(tf);
+
+        let from = till - TimeDelta::nanoseconds(tf.nanos()) * count;
+        let (bars_tx, bars_rx) = tokio::sync::oneshot::channel();
+        let a =
+            GetBarsAction::new(asset.iid().clone(), tf, from, till, bars_tx);
+        tx.send(Action::GetBars(a)).unwrap();
+
+        let Ok(bars) = bars_rx.await else {
+            log::warn!("- chart {tf} without history");
+            continue;
+        };
+        let chart = asset.chart_mut(tf).unwrap();
+        for bar in bars {
+            chart.add_bar(bar);
+        }
     }
 }
+async fn get_account(
+    tx: &tokio::sync::mpsc::UnboundedSender<Action>,
+    name: &str,
+) -> Account {
+    let (account_tx, account_rx) = tokio::sync::oneshot::channel();
+    let a = Action::GetAccount(GetAccountAction::new(name, account_tx));
+    tx.send(a).unwrap();
+
+    match account_rx.await {
+        Ok(account) => account,
+        Err(_) => todo!(),
+    }
+}
+/// Активные ордера брокера по счету и инструменту. Брокер не
+/// ответил - ордеров нет.
+async fn get_orders(
+    tx: &tokio::sync::mpsc::UnboundedSender<Action>,
+    account: &Account,
+    iid: &Iid,
+) -> Vec<Order> {
+    let (orders_tx, orders_rx) = tokio::sync::oneshot::channel();
+    let a = GetOrdersAction::new(account.clone(), iid.clone(), orders_tx);
+    tx.send(Action::GetOrders(a)).unwrap();
+
+    orders_rx.await.unwrap_or_else(|_| {
+        log::warn!("- orders of {} not received", account.name());
+        Vec::new()
+    })
+}
+/// Позиция брокера по счету и инструменту в штуках. Брокер не
+/// ответил - позиция неизвестна.
+async fn get_position(
+    tx: &tokio::sync::mpsc::UnboundedSender<Action>,
+    account: &Account,
+    iid: &Iid,
+) -> Option<i64> {
+    let (position_tx, position_rx) = tokio::sync::oneshot::channel();
+    let a = GetPositionAction::new(account.clone(), iid.clone(), position_tx);
+    tx.send(Action::GetPosition(a)).unwrap();
+
+    position_rx.await.ok().or_else(|| {
+        log::warn!("- position of {} not received", account.name());
+        None
+    })
+}
+/// Операции брокера по счету и инструменту начиная с from. Брокер не
+/// ответил - операций нет.
+async fn get_operations(
+    tx: &tokio::sync::mpsc::UnboundedSender<Action>,
+    account: &Account,
+    iid: &Iid,
+    from: DateTime<Utc>,
+) -> Vec<Operation> {
+    let (operations_tx, operations_rx) = tokio::sync::oneshot::channel();
+    let a = GetOperationsAction::new(
+        account.clone(),
+        iid.clone(),
+        from,
+        operations_tx,
+    );
+    tx.send(Action::GetOperations(a)).unwrap();
+
+    operations_rx.await.unwrap_or_else(|_| {
+        log::warn!("- operations of {} not received", account.name());
+        Vec::new()
+    })
+}
+/// Неторговые денежные операции брокера по счету начиная с from.
+/// Брокер не ответил - операций нет.
+async fn get_cash_operations(
+    tx: &tokio::sync::mpsc::UnboundedSender<Action>,
+    account: &Account,
+    from: DateTime<Utc>,
+) -> Vec<CashOperation> {
+    let (operations_tx, operations_rx) = tokio::sync::oneshot::channel();
+    let a =
+        GetCashOperationsAction::new(account.clone(), from, operations_tx);
+    tx.send(Action::GetCashOperations(a)).unwrap();
+
+    operations_rx.await.unwrap_or_else(|_| {
+        log::warn!("- cash operations of {} not received", account.name());
+        Vec::new()
+    })
+}
+/// Отклоненный риск менеджером ордер, как событие брокера.
+fn reject(a: OrderAction, reason: &str) -> Option<OrderEvent> {
+    let order = match a.order {
+        Order::Market(MarketOrder::New(o)) => {
+            Order::Market(MarketOrder::Rejected(o.reject(reason)))
+        }
+        Order::Limit(LimitOrder::New(o)) => {
+            Order::Limit(LimitOrder::Rejected(o.reject(reason)))
+        }
+        Order::Stop(StopOrder::New(o)) => {
+            Order::Stop(StopOrder::Rejected(o.reject(reason)))
+        }
+        order => {
+            log::error!("Risk reject not new order: {order}");
+            return None;
+        }
+    };
+
+    Some(OrderEvent::new(a.account, a.iid, a.owner, order))
+}
 async fn start_broker(mut broker: Tinkoff) {
     broker.start().await
 }
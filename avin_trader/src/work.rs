@@ -5,70 +5,484 @@
  * LICENSE:     MIT
  ****************************************************************************/
 
-use avin_core::{Asset, Event};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::{Mutex, Notify};
+
+use avin_core::{Account, Action, Asset, Event, Order, OrderEvent};
 use avin_strategy::Strategy;
 
+/// Command of trader to running work.
+///
+/// # ru
+/// Команда трейдера работающей работе: замена стратегии новой версией
+/// из перезагруженного плагина или удаление стратегии выгруженного.
+/// Новая стратегия инициализируется на графиках работы и начинает с
+/// чистого состояния, открытую позицию она видит только через счет.
+/// Так же трейдер перезапускает упавшую стратегию. Пауза - стратегии
+/// не обрабатывают бары (клиринг, закрытие сессии), графики и события
+/// ордеров обновляются.
+pub enum WorkCmd {
+    Replace {
+        strategy: Box<dyn Strategy>,
+        trader: UnboundedSender<Action>,
+        account: Account,
+        /// Активные ордера брокера для [`Strategy::reconcile`].
+        orders: Vec<Order>,
+    },
+    Remove(String),
+    Pause,
+    Resume,
+}
+
+/// Fault of strategy: its task panicked, strategy is stopped.
+///
+/// # ru
+/// Падение стратегии: ее задача запаниковала, стратегия остановлена.
+/// Остальные стратегии работы и обновление графиков продолжают
+/// работать, решение о перезапуске принимает трейдер.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkFault {
+    pub figi: String,
+    /// Имя стратегии - владелец ее ордеров.
+    pub owner: String,
+    /// Сообщение паники.
+    pub reason: String,
+}
+impl std::fmt::Display for WorkFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Fault {} {}: {}", self.figi, self.owner, self.reason)
+    }
+}
+
+/// Work of trader on one instrument.
+///
+/// # ru
+/// Работа трейдера по одному инструменту. Сама работа только обновляет
+/// графики актива событиями брокера, каждая стратегия работает в своей
+/// задаче tokio и видит актив через общую блокировку. Работа не ждет
+/// блокировку, пока ее держит стратегия: рыночные события копятся в
+/// очереди работы и применяются, как только актив освободится, события
+/// ордеров и команды трейдера тем временем идут дальше. Новые бары
+/// будят задачу стратегии, пока она занята - пробуждения схлопываются
+/// в одно: медленная стратегия пропускает бары, но не обрабатывает
+/// одно и то же состояние актива несколько раз. Паника
+/// стратегии останавливает только ее задачу: работа сообщает о падении
+/// трейдеру ([`WorkFault`]) и удаляет задачу, рыночные данные и другие
+/// стратегии работают дальше.
 pub struct Work {
-    asset: Asset,
+    asset: Arc<Mutex<Asset>>,
+    figi: String,
     strategys: Vec<Box<dyn Strategy>>,
-    in_tx: tokio::sync::mpsc::UnboundedSender<Event>,
-    in_rx: tokio::sync::mpsc::UnboundedReceiver<Event>,
+    slots: Vec<Slot>,
+    next_slot: u64,
+    /// Рыночные события, ждущие освобождения актива.
+    pending: VecDeque<Event>,
+    /// Стратегия отпустила актив.
+    released: Arc<Notify>,
+    /// Завершенные задачи стратегий.
+    exit_tx: UnboundedSender<u64>,
+    exit_rx: UnboundedReceiver<u64>,
+    in_tx: UnboundedSender<Event>,
+    in_rx: UnboundedReceiver<Event>,
+    cmd_tx: UnboundedSender<WorkCmd>,
+    cmd_rx: UnboundedReceiver<WorkCmd>,
+    fault_tx: Option<UnboundedSender<WorkFault>>,
+    paused: bool,
 }
 impl Work {
     pub fn new(asset: Asset) -> Work {
         let (in_tx, in_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (cmd_tx, cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (exit_tx, exit_rx) = tokio::sync::mpsc::unbounded_channel();
 
         Work {
-            asset,
+            figi: asset.figi().clone(),
+            asset: Arc::new(Mutex::new(asset)),
             strategys: Vec::new(),
+            slots: Vec::new(),
+            next_slot: 0,
+            pending: VecDeque::new(),
+            released: Arc::new(Notify::new()),
+            exit_tx,
+            exit_rx,
             in_tx,
             in_rx,
+            cmd_tx,
+            cmd_rx,
+            fault_tx: None,
+            paused: false,
         }
     }
 
     pub fn figi(&self) -> &String {
-        self.asset.figi()
+        &self.figi
     }
-    pub fn asset(&mut self) -> &mut Asset {
-        &mut self.asset
+    pub fn asset(&self) -> Arc<Mutex<Asset>> {
+        self.asset.clone()
     }
-    pub fn strategys(&self) -> &Vec<Box<dyn Strategy>> {
-        &self.strategys
+    /// Names of strategies: waiting start and running.
+    ///
+    /// # ru
+    /// Имена стратегий: ожидающих запуска и работающих.
+    pub fn names(&self) -> Vec<String> {
+        self.strategys
+            .iter()
+            .map(|i| i.name().to_string())
+            .chain(self.slots.iter().map(|i| i.owner.clone()))
+            .collect()
     }
 
+    /// Add initialized strategy, its task is spawned at start.
+    ///
+    /// # ru
+    /// Добавляет инициализированную стратегию, ее задача запускается
+    /// в [`Work::start`].
     pub fn add_strategy(&mut self, strategy: impl Strategy) {
         self.strategys.push(Box::new(strategy));
     }
-    pub fn get_sender(&self) -> tokio::sync::mpsc::UnboundedSender<Event> {
+    /// Set channel of strategy faults, without it faults are only
+    /// logged.
+    ///
+    /// # ru
+    /// Устанавливает канал падений стратегий, без него падения только
+    /// пишутся в лог.
+    pub fn set_fault_sender(&mut self, tx: UnboundedSender<WorkFault>) {
+        self.fault_tx = Some(tx);
+    }
+    pub fn get_sender(&self) -> UnboundedSender<Event> {
         self.in_tx.clone()
     }
+    pub fn get_cmd_sender(&self) -> UnboundedSender<WorkCmd> {
+        self.cmd_tx.clone()
+    }
 
     pub async fn start(&mut self) {
-        while let Some(e) = self.in_rx.recv().await {
+        for strategy in std::mem::take(&mut self.strategys) {
+            self.spawn(strategy, None);
+        }
+
+        while self.step().await {}
+    }
+
+    // private
+    /// Один шаг работы: событие брокера, команда трейдера, освобождение
+    /// актива или завершение задачи стратегии. false - канал событий
+    /// закрыт, работа завершается.
+    async fn step(&mut self) -> bool {
+        tokio::select! {
+            e = self.in_rx.recv() => match e {
+                Some(e) => self.event(e),
+                None => return false,
+            },
+            Some(cmd) = self.cmd_rx.recv() => self.cmd(cmd),
+            _ = self.released.notified() => self.apply(),
+            Some(id) = self.exit_rx.recv() => {
+                self.slots.retain(|i| i.id != id);
+            }
+        }
+
+        true
+    }
+    fn event(&mut self, e: Event) {
+        match e {
+            Event::Bar(_) | Event::Tic(_) => {
+                self.pending.push_back(e);
+                self.apply();
+            }
+            Event::Book(_) => {}
+            Event::Order(e) => {
+                let Some(slot) =
+                    self.slots.iter().find(|i| *i.owner == e.owner)
+                else {
+                    return;
+                };
+                if slot.tx.send(e).is_err() {
+                    log::warn!(
+                        ":: Work {} order event to stopped {}",
+                        self.figi,
+                        slot.owner
+                    );
+                }
+            }
+        }
+    }
+    /// Применяет накопленные рыночные события, если актив свободен,
+    /// иначе их применит пробуждение от отпустившей актив стратегии.
+    fn apply(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let Ok(mut asset) = self.asset.try_lock() else {
+            return;
+        };
+
+        let mut new_bar = false;
+        while let Some(e) = self.pending.pop_front() {
             match e {
                 Event::Bar(e) => {
-                    self.asset.bar_event(e);
-                    self.process_all_strategy();
-                }
-                Event::Tic(e) => {
-                    self.asset.tic_event(e);
+                    asset.bar_event(e);
+                    new_bar = true;
                 }
-                Event::Order(e) => {
-                    for strategy in self.strategys.iter_mut() {
-                        if *strategy.name() == e.owner {
-                            strategy.order_event(e);
-                            break;
-                        }
-                    }
+                Event::Tic(e) => asset.tic_event(e),
+                _ => unreachable!(),
+            }
+        }
+        drop(asset);
+
+        if new_bar {
+            self.process_all_strategy();
+        }
+    }
+    fn cmd(&mut self, cmd: WorkCmd) {
+        match cmd {
+            WorkCmd::Replace {
+                strategy,
+                trader,
+                account,
+                orders,
+            } => {
+                let name = strategy.name().to_string();
+                log::info!(":: Work {} reload {name}", self.figi);
+                // старая задача завершится, когда закроется ее канал
+                self.slots.retain(|i| i.owner != name);
+                self.spawn(strategy, Some((trader, account, orders)));
+            }
+            WorkCmd::Remove(name) => {
+                log::info!(":: Work {} remove {name}", self.figi);
+                self.slots.retain(|i| i.owner != name);
+            }
+            WorkCmd::Pause => self.paused = true,
+            WorkCmd::Resume => self.paused = false,
+        }
+    }
+    fn process_all_strategy(&mut self) {
+        if self.paused {
+            return;
+        }
+
+        // упавшие стратегии удалены, их перезапускает трейдер
+        for slot in self.slots.iter() {
+            slot.process.notify_one();
+        }
+    }
+    /// Запускает задачу стратегии под надзором: паника стратегии
+    /// перехватывается и превращается в [`WorkFault`].
+    ///
+    /// Имя и сообщение паники стратегии плагина указывают в память его
+    /// библиотеки, поэтому здесь все хранится копиями, а упавшая
+    /// стратегия (и с ней библиотека) удаляется только после отправки
+    /// сообщения о падении.
+    fn spawn(&mut self, strategy: Box<dyn Strategy>, init: Option<Init>) {
+        let owner = strategy.name().to_string();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let process = Arc::new(Notify::new());
+        let id = self.next_slot;
+        self.next_slot += 1;
+        self.slots.push(Slot {
+            id,
+            owner: owner.clone(),
+            tx,
+            process: process.clone(),
+        });
+
+        let asset = Shared {
+            asset: self.asset.clone(),
+            released: self.released.clone(),
+        };
+        let task = tokio::spawn(run(strategy, asset, init, rx, process));
+        let figi = self.figi.clone();
+        let fault_tx = self.fault_tx.clone();
+        let exit_tx = self.exit_tx.clone();
+        tokio::spawn(async move {
+            let r = task.await;
+            // задача завершена: работа удаляет ее слот, события ордеров
+            // упавшей стратегии больше не копятся в ее канале
+            let _ = exit_tx.send(id);
+            let (reason, fallen) = match r {
+                Ok(Ok(())) => return,
+                Ok(Err((reason, strategy))) => (reason, Some(strategy)),
+                Err(e) if e.is_panic() => {
+                    (panic_message(e.into_panic()), None)
                 }
+                Err(_) => return,
+            };
+            let fault = WorkFault {
+                figi,
+                owner,
+                reason,
+            };
+            log::error!(":: {fault}");
+            if let Some(tx) = fault_tx {
+                let _ = tx.send(fault);
             }
+            drop(fallen);
+        });
+    }
+}
+
+/// Задача стратегии: канал событий ордеров (по порядку) и пробуждение
+/// на новый бар.
+struct Slot {
+    id: u64,
+    owner: String,
+    tx: UnboundedSender<OrderEvent>,
+    process: Arc<Notify>,
+}
+/// Актив работы для задачи стратегии: после каждого обращения
+/// стратегия будит работу, чтобы та применила накопленные события.
+struct Shared {
+    asset: Arc<Mutex<Asset>>,
+    released: Arc<Notify>,
+}
+/// Инициализация новой стратегии в ее задаче: канал трейдера, счет и
+/// активные ордера брокера.
+type Init = (UnboundedSender<Action>, Account, Vec<Order>);
+
+/// Упавшая стратегия: сообщение паники и сама стратегия.
+type Fallen = (String, Box<dyn Strategy>);
+
+/// Задача стратегии: инициализация (если нужна), затем обработка
+/// событий ордеров и баров до закрытия канала. События ордеров идут
+/// раньше бара: стратегия решает, зная ответы брокера. Паника в
+/// стратегии перехватывается, пока стратегия жива, и задача
+/// возвращает ее вместе с сообщением.
+async fn run(
+    mut strategy: Box<dyn Strategy>,
+    shared: Shared,
+    init: Option<Init>,
+    mut rx: UnboundedReceiver<OrderEvent>,
+    process: Arc<Notify>,
+) -> Result<(), Fallen> {
+    if let Some((trader, account, orders)) = init {
+        let mut asset = shared.asset.lock().await;
+        let r = guard(|| {
+            strategy.init(trader, account, &mut asset);
+            strategy.reconcile(orders);
+        });
+        drop(asset);
+        shared.released.notify_one();
+        if let Err(reason) = r {
+            return Err((reason, strategy));
         }
     }
 
-    // private
-    fn process_all_strategy(&mut self) {
-        for strategy in self.strategys.iter_mut() {
-            strategy.process(&self.asset);
+    loop {
+        let r = tokio::select! {
+            biased;
+            e = rx.recv() => match e {
+                Some(e) => guard(|| strategy.order_event(e)),
+                None => break,
+            },
+            _ = process.notified() => {
+                let asset = shared.asset.lock().await;
+                let r = guard(|| strategy.process(&asset));
+                drop(asset);
+                shared.released.notify_one();
+                r
+            }
+        };
+        if let Err(reason) = r {
+            return Err((reason, strategy));
+        }
+    }
+
+    Ok(())
+}
+/// Вызов стратегии с перехватом паники, ошибка - сообщение паники.
+fn guard(f: impl FnOnce()) -> Result<(), String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+        .map_err(panic_message)
+}
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avin_core::fixture::{self, iid};
+    use avin_core::{Bar, TimeFrame};
+
+    use super::*;
+
+    /// Стратегия, которая держит актив, пока ее не отпустят, и
+    /// падает на втором баре.
+    struct Slow {
+        gate: std::sync::mpsc::Receiver<()>,
+    }
+    impl Strategy for Slow {
+        fn name(&self) -> &'static str {
+            "Slow"
+        }
+        fn init(&mut self, _: Trader, _: Account, _: &mut Asset) {}
+        fn process(&mut self, asset: &Asset) {
+            if asset.chart(TimeFrame::M1).unwrap().bars().len() > 1 {
+                panic!("boom");
+            }
+            self.gate.recv().unwrap();
+        }
+        fn order_event(&mut self, _: OrderEvent) {}
+    }
+    type Trader = UnboundedSender<Action>;
+
+    fn bar(ts: i64) -> Event {
+        fixture::bar_event(Bar::new(ts, 1.0, 1.0, 1.0, 1.0, 1))
+    }
+
+    #[test]
+    fn guard_panic() {
+        assert_eq!(guard(|| {}), Ok(()));
+        assert_eq!(guard(|| panic!("boom")), Err("boom".to_string()));
+    }
+    #[test]
+    fn panic_payload() {
+        let e = std::panic::catch_unwind(|| panic!("boom")).unwrap_err();
+        assert_eq!(panic_message(e), "boom");
+
+        let e =
+            std::panic::catch_unwind(|| panic!("bar {}", 42)).unwrap_err();
+        assert_eq!(panic_message(e), "bar 42");
+    }
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn busy_strategy_and_fault() {
+        let mut asset = Asset::from_iid(iid());
+        asset.load_chart_empty(TimeFrame::M1);
+        let mut work = Work::new(asset);
+        let (fault_tx, mut fault_rx) = tokio::sync::mpsc::unbounded_channel();
+        work.set_fault_sender(fault_tx);
+        let (gate, rx) = std::sync::mpsc::sync_channel(0);
+        work.spawn(Box::new(Slow { gate: rx }), None);
+        let tx = work.get_sender();
+
+        // стратегия держит актив: бары ждут в очереди, работа не стоит
+        tx.send(bar(0)).unwrap();
+        work.step().await;
+        while work.asset.try_lock().is_ok() {
+            tokio::task::yield_now().await;
+        }
+        tx.send(bar(60_000_000_000)).unwrap();
+        work.step().await;
+        assert_eq!(work.pending.len(), 1);
+        gate.send(()).unwrap();
+
+        // стратегия отпустила актив: бар применен, на нем она падает
+        while !work.pending.is_empty() {
+            work.step().await;
+        }
+        let fault = fault_rx.recv().await.unwrap();
+        assert_eq!(fault.owner, "Slow");
+        assert_eq!(fault.reason, "boom");
+        while !work.slots.is_empty() {
+            work.step().await;
         }
+        assert!(work.names().is_empty());
     }
 }
@@ -156,6 +156,24 @@ pub struct LogSettings {
 pub struct DataSettings {
     pub format: String,
     pub converter: Vec<ConvertRule>,
+    #[serde(default)]
+    pub synthetic: Vec<SyntheticCfg>,
+    #[serde(default)]
+    pub continuous: Vec<ContinuousCfg>,
+    #[serde(default)]
+    pub adjusted: bool,
+    #[serde(default)]
+    pub adjust_dividends: bool,
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+    #[serde(default = "default_cache_max_age")]
+    pub cache_max_age: u32,
+}
+fn default_cache_ttl() -> u64 {
+    3600
+}
+fn default_cache_max_age() -> u32 {
+    7
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ConvertRule {
@@ -164,6 +182,33 @@ pub struct ConvertRule {
     pub output: String,
 }
 #[derive(Debug, Deserialize, Serialize)]
+pub struct SyntheticCfg {
+    pub iid: String,
+    pub name: String,
+    pub legs: Vec<LegCfg>,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LegCfg {
+    pub iid: String,
+    pub weight: f64,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContinuousCfg {
+    pub iid: String,
+    pub name: String,
+    pub step: f64,
+    pub contracts: Vec<ContractCfg>,
+    #[serde(default)]
+    pub roll_days: u32,
+    #[serde(default)]
+    pub adjust: String,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContractCfg {
+    pub iid: String,
+    pub expiration: String,
+}
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CoreSettings {
     pub default_asset_list: String,
     pub default_bars_count: usize,
@@ -171,15 +216,195 @@ pub struct CoreSettings {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TesterSettings {
     pub default_commission: f64,
+    #[serde(default)]
+    pub holdout: Vec<HoldoutCfg>,
+    #[serde(default)]
+    pub holdout_warn: bool,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HoldoutCfg {
+    pub iid: String,
+    pub begin: String,
+    #[serde(default)]
+    pub end: String,
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TraderSettings {
     pub work_list: Vec<WorkCfg>,
+    #[serde(default)]
+    pub strategy_list: Vec<String>,
+    #[serde(default)]
+    pub watch_list: Vec<WatchCfg>,
+    #[serde(default)]
+    pub paper: bool,
+    #[serde(default = "default_paper_deposit")]
+    pub paper_deposit: f64,
+    #[serde(default)]
+    pub paper_imperfection: String,
+    #[serde(default)]
+    pub paper_session: String,
+    #[serde(default)]
+    pub risk: RiskCfg,
+    #[serde(default)]
+    pub notify: NotifyCfg,
+    #[serde(default)]
+    pub plugins: Vec<String>,
+    #[serde(default)]
+    pub journal: String,
+    #[serde(default)]
+    pub reconcile: ReconcileCfg,
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    #[serde(default)]
+    pub session: SessionCfg,
+}
+fn default_paper_deposit() -> f64 {
+    100_000.0
+}
+fn default_max_restarts() -> u32 {
+    3
+}
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RiskCfg {
+    #[serde(flatten)]
+    pub rules: RiskRulesCfg,
+    #[serde(default)]
+    pub flatten: bool,
+    #[serde(default)]
+    pub restricted: Vec<String>,
+    #[serde(default)]
+    pub log: String,
+    #[serde(default)]
+    pub strategy: Vec<StrategyRiskCfg>,
+}
+/// Trading sessions of trader, Moscow time.
+///
+/// # ru
+/// Торговые сессии трейдера, московское время "ЧЧ:ММ-ЧЧ:ММ". По
+/// умолчанию расписание MOEX: основная сессия 10:00-18:40, клиринг
+/// 14:00-14:05, вечерняя сессия 19:05-23:50. Пустая вечерняя - ее нет.
+/// holidays - нерабочие дни "ГГГГ-ММ-ДД", flatten - за сколько минут
+/// до закрытия закрыть позиции, 0 - не закрывать.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionCfg {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_session_main")]
+    pub main: String,
+    #[serde(default = "default_session_clearing")]
+    pub clearing: Vec<String>,
+    #[serde(default = "default_session_evening")]
+    pub evening: String,
+    #[serde(default)]
+    pub holidays: Vec<String>,
+    #[serde(default)]
+    pub flatten: u32,
+}
+impl Default for SessionCfg {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            main: default_session_main(),
+            clearing: default_session_clearing(),
+            evening: default_session_evening(),
+            holidays: Vec::new(),
+            flatten: 0,
+        }
+    }
+}
+fn default_session_main() -> String {
+    "10:00-18:40".to_string()
+}
+fn default_session_clearing() -> Vec<String> {
+    vec!["14:00-14:05".to_string()]
+}
+fn default_session_evening() -> String {
+    "19:05-23:50".to_string()
+}
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ReconcileCfg {
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub orphans: String,
+}
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NotifyCfg {
+    #[serde(default)]
+    pub log: String,
+    #[serde(flatten)]
+    pub sinks: AlertSinkCfg,
+}
+/// Delivery channels of alerts: Telegram, HTTP webhook, SMTP email.
+///
+/// # ru
+/// Каналы доставки оповещений, общие для уведомлений трейдера и
+/// сканера. Канал включен, если заданы его обязательные поля: токен и
+/// чат Telegram, url вебхука, сервер и получатели почты. Уровни
+/// *_level - минимальная важность для канала (info, warning, alert),
+/// пустой - все. Почта только через шифрованное соединение:
+/// smtp_port 0 или 465 - сразу TLS, другой порт - STARTTLS.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct AlertSinkCfg {
+    #[serde(default)]
+    pub telegram_token: String,
+    #[serde(default)]
+    pub telegram_chat: String,
+    #[serde(default)]
+    pub telegram_level: String,
+    #[serde(default)]
+    pub webhook_url: String,
+    #[serde(default)]
+    pub webhook_level: String,
+    #[serde(default)]
+    pub smtp_host: String,
+    #[serde(default)]
+    pub smtp_port: u16,
+    #[serde(default)]
+    pub smtp_user: String,
+    #[serde(default)]
+    pub smtp_password: String,
+    #[serde(default)]
+    pub email_from: String,
+    #[serde(default)]
+    pub email_to: Vec<String>,
+    #[serde(default)]
+    pub email_level: String,
+}
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct RiskRulesCfg {
+    #[serde(default)]
+    pub max_daily_loss: f64,
+    #[serde(default)]
+    pub max_drawdown: f64,
+    #[serde(default)]
+    pub max_position_value: f64,
+    #[serde(default)]
+    pub max_orders_per_minute: u32,
+    #[serde(default)]
+    pub max_order_value: f64,
+    #[serde(default)]
+    pub max_position: u32,
+    #[serde(default)]
+    pub max_price_deviation: f64,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StrategyRiskCfg {
+    pub name: String,
+    #[serde(flatten)]
+    pub rules: RiskRulesCfg,
 }
 #[derive(Debug, Deserialize, Serialize)]
 pub struct WorkCfg {
     pub iid: String,
     pub strategy: Vec<String>,
+    #[serde(default)]
+    pub accounts: Vec<String>,
+}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WatchCfg {
+    pub list: String,
+    pub strategy: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -0,0 +1,300 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+//! Numeric kernels for indicators over columns of prices.
+//!
+//! # ru
+//! Вычислительные ядра индикаторов над колонками цен. Работают с
+//! непрерывными срезами &[f64], например с колонками графика, и
+//! проходят данные один раз: скользящие окна считаются через
+//! накопленные суммы (sma, zscore) или монотонную очередь (highest,
+//! lowest), поэтому время O(n) при любом периоде. Циклы идут по
+//! итераторам срезов, а не по индексам.
+//!
+//! Все функции возвращают вектор той же длины, что и входные данные.
+//! В начале, пока данных меньше периода, значение считается по
+//! имеющимся данным (неполное окно).
+
+use std::collections::VecDeque;
+
+/// Simple moving average.
+///
+/// # ru
+/// Простое скользящее среднее.
+pub fn sma(src: &[f64], period: usize) -> Vec<f64> {
+    let period = period.max(1);
+    let mut out = Vec::with_capacity(src.len());
+    let mut sum = 0.0;
+
+    // неполное окно в начале, затем окно сдвигается: значение
+    // входит в сумму, значение period шагов назад выходит
+    let (head, tail) = src.split_at(period.min(src.len()));
+    for (n, x) in head.iter().enumerate() {
+        sum += x;
+        out.push(sum / (n + 1) as f64);
+    }
+    for (x, old) in tail.iter().zip(src) {
+        sum += x - old;
+        out.push(sum / period as f64);
+    }
+
+    out
+}
+/// Exponential moving average.
+///
+/// # ru
+/// Экспоненциальное скользящее среднее, коэффициент 2 / (period + 1).
+pub fn ema(src: &[f64], period: usize) -> Vec<f64> {
+    let k = 2.0 / (period.max(1) as f64 + 1.0);
+    let mut out = Vec::with_capacity(src.len());
+
+    let mut prev = match src.first() {
+        Some(x) => *x,
+        None => return out,
+    };
+    for x in src.iter() {
+        prev += k * (x - prev);
+        out.push(prev);
+    }
+
+    out
+}
+/// True range of bars.
+///
+/// # ru
+/// Истинный диапазон баров: max(h - l, |h - prev_c|, |l - prev_c|).
+/// Для первого бара - просто h - l.
+pub fn true_range(h: &[f64], l: &[f64], c: &[f64]) -> Vec<f64> {
+    assert_eq!(h.len(), l.len());
+    assert_eq!(h.len(), c.len());
+
+    let mut out = Vec::with_capacity(h.len());
+    if h.is_empty() {
+        return out;
+    }
+
+    out.push(h[0] - l[0]);
+    let prev_c = &c[..c.len() - 1];
+    for ((h, l), pc) in h[1..].iter().zip(&l[1..]).zip(prev_c) {
+        let hl = h - l;
+        let hc = (h - pc).abs();
+        let lc = (l - pc).abs();
+        out.push(hl.max(hc).max(lc));
+    }
+
+    out
+}
+/// Highest value over period.
+///
+/// # ru
+/// Максимальное значение за период. Монотонная очередь индексов:
+/// каждое значение входит и выходит из нее один раз, O(n) при любом
+/// периоде.
+pub fn highest(src: &[f64], period: usize) -> Vec<f64> {
+    window_extreme(src, period, |a, b| a >= b)
+}
+/// Lowest value over period.
+///
+/// # ru
+/// Минимальное значение за период, как [`highest`].
+pub fn lowest(src: &[f64], period: usize) -> Vec<f64> {
+    window_extreme(src, period, |a, b| a <= b)
+}
+/// Relative strength index.
+///
+/// # ru
+/// Индекс относительной силы со сглаживанием Уайлдера, от 0 до 100.
+/// Пока изменений меньше периода - средние по имеющимся изменениям.
+/// Без движения цены - 50, без падений - 100.
+pub fn rsi(src: &[f64], period: usize) -> Vec<f64> {
+    let period = period.max(1) as f64;
+    let mut out = Vec::with_capacity(src.len());
+    if src.is_empty() {
+        return out;
+    }
+
+    out.push(50.0);
+    let mut gain = 0.0;
+    let mut loss = 0.0;
+    for (i, w) in src.windows(2).enumerate() {
+        let change = w[1] - w[0];
+        let n = ((i + 1) as f64).min(period);
+        gain += (change.max(0.0) - gain) / n;
+        loss += ((-change).max(0.0) - loss) / n;
+
+        let value = if loss == 0.0 {
+            if gain == 0.0 { 50.0 } else { 100.0 }
+        } else {
+            100.0 - 100.0 / (1.0 + gain / loss)
+        };
+        out.push(value);
+    }
+
+    out
+}
+/// Z-score over period.
+///
+/// # ru
+/// Отклонение значения от скользящего среднего в стандартных
+/// отклонениях за период. Если разброса нет - 0.
+///
+/// Среднее и дисперсия окна считаются по скользящим суммам значений
+/// и их квадратов, как в [`sma`].
+pub fn zscore(src: &[f64], period: usize) -> Vec<f64> {
+    let period = period.max(1);
+    let mut out = Vec::with_capacity(src.len());
+    let mut sum = 0.0;
+    let mut sq = 0.0;
+
+    let (head, tail) = src.split_at(period.min(src.len()));
+    for (n, x) in head.iter().enumerate() {
+        sum += x;
+        sq += x * x;
+        out.push(score(*x, sum, sq, (n + 1) as f64));
+    }
+    for (x, old) in tail.iter().zip(src) {
+        sum += x - old;
+        sq += x * x - old * old;
+        out.push(score(*x, sum, sq, period as f64));
+    }
+
+    out
+}
+
+/// z-score значения x по сумме и сумме квадратов окна из n значений.
+/// Сдвиг окна вычитанием накапливает ошибку округления, поэтому
+/// дисперсию меньше относительной погрешности считаем нулевой.
+fn score(x: f64, sum: f64, sq: f64, n: f64) -> f64 {
+    let mean = sum / n;
+    let var = sq / n - mean * mean;
+    if var <= 1e-12 * mean * mean {
+        return 0.0;
+    }
+
+    (x - mean) / var.sqrt()
+}
+
+/// Экстремум скользящего окна: в очереди индексы значений по убыванию
+/// "силы", первый - экстремум окна. Новое значение вытесняет с конца
+/// все, что не сильнее его, с начала уходят индексы вне окна.
+fn window_extreme(
+    src: &[f64],
+    period: usize,
+    stronger: impl Fn(f64, f64) -> bool,
+) -> Vec<f64> {
+    let period = period.max(1);
+    let mut out = Vec::with_capacity(src.len());
+    let mut deque = VecDeque::with_capacity(period.min(src.len()));
+
+    for (i, x) in src.iter().enumerate() {
+        while deque.back().is_some_and(|j: &usize| stronger(*x, src[*j])) {
+            deque.pop_back();
+        }
+        deque.push_back(i);
+        if deque.front().is_some_and(|j| j + period <= i) {
+            deque.pop_front();
+        }
+        out.push(src[deque[0]]);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_average() {
+        let src = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(sma(&src, 2), vec![1.0, 1.5, 2.5, 3.5, 4.5]);
+        assert_eq!(sma(&src, 10), vec![1.0, 1.5, 2.0, 2.5, 3.0]);
+
+        let e = ema(&src, 3);
+        assert_eq!(e[0], 1.0);
+        assert_eq!(e[1], 1.5);
+        assert_eq!(e[2], 2.25);
+        assert!(ema(&[], 3).is_empty());
+    }
+    #[test]
+    fn range() {
+        let h = [10.0, 12.0, 11.0];
+        let l = [9.0, 11.0, 8.0];
+        let c = [9.5, 11.5, 9.0];
+        assert_eq!(true_range(&h, &l, &c), vec![1.0, 2.5, 3.5]);
+
+        assert_eq!(highest(&h, 2), vec![10.0, 12.0, 12.0]);
+        assert_eq!(lowest(&l, 2), vec![9.0, 9.0, 8.0]);
+    }
+    #[test]
+    fn window() {
+        let src = [5.0, 1.0, 4.0, 2.0, 3.0, 3.0, 0.0, 6.0];
+        for period in 1..=src.len() + 1 {
+            let naive = |f: fn(f64, f64) -> f64, init: f64| {
+                (0..src.len())
+                    .map(|i| {
+                        let begin = (i + 1).saturating_sub(period);
+                        src[begin..=i].iter().fold(init, |a, b| f(a, *b))
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(highest(&src, period), naive(f64::max, f64::MIN));
+            assert_eq!(lowest(&src, period), naive(f64::min, f64::MAX));
+        }
+        assert!(highest(&[], 3).is_empty());
+    }
+    #[test]
+    fn relative_strength() {
+        let up = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(rsi(&up, 14), vec![50.0, 100.0, 100.0, 100.0]);
+        let down = [4.0, 3.0, 2.0];
+        assert_eq!(rsi(&down, 14), vec![50.0, 0.0, 0.0]);
+
+        // +2 -1 +2: средний рост 4/3, среднее падение 1/3 -> 80
+        let r = rsi(&[10.0, 12.0, 11.0, 13.0], 3);
+        assert!((r[3] - 80.0).abs() < 1e-9);
+        assert!(rsi(&[], 14).is_empty());
+    }
+    #[test]
+    fn z_score() {
+        let z = zscore(&[1.0, 1.0, 1.0, 3.0], 4);
+        assert_eq!(&z[..3], &[0.0, 0.0, 0.0]);
+        // среднее 1.5, отклонение sqrt(0.75)
+        assert!((z[3] - 1.5 / 0.75_f64.sqrt()).abs() < 1e-9);
+
+        let z = zscore(&[5.0, 1.0, 3.0], 2);
+        assert_eq!(z, vec![0.0, -1.0, 1.0]);
+        assert!(zscore(&[], 3).is_empty());
+
+        // скользящие суммы совпадают с пересчетом каждого окна,
+        // в том числе после выхода разброса из окна
+        let src = [300.1, 301.4, 299.8, 300.0, 300.0, 300.0, 302.7, 298.2];
+        for period in 1..=src.len() + 1 {
+            let naive: Vec<(f64, f64)> = (0..src.len())
+                .map(|i| {
+                    let w = &src[(i + 1).saturating_sub(period)..=i];
+                    let n = w.len() as f64;
+                    let mean = w.iter().sum::<f64>() / n;
+                    let var =
+                        w.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+                    let z = if var.sqrt() < 1e-9 {
+                        0.0
+                    } else {
+                        (src[i] - mean) / var.sqrt()
+                    };
+                    (mean, z)
+                })
+                .collect();
+            let mean = sma(&src, period);
+            let z = zscore(&src, period);
+            for (i, (m, zn)) in naive.iter().enumerate() {
+                assert!((mean[i] - m).abs() < 1e-9, "sma {period} {i}");
+                assert!((z[i] - zn).abs() < 1e-6, "zscore {period} {i}");
+            }
+        }
+    }
+}
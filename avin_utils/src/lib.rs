@@ -15,17 +15,25 @@
 mod cmd;
 mod conf;
 mod error;
+mod kernel;
 mod logger;
 mod misc;
+mod random;
 mod timer;
 
 pub use cmd::Cmd;
-pub use conf::{CFG, Configuration};
+pub use conf::{
+    AlertSinkCfg, CFG, Configuration, ContinuousCfg, ContractCfg, HoldoutCfg,
+    LegCfg, NotifyCfg, ReconcileCfg, RiskCfg, RiskRulesCfg, SessionCfg,
+    StrategyRiskCfg, SyntheticCfg, WatchCfg,
+};
 pub use error::AvinError;
+pub use kernel::{ema, highest, lowest, rsi, sma, true_range, zscore};
 pub use logger::init_logger;
 pub use misc::{
     DAY_BEGIN, DAY_END, MINUTES_IN_DAY, MSK_OFFSET, bisect_left,
     bisect_right, dt, filter_dt, max, min, next_month, replace_ts, round,
     round_price, str_date_to_utc, str_dt_to_utc, sum, ts,
 };
+pub use random::{Random, xorshift};
 pub use timer::Timer;
@@ -0,0 +1,81 @@
+/*****************************************************************************
+ * URL:         http://avin.info
+ * AUTHOR:      Alex Avin
+ * E-MAIL:      mr.alexavin@gmail.com
+ * LICENSE:     MIT
+ ****************************************************************************/
+
+/// Pseudo-random generator with reproducible sequence.
+///
+/// # ru
+/// Генератор псевдослучайных чисел тестера и симулятора: xorshift64,
+/// без внешних зависимостей. Одинаковый seed - одинаковая
+/// последовательность, поэтому тесты и оптимизация воспроизводимы.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // нулевое состояние xorshift не меняется, заменяем его
+        let state = if seed == 0 {
+            0x2545_F491_4F6C_DD1D
+        } else {
+            seed
+        };
+
+        Self { state }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        xorshift(&mut self.state)
+    }
+    /// Случайное число в [0, n).
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+    /// true с вероятностью p.
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.uniform() < p
+    }
+    /// Равномерно распределенное число в [0, 1).
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// Нормально распределенное число, среднее 0, отклонение 1
+    /// (преобразование Бокса-Мюллера).
+    pub fn gaussian(&mut self) -> f64 {
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+/// Шаг генератора xorshift64, state - ненулевое состояние.
+pub fn xorshift(state: &mut u64) -> u64 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence() {
+        let mut a = Random::new(7);
+        let mut b = Random::new(7);
+        assert_eq!(a.next_u64(), b.next_u64());
+        assert_ne!(Random::new(0).next_u64(), 0);
+
+        for _ in 0..100 {
+            assert!((0.0..1.0).contains(&a.uniform()));
+            assert!(a.below(3) < 3);
+        }
+    }
+}